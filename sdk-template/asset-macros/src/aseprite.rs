@@ -0,0 +1,324 @@
+//! Reads Aseprite's .ase/.aseprite files: every frame flattened to RGBA, plus
+//! frame durations, animation tags and slices.
+//!
+//! Visible layers are composited bottom to top with plain alpha blending;
+//! blend modes other than normal are treated as normal. Tilemap layers and
+//! external files aren't supported.
+
+use std::fs;
+
+use crate::image::RgbaImage;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_SLICE: u16 = 0x2022;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_REFERENCE: u16 = 64;
+
+pub struct Tag {
+    pub name: String,
+    pub from: u16,
+    pub to: u16,
+    /// 0 forward, 1 reverse, 2 ping-pong, 3 ping-pong starting in reverse
+    pub direction: u8,
+}
+
+pub struct Slice {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct Aseprite {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<RgbaImage>,
+    /// How long each frame shows, in milliseconds
+    pub durations: Vec<u16>,
+    pub tags: Vec<Tag>,
+    pub slices: Vec<Slice>,
+}
+
+struct Layer {
+    flags: u16,
+    kind: u16,
+    opacity: u8,
+}
+
+enum CelPixels {
+    Image { width: u32, height: u32, data: Vec<u8> },
+    Linked(u16),
+}
+
+struct Cel {
+    layer: u16,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    pixels: CelPixels,
+}
+
+/// Little-endian reads over a chunk of the file
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let end = (self.pos + len).min(self.data.len());
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        if bytes.len() < len {
+            panic!("Aseprite file ends in the middle of a chunk");
+        }
+        bytes
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.bytes(len);
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes(2).try_into().unwrap())
+    }
+
+    fn i16(&mut self) -> i16 {
+        self.u16() as i16
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes(4).try_into().unwrap())
+    }
+
+    fn i32(&mut self) -> i32 {
+        self.u32() as i32
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.u16() as usize;
+        String::from_utf8_lossy(self.bytes(len)).to_string()
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        self.bytes(self.data.len() - self.pos)
+    }
+}
+
+/// Pixels in the file's color depth, as RGBA
+fn to_rgba(data: &[u8], depth: u16, palette: &[[u8; 4]], transparent_index: u8) -> Vec<[u8; 4]> {
+    match depth {
+        32 => data.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        16 => data.chunks(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        8 => data.iter()
+            .map(|&i| if i == transparent_index { [0; 4] } else { palette.get(i as usize).copied().unwrap_or([0; 4]) })
+            .collect(),
+        _ => panic!("Unsupported Aseprite color depth: {} bits", depth),
+    }
+}
+
+/// Draw `src` over `dst` at (`x`, `y`), scaling its alpha by `opacity`
+fn composite(dst: &mut RgbaImage, src: &[[u8; 4]], src_width: u32, x: i32, y: i32, opacity: u32) {
+    for (i, &[r, g, b, a]) in src.iter().enumerate() {
+        let (dx, dy) = (x + (i as u32 % src_width) as i32, y + (i as u32 / src_width) as i32);
+        if dx < 0 || dy < 0 || dx >= dst.width as i32 || dy >= dst.height as i32 {
+            continue;
+        }
+        let alpha = a as u32 * opacity / 255;
+        if alpha == 0 {
+            continue;
+        }
+
+        let under = &mut dst.pixels[(dy as u32 * dst.width + dx as u32) as usize];
+        let out_alpha = alpha + under[3] as u32 * (255 - alpha) / 255;
+        let blend = |top: u8, bottom: u8| {
+            ((top as u32 * alpha + bottom as u32 * under[3] as u32 * (255 - alpha) / 255) / out_alpha) as u8
+        };
+        *under = [blend(r, under[0]), blend(g, under[1]), blend(b, under[2]), out_alpha as u8];
+    }
+}
+
+pub fn load_aseprite(file_path: &str) -> Aseprite {
+    let file = fs::read(file_path).expect(&format!("Failed to read file: {}", file_path));
+    let mut header = Reader::new(&file);
+
+    header.skip(4); // file size
+    if header.u16() != HEADER_MAGIC {
+        panic!("{} isn't an Aseprite file", file_path);
+    }
+    let frame_count = header.u16();
+    let width = header.u16() as u32;
+    let height = header.u16() as u32;
+    let depth = header.u16();
+    header.skip(4 + 2 + 4 + 4); // flags, speed, reserved
+    let transparent_index = header.u8();
+    header.skip(128 - header.pos);
+
+    let mut palette = vec![[0u8; 4]; 256];
+    let mut layers: Vec<Layer> = vec![];
+    let mut tags = vec![];
+    let mut slices = vec![];
+    let mut durations = vec![];
+    let mut frame_cels: Vec<Vec<Cel>> = vec![];
+
+    let mut pos = 128;
+    for _ in 0..frame_count {
+        let mut frame = Reader::new(&file[pos..]);
+        let frame_len = frame.u32() as usize;
+        if frame.u16() != FRAME_MAGIC {
+            panic!("{}: frame {} is corrupt", file_path, durations.len());
+        }
+        let old_chunks = frame.u16();
+        durations.push(frame.u16());
+        frame.skip(2);
+        let chunks = match frame.u32() {
+            0 => old_chunks as u32,
+            n => n,
+        };
+
+        let mut cels = vec![];
+        for _ in 0..chunks {
+            let chunk_len = frame.u32() as usize;
+            let kind = frame.u16();
+            let mut chunk = Reader::new(frame.bytes(chunk_len - 6));
+
+            match kind {
+                CHUNK_LAYER => {
+                    let flags = chunk.u16();
+                    let kind = chunk.u16();
+                    chunk.skip(2 + 2 + 2 + 2); // child level, default size, blend mode
+                    let opacity = chunk.u8();
+                    layers.push(Layer { flags, kind, opacity });
+                }
+                CHUNK_CEL => {
+                    let layer = chunk.u16();
+                    let x = chunk.i16() as i32;
+                    let y = chunk.i16() as i32;
+                    let opacity = chunk.u8();
+                    let cel_type = chunk.u16();
+                    chunk.skip(2 + 5); // z-index, reserved
+                    let pixels = match cel_type {
+                        0 => {
+                            let (w, h) = (chunk.u16() as u32, chunk.u16() as u32);
+                            CelPixels::Image { width: w, height: h, data: chunk.rest().to_vec() }
+                        }
+                        1 => CelPixels::Linked(chunk.u16()),
+                        2 => {
+                            let (w, h) = (chunk.u16() as u32, chunk.u16() as u32);
+                            let data = miniz_oxide::inflate::decompress_to_vec_zlib(chunk.rest())
+                                .expect(&format!("{}: a cel's pixels are corrupt", file_path));
+                            CelPixels::Image { width: w, height: h, data }
+                        }
+                        _ => panic!("{}: tilemap layers aren't supported", file_path),
+                    };
+                    cels.push(Cel { layer, x, y, opacity, pixels });
+                }
+                CHUNK_PALETTE => {
+                    chunk.skip(4);
+                    let (first, last) = (chunk.u32() as usize, chunk.u32() as usize);
+                    chunk.skip(8);
+                    for i in first..=last {
+                        let flags = chunk.u16();
+                        let color = [chunk.u8(), chunk.u8(), chunk.u8(), chunk.u8()];
+                        if flags & 1 != 0 {
+                            chunk.string();
+                        }
+                        if let Some(entry) = palette.get_mut(i) {
+                            *entry = color;
+                        }
+                    }
+                }
+                CHUNK_OLD_PALETTE if palette.iter().all(|c| c[3] == 0) => {
+                    let mut index = 0usize;
+                    for _ in 0..chunk.u16() {
+                        index += chunk.u8() as usize;
+                        let count = match chunk.u8() {
+                            0 => 256,
+                            n => n as usize,
+                        };
+                        for _ in 0..count {
+                            let color = [chunk.u8(), chunk.u8(), chunk.u8(), 0xFF];
+                            if let Some(entry) = palette.get_mut(index) {
+                                *entry = color;
+                            }
+                            index += 1;
+                        }
+                    }
+                }
+                CHUNK_TAGS => {
+                    let count = chunk.u16();
+                    chunk.skip(8);
+                    for _ in 0..count {
+                        let from = chunk.u16();
+                        let to = chunk.u16();
+                        let direction = chunk.u8();
+                        chunk.skip(2 + 6 + 3 + 1); // repeat, reserved, color
+                        tags.push(Tag { name: chunk.string(), from, to, direction });
+                    }
+                }
+                CHUNK_SLICE => {
+                    let keys = chunk.u32();
+                    chunk.skip(4 + 4); // flags, reserved
+                    let name = chunk.string();
+                    // the first key is the slice as drawn; later keys animate it
+                    if keys > 0 {
+                        chunk.skip(4); // frame
+                        let (x, y) = (chunk.i32(), chunk.i32());
+                        let (width, height) = (chunk.u32(), chunk.u32());
+                        slices.push(Slice { name, x, y, width, height });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        frame_cels.push(cels);
+        pos += frame_len;
+    }
+
+    let frames = (0..frame_cels.len())
+        .map(|f| {
+            let mut image = RgbaImage { width, height, pixels: vec![[0; 4]; (width * height) as usize] };
+            for (index, layer) in layers.iter().enumerate() {
+                if layer.flags & LAYER_VISIBLE == 0 || layer.flags & LAYER_REFERENCE != 0 || layer.kind != 0 {
+                    continue;
+                }
+                let Some(cel) = frame_cels[f].iter().find(|c| c.layer as usize == index) else { continue };
+                // a linked cel is the same layer's cel from another frame, position and all
+                let source = match cel.pixels {
+                    CelPixels::Linked(other) => frame_cels.get(other as usize)
+                        .and_then(|cels| cels.iter().find(|c| c.layer as usize == index)),
+                    CelPixels::Image { .. } => Some(cel),
+                };
+                let Some(cel @ Cel { pixels: CelPixels::Image { width: w, height: h, data }, .. }) = source else { continue };
+
+                let rgba = to_rgba(data, depth, &palette, transparent_index);
+                if rgba.len() < (w * h) as usize {
+                    panic!("{}: a cel has fewer pixels than its size", file_path);
+                }
+                let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+                composite(&mut image, &rgba, *w, cel.x, cel.y, opacity);
+            }
+            image
+        })
+        .collect();
+
+    Aseprite { width, height, frames, durations, tags, slices }
+}
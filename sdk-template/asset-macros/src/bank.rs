@@ -0,0 +1,193 @@
+//! `#[bank(N)]`: put a function or static in ROM bank N, and note it in the
+//! `.gt_banks` inventory that gtrom's bank report reads back out of the ELF.
+//!
+//! A banked function is renamed and given a trampoline under its own name in
+//! the fixed bank, which maps bank N in around the call, so it can be called
+//! from anywhere. `#[bank(N, near)]` skips the trampoline, for functions only
+//! called from code that's in bank N already.
+//!
+//! Each inventory entry is `[bank, kind, name..., 0]`, where kind is 0 for a
+//! near function, 1 for a static and 2 for a function with a trampoline. The
+//! linker script keeps `.gt_banks` out of the ROM, so the entries cost nothing
+//! at runtime.
+
+use proc_macro2::{Delimiter, Literal, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::parse::{ParseStream, Parser};
+use syn::spanned::Spanned;
+
+/// The highest bank; 127 is the fixed bank, where unbanked code goes anyway
+const MAX_BANK: u8 = 127;
+
+enum Kind {
+    Fn,
+    Static,
+}
+
+fn error(span: Span, message: &str) -> TokenStream {
+    syn::Error::new(span, message).to_compile_error()
+}
+
+/// `N` or `N, near`
+fn parse_args(input: ParseStream) -> syn::Result<(u8, bool)> {
+    let lit: syn::LitInt = input.parse()?;
+    let bank = lit.base10_parse::<u8>()?;
+    if bank > MAX_BANK {
+        return Err(syn::Error::new(lit.span(), format!("bank {} is out of range (0-{})", bank, MAX_BANK)));
+    }
+
+    let near = if input.is_empty() {
+        false
+    } else {
+        input.parse::<syn::Token![,]>()?;
+        let flag: syn::Ident = input.parse()?;
+        if flag != "near" {
+            return Err(syn::Error::new(flag.span(), "the only option is `near`"));
+        }
+        true
+    };
+    Ok((bank, near))
+}
+
+/// What the item is and its name, found by walking past its attributes,
+/// visibility and qualifiers. Works on tokens rather than parsing the item, so
+/// any attribute the compiler accepts is fine here too.
+fn describe(item: &TokenStream) -> Result<(Kind, proc_macro2::Ident), TokenStream> {
+    let mut tokens = item.clone().into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let TokenTree::Ident(ident) = token else { continue };
+        let kind = match ident.to_string().as_str() {
+            "fn" => Kind::Fn,
+            "static" => {
+                if matches!(tokens.peek(), Some(TokenTree::Ident(m)) if m == "mut") {
+                    return Err(error(ident.span(), "#[bank] statics live in ROM, so they can't be `static mut`"));
+                }
+                Kind::Static
+            }
+            "pub" => {
+                // skip pub(crate) and friends
+                if matches!(tokens.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis) {
+                    tokens.next();
+                }
+                continue;
+            }
+            "const" | "unsafe" | "extern" | "async" => continue,
+            _ => return Err(error(ident.span(), "#[bank] goes on a function or a static")),
+        };
+        return match tokens.next() {
+            Some(TokenTree::Ident(name)) => Ok((kind, name)),
+            _ => Err(error(ident.span(), "expected a name")),
+        };
+    }
+    Err(error(Span::call_site(), "#[bank] goes on a function or a static"))
+}
+
+/// The function, renamed and placed in `section`, and a trampoline under its
+/// old name that calls it with `bank` mapped in
+fn trampoline(item: TokenStream, bank: u8, section: &str) -> TokenStream {
+    let func = match syn::parse2::<syn::ItemFn>(item) {
+        Ok(func) => func,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    let sig = &func.sig;
+    let problem = if !sig.generics.params.is_empty() {
+        Some("generic")
+    } else if sig.constness.is_some() {
+        Some("const")
+    } else if sig.asyncness.is_some() {
+        Some("async")
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        let message = format!("#[bank] can't make a trampoline for a {} fn; use #[bank({}, near)] and switch banks yourself", problem, bank);
+        return error(sig.span(), &message);
+    }
+
+    // the trampoline takes plain arguments, whatever patterns the function destructures them with
+    let mut outer = sig.clone();
+    let mut args = vec![];
+    for (i, input) in outer.inputs.iter_mut().enumerate() {
+        let syn::FnArg::Typed(arg) = input else {
+            return error(input.span(), "#[bank] trampolines only work for free functions, not methods");
+        };
+        if let syn::Type::ImplTrait(_) = *arg.ty {
+            return error(arg.ty.span(), &format!("#[bank] can't make a trampoline for a generic fn; use #[bank({}, near)]", bank));
+        }
+        let name = format_ident!("arg{}", i);
+        *arg.pat = syn::parse_quote!(#name);
+        args.push(name);
+    }
+
+    let name = &sig.ident;
+    let inner_name = format_ident!("__{}_bank{}", name, bank);
+    let mut inner = func.clone();
+    inner.sig.ident = inner_name.clone();
+    inner.vis = syn::Visibility::Inherited;
+
+    // docs and cfgs go on the trampoline, since that's what callers see
+    let outer_attrs: Vec<_> = func.attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc") || attr.path().is_ident("cfg"))
+        .collect();
+    let vis = &func.vis;
+    let call = match sig.unsafety {
+        Some(_) => quote! { unsafe { #inner_name(#(#args),*) } },
+        None => quote! { #inner_name(#(#args),*) },
+    };
+
+    quote! {
+        #[unsafe(link_section = #section)]
+        #[inline(never)]
+        #inner
+
+        #(#outer_attrs)*
+        #[inline(never)]
+        #vis #outer {
+            ::gametank::via::far_call(#bank, || #call)
+        }
+    }
+}
+
+pub fn expand(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let (bank, near) = match parse_args.parse2(attr) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    let (kind, name) = match describe(&item) {
+        Ok(found) => found,
+        Err(e) => return e,
+    };
+
+    let (placed, kind_byte) = match kind {
+        Kind::Fn if near => {
+            let section = format!(".text.bank{}", bank);
+            (quote! { #[unsafe(link_section = #section)] #item }, 0u8)
+        }
+        Kind::Fn => (trampoline(item, bank, &format!(".text.bank{}", bank)), 2u8),
+        Kind::Static => {
+            if near {
+                return error(Span::call_site(), "`near` is for functions; statics never get a trampoline");
+            }
+            let section = format!(".rodata.bank{}", bank);
+            (quote! { #[unsafe(link_section = #section)] #item }, 1u8)
+        }
+    };
+
+    let mut entry = vec![bank, kind_byte];
+    entry.extend(name.to_string().bytes());
+    entry.push(0);
+    let len = entry.len();
+    let entry = Literal::byte_string(&entry);
+
+    quote! {
+        #placed
+
+        const _: () = {
+            #[used]
+            #[unsafe(link_section = ".gt_banks")]
+            static ENTRY: [u8; #len] = *#entry;
+        };
+    }
+}
@@ -10,6 +10,8 @@ use tinybmp::ColorTable;
 use embedded_graphics::pixelcolor::Rgb888;
 use embedded_graphics::prelude::RgbColor;
 
+use crate::image::RgbaImage;
+
 pub static PALETTE: [(u8, u8, u8, u8); 256] = [
     (0x1a, 0x1a, 0x1a, 0xFF), (0x31, 0x31, 0x31, 0xFF), (0x47, 0x47, 0x47, 0xFF), (0x5d, 0x5d, 0x5d, 0xFF), (0x74, 0x74, 0x74, 0xFF), (0x8b, 0x8b, 0x8a, 0xFF), (0xa1, 0xa1, 0xa1, 0xFF), (0xb9, 0xb9, 0xb9, 0xFF),
     (0x1a, 0x1e, 0x00, 0xFF), (0x31, 0x35, 0x0a, 0xFF), (0x47, 0x4b, 0x21, 0xFF), (0x5e, 0x61, 0x35, 0xFF), (0x75, 0x78, 0x4d, 0xFF), (0x8c, 0x8f, 0x65, 0xFF), (0xa2, 0xa5, 0x7c, 0xFF), (0xb9, 0xbd, 0x93, 0xFF),
@@ -56,7 +58,7 @@ fn palette_as_rgb888() -> Vec<Rgb888> {
     palette
 }
 
-fn color_map() -> HashMap<Rgb888, u8> {
+pub(crate) fn color_map() -> HashMap<Rgb888, u8> {
     let mut map = HashMap::new();
 
     let palette = palette_as_rgb888();
@@ -180,51 +182,16 @@ impl SpriteSheetImage {
 //     result
 // }
 
-/// Load a BMP file and return raw pixel data mapped to GameTank colors.
-/// Works with both indexed and true-color BMP files.
-/// Colors not in the palette are mapped to the closest match.
-pub fn load_bmp_raw(file_path: String) -> Vec<u8> {
-    let file_contents = fs::read(&file_path).expect(&format!("Failed to read file: {}", file_path));
+/// Load a BMP file's pixels, indexed or true-color, as opaque RGBA
+pub fn load_bmp_rgba(file_path: &str) -> RgbaImage {
+    let file_contents = fs::read(file_path).expect(&format!("Failed to read file: {}", file_path));
     let bmp = tinybmp::Bmp::<Rgb888>::from_slice(file_contents.as_slice())
         .expect(&format!("Failed to parse BMP: {}", file_path));
-    let color_map = color_map();
-    let palette = palette_as_rgb888();
-
-    // Map each pixel directly to GameTank colors
-    bmp.pixels()
-        .map(|pixel| {
-            let color = pixel.1;
-            // Try exact match first
-            if let Some(&gt_color) = color_map.get(&color) {
-                gt_color
-            } else {
-                // Find closest color in palette
-                find_closest_color(&color, &palette, &color_map)
-            }
-        })
-        .collect()
-}
-
-/// Find the closest color in the GameTank palette using Euclidean distance in RGB space
-fn find_closest_color(target: &Rgb888, palette: &[Rgb888], color_map: &HashMap<Rgb888, u8>) -> u8 {
-    let mut best_match = palette[0];
-    let mut best_distance = color_distance(target, &palette[0]);
+    let size = bmp.as_raw().header().image_size;
 
-    for color in palette.iter().skip(1) {
-        let distance = color_distance(target, color);
-        if distance < best_distance {
-            best_distance = distance;
-            best_match = *color;
-        }
+    RgbaImage {
+        width: size.width,
+        height: size.height,
+        pixels: bmp.pixels().map(|pixel| [pixel.1.r(), pixel.1.g(), pixel.1.b(), 0xFF]).collect(),
     }
-
-    *color_map.get(&best_match).unwrap()
-}
-
-/// Calculate squared Euclidean distance between two colors
-fn color_distance(c1: &Rgb888, c2: &Rgb888) -> u32 {
-    let dr = (c1.r() as i32) - (c2.r() as i32);
-    let dg = (c1.g() as i32) - (c2.g() as i32);
-    let db = (c1.b() as i32) - (c2.b() as i32);
-    (dr * dr + dg * dg + db * db) as u32
 }
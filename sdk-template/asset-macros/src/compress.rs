@@ -0,0 +1,70 @@
+//! The compressor behind `include_bmp_compressed!`: byte-oriented LZ77 with
+//! run-length encoding, cheap enough for the 6502 to unpack while it streams
+//! the output into sprite RAM.
+//!
+//! The stream is a sequence of tokens:
+//!
+//! - `0nnnnnnn`, then n + 1 bytes: literal bytes (1-128)
+//! - `10nnnnnn b`: byte b, n + 3 times (3-66)
+//! - `11nnnnnn d`: n + 3 bytes copied from d + 1 bytes back (3-66, up to 256 back)
+//!
+//! Sprite RAM is write-only to the CPU, so the decompressor keeps the last 256
+//! bytes it wrote in RAM; that's where the window size comes from. A full
+//! 128-wide image still gets to copy from the two rows above.
+
+const MAX_LITERALS: usize = 128;
+const MIN_RUN: usize = 3;
+const MAX_RUN: usize = 66;
+const WINDOW: usize = 256;
+
+/// The longest earlier copy of the bytes at `pos`, as (distance back, length)
+fn longest_match(data: &[u8], pos: usize) -> (usize, usize) {
+    let limit = MAX_RUN.min(data.len() - pos);
+    let mut best = (0, 0);
+    for distance in 1..=WINDOW.min(pos) {
+        let from = pos - distance;
+        // the copy can overlap what it's writing, the same as on the console
+        let len = (0..limit).take_while(|&i| data[from + i] == data[pos + i]).count();
+        if len > best.1 {
+            best = (distance, len);
+        }
+    }
+    best
+}
+
+fn flush_literals(out: &mut Vec<u8>, literals: &mut Vec<u8>) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+    literals.clear();
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut literals = vec![];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let limit = MAX_RUN.min(data.len() - pos);
+        let run = data[pos..pos + limit].iter().take_while(|&&b| b == data[pos]).count();
+        let (distance, len) = longest_match(data, pos);
+
+        if run >= MIN_RUN && run >= len {
+            flush_literals(&mut out, &mut literals);
+            out.push(0x80 | (run - MIN_RUN) as u8);
+            out.push(data[pos]);
+            pos += run;
+        } else if len >= MIN_RUN {
+            flush_literals(&mut out, &mut literals);
+            out.push(0xC0 | (len - MIN_RUN) as u8);
+            out.push((distance - 1) as u8);
+            pos += len;
+        } else {
+            literals.push(data[pos]);
+            pos += 1;
+        }
+    }
+    flush_literals(&mut out, &mut literals);
+    out
+}
@@ -0,0 +1,62 @@
+//! `#[gt_main]`: turn a plain function into the entry point the SDK's boot code calls.
+//!
+//! The SDK declares `fn main(console: &mut Console)` and calls it once crt0
+//! has set up RAM and the stack; it has to be unmangled for the linker to find
+//! it. This writes that part, so the game's own function can be called anything.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+
+pub fn expand(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(attr.span(), "#[gt_main] takes no arguments").to_compile_error();
+    }
+
+    let func = match syn::parse2::<syn::ItemFn>(item) {
+        Ok(func) => func,
+        Err(e) => return syn::Error::new(e.span(), "#[gt_main] goes on `fn main(console: &mut Console)`").to_compile_error(),
+    };
+
+    let sig = &func.sig;
+    let problem = if sig.inputs.len() != 1 {
+        Some("takes exactly one argument, `console: &mut Console`")
+    } else if !sig.generics.params.is_empty() {
+        Some("can't be generic")
+    } else if sig.asyncness.is_some() {
+        Some("can't be async")
+    } else if !matches!(sig.output, syn::ReturnType::Default) {
+        Some("doesn't return anything; loop forever instead")
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        return syn::Error::new(sig.span(), format!("the #[gt_main] function {}", problem)).to_compile_error();
+    }
+
+    let name = &sig.ident;
+    // a clear type error here beats a link error from the boot code
+    let check = quote! {
+        const _: fn(&mut ::gametank::console::Console) = #name;
+    };
+
+    if name == "main" {
+        quote! {
+            #[unsafe(no_mangle)]
+            #func
+
+            #check
+        }
+    } else {
+        quote! {
+            #func
+
+            #check
+
+            #[unsafe(no_mangle)]
+            fn main(console: &mut ::gametank::console::Console) {
+                #name(console)
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Arguments for `include_font!`: the image, its glyph size as `"8x8"`, and
+//! the usual image options.
+
+use syn::parse::{Parse, ParseStream};
+use syn::{LitStr, Token};
+
+use crate::image::{parse_options, ImageOptions};
+
+pub struct FontArgs {
+    pub path: String,
+    pub glyph_width: u8,
+    pub glyph_height: u8,
+    pub options: ImageOptions,
+}
+
+impl Parse for FontArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse::<LitStr>()?.value();
+        input.parse::<Token![,]>()?;
+
+        let size: LitStr = input.parse()?;
+        let glyph = size.value().split_once('x').and_then(|(w, h)| {
+            let side = |v: &str| v.trim().parse::<u8>().ok().filter(|n| (1..=128).contains(n));
+            Some((side(w)?, side(h)?))
+        });
+        let Some((glyph_width, glyph_height)) = glyph else {
+            return Err(syn::Error::new(size.span(), "the glyph size looks like \"8x8\", each side 1-128"));
+        };
+
+        let options = parse_options(input)?;
+        Ok(Self { path, glyph_width, glyph_height, options })
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitBool, LitInt, LitStr, Token};
+
+use crate::bmp::{color_map, load_bmp_rgba, PALETTE};
+
+/// Decoded pixels, row by row, as RGBA
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+/// How to turn full color art into GameTank colors
+#[derive(Default)]
+pub struct ImageOptions {
+    /// Ordered (Bayer) dithering, to fake the colors in between palette entries
+    pub dither: bool,
+    /// A color key that becomes 0, for art without an alpha channel
+    pub transparent: Option<[u8; 3]>,
+}
+
+/// `"path"`, then optionally `dither = true` and `transparent = 0xRRGGBB`
+pub struct ImageArgs {
+    pub path: String,
+    pub options: ImageOptions,
+}
+
+impl Parse for ImageArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse::<LitStr>()?.value();
+        let options = parse_options(input)?;
+        Ok(Self { path, options })
+    }
+}
+
+/// The `, dither = true, transparent = 0xRRGGBB` that can end any image macro's arguments
+pub fn parse_options(input: ParseStream) -> syn::Result<ImageOptions> {
+    let mut options = ImageOptions::default();
+
+    while !input.is_empty() {
+        input.parse::<Token![,]>()?;
+        if input.is_empty() {
+            break;
+        }
+        let key: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        match key.to_string().as_str() {
+            "dither" => options.dither = input.parse::<LitBool>()?.value,
+            "transparent" => {
+                let lit: LitInt = input.parse()?;
+                let rgb = lit.base10_parse::<u32>()?;
+                if rgb > 0xFF_FFFF {
+                    return Err(syn::Error::new(lit.span(), "transparent is a 0xRRGGBB color"));
+                }
+                let [_, r, g, b] = rgb.to_be_bytes();
+                options.transparent = Some([r, g, b]);
+            }
+            _ => return Err(syn::Error::new(key.span(), "expected `dither` or `transparent`")),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Decode a PNG of any color type or bit depth to 8-bit RGBA
+pub fn load_png(file_path: &str) -> RgbaImage {
+    let file = File::open(file_path).expect(&format!("Failed to read file: {}", file_path));
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    // palettes, low bit depths and tRNS chunks all come out as 8-bit gray/RGB(A)
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().expect(&format!("Failed to parse PNG: {}", file_path));
+
+    let mut buf = vec![0; reader.output_buffer_size().expect("PNG is too big")];
+    let info = reader.next_frame(&mut buf).expect(&format!("Failed to decode PNG: {}", file_path));
+    let bytes = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        png::ColorType::Rgb => bytes.chunks(3).map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => bytes.iter().map(|&g| [g, g, g, 0xFF]).collect(),
+        png::ColorType::Indexed => panic!("{}: indexed PNG wasn't expanded", file_path),
+    };
+
+    RgbaImage { width: info.width, height: info.height, pixels }
+}
+
+/// Distance between two colors as the eye sees it, roughly: the "redmean"
+/// weighting, which counts green most and leans on red or blue depending on
+/// how red the pair is
+fn perceptual_distance([r1, g1, b1]: [u8; 3], [r2, g2, b2]: [u8; 3]) -> u32 {
+    let mean_r = (r1 as i32 + r2 as i32) / 2;
+    let (dr, dg, db) = (r1 as i32 - r2 as i32, g1 as i32 - g2 as i32, b1 as i32 - b2 as i32);
+    ((((512 + mean_r) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean_r) * db * db) >> 8)) as u32
+}
+
+/// The GameTank color that looks closest to `rgb`. A color's byte is its index in the palette.
+fn nearest_color(rgb: [u8; 3]) -> u8 {
+    PALETTE.iter()
+        .enumerate()
+        .min_by_key(|(_, &(r, g, b, _))| perceptual_distance(rgb, [r, g, b]))
+        .map(|(i, _)| i as u8)
+        .unwrap()
+}
+
+/// 4x4 Bayer matrix; each cell is a threshold from 0 to 15
+const BAYER: [[i32; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+/// Roughly the gap between neighbouring lightness steps in the palette, so
+/// dithering spreads a color across the two entries it falls between
+const DITHER_SPREAD: i32 = 24;
+
+/// Map each pixel to a GameTank color. Pixels that are mostly transparent, or
+/// match the transparent color key, become 0, which the blitter skips.
+pub fn to_gametank_colors(image: &RgbaImage, options: &ImageOptions) -> Vec<u8> {
+    assert_eq!(image.pixels.len(), (image.width * image.height) as usize, "image size doesn't match its pixels");
+    let exact = color_map();
+    let mut cache = HashMap::new();
+
+    image.pixels.iter()
+        .enumerate()
+        .map(|(i, &[r, g, b, a])| {
+            if a < 128 || options.transparent == Some([r, g, b]) {
+                return 0;
+            }
+
+            let rgb = if options.dither {
+                let (x, y) = (i % image.width as usize, i / image.width as usize);
+                let offset = (BAYER[y % 4][x % 4] * 2 - 15) * DITHER_SPREAD / 32;
+                [r, g, b].map(|c| (c as i32 + offset).clamp(0, 255) as u8)
+            } else {
+                [r, g, b]
+            };
+
+            *cache.entry(rgb).or_insert_with(|| {
+                let [r, g, b] = rgb;
+                exact.get(&Rgb888::new(r, g, b)).copied().unwrap_or_else(|| nearest_color(rgb))
+            })
+        })
+        .collect()
+}
+
+/// Load a PNG or BMP, picked by extension
+pub fn load_rgba(file_path: &str) -> RgbaImage {
+    let extension = file_path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => load_png(file_path),
+        Some("bmp") => load_bmp_rgba(file_path),
+        _ => panic!("{}: only .png and .bmp images are supported", file_path),
+    }
+}
+
+/// Load a PNG or BMP, picked by extension, as one GameTank color byte per pixel
+pub fn load_image_raw(file_path: &str, options: &ImageOptions) -> Vec<u8> {
+    to_gametank_colors(&load_rgba(file_path), options)
+}
@@ -10,7 +10,15 @@ use syn::{parse_macro_input, LitStr};
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 
 
+mod aseprite;
+mod bank;
 mod bmp;
+mod compress;
+mod entry;
+mod font;
+mod image;
+mod wav;
+mod wavetable;
 
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -204,15 +212,16 @@ pub fn include_spritesheet(input: TokenStream) -> TokenStream {
 
 /// Include a BMP file as a byte array.
 /// Usage: `include_bmp!("path/to/file.bmp")`
+///
+/// Takes the same options as [`include_image!`].
 /// 
 /// Note: A 128x128 image is 16,384 bytes which exceeds a single 16KB bank.
-/// For large images, consider splitting or using `include_bmp_banked!`.
+/// For large images, consider splitting or using [`include_bmp_compressed!`].
 #[proc_macro]
 pub fn include_bmp(input: TokenStream) -> TokenStream {
-    let path_lit = parse_macro_input!(input as LitStr);
-    let path = path_lit.value();
+    let args = parse_macro_input!(input as image::ImageArgs);
 
-    let pixels = bmp::load_bmp_raw(path);
+    let pixels = image::to_gametank_colors(&bmp::load_bmp_rgba(&args.path), &args.options);
 
     let output = quote! {
         [ #( #pixels ),* ]
@@ -221,6 +230,316 @@ pub fn include_bmp(input: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Include a BMP file compressed, as a `gametank::assets::CompressedImage`.
+/// Usage: `include_bmp_compressed!("path/to/file.bmp")`
+///
+/// ```ignore
+/// static BACKGROUND: CompressedImage = include_bmp_compressed!("assets/background.bmp");
+///
+/// BACKGROUND.unpack(&mut sprite_mem);
+/// ```
+///
+/// Flat areas and repeated rows shrink a lot; a typical 16KB background comes
+/// out at a few KB. Unpacking writes straight into sprite RAM, a quadrant's
+/// worth at most, so the image can be at most 16,384 pixels. Takes the same
+/// options as [`include_image!`].
+#[proc_macro]
+pub fn include_bmp_compressed(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as image::ImageArgs);
+
+    let image = bmp::load_bmp_rgba(&args.path);
+    if image.width > 255 || image.height > 255 || image.width * image.height > 0x4000 {
+        panic!("{}: {}x{} doesn't fit in a sprite RAM quadrant (16,384 pixels)", args.path, image.width, image.height);
+    }
+    let (width, height) = (image.width as u8, image.height as u8);
+    let data = compress::compress(&image::to_gametank_colors(&image, &args.options));
+
+    let output = quote! {
+        ::gametank::assets::CompressedImage {
+            width: #width,
+            height: #height,
+            data: &[ #( #data ),* ],
+        }
+    };
+
+    output.into()
+}
+
+/// Include a PNG file as a byte array, one GameTank color per pixel.
+/// Usage: `include_png!("path/to/file.png")`
+///
+/// Any color type or bit depth works. Takes the same options as [`include_image!`].
+#[proc_macro]
+pub fn include_png(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as image::ImageArgs);
+
+    let pixels = image::to_gametank_colors(&image::load_png(&args.path), &args.options);
+
+    let output = quote! {
+        [ #( #pixels ),* ]
+    };
+
+    output.into()
+}
+
+/// Include a PNG or BMP file as a byte array, going by the file extension.
+/// Usage: `include_image!("path/to/file.png")`
+///
+/// Colors are mapped to the GameTank color that looks closest, and mostly
+/// transparent pixels to 0, which the blitter skips. Options go after the path:
+///
+/// ```ignore
+/// static PLAYER: [u8; 16 * 16] = include_image!("player.png", dither = true, transparent = 0xFF00FF);
+/// ```
+///
+/// - `dither = true`: ordered dithering, for gradients and photos
+/// - `transparent = 0xRRGGBB`: a color key that becomes 0, for art without alpha
+#[proc_macro]
+pub fn include_image(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as image::ImageArgs);
+
+    let pixels = image::load_image_raw(&args.path, &args.options);
+
+    let output = quote! {
+        [ #( #pixels ),* ]
+    };
+
+    output.into()
+}
+
+/// Include an Aseprite file as a `gametank::assets::AsepriteSprite`: each
+/// frame with its visible layers flattened, frame durations, animation tags and
+/// slices. Usage: `include_aseprite!("path/to/player.ase")`
+///
+/// ```ignore
+/// static PLAYER: AsepriteSprite = include_aseprite!("assets/player.ase", transparent = 0xFF00FF);
+///
+/// let walk = PLAYER.tag("walk").unwrap();
+/// ```
+///
+/// Takes the same options as [`include_image!`]. The sprite can be at most 255x255.
+#[proc_macro]
+pub fn include_aseprite(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as image::ImageArgs);
+    let sprite = aseprite::load_aseprite(&args.path);
+
+    if sprite.width > 255 || sprite.height > 255 {
+        panic!("{}: {}x{} is too big; sprites can be at most 255x255", args.path, sprite.width, sprite.height);
+    }
+    let width = sprite.width as u8;
+    let height = sprite.height as u8;
+
+    let frames = sprite.frames.iter().map(|frame| {
+        let pixels = image::to_gametank_colors(frame, &args.options);
+        quote! { &[ #( #pixels ),* ] }
+    });
+    let durations = &sprite.durations;
+
+    let tags = sprite.tags.iter().map(|tag| {
+        let (name, from, to) = (&tag.name, tag.from, tag.to);
+        let direction = match tag.direction {
+            1 => quote! { Reverse },
+            2 => quote! { PingPong },
+            3 => quote! { PingPongReverse },
+            _ => quote! { Forward },
+        };
+        quote! {
+            ::gametank::assets::AnimationTag {
+                name: #name,
+                from: #from,
+                to: #to,
+                direction: ::gametank::assets::Direction::#direction,
+            }
+        }
+    });
+
+    let slices = sprite.slices.iter().map(|slice| {
+        let name = &slice.name;
+        let (x, y) = (slice.x as i16, slice.y as i16);
+        let (w, h) = (slice.width.min(255) as u8, slice.height.min(255) as u8);
+        quote! {
+            ::gametank::assets::SpriteSlice { name: #name, x: #x, y: #y, width: #w, height: #h }
+        }
+    });
+
+    let output = quote! {
+        ::gametank::assets::AsepriteSprite {
+            width: #width,
+            height: #height,
+            frames: &[ #( #frames ),* ],
+            durations: &[ #( #durations ),* ],
+            tags: &[ #( #tags ),* ],
+            slices: &[ #( #slices ),* ],
+        }
+    };
+
+    output.into()
+}
+
+/// Include a bitmap font as a `gametank::assets::Font`, for the SDK's `text`
+/// module. Usage: `include_font!("path/to/font.png", "8x8")`
+///
+/// The image is a grid of equal glyphs in ASCII order, starting with a space
+/// at the top left: the classic 16 columns of 8x8 glyphs are 128x48 for ` `
+/// to `~`. It goes into sprite RAM as it is, so it can be at most 128x128.
+///
+/// ```ignore
+/// static FONT: Font = include_font!("assets/font.png", "8x8", transparent = 0x000000);
+/// ```
+///
+/// Takes the same options as [`include_image!`] after the glyph size.
+#[proc_macro]
+pub fn include_font(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as font::FontArgs);
+    let image = image::load_rgba(&args.path);
+
+    let (glyph_width, glyph_height) = (args.glyph_width, args.glyph_height);
+    if image.width > 128 || image.height > 128 {
+        panic!("{}: {}x{} is too big; a font has to fit in a 128x128 sprite RAM quadrant", args.path, image.width, image.height);
+    }
+    if image.width % glyph_width as u32 != 0 || image.height % glyph_height as u32 != 0 {
+        panic!("{}: {}x{} doesn't divide into {}x{} glyphs", args.path, image.width, image.height, glyph_width, glyph_height);
+    }
+    let (width, height) = (image.width as u8, image.height as u8);
+    let pixels = image::to_gametank_colors(&image, &args.options);
+
+    let output = quote! {
+        ::gametank::assets::Font {
+            width: #width,
+            height: #height,
+            glyph_width: #glyph_width,
+            glyph_height: #glyph_height,
+            pixels: &[ #( #pixels ),* ],
+        }
+    };
+
+    output.into()
+}
+
+/// Include a WAV file as a `gametank::assets::PcmSample`: mixed down to mono,
+/// resampled and converted to unsigned 8-bit for the DAC.
+/// Usage: `include_wav!("path/to/kick.wav")`
+///
+/// ```ignore
+/// static VOICE: PcmSample = include_wav!("assets/voice.wav", rate = 8000, bank = 40);
+/// ```
+///
+/// - `rate = N`: the sample rate to resample to, 14000 Hz by default
+/// - `bank = N`: put the samples in ROM bank N, and the banks after it if
+///   they don't fit in one 16KB bank. Without it the samples stay in the
+///   fixed bank, so keep those short.
+#[proc_macro]
+pub fn include_wav(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as wav::WavArgs);
+    let samples = wav::load_wav(&args.path, args.rate);
+
+    const BANK_SIZE: usize = 0x4000;
+    const FIXED_BANK: u8 = 127;
+    let parts: Vec<&[u8]> = match args.bank {
+        Some(_) => samples.chunks(BANK_SIZE).collect(),
+        None => vec![&samples[..]],
+    };
+    if let Some(first) = args.bank {
+        if first as usize + parts.len() > FIXED_BANK as usize {
+            panic!("{}: {} bytes need {} banks from bank {}, past the last switchable bank", args.path, samples.len(), parts.len(), first);
+        }
+    }
+
+    let statics = parts.iter().enumerate().map(|(i, part)| {
+        let ident = Ident::new(&format!("PART{}", i), Span::call_site());
+        let len = part.len();
+        let section = args.bank.map(|first| {
+            let section = format!(".rodata.bank{}", first as usize + i);
+            quote! { #[unsafe(link_section = #section)] }
+        });
+        quote! {
+            #section
+            static #ident: [u8; #len] = [ #( #part ),* ];
+        }
+    });
+    let chunks = (0..parts.len()).map(|i| {
+        let ident = Ident::new(&format!("PART{}", i), Span::call_site());
+        let bank = args.bank.map_or(FIXED_BANK, |first| first + i as u8);
+        quote! { ::gametank::assets::SampleChunk { bank: #bank, samples: &#ident } }
+    });
+    let sample_rate = args.rate as u16;
+    let len = samples.len() as u32;
+
+    let output = quote! {
+        {
+            #( #statics )*
+
+            ::gametank::assets::PcmSample {
+                sample_rate: #sample_rate,
+                len: #len,
+                chunks: &[ #( #chunks ),* ],
+            }
+        }
+    };
+
+    output.into()
+}
+
+fn wavetable(samples: Vec<u8>) -> TokenStream {
+    let output = quote! {
+        [ #( #samples ),* ]
+    };
+
+    output.into()
+}
+
+/// A sine wavetable: a `[u8; 256]` single cycle for the wavetable firmwares.
+/// Usage: `wavetable_sine!()`
+///
+/// ```ignore
+/// static SINE: [u8; 256] = wavetable_sine!(amplitude = 127);
+/// ```
+///
+/// - `amplitude = N`: how far the wave swings either side of 0x80, 1-127.
+///   The default of 64 is as far as the 8-voice firmware can take at full
+///   volume; the 7-voice linear firmware can take 127.
+///
+/// The other wavetable macros take the same option after their own arguments.
+#[proc_macro]
+pub fn wavetable_sine(input: TokenStream) -> TokenStream {
+    let options = parse_macro_input!(input as wavetable::WaveOptions);
+    wavetable(wavetable::sine(&options))
+}
+
+/// A sawtooth wavetable, rising from the middle like [`wavetable_sine!`].
+/// Usage: `wavetable_saw!()`
+#[proc_macro]
+pub fn wavetable_saw(input: TokenStream) -> TokenStream {
+    let options = parse_macro_input!(input as wavetable::WaveOptions);
+    wavetable(wavetable::saw(&options))
+}
+
+/// A pulse wavetable, high for the first `duty` percent of the cycle.
+/// Usage: `wavetable_pulse!(25)`
+///
+/// 50 is a square wave; 25 and 12 are the thinner NES-style pulses.
+#[proc_macro]
+pub fn wavetable_pulse(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as wavetable::PulseArgs);
+    wavetable(wavetable::pulse(&args))
+}
+
+/// A wavetable from a WAV file holding a single cycle of a waveform.
+/// Usage: `wavetable_from_samples!("path/to/single_cycle.wav")`
+///
+/// ```ignore
+/// static ORGAN: [u8; 256] = wavetable_from_samples!("assets/organ.wav", amplitude = 48);
+/// ```
+///
+/// The whole file is stretched or squeezed to 256 samples whatever its rate,
+/// so trim it to exactly one cycle. It's centered and scaled so its peak
+/// reaches the amplitude.
+#[proc_macro]
+pub fn wavetable_from_samples(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as wavetable::SampleArgs);
+    wavetable(wavetable::from_samples(&args))
+}
+
 #[proc_macro]
 pub fn string_to_indices(input: TokenStream) -> TokenStream {
     let input_string = parse_macro_input!(input as LitStr).value();
@@ -250,3 +569,48 @@ pub fn string_to_indices(input: TokenStream) -> TokenStream {
 
     output.into()
 }
+
+/// Put a function or static in ROM bank `N`, instead of hand-typing its link section:
+///
+/// ```ignore
+/// #[bank(126)]
+/// fn draw_background(blitter: &mut BlitterGuard) { ... }
+///
+/// #[bank(10)]
+/// static LEVEL_DATA: [u8; 8192] = *include_bytes!("level1.bin");
+/// ```
+///
+/// Functions go in `.text.bankN` and statics in `.rodata.bankN`. Each item is
+/// also listed in the bank inventory that `gtrom build` prints with its bank
+/// report.
+///
+/// A function keeps its name for a trampoline in the fixed bank that switches
+/// to bank `N`, calls it, and switches back, so callers don't have to care
+/// where it lives. `#[bank(N, near)]` leaves the trampoline out, for functions
+/// only called from bank `N` itself. Statics don't get one: switch to their
+/// bank before reading them.
+#[proc_macro_attribute]
+pub fn bank(attr: TokenStream, item: TokenStream) -> TokenStream {
+    bank::expand(attr.into(), item.into()).into()
+}
+
+/// Mark the function the console boots into:
+///
+/// ```ignore
+/// #![no_std]
+/// #![no_main]
+///
+/// #[gt_main]
+/// fn game(console: &mut Console) {
+///     loop { /* ... */ }
+/// }
+/// ```
+///
+/// The SDK's boot code sets up RAM and the stack, builds the `Console` and
+/// calls this with it; the panic handler lives in the SDK too. Returning
+/// counts as a panic, so loop forever. `#![no_std]` and `#![no_main]` still go at the top of
+/// `main.rs`, since an attribute can't add them to the crate.
+#[proc_macro_attribute]
+pub fn gt_main(attr: TokenStream, item: TokenStream) -> TokenStream {
+    entry::expand(attr.into(), item.into()).into()
+}
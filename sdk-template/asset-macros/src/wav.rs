@@ -0,0 +1,168 @@
+//! WAV loading for `include_wav!`: PCM or float, any channel count, mixed
+//! down to mono and resampled to unsigned 8-bit, the format the DAC takes.
+
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitInt, LitStr, Token};
+
+/// Roughly the rate the audio coprocessor's firmware runs at
+pub const DEFAULT_RATE: u32 = 14000;
+
+/// The highest bank a sample can go in; 127 is the fixed bank
+const MAX_BANK: u8 = 126;
+
+/// `"path"`, then optionally `rate = 14000` and `bank = N`
+pub struct WavArgs {
+    pub path: String,
+    pub rate: u32,
+    pub bank: Option<u8>,
+}
+
+impl Parse for WavArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse::<LitStr>()?.value();
+        let mut args = WavArgs { path, rate: DEFAULT_RATE, bank: None };
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let lit: LitInt = input.parse()?;
+            match key.to_string().as_str() {
+                "rate" => {
+                    args.rate = lit.base10_parse()?;
+                    if !(1000..=u16::MAX as u32).contains(&args.rate) {
+                        return Err(syn::Error::new(lit.span(), "rate has to be 1000-65535 Hz"));
+                    }
+                }
+                "bank" => {
+                    let bank = lit.base10_parse::<u8>()?;
+                    if bank > MAX_BANK {
+                        return Err(syn::Error::new(lit.span(), format!("bank {} is out of range (0-{})", bank, MAX_BANK)));
+                    }
+                    args.bank = Some(bank);
+                }
+                _ => return Err(syn::Error::new(key.span(), "expected `rate` or `bank`")),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+struct Format {
+    tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+/// The RIFF chunks of a WAV file, by id
+fn chunks(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut chunks = vec![];
+    let mut rest = &data[12..];
+    while rest.len() >= 8 {
+        let id = &rest[0..4];
+        let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let body = rest.get(8..8 + len).unwrap_or(&rest[8..]);
+        chunks.push((id, body));
+        // chunks are padded to an even length
+        rest = rest.get(8 + len + (len & 1)..).unwrap_or(&[]);
+    }
+    Some(chunks)
+}
+
+fn parse_format(body: &[u8]) -> Option<Format> {
+    if body.len() < 16 {
+        return None;
+    }
+    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of the subformat GUID
+    if tag == 0xFFFE && body.len() >= 26 {
+        tag = u16::from_le_bytes([body[24], body[25]]);
+    }
+    Some(Format {
+        tag,
+        channels: u16::from_le_bytes([body[2], body[3]]),
+        sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+        bits: u16::from_le_bytes([body[14], body[15]]),
+    })
+}
+
+/// One sample as -1.0..1.0
+fn decode(format: &Format, bytes: &[u8]) -> Option<f32> {
+    Some(match (format.tag, format.bits) {
+        (1, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+        (1, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (1, 24) => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0,
+        (1, 32) => i32::from_le_bytes(bytes[0..4].try_into().unwrap()) as f32 / 2147483648.0,
+        (3, 32) => f32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+        _ => return None,
+    })
+}
+
+/// Linear interpolation is plenty for 8-bit output
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let step = from as f64 / to as f64;
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let a = samples[index];
+            let b = samples.get(index + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// The file's samples as -1.0..1.0 mono, and their sample rate
+pub fn load_mono(file_path: &str) -> (Vec<f32>, u32) {
+    let data = std::fs::read(file_path).expect(&format!("Failed to read file: {}", file_path));
+    let chunks = chunks(&data).unwrap_or_else(|| panic!("{} isn't a WAV file", file_path));
+
+    let format = chunks.iter()
+        .find(|(id, _)| *id == b"fmt ")
+        .and_then(|(_, body)| parse_format(body))
+        .unwrap_or_else(|| panic!("{} has no usable format chunk", file_path));
+    let body = chunks.iter()
+        .find(|(id, _)| *id == b"data")
+        .map(|(_, body)| *body)
+        .unwrap_or_else(|| panic!("{} has no data chunk", file_path));
+
+    let sample_bytes = format.bits as usize / 8;
+    let frame_bytes = sample_bytes * format.channels as usize;
+    if frame_bytes == 0 || decode(&format, &[0; 4]).is_none() {
+        panic!("{}: unsupported WAV format (tag {}, {} bits)", file_path, format.tag, format.bits);
+    }
+
+    // mix down to mono
+    let mono = body.chunks_exact(frame_bytes)
+        .map(|frame| {
+            let sum: f32 = frame.chunks_exact(sample_bytes).filter_map(|s| decode(&format, s)).sum();
+            sum / format.channels as f32
+        })
+        .collect();
+
+    (mono, format.sample_rate)
+}
+
+/// The file's samples at `rate`, as unsigned 8-bit mono
+pub fn load_wav(file_path: &str, rate: u32) -> Vec<u8> {
+    let (mono, sample_rate) = load_mono(file_path);
+    resample(&mono, sample_rate, rate)
+        .into_iter()
+        .map(|s| ((s.clamp(-1.0, 1.0) * 127.0) + 128.0).round() as u8)
+        .collect()
+}
@@ -0,0 +1,149 @@
+//! Single-cycle waveforms for the wavetable firmwares: 256 unsigned 8-bit
+//! samples centered on 0x80, generated or taken from a WAV file.
+
+use std::f32::consts::TAU;
+
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitInt, LitStr, Token};
+
+use crate::wav::load_mono;
+
+pub const WAVETABLE_SIZE: usize = 256;
+
+/// Peak distance from 0x80 by default: what the 8-voice firmware's volume
+/// table has room for, and the height of its built-in sine
+const DEFAULT_AMPLITUDE: u8 = 64;
+const MAX_AMPLITUDE: u8 = 127;
+
+/// Optionally `amplitude = N`, after a comma if it follows another argument
+pub struct WaveOptions {
+    pub amplitude: u8,
+}
+
+fn parse_options(input: ParseStream) -> syn::Result<WaveOptions> {
+    let mut options = WaveOptions { amplitude: DEFAULT_AMPLITUDE };
+
+    while !input.is_empty() {
+        let key: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let lit: LitInt = input.parse()?;
+        match key.to_string().as_str() {
+            "amplitude" => {
+                options.amplitude = lit.base10_parse()?;
+                if !(1..=MAX_AMPLITUDE).contains(&options.amplitude) {
+                    return Err(syn::Error::new(lit.span(), format!("amplitude has to be 1-{}", MAX_AMPLITUDE)));
+                }
+            }
+            _ => return Err(syn::Error::new(key.span(), "expected `amplitude`")),
+        }
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(options)
+}
+
+/// A comma, unless there's nothing after it
+fn parse_separator(input: ParseStream) -> syn::Result<()> {
+    if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+    }
+    Ok(())
+}
+
+impl Parse for WaveOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        parse_options(input)
+    }
+}
+
+/// The duty cycle in percent, then the options
+pub struct PulseArgs {
+    pub duty: u8,
+    pub options: WaveOptions,
+}
+
+impl Parse for PulseArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitInt = input.parse()?;
+        let duty = lit.base10_parse()?;
+        if !(1..=99).contains(&duty) {
+            return Err(syn::Error::new(lit.span(), "duty has to be 1-99 percent"));
+        }
+        parse_separator(input)?;
+        Ok(Self { duty, options: parse_options(input)? })
+    }
+}
+
+/// The WAV file, then the options
+pub struct SampleArgs {
+    pub path: String,
+    pub options: WaveOptions,
+}
+
+impl Parse for SampleArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse::<LitStr>()?.value();
+        parse_separator(input)?;
+        Ok(Self { path, options: parse_options(input)? })
+    }
+}
+
+/// Samples from `wave`, given each one's phase as 0.0..1.0
+fn generate(options: &WaveOptions, wave: impl Fn(f32) -> f32) -> Vec<u8> {
+    (0..WAVETABLE_SIZE)
+        .map(|i| to_u8(wave(i as f32 / WAVETABLE_SIZE as f32), options.amplitude))
+        .collect()
+}
+
+/// -1.0..1.0 to an unsigned sample `amplitude` either side of 0x80
+fn to_u8(sample: f32, amplitude: u8) -> u8 {
+    (128.0 + sample.clamp(-1.0, 1.0) * amplitude as f32).round() as u8
+}
+
+pub fn sine(options: &WaveOptions) -> Vec<u8> {
+    generate(options, |phase| (phase * TAU).sin())
+}
+
+/// Rises from the middle like the sine, and drops at the half way point
+pub fn saw(options: &WaveOptions) -> Vec<u8> {
+    generate(options, |phase| ((phase + 0.5) % 1.0) * 2.0 - 1.0)
+}
+
+/// High for the first `duty` percent of the cycle
+pub fn pulse(args: &PulseArgs) -> Vec<u8> {
+    let duty = args.duty as f32 / 100.0;
+    generate(&args.options, |phase| if phase < duty { 1.0 } else { -1.0 })
+}
+
+/// The whole file as one cycle: stretched or squeezed to 256 samples,
+/// centered, and scaled so its peak reaches the amplitude
+pub fn from_samples(args: &SampleArgs) -> Vec<u8> {
+    let (samples, _) = load_mono(&args.path);
+    if samples.is_empty() {
+        panic!("{} has no samples", args.path);
+    }
+
+    // wraps around the end, since the cycle repeats
+    let len = samples.len();
+    let cycle: Vec<f32> = (0..WAVETABLE_SIZE)
+        .map(|i| {
+            let pos = i as f32 * len as f32 / WAVETABLE_SIZE as f32;
+            let index = pos as usize;
+            let frac = pos - index as f32;
+            let a = samples[index];
+            let b = samples[(index + 1) % len];
+            a + (b - a) * frac
+        })
+        .collect();
+
+    let mean = cycle.iter().sum::<f32>() / WAVETABLE_SIZE as f32;
+    let peak = cycle.iter().map(|s| (s - mean).abs()).fold(0.0, f32::max);
+    let scale = if peak > 0.0 { 1.0 / peak } else { 0.0 };
+
+    cycle.iter()
+        .map(|s| to_u8((s - mean) * scale, args.options.amplitude))
+        .collect()
+}
@@ -26,6 +26,19 @@ fn main() {
     //     .status().unwrap().success());
     // println!("cargo:warning=Generated target/audiofw.bin");
 
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+
+    // `gtrom build` writes a linker script for the memory map in gtrom.toml;
+    // plain `cargo build` gets the default 2MB layout below
+    let generated = Path::new(&manifest_dir).join("target/gtrom/linker.ld");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", generated.display());
+    if generated.exists() {
+        println!("cargo:rustc-link-arg=-T{}", generated.display());
+        link_asm(&manifest_dir);
+        return;
+    }
+
     let out_dir = env::var("OUT_DIR").unwrap();
     let link_path = Path::new(&out_dir).join("linker.ld");
     let mut f = File::create(&link_path).expect("failed to create linker.ld");
@@ -79,6 +92,8 @@ fn main() {
         "  .data : {{ __data_start = .; *(.data*) __data_end = .; }} > RAM AT > FIXED_FLASH"
     )
     .unwrap();
+    // #[bank] inventory for gtrom's report; INFO keeps it out of the ROM
+    writeln!(f, "  .gt_banks 0 (INFO) : {{ KEEP(*(.gt_banks)) }}").unwrap();
 
     writeln!(f, "  PROVIDE(__zp_load = LOADADDR(.zp));").unwrap();
     writeln!(f, "  PROVIDE(__zp_start = ADDR(.zp));").unwrap();
@@ -100,8 +115,11 @@ fn main() {
     // Hook up the linker script
     println!("cargo:rustc-link-arg=-T{}", link_path.display());
 
+    link_asm(&manifest_dir);
+}
+
+fn link_asm(manifest_dir: &str) {
     // Preserve static asm lib - use absolute path for container compatibility
-    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     println!("cargo:rustc-link-search=native={}/target/asm", manifest_dir);
     println!("cargo:rustc-link-lib=static=asm");
 }
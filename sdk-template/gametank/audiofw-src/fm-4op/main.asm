@@ -0,0 +1,223 @@
+.global audio_irq
+.extern logsin_table
+.extern exp_table
+.extern exp_neg_table
+.section .text
+
+; Memory map (4KB = $0000 - $0FFF) as a Markdown table:
+; | Range        | Size         | Purpose                        | Notes                                      |
+; |--------------|--------------|--------------------------------|--------------------------------------------|
+; | $0000-$0040  | $0040 (64)   | Zero Page (Reserved)           | Fast addressing; pointers & small vars     |
+; | $0041-$0072  | $0032 (50)   | Voices (2 × 25 bytes)          | VOICE_BASE = $0041, VOICE_SIZE = 25        |
+; | $0073-$0075  | $0003 (3)    | IRQ temporaries                | OP_INDEX, OP_OUT, MIX                      |
+; | $0100-$01FF  | $0100 (256)  | CPU Stack                      | CPU stack                                  |
+; | $0200-$04FF  | $0300 (768)  | Log-sine and exp tables        | logsin_table, exp_table, exp_neg_table     |
+; | $0500-$0FF9  | $0AFA (2810) | Code / other data              | ARAM region for program code               |
+; | $0FFA-$0FFF  | $0006 (6)    | Vector table                   | NMI, RESET, IRQ vectors                    |
+;
+; Addresses are little-endian, and ranges are inclusive. The ROM reads this
+; layout from gt-audio-abi's `fm_4op` module, so change both together.
+;
+; Each voice is an algorithm byte and 4 operators. An operator is a sine
+; whose phase is pushed around by the operator before it (a modulator), or
+; that goes straight to the output (a carrier). The algorithm picks which:
+;
+; | Algorithm | Name     | Routing                    |
+; |-----------|----------|----------------------------|
+; | 0         | STACK    | 0 -> 1 -> 2 -> 3 -> out    |
+; | 1         | PAIRS    | 0 -> 1 -> out, 2 -> 3 -> out |
+; | 2         | BRANCH   | 0 -> 1 -> 2 -> out, 3 -> out |
+; | 3         | ADDITIVE | 0, 1, 2, 3 -> out          |
+;
+; Operator levels are attenuations on a log scale (32 to a halving, 0 is
+; loudest), so scaling a sine is an add between two table lookups. Carriers
+; are shifted down by how many share the voice, so every algorithm peaks at
+; the same loudness and the two voices can't overflow the mix.
+
+; Define the base address for the voices (zero page)
+.set VOICE_BASE, 0x0041   ; zero-page base for voice control registers
+.set VOICE_SIZE, 25       ; algorithm + 4 operators
+.set VOICE_COUNT, 2
+.set OP_SIZE, 6           ; phase, freq, attenuation, ratio
+.set VOICE_END, (VOICE_BASE + (VOICE_SIZE * VOICE_COUNT) - 1)  ; last byte used by voices (0x0072)
+
+; Temporary ZP storage for IRQ
+.set OP_INDEX, 0x0073     ; modulated phase of the current operator
+.set OP_OUT, 0x0074       ; signed output of the last modulator
+.set MIX, 0x0075          ; signed running mix
+
+; Macro to define offsets for a voice and its operators. The ratio byte is
+; only read by the ROM, to work out operator frequencies from a note.
+.macro DEFINE_OPERATOR v, k
+    .set OP_\v\()_\k\()_BASE, (VOICE_\v\()_BASE + 1 + (OP_SIZE * \k))
+    .set OP_\v\()_\k\()_PHASE_L, (OP_\v\()_\k\()_BASE + 0)
+    .set OP_\v\()_\k\()_PHASE_H, (OP_\v\()_\k\()_BASE + 1)
+    .set OP_\v\()_\k\()_FREQ_L, (OP_\v\()_\k\()_BASE + 2)
+    .set OP_\v\()_\k\()_FREQ_H, (OP_\v\()_\k\()_BASE + 3)
+    .set OP_\v\()_\k\()_ATTEN, (OP_\v\()_\k\()_BASE + 4)
+    .set OP_\v\()_\k\()_RATIO, (OP_\v\()_\k\()_BASE + 5)
+.endm
+
+.macro DEFINE_VOICE v
+    .set VOICE_\v\()_BASE, (VOICE_BASE + (VOICE_SIZE * \v))
+    .set VOICE_\v\()_ALGORITHM, (VOICE_\v\()_BASE + 0)
+    DEFINE_OPERATOR \v, 0
+    DEFINE_OPERATOR \v, 1
+    DEFINE_OPERATOR \v, 2
+    DEFINE_OPERATOR \v, 3
+.endm
+
+DEFINE_VOICE 0
+DEFINE_VOICE 1
+
+; Macro to run one operator of voice v under algorithm alg.
+;   modulated: add the previous operator's output (OP_OUT) to the phase
+;   carrier:   0 to leave the output in OP_OUT for the next operator, or
+;              1 + how far to shift it down before mixing it in
+.macro OPERATOR v, alg, k, modulated, carrier
+    ; Add FREQ to PHASE (16-bit addition)
+    clc
+    lda OP_\v\()_\k\()_PHASE_L
+    adc OP_\v\()_\k\()_FREQ_L
+    sta OP_\v\()_\k\()_PHASE_L
+    lda OP_\v\()_\k\()_PHASE_H
+    adc OP_\v\()_\k\()_FREQ_H
+    sta OP_\v\()_\k\()_PHASE_H
+
+.if \modulated
+    clc
+    adc OP_OUT             ; push the phase around by the modulator
+.endif
+    sta OP_INDEX
+
+    ; log(sine) + attenuation, stuck at the quietest if it overflows
+    and #0x7f
+    tax
+    lda logsin_table, x
+    clc
+    adc OP_\v\()_\k\()_ATTEN
+    bcc loud_\v\()_\alg\()_\k
+    lda #0xff
+loud_\v\()_\alg\()_\k:
+    tax
+
+    ; back to amplitude, negative for the second half of the wave
+    bit OP_INDEX
+    bmi negative_\v\()_\alg\()_\k
+    lda exp_table, x
+    jmp signed_\v\()_\alg\()_\k
+negative_\v\()_\alg\()_\k:
+    lda exp_neg_table, x
+signed_\v\()_\alg\()_\k:
+
+.if \carrier
+    .rept (\carrier - 1)
+    cmp #0x80              ; arithmetic shift right
+    ror a
+    .endr
+    clc
+    adc MIX
+    sta MIX
+.else
+    sta OP_OUT
+.endif
+.endm
+
+; Macro to run a voice: jump to its algorithm's unrolled operators
+.macro PROCESS_VOICE v
+    lda VOICE_\v\()_ALGORITHM
+    and #3
+    asl a
+    tax
+    jmp (algorithms_\v, x)
+
+algorithm_\v\()_0:         ; STACK: one carrier
+    OPERATOR \v, 0, 0, 0, 0
+    OPERATOR \v, 0, 1, 1, 0
+    OPERATOR \v, 0, 2, 1, 0
+    OPERATOR \v, 0, 3, 1, 2
+    jmp voice_done_\v
+
+algorithm_\v\()_1:         ; PAIRS: two carriers
+    OPERATOR \v, 1, 0, 0, 0
+    OPERATOR \v, 1, 1, 1, 3
+    OPERATOR \v, 1, 2, 0, 0
+    OPERATOR \v, 1, 3, 1, 3
+    jmp voice_done_\v
+
+algorithm_\v\()_2:         ; BRANCH: two carriers
+    OPERATOR \v, 2, 0, 0, 0
+    OPERATOR \v, 2, 1, 1, 0
+    OPERATOR \v, 2, 2, 1, 3
+    OPERATOR \v, 2, 3, 0, 3
+    jmp voice_done_\v
+
+algorithm_\v\()_3:         ; ADDITIVE: four carriers
+    OPERATOR \v, 3, 0, 0, 4
+    OPERATOR \v, 3, 1, 0, 4
+    OPERATOR \v, 3, 2, 0, 4
+    OPERATOR \v, 3, 3, 0, 4
+
+voice_done_\v:
+.endm
+
+audio_irq:
+    ; Mix signed around 0, centered at the end
+    lda #0
+    sta MIX
+
+    PROCESS_VOICE 0
+    PROCESS_VOICE 1
+
+    ; Output final mixed sample
+    lda MIX
+    eor #0x80              ; signed to unsigned
+    sta 0x8040
+
+    rti                    ; return from interrupt
+
+; Algorithm jump tables, one per voice
+algorithms_0:
+    .word algorithm_0_0, algorithm_0_1, algorithm_0_2, algorithm_0_3
+algorithms_1:
+    .word algorithm_1_0, algorithm_1_1, algorithm_1_2, algorithm_1_3
+
+
+; Simple main function that just waits
+.section .text
+.global _start
+_start:
+    sei                    ; disable interrupts during setup
+    cld                    ; clear decimal mode
+
+    ; Initialize stack pointer
+    ldx #0xff
+    txs
+
+    ; Voice data is already initialized by .data.voices section
+    ; (algorithm 0, every operator silent at ratio 1)
+
+    ; Enable interrupts
+    cli
+
+main_loop:
+    wai                    ; wait for interrupt
+    jmp main_loop          ; loop forever
+
+; Initialize voice data: silent operators, each at the note's frequency
+.section .data.voices
+.rept 2
+    .byte 0                ; algorithm
+    .rept 4
+    .byte 0, 0             ; phase_l, phase_h
+    .byte 0, 0             ; freq_l, freq_h
+    .byte 0xff             ; attenuation (silent)
+    .byte 2                ; ratio, in halves (2 = the note itself)
+    .endr
+.endr
+
+; Vector table (must be at $FFFA-$FFFF)
+.section .vector_table, "a"
+    .word audio_irq        ; NMI vector ($FFFA-$FFFB)
+    .word _start           ; RESET vector ($FFFC-$FFFD)
+    .word audio_irq        ; IRQ/BRK vector ($FFFE-$FFFF)
@@ -0,0 +1,155 @@
+.global audio_irq
+.global audio_nmi
+.section .text
+
+; Memory map (4KB = $0000 - $0FFF) as a Markdown table:
+; | Range        | Size         | Purpose                        | Notes                                      |
+; |--------------|--------------|--------------------------------|--------------------------------------------|
+; | $0000-$0040  | $0040 (64)   | Zero Page (Reserved)           | Fast addressing; pointers & small vars     |
+; | $0041-$0047  | $0007 (7)    | Stream state                   | STREAM_BASE = $0041                        |
+; | $0100-$01FF  | $0100 (256)  | CPU Stack                      | CPU stack                                  |
+; | $0400-$07FF  | $0400 (1KB)  | Buffer A                       | BUFFER_A, filled by the main CPU           |
+; | $0800-$0BFF  | $0400 (1KB)  | Buffer B                       | BUFFER_B, filled by the main CPU           |
+; | $0E00-$0FF9  | $01FA (506)  | Code / other data              | ARAM region for program code               |
+; | $0FFA-$0FFF  | $0006 (6)    | Vector table                   | NMI, RESET, IRQ vectors                    |
+;
+; Addresses are little-endian, and ranges are inclusive. The ROM reads this
+; layout from gt-audio-abi's `pcm` module, so change both together.
+;
+; Streaming protocol: the IRQ plays one unsigned 8-bit sample per interrupt,
+; through buffer A, then B, then A again. A buffer only plays once its
+; FILLED flag is set, and the IRQ clears the flag when it's played the
+; last byte, handing the buffer back. The main CPU:
+;
+; 1. waits for a buffer's FILLED flag to read 0, and fills it
+; 2. writes CMD_FILLED_A and/or CMD_FILLED_B to COMMAND
+; 3. writes to the ACP's NMI register ($2001); the NMI sets the flags and
+;    clears COMMAND
+;
+; If the next buffer isn't filled in time, the output holds its last level
+; until it is. CMD_STOP drops both buffers and rewinds to the start of A;
+; the CPU waits for RESTART to read 0 before filling again.
+
+; Stream state (zero page)
+.set STREAM_BASE, 0x0041
+.set PTR_L, 0x0041        ; next sample to play
+.set PTR_H, 0x0042
+.set CURRENT, 0x0043      ; buffer playing: 0 = A, 1 = B
+.set FILLED, 0x0044       ; FILLED + CURRENT is the playing buffer's flag
+.set FILLED_A, 0x0044     ; non-zero while buffer A holds samples to play
+.set FILLED_B, 0x0045     ; non-zero while buffer B holds samples to play
+.set COMMAND, 0x0046      ; written by the CPU before an NMI
+.set RESTART, 0x0047      ; set by CMD_STOP, cleared once rewound
+
+; Commands
+.set CMD_FILLED_A, 0x01
+.set CMD_FILLED_B, 0x02
+.set CMD_STOP, 0x80
+
+; Buffers: page aligned and 4 pages long, so a buffer ends when the low
+; two bits of the pointer's high byte wrap to 0
+.set BUFFER_A, 0x0400
+.set BUFFER_B, 0x0800
+.set BUFFER_END, 0x0C00
+.set BUFFER_PAGES_MASK, 0x03
+
+audio_irq:
+    ldx CURRENT
+    lda FILLED, x
+    beq starved            ; nothing to play: hold the last level
+
+    lda (PTR_L)
+    sta 0x8040
+
+    inc PTR_L
+    bne irq_done
+    inc PTR_H
+    lda PTR_H
+    and #BUFFER_PAGES_MASK
+    bne irq_done
+
+    ; Played the whole buffer: hand it back and move to the other one
+    stz FILLED, x
+    txa
+    eor #1
+    sta CURRENT
+    bne irq_done           ; A ran straight on into B
+    lda #>BUFFER_A         ; B wraps back around to A
+    sta PTR_H
+irq_done:
+    rti
+
+starved:
+    lda RESTART
+    beq irq_done
+    stz CURRENT
+    stz PTR_L
+    lda #>BUFFER_A
+    sta PTR_H
+    stz RESTART
+    rti
+
+; The CPU's handshake: take COMMAND and clear it to say it's done
+audio_nmi:
+    pha
+    lda COMMAND
+    bpl check_a
+
+    ; CMD_STOP: drop both buffers, and have the IRQ rewind once it sees
+    ; nothing to play
+    stz FILLED_A
+    stz FILLED_B
+    lda #1
+    sta RESTART
+    bra nmi_done
+
+check_a:
+    and #CMD_FILLED_A
+    beq check_b
+    sta FILLED_A
+check_b:
+    lda COMMAND
+    and #CMD_FILLED_B
+    beq nmi_done
+    sta FILLED_B
+
+nmi_done:
+    stz COMMAND
+    pla
+    rti
+
+
+; Simple main function that just waits
+.section .text
+.global _start
+_start:
+    sei                    ; disable interrupts during setup
+    cld                    ; clear decimal mode
+
+    ; Initialize stack pointer
+    ldx #0xff
+    txs
+
+    ; Stream state is already initialized by .data.stream section
+    ; (at the start of A, with nothing to play)
+
+    ; Enable interrupts
+    cli
+
+main_loop:
+    wai                    ; wait for interrupt
+    jmp main_loop          ; loop forever
+
+; Initialize stream state: starved at the start of buffer A
+.section .data.stream
+    .word BUFFER_A         ; ptr
+    .byte 0                ; current
+    .byte 0, 0             ; filled_a, filled_b
+    .byte 0                ; command
+    .byte 0                ; restart
+
+; Vector table (must be at $FFFA-$FFFF)
+.section .vector_table, "a"
+    .word audio_nmi        ; NMI vector ($FFFA-$FFFB)
+    .word _start           ; RESET vector ($FFFC-$FFFD)
+    .word audio_irq        ; IRQ/BRK vector ($FFFE-$FFFF)
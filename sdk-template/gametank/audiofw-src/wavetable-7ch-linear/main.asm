@@ -6,16 +6,18 @@
 ; Memory map (4KB = $0000 - $0FFF) as a Markdown table:
 ; | Range        | Size         | Purpose                        | Notes                                      |
 ; |--------------|--------------|--------------------------------|--------------------------------------------|
-; | $0000-$0040  | $0040 (64)   | Zero Page (Reserved)           | Fast addressing; pointers & small vars     |
-; | $0041-$0089  | $0048 (72)   | Voices (8 × 9 bytes)           | VOICE_BASE = $0041, VOICE_SIZE = 9        |
+; | $0000-$0040  | $0041 (65)   | Zero Page (Reserved)           | The compiler's registers                   |
+; | $0041-$007F  | $003F (63)   | Voices (7 × 9 bytes)           | VOICE_BASE = $0041, VOICE_SIZE = 9         |
 ; | $008A-$008C  | $0003 (3)    | IRQ temporaries                | MIX_ACCUMULATOR, TEMP_RESULT1/2           |
 ; | $0100-$01FF  | $0100 (256)  | CPU Stack                      | CPU stack                                  |
 ; | $0200-$05FF  | $0400 (1KB)  | Volume tables (4 × 256)        | vol_table_0 through vol_table_3           |
 ; | $0600-$0BFF  | $0600 (1.5KB)| Wavetables (6 × 256)           | WAVETABLE_BASE = $0600, WAVETABLE_SIZE = 256 |
-; | $0C00-$0FF9  | $03FA (1018) | Code / other data              | ARAM region for program code              |
+; | $0C00-$0DFF  | $0200 (512)  | Unused                         | The linker's WAVE region runs to $0DFF     |
+; | $0E00-$0FF9  | $01FA (506)  | Code / other data              | ARAM region for program code               |
 ; | $0FFA-$0FFF  | $0006 (6)    | Vector table                   | NMI, RESET, IRQ vectors                   |
 ;
-; Addresses are little-endian, and ranges are inclusive.
+; Addresses are little-endian, and ranges are inclusive. The ROM reads this
+; layout from gt-audio-abi's `wavetable_7ch_linear` module, so change both together.
 
 ; Define the base address for the voices (zero page)
 .set VOICE_BASE, 0x0041   ; zero-page base for voice control registers
@@ -29,9 +31,9 @@
 .set TEMP_RESULT2, 0x008C ; temporary storage for second vol_table result
 
 ; Define where wavetables live
-.set WAVETABLE_BASE, 0x0400    ; base address for wavetable storage
+.set WAVETABLE_BASE, 0x0600    ; base address for wavetable storage
 .set WAVETABLE_SIZE, 256       ; each wavetable is 256 samples (bytes)
-.set WAVETABLE_COUNT, 8
+.set WAVETABLE_COUNT, 6
 .set WAVETABLE_END, (WAVETABLE_BASE + (WAVETABLE_SIZE * WAVETABLE_COUNT) - 1) ; (0x0BFF)
 
 ; Macro to define offsets for a voice
@@ -62,15 +64,13 @@ DEFINE_VOICE 4
 DEFINE_VOICE 5
 DEFINE_VOICE 6
 
-; Define 8 wavetables (change count if needed)
+; Define 6 wavetables, one per WAVETABLE_COUNT
 DEFINE_WAVETABLE 0
 DEFINE_WAVETABLE 1
 DEFINE_WAVETABLE 2
 DEFINE_WAVETABLE 3
 DEFINE_WAVETABLE 4
 DEFINE_WAVETABLE 5
-DEFINE_WAVETABLE 6
-DEFINE_WAVETABLE 7
 
 ; Macro to process a single voice and mix into TEMP_SAMPLE
 .macro PROCESS_VOICE n
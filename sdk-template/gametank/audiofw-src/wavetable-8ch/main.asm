@@ -6,14 +6,21 @@
 ; Memory map (4KB = $0000 - $0FFF) as a Markdown table:
 ; | Range        | Size         | Purpose                        | Notes                                      |
 ; |--------------|--------------|--------------------------------|--------------------------------------------|
-; | $0000-$0040  | $0100 (256)  | Zero Page (Reserved)           | Fast addressing; pointers & small vars     |
-; | $0041-$0078  | $0038 (56)   | Voices (8 × 7 bytes)           | VOICE_BASE = $0042, VOICE_SIZE = 7        |
+; | $0000-$0040  | $0041 (65)   | Zero Page (Reserved)           | The compiler's registers                   |
+; | $0041-$0078  | $0038 (56)   | Voices (8 × 7 bytes)           | VOICE_BASE = $0041, VOICE_SIZE = 7         |
+; | $0079-$007D  | $0005 (5)    | IRQ temps, envelope tick       | TICK counts samples for the main loop      |
+; | $007E-$007F  | $0002 (2)    | Noise LFSR                     | 16-bit Galois, taps $B400                  |
+; | $0080-$00B7  | $0038 (56)   | Envelopes (8 × 7 bytes)        | ENV_BASE = $0080, same stride as voices    |
+; | $00B8        | $0001 (1)    | Noise mode                     | Non-zero: voice 7 plays noise              |
+; | $00B9-$00F0  | $0038 (56)   | Slides (8 × 7 bytes)           | SLIDE_BASE = $00B9, same stride as voices  |
+; | $00F1-$00F2  | $0002 (2)    | Slide temps                    | Main loop only; IRQ never reads them       |
 ; | $0100-$01FF  | $0100 (256)  | CPU Stack                      | CPU stack                                  |
-; | $0200-$03FF  | $0200 (512)  | Hardcoded wavetables (2 × 256) | WAVETABLE_BASE = $0200, WAVETABLE_SIZE = 256 |
-; | $0400-$0BFF  | $0A00 (2560) | Wavetables (10 × 256)          | WAVETABLE_BASE = $0400, WAVETABLE_SIZE = 256 |
-; | $0E00-$0FFF  | $0200 (512)  | Code / other data              | Remaining 1KB for code/data/ROM layout     |
+; | $0200-$02FF  | $0100 (256)  | Volume table                   | vol_table                                  |
+; | $0300-$0CFF  | $0A00 (2560) | Wavetables (10 × 256)          | WAVETABLE_BASE = $0300; sine_table is 0    |
+; | $0D00-$0FFF  | $0300 (768)  | Code / other data              | Code, data and vectors ($0FFA-$0FFF)       |
 ;
-; Addresses are little-endian, and ranges are inclusive.
+; Addresses are little-endian, and ranges are inclusive. The ROM reads this
+; layout from gt-audio-abi's `wavetable_8ch` module, so change both together.
 
 ; Define the base address for the voices (zero page)
 .set VOICE_BASE, 0x0041   ; zero-page base for voice control registers
@@ -26,11 +33,64 @@
 .set TEMP_RESULT1, 0x007a ; temporary storage for first vol_table result
 .set TEMP_RESULT2, 0x007b ; temporary storage for second vol_table result
 
+; Envelope clock: the IRQ counts samples, the main loop steps envelopes
+; every ENV_PERIOD of them (~220Hz at ~14kHz)
+.set TICK, 0x007c
+.set LAST_TICK, 0x007d
+.set ENV_PERIOD, 64
+
+; Envelopes, one per voice, laid out with the voices' stride so the same
+; X offset reaches a voice and its envelope
+.set ENV_BASE, 0x0080
+.set ENV_STAGE, (ENV_BASE + 0)   ; ENV_OFF, or where the envelope is
+.set ENV_LEVEL, (ENV_BASE + 1)   ; 0-255; the voice's volume is level / 4
+.set ENV_ATTACK, (ENV_BASE + 2)  ; added to level per step until 255
+.set ENV_DECAY, (ENV_BASE + 3)   ; taken from level per step down to sustain
+.set ENV_SUSTAIN, (ENV_BASE + 4) ; level held while the note is on
+.set ENV_RELEASE, (ENV_BASE + 5) ; taken from level per step down to 0
+.set ENV_END, (ENV_BASE + (VOICE_SIZE * VOICE_COUNT) - 1) ; (0x00B7)
+
+; Noise: with NOISE_MODE set, NOISE_VOICE plays an LFSR instead of its
+; wavetable, stepping it each time the voice's phase wraps, so its frequency
+; sets the noise's pitch
+.set NOISE_L, 0x007e
+.set NOISE_H, 0x007f
+.set NOISE_MODE, 0x00b8
+.set NOISE_VOICE, 7
+.set NOISE_TAPS, 0xb4     ; high byte of the $B400 taps
+
+; Slides, one per voice with the voices' stride like the envelopes. While a
+; step is non-zero, every envelope step moves the voice's frequency or
+; volume that far towards the target, then clears the step on arrival.
+.set SLIDE_BASE, 0x00b9
+.set SLIDE_STEP_L, (SLIDE_BASE + 0)    ; frequency change per step
+.set SLIDE_STEP_H, (SLIDE_BASE + 1)
+.set SLIDE_TARGET_L, (SLIDE_BASE + 2)  ; frequency to stop at
+.set SLIDE_TARGET_H, (SLIDE_BASE + 3)
+.set SLIDE_VOL_STEP, (SLIDE_BASE + 4)  ; volume change per step
+.set SLIDE_VOL_TARGET, (SLIDE_BASE + 5); volume to stop at
+.set SLIDE_END, (SLIDE_BASE + (VOICE_SIZE * VOICE_COUNT) - 1) ; (0x00F0)
+.set SLIDE_TEMP_L, 0x00f1
+.set SLIDE_TEMP_H, 0x00f2
+
+; The voice registers the main loop touches, for use with a voice offset in X
+.set VOICE_FREQ_L, (VOICE_BASE + 2)
+.set VOICE_FREQ_H, (VOICE_BASE + 3)
+.set VOICE_VOLUME, (VOICE_BASE + 6)
+
+; Envelope stages. ENV_OFF leaves the volume to the ROM.
+.set ENV_OFF, 0
+.set ENV_ATTACK_STAGE, 1
+.set ENV_DECAY_STAGE, 2
+.set ENV_SUSTAIN_STAGE, 3
+.set ENV_RELEASE_STAGE, 4
+.set ENV_DONE, 5
+
 ; Define where wavetables live
-.set WAVETABLE_BASE, 0x0400    ; base address for wavetable storage
+.set WAVETABLE_BASE, 0x0300    ; base address for wavetable storage
 .set WAVETABLE_SIZE, 256       ; each wavetable is 256 samples (bytes)
-.set WAVETABLE_COUNT, 8
-.set WAVETABLE_END, (WAVETABLE_BASE + (WAVETABLE_SIZE * WAVETABLE_COUNT) - 1) ; (0x0BFF)
+.set WAVETABLE_COUNT, 10
+.set WAVETABLE_END, (WAVETABLE_BASE + (WAVETABLE_SIZE * WAVETABLE_COUNT) - 1) ; (0x0CFF)
 
 ; Macro to define offsets for a voice
 .macro DEFINE_VOICE voice_index
@@ -59,7 +119,7 @@ DEFINE_VOICE 5
 DEFINE_VOICE 6
 DEFINE_VOICE 7
 
-; Define 8 wavetables (change count if needed)
+; Define 10 wavetables, one per WAVETABLE_COUNT
 DEFINE_WAVETABLE 0
 DEFINE_WAVETABLE 1
 DEFINE_WAVETABLE 2
@@ -68,6 +128,8 @@ DEFINE_WAVETABLE 4
 DEFINE_WAVETABLE 5
 DEFINE_WAVETABLE 6
 DEFINE_WAVETABLE 7
+DEFINE_WAVETABLE 8
+DEFINE_WAVETABLE 9
 
 ; Macro to process a single voice and mix into TEMP_SAMPLE
 .macro PROCESS_VOICE n
@@ -80,9 +142,30 @@ DEFINE_WAVETABLE 7
     adc VOICE_\n\()_FREQ_H
     sta VOICE_\n\()_PHASE_H
 
+    ; The noise voice steps the LFSR when its phase wraps (carry still set
+    ; from the add) and plays its low byte
+.if \n == NOISE_VOICE
+    ldx NOISE_MODE
+    beq noise_tone
+    bcc noise_hold
+    lsr NOISE_H
+    ror NOISE_L
+    bcc noise_hold
+    lda NOISE_H
+    eor #NOISE_TAPS
+    sta NOISE_H
+noise_hold:
+    lda NOISE_L
+    jmp noise_sample
+noise_tone:
+.endif
+
     ; Get wavetable sample using phase_high as index
     tax                    ; X = phase high byte
     lda sine_table, x      ; lookup sample (TODO: use per-voice wavetable pointer)
+.if \n == NOISE_VOICE
+noise_sample:
+.endif
 
     ; Scale to 7-bit for volume scaling
     lsr a                  ; divide by 2
@@ -115,6 +198,11 @@ DEFINE_WAVETABLE 7
 .endm
 
 audio_irq:
+    ; the main loop keeps its voice offset in X between samples
+    pha
+    phx
+    inc TICK               ; envelope clock
+
     ; Initialize accumulator with center value (silence = 0x80)
     ; We'll mix all voices relative to center
     lda #0x80
@@ -134,6 +222,8 @@ audio_irq:
     lda TEMP_SAMPLE
     sta 0x8040
 
+    plx
+    pla
     rti                    ; return from interrupt
 
 
@@ -158,13 +248,182 @@ _start:
     ; Initialize stack pointer
     ldx #0xff
     txs
-    
+
+    ; Clear the envelope clock, noise, envelopes and slides
+    lda #0
+    ldx #(SLIDE_END - TICK)
+clear_env:
+    sta TICK, x
+    dex
+    bpl clear_env
+    inc NOISE_L            ; the LFSR never leaves 0, so don't start there
+
     ; Enable interrupts
     cli
-    
+
+; Sleep between samples, and step the envelopes and slides every
+; ENV_PERIOD of them.
+; This runs in whatever time the IRQ leaves over.
 main_loop:
     wai                    ; wait for interrupt
-    jmp main_loop          ; loop forever
+    lda TICK
+    sec
+    sbc LAST_TICK
+    cmp #ENV_PERIOD
+    bcc main_loop
+    lda TICK
+    sta LAST_TICK
+
+    ldx #0                 ; X = voice/envelope/slide offset
+env_loop:
+    lda ENV_STAGE, x
+    beq slide              ; ENV_OFF: the ROM sets the volume
+    cmp #ENV_ATTACK_STAGE
+    beq env_attack
+    cmp #ENV_DECAY_STAGE
+    beq env_decay
+    cmp #ENV_RELEASE_STAGE
+    beq env_release
+    jmp env_write          ; sustain and done hold their level
+
+env_attack:
+    ; level += attack, into decay at the top
+    lda ENV_LEVEL, x
+    clc
+    adc ENV_ATTACK, x
+    bcc env_store
+    lda #ENV_DECAY_STAGE
+    sta ENV_STAGE, x
+    lda #0xff
+    jmp env_store
+
+env_decay:
+    ; level -= decay, into sustain at the sustain level
+    lda ENV_LEVEL, x
+    sec
+    sbc ENV_DECAY, x
+    bcc env_to_sustain
+    cmp ENV_SUSTAIN, x
+    bcs env_store
+env_to_sustain:
+    lda #ENV_SUSTAIN_STAGE
+    sta ENV_STAGE, x
+    lda ENV_SUSTAIN, x
+    jmp env_store
+
+env_release:
+    ; level -= release, done at 0
+    lda ENV_LEVEL, x
+    sec
+    sbc ENV_RELEASE, x
+    bcs env_store
+    lda #ENV_DONE
+    sta ENV_STAGE, x
+    lda #0
+
+env_store:
+    sta ENV_LEVEL, x
+env_write:
+    lda ENV_LEVEL, x
+    lsr a
+    lsr a
+    sta VOICE_VOLUME, x    ; volume = level / 4
+
+slide:
+    lda SLIDE_STEP_L, x
+    ora SLIDE_STEP_H, x
+    beq slide_volume
+    lda VOICE_FREQ_L, x    ; up or down?
+    cmp SLIDE_TARGET_L, x
+    lda VOICE_FREQ_H, x
+    sbc SLIDE_TARGET_H, x
+    bcc pitch_up
+
+    ; freq -= step, landing once it's below the target
+    lda VOICE_FREQ_L, x
+    sec
+    sbc SLIDE_STEP_L, x
+    sta SLIDE_TEMP_L
+    lda VOICE_FREQ_H, x
+    sbc SLIDE_STEP_H, x
+    bcc pitch_land
+    sta SLIDE_TEMP_H
+    lda SLIDE_TEMP_L
+    cmp SLIDE_TARGET_L, x
+    lda SLIDE_TEMP_H
+    sbc SLIDE_TARGET_H, x
+    bcc pitch_land
+    bcs pitch_store
+
+pitch_up:
+    ; freq += step, landing once it reaches the target
+    lda VOICE_FREQ_L, x
+    clc
+    adc SLIDE_STEP_L, x
+    sta SLIDE_TEMP_L
+    lda VOICE_FREQ_H, x
+    adc SLIDE_STEP_H, x
+    bcs pitch_land
+    sta SLIDE_TEMP_H
+    lda SLIDE_TEMP_L
+    cmp SLIDE_TARGET_L, x
+    lda SLIDE_TEMP_H
+    sbc SLIDE_TARGET_H, x
+    bcs pitch_land
+
+pitch_store:
+    ; the IRQ mustn't add a half-written frequency to the phase
+    sei
+    lda SLIDE_TEMP_L
+    sta VOICE_FREQ_L, x
+    lda SLIDE_TEMP_H
+    sta VOICE_FREQ_H, x
+    cli
+    bra slide_volume
+
+pitch_land:
+    sei
+    lda SLIDE_TARGET_L, x
+    sta VOICE_FREQ_L, x
+    lda SLIDE_TARGET_H, x
+    sta VOICE_FREQ_H, x
+    cli
+    stz SLIDE_STEP_L, x
+    stz SLIDE_STEP_H, x
+
+slide_volume:
+    lda SLIDE_VOL_STEP, x
+    beq env_next
+    lda VOICE_VOLUME, x
+    cmp SLIDE_VOL_TARGET, x
+    bcc volume_up
+    sbc SLIDE_VOL_STEP, x  ; carry set by the cmp
+    bcc volume_land
+    cmp SLIDE_VOL_TARGET, x
+    bcc volume_land
+    bcs volume_store
+volume_up:
+    adc SLIDE_VOL_STEP, x  ; carry clear from the cmp
+    cmp SLIDE_VOL_TARGET, x
+    bcs volume_land
+volume_store:
+    sta VOICE_VOLUME, x
+    bra env_next
+volume_land:
+    lda SLIDE_VOL_TARGET, x
+    sta VOICE_VOLUME, x
+    stz SLIDE_VOL_STEP, x
+
+env_next:
+    txa
+    clc
+    adc #VOICE_SIZE
+    tax
+    cpx #(VOICE_SIZE * VOICE_COUNT)
+    beq env_done
+    jmp env_loop           ; too far back to branch
+env_done:
+    jmp main_loop
 
 ; Vector table (must be at $FFFA-$FFFF)
 .section .vector_table, "a"
@@ -0,0 +1,400 @@
+//! # Assets
+//!
+//! Types for the assets `gtrom` converts from `assets.toml`.
+//!
+//! List images, tile maps and sounds in an `assets.toml` next to your
+//! `Cargo.toml`, each with the ROM bank it should live in:
+//!
+//! ```toml
+//! [[image]]
+//! name = "BACKGROUND"
+//! path = "assets/background.bmp"
+//! bank = 124
+//!
+//! [[map]]
+//! name = "LEVEL1"
+//! path = "assets/level1.csv"
+//! bank = 20
+//!
+//! [[sound]]
+//! name = "JUMP"
+//! path = "assets/jump.wav"
+//! bank = 30
+//!
+//! [[tiled]]
+//! name = "WORLD1"
+//! path = "assets/world1.tmx"
+//! bank = 40
+//! collision = "walls"
+//! ```
+//!
+//! `gtrom build` converts them before compiling, and [`include_assets!`]
+//! pulls the generated statics into your crate:
+//!
+//! ```ignore
+//! gametank::include_assets!();
+//!
+//! console.set_rom_bank(assets::BACKGROUND.bank);
+//! sm.bytes().copy_from_slice(assets::BACKGROUND.pixels);
+//! ```
+//!
+//! The descriptors live in the fixed bank; the data they point at lives in
+//! `bank`, so switch to it before reading.
+//!
+//! A `[[tiled]]` map can be bigger than a bank: its layers are split into
+//! chunks of whole rows, placed in `bank` and the banks after it, and each
+//! [`TileChunk`] says where it went. [`tilemap`](crate::tilemap) draws them.
+
+use crate::{
+    blitter::SpriteQuadrant,
+    text::LoadedFont,
+    video_dma::spritemem::SpriteMemGuard,
+};
+
+/// Bank for assets without one in `assets.toml`, always mapped at `$C000`
+pub const FIXED_BANK: u8 = 127;
+
+/// A full color image, one GameTank color byte per pixel, row by row
+#[derive(Debug, Copy, Clone)]
+pub struct Image {
+    pub bank: u8,
+    pub width: u8,
+    pub height: u8,
+    pub pixels: &'static [u8],
+}
+
+/// An image stored as palette indices, packed `pixels_per_byte` to a byte
+/// starting from the low bits
+#[derive(Debug, Copy, Clone)]
+pub struct IndexedImage {
+    pub bank: u8,
+    pub width: u8,
+    pub height: u8,
+    pub pixels_per_byte: u8,
+    pub palette: &'static [u8],
+    pub pixels: &'static [u8],
+}
+
+/// An image from `include_bmp_compressed!`: GameTank color bytes, row by
+/// row, compressed with [`decompress`]'s format
+#[derive(Debug, Copy, Clone)]
+pub struct CompressedImage {
+    pub width: u8,
+    pub height: u8,
+    pub data: &'static [u8],
+}
+
+impl CompressedImage {
+    /// Unpack into the current sprite RAM quadrant, from its top left. The
+    /// rows land one after another, so an image narrower than 128 pixels
+    /// comes out sheared; use [`unpack_into`](Self::unpack_into) and copy the
+    /// rows into place for those.
+    pub fn unpack(&self, sprite_mem: &mut SpriteMemGuard) {
+        decompress(self.data, sprite_mem.bytes());
+    }
+
+    /// Unpack into `out`, returning how many bytes were written
+    pub fn unpack_into(&self, out: &mut [u8]) -> usize {
+        decompress(self.data, out)
+    }
+}
+
+/// Unpack the output of the asset macros' compressor into `out`, stopping
+/// when either runs out, and return how many bytes were written.
+///
+/// The data is a sequence of tokens:
+///
+/// - `0nnnnnnn`, then n + 1 bytes: literal bytes
+/// - `10nnnnnn b`: byte b, n + 3 times
+/// - `11nnnnnn d`: n + 3 bytes copied from d + 1 bytes back
+///
+/// `out` is only ever written, never read, so it can be sprite RAM: the
+/// last 256 bytes written are kept on the stack for the copies.
+pub fn decompress(data: &[u8], out: &mut [u8]) -> usize {
+    let mut history = [0u8; 256];
+    let mut written = 0;
+    let mut i = 0;
+
+    #[inline(always)]
+    fn put(out: &mut [u8], history: &mut [u8; 256], written: &mut usize, byte: u8) {
+        history[*written & 0xFF] = byte;
+        out[*written] = byte;
+        *written += 1;
+    }
+
+    while i < data.len() && written < out.len() {
+        let token = data[i];
+        i += 1;
+        let len = (token & 0x3F) as usize + 3;
+        match token >> 6 {
+            0 | 1 => {
+                let end = (i + token as usize + 1).min(data.len());
+                for &byte in &data[i..end] {
+                    if written == out.len() {
+                        break;
+                    }
+                    put(out, &mut history, &mut written, byte);
+                }
+                i = end;
+            }
+            2 => {
+                let Some(&byte) = data.get(i) else { break };
+                i += 1;
+                for _ in 0..len.min(out.len() - written) {
+                    put(out, &mut history, &mut written, byte);
+                }
+            }
+            _ => {
+                let Some(&back) = data.get(i) else { break };
+                i += 1;
+                for _ in 0..len.min(out.len() - written) {
+                    let byte = history[written.wrapping_sub(back as usize + 1) & 0xFF];
+                    put(out, &mut history, &mut written, byte);
+                }
+            }
+        }
+    }
+    written
+}
+
+/// A bitmap font from `include_font!`: a grid of `glyph_width` x
+/// `glyph_height` glyphs in ASCII order from a space, one GameTank color byte
+/// per pixel. Load it into sprite RAM, then draw with [`text`](crate::text).
+#[derive(Debug, Copy, Clone)]
+pub struct Font {
+    pub width: u8,
+    pub height: u8,
+    pub glyph_width: u8,
+    pub glyph_height: u8,
+    pub pixels: &'static [u8],
+}
+
+impl Font {
+    /// Copy the font into the current sprite RAM quadrant with its top left
+    /// at (`x`, `y`), and return where it ended up for [`draw_text`](crate::text::draw_text).
+    /// `quadrant` is the one selected with
+    /// [`set_vram_quad`](crate::video_dma::blitter::BlitterGuard::set_vram_quad),
+    /// so the blitter coordinates come out right.
+    pub fn load(&self, sprite_mem: &mut SpriteMemGuard, quadrant: SpriteQuadrant, x: u8, y: u8) -> LoadedFont {
+        let bytes = sprite_mem.bytes();
+        let width = self.width as usize;
+        for (row, line) in self.pixels.chunks(width).enumerate() {
+            let start = (y as usize + row) * 128 + x as usize;
+            bytes[start..start + width].copy_from_slice(line);
+        }
+
+        LoadedFont {
+            x: quadrant.value_gx() + x,
+            y: quadrant.value_gy() + y,
+            glyph_width: self.glyph_width,
+            glyph_height: self.glyph_height,
+            columns: self.width / self.glyph_width,
+            glyphs: (self.width / self.glyph_width) as u16 * (self.height / self.glyph_height) as u16,
+        }
+    }
+}
+
+/// A grid of tile indices, row by row
+#[derive(Debug, Copy, Clone)]
+pub struct TileMap {
+    pub bank: u8,
+    pub width: u16,
+    pub height: u16,
+    pub tiles: &'static [u8],
+}
+
+impl TileMap {
+    /// The tile at column `x`, row `y`. The map's bank must be switched in.
+    pub fn tile(&self, x: u16, y: u16) -> u8 {
+        self.tiles[y as usize * self.width as usize + x as usize]
+    }
+}
+
+/// A map from the Tiled editor. Tile layers hold Tiled's tile ids, so 0 is an
+/// empty cell and tile n is the tileset's tile n - 1.
+#[derive(Debug, Copy, Clone)]
+pub struct TiledMap {
+    pub width: u16,
+    pub height: u16,
+    pub tile_width: u8,
+    pub tile_height: u8,
+    pub layers: &'static [TileLayer],
+    /// The layer named in `collision` (by default one called "collision")
+    pub collision: Option<CollisionMap>,
+    /// Every object from the map's object layers
+    pub objects: &'static [MapObject],
+}
+
+impl TiledMap {
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// The objects whose class (or type) is `class`
+    pub fn objects_of<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a MapObject> + 'a {
+        self.objects.iter().filter(move |object| object.class == class)
+    }
+}
+
+/// One tile layer of a [`TiledMap`], split into chunks of `rows_per_chunk`
+/// rows so each fits in a bank
+#[derive(Debug, Copy, Clone)]
+pub struct TileLayer {
+    pub name: &'static str,
+    pub width: u16,
+    pub height: u16,
+    pub rows_per_chunk: u16,
+    pub chunks: &'static [TileChunk],
+}
+
+impl TileLayer {
+    /// The chunk holding row `y`
+    pub fn chunk(&self, y: u16) -> &TileChunk {
+        &self.chunks[(y / self.rows_per_chunk) as usize]
+    }
+
+    /// The tiles of row `y`. The bank of [`chunk(y)`](Self::chunk) must be switched in.
+    pub fn row(&self, y: u16) -> &'static [u8] {
+        let start = (y % self.rows_per_chunk) as usize * self.width as usize;
+        &self.chunk(y).tiles[start..start + self.width as usize]
+    }
+}
+
+/// Whole rows of a [`TileLayer`], all in one bank
+#[derive(Debug, Copy, Clone)]
+pub struct TileChunk {
+    pub bank: u8,
+    pub tiles: &'static [u8],
+}
+
+/// Which tiles are solid: one bit per tile, most significant bit first, with
+/// each row padded to whole bytes
+#[derive(Debug, Copy, Clone)]
+pub struct CollisionMap {
+    pub bank: u8,
+    pub width: u16,
+    pub height: u16,
+    pub bits: &'static [u8],
+}
+
+impl CollisionMap {
+    /// Whether the tile at column `x`, row `y` is solid; outside the map
+    /// counts as solid. The map's bank must be switched in.
+    pub fn is_solid(&self, x: u16, y: u16) -> bool {
+        if x >= self.width || y >= self.height {
+            return true;
+        }
+        let stride = self.width.div_ceil(8) as usize;
+        self.bits[y as usize * stride + x as usize / 8] & (0x80 >> (x % 8)) != 0
+    }
+}
+
+/// An object placed on a [`TiledMap`], in pixels from the map's top left.
+/// Tile objects are moved up by their height, so `y` is always their top.
+#[derive(Debug, Copy, Clone)]
+pub struct MapObject {
+    pub name: &'static str,
+    pub class: &'static str,
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Unsigned 8-bit mono samples
+#[derive(Debug, Copy, Clone)]
+pub struct Sound {
+    pub bank: u8,
+    pub sample_rate: u16,
+    pub samples: &'static [u8],
+}
+
+/// Unsigned 8-bit mono samples from `include_wav!`, split into chunks that
+/// each fit in a bank, for playback through a sample firmware
+#[derive(Debug, Copy, Clone)]
+pub struct PcmSample {
+    pub sample_rate: u16,
+    /// Samples across all the chunks
+    pub len: u32,
+    pub chunks: &'static [SampleChunk],
+}
+
+/// Part of a [`PcmSample`], all in one bank
+#[derive(Debug, Copy, Clone)]
+pub struct SampleChunk {
+    pub bank: u8,
+    pub samples: &'static [u8],
+}
+
+/// A tile `gtrom slice` packed into sprite RAM: `x` and `y` are the blitter's
+/// GX/GY within `page`, so a tile in quadrant Two already has 128 added to `x`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpriteTile {
+    pub page: u8,
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+/// How an animation tag steps through its frames
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    Forward,
+    Reverse,
+    PingPong,
+    /// Ping-pong, starting from the last frame
+    PingPongReverse,
+}
+
+/// A named run of frames, `from` to `to` inclusive
+#[derive(Debug, Copy, Clone)]
+pub struct AnimationTag {
+    pub name: &'static str,
+    pub from: u16,
+    pub to: u16,
+    pub direction: Direction,
+}
+
+/// A named rectangle drawn on the sprite, like a hitbox or an attachment point
+#[derive(Debug, Copy, Clone)]
+pub struct SpriteSlice {
+    pub name: &'static str,
+    pub x: i16,
+    pub y: i16,
+    pub width: u8,
+    pub height: u8,
+}
+
+/// An Aseprite file from `include_aseprite!`: every frame with its layers
+/// flattened, one GameTank color byte per pixel
+#[derive(Debug, Copy, Clone)]
+pub struct AsepriteSprite {
+    pub width: u8,
+    pub height: u8,
+    pub frames: &'static [&'static [u8]],
+    /// How long each frame shows, in milliseconds
+    pub durations: &'static [u16],
+    pub tags: &'static [AnimationTag],
+    pub slices: &'static [SpriteSlice],
+}
+
+impl AsepriteSprite {
+    pub fn tag(&self, name: &str) -> Option<&AnimationTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&SpriteSlice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+}
+
+/// Declares `mod assets` with everything `gtrom` generated from `assets.toml`
+#[macro_export]
+macro_rules! include_assets {
+    () => {
+        pub mod assets {
+            include!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/assets/assets.rs"));
+        }
+    };
+}
@@ -0,0 +1,180 @@
+//! # 4-Operator FM Synthesizer
+//!
+//! This firmware provides 2 voices, each made of 4 sine **operators**. An
+//! operator either bends the phase of the one after it (a modulator) or is
+//! heard (a carrier), and the voice's [`Algorithm`] picks which. Modulators
+//! add overtones to the carriers: more modulator level, brighter sound.
+//!
+//! Each operator has:
+//! - **Level** - 0 (silent) to 63 (max)
+//! - **Ratio** - Its frequency as a multiple of the note, in halves
+//!
+//! ## Quick Start
+//!
+//! ```rust,ignore
+//! use rom::sdk::audio::{voices, Algorithm, MidiNote};
+//!
+//! let v = voices();
+//!
+//! // An electric piano-ish patch: a 1:1 carrier bent by a 1:1 modulator
+//! v[0].set_algorithm(Algorithm::Pairs);
+//! v[0].set_ratio(0, 2);  v[0].set_level(0, 40);  // modulator
+//! v[0].set_ratio(1, 2);  v[0].set_level(1, 63);  // carrier
+//! v[0].set_note(MidiNote::C4);
+//!
+//! // Stop a voice
+//! v[0].mute();
+//! ```
+//!
+//! Operators count from 0, the top of the chain.
+//!
+//! ## Algorithms
+//!
+//! | Algorithm | Routing                        |
+//! |-----------|--------------------------------|
+//! | `Stack`   | 0 → 1 → 2 → 3 → out            |
+//! | `Pairs`   | 0 → 1 → out, 2 → 3 → out       |
+//! | `Branch`  | 0 → 1 → 2 → out, 3 → out       |
+//! | `Additive`| 0, 1, 2, 3 → out               |
+//!
+//! The firmware scales carriers by how many there are, so switching
+//! algorithms doesn't change how loud a voice peaks.
+
+use gt_audio_abi::{cpu, fm_4op as abi};
+
+use crate::audio::pitch_table::{midi_inc, MidiNote};
+
+/// Base address for voice registers (CPU-side address, ACP RAM at 0x3000)
+pub const VOICE_BASE: usize = cpu(abi::VOICE_BASE);
+/// Number of bytes per voice
+pub const VOICE_SIZE: usize = abi::VOICE_SIZE as usize;
+/// Number of voices
+pub const VOICE_COUNT: usize = abi::VOICE_COUNT;
+/// Operators per voice
+pub const OPERATOR_COUNT: usize = abi::OPERATOR_COUNT;
+
+/// How a voice's operators connect; see [Algorithms](self#algorithms)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Algorithm {
+    Stack = 0,
+    Pairs = 1,
+    Branch = 2,
+    Additive = 3,
+}
+
+/// One operator, laid out to match the firmware
+#[repr(C, packed)]
+struct Operator {
+    /// Phase accumulator (high byte indexes the sine)
+    phase: u16,
+    /// Frequency increment added to phase each sample
+    frequency: u16,
+    /// 0 is loudest; every 32 halves the amplitude
+    attenuation: u8,
+    /// Frequency as a multiple of the note, in halves. Only the ROM reads it.
+    ratio: u8,
+}
+
+/// A single FM voice.
+///
+/// This struct is laid out to match the ACP firmware's memory layout exactly.
+/// All fields are little-endian as expected by the 6502.
+#[repr(C, packed)]
+pub struct Voice {
+    algorithm: u8,
+    operators: [Operator; OPERATOR_COUNT],
+}
+
+impl Voice {
+    /// Set how the operators connect.
+    #[inline]
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm as u8;
+    }
+
+    /// Set operator `op`'s frequency relative to the note, in halves: 1 is
+    /// an octave down, 2 the note itself, 3 a fifth above the octave, 4 the
+    /// octave. Takes effect at the next [`set_note`](Self::set_note).
+    #[inline]
+    pub fn set_ratio(&mut self, op: usize, ratio: u8) {
+        self.operators[op].ratio = ratio;
+    }
+
+    /// Set operator `op`'s level (0 = silence, 63 = maximum).
+    ///
+    /// On a modulator, this is how much it bends the next operator.
+    #[inline]
+    pub fn set_level(&mut self, op: usize, level: u8) {
+        self.operators[op].attenuation = match level {
+            0 => 0xFF,
+            level => (63 - level.min(63)) << 2,
+        };
+    }
+
+    /// Set every operator's frequency from a MIDI note number and its ratio.
+    #[inline]
+    pub fn set_note(&mut self, note: MidiNote) {
+        self.set_frequency(midi_inc(note));
+    }
+
+    /// Set every operator's frequency from the note's increment and its ratio.
+    ///
+    /// Use `pitch_table::midi_inc()` to convert from MIDI notes,
+    /// or calculate directly: `inc = (freq_hz * 65536) / SAMPLE_RATE`
+    pub fn set_frequency(&mut self, freq_inc: u16) {
+        let half = freq_inc >> 1;
+        for op in &mut self.operators {
+            op.frequency = half.wrapping_mul(op.ratio as u16);
+        }
+    }
+
+    /// Set operator `op`'s frequency increment directly, ignoring its ratio.
+    #[inline]
+    pub fn set_operator_frequency(&mut self, op: usize, freq_inc: u16) {
+        self.operators[op].frequency = freq_inc;
+    }
+
+    /// Silence this voice immediately.
+    pub fn mute(&mut self) {
+        for op in &mut self.operators {
+            op.attenuation = 0xFF;
+        }
+    }
+
+    /// Reset every operator's phase to zero, so a note starts the same way
+    /// each time.
+    pub fn reset_phase(&mut self) {
+        for op in &mut self.operators {
+            op.phase = 0;
+        }
+    }
+}
+
+/// Get a mutable reference to both voices.
+///
+/// # Safety
+/// This function creates a mutable reference to memory-mapped hardware.
+/// The caller must ensure exclusive access to the voice registers.
+#[inline]
+pub fn voices() -> &'static mut [Voice; VOICE_COUNT] {
+    unsafe { &mut *(VOICE_BASE as *mut [Voice; VOICE_COUNT]) }
+}
+
+/// Get a mutable reference to a single voice by index (0-1).
+///
+/// # Panics
+/// Panics if `index >= 2`.
+#[inline]
+pub fn voice(index: usize) -> &'static mut Voice {
+    assert!(index < VOICE_COUNT, "voice index out of range");
+    unsafe { &mut *((VOICE_BASE + index * VOICE_SIZE) as *mut Voice) }
+}
+
+/// Silence all voices.
+#[inline]
+pub fn mute_all() {
+    for voice in voices().iter_mut() {
+        voice.mute();
+    }
+}
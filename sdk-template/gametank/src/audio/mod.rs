@@ -39,14 +39,39 @@
 //! v[0].mute();
 //! ```
 //!
+//! ## Songs
+//!
+//! [`music`] plays songs exported from the gtgo tracker, a frame at a time:
+//!
+//! ```rust,ignore
+//! music.play(SONG);
+//! // once a frame
+//! music.update(Some(&mut sfx));
+//! ```
+//!
+//! ## Sound Effects
+//!
+//! [`sfx`] plays short scripted effects on the top voices, taking them over
+//! from the music until they're done:
+//!
+//! ```rust,ignore
+//! sfx.play(&COIN);
+//! // once a frame
+//! sfx.update();
+//! ```
+//!
 //! ## Custom Wavetables
 //!
-//! You can load custom 256-byte waveforms into the wavetable slots:
+//! You can load custom 256-byte waveforms into the wavetable slots. The
+//! `wavetable_sine!`, `wavetable_saw!`, `wavetable_pulse!` and
+//! `wavetable_from_samples!` macros in `gametank-asset-macros` make them at
+//! compile time:
 //!
 //! ```rust,ignore
-//! // Wavetables live at $3400-$3BFF in audio RAM (8 × 256 bytes)
-//! let waveform: [u8; 256] = make_sine_wave();
-//! console.audio[0x400..0x500].copy_from_slice(&waveform);
+//! static ORGAN: [u8; 256] = wavetable_from_samples!("assets/organ.wav");
+//!
+//! // With audio-wavetable-8ch, slots 1-10 live at $3400-$3DFF in audio RAM
+//! console.audio.aram[0x400..0x500].copy_from_slice(&ORGAN);
 //! ```
 //!
 //! ## Audio Firmware
@@ -54,6 +79,10 @@
 //! Enable a firmware via Cargo features:
 //! - `audio-wavetable-8ch` - 8-channel wavetable synth (default, recommended)
 //! - `audio-wavetable-7ch-linear` - 7-channel wavetable synth with linear volume (16 levels)
+//! - `audio-fm-4op` - 2-voice, 4-operator FM synth (see [`fm_4op`]). Its binary
+//!   isn't prebuilt; make it with `gtrom audio build gametank/audiofw-src/fm-4op`.
+//! - `audio-pcm` - streams 8-bit samples from ROM (see [`pcm`]). Also built
+//!   with `gtrom audio build gametank/audiofw-src/pcm`.
 //!
 //! The firmware runs on the Audio Coprocessor at ~14kHz sample rate,
 //! with about 660 CPU cycles available per sample for synthesis.
@@ -65,6 +94,12 @@ pub static FIRMWARE: &[u8; 4096] = include_bytes!("../../audiofw/wavetable-8ch.b
 #[cfg(feature = "audio-wavetable-7ch-linear")]
 pub static FIRMWARE: &[u8; 4096] = include_bytes!("../../audiofw/wavetable-7ch-linear.bin");
 
+#[cfg(feature = "audio-fm-4op")]
+pub static FIRMWARE: &[u8; 4096] = include_bytes!("../../audiofw/fm-4op.bin");
+
+#[cfg(feature = "audio-pcm")]
+pub static FIRMWARE: &[u8; 4096] = include_bytes!("../../audiofw/pcm.bin");
+
 // Audio interface modules - selected via Cargo.toml features
 #[cfg(feature = "audio-wavetable-8ch")]
 pub mod wavetable_8ch;
@@ -76,7 +111,20 @@ pub mod wavetable_7ch_linear;
 #[cfg(feature = "audio-wavetable-7ch-linear")]
 pub use wavetable_7ch_linear::*;
 
+#[cfg(feature = "audio-fm-4op")]
+pub mod fm_4op;
+#[cfg(feature = "audio-fm-4op")]
+pub use fm_4op::*;
+
+#[cfg(feature = "audio-pcm")]
+pub mod pcm;
+
 // Shared
 pub mod pitch_table;
 pub use pitch_table::MidiNote;
 
+#[cfg(any(feature = "audio-wavetable-8ch", feature = "audio-wavetable-7ch-linear"))]
+pub mod sfx;
+#[cfg(any(feature = "audio-wavetable-8ch", feature = "audio-wavetable-7ch-linear"))]
+pub mod music;
+
@@ -0,0 +1,433 @@
+//! # Music
+//!
+//! A [`Music`] player steps through a song once a frame and writes the
+//! voices. Songs come from the gtgo tracker's export, as a compact binary
+//! that's read straight out of ROM:
+//!
+//! ```rust,ignore
+//! static SONG: &[u8] = include_bytes!("../assets/title.gtsong");
+//!
+//! let mut music = Music::new();
+//! music.play(SONG);
+//!
+//! loop {
+//!     clock.wait();
+//!     sfx.update();
+//!     music.update(Some(&mut sfx));
+//! }
+//! ```
+//!
+//! Passing the [`SfxPlayer`] keeps the music off the voices an effect has
+//! borrowed; what it would have played there is picked up when the effect
+//! is done. The song has to stay readable while it plays, so keep it in the
+//! fixed bank, or switch its bank in around [`update`](Music::update).
+//!
+//! gtgo's Export > Rust module writes the song with a `.rs` that includes
+//! it as a static in the bank picked in that menu, ready to `mod` in. The
+//! module's wavetables come along as a second static, for wavetable slots
+//! 1-8 in audio RAM:
+//!
+//! ```rust,ignore
+//! mod title;
+//!
+//! console.set_rom_bank(12);
+//! console.audio.aram[0x400..0xC00].copy_from_slice(&title::TITLE_WAVETABLES);
+//! music.play(&title::TITLE);
+//! ```
+//!
+//! ## Song Format
+//!
+//! ```text
+//! "GTS" version:u8  bpm:u8  channels:u8
+//! order_len:u8  order:[u8; order_len]       patterns to play, in order
+//! pattern_count:u8  offsets:[u16; pattern_count]   from the start of the song
+//! ```
+//!
+//! A pattern is a stream of commands. Each row's commands end with a wait
+//! for one or more rows, and the pattern with [`END`](cmd::END), at which
+//! point the next pattern in the order plays (looping back to the start
+//! after the last). See [`cmd`] for the commands; multi-byte parameters are
+//! little-endian.
+
+use crate::audio::{pitch_table::MIDI_INCREMENTS, sfx::SfxPlayer, voice, VOICE_COUNT};
+
+/// Song format version this player reads, along with the ones before it
+pub const VERSION: u8 = 2;
+
+/// Pattern stream commands
+pub mod cmd {
+    /// `0x00-0x7F`: play this MIDI note on the current channel
+    pub const NOTE_MAX: u8 = 0x7F;
+    /// `0x80-0x87`: commands after this are for channel `n - 0x80`
+    pub const CHANNEL: u8 = 0x80;
+    /// `volume:u8`
+    pub const VOLUME: u8 = 0x90;
+    /// `address:u16`, ACP-side
+    pub const WAVETABLE: u8 = 0x91;
+    /// `phase:u16`
+    pub const PHASE: u8 = 0x92;
+    /// `depth:u8 speed:u8`, depth 0 stops it
+    pub const VIBRATO: u8 = 0x93;
+    /// `depth:u8 speed:u8`, depth 0 stops it
+    pub const TREMOLO: u8 = 0x94;
+    /// `frames:u8 delta:i16` added to the frequency each frame, 0 frames
+    /// for until it's stopped
+    pub const SLIDE_PITCH: u8 = 0x95;
+    /// `frames:u8 delta:i8` added to the volume each frame
+    pub const SLIDE_VOLUME: u8 = 0x96;
+    pub const STOP_PITCH_SLIDE: u8 = 0x97;
+    pub const STOP_VOLUME_SLIDE: u8 = 0x98;
+    /// Silence the current channel
+    pub const NOTE_OFF: u8 = 0x99;
+    /// `bpm:u8`
+    pub const TEMPO: u8 = 0xA0;
+    /// `order:u8`: play this entry of the order next, from its first row
+    pub const JUMP: u8 = 0xA1;
+    /// `row:u8`: start the next pattern at this row
+    pub const BREAK_TO: u8 = 0xA2;
+    /// Go on to the next pattern in the order now
+    pub const ADVANCE: u8 = 0xA3;
+    /// Stop the song
+    pub const STOP: u8 = 0xA4;
+    /// `amount:u8`: make even rows longer and odd rows shorter by this many
+    /// 256ths of a row, for swing; 0 plays them straight. Since version 2.
+    pub const SWING: u8 = 0xA5;
+    /// `rows:u8`: the end of a row, and how many rows until the next commands
+    pub const WAIT: u8 = 0xF0;
+    /// The end of the pattern
+    pub const END: u8 = 0xFF;
+}
+
+/// A low frequency oscillator for vibrato and tremolo: a triangle between
+/// `-depth` and `depth`
+#[derive(Debug, Copy, Clone, Default)]
+struct Lfo {
+    depth: u8,
+    speed: u8,
+    phase: u8,
+}
+
+impl Lfo {
+    fn step(&mut self) -> i16 {
+        if self.depth == 0 {
+            return 0;
+        }
+        self.phase = self.phase.wrapping_add(self.speed);
+        // 0..=127 and back down, centered on 0
+        let triangle = if self.phase < 128 { self.phase as i16 } else { 255 - self.phase as i16 } - 64;
+        triangle * self.depth as i16 / 64
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Channel {
+    frequency: u16,
+    volume: u8,
+    wavetable: u16,
+    vibrato: Lfo,
+    tremolo: Lfo,
+    pitch_slide: i16,
+    pitch_slide_frames: u8,
+    volume_slide: i8,
+    volume_slide_frames: u8,
+    /// Set the voice's phase on the next write
+    phase: Option<u16>,
+}
+
+impl Channel {
+    /// Run this frame's effects, returning what the voice should play
+    fn step(&mut self) -> (u16, u8) {
+        if self.pitch_slide != 0 {
+            self.frequency = self.frequency.wrapping_add_signed(self.pitch_slide);
+            if self.pitch_slide_frames > 0 {
+                self.pitch_slide_frames -= 1;
+                if self.pitch_slide_frames == 0 {
+                    self.pitch_slide = 0;
+                }
+            }
+        }
+        if self.volume_slide != 0 {
+            self.volume = self.volume.saturating_add_signed(self.volume_slide);
+            if self.volume_slide_frames > 0 {
+                self.volume_slide_frames -= 1;
+                if self.volume_slide_frames == 0 {
+                    self.volume_slide = 0;
+                }
+            }
+        }
+
+        let vibrato = self.vibrato.step();
+        let frequency = self.frequency.wrapping_add_signed(vibrato * (self.frequency >> 8) as i16 / 8);
+        let volume = (self.volume as i16 + self.tremolo.step()).max(0) as u8;
+        (frequency, if self.frequency == 0 { 0 } else { volume })
+    }
+}
+
+/// Plays a song; see the [module docs](self)
+pub struct Music {
+    song: &'static [u8],
+    playing: bool,
+    channels: [Channel; VOICE_COUNT],
+    /// Where the order list starts and how long it is
+    order_at: u16,
+    order_len: u8,
+    order: u8,
+    /// Next command to read in the current pattern
+    at: u16,
+    /// Rows left until the next commands
+    wait_rows: u8,
+    /// Frames left in this row
+    frames_left: u8,
+    frames_per_row: u8,
+    /// 256ths of a row the even rows borrow from the odd ones
+    swing: u8,
+    /// Row of the pattern playing, to know which way it swings
+    row: u8,
+    /// The row to start the next pattern at, from `BREAK_TO`
+    break_to: u8,
+    current: u8,
+}
+
+impl Music {
+    pub const fn new() -> Self {
+        const SILENT: Channel = Channel {
+            frequency: 0,
+            volume: 0,
+            wavetable: 0,
+            vibrato: Lfo { depth: 0, speed: 0, phase: 0 },
+            tremolo: Lfo { depth: 0, speed: 0, phase: 0 },
+            pitch_slide: 0,
+            pitch_slide_frames: 0,
+            volume_slide: 0,
+            volume_slide_frames: 0,
+            phase: None,
+        };
+        Self {
+            song: &[],
+            playing: false,
+            channels: [SILENT; VOICE_COUNT],
+            order_at: 0,
+            order_len: 0,
+            order: 0,
+            at: 0,
+            wait_rows: 0,
+            frames_left: 0,
+            frames_per_row: 1,
+            swing: 0,
+            row: 0,
+            break_to: 0,
+            current: 0,
+        }
+    }
+
+    /// Start `song` from the top. Returns false, and plays nothing, if it
+    /// isn't a song this player can read.
+    pub fn play(&mut self, song: &'static [u8]) -> bool {
+        let ok = song.len() > 7 && &song[..3] == b"GTS" && (1..=VERSION).contains(&song[3]);
+        if !ok {
+            self.playing = false;
+            return false;
+        }
+        *self = Self::new();
+        self.song = song;
+        self.set_tempo(song[4]);
+        self.order_at = 7;
+        self.order_len = song[6];
+        self.playing = self.order_len > 0 && self.start_pattern(0, 0);
+        self.playing
+    }
+
+    /// Stop and silence every voice the song was using
+    pub fn stop(&mut self, sfx: Option<&mut SfxPlayer>) {
+        self.playing = false;
+        for channel in &mut self.channels {
+            channel.frequency = 0;
+        }
+        self.write_voices(sfx);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Which entry of the order is playing
+    pub fn position(&self) -> u8 {
+        self.order
+    }
+
+    fn set_tempo(&mut self, bpm: u8) {
+        // 3600 frames a minute at 60Hz, a row per beat
+        self.frames_per_row = (3600 / bpm.max(15) as u16) as u8;
+    }
+
+    /// How long the row starting now lasts: with swing, even rows go long
+    /// and odd rows short
+    fn row_frames(&self) -> u8 {
+        let swing = ((self.frames_per_row as u16 * self.swing as u16) >> 8) as u8;
+        if self.row & 1 == 0 {
+            self.frames_per_row.saturating_add(swing)
+        } else {
+            self.frames_per_row.saturating_sub(swing).max(1)
+        }
+    }
+
+    fn byte(&self, at: u16) -> u8 {
+        self.song.get(at as usize).copied().unwrap_or(cmd::END)
+    }
+
+    fn word(&self, at: u16) -> u16 {
+        u16::from_le_bytes([self.byte(at), self.byte(at + 1)])
+    }
+
+    /// Start entry `order` of the order list at row `row`, skipping the
+    /// commands before it
+    fn start_pattern(&mut self, order: u8, row: u8) -> bool {
+        let order = if order >= self.order_len { 0 } else { order };
+        let pattern = self.byte(self.order_at + order as u16);
+        let patterns_at = self.order_at + self.order_len as u16;
+        if pattern >= self.byte(patterns_at) {
+            return false;
+        }
+        self.order = order;
+        self.at = self.word(patterns_at + 1 + pattern as u16 * 2);
+        self.wait_rows = 0;
+        self.frames_left = 0;
+        self.row = row;
+
+        // fast-forward, keeping only the waits
+        let mut rows = row;
+        while rows > 0 {
+            match self.byte(self.at) {
+                cmd::WAIT => {
+                    let wait = self.byte(self.at + 1);
+                    self.at += 2;
+                    if wait > rows {
+                        self.wait_rows = wait - rows;
+                        break;
+                    }
+                    rows -= wait;
+                }
+                cmd::END => break,
+                command => self.at += 1 + Self::params(command),
+            }
+        }
+        true
+    }
+
+    /// How many parameter bytes `command` takes
+    fn params(command: u8) -> u16 {
+        match command {
+            cmd::VOLUME | cmd::TEMPO | cmd::JUMP | cmd::BREAK_TO | cmd::SWING | cmd::WAIT => 1,
+            cmd::WAVETABLE | cmd::PHASE | cmd::VIBRATO | cmd::TREMOLO | cmd::SLIDE_VOLUME => 2,
+            cmd::SLIDE_PITCH => 3,
+            _ => 0,
+        }
+    }
+
+    /// Run the commands for the next row
+    fn read_row(&mut self) {
+        // plenty for any real row; stops a broken song looping forever
+        for _ in 0..255 {
+            let command = self.byte(self.at);
+            let [a, b, c] = [1, 2, 3].map(|i| self.byte(self.at + i));
+            self.at += 1 + Self::params(command);
+
+            let channel = &mut self.channels[self.current as usize];
+            match command {
+                0..=cmd::NOTE_MAX => {
+                    channel.frequency = MIDI_INCREMENTS[command as usize];
+                    channel.pitch_slide = 0;
+                }
+                0x80..=0x87 => self.current = (command - cmd::CHANNEL).min(VOICE_COUNT as u8 - 1),
+                cmd::VOLUME => channel.volume = a,
+                cmd::WAVETABLE => channel.wavetable = u16::from_le_bytes([a, b]),
+                cmd::PHASE => channel.phase = Some(u16::from_le_bytes([a, b])),
+                cmd::VIBRATO => channel.vibrato = Lfo { depth: a, speed: b, phase: 0 },
+                cmd::TREMOLO => channel.tremolo = Lfo { depth: a, speed: b, phase: 0 },
+                cmd::SLIDE_PITCH => {
+                    channel.pitch_slide_frames = a;
+                    channel.pitch_slide = i16::from_le_bytes([b, c]);
+                }
+                cmd::SLIDE_VOLUME => {
+                    channel.volume_slide_frames = a;
+                    channel.volume_slide = b as i8;
+                }
+                cmd::STOP_PITCH_SLIDE => channel.pitch_slide = 0,
+                cmd::STOP_VOLUME_SLIDE => channel.volume_slide = 0,
+                cmd::NOTE_OFF => channel.frequency = 0,
+                cmd::TEMPO => self.set_tempo(a),
+                cmd::BREAK_TO => self.break_to = a,
+                cmd::SWING => self.swing = a,
+                cmd::WAIT => {
+                    self.wait_rows = a.max(1);
+                    return;
+                }
+                cmd::STOP => {
+                    self.playing = false;
+                    for channel in &mut self.channels {
+                        channel.frequency = 0;
+                    }
+                    return;
+                }
+                cmd::JUMP | cmd::ADVANCE | cmd::END => {
+                    let (order, row) = match command {
+                        cmd::JUMP => (a, 0),
+                        _ => (self.order + 1, core::mem::take(&mut self.break_to)),
+                    };
+                    self.playing = self.start_pattern(order, row);
+                    // carry on with the new pattern's row, unless that's
+                    // partway through a wait
+                    if !self.playing || self.wait_rows > 0 {
+                        return;
+                    }
+                }
+                // unknown: skip it
+                _ => {}
+            }
+        }
+    }
+
+    /// Move the song along a frame and write the voices. Call once a frame.
+    pub fn update(&mut self, sfx: Option<&mut SfxPlayer>) {
+        if !self.playing {
+            return;
+        }
+
+        if self.frames_left == 0 {
+            if self.wait_rows == 0 {
+                self.read_row();
+            }
+            self.wait_rows = self.wait_rows.saturating_sub(1);
+            self.frames_left = self.row_frames();
+            self.row = self.row.wrapping_add(1);
+        }
+        self.frames_left -= 1;
+
+        self.write_voices(sfx);
+    }
+
+    fn write_voices(&mut self, mut sfx: Option<&mut SfxPlayer>) {
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let (frequency, volume) = channel.step();
+            if sfx.as_deref_mut().is_some_and(|sfx| sfx.write_under(index, frequency, channel.wavetable, volume)) {
+                continue;
+            }
+
+            let voice = voice(index);
+            voice.set_frequency(frequency);
+            voice.set_volume(volume);
+            if channel.wavetable != 0 {
+                voice.set_wavetable(channel.wavetable);
+            }
+            if let Some(phase) = channel.phase.take() {
+                voice.set_phase(phase);
+            }
+        }
+    }
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,165 @@
+//! # PCM Sample Streaming
+//!
+//! The `audio-pcm` firmware plays unsigned 8-bit samples, one per ACP
+//! interrupt, out of two 1KB buffers in audio RAM. While it plays one, the
+//! main CPU fills the other, and an NMI tells the firmware it's ready. A
+//! [`PcmPlayer`] does the filling from a [`PcmSample`], reading across
+//! however many ROM banks the sample spans:
+//!
+//! ```rust,ignore
+//! use gametank::audio::pcm::PcmPlayer;
+//!
+//! static VOICE: PcmSample = include_wav!("assets/voice.wav", bank = 40);
+//!
+//! let mut pcm = PcmPlayer::new();
+//! pcm.play(console, &VOICE);
+//!
+//! loop {
+//!     clock.wait();
+//!     pcm.update(console);
+//! }
+//! ```
+//!
+//! A buffer lasts a little over 4 frames at ~14kHz, so calling
+//! [`update`](PcmPlayer::update) once a frame keeps well ahead of the
+//! firmware. If it falls behind, the output holds its last level until the
+//! next buffer arrives.
+//!
+//! Samples play at the ACP's interrupt rate, so convert them to that;
+//! `include_wav!`'s default of 14000Hz matches the usual setup.
+//!
+//! [`update`](PcmPlayer::update) switches ROM banks to read the sample, and
+//! leaves whichever bank it read last mapped in, so call it from code in the
+//! fixed bank.
+
+use gt_audio_abi::{cpu, pcm as abi, pcm::cmd, ARAM_CPU_BASE};
+
+use crate::{assets::PcmSample, console::Console};
+
+/// Stream state (CPU-side addresses, ACP RAM at 0x3000)
+pub const FILLED_A: usize = cpu(abi::FILLED_A);
+pub const FILLED_B: usize = cpu(abi::FILLED_B);
+/// Written before poking the ACP's NMI; the firmware clears it when done
+pub const COMMAND: usize = cpu(abi::COMMAND);
+/// Non-zero until the firmware has rewound after a stop
+pub const RESTART: usize = cpu(abi::RESTART);
+
+/// Sample buffers (CPU-side), played A then B then A again
+pub const BUFFER_A: usize = cpu(abi::BUFFER_A);
+pub const BUFFER_B: usize = cpu(abi::BUFFER_B);
+/// Size of each buffer in bytes
+pub const BUFFER_SIZE: usize = abi::BUFFER_SIZE as usize;
+
+/// What the DAC sits at when there's nothing to play
+const SILENCE: u8 = 0x80;
+
+#[inline(always)]
+fn read(addr: usize) -> u8 {
+    unsafe { (addr as *const u8).read_volatile() }
+}
+
+#[inline(always)]
+fn write(addr: usize, value: u8) {
+    unsafe { (addr as *mut u8).write_volatile(value) }
+}
+
+/// Hand `command` to the firmware
+fn send(console: &mut Console, command: u8) {
+    write(COMMAND, command);
+    unsafe { core::ptr::write_volatile(console.audio.audio_nmi, 0) };
+}
+
+/// Streams a [`PcmSample`] to the `audio-pcm` firmware; see the
+/// [module docs](self)
+pub struct PcmPlayer {
+    /// What's left to send, or None once it's all in the buffers
+    sample: Option<&'static PcmSample>,
+    chunk: u8,
+    offset: u16,
+    /// Buffer to fill next: 0 = A, 1 = B
+    next: u8,
+    /// Waiting for the firmware to rewind after a stop
+    restarting: bool,
+}
+
+impl PcmPlayer {
+    pub const fn new() -> Self {
+        Self { sample: None, chunk: 0, offset: 0, next: 0, restarting: false }
+    }
+
+    /// Stop whatever's playing and start `sample` from the beginning. It
+    /// starts at the next [`update`](Self::update).
+    pub fn play(&mut self, console: &mut Console, sample: &'static PcmSample) {
+        self.stop(console);
+        self.sample = (!sample.chunks.is_empty()).then_some(sample);
+    }
+
+    /// Stop playing, dropping anything already in the buffers.
+    pub fn stop(&mut self, console: &mut Console) {
+        send(console, cmd::STOP);
+        *self = Self { restarting: true, ..Self::new() };
+    }
+
+    /// Whether there's any of the sample left to hear
+    pub fn is_playing(&self) -> bool {
+        self.sample.is_some() || read(FILLED_A) != 0 || read(FILLED_B) != 0
+    }
+
+    /// Fill whichever buffers the firmware has finished with. Call once a
+    /// frame.
+    pub fn update(&mut self, console: &mut Console) {
+        if self.restarting {
+            if read(RESTART) != 0 {
+                return;
+            }
+            self.restarting = false;
+        }
+        if read(COMMAND) != 0 {
+            return;
+        }
+
+        let mut command = 0;
+        for _ in 0..2 {
+            let (filled, buffer, bit) = match self.next {
+                0 => (FILLED_A, BUFFER_A, cmd::FILLED_A),
+                _ => (FILLED_B, BUFFER_B, cmd::FILLED_B),
+            };
+            if self.sample.is_none() || read(filled) != 0 {
+                break;
+            }
+            self.fill(console, buffer);
+            command |= bit;
+            self.next ^= 1;
+        }
+        if command != 0 {
+            send(console, command);
+        }
+    }
+
+    /// Copy the next buffer's worth of the sample into `buffer`, padding
+    /// with silence past its end
+    fn fill(&mut self, console: &mut Console, buffer: usize) {
+        let start = buffer - ARAM_CPU_BASE as usize;
+        let mut at = 0;
+        while at < BUFFER_SIZE {
+            let Some(sample) = self.sample else { break };
+            let chunk = &sample.chunks[self.chunk as usize];
+            console.set_rom_bank(chunk.bank);
+
+            let rest = &chunk.samples[self.offset as usize..];
+            let len = rest.len().min(BUFFER_SIZE - at);
+            console.audio.aram[start + at..start + at + len].copy_from_slice(&rest[..len]);
+            at += len;
+            self.offset += len as u16;
+
+            if self.offset as usize == chunk.samples.len() {
+                self.chunk += 1;
+                self.offset = 0;
+                if self.chunk as usize == sample.chunks.len() {
+                    self.sample = None;
+                }
+            }
+        }
+        console.audio.aram[start + at..start + BUFFER_SIZE].fill(SILENCE);
+    }
+}
@@ -0,0 +1,209 @@
+//! # Sound Effects
+//!
+//! A sound effect is a short script of steps, each a pitch and volume held
+//! for some frames. An [`SfxPlayer`] plays them on the top one or two
+//! voices, taking them over from the music while an effect plays and
+//! putting the music's note back when it's done:
+//!
+//! ```rust,ignore
+//! use gametank::audio::{sfx::{Sfx, SfxPlayer, Step}, MidiNote, WAVETABLE};
+//!
+//! static JUMP: Sfx = Sfx {
+//!     wavetable: WAVETABLE[1],
+//!     priority: 1,
+//!     steps: &[
+//!         Step::note(MidiNote::C5, 50, 2),
+//!         Step::note(MidiNote::E5, 40, 2),
+//!         Step::note(MidiNote::G5, 30, 3),
+//!     ],
+//! };
+//!
+//! let mut sfx = SfxPlayer::new(2);
+//!
+//! loop {
+//!     clock.wait();
+//!     if jumped {
+//!         sfx.play(&JUMP);
+//!     }
+//!     sfx.update();
+//! }
+//! ```
+//!
+//! With every sfx voice busy, a new effect takes over the one playing the
+//! least important effect, if that's no more important than the new one.
+//! Otherwise it's dropped. Volumes are whatever the firmware's
+//! [`Voice::set_volume`] takes.
+
+use crate::audio::{pitch_table::midi_inc, voice, MidiNote, Voice, VOICE_COUNT};
+
+/// The most voices an [`SfxPlayer`] will take
+pub const MAX_SFX_VOICES: usize = 2;
+
+/// One step of a sound effect
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Step {
+    /// Frequency increment, as for [`Voice::set_frequency`]; 0 is a rest
+    pub frequency: u16,
+    pub volume: u8,
+    /// How long the step lasts, in calls to [`SfxPlayer::update`]
+    pub frames: u8,
+}
+
+impl Step {
+    pub const fn note(note: MidiNote, volume: u8, frames: u8) -> Self {
+        Self { frequency: midi_inc(note), volume, frames }
+    }
+
+    pub const fn rest(frames: u8) -> Self {
+        Self { frequency: 0, volume: 0, frames }
+    }
+}
+
+/// A sound effect; see the [module docs](self)
+#[derive(Debug)]
+pub struct Sfx {
+    /// ACP-side wavetable address, from `WAVETABLE`
+    pub wavetable: u16,
+    /// Higher cuts off lower when there's no free voice
+    pub priority: u8,
+    pub steps: &'static [Step],
+}
+
+/// What a voice was doing before an effect took it over
+#[derive(Copy, Clone)]
+struct Saved {
+    frequency: u16,
+    wavetable: u16,
+    volume: u8,
+}
+
+impl Saved {
+    fn take(voice: &Voice) -> Self {
+        Self { frequency: voice.frequency(), wavetable: voice.wavetable(), volume: voice.get_volume() }
+    }
+
+    fn restore(&self, voice: &mut Voice) {
+        voice.set_frequency(self.frequency);
+        voice.set_wavetable(self.wavetable);
+        voice.set_volume(self.volume);
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Playing {
+    sfx: &'static Sfx,
+    step: u8,
+    frames_left: u8,
+    saved: Saved,
+}
+
+/// Plays [`Sfx`] on the top voices; see the [module docs](self)
+pub struct SfxPlayer {
+    channels: [Option<Playing>; MAX_SFX_VOICES],
+    count: u8,
+}
+
+impl SfxPlayer {
+    /// A player using the top `voices` voices (1 or 2)
+    pub const fn new(voices: u8) -> Self {
+        let count = if voices == 0 { 1 } else if voices as usize > MAX_SFX_VOICES { MAX_SFX_VOICES as u8 } else { voices };
+        Self { channels: [None; MAX_SFX_VOICES], count }
+    }
+
+    /// The voice index channel `channel` plays on, counting down from the top
+    #[inline(always)]
+    const fn voice_index(channel: usize) -> usize {
+        VOICE_COUNT - 1 - channel
+    }
+
+    /// Start `sfx`, returning false if every voice is busy with something
+    /// more important
+    pub fn play(&mut self, sfx: &'static Sfx) -> bool {
+        let channels = &self.channels[..self.count as usize];
+        let free = channels.iter().position(|c| c.is_none());
+        let weakest = channels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.map(|c| (i, c.sfx.priority)))
+            .min_by_key(|&(_, priority)| priority)
+            .filter(|&(_, priority)| priority <= sfx.priority)
+            .map(|(i, _)| i);
+        let Some(channel) = free.or(weakest) else { return false };
+
+        let voice = voice(Self::voice_index(channel));
+        // stealing from another effect keeps the music that was under it
+        let saved = match self.channels[channel] {
+            Some(playing) => playing.saved,
+            None => Saved::take(voice),
+        };
+        voice.set_wavetable(sfx.wavetable);
+        self.channels[channel] = Some(Playing { sfx, step: 0, frames_left: 0, saved });
+        self.advance(channel);
+        true
+    }
+
+    /// Move every effect along a frame. Call once a frame.
+    pub fn update(&mut self) {
+        for channel in 0..self.count as usize {
+            if let Some(playing) = &mut self.channels[channel] {
+                playing.frames_left = playing.frames_left.saturating_sub(1);
+                if playing.frames_left == 0 {
+                    self.advance(channel);
+                }
+            }
+        }
+    }
+
+    /// Start the next step on `channel`, or give the voice back after the last
+    fn advance(&mut self, channel: usize) {
+        let Some(playing) = &mut self.channels[channel] else { return };
+        let voice = voice(Self::voice_index(channel));
+        match playing.sfx.steps.get(playing.step as usize) {
+            Some(step) => {
+                voice.set_frequency(step.frequency);
+                voice.set_volume(if step.frequency == 0 { 0 } else { step.volume });
+                playing.step += 1;
+                playing.frames_left = step.frames.max(1);
+            }
+            None => {
+                playing.saved.restore(voice);
+                self.channels[channel] = None;
+            }
+        }
+    }
+
+    /// Cut every effect short and give the voices back
+    pub fn stop_all(&mut self) {
+        for channel in 0..self.count as usize {
+            if let Some(playing) = self.channels[channel].take() {
+                playing.saved.restore(voice(Self::voice_index(channel)));
+            }
+        }
+    }
+
+    /// Whether an effect has voice `index` right now, so music should leave
+    /// it alone
+    pub fn is_stolen(&self, index: usize) -> bool {
+        (0..self.count as usize).any(|channel| Self::voice_index(channel) == index && self.channels[channel].is_some())
+    }
+
+    /// If an effect has voice `index`, remember this as what to put back
+    /// when it's done and return true. Music writes through this, so it
+    /// picks up where it should be rather than where it was.
+    pub fn write_under(&mut self, index: usize, frequency: u16, wavetable: u16, volume: u8) -> bool {
+        let channel = (VOICE_COUNT - 1).wrapping_sub(index);
+        match self.channels[..self.count as usize].get_mut(channel) {
+            Some(Some(playing)) => {
+                let wavetable = if wavetable == 0 { playing.saved.wavetable } else { wavetable };
+                playing.saved = Saved { frequency, wavetable, volume };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether any effect is playing
+    pub fn is_playing(&self) -> bool {
+        self.channels.iter().any(Option::is_some)
+    }
+}
@@ -15,6 +15,11 @@
 //! - Level 9-12: 87.5% table with shifts 3,2,1,0
 //! - Level 13-16: 100% table with shifts 3,2,1,0
 //!
+//! The levels are linear in amplitude, so the quiet end is much coarser to
+//! the ear than the loud end: level 1 is 22dB down, level 12 only 6dB.
+//! [`Voice::set_volume_db`] picks whichever level is closest to a volume in
+//! decibels, which makes for even-sounding fades.
+//!
 //! ## Quick Start
 //!
 //! ```rust,ignore
@@ -44,65 +49,79 @@
 //! v[1].set_wavetable(WAVETABLE[1]);  // Second waveform
 //! ```
 //!
-//! You can load custom waveforms into audio RAM:
+//! You can load custom waveforms into audio RAM, generated at compile time by
+//! the `wavetable_*!` macros in `gametank-asset-macros`. This firmware takes
+//! them at full amplitude:
 //!
 //! ```rust,ignore
+//! use gametank_asset_macros::wavetable_saw;
+//!
+//! static SAW: [u8; 256] = wavetable_saw!(amplitude = 127);
+//!
 //! // Wavetable 0 is at $3600, wavetable 1 at $3700, etc.
-//! let my_wave: [u8; 256] = generate_sine();
-//! console.audio[0x600..0x700].copy_from_slice(&my_wave);
+//! console.audio.aram[0x600..0x700].copy_from_slice(&SAW);
 //! ```
 
+use gt_audio_abi::{cpu, volume, wavetable_7ch_linear as abi};
+
 use crate::audio::pitch_table::{midi_inc, MidiNote};
 
 /// Base address for voice registers (CPU-side address, ACP RAM at 0x3000)
-pub const VOICE_BASE: usize = 0x3041;
+pub const VOICE_BASE: usize = cpu(abi::VOICE_BASE);
 /// Number of bytes per voice
-pub const VOICE_SIZE: usize = 9;
+pub const VOICE_SIZE: usize = abi::VOICE_SIZE as usize;
 /// Number of voices
-pub const VOICE_COUNT: usize = 7;
+pub const VOICE_COUNT: usize = abi::VOICE_COUNT;
 
 /// Base address for wavetables in ACP RAM (CPU-side)
-pub const WAVETABLE_BASE: usize = 0x3600;
+pub const WAVETABLE_BASE: usize = cpu(abi::WAVETABLE_BASE);
 /// Size of each wavetable in bytes
-pub const WAVETABLE_SIZE: usize = 256;
+pub const WAVETABLE_SIZE: usize = abi::WAVETABLE_SIZE as usize;
 /// Number of wavetables available
-pub const WAVETABLE_COUNT: usize = 6;
+pub const WAVETABLE_COUNT: usize = abi::WAVETABLE_COUNT;
 
 /// Wavetable slot addresses (ACP-side, for setting voice wavetable pointer)
-pub const WAVETABLE: [u16; WAVETABLE_COUNT] = [
-    0x0600, 0x0700, 0x0800, 0x0900, 0x0A00, 0x0B00,
-];
+pub const WAVETABLE: [u16; WAVETABLE_COUNT] = {
+    let mut slots = [0; WAVETABLE_COUNT];
+    let mut i = 0;
+    while i < WAVETABLE_COUNT {
+        slots[i] = abi::WAVETABLE_BASE + abi::WAVETABLE_SIZE * i as u16;
+        i += 1;
+    }
+    slots
+};
 
 /// Volume level mapping to table pointer + shift
 /// Each entry: (volume_table_ptr, shift_count)
-/// 16 linear levels sorted by shift (most impact) then table
-const VOLUME_MAP: [(u16, u8); 17] = [
-    (0x0500, 4), // 0: silence (shift >= 4 gives silence)
-    
-    // Shift 3 (divide by 8) - quietest audible levels
-    (0x0500, 3), // 1: table 3 (62.5%), shift 3
-    (0x0400, 3), // 2: table 2 (75%), shift 3
-    (0x0300, 3), // 3: table 1 (87.5%), shift 3
-    (0x0200, 3), // 4: table 0 (100%), shift 3
-    
-    // Shift 2 (divide by 4)
-    (0x0500, 2), // 5: table 3 (62.5%), shift 2
-    (0x0400, 2), // 6: table 2 (75%), shift 2
-    (0x0300, 2), // 7: table 1 (87.5%), shift 2
-    (0x0200, 2), // 8: table 0 (100%), shift 2
-    
-    // Shift 1 (divide by 2)
-    (0x0500, 1), // 9: table 3 (62.5%), shift 1
-    (0x0400, 1), // 10: table 2 (75%), shift 1
-    (0x0300, 1), // 11: table 1 (87.5%), shift 1
-    (0x0200, 1), // 12: table 0 (100%), shift 1
-    
-    // Shift 0 (no division) - loudest levels
-    (0x0500, 0), // 13: table 3 (62.5%), shift 0
-    (0x0400, 0), // 14: table 2 (75%), shift 0
-    (0x0300, 0), // 15: table 1 (87.5%), shift 0
-    (0x0200, 0), // 16: table 0 (100%), shift 0
-];
+/// 16 linear levels sorted by shift (most impact) then table, quietest first
+const VOLUME_MAP: [(u16, u8); 17] = {
+    // 0: silence (shift >= 4 gives silence)
+    let mut map = [(abi::VOLUME_TABLES[3], abi::SILENT_SHIFT); 17];
+    let mut level = 1;
+    while level <= 16 {
+        let shift = 3 - (level - 1) / 4;
+        let table = 3 - (level - 1) % 4;
+        map[level] = (abi::VOLUME_TABLES[table], shift as u8);
+        level += 1;
+    }
+    map
+};
+
+/// How loud each level in [`VOLUME_MAP`] is, 0.0-1.0
+const VOLUME_GAIN: [f64; 17] = {
+    let mut gains = [0.0; 17];
+    let mut level = 1;
+    while level <= 16 {
+        let shift = 3 - (level - 1) / 4;
+        let table = 3 - (level - 1) % 4;
+        gains[level] = abi::VOLUME_TABLE_GAIN[table] / (1 << shift) as f64;
+        level += 1;
+    }
+    gains
+};
+
+/// Volume level for each decibel down from full, starting at 0dB
+pub const VOLUME_DB: [u8; 40] = volume::levels(&VOLUME_GAIN, 1.0);
 
 /// A single synthesizer voice.
 ///
@@ -150,6 +169,15 @@ impl Voice {
         self.shift = shift;
     }
 
+    /// Set the volume in decibels from full (0 = maximum, -12 = about a
+    /// quarter as loud), to the nearest of the 16 levels. Anything above 0
+    /// is 0.
+    #[inline]
+    pub fn set_volume_db(&mut self, db: i8) {
+        let step = db.min(0).unsigned_abs() as usize;
+        self.set_volume(VOLUME_DB.get(step).copied().unwrap_or(0));
+    }
+
     /// Set which wavetable this voice uses.
     /// 
     /// Pass the ACP-side address (e.g., `WAVETABLE[0]` = 0x0600).
@@ -161,7 +189,7 @@ impl Voice {
     /// Silence this voice immediately.
     #[inline]
     pub fn mute(&mut self) {
-        self.shift = 4; // Shift >= 4 gives silence
+        self.shift = abi::SILENT_SHIFT; // Shift >= 4 gives silence
     }
 
     /// Reset the phase accumulator to zero (useful for hard sync effects).
@@ -170,6 +198,24 @@ impl Voice {
         self.phase = 0;
     }
 
+    /// The current frequency increment
+    #[inline]
+    pub fn frequency(&self) -> u16 {
+        self.frequency
+    }
+
+    /// The ACP-side address of the current wavetable
+    #[inline]
+    pub fn wavetable(&self) -> u16 {
+        self.wavetable
+    }
+
+    /// Set the phase accumulator, to start a note partway through its wave
+    #[inline]
+    pub fn set_phase(&mut self, phase: u16) {
+        self.phase = phase;
+    }
+
     /// Get the current volume level (0-16).
     #[inline]
     pub fn get_volume(&self) -> u8 {
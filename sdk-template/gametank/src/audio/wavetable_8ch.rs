@@ -4,6 +4,9 @@
 //! - **Note/Frequency** - MIDI notes or raw frequency values
 //! - **Volume** - 0 (silent) to 63 (max)
 //! - **Wavetable** - One of 8 waveform slots
+//! - **Envelope** - Optional attack/decay/sustain/release on the volume
+//!
+//! and the last voice can play noise instead of a wavetable.
 //!
 //! ## Quick Start
 //!
@@ -25,6 +28,69 @@
 //! v[0].mute();
 //! ```
 //!
+//! ## Volume in Decibels
+//!
+//! Volume steps aren't equally loud apart: 63 to 50 is barely a dip, 13 to
+//! 0 a cliff. [`Voice::set_volume_db`] takes decibels instead, so stepping
+//! it by a constant amount each frame makes a fade that sounds even:
+//!
+//! ```rust,ignore
+//! v[0].set_volume_db(-12);  // about a quarter as loud
+//! ```
+//!
+//! Quieter than [`VOLUME_DB`] reaches is silence.
+//!
+//! ## Envelopes
+//!
+//! Turning a voice's volume on and off sounds flat. With an envelope set,
+//! the firmware shapes the volume itself between [`Voice::note_on`] and
+//! [`Voice::note_off`]:
+//!
+//! ```rust,ignore
+//! // quick attack, fall to about half, fade out slowly after note off
+//! v[0].set_envelope(64, 4, 32, 2);
+//!
+//! v[0].note_on(MidiNote::C4);
+//! // ...
+//! v[0].note_off();
+//! ```
+//!
+//! The envelope steps about 220 times a second, on an 8-bit level that's 4
+//! to a volume step. Attack, decay and release are how much the level moves
+//! per step: 255 attacks at once, 1 takes over a second. The envelope
+//! overwrites the volume, so [`Voice::set_volume`] does nothing until
+//! [`Voice::clear_envelope`].
+//!
+//! ## Slides
+//!
+//! The firmware can glide a voice's pitch or volume on its own, so the ROM
+//! doesn't have to update them every frame:
+//!
+//! ```rust,ignore
+//! v[0].set_note(MidiNote::C4);
+//! v[0].slide_to(MidiNote::G4, 20);  // portamento over 20 frames
+//! v[1].slide_volume(0, 60);         // fade out over a second
+//! ```
+//!
+//! A slide moves in the same steps as envelopes and stops at its target.
+//! Setting the note or volume directly doesn't cancel one; it carries on
+//! from wherever it's put, so use [`Voice::stop_slides`] for that. A volume
+//! slide on a voice with an envelope loses to the envelope.
+//!
+//! ## Noise
+//!
+//! For drums and explosions, [`set_noise`] switches voice [`NOISE_VOICE`]
+//! from its wavetable to white noise. The noise changes value each time the
+//! voice's phase wraps, so its frequency still sets the pitch: high notes
+//! hiss, low notes rumble.
+//!
+//! ```rust,ignore
+//! set_noise(true);
+//! let drum = voice(NOISE_VOICE);
+//! drum.set_envelope(255, 16, 0, 8);
+//! drum.note_on(MidiNote::C7);
+//! ```
+//!
 //! ## Wavetables
 //!
 //! The firmware has 8 wavetable slots. Use [`WAVETABLE`] to get slot addresses:
@@ -34,36 +100,69 @@
 //! v[1].set_wavetable(WAVETABLE[1]);  // Second waveform
 //! ```
 //!
-//! You can load custom waveforms (256 bytes each) into audio RAM:
+//! You can load custom waveforms (256 bytes each) into audio RAM, generated
+//! at compile time by the `wavetable_*!` macros in `gametank-asset-macros`:
 //!
 //! ```rust,ignore
-//! // Wavetable 0 is at $3400, wavetable 1 at $3500, etc.
-//! let my_wave: [u8; 256] = generate_sine();
-//! console.audio[0x400..0x500].copy_from_slice(&my_wave);
+//! use gametank_asset_macros::wavetable_pulse;
+//!
+//! static SQUARE: [u8; 256] = wavetable_pulse!(50);
+//!
+//! // Slot 0 holds the built-in sine; slot 1 is at $3400, slot 2 at $3500, etc.
+//! console.audio.aram[0x400..0x500].copy_from_slice(&SQUARE);
+//! v[0].set_wavetable(WAVETABLE[1]);
 //! ```
+//!
+//! Keep their amplitude at the default of 64: louder waves overflow this
+//! firmware's volume table.
+
+use gt_audio_abi::{cpu, volume, wavetable_8ch::{self as abi, stage}};
 
 use crate::audio::pitch_table::{midi_inc, MidiNote};
 
 /// Base address for voice registers (CPU-side address, ACP RAM at 0x3000)
-pub const VOICE_BASE: usize = 0x3041;
+pub const VOICE_BASE: usize = cpu(abi::VOICE_BASE);
 /// Number of bytes per voice
-pub const VOICE_SIZE: usize = 7;
+pub const VOICE_SIZE: usize = abi::VOICE_SIZE as usize;
 /// Number of voices
-pub const VOICE_COUNT: usize = 8;
+pub const VOICE_COUNT: usize = abi::VOICE_COUNT;
+
+/// Base address for the voices' envelopes (CPU-side), one per voice with the
+/// same stride as the voices
+pub const ENVELOPE_BASE: usize = cpu(abi::ENVELOPE_BASE);
+
+/// Base address for the voices' slides (CPU-side), one per voice with the
+/// same stride as the voices
+pub const SLIDE_BASE: usize = cpu(abi::SLIDE_BASE);
+
+/// Volume setting for each decibel down from full, starting at 0dB
+pub const VOLUME_DB: [u8; 40] = volume::levels(&abi::VOLUME_GAIN, 1.0);
+
+/// Envelope and slide steps per frame: ~14kHz / 64 samples / 60Hz
+const STEPS_PER_FRAME: (u16, u16) = (11, 3);
+
+/// The voice that can play noise
+pub const NOISE_VOICE: usize = abi::NOISE_VOICE;
+/// Non-zero switches [`NOISE_VOICE`] to noise (CPU-side)
+pub const NOISE_MODE: usize = cpu(abi::NOISE_MODE);
 
 /// Base address for wavetables in ACP RAM (CPU-side)
-pub const WAVETABLE_BASE: usize = 0x3300;
+pub const WAVETABLE_BASE: usize = cpu(abi::WAVETABLE_BASE);
 /// Size of each wavetable in bytes
-pub const WAVETABLE_SIZE: usize = 256;
+pub const WAVETABLE_SIZE: usize = abi::WAVETABLE_SIZE as usize;
 /// Number of wavetables available
-pub const WAVETABLE_COUNT: usize = 11;
+pub const WAVETABLE_COUNT: usize = abi::WAVETABLE_COUNT;
 
-/// Wavetable slot addresses (CPU-side)
-pub const WAVETABLE: [u16; WAVETABLE_COUNT] = [
-    0x0300, 0x0400, 0x0500, 0x0600, 0x0700,
-    0x0800, 0x0900, 0x0A00, 0x0B00, 0x0C00,
-    0x0D00,
-];
+/// Wavetable slot addresses (ACP-side, for setting voice wavetable pointer)
+pub const WAVETABLE: [u16; WAVETABLE_COUNT] = {
+    let mut slots = [0; WAVETABLE_COUNT];
+    let mut i = 0;
+    while i < WAVETABLE_COUNT {
+        slots[i] = abi::WAVETABLE_BASE + abi::WAVETABLE_SIZE * i as u16;
+        i += 1;
+    }
+    slots
+};
 
 /// A single synthesizer voice.
 ///
@@ -81,7 +180,126 @@ pub struct Voice {
     volume: u8,
 }
 
+/// A voice's envelope, as the firmware steps it
+#[repr(C, packed)]
+struct Envelope {
+    stage: u8,
+    /// 0-255; the volume is this / 4
+    level: u8,
+    attack: u8,
+    decay: u8,
+    sustain: u8,
+    release: u8,
+}
+
+/// A voice's slides, as the firmware steps them
+#[repr(C, packed)]
+struct Slide {
+    /// Non-zero while sliding
+    step: u16,
+    target: u16,
+    /// Non-zero while sliding
+    volume_step: u8,
+    volume_target: u8,
+}
+
+/// Envelope and slide steps in `frames` frames, at least one
+fn steps(frames: u8) -> u16 {
+    let (num, den) = STEPS_PER_FRAME;
+    (frames as u16 * num / den).max(1)
+}
+
 impl Voice {
+    /// This voice's envelope, which sits as far past [`ENVELOPE_BASE`] as
+    /// the voice does past [`VOICE_BASE`]
+    #[inline]
+    fn envelope(&mut self) -> &mut Envelope {
+        let offset = self as *mut Voice as usize - VOICE_BASE;
+        unsafe { &mut *((ENVELOPE_BASE + offset) as *mut Envelope) }
+    }
+
+    /// This voice's slides, as far past [`SLIDE_BASE`] as the voice is past
+    /// [`VOICE_BASE`]
+    #[inline]
+    fn slide(&mut self) -> &mut Slide {
+        let offset = self as *mut Voice as usize - VOICE_BASE;
+        unsafe { &mut *((SLIDE_BASE + offset) as *mut Slide) }
+    }
+
+    /// Glide to `note` over `frames` frames. See [Slides](self#slides).
+    #[inline]
+    pub fn slide_to(&mut self, note: MidiNote, frames: u8) {
+        self.slide_frequency(midi_inc(note), frames);
+    }
+
+    /// Glide to the frequency increment `target` over `frames` frames.
+    pub fn slide_frequency(&mut self, target: u16, frames: u8) {
+        let distance = self.frequency.abs_diff(target);
+        let slide = self.slide();
+        slide.step = 0;
+        if distance == 0 {
+            return;
+        }
+        slide.target = target;
+        slide.step = (distance / steps(frames)).max(1);
+    }
+
+    /// Fade to `volume` (0-63) over `frames` frames.
+    pub fn slide_volume(&mut self, volume: u8, frames: u8) {
+        let volume = volume.min(63);
+        let distance = self.volume.abs_diff(volume) as u16;
+        let slide = self.slide();
+        slide.volume_step = 0;
+        if distance == 0 {
+            return;
+        }
+        slide.volume_target = volume;
+        slide.volume_step = (distance / steps(frames)).max(1) as u8;
+    }
+
+    /// Stop any slides where they are
+    #[inline]
+    pub fn stop_slides(&mut self) {
+        let slide = self.slide();
+        slide.step = 0;
+        slide.volume_step = 0;
+    }
+
+    /// Shape this voice's volume with an envelope from the next
+    /// [`note_on`](Self::note_on). `attack`, `decay` and `release` are how
+    /// fast the level moves each step, and `sustain` is the volume (0-63)
+    /// it holds while the note is on. See [Envelopes](self#envelopes).
+    pub fn set_envelope(&mut self, attack: u8, decay: u8, sustain: u8, release: u8) {
+        let envelope = self.envelope();
+        envelope.attack = attack.max(1);
+        envelope.decay = decay;
+        envelope.sustain = sustain.min(63) << 2;
+        envelope.release = release.max(1);
+    }
+
+    /// Stop the envelope, leaving the volume to [`set_volume`](Self::set_volume)
+    #[inline]
+    pub fn clear_envelope(&mut self) {
+        self.envelope().stage = stage::OFF;
+    }
+
+    /// Start `note` from silence through the envelope's attack
+    pub fn note_on(&mut self, note: MidiNote) {
+        self.set_note(note);
+        let envelope = self.envelope();
+        envelope.level = 0;
+        envelope.stage = stage::ATTACK;
+    }
+
+    /// Let the note fade out through the envelope's release
+    #[inline]
+    pub fn note_off(&mut self) {
+        let envelope = self.envelope();
+        if envelope.stage != stage::OFF {
+            envelope.stage = stage::RELEASE;
+        }
+    }
+
     /// Set the voice frequency from a MIDI note number.
     #[inline]
     pub fn set_note(&mut self, note: MidiNote) {
@@ -105,6 +323,14 @@ impl Voice {
         self.volume = volume;
     }
 
+    /// Set the volume in decibels from full (0 = maximum, -12 = about a
+    /// quarter as loud). Anything above 0 is 0.
+    #[inline]
+    pub fn set_volume_db(&mut self, db: i8) {
+        let step = db.min(0).unsigned_abs() as usize;
+        self.volume = VOLUME_DB.get(step).copied().unwrap_or(0);
+    }
+
     /// Set which wavetable this voice uses.
     /// 
     /// Pass the ACP-side address (e.g., `WAVETABLE[0]` = 0x0400).
@@ -125,6 +351,24 @@ impl Voice {
         self.phase = 0;
     }
 
+    /// The current frequency increment
+    #[inline]
+    pub fn frequency(&self) -> u16 {
+        self.frequency
+    }
+
+    /// The ACP-side address of the current wavetable
+    #[inline]
+    pub fn wavetable(&self) -> u16 {
+        self.wavetable
+    }
+
+    /// Set the phase accumulator, to start a note partway through its wave
+    #[inline]
+    pub fn set_phase(&mut self, phase: u16) {
+        self.phase = phase;
+    }
+
     /// Get the current volume level.
     #[inline]
     pub fn get_volume(&self) -> u8 {
@@ -152,6 +396,12 @@ pub fn voice(index: usize) -> &'static mut Voice {
     unsafe { &mut *((VOICE_BASE + index * VOICE_SIZE) as *mut Voice) }
 }
 
+/// Switch voice [`NOISE_VOICE`] between noise and its wavetable.
+#[inline]
+pub fn set_noise(enabled: bool) {
+    unsafe { (NOISE_MODE as *mut u8).write_volatile(enabled as u8) }
+}
+
 /// Silence all voices.
 #[inline]
 pub fn mute_all() {
@@ -71,6 +71,18 @@ impl Bcr {
     }
 }
 
+/// Acknowledge the blitter's IRQ, from an IRQ handler; see
+/// [`set_irq_handler`](crate::boot::set_irq_handler).
+///
+/// # Safety
+///
+/// The blitter's registers have to be mapped (`DMA_ENABLE`) when the IRQ
+/// lands, or this writes a pixel to the framebuffer instead.
+#[inline(always)]
+pub unsafe fn acknowledge_blit() {
+    unsafe { Bcr::new().start.write(0) };
+}
+
 /// Blitter fill mode.
 #[derive(PartialEq)]
 pub enum BlitterFillMode {
@@ -129,4 +141,15 @@ impl SpriteQuadrant {
             Self::Three | Self::Four => 128,
         }
     }
+
+    /// The quadrant sprite RAM coordinate (`gx`, `gy`) is in.
+    #[inline(always)]
+    pub fn containing(gx: u8, gy: u8) -> Self {
+        match (gx >= 128, gy >= 128) {
+            (false, false) => Self::One,
+            (true, false) => Self::Two,
+            (false, true) => Self::Three,
+            (true, true) => Self::Four,
+        }
+    }
 }
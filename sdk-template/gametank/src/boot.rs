@@ -2,7 +2,7 @@
 use core::panic::PanicInfo;
 use core::ptr;
 
-use crate::{blitter::SpriteQuadrant, console::Console};
+use crate::{blitter::SpriteQuadrant, console::Console, scr::VideoFlags, sync::IrqCell};
 
 unsafe extern "Rust" {
     unsafe fn main(console: &mut Console);
@@ -10,13 +10,17 @@ unsafe extern "Rust" {
 
 #[cfg(target_arch = "mos")]
 #[panic_handler]
-fn panic(_panic: &PanicInfo<'_>) -> ! {
-    loop {}
+fn panic(panic: &PanicInfo<'_>) -> ! {
+    crate::crash::show(panic)
 }
 
 #[unsafe(link_section = ".data.zp")]
 pub static mut VBLANK: bool = false;
 
+/// Bumped by every vblank; see [`FrameClock`](crate::frame::FrameClock)
+#[unsafe(link_section = ".data.zp")]
+pub static mut VBLANK_COUNT: u8 = 0;
+
 unsafe extern "C" {
     pub unsafe fn return_from_interrupt();
 
@@ -26,6 +30,10 @@ unsafe extern "C" {
 
     pub unsafe fn disable_irq_handler();
 
+    unsafe fn nmi_entry();
+
+    unsafe fn irq_entry();
+
     /// Set the overflow (V) flag. Used by llvm-mos for certain operations.
     // pub unsafe fn __set_v();
 
@@ -79,20 +87,64 @@ unsafe fn init_data_and_bss() {
     }
 }
 
+static mut NMI_HANDLER: Option<fn()> = None;
+
+static IRQ_HANDLER: IrqCell<Option<fn()>> = IrqCell::new(None);
+
+/// Run `handler` on every vblank, after the SDK has counted it. It runs in
+/// the middle of whatever the main loop was doing, so keep it short, and
+/// share data with it through single bytes; the NMI can't be masked, so an
+/// [`IrqCell`] won't keep it out. `None` goes back to just counting.
+pub fn set_nmi_handler(console: &mut Console, handler: Option<fn()>) {
+    let video_flags = console.video_flags;
+    console.video_flags.remove(VideoFlags::DMA_NMI);
+    console.write_video_flags();
+    unsafe { NMI_HANDLER = handler };
+    console.video_flags = video_flags;
+    console.write_video_flags();
+}
+
+/// Run `handler` on every IRQ, for raster effects or the VIA's timers.
+/// IRQs are masked from reset, so nothing happens until
+/// [`enable_irq_handler`] unmasks them. Share data with the handler through
+/// an [`IrqCell`].
+///
+/// The blitter raises an IRQ when a blit finishes and holds it until it's
+/// acknowledged. With IRQs masked that only wakes
+/// [`wait_blit`](crate::video_dma::blitter::BlitterGuard::wait_blit), which
+/// acknowledges it. With them unmasked, the handler runs as soon as the blit
+/// is done, and again every time it returns until the IRQ is acknowledged,
+/// so the handler has to do that itself
+/// ([`acknowledge_blit`](crate::blitter::acknowledge_blit)), or
+/// [`VideoFlags::DMA_IRQ`] has to be off.
+pub fn set_irq_handler(handler: Option<fn()>) {
+    IRQ_HANDLER.set(handler);
+}
+
 #[unsafe(no_mangle)]
 extern "C" fn vblank_nmi() {
     unsafe {
         VBLANK = true;
-        return_from_interrupt();
+        VBLANK_COUNT = VBLANK_COUNT.wrapping_add(1);
+        if let Some(handler) = NMI_HANDLER {
+            handler();
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn irq_dispatch() {
+    if let Some(handler) = IRQ_HANDLER.get() {
+        handler();
     }
 }
 
 #[unsafe(link_section = ".vector_table")]
 #[unsafe(no_mangle)]
 pub static _VECTOR_TABLE: [unsafe extern "C" fn(); 3] = [
-    vblank_nmi,            // Non-Maskable Interrupt vector
-    __boot,                // Reset vector
-    return_from_interrupt, // IRQ/BRK vector
+    nmi_entry, // Non-Maskable Interrupt vector
+    __boot,    // Reset vector
+    irq_entry, // IRQ/BRK vector
 ];
 
 
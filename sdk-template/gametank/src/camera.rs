@@ -0,0 +1,168 @@
+//! # Camera
+//!
+//! A [`Camera`] is the window the screen shows into a world bigger than it:
+//! its top left corner in world pixels, kept inside the world. Things in the
+//! world are drawn where [`to_screen`](Camera::to_screen) puts them, and a
+//! [`ScrollingMap`] follows it with [`scroll`](Camera::scroll):
+//!
+//! ```ignore
+//! let mut camera = Camera::new(map.pixel_size());
+//!
+//! loop {
+//!     clock.wait();
+//!     console.flip_framebuffers();
+//!
+//!     camera.follow(player.rect(), 32);
+//!     camera.scroll(&mut map);
+//!     map.update(console);
+//!     map.draw(console);
+//!
+//!     for enemy in &enemies {
+//!         if let Some((x, y)) = camera.on_screen(&enemy.rect()) {
+//!             enemy.sprite.draw(&mut blitter, x, y);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ## Parallax
+//!
+//! A [`ParallaxLayer`] is a [`ScrollingMap`] that covers one band of screen
+//! rows and scrolls slower than the camera, like a sky at the top and hills
+//! under it. Each layer keeps its tiles in its own sprite page and only
+//! copies the strips that scrolled into view, as any scrolling map does;
+//! drawing one only blits the tiles in its band:
+//!
+//! ```ignore
+//! let mut layers = [
+//!     // each a ScrollingMap on its own sprite page
+//!     ParallaxLayer::new(sky, 2, 0, 40),     // a quarter of the camera's speed
+//!     ParallaxLayer::new(hills, 1, 40, 32),  // half
+//!     ParallaxLayer::new(ground, 0, 72, 56), // full
+//! ];
+//!
+//! camera.follow(player.rect(), 32);
+//! draw_parallax(console, &camera, &mut layers);
+//! ```
+//!
+//! A band shows the same rows of its map that it covers on screen, so lay
+//! each map out a screen tall, with its band's rows filled in. Bands don't
+//! overlap, so there's no transparency to worry about, and all of them
+//! together cost the same blitter time as one full screen map.
+
+use crate::{collision::Rect, console::Console, screen, tilemap::ScrollingMap};
+
+/// The window the screen shows into the world; see the [module docs](self)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    /// Top left corner, in world pixels
+    pub x: i16,
+    pub y: i16,
+    /// How far the camera can go before showing past the world's edges
+    max_x: i16,
+    max_y: i16,
+}
+
+impl Camera {
+    /// A camera at the top left of a world `size` pixels wide and tall
+    pub const fn new(size: (u16, u16)) -> Self {
+        let (max_x, max_y) = (size.0.saturating_sub(screen::SIZE as u16), size.1.saturating_sub(screen::SIZE as u16));
+        Self { x: 0, y: 0, max_x: max_x as i16, max_y: max_y as i16 }
+    }
+
+    /// Move the top left corner to (`x`, `y`), kept inside the world
+    pub fn set(&mut self, x: i16, y: i16) {
+        self.x = x.clamp(0, self.max_x);
+        self.y = y.clamp(0, self.max_y);
+    }
+
+    /// Put the middle of the screen on (`x`, `y`), as near as the world's
+    /// edges allow
+    pub fn center_on(&mut self, x: i16, y: i16) {
+        self.set(x - screen::SIZE as i16 / 2, y - screen::SIZE as i16 / 2);
+    }
+
+    /// Move just enough to keep `target` at least `margin` pixels in from
+    /// every edge of the screen, so small moves don't scroll
+    pub fn follow(&mut self, target: Rect, margin: u8) {
+        let margin = margin as i16;
+        let (mut x, mut y) = (self.x, self.y);
+        if target.x < x + margin {
+            x = target.x - margin;
+        } else if target.right() > x + screen::SIZE as i16 - margin {
+            x = target.right() - screen::SIZE as i16 + margin;
+        }
+        if target.y < y + margin {
+            y = target.y - margin;
+        } else if target.bottom() > y + screen::SIZE as i16 - margin {
+            y = target.bottom() - screen::SIZE as i16 + margin;
+        }
+        self.set(x, y);
+    }
+
+    /// Where the world point (`x`, `y`) lands on screen, possibly off it
+    #[inline(always)]
+    pub const fn to_screen(&self, x: i16, y: i16) -> (i16, i16) {
+        (x - self.x, y - self.y)
+    }
+
+    /// Where screen pixel (`x`, `y`) is in the world
+    #[inline(always)]
+    pub const fn to_world(&self, x: u8, y: u8) -> (i16, i16) {
+        (self.x + x as i16, self.y + y as i16)
+    }
+
+    /// The part of the world on screen
+    pub const fn view(&self) -> Rect {
+        Rect::new(self.x, self.y, screen::SIZE, screen::SIZE)
+    }
+
+    /// Where `rect`'s top left lands on screen, if any of it shows
+    pub fn on_screen(&self, rect: &Rect) -> Option<(i16, i16)> {
+        self.view().overlaps(rect).then(|| self.to_screen(rect.x, rect.y))
+    }
+
+    /// Point `map` where the camera is
+    pub fn scroll(&self, map: &mut ScrollingMap) {
+        map.set_camera(self.x as u16, self.y as u16);
+    }
+}
+
+/// A map drawn in a band of screen rows, scrolling at a fraction of the
+/// camera's speed; see [Parallax](self#parallax)
+pub struct ParallaxLayer<'a> {
+    pub map: ScrollingMap<'a>,
+    /// Scrolls at 1/2<sup>`shift`</sup> of the camera's speed: 0 keeps up
+    /// with it, 1 is half as fast, 2 a quarter
+    pub shift: u8,
+    /// The first screen row of the band
+    pub top: u8,
+    /// How many rows the band covers
+    pub height: u8,
+}
+
+impl<'a> ParallaxLayer<'a> {
+    pub fn new(map: ScrollingMap<'a>, shift: u8, top: u8, height: u8) -> Self {
+        Self { map, shift, top, height }
+    }
+
+    /// Scroll to match `camera` and copy in whatever strips that brought
+    /// into view
+    pub fn update(&mut self, console: &mut Console, camera: &Camera) {
+        let (x, y) = (camera.x as u16 >> self.shift, camera.y as u16 >> self.shift);
+        self.map.set_camera(x, y);
+        self.map.update(console);
+    }
+
+    pub fn draw(&self, console: &mut Console) {
+        self.map.draw_band(console, self.top, self.height);
+    }
+}
+
+/// Update and draw every layer for this frame's `camera`
+pub fn draw_parallax(console: &mut Console, camera: &Camera, layers: &mut [ParallaxLayer]) {
+    for layer in layers {
+        layer.update(console, camera);
+        layer.draw(console);
+    }
+}
@@ -0,0 +1,359 @@
+//! # Collision
+//!
+//! Rectangles in whole pixels, positions in [`Fixed`] point so things can
+//! move less than a pixel a frame, and three levels of checking:
+//!
+//! - [`Rect::overlaps`] and [`Rect::contains`] for one thing against another
+//! - [`Body::move_by`] to move against a tile map without tunneling into walls
+//! - [`Broadphase`] to skip the pairs that are nowhere near each other
+//!
+//! ```ignore
+//! let mut player = Body::new(Fixed::from_int(24), Fixed::from_int(40), 8, 14);
+//! let mut vy = Fixed::ZERO;
+//!
+//! loop {
+//!     clock.wait();
+//!     vy += GRAVITY;
+//!     let hit = player.move_by(walk_speed, vy, &LEVEL);
+//!     if hit.y {
+//!         vy = Fixed::ZERO;
+//!     }
+//!
+//!     for coin in &mut coins {
+//!         if coin.rect.overlaps(&player.rect()) {
+//!             coin.collect();
+//!         }
+//!     }
+//! }
+//! ```
+
+use core::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use crate::assets::TiledMap;
+
+/// A signed 12.4 fixed point number: whole pixels in the top 12 bits,
+/// sixteenths of a pixel in the bottom 4. That covers -2048 to 2047 pixels,
+/// enough for a 256 tile wide map of 8 pixel tiles, in one 16-bit word.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(pub i16);
+
+impl Fixed {
+    pub const FRAC_BITS: u8 = 4;
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << Self::FRAC_BITS);
+
+    pub const fn from_int(n: i16) -> Self {
+        Self(n << Self::FRAC_BITS)
+    }
+
+    /// `num / den` of a pixel, e.g. `Fixed::from_ratio(3, 2)` for 1.5
+    pub const fn from_ratio(num: i16, den: i16) -> Self {
+        Self((num << Self::FRAC_BITS) / den)
+    }
+
+    /// The whole pixel, rounding down
+    pub const fn floor(self) -> i16 {
+        self.0 >> Self::FRAC_BITS
+    }
+
+    /// The nearest whole pixel
+    pub const fn round(self) -> i16 {
+        (self.0 + (1 << (Self::FRAC_BITS - 1))) >> Self::FRAC_BITS
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+    fn add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+    fn sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+    fn neg(self) -> Fixed {
+        Fixed(self.0.wrapping_neg())
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Fixed) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Fixed) {
+        *self = *self - rhs;
+    }
+}
+
+/// A rectangle in whole pixels, `x` and `y` its top left
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: i16,
+    pub y: i16,
+    pub w: u8,
+    pub h: u8,
+}
+
+impl Rect {
+    pub const fn new(x: i16, y: i16, w: u8, h: u8) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// One past the rightmost column
+    #[inline]
+    pub const fn right(&self) -> i16 {
+        self.x + self.w as i16
+    }
+
+    /// One past the bottom row
+    #[inline]
+    pub const fn bottom(&self) -> i16 {
+        self.y + self.h as i16
+    }
+
+    /// Whether the two share at least one pixel. Touching edges don't count.
+    #[inline]
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    #[inline]
+    pub fn contains(&self, x: i16, y: i16) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// `rect` moved as little as it takes to be inside this one, like
+    /// keeping something in the playfield. If it's too big to fit, its top
+    /// left lines up with this one's.
+    #[inline]
+    pub fn clamp(&self, rect: Rect) -> Rect {
+        let x = rect.x.min(self.right() - rect.w as i16).max(self.x);
+        let y = rect.y.min(self.bottom() - rect.h as i16).max(self.y);
+        Rect { x, y, ..rect }
+    }
+}
+
+/// Something with solid tiles to run into
+pub trait SolidTiles {
+    /// Tile width and height in pixels
+    fn tile_size(&self) -> (u8, u8);
+
+    /// Whether the tile at column `x`, row `y` is solid. This gets asked
+    /// about tiles off the edges of the map too.
+    fn is_solid(&self, x: i16, y: i16) -> bool;
+}
+
+/// The map's collision layer, with everything off the map solid. Switch in
+/// [`CollisionMap::bank`](crate::assets::CollisionMap) before moving
+/// anything against it; a map without a collision layer has no walls.
+impl SolidTiles for TiledMap {
+    fn tile_size(&self) -> (u8, u8) {
+        (self.tile_width, self.tile_height)
+    }
+
+    fn is_solid(&self, x: i16, y: i16) -> bool {
+        match &self.collision {
+            Some(collision) => x < 0 || y < 0 || collision.is_solid(x as u16, y as u16),
+            None => false,
+        }
+    }
+}
+
+/// Which ways a [`Body::move_by`] was stopped
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Hit {
+    pub x: bool,
+    pub y: bool,
+}
+
+/// A moving rectangle with a sub-pixel position
+#[derive(Debug, Copy, Clone)]
+pub struct Body {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub w: u8,
+    pub h: u8,
+}
+
+/// The tile column or row holding pixel `p`
+#[inline(always)]
+fn tile_of(p: i16, size: u8) -> i16 {
+    p.div_euclid(size as i16)
+}
+
+impl Body {
+    pub const fn new(x: Fixed, y: Fixed, w: u8, h: u8) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// The pixels it covers right now
+    #[inline]
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x.floor(), self.y.floor(), self.w, self.h)
+    }
+
+    /// Move across, then down, stopping flush against the first solid tile
+    /// each way. Every tile on the way is checked, so nothing fast goes
+    /// through a thin wall. A body that starts inside a wall can move
+    /// out of it.
+    pub fn move_by(&mut self, dx: Fixed, dy: Fixed, tiles: &impl SolidTiles) -> Hit {
+        let (tile_w, tile_h) = tiles.tile_size();
+        let mut hit = Hit::default();
+
+        if dx != Fixed::ZERO {
+            let from = self.rect();
+            self.x += dx;
+            let to = self.rect();
+            let rows = tile_of(from.y, tile_h)..=tile_of(from.bottom() - 1, tile_h);
+            let blocked = |column: i16| rows.clone().any(|row| tiles.is_solid(column, row));
+
+            if dx > Fixed::ZERO {
+                // the columns the right edge moves into
+                let first = tile_of(from.right() - 1, tile_w) + 1;
+                if let Some(column) = (first..=tile_of(to.right() - 1, tile_w)).find(|&c| blocked(c)) {
+                    self.x = Fixed::from_int(column * tile_w as i16 - self.w as i16);
+                    hit.x = true;
+                }
+            } else {
+                let first = tile_of(from.x, tile_w) - 1;
+                if let Some(column) = (tile_of(to.x, tile_w)..=first).rev().find(|&c| blocked(c)) {
+                    self.x = Fixed::from_int((column + 1) * tile_w as i16);
+                    hit.x = true;
+                }
+            }
+        }
+
+        if dy != Fixed::ZERO {
+            let from = self.rect();
+            self.y += dy;
+            let to = self.rect();
+            let columns = tile_of(from.x, tile_w)..=tile_of(from.right() - 1, tile_w);
+            let blocked = |row: i16| columns.clone().any(|column| tiles.is_solid(column, row));
+
+            if dy > Fixed::ZERO {
+                let first = tile_of(from.bottom() - 1, tile_h) + 1;
+                if let Some(row) = (first..=tile_of(to.bottom() - 1, tile_h)).find(|&r| blocked(r)) {
+                    self.y = Fixed::from_int(row * tile_h as i16 - self.h as i16);
+                    hit.y = true;
+                }
+            } else {
+                let first = tile_of(from.y, tile_h) - 1;
+                if let Some(row) = (tile_of(to.y, tile_h)..=first).rev().find(|&r| blocked(r)) {
+                    self.y = Fixed::from_int((row + 1) * tile_h as i16);
+                    hit.y = true;
+                }
+            }
+        }
+
+        hit
+    }
+}
+
+/// log2 of a broadphase cell's size in pixels
+const CELL_SHIFT: u8 = 4;
+/// Cells across and down
+const GRID: i16 = 8;
+
+/// A coarse grid over a 128x128 area (usually the screen) that remembers
+/// which of up to 16 things touch each 16x16 cell, one bit per thing.
+/// Checking only what shares a cell cuts the pairs to test from
+/// everything-against-everything down to near neighbours.
+///
+/// ```ignore
+/// let mut grid = Broadphase::new(camera_x, camera_y);
+/// for (id, enemy) in enemies.iter().enumerate() {
+///     grid.insert(id as u8, &enemy.rect());
+/// }
+/// for id in Broadphase::ids(grid.candidates(&sword)) {
+///     if enemies[id as usize].rect().overlaps(&sword) {
+///         enemies[id as usize].hurt();
+///     }
+/// }
+/// ```
+pub struct Broadphase {
+    cells: [u16; (GRID * GRID) as usize],
+    x: i16,
+    y: i16,
+}
+
+impl Broadphase {
+    /// An empty grid with its top left at (`x`, `y`)
+    pub const fn new(x: i16, y: i16) -> Self {
+        Self { cells: [0; (GRID * GRID) as usize], x, y }
+    }
+
+    /// Empty it out and move it, ready for the next frame
+    pub fn clear(&mut self, x: i16, y: i16) {
+        self.cells = [0; (GRID * GRID) as usize];
+        self.x = x;
+        self.y = y;
+    }
+
+    /// The first and last cell columns and rows `rect` touches, or None if
+    /// it's entirely outside the grid
+    fn span(&self, rect: &Rect) -> Option<(u8, u8, u8, u8)> {
+        let (left, top) = (rect.x - self.x, rect.y - self.y);
+        let (right, bottom) = (rect.right() - 1 - self.x, rect.bottom() - 1 - self.y);
+        let size = GRID << CELL_SHIFT;
+        if rect.w == 0 || rect.h == 0 || right < 0 || bottom < 0 || left >= size || top >= size {
+            return None;
+        }
+        let cell = |p: i16| (p.clamp(0, size - 1) >> CELL_SHIFT) as u8;
+        Some((cell(left), cell(top), cell(right), cell(bottom)))
+    }
+
+    /// Record thing number `id` (0-15) as covering `rect`. Anything outside
+    /// the grid is left out.
+    pub fn insert(&mut self, id: u8, rect: &Rect) {
+        let Some((x0, y0, x1, y1)) = self.span(rect) else {
+            return;
+        };
+        let bit = 1 << (id & 15);
+        for row in y0..=y1 {
+            for column in x0..=x1 {
+                self.cells[row as usize * GRID as usize + column as usize] |= bit;
+            }
+        }
+    }
+
+    /// A bit for each thing sharing a cell with `rect`. They might not
+    /// actually overlap it; check the ones that come back.
+    pub fn candidates(&self, rect: &Rect) -> u16 {
+        let Some((x0, y0, x1, y1)) = self.span(rect) else {
+            return 0;
+        };
+        let mut found = 0;
+        for row in y0..=y1 {
+            for column in x0..=x1 {
+                found |= self.cells[row as usize * GRID as usize + column as usize];
+            }
+        }
+        found
+    }
+
+    /// The ids set in a mask from [`candidates`](Self::candidates)
+    pub fn ids(mut mask: u16) -> impl Iterator<Item = u8> {
+        let mut id = 0;
+        core::iter::from_fn(move || {
+            while mask != 0 {
+                let set = mask & 1 != 0;
+                mask >>= 1;
+                id += 1;
+                if set {
+                    return Some(id - 1);
+                }
+            }
+            None
+        })
+    }
+}
@@ -0,0 +1,82 @@
+//! # Colors
+//!
+//! A GameTank color is one byte, `0bHHH_SS_LLL`: 3 bits of hue, 2 of
+//! saturation and 3 of luminosity. [`hsl`] builds one, and the common ones
+//! have names:
+//!
+//! ```ignore
+//! use gametank::color::{self, hue, hsl};
+//!
+//! let sky = hsl(hue::BLUE, 2, 5);
+//! blitter.draw_rect(0, 0, 127, 40, sky);
+//! blitter.draw_rect(10, 10, 8, 8, color::YELLOW);
+//! ```
+//!
+//! The blitter's fill color register takes colors inverted, which is why
+//! older code passes `!color` to [`draw_square`]. [`draw_rect`] does the
+//! inverting itself, so colors from here can go straight in. Pixels written
+//! straight to the framebuffer aren't inverted either way.
+//!
+//! [`draw_square`]: crate::video_dma::blitter::BlitterGuard::draw_square
+//! [`draw_rect`]: crate::video_dma::blitter::BlitterGuard::draw_rect
+
+/// The eight hues, for [`hsl`]
+pub mod hue {
+    pub const YELLOW: u8 = 0;
+    pub const ORANGE: u8 = 1;
+    pub const RED: u8 = 2;
+    pub const MAGENTA: u8 = 3;
+    pub const VIOLET: u8 = 4;
+    pub const BLUE: u8 = 5;
+    pub const CYAN: u8 = 6;
+    pub const GREEN: u8 = 7;
+}
+
+/// Build a color from `hue` (0-7, see [`hue`]), `saturation` (0-3, 0 for
+/// grays) and `luminosity` (0-7). Out of range bits are dropped.
+#[inline(always)]
+pub const fn hsl(hue: u8, saturation: u8, luminosity: u8) -> u8 {
+    ((hue & 0b111) << 5) | ((saturation & 0b11) << 3) | (luminosity & 0b111)
+}
+
+/// The hue of `color`
+#[inline(always)]
+pub const fn hue_of(color: u8) -> u8 {
+    color >> 5
+}
+
+/// The saturation of `color`
+#[inline(always)]
+pub const fn saturation_of(color: u8) -> u8 {
+    (color >> 3) & 0b11
+}
+
+/// The luminosity of `color`
+#[inline(always)]
+pub const fn luminosity_of(color: u8) -> u8 {
+    color & 0b111
+}
+
+/// `color` a step or more lighter (positive `steps`) or darker (negative),
+/// stopping at the ends of the range
+#[inline(always)]
+pub const fn shade(color: u8, steps: i8) -> u8 {
+    let luminosity = luminosity_of(color) as i8 + steps;
+    let luminosity = if luminosity < 0 { 0 } else if luminosity > 7 { 7 } else { luminosity };
+    (color & !0b111) | luminosity as u8
+}
+
+pub const BLACK: u8 = hsl(hue::YELLOW, 0, 0);
+pub const DARK_GRAY: u8 = hsl(hue::YELLOW, 0, 2);
+pub const GRAY: u8 = hsl(hue::YELLOW, 0, 4);
+pub const LIGHT_GRAY: u8 = hsl(hue::YELLOW, 0, 6);
+pub const WHITE: u8 = hsl(hue::YELLOW, 0, 7);
+
+pub const YELLOW: u8 = hsl(hue::YELLOW, 3, 4);
+pub const ORANGE: u8 = hsl(hue::ORANGE, 3, 4);
+pub const RED: u8 = hsl(hue::RED, 3, 4);
+pub const MAGENTA: u8 = hsl(hue::MAGENTA, 3, 4);
+pub const VIOLET: u8 = hsl(hue::VIOLET, 3, 4);
+pub const BLUE: u8 = hsl(hue::BLUE, 3, 4);
+pub const CYAN: u8 = hsl(hue::CYAN, 3, 4);
+pub const GREEN: u8 = hsl(hue::GREEN, 3, 4);
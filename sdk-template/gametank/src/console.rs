@@ -1,4 +1,4 @@
-use crate::{input::GenesisGamepad, scr::{BankFlags, VideoFlags}, via::Via, video_dma::{DmaManager, VideoDma, blitter::BlitterGuard, spritemem::SpriteMem}};
+use crate::{input::{GenesisGamepad, Gamepads}, scr::{BankFlags, VideoFlags}, via::Via, video_dma::{DmaManager, VideoDma, blitter::BlitterGuard, spritemem::SpriteMem}};
 
 /// Write-only register at $2005
 const BANK_REG: *mut u8 = 0x2005 as *mut u8;
@@ -45,7 +45,7 @@ impl Console {
             video_flags,
             dma: DmaManager::new(VideoDma::DmaSprites(SpriteMem)),
             audio: AudioManager {
-                aram: unsafe { &mut *(0x3000 as *mut [u8; 4096]) },
+                aram: unsafe { &mut *(gt_audio_abi::ARAM_CPU_BASE as usize as *mut [u8; 4096]) },
                 audio_reset: unsafe { &mut *(0x2000 as *mut u8) },
                 audio_nmi: unsafe { &mut *(0x2001 as *mut u8) },
                 audio_freq: unsafe { &mut *(0x2006 as *mut u8) },
@@ -75,10 +75,28 @@ impl Console {
         self.write_video_flags();
     }
 
+    /// Select sprite RAM page `page` (0-7), for loading sprites and for
+    /// the blitter to copy from
+    #[inline(always)]
+    pub fn set_sprite_page(&mut self, page: u8) {
+        self.bank_flags = self.bank_flags.with_sprite_page(page);
+        self.write_bank_flags();
+    }
+
+    /// The selected sprite RAM page
+    #[inline(always)]
+    pub fn sprite_page(&self) -> u8 {
+        self.bank_flags.sprite_page()
+    }
+
     pub fn genesis_gamepads(&self) -> (GenesisGamepad<1>, GenesisGamepad<2>) {
         (GenesisGamepad::new(), GenesisGamepad::new())
     }
 
+    pub fn gamepads(&self) -> Gamepads {
+        Gamepads::new()
+    }
+
     pub fn set_rom_bank(&mut self, bank: u8) {
         self.via.change_rom_bank(bank);
     }
@@ -0,0 +1,201 @@
+//! # Crash Screen
+//!
+//! When the program panics, the SDK's panic handler puts up a screen with
+//! where it happened instead of hanging on whatever was last drawn:
+//!
+//! ```text
+//! PANIC
+//!
+//! SRC/MAIN.RS:42
+//!
+//! ATTEMPT TO SUBTRACT WITH OVERFLOW
+//! ```
+//!
+//! It writes straight to both framebuffers with the CPU, using a tiny font
+//! built into the SDK, so it works no matter what was in sprite RAM or what
+//! the blitter was in the middle of. Text is uppercase only.
+//!
+//! Messages built with `format!`-style arguments (like an index out of
+//! bounds, with the index) only show with the `panic-message` feature, which
+//! pulls in `core::fmt`. That's several kilobytes of the fixed bank, so it's
+//! off by default; without it, formatted messages show as `(FORMATTED)`.
+
+use core::panic::PanicInfo;
+
+use crate::{
+    blitter::Bcr,
+    boot::{disable_irq_handler, wait},
+    color::{self, hsl, hue},
+    scr::{BankFlags, VideoFlags},
+};
+
+/// Write-only register at $2005
+const BANK_REG: *mut u8 = 0x2005 as *mut u8;
+/// Write-only register at $2007
+const VIDEO_REG: *mut u8 = 0x2007 as *mut u8;
+/// With the CPU on the framebuffer, it's all of $4000-$7FFF
+const FRAMEBUFFER: *mut u8 = 0x4000 as *mut u8;
+
+const BACKGROUND: u8 = hsl(hue::RED, 3, 2);
+const TEXT: u8 = color::WHITE;
+
+/// Each glyph is 3x5 pixels in a 4x6 cell
+const CELL_WIDTH: u8 = 4;
+const CELL_HEIGHT: u8 = 6;
+/// Inside the area most TVs show; see `draw_letterbox`
+const LEFT: u8 = 2;
+const TOP: u8 = 12;
+const COLUMNS: u8 = 31;
+const ROWS: u8 = 17;
+
+/// ASCII 0x20 to 0x5F, 3x5 pixels each, top row first, most significant
+/// bit on the left. Bit 15 is unused.
+static FONT: [u16; 64] = [
+    0x0000, 0x2482, 0x5A00, 0x5F7D, 0x3C9E, 0x52A5, 0x2AAB, 0x2400,  //  !"#$%&'
+    0x1491, 0x4494, 0x0AA8, 0x05D0, 0x0014, 0x01C0, 0x0002, 0x12A4,  // ()*+,-./
+    0x7B6F, 0x2C97, 0x62A7, 0x628E, 0x5BC9, 0x798E, 0x39EF, 0x7292,  // 01234567
+    0x7BEF, 0x7BCE, 0x0410, 0x0414, 0x1511, 0x0E38, 0x4454, 0x6282,  // 89:;<=>?
+    0x2BE3, 0x2BED, 0x6BAE, 0x3923, 0x6B6E, 0x79A7, 0x79A4, 0x396B,  // @ABCDEFG
+    0x5BED, 0x7497, 0x126A, 0x5BAD, 0x4927, 0x5FED, 0x6B6D, 0x2B6A,  // HIJKLMNO
+    0x6BA4, 0x2B73, 0x6BAD, 0x388E, 0x7492, 0x5B6F, 0x5B6A, 0x5BFD,  // PQRSTUVW
+    0x5AAD, 0x5A92, 0x72A7, 0x3493, 0x4889, 0x6496, 0x2A00, 0x0007,  // XYZ[\]^_
+];
+
+/// Text cursor over the framebuffer the CPU can currently see
+struct Screen {
+    column: u8,
+    row: u8,
+}
+
+impl Screen {
+    fn clear() -> Self {
+        for offset in 0..0x4000 {
+            unsafe { FRAMEBUFFER.add(offset).write_volatile(BACKGROUND) };
+        }
+        Self { column: 0, row: 0 }
+    }
+
+    fn newline(&mut self) {
+        self.column = 0;
+        self.row += 1;
+    }
+
+    fn put(&mut self, byte: u8) {
+        if byte == b'\n' {
+            self.newline();
+            return;
+        }
+        if self.row >= ROWS {
+            return;
+        }
+
+        let glyph = match byte.to_ascii_uppercase() {
+            c @ 0x20..=0x5F => FONT[(c - 0x20) as usize],
+            _ => FONT[(b'?' - 0x20) as usize],
+        };
+        let x = LEFT + self.column * CELL_WIDTH;
+        let y = TOP + self.row * CELL_HEIGHT;
+        for bit in 0..15u8 {
+            if glyph & (0x4000 >> bit) != 0 {
+                let offset = (y + bit / 3) as usize * 128 + (x + bit % 3) as usize;
+                unsafe { FRAMEBUFFER.add(offset).write_volatile(TEXT) };
+            }
+        }
+
+        self.column += 1;
+        if self.column == COLUMNS {
+            self.newline();
+        }
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.put(byte);
+        }
+    }
+
+    /// The digits of `n`, filling `buf` from the end
+    fn digits(mut n: u32, buf: &mut [u8; 10]) -> &[u8] {
+        let mut start = buf.len();
+        loop {
+            start -= 1;
+            buf[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                return &buf[start..];
+            }
+        }
+    }
+
+    /// `file:line`, with the front of the path cut off to fit on one row
+    fn put_location(&mut self, file: &str, line: u32) {
+        let mut buf = [0; 10];
+        let line = Self::digits(line, &mut buf);
+        let room = (COLUMNS as usize).saturating_sub(line.len() + 1);
+        let file = file.as_bytes();
+        if file.len() > room {
+            self.put_bytes(b"...");
+            self.put_bytes(&file[file.len() - (room - 3)..]);
+        } else {
+            self.put_bytes(file);
+        }
+        self.put(b':');
+        self.put_bytes(line);
+        self.newline();
+    }
+
+    fn draw(&mut self, info: &PanicInfo) {
+        self.put_bytes(b"PANIC\n\n");
+        if let Some(location) = info.location() {
+            self.put_location(location.file(), location.line());
+            self.newline();
+        }
+        self.put_message(info);
+    }
+
+    #[cfg(feature = "panic-message")]
+    fn put_message(&mut self, info: &PanicInfo) {
+        use core::fmt::Write;
+        let _ = write!(self, "{}", info.message());
+    }
+
+    #[cfg(not(feature = "panic-message"))]
+    fn put_message(&mut self, info: &PanicInfo) {
+        self.put_bytes(info.message().as_str().unwrap_or("(formatted)").as_bytes());
+    }
+}
+
+#[cfg(feature = "panic-message")]
+impl core::fmt::Write for Screen {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.put_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Show the crash screen and stop there. This is what the SDK's panic
+/// handler calls.
+pub fn show(info: &PanicInfo) -> ! {
+    unsafe {
+        disable_irq_handler();
+
+        // let any blit that's running finish, so it can't draw over the
+        // screen: it raises an IRQ when it's done, and vblank comes soon
+        // enough if nothing's running
+        VIDEO_REG.write_volatile((VideoFlags::DMA_ENABLE | VideoFlags::DMA_IRQ | VideoFlags::DMA_NMI).bits());
+        wait();
+        Bcr::new().start.write(0);
+
+        // draw the same thing in both framebuffers, so it doesn't matter
+        // which one is on screen
+        for framebuffer in [BankFlags::empty(), BankFlags::FRAMEBUFFER_SELECT] {
+            BANK_REG.write_volatile(framebuffer.bits());
+            VIDEO_REG.write_volatile((VideoFlags::DMA_CPU_TO_VRAM | VideoFlags::DMA_NMI).bits());
+            Screen::clear().draw(info);
+        }
+    }
+
+    loop {
+        unsafe { wait(); }
+    }
+}
@@ -0,0 +1,44 @@
+//! # Debug Output
+//!
+//! Print from a ROM to the emulator. [`debug!`](crate::debug!) takes the same
+//! arguments as `format!` and sends a line to gte's debug log window (and
+//! its log output):
+//!
+//! ```ignore
+//! use gametank::debug;
+//!
+//! debug!("player at {}, {}", player.x, player.y);
+//! debug!("spawned");
+//! ```
+//!
+//! The text goes out through [`DEBUG_PORT`], an address that nothing on the
+//! console answers to, so a debug build still runs on hardware. In release
+//! builds `debug!` compiles to nothing, formatting and all.
+
+use core::fmt;
+
+/// Bytes written here show up in gte's debug log; a newline ends the line
+pub const DEBUG_PORT: *mut u8 = 0x200A as *mut u8;
+
+/// Writes text to [`DEBUG_PORT`]
+pub struct DebugPort;
+
+impl fmt::Write for DebugPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            unsafe { core::ptr::write_volatile(DEBUG_PORT, byte) };
+        }
+        Ok(())
+    }
+}
+
+/// Print a line to the emulator's debug log, in debug builds only
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if cfg!(debug_assertions) {
+            use core::fmt::Write as _;
+            let _ = writeln!($crate::debug::DebugPort, $($arg)*);
+        }
+    };
+}
@@ -0,0 +1,208 @@
+//! # Number Formatting
+//!
+//! `core::fmt` works, but it's big and slow on a 6502, and pulls in
+//! division routines for every integer size it touches. These turn numbers
+//! into ASCII in a small buffer instead, by counting subtractions, and hand
+//! back a `&str` that goes straight to [`draw_text`](crate::text::draw_text):
+//!
+//! ```ignore
+//! let mut buf = [0; 5];
+//! draw_text(&mut blitter, &font, 4, 4, "SCORE");
+//! draw_text(&mut blitter, &font, 52, 4, fmt::u16_dec_zeroed(score, &mut buf));
+//!
+//! let mut buf = [0; 2];
+//! draw_text(&mut blitter, &font, 4, 12, fmt::u8_hex(bank, &mut buf));
+//! ```
+//!
+//! For a score that only ever gets added to and shown, keeping it in BCD
+//! (a decimal digit per nibble) skips the conversion entirely: [`bcd_add`]
+//! it up, and print it with [`u16_hex`].
+
+use core::str;
+
+const POWERS_U8: [u16; 3] = [100, 10, 1];
+const POWERS_U16: [u16; 5] = [10000, 1000, 100, 10, 1];
+
+/// Write a digit of `n` per power into `out`, most significant first
+#[inline(always)]
+fn digits(mut n: u16, powers: &[u16], out: &mut [u8]) {
+    for (digit, &power) in out.iter_mut().zip(powers) {
+        *digit = b'0';
+        while n >= power {
+            n -= power;
+            *digit += 1;
+        }
+    }
+}
+
+/// `digits` with the leading zeros cut off, leaving at least one
+fn trimmed(digits: &[u8]) -> &str {
+    let start = digits.iter().position(|&d| d != b'0').unwrap_or(digits.len() - 1);
+    ascii(&digits[start..])
+}
+
+#[inline(always)]
+fn ascii(bytes: &[u8]) -> &str {
+    // only ever digits and letters from here
+    unsafe { str::from_utf8_unchecked(bytes) }
+}
+
+/// `n` in decimal, with no leading zeros
+pub fn u8_dec(n: u8, buf: &mut [u8; 3]) -> &str {
+    digits(n as u16, &POWERS_U8, buf);
+    trimmed(buf)
+}
+
+/// `n` in decimal, with no leading zeros
+pub fn u16_dec(n: u16, buf: &mut [u8; 5]) -> &str {
+    digits(n, &POWERS_U16, buf);
+    trimmed(buf)
+}
+
+/// `n` in decimal, with a `-` in front if it's negative and no leading zeros
+pub fn i16_dec(n: i16, buf: &mut [u8; 6]) -> &str {
+    let mut all = [0; 5];
+    let len = u16_dec(n.unsigned_abs(), &mut all).len();
+    buf[1..=len].copy_from_slice(&all[all.len() - len..]);
+    if n < 0 {
+        buf[0] = b'-';
+        ascii(&buf[..=len])
+    } else {
+        ascii(&buf[1..=len])
+    }
+}
+
+/// `n` in decimal, padded with zeros to fill all of `buf`, for counters that
+/// shouldn't shift around as they grow. If `n` has more digits than that,
+/// only the last ones fit.
+pub fn u16_dec_zeroed(n: u16, buf: &mut [u8]) -> &str {
+    let mut all = [0; 5];
+    digits(n, &POWERS_U16, &mut all);
+    let len = buf.len();
+    let kept = len.min(all.len());
+    buf[..len - kept].fill(b'0');
+    buf[len - kept..].copy_from_slice(&all[all.len() - kept..]);
+    ascii(buf)
+}
+
+/// The hex digit for the low nibble of `n`
+#[inline(always)]
+const fn hex_digit(n: u8) -> u8 {
+    let n = n & 0xF;
+    if n < 10 { b'0' + n } else { b'A' + n - 10 }
+}
+
+/// `n` as two hex digits, uppercase
+pub fn u8_hex(n: u8, buf: &mut [u8; 2]) -> &str {
+    *buf = [hex_digit(n >> 4), hex_digit(n)];
+    ascii(buf)
+}
+
+/// `n` as four hex digits, uppercase
+pub fn u16_hex(n: u16, buf: &mut [u8; 4]) -> &str {
+    let [high, low] = n.to_be_bytes();
+    *buf = [hex_digit(high >> 4), hex_digit(high), hex_digit(low >> 4), hex_digit(low)];
+    ascii(buf)
+}
+
+/// `n` (0-9999) in BCD. Above that, only the last four digits are kept.
+pub fn to_bcd(n: u16) -> u16 {
+    let mut all = [0; 5];
+    digits(n, &POWERS_U16, &mut all);
+    all[1..].iter().fold(0, |bcd, &digit| (bcd << 4) | (digit - b'0') as u16)
+}
+
+/// The number in BCD `bcd`
+pub const fn from_bcd(bcd: u16) -> u16 {
+    let mut n = 0;
+    let mut shift = 12;
+    loop {
+        n = n * 10 + ((bcd >> shift) & 0xF);
+        if shift == 0 {
+            return n;
+        }
+        shift -= 4;
+    }
+}
+
+/// `a + b` in BCD, stopping at 9999 instead of wrapping
+pub const fn bcd_add(a: u16, b: u16) -> u16 {
+    let mut sum = 0;
+    let mut carry = 0;
+    let mut shift = 0;
+    while shift < 16 {
+        let mut digit = ((a >> shift) & 0xF) + ((b >> shift) & 0xF) + carry;
+        carry = (digit >= 10) as u16;
+        if carry == 1 {
+            digit -= 10;
+        }
+        sum |= digit << shift;
+        shift += 4;
+    }
+    if carry == 1 { 0x9999 } else { sum }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal() {
+        assert_eq!(u8_dec(0, &mut [0; 3]), "0");
+        assert_eq!(u8_dec(7, &mut [0; 3]), "7");
+        assert_eq!(u8_dec(100, &mut [0; 3]), "100");
+        assert_eq!(u8_dec(u8::MAX, &mut [0; 3]), "255");
+        assert_eq!(u16_dec(0, &mut [0; 5]), "0");
+        assert_eq!(u16_dec(1000, &mut [0; 5]), "1000");
+        assert_eq!(u16_dec(u16::MAX, &mut [0; 5]), "65535");
+    }
+
+    #[test]
+    fn signed_decimal() {
+        assert_eq!(i16_dec(0, &mut [0; 6]), "0");
+        assert_eq!(i16_dec(-1, &mut [0; 6]), "-1");
+        assert_eq!(i16_dec(-250, &mut [0; 6]), "-250");
+        assert_eq!(i16_dec(i16::MAX, &mut [0; 6]), "32767");
+        assert_eq!(i16_dec(i16::MIN, &mut [0; 6]), "-32768");
+    }
+
+    #[test]
+    fn zero_padding() {
+        assert_eq!(u16_dec_zeroed(0, &mut [0; 5]), "00000");
+        assert_eq!(u16_dec_zeroed(42, &mut [0; 5]), "00042");
+        assert_eq!(u16_dec_zeroed(u16::MAX, &mut [0; 7]), "0065535");
+        assert_eq!(u16_dec_zeroed(12345, &mut [0; 3]), "345");
+        assert_eq!(u16_dec_zeroed(9, &mut [0; 1]), "9");
+        assert_eq!(u16_dec_zeroed(9, &mut []), "");
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(u8_hex(0, &mut [0; 2]), "00");
+        assert_eq!(u8_hex(0xA7, &mut [0; 2]), "A7");
+        assert_eq!(u8_hex(u8::MAX, &mut [0; 2]), "FF");
+        assert_eq!(u16_hex(0, &mut [0; 4]), "0000");
+        assert_eq!(u16_hex(0xBEEF, &mut [0; 4]), "BEEF");
+        assert_eq!(u16_hex(u16::MAX, &mut [0; 4]), "FFFF");
+    }
+
+    #[test]
+    fn bcd() {
+        assert_eq!(to_bcd(0), 0);
+        assert_eq!(to_bcd(1234), 0x1234);
+        assert_eq!(to_bcd(9999), 0x9999);
+        assert_eq!(to_bcd(u16::MAX), 0x5535);
+        assert_eq!(from_bcd(0), 0);
+        assert_eq!(from_bcd(0x0907), 907);
+        assert_eq!(from_bcd(0x9999), 9999);
+    }
+
+    #[test]
+    fn bcd_addition() {
+        assert_eq!(bcd_add(0, 0), 0);
+        assert_eq!(bcd_add(0x0199, 0x0001), 0x0200);
+        assert_eq!(bcd_add(0x4567, 0x4433), 0x9000);
+        assert_eq!(bcd_add(0x9999, 0x0001), 0x9999);
+        assert_eq!(bcd_add(0x9999, 0x9999), 0x9999);
+    }
+}
@@ -0,0 +1,136 @@
+//! # Frame Timing
+//!
+//! [`boot::wait`](crate::boot::wait) sleeps until the next interrupt, which
+//! is usually vblank but can also be the blitter finishing. A [`FrameClock`]
+//! waits for vblank proper, counts frames, and notices when the game ran
+//! long and missed some:
+//!
+//! ```ignore
+//! let mut clock = FrameClock::new();
+//! loop {
+//!     clock.wait();
+//!
+//!     // physics at the full 60 Hz, however many frames went by
+//!     for _ in 0..clock.delta() {
+//!         step_physics();
+//!     }
+//!
+//!     // enemies think at 20 Hz
+//!     clock.run_at_hz(20, || enemies.think());
+//!
+//!     // blink once a second
+//!     let cursor_on = clock.count() % 60 < 30;
+//!
+//!     if clock.lagged() {
+//!         // drop some particles
+//!     }
+//! }
+//! ```
+//!
+//! The vblank handler counts in a single byte, so one frame that runs more
+//! than 255 vblanks long loses track of the time.
+
+use crate::boot::{VBLANK_COUNT, wait};
+
+/// vblanks a second
+pub const FRAME_RATE: u8 = 60;
+
+#[inline(always)]
+fn vblanks() -> u8 {
+    unsafe { core::ptr::read_volatile(&raw const VBLANK_COUNT) }
+}
+
+/// Counts frames and missed vblanks
+pub struct FrameClock {
+    /// Frames since the clock started
+    count: u32,
+    /// The vblank handler's count as of the last `wait`
+    last: u8,
+    /// Frames the last `wait` covered; 1 unless the game lagged
+    delta: u8,
+    /// vblanks missed since the clock started
+    missed: u32,
+}
+
+impl FrameClock {
+    /// Start counting from the current frame
+    pub fn new() -> Self {
+        Self { count: 0, last: vblanks(), delta: 0, missed: 0 }
+    }
+
+    /// Sleep until the next vblank, returning how many frames passed since
+    /// the last `wait`. Anything over 1 means the frame ran long.
+    pub fn wait(&mut self) -> u8 {
+        let before = vblanks();
+        // other interrupts wake the CPU too, so sleep until the count moves
+        while vblanks() == before {
+            unsafe { wait(); }
+        }
+
+        let now = vblanks();
+        self.delta = now.wrapping_sub(self.last);
+        self.last = now;
+        self.count += self.delta as u32;
+        self.missed += self.delta.saturating_sub(1) as u32;
+        self.delta
+    }
+
+    /// Frames counted so far
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Frames the last `wait` covered
+    #[inline]
+    pub fn delta(&self) -> u8 {
+        self.delta
+    }
+
+    /// Whether the last frame ran over and missed a vblank
+    #[inline]
+    pub fn lagged(&self) -> bool {
+        self.delta > 1
+    }
+
+    /// vblanks missed since the clock started
+    #[inline]
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    /// Frames since `mark`, an earlier [`count`](Self::count)
+    #[inline]
+    pub fn since(&self, mark: u32) -> u32 {
+        self.count.wrapping_sub(mark)
+    }
+
+    /// Whether this is one of every `n` frames
+    #[inline]
+    pub fn every(&self, n: u8) -> bool {
+        self.count.is_multiple_of(n.max(1) as u32)
+    }
+
+    /// Call `f` `hz` times a second, spread evenly over the frames: at
+    /// 30 Hz, every other frame; at 24, twice in five. After a long frame
+    /// it catches up, calling `f` once for each tick it skipped over.
+    /// `hz` over 60 is treated as 60.
+    pub fn run_at_hz(&self, hz: u8, mut f: impl FnMut()) {
+        let (hz, rate) = (hz.min(FRAME_RATE) as u16, FRAME_RATE as u16);
+        let start = self.count.wrapping_sub(self.delta as u32);
+        for frame in 0..self.delta as u32 {
+            // where this frame falls in the second; a tick lands wherever
+            // `phase * hz / 60` rolls over to the next whole number
+            let phase = (start.wrapping_add(frame) % rate as u32) as u16;
+            if (phase + 1) * hz / rate != phase * hz / rate {
+                f();
+            }
+        }
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,130 @@
+//! # Heap
+//!
+//! With the `alloc` feature, `Vec`, `Box` and friends from the `alloc` crate
+//! work, out of a fixed arena the game sets aside. [`BumpAlloc`] hands out
+//! memory by counting up through the arena and never really frees it: it's
+//! all given back at once with [`reset`](BumpAlloc::reset), between levels
+//! or at the top of a frame.
+//!
+//! ```ignore
+//! extern crate alloc;
+//! use alloc::vec::Vec;
+//! use gametank::heap::BumpAlloc;
+//!
+//! #[global_allocator]
+//! static HEAP: BumpAlloc<2048> = BumpAlloc::new();
+//!
+//! fn load_level(level: &LevelData) -> Vec<Enemy> {
+//!     // everything from the last level is gone by now
+//!     unsafe { HEAP.reset() };
+//!     level.spawns.iter().map(Enemy::spawn).collect()
+//! }
+//! ```
+//!
+//! The arena is a plain static, so it comes out of the same 8KB as
+//! everything else; keep it small. Freeing or growing the most recent
+//! allocation is done in place, so a single `Vec` that's pushed to can grow
+//! without leaving copies of itself behind. Anything else that's freed stays
+//! used until the next reset. Running out panics.
+
+use core::{alloc::{GlobalAlloc, Layout}, cell::{Cell, UnsafeCell}, ptr};
+
+use crate::sync::critical_section;
+
+/// A bump allocator over a `SIZE` byte arena; see the [module docs](self)
+pub struct BumpAlloc<const SIZE: usize> {
+    arena: UnsafeCell<[u8; SIZE]>,
+    /// Offset of the first free byte
+    next: Cell<usize>,
+}
+
+// Only touched inside critical sections, and there's only the one CPU
+unsafe impl<const SIZE: usize> Sync for BumpAlloc<SIZE> {}
+
+impl<const SIZE: usize> BumpAlloc<SIZE> {
+    pub const fn new() -> Self {
+        Self { arena: UnsafeCell::new([0; SIZE]), next: Cell::new(0) }
+    }
+
+    /// Bytes handed out since the last reset, counting alignment padding
+    /// and anything freed out of order
+    pub fn used(&self) -> usize {
+        critical_section(|| self.next.get())
+    }
+
+    /// Bytes left in the arena
+    pub fn remaining(&self) -> usize {
+        SIZE - self.used()
+    }
+
+    /// Give the whole arena back.
+    ///
+    /// # Safety
+    ///
+    /// Everything allocated since the last reset has to be gone already
+    /// (dropped or forgotten), since its memory gets handed out again.
+    pub unsafe fn reset(&self) {
+        critical_section(|| self.next.set(0));
+    }
+
+    fn base(&self) -> usize {
+        self.arena.get() as usize
+    }
+}
+
+impl<const SIZE: usize> Default for BumpAlloc<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const SIZE: usize> GlobalAlloc for BumpAlloc<SIZE> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        critical_section(|| {
+            let base = self.base();
+            let start = (base + self.next.get()).next_multiple_of(layout.align()) - base;
+            match start.checked_add(layout.size()) {
+                Some(end) if end <= SIZE => {
+                    self.next.set(end);
+                    (base + start) as *mut u8
+                }
+                _ => ptr::null_mut(),
+            }
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        critical_section(|| {
+            // only the last allocation can be given back early
+            let start = ptr as usize - self.base();
+            if start + layout.size() == self.next.get() {
+                self.next.set(start);
+            }
+        });
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let start = ptr as usize - self.base();
+        let in_place = critical_section(|| {
+            let last = start + layout.size() == self.next.get();
+            let fits = start.checked_add(new_size).is_some_and(|end| end <= SIZE);
+            if last && fits {
+                self.next.set(start + new_size);
+            }
+            last && fits
+        });
+        if in_place {
+            return ptr;
+        }
+
+        let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+        let new_ptr = unsafe { self.alloc(new_layout) };
+        if !new_ptr.is_null() {
+            unsafe {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+        }
+        new_ptr
+    }
+}
@@ -1,3 +1,28 @@
+//! # Controller Input
+//!
+//! Genesis-style gamepads on the two controller ports. Poll both once a frame
+//! and ask about buttons as often as you like; every question that frame gets
+//! the same answer:
+//!
+//! ```ignore
+//! let mut pads = console.gamepads();
+//! loop {
+//!     unsafe { wait(); }
+//!     pads.poll();
+//!
+//!     if pads.just_pressed(Player::One, Buttons::A) {
+//!         jump();
+//!     }
+//!     if pads.pressed(Player::Two, Buttons::Left) {
+//!         p2.x -= 1;
+//!     }
+//! }
+//! ```
+//!
+//! Releases are debounced: a button has to read as up on two polls in a row
+//! before it counts as released, so a bouncing contact doesn't turn into a
+//! second `just_pressed`. Presses still show up on the first poll.
+
 use bit_field::BitField;
 
 const GPR1: *const u8 = 0x2008 as *const u8;
@@ -52,6 +77,8 @@ impl Buttons {
 pub struct GenesisGamepad<const PORT: u8> {
     pub buttons: u8,
     pub buttons_last: u8,
+    /// What the port read last time, before debouncing
+    raw_last: u8,
 }
 
 impl<const PORT: u8> GenesisGamepad<PORT> {
@@ -59,8 +86,18 @@ impl<const PORT: u8> GenesisGamepad<PORT> {
         Self {
             buttons: 0,
             buttons_last: 0,
+            raw_last: 0,
         }
     }
+
+    /// Take a fresh read of the port.
+    /// A button stays down until it's read as up twice in a row.
+    #[inline(always)]
+    fn latch(&mut self, raw: u8) {
+        self.buttons_last = self.buttons;
+        self.buttons = raw | (self.buttons & self.raw_last);
+        self.raw_last = raw;
+    }
 }
 
 impl GenesisGamepad<1> {
@@ -72,9 +109,8 @@ impl GenesisGamepad<1> {
         let byte0 = read_gpr1();
         let byte1 = read_gpr1();
 
-        self.buttons_last = self.buttons;
         // bits: start, a | c, b, up, down, left, right
-        self.buttons = ((!byte0 << 2) & 0b1100_0000) | (!byte1 & 0b0011_1111);
+        self.latch(((!byte0 << 2) & 0b1100_0000) | (!byte1 & 0b0011_1111));
     }
 }
 
@@ -87,9 +123,8 @@ impl GenesisGamepad<2> {
         let byte0 = read_gpr2();
         let byte1 = read_gpr2();
 
-        self.buttons_last = self.buttons;
         // bits: start, a | c, b, up, down, left, right
-        self.buttons = ((!byte0 << 2) & 0b1100_0000) | (!byte1 & 0b0011_1111);
+        self.latch(((!byte0 << 2) & 0b1100_0000) | (!byte1 & 0b0011_1111));
     }
 }
 
@@ -116,3 +151,67 @@ impl<const PORT: u8> GenesisGamepad<PORT> {
         !self.is_pressed(button) && self.was_pressed(button)
     }
 }
+
+/// Which controller port
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Player {
+    One,
+    Two,
+}
+
+/// Both gamepads, read together once a frame with [`poll`](Self::poll)
+pub struct Gamepads {
+    pub p1: GenesisGamepad<1>,
+    pub p2: GenesisGamepad<2>,
+}
+
+impl Gamepads {
+    pub const fn new() -> Self {
+        Self {
+            p1: GenesisGamepad::new(),
+            p2: GenesisGamepad::new(),
+        }
+    }
+
+    /// Read both ports. Call this once a frame, after `wait()`; calling it
+    /// twice in a frame makes that frame's presses look held.
+    #[inline(always)]
+    pub fn poll(&mut self) {
+        self.p1.read();
+        self.p2.read();
+    }
+
+    #[inline]
+    fn buttons(&self, player: Player) -> (u8, u8) {
+        match player {
+            Player::One => (self.p1.buttons, self.p1.buttons_last),
+            Player::Two => (self.p2.buttons, self.p2.buttons_last),
+        }
+    }
+
+    /// Whether the button is held down
+    #[inline]
+    pub fn pressed(&self, player: Player, button: Buttons) -> bool {
+        self.buttons(player).0.get_bit(button.idx())
+    }
+
+    /// Whether the button went down since the last poll
+    #[inline]
+    pub fn just_pressed(&self, player: Player, button: Buttons) -> bool {
+        let (now, last) = self.buttons(player);
+        now.get_bit(button.idx()) && !last.get_bit(button.idx())
+    }
+
+    /// Whether the button came up since the last poll
+    #[inline]
+    pub fn just_released(&self, player: Player, button: Buttons) -> bool {
+        let (now, last) = self.buttons(player);
+        !now.get_bit(button.idx()) && last.get_bit(button.idx())
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -7,14 +7,18 @@
 //!
 //! ## Quick Start
 //!
-//! Every GameTank program starts with a `main` function that receives a [`Console`](scr::Console):
+//! Every GameTank program starts with a function marked `#[gt_main]` (from
+//! `gametank-asset-macros`) that receives a [`Console`](console::Console):
 //!
 //! ```ignore
-//! use rom::sdk::{scr::Console, via::Via};
-//! use rom::boot::wait;
+//! #![no_std]
+//! #![no_main]
 //!
-//! #[unsafe(no_mangle)]
-//! fn main(mut console: Console) {
+//! use gametank::{boot::wait, console::Console, via::Via};
+//! use gametank_asset_macros::gt_main;
+//!
+//! #[gt_main]
+//! fn main(console: &mut Console) {
 //!     let via = unsafe { Via::new() };
 //!     
 //!     loop {
@@ -79,24 +83,34 @@
 //! blitter.wait_blit();
 //! ```
 //!
+//! ## Input
+//!
+//! Poll the gamepads once a frame, then ask about buttons; see [`input`]:
+//!
+//! ```ignore
+//! let mut pads = console.gamepads();
+//! pads.poll();
+//! if pads.just_pressed(Player::One, Buttons::Start) {
+//!     paused = !paused;
+//! }
+//! ```
+//!
 //! ## Colors
 //!
-//! Colors are 8-bit HSL: `0bHHH_SS_LLL` (Hue, Saturation, Luminosity)
+//! Colors are 8-bit HSL: `0bHHH_SS_LLL` (Hue, Saturation, Luminosity).
+//! [`color`] names the common ones and builds the rest:
 //!
 //! ```ignore
-//! // Common colors
-//! const BLACK: u8  = 0b000_00_000;
-//! const WHITE: u8  = 0b000_00_111;
-//! const RED: u8    = 0b010_11_100;
-//! const GREEN: u8  = 0b111_11_100;
-//! const BLUE: u8   = 0b101_11_100;
-//! const YELLOW: u8 = 0b000_11_100;
+//! use gametank::color::{self, hsl, hue};
 //!
 //! // Hues: 0=Yellow, 1=Orange, 2=Red, 3=Magenta, 4=Violet, 5=Blue, 6=Cyan, 7=Green
 //! // Saturation 0 = grayscale
+//! let dusk = hsl(hue::VIOLET, 2, 3);
 //!
-//! // IMPORTANT: Invert colors when drawing!
-//! blitter.draw_square(&mut console.sc, x, y, w, h, !RED);
+//! // The blitter's fill color is inverted: draw_rect does that for you,
+//! // draw_square doesn't
+//! blitter.draw_rect(x, y, w, h, color::RED);
+//! blitter.draw_square(x, y, w, h, !dusk);
 //! ```
 //!
 //! ## Loading Sprites
@@ -114,6 +128,9 @@
 //! sprite_mem.bytes()[..SPRITES.len()].copy_from_slice(SPRITES);
 //! ```
 //!
+//! To have the SDK keep track of which page and quadrant each sprite went
+//! in, load them with a [`SpriteRam`](vram::SpriteRam) instead.
+//!
 //! ## Audio
 //!
 //! The GameTank has a dedicated audio coprocessor. Initialize it with firmware:
@@ -138,8 +155,8 @@
 //! For large games, store assets in ROM banks and switch as needed:
 //!
 //! ```ignore
-//! // Place data in a specific bank
-//! #[unsafe(link_section = ".rodata.bank10")]
+//! // Place data in a specific bank (`#[bank]` is from gametank-asset-macros)
+//! #[bank(10)]
 //! static LEVEL_DATA: [u8; 8192] = [...];
 //!
 //! // Switch to that bank before accessing
@@ -148,6 +165,19 @@
 //! // Now LEVEL_DATA is accessible at its address
 //! ```
 //!
+//! With more than a few assets, list them in `assets.toml` instead and let
+//! `gtrom` place them; see [`assets`].
+//!
+//! Banks can also hold save data, written at runtime; see [`save`]. For
+//! more RAM, there are three more 8KB banks besides the one the program runs
+//! in; see [`ram`].
+//!
+//! ## Allocation
+//!
+//! There's no heap by default. The `alloc` feature adds a bump allocator
+//! for `Vec` and `Box`, over an arena the game picks the size of; see
+//! `heap`.
+//!
 //! ## Hardware Overview
 //!
 //! | Feature | Spec |
@@ -160,12 +190,34 @@
 //! | ROM | 2MB (128 × 16KB banks) |
 //! | Audio | 6502 coprocessor, 8-bit DAC, ~14kHz |
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod blitter;
 pub mod scr;
+pub mod color;
+pub mod screen;
 pub mod via;
 pub mod video_dma;
 pub mod audio;
 pub mod boot;
+pub mod crash;
+pub mod debug;
+pub mod sync;
+pub mod save;
+pub mod ram;
+#[cfg(feature = "alloc")]
+pub mod heap;
+pub mod frame;
+pub mod rand;
+pub mod collision;
 pub mod input;
 pub mod console;
+pub mod assets;
+pub mod tilemap;
+pub mod camera;
+pub mod text;
+pub mod fmt;
+pub mod sprite;
+pub mod vram;
 
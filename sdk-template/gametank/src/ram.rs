@@ -0,0 +1,191 @@
+//! # RAM Banks
+//!
+//! The console has 32KB of RAM in four 8KB banks, but only one is mapped at
+//! a time, at `$0000-$1FFF`. The program runs in bank 0; banks 1-3 are
+//! extra room for things that don't need to be touched every frame, like
+//! level state, inventories or decompressed maps.
+//!
+//! Switching banks swaps out *everything* below `$2000`: zero page, the
+//! stack and every static. Rust code can't keep running with another bank
+//! mapped, so there's no plain "switch bank" here. Instead, bytes are copied
+//! in and out through a couple of small assembly routines (in the template's
+//! `src/asm/ram-banks.asm`) that switch, touch one byte, and switch back:
+//!
+//! ```ignore
+//! // at startup
+//! let mut far = FarRam::new();
+//! let mut level = far.alloc(console, RamBank::One, Level::new()).unwrap();
+//!
+//! // later
+//! level.update(console, |level| level.doors_open += 1);
+//! let doors = level.get(console).doors_open;
+//! ```
+//!
+//! Every byte costs a round trip, around 60 cycles, so copy what's needed
+//! rather than the whole thing where it's big.
+//!
+//! The vblank NMI and IRQs are dispatched through handlers and pointers in
+//! bank 0, which aren't there while a far bank is mapped; an interrupt
+//! landing then would jump through whatever that bank holds instead. So
+//! like [`save`](crate::save), copies mask IRQs in a
+//! [`critical_section`] and turn the vblank NMI off, putting both back once
+//! the last byte is through. A vblank that comes along during a copy isn't
+//! counted, so a long one can make
+//! [`FrameClock`](crate::frame::FrameClock) fall a frame behind.
+//!
+//! In banks 1-3, [`FAR_START`] up to `$2000` is free to use: the zero page
+//! and stack page are left alone, since the routines above scribble on
+//! them.
+
+use core::{marker::PhantomData, mem::{MaybeUninit, size_of}};
+
+use crate::{console::Console, scr::VideoFlags, sync::critical_section};
+
+unsafe extern "C" {
+    fn ram_bank_peek(far: u8, home: u8, address: *const u8) -> u8;
+    fn ram_bank_poke(far: u8, home: u8, address: *mut u8, value: u8);
+}
+
+/// Where the free part of a far bank starts, above the zero page and stack
+pub const FAR_START: u16 = 0x0200;
+/// Where RAM ends and the console's registers start
+pub const FAR_END: u16 = 0x2000;
+
+/// One of the RAM banks the program isn't running in
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RamBank {
+    One = 1,
+    Two = 2,
+    Three = 3,
+}
+
+/// Run `f` with no interrupts able to land, since their handlers are in bank 0
+fn without_interrupts(console: &mut Console, f: impl FnOnce()) {
+    let video_flags = console.video_flags;
+    console.video_flags.remove(VideoFlags::DMA_NMI);
+    console.write_video_flags();
+
+    critical_section(f);
+
+    console.video_flags = video_flags;
+    console.write_video_flags();
+}
+
+/// Copy `out.len()` bytes from `address` in `bank` into `out`
+pub fn read(console: &mut Console, bank: RamBank, address: u16, out: &mut [u8]) {
+    let home = console.bank_flags;
+    let far = home.with_ram_bank(bank as u8);
+    without_interrupts(console, || {
+        for (i, byte) in out.iter_mut().enumerate() {
+            let at = address.wrapping_add(i as u16) as *const u8;
+            *byte = unsafe { ram_bank_peek(far.bits(), home.bits(), at) };
+        }
+    });
+}
+
+/// Copy `data` to `address` in `bank`
+pub fn write(console: &mut Console, bank: RamBank, address: u16, data: &[u8]) {
+    let home = console.bank_flags;
+    let far = home.with_ram_bank(bank as u8);
+    without_interrupts(console, || {
+        for (i, &byte) in data.iter().enumerate() {
+            let at = address.wrapping_add(i as u16) as *mut u8;
+            unsafe { ram_bank_poke(far.bits(), home.bits(), at, byte) };
+        }
+    });
+}
+
+/// A `T` kept in a far RAM bank. It can't be borrowed, since its bank is
+/// never mapped while Rust code runs; it's copied out and back instead.
+///
+/// `T` should be plain data: no references, since whatever they point at
+/// in bank 0 could be gone by the time it's copied back.
+pub struct BankedBox<T: Copy> {
+    bank: RamBank,
+    address: u16,
+    _t: PhantomData<T>,
+}
+
+impl<T: Copy> BankedBox<T> {
+    /// A box over whatever's at `address` in `bank`.
+    ///
+    /// # Safety
+    ///
+    /// A `T` has to have been written there, and nothing else can overlap
+    /// it; a [`FarRam`] keeps track of that.
+    pub const unsafe fn from_raw(bank: RamBank, address: u16) -> Self {
+        Self { bank, address, _t: PhantomData }
+    }
+
+    pub fn bank(&self) -> RamBank {
+        self.bank
+    }
+
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// A copy of the value
+    pub fn get(&self, console: &mut Console) -> T {
+        let mut value = MaybeUninit::<T>::uninit();
+        let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        read(console, self.bank, self.address, bytes);
+        unsafe { value.assume_init() }
+    }
+
+    /// Replace the value
+    pub fn set(&mut self, console: &mut Console, value: T) {
+        let bytes = unsafe { core::slice::from_raw_parts(&value as *const T as *const u8, size_of::<T>()) };
+        write(console, self.bank, self.address, bytes);
+    }
+
+    /// Copy the value out, change it with `f` and copy it back
+    pub fn update<R>(&mut self, console: &mut Console, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut value = self.get(console);
+        let result = f(&mut value);
+        self.set(console, value);
+        result
+    }
+}
+
+/// Hands out space in the far banks for [`BankedBox`]es, counting up from
+/// [`FAR_START`]. Nothing's freed one at a time; [`clear`](Self::clear)
+/// starts a bank over.
+#[derive(Debug, Clone)]
+pub struct FarRam {
+    /// The next free address in banks 1-3
+    next: [u16; 3],
+}
+
+impl FarRam {
+    pub const fn new() -> Self {
+        Self { next: [FAR_START; 3] }
+    }
+
+    /// Put `value` in `bank`, or `None` if there's no room left there
+    pub fn alloc<T: Copy>(&mut self, console: &mut Console, bank: RamBank, value: T) -> Option<BankedBox<T>> {
+        let next = &mut self.next[bank as usize - 1];
+        let end = next.checked_add(size_of::<T>() as u16).filter(|&end| end <= FAR_END)?;
+        let mut boxed = unsafe { BankedBox::from_raw(bank, *next) };
+        *next = end;
+        boxed.set(console, value);
+        Some(boxed)
+    }
+
+    /// Bytes still free in `bank`
+    pub fn free(&self, bank: RamBank) -> u16 {
+        FAR_END - self.next[bank as usize - 1]
+    }
+
+    /// Forget everything in `bank`. Boxes already handed out from it will
+    /// overlap the next ones.
+    pub fn clear(&mut self, bank: RamBank) {
+        self.next[bank as usize - 1] = FAR_START;
+    }
+}
+
+impl Default for FarRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
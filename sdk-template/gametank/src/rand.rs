@@ -0,0 +1,170 @@
+//! # Random Numbers
+//!
+//! A 16-bit xorshift generator: a handful of shifts per number, which the
+//! 6502 can afford every frame. It isn't remotely cryptographic, but it
+//! doesn't need to be.
+//!
+//! The console has no source of randomness, so the same seed always gives
+//! the same numbers. Seed from something the player does, like how many
+//! frames they sat on the title screen:
+//!
+//! ```ignore
+//! let mut clock = FrameClock::new();
+//! let mut pads = console.gamepads();
+//! loop {
+//!     clock.wait();
+//!     pads.poll();
+//!     if pads.just_pressed(Player::One, Buttons::Start) {
+//!         break;
+//!     }
+//! }
+//! let mut rng = Rng::from_entropy(&clock, &pads);
+//!
+//! let x = rng.range(8, 120);
+//! if rng.chance(25) {
+//!     drop_item(x);
+//! }
+//! ```
+
+use crate::{frame::FrameClock, input::Gamepads};
+
+/// xorshift16, with the 7, 9, 8 shifts: every state but 0, in a 65535 long cycle
+#[derive(Debug, Copy, Clone)]
+pub struct Rng {
+    state: u16,
+}
+
+impl Rng {
+    /// A generator that always starts the same way from the same seed
+    pub const fn new(seed: u16) -> Self {
+        // zero is the one state xorshift can't leave
+        Self { state: if seed == 0 { 0xACE1 } else { seed } }
+    }
+
+    /// Seeded from the frame count and whatever buttons are down
+    pub fn from_entropy(clock: &FrameClock, pads: &Gamepads) -> Self {
+        let count = clock.count();
+        let mut rng = Self::new(count as u16 ^ (count >> 16) as u16);
+        rng.stir(pads.p1.buttons);
+        rng.stir(pads.p2.buttons);
+        rng
+    }
+
+    /// Mix a byte of entropy into the state, like a frame count when the
+    /// player presses something
+    pub fn stir(&mut self, byte: u8) {
+        self.state ^= (byte as u16) << 8 | byte as u16;
+        if self.state == 0 {
+            self.state = 0xACE1;
+        }
+        self.rand_u16();
+    }
+
+    pub fn rand_u16(&mut self) -> u16 {
+        let mut x = self.state;
+        x ^= x << 7;
+        x ^= x >> 9;
+        x ^= x << 8;
+        self.state = x;
+        x
+    }
+
+    /// The high byte; xorshift's low bits are the weaker ones
+    pub fn rand_u8(&mut self) -> u8 {
+        (self.rand_u16() >> 8) as u8
+    }
+
+    pub fn rand_bool(&mut self) -> bool {
+        self.rand_u8() & 0x80 != 0
+    }
+
+    /// A number from `lo` up to but not including `hi`, or `lo` if the range
+    /// is empty. Scaled rather than taken modulo, so there's no division;
+    /// when the range doesn't divide 256, some numbers come up a little more
+    /// often than others.
+    pub fn range(&mut self, lo: u8, hi: u8) -> u8 {
+        if hi <= lo {
+            return lo;
+        }
+        let span = (hi - lo) as u16;
+        lo + ((self.rand_u8() as u16 * span) >> 8) as u8
+    }
+
+    /// True `percent` times out of 100
+    pub fn chance(&mut self, percent: u8) -> bool {
+        self.range(0, 100) < percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xorshift_sequence() {
+        let mut rng = Rng::new(1);
+        assert_eq!([rng.rand_u16(), rng.rand_u16(), rng.rand_u16(), rng.rand_u16()], [0x8181, 0x6021, 0xE999, 0x2E0B]);
+    }
+
+    #[test]
+    fn zero_seed_is_replaced() {
+        let mut zero = Rng::new(0);
+        let mut fallback = Rng::new(0xACE1);
+        assert_eq!(zero.rand_u16(), 0xD30F);
+        assert_eq!(fallback.rand_u16(), 0xD30F);
+    }
+
+    #[test]
+    fn full_period() {
+        let mut rng = Rng::new(1);
+        for _ in 0..65534 {
+            assert_ne!(rng.rand_u16(), 1);
+        }
+        assert_eq!(rng.rand_u16(), 1);
+    }
+
+    #[test]
+    fn stir_never_zeroes_the_state() {
+        // 0x8181 ^ 0x8181 would leave xorshift stuck at 0
+        let mut rng = Rng::new(0x8181);
+        rng.stir(0x81);
+        assert_ne!(rng.rand_u16(), 0);
+    }
+
+    #[test]
+    fn range_stays_in_bounds() {
+        let mut rng = Rng::new(1234);
+        for _ in 0..1000 {
+            assert!((8..120).contains(&rng.range(8, 120)));
+            assert!(rng.range(0, 255) < 255);
+            assert_eq!(rng.range(10, 11), 10);
+        }
+    }
+
+    #[test]
+    fn range_reaches_both_ends() {
+        let mut rng = Rng::new(1);
+        let mut seen = [false; 4];
+        for _ in 0..1000 {
+            seen[rng.range(0, 4) as usize] = true;
+        }
+        assert_eq!(seen, [true; 4]);
+    }
+
+    #[test]
+    fn empty_range_gives_lo() {
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.range(0, 0), 0);
+        assert_eq!(rng.range(255, 255), 255);
+        assert_eq!(rng.range(200, 100), 200);
+    }
+
+    #[test]
+    fn chance_edges() {
+        let mut rng = Rng::new(99);
+        for _ in 0..1000 {
+            assert!(!rng.chance(0));
+            assert!(rng.chance(100));
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! # Save Games
+//!
+//! Keeps save data in the cartridge's flash, written with the same command
+//! sequences the flash chip (and gte) understands: byte program, and erase.
+//!
+//! ```ignore
+//! // high scores, 32 bytes
+//! static SAVES: SaveStore<32> = SaveStore::new(14, 16);
+//!
+//! let mut scores = [0u8; 32];
+//! if !SAVES.load(&mut scores) {
+//!     scores = DEFAULT_SCORES;
+//! }
+//! // ...
+//! SAVES.save(console, &scores);
+//! ```
+//!
+//! ## How it's stored
+//!
+//! Each bank is cut into slots of the save's size plus 5 bytes: a marker, a
+//! sequence number and a CRC. Every save goes in the next empty slot, so a
+//! bank takes many saves before it has to be erased, and `load` picks the
+//! newest slot whose CRC checks out. A save that was cut off by the power
+//! going out just doesn't count, and the one before it loads instead.
+//!
+//! When one bank fills up, the other is erased and saving carries on there.
+//! The last save in the full bank stays until that one's erased in turn, so
+//! there's always a good copy somewhere.
+//!
+//! ## Choosing banks
+//!
+//! Flash erases in 64KB blocks, four banks at a time. Because of how the
+//! cartridge is wired, the four banks that go together aren't next to each
+//! other: [`erase_block_banks`] lists them. **Erasing a save bank wipes the
+//! other three banks in its block**, so keep all eight banks (four for each
+//! save bank) free of code and assets. Filling them with `#[bank(N)]` statics
+//! stops the linker from putting anything else there:
+//!
+//! ```ignore
+//! // erase_block_banks(14) == [14, 78, 46, 110]
+//! // erase_block_banks(16) == [16, 80, 48, 112]
+//! #[used]
+//! #[bank(78)]
+//! static RESERVED_78: [u8; 0x4000] = [0xFF; 0x4000];
+//! // ...and the same for the other seven; 0xFF is what erased flash reads as
+//! ```
+//!
+//! The fixed bank and the three banks next to it (31, 63 and 95) have
+//! smaller erase blocks and can't hold saves.
+//!
+//! ## While it's writing
+//!
+//! The flash can't be read while it's busy, and that includes running code
+//! from it, so the routines that talk to the chip are copied to RAM at boot.
+//! Interrupts are turned off for the duration: the vblank NMI is disabled
+//! (and turned back on afterwards), and IRQs are masked in a
+//! [`critical_section`].
+//! An erase takes around a second, and the screen holds still meanwhile.
+//! These need an optimized build of the SDK, so that nothing in them turns
+//! into a call back out to flash.
+
+use crate::{console::Console, scr::VideoFlags, sync::critical_section, via::far_call};
+
+/// Where the switchable bank shows up
+const WINDOW: u16 = 0x8000;
+const BANK_SIZE: u16 = 0x4000;
+/// The chip's command addresses, in the bank window
+const COMMAND_A: *mut u8 = (WINDOW + 0xAAA) as *mut u8;
+const COMMAND_B: *mut u8 = (WINDOW + 0x555) as *mut u8;
+
+/// First byte of a slot that was written all the way through
+const MARKER: u8 = 0x47;
+/// Marker and sequence number
+const HEADER: u16 = 3;
+const CRC_SIZE: u16 = 2;
+const ERASED: u8 = 0xFF;
+
+/// The cartridge reverses the order of the bank bits on the way to the flash
+const fn physical_bank(bank: u8) -> u8 {
+    let mut result = 0;
+    let mut i = 0;
+    while i < 7 {
+        result |= ((bank >> i) & 1) << (6 - i);
+        i += 1;
+    }
+    result
+}
+
+/// The four banks erased along with `bank`, including `bank` itself.
+/// Only meaningful for banks that can hold saves.
+pub const fn erase_block_banks(bank: u8) -> [u8; 4] {
+    // the mapping is its own inverse
+    let first = physical_bank(bank & 0x7F) & !3;
+    [physical_bank(first), physical_bank(first + 1), physical_bank(first + 2), physical_bank(first + 3)]
+}
+
+/// Whether `bank` is in one of the 64KB blocks; the last 64KB of flash is
+/// split into smaller blocks, with the fixed bank among them
+const fn in_full_block(bank: u8) -> bool {
+    bank < 0x80 && physical_bank(bank) < 124
+}
+
+/// Wait for a program or erase to finish, by reading back until the chip
+/// stops returning its status and returns `want`. Status bit 5 means the
+/// chip gave up.
+#[inline(always)]
+unsafe fn wait_done(address: *const u8, want: u8) -> bool {
+    loop {
+        let value = unsafe { address.read_volatile() };
+        if value == want {
+            return true;
+        }
+        if value & 0x20 != 0 {
+            return unsafe { address.read_volatile() } == want;
+        }
+    }
+}
+
+/// Program `len` bytes from `src` to `offset` in the current bank. Flash
+/// can only clear bits, so the bytes there should be erased first.
+#[unsafe(link_section = ".data.ram_text")]
+#[inline(never)]
+unsafe fn program(offset: u16, src: *const u8, len: u16) -> bool {
+    let mut i = 0;
+    while i < len {
+        unsafe {
+            // read it now, while the chip is still readable
+            let byte = src.add(i as usize).read_volatile();
+            let dst = (WINDOW + offset + i) as *mut u8;
+            COMMAND_A.write_volatile(0xAA);
+            COMMAND_B.write_volatile(0x55);
+            COMMAND_A.write_volatile(0xA0);
+            dst.write_volatile(byte);
+            if !wait_done(dst, byte) {
+                return false;
+            }
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Erase the block the current bank is in
+#[unsafe(link_section = ".data.ram_text")]
+#[inline(never)]
+unsafe fn erase() -> bool {
+    unsafe {
+        COMMAND_A.write_volatile(0xAA);
+        COMMAND_B.write_volatile(0x55);
+        COMMAND_A.write_volatile(0x80);
+        COMMAND_A.write_volatile(0xAA);
+        COMMAND_B.write_volatile(0x55);
+        (WINDOW as *mut u8).write_volatile(0x30);
+        wait_done(WINDOW as *const u8, ERASED)
+    }
+}
+
+/// CRC-16/CCITT over the sequence number and the save
+fn crc16(bytes: impl Iterator<Item = u8>) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Read a byte at `offset` in whatever bank is switched in
+#[inline(always)]
+fn peek(offset: u16) -> u8 {
+    unsafe { ((WINDOW + offset) as *const u8).read_volatile() }
+}
+
+/// A slot that holds a good save
+#[derive(Copy, Clone)]
+struct Slot {
+    bank: u8,
+    index: u16,
+    sequence: u16,
+}
+
+/// Saves of `LEN` bytes, rotated through two banks; see the [module docs](self)
+pub struct SaveStore<const LEN: usize> {
+    banks: [u8; 2],
+}
+
+impl<const LEN: usize> SaveStore<LEN> {
+    const SLOT: u16 = HEADER + LEN as u16 + CRC_SIZE;
+    const SLOTS: u16 = BANK_SIZE / Self::SLOT;
+
+    /// Saves kept in banks `a` and `b`, which have to be in different erase
+    /// blocks and outside the last 64KB. Checked at compile time when used
+    /// in a `static` or `const`.
+    pub const fn new(a: u8, b: u8) -> Self {
+        assert!(LEN > 0 && LEN <= (BANK_SIZE - HEADER - CRC_SIZE) as usize, "a save has to fit in a bank");
+        assert!(in_full_block(a) && in_full_block(b), "save banks can't be 31, 63, 95 or 127");
+        assert!(
+            physical_bank(a) & !3 != physical_bank(b) & !3,
+            "the two save banks are erased together; pick banks from different erase blocks"
+        );
+        Self { banks: [a, b] }
+    }
+
+    fn slot_offset(index: u16) -> u16 {
+        index * Self::SLOT
+    }
+
+    /// The sequence number in the slot, if it holds a complete save with a
+    /// good CRC. The bank has to be switched in.
+    fn check_slot(index: u16) -> Option<u16> {
+        let offset = Self::slot_offset(index);
+        if peek(offset) != MARKER {
+            return None;
+        }
+        let body = (offset + 1..offset + HEADER + LEN as u16).map(peek);
+        let crc_at = offset + HEADER + LEN as u16;
+        let stored = u16::from_le_bytes([peek(crc_at), peek(crc_at + 1)]);
+        (crc16(body) == stored).then(|| u16::from_le_bytes([peek(offset + 1), peek(offset + 2)]))
+    }
+
+    /// Whether every byte of the slot is still erased, so it can be written.
+    /// The bank has to be switched in.
+    fn slot_erased(index: u16) -> bool {
+        let offset = Self::slot_offset(index);
+        (offset..offset + Self::SLOT).all(|at| peek(at) == ERASED)
+    }
+
+    /// The newest good save in either bank
+    fn newest(&self) -> Option<Slot> {
+        let mut newest: Option<Slot> = None;
+        for bank in self.banks {
+            far_call(bank, || {
+                for index in 0..Self::SLOTS {
+                    let Some(sequence) = Self::check_slot(index) else {
+                        continue;
+                    };
+                    // sequence numbers wrap, so compare by distance
+                    let newer = newest.is_none_or(|n| (sequence.wrapping_sub(n.sequence) as i16) > 0);
+                    if newer {
+                        newest = Some(Slot { bank, index, sequence });
+                    }
+                }
+            });
+        }
+        newest
+    }
+
+    /// Read the newest save into `out`. Returns false if there isn't one.
+    pub fn load(&self, out: &mut [u8; LEN]) -> bool {
+        let Some(slot) = self.newest() else {
+            return false;
+        };
+        far_call(slot.bank, || {
+            let offset = Self::slot_offset(slot.index) + HEADER;
+            for (i, byte) in out.iter_mut().enumerate() {
+                *byte = peek(offset + i as u16);
+            }
+        });
+        true
+    }
+
+    /// Whether anything has been saved
+    pub fn exists(&self) -> bool {
+        self.newest().is_some()
+    }
+
+    /// Write `data` as the newest save. Returns false if the flash wouldn't
+    /// take it; the previous save is still there in that case.
+    ///
+    /// `data` has to be in RAM or the fixed bank, since the save bank is
+    /// switched in while it's written.
+    pub fn save(&self, console: &mut Console, data: &[u8; LEN]) -> bool {
+        let newest = self.newest();
+        let sequence = newest.map_or(0, |slot| slot.sequence.wrapping_add(1));
+
+        // the next erased slot after the newest save, in the same bank
+        let current = newest.map_or(self.banks[0], |slot| slot.bank);
+        let after = newest.map_or(0, |slot| slot.index + 1);
+        let free = far_call(current, || (after..Self::SLOTS).find(|&index| Self::slot_erased(index)));
+        let (bank, index, needs_erase) = match free {
+            Some(index) => (current, index, false),
+            None => {
+                let other = if current == self.banks[0] { self.banks[1] } else { self.banks[0] };
+                (other, 0, true)
+            }
+        };
+
+        let header = sequence.to_le_bytes();
+        let crc = crc16(header.iter().chain(data.iter()).copied()).to_le_bytes();
+        let marker = [MARKER];
+        let offset = Self::slot_offset(index);
+
+        let video_flags = console.video_flags;
+        console.video_flags.remove(VideoFlags::DMA_NMI);
+        console.write_video_flags();
+
+        let written = critical_section(|| {
+            far_call(bank, || unsafe {
+                (!needs_erase || erase())
+                    && program(offset + 1, header.as_ptr(), 2)
+                    && program(offset + HEADER, data.as_ptr(), LEN as u16)
+                    && program(offset + HEADER + LEN as u16, crc.as_ptr(), CRC_SIZE)
+                    // last, so the slot only counts once everything else is in
+                    && program(offset, marker.as_ptr(), 1)
+            })
+        });
+
+        console.video_flags = video_flags;
+        console.write_video_flags();
+        written
+    }
+
+    /// Wipe both banks, forgetting every save
+    pub fn erase_all(&self, console: &mut Console) -> bool {
+        let video_flags = console.video_flags;
+        console.video_flags.remove(VideoFlags::DMA_NMI);
+        console.write_video_flags();
+
+        let erased = critical_section(|| self.banks.iter().all(|&bank| far_call(bank, || unsafe { erase() })));
+
+        console.video_flags = video_flags;
+        console.write_video_flags();
+        erased
+    }
+}
@@ -16,7 +16,12 @@
 //!
 //! ## BankFlags (`$2005`)
 //!
-//! Controls sprite RAM page, framebuffer selection, and clipping.
+//! Controls sprite RAM page, framebuffer selection, and clipping. The sprite
+//! page is a 3-bit number rather than a flag; read and set it with
+//! [`BankFlags::sprite_page`] and [`BankFlags::with_sprite_page`], or
+//! [`Console::set_sprite_page`](crate::console::Console::set_sprite_page).
+//! The RAM bank is the same, with [`BankFlags::ram_bank`] and
+//! [`BankFlags::with_ram_bank`]; see [`ram`](crate::ram) before switching it.
 
 bitflags::bitflags! {
     /// Video/Blitter control flags at `$2007`.
@@ -76,3 +81,34 @@ bitflags::bitflags! {
         const RAM_BANK_3          = 0b1100_0000;
     }
 }
+
+/// Sprite page bits of [`BankFlags`]
+const SPRITE_PAGE_BITS: u8 = 0b0000_0111;
+/// RAM bank bits of [`BankFlags`]
+const RAM_BANK_BITS: u8 = 0b1100_0000;
+
+impl BankFlags {
+    /// The selected sprite RAM page (0-7)
+    #[inline(always)]
+    pub const fn sprite_page(self) -> u8 {
+        self.bits() & SPRITE_PAGE_BITS
+    }
+
+    /// The same flags with sprite RAM page `page` (0-7) selected instead
+    #[inline(always)]
+    pub const fn with_sprite_page(self, page: u8) -> Self {
+        Self::from_bits_retain((self.bits() & !SPRITE_PAGE_BITS) | (page & SPRITE_PAGE_BITS))
+    }
+
+    /// The RAM bank mapped at `$0000-$1FFF` (0-3)
+    #[inline(always)]
+    pub const fn ram_bank(self) -> u8 {
+        (self.bits() & RAM_BANK_BITS) >> 6
+    }
+
+    /// The same flags with RAM bank `bank` (0-3) mapped instead
+    #[inline(always)]
+    pub const fn with_ram_bank(self, bank: u8) -> Self {
+        Self::from_bits_retain((self.bits() & !RAM_BANK_BITS) | ((bank << 6) & RAM_BANK_BITS))
+    }
+}
@@ -0,0 +1,70 @@
+//! # Safe Area
+//!
+//! A TV doesn't show the whole 128×128 framebuffer: the top and bottom
+//! rows are usually cut off, and the rightmost column sets the border
+//! color. A [`SafeArea`] says how much to cover up on each side, and the
+//! rest is the playfield:
+//!
+//! ```ignore
+//! const SAFE_AREA: SafeArea = SafeArea { top: 12, bottom: 12, ..SafeArea::DEFAULT };
+//!
+//! // keep the player on screen
+//! let field = SAFE_AREA.playfield();
+//! let bounds = field.clamp(player.rect());
+//!
+//! // each frame, after drawing everything else
+//! blitter.draw_safe_area(&SAFE_AREA);
+//! blitter.wait_blit();
+//! ```
+//!
+//! [`BlitterGuard::draw_letterbox`](crate::video_dma::blitter::BlitterGuard::draw_letterbox)
+//! draws [`SafeArea::DEFAULT`].
+
+use crate::{collision::Rect, color};
+
+/// Framebuffer width and height in pixels
+pub const SIZE: u8 = 128;
+
+/// Margins to cover on each side of the screen, and what to cover them with
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SafeArea {
+    pub top: u8,
+    pub bottom: u8,
+    pub left: u8,
+    pub right: u8,
+    /// Not inverted; see [`color`](crate::color)
+    pub color: u8,
+}
+
+impl SafeArea {
+    /// 10 rows top and bottom and the border column on the right, in black
+    pub const DEFAULT: Self = Self { top: 10, bottom: 10, left: 0, right: 1, color: color::BLACK };
+
+    /// The part of the screen inside the margins
+    pub const fn playfield(&self) -> Rect {
+        let (top, bottom) = (self.top as u16, self.bottom as u16);
+        let (left, right) = (self.left as u16, self.right as u16);
+        let width = (SIZE as u16).saturating_sub(left + right);
+        let height = (SIZE as u16).saturating_sub(top + bottom);
+        Rect::new(self.left as i16, self.top as i16, width as u8, height as u8)
+    }
+}
+
+impl Default for SafeArea {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Cut the span `start..start + len` down to what's on screen: how many
+/// pixels are cut off the start, where the rest lands, and how long it is
+#[inline(always)]
+pub(crate) fn clip(start: i16, len: u8) -> Option<(u8, u8, u8)> {
+    let skip = (-start).max(0);
+    let end = (start + len as i16).min(SIZE as i16);
+    let visible = end - start - skip;
+    if visible <= 0 {
+        return None;
+    }
+    Some((skip as u8, (start + skip) as u8, visible as u8))
+}
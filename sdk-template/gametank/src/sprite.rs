@@ -0,0 +1,203 @@
+//! # Metasprites and Animation
+//!
+//! A [`MetaSprite`] is a character made of several rectangles of sprite RAM,
+//! each placed relative to the character's origin, drawn with one call:
+//!
+//! ```ignore
+//! static KNIGHT: MetaSprite = MetaSprite {
+//!     parts: &[
+//!         SpritePart { gx: 0, gy: 0, width: 16, height: 16, x: -8, y: -32, flip_x: false, flip_y: false },
+//!         SpritePart { gx: 0, gy: 16, width: 16, height: 16, x: -8, y: -16, flip_x: false, flip_y: false },
+//!     ],
+//! };
+//!
+//! KNIGHT.draw(&mut blitter, player.x, player.y, facing_left, false);
+//! ```
+//!
+//! An [`AnimationPlayer`] counts frames so you don't have to. It plays a run
+//! of frame numbers at a fixed speed, or an Aseprite tag with the durations
+//! and direction set in Aseprite:
+//!
+//! ```ignore
+//! static PLAYER: AsepriteSprite = include_aseprite!("assets/player.ase");
+//!
+//! let mut walk = AnimationPlayer::from_tag(&PLAYER, PLAYER.tag("walk").unwrap());
+//! loop {
+//!     unsafe { wait(); }
+//!     walk.tick();
+//!     let pixels = PLAYER.frames[walk.frame() as usize];
+//!     // ...
+//! }
+//! ```
+//!
+//! Ticks are vblanks, 60 to the second.
+
+use crate::{
+    assets::{AnimationTag, AsepriteSprite, Direction, SpriteTile},
+    screen::clip,
+    video_dma::blitter::{BlitterGuard, FLIP},
+};
+
+/// One rectangle of a [`MetaSprite`]: where it is in sprite RAM (blitter
+/// GX/GY on the current page) and where its top left goes relative to the
+/// metasprite's origin
+#[derive(Debug, Copy, Clone)]
+pub struct SpritePart {
+    pub gx: u8,
+    pub gy: u8,
+    pub width: u8,
+    pub height: u8,
+    pub x: i8,
+    pub y: i8,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl SpritePart {
+    /// A part showing a tile from `gtrom slice`, at (`x`, `y`) from the origin
+    pub const fn from_tile(tile: &SpriteTile, x: i8, y: i8) -> Self {
+        Self { gx: tile.x, gy: tile.y, width: tile.width, height: tile.height, x, y, flip_x: false, flip_y: false }
+    }
+}
+
+/// Sprite RAM rectangles drawn together. All the parts have to be on the
+/// same sprite page, since the page is selected for the whole draw.
+#[derive(Debug, Copy, Clone)]
+pub struct MetaSprite {
+    pub parts: &'static [SpritePart],
+}
+
+impl MetaSprite {
+    /// Draw with the origin at (`x`, `y`) on screen. `flip_x` and `flip_y`
+    /// mirror the whole metasprite around its origin, parts and placement
+    /// both. Parts hanging off the screen are clipped.
+    pub fn draw(&self, blitter: &mut BlitterGuard, x: i16, y: i16, flip_x: bool, flip_y: bool) {
+        for part in self.parts {
+            let (width, height) = (part.width & !FLIP, part.height & !FLIP);
+            let left = if flip_x { x - part.x as i16 - width as i16 } else { x + part.x as i16 };
+            let top = if flip_y { y - part.y as i16 - height as i16 } else { y + part.y as i16 };
+            let (Some((skip_x, fb_x, w)), Some((skip_y, fb_y, h))) = (clip(left, width), clip(top, height)) else {
+                continue;
+            };
+
+            // the visible part of the source is at the far end when mirrored
+            let mirror_x = part.flip_x != flip_x;
+            let mirror_y = part.flip_y != flip_y;
+            let gx = part.gx.wrapping_add(if mirror_x { width - skip_x - w } else { skip_x });
+            let gy = part.gy.wrapping_add(if mirror_y { height - skip_y - h } else { skip_y });
+
+            blitter.draw_sprite_flipped(gx, gy, fb_x, fb_y, w, h, mirror_x, mirror_y);
+            blitter.wait_blit();
+        }
+    }
+}
+
+/// How long each frame of an animation lasts
+#[derive(Debug, Copy, Clone)]
+enum Timing {
+    /// The same number of ticks for every frame
+    Ticks(u8),
+    /// Per frame, in milliseconds, indexed by frame number
+    Milliseconds(&'static [u16]),
+}
+
+/// Steps through a run of frame numbers, once a tick
+#[derive(Debug, Copy, Clone)]
+pub struct AnimationPlayer {
+    from: u16,
+    to: u16,
+    direction: Direction,
+    looping: bool,
+    timing: Timing,
+    frame: u16,
+    /// Which way a ping-pong is going now
+    reversed: bool,
+    ticks_left: u16,
+    finished: bool,
+}
+
+impl AnimationPlayer {
+    /// Frames `from` to `to`, inclusive, `ticks_per_frame` ticks each
+    pub fn new(from: u16, to: u16, ticks_per_frame: u8, looping: bool) -> Self {
+        Self::start(from, to, Direction::Forward, looping, Timing::Ticks(ticks_per_frame.max(1)))
+    }
+
+    /// An Aseprite tag, with the frame durations and direction from the file. Loops.
+    pub fn from_tag(sprite: &AsepriteSprite, tag: &AnimationTag) -> Self {
+        Self::start(tag.from, tag.to, tag.direction, true, Timing::Milliseconds(sprite.durations))
+    }
+
+    fn start(from: u16, to: u16, direction: Direction, looping: bool, timing: Timing) -> Self {
+        let reversed = matches!(direction, Direction::Reverse | Direction::PingPongReverse);
+        let mut player = Self {
+            from,
+            to: to.max(from),
+            direction,
+            looping,
+            timing,
+            frame: if reversed { to.max(from) } else { from },
+            reversed,
+            ticks_left: 0,
+            finished: false,
+        };
+        player.ticks_left = player.duration();
+        player
+    }
+
+    /// Ticks the current frame lasts
+    fn duration(&self) -> u16 {
+        match self.timing {
+            Timing::Ticks(ticks) => ticks as u16,
+            // 60 ticks a second, rounded, but never less than one
+            Timing::Milliseconds(durations) => {
+                let ms = durations.get(self.frame as usize).copied().unwrap_or(100) as u32;
+                ((ms * 60 + 500) / 1000).max(1) as u16
+            }
+        }
+    }
+
+    /// Go back to the start
+    pub fn restart(&mut self) {
+        *self = Self::start(self.from, self.to, self.direction, self.looping, self.timing);
+    }
+
+    /// The frame number to show
+    pub fn frame(&self) -> u16 {
+        self.frame
+    }
+
+    /// Whether a non-looping animation has shown its last frame for its full time
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Count one tick, returning true if that moved to another frame
+    pub fn tick(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+        if self.ticks_left > 0 {
+            return false;
+        }
+
+        let ping_pong = matches!(self.direction, Direction::PingPong | Direction::PingPongReverse);
+        let at_end = if self.reversed { self.frame == self.from } else { self.frame == self.to };
+
+        if !at_end {
+            self.frame = if self.reversed { self.frame - 1 } else { self.frame + 1 };
+        } else if !self.looping && (!ping_pong || self.reversed == (self.direction == Direction::PingPong)) {
+            // played through once; a ping-pong has to get there and back
+            self.finished = true;
+            return false;
+        } else if ping_pong && self.from != self.to {
+            self.reversed = !self.reversed;
+            self.frame = if self.reversed { self.frame - 1 } else { self.frame + 1 };
+        } else {
+            self.frame = if self.reversed { self.to } else { self.from };
+        }
+
+        self.ticks_left = self.duration();
+        true
+    }
+}
@@ -0,0 +1,92 @@
+//! # Sharing Data With Interrupts
+//!
+//! An IRQ can land between any two instructions, so a `static mut` that
+//! both the main loop and an IRQ handler touch can be caught half-written,
+//! and Rust is free to assume it isn't changing under it besides.
+//! [`IrqCell`] wraps such a value so it's only ever reached with IRQs
+//! masked:
+//!
+//! ```ignore
+//! static SCORE: IrqCell<u16> = IrqCell::new(0);
+//!
+//! // in the IRQ handler
+//! SCORE.update(|score| *score += 10);
+//!
+//! // in the main loop
+//! let score = SCORE.get();
+//! ```
+//!
+//! [`critical_section`] is the masking on its own, for when there's more to
+//! do than touch one value. Sections nest: the inner one leaves IRQs masked
+//! on the way out if they were masked on the way in.
+//!
+//! The vblank NMI can't be masked. The SDK's own NMI only touches single
+//! bytes ([`VBLANK_COUNT`](crate::boot::VBLANK_COUNT)), which can't tear.
+
+use core::cell::{Cell, UnsafeCell};
+
+unsafe extern "C" {
+    fn irq_save() -> u8;
+    fn irq_restore(status: u8);
+}
+
+/// Run `f` with IRQs masked, then put the mask back the way it was
+#[inline(always)]
+pub fn critical_section<R>(f: impl FnOnce() -> R) -> R {
+    let status = unsafe { irq_save() };
+    let result = f();
+    unsafe { irq_restore(status) };
+    result
+}
+
+/// A value shared between the main loop and IRQ handlers. Every access
+/// happens inside a [`critical_section`].
+pub struct IrqCell<T> {
+    value: UnsafeCell<T>,
+    borrowed: Cell<bool>,
+}
+
+// Only reached with IRQs masked, and there's only the one CPU
+unsafe impl<T: Send> Sync for IrqCell<T> {}
+
+impl<T> IrqCell<T> {
+    pub const fn new(value: T) -> Self {
+        Self { value: UnsafeCell::new(value), borrowed: Cell::new(false) }
+    }
+
+    /// Run `f` on the value with IRQs masked.
+    ///
+    /// Panics if `f` tries to borrow this same cell again.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        critical_section(|| {
+            if self.borrowed.replace(true) {
+                panic!("IrqCell borrowed twice");
+            }
+            let result = f(unsafe { &mut *self.value.get() });
+            self.borrowed.set(false);
+            result
+        })
+    }
+
+    /// Replace the value, returning the old one
+    pub fn replace(&self, value: T) -> T {
+        self.update(|current| core::mem::replace(current, value))
+    }
+
+    /// The value, bypassing the critical section since `&mut self` means
+    /// nothing else can be looking
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Copy> IrqCell<T> {
+    /// A copy of the value
+    pub fn get(&self) -> T {
+        self.update(|value| *value)
+    }
+
+    pub fn set(&self, value: T) {
+        self.update(|current| *current = value);
+    }
+}
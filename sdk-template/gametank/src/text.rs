@@ -0,0 +1,81 @@
+//! # Text
+//!
+//! Draws strings with a bitmap font from `include_font!`. Load the font into
+//! a spare corner of sprite RAM once, then blit a glyph per character:
+//!
+//! ```ignore
+//! static FONT: Font = include_font!("assets/font.png", "8x8");
+//!
+//! // at startup: the bottom of quadrant Four is a good place to keep it
+//! let mut blitter = console.dma.blitter(&mut console.sc).unwrap();
+//! blitter.set_vram_quad(SpriteQuadrant::Four);
+//! drop(blitter);
+//! let mut sm = console.dma.sprite_mem(&mut console.sc).unwrap();
+//! let font = FONT.load(&mut sm, SpriteQuadrant::Four, 0, 80);
+//! drop(sm);
+//!
+//! // every frame
+//! let mut blitter = console.dma.blitter(&mut console.sc).unwrap();
+//! draw_text(&mut blitter, &font, 4, 4, "SCORE 0042");
+//! ```
+//!
+//! Strings are drawn byte by byte, so stick to ASCII. `\n` starts a new line
+//! under the first; bytes the font has no glyph for leave a gap. For
+//! numbers, [`fmt`](crate::fmt) turns them into strings without `core::fmt`.
+
+use crate::{screen, video_dma::blitter::BlitterGuard};
+
+/// Where a [`Font`](crate::assets::Font) was loaded in sprite RAM, from
+/// [`Font::load`](crate::assets::Font::load). `x` and `y` are the blitter's
+/// GX/GY of the first glyph, the space.
+#[derive(Debug, Copy, Clone)]
+pub struct LoadedFont {
+    pub x: u8,
+    pub y: u8,
+    pub glyph_width: u8,
+    pub glyph_height: u8,
+    pub columns: u8,
+    pub glyphs: u16,
+}
+
+impl LoadedFont {
+    /// GX/GY of the glyph for `byte`, if the font has one
+    #[inline(always)]
+    fn glyph(&self, byte: u8) -> Option<(u8, u8)> {
+        let index = byte.checked_sub(b' ')?;
+        if index as u16 >= self.glyphs {
+            return None;
+        }
+        Some((
+            self.x + (index % self.columns) * self.glyph_width,
+            self.y + (index / self.columns) * self.glyph_height,
+        ))
+    }
+}
+
+/// Draw `text` with its top left at (`x`, `y`) on screen. Glyphs running off
+/// the right edge are cut off.
+pub fn draw_text(blitter: &mut BlitterGuard, font: &LoadedFont, x: u8, y: u8, text: &str) {
+    let (mut cx, mut cy) = (x, y);
+    for &byte in text.as_bytes() {
+        if byte == b'\n' {
+            cx = x;
+            cy = cy.saturating_add(font.glyph_height);
+            continue;
+        }
+        if let Some((sx, sy)) = font.glyph(byte).filter(|_| byte != b' ' && cx < screen::SIZE) {
+            let width = font.glyph_width.min(screen::SIZE - cx);
+            blitter.draw_sprite(sx, sy, cx, cy, width, font.glyph_height);
+            blitter.wait_blit();
+        }
+        cx = cx.saturating_add(font.glyph_width);
+    }
+}
+
+/// How wide `text` is in pixels, going by its longest line
+pub fn text_width(font: &LoadedFont, text: &str) -> u16 {
+    text.split('\n')
+        .map(|line| line.len() as u16 * font.glyph_width as u16)
+        .max()
+        .unwrap_or(0)
+}
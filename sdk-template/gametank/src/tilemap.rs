@@ -0,0 +1,356 @@
+//! # Tile Maps
+//!
+//! Draws a [`TileLayer`] from a `[[tiled]]` map in `assets.toml`, with a
+//! camera that can sit anywhere on the map. Rows are read one at a time with
+//! their chunk's bank switched in, and each tile is a blit from a tileset
+//! already loaded into sprite RAM.
+//!
+//! ```ignore
+//! gametank::include_assets!();
+//!
+//! // the tileset image, 8x8 tiles, 16 to a row, at the top left of sprite page 0
+//! const TILES: Tileset = Tileset { x: 0, y: 0, columns: 16, tile_width: 8, tile_height: 8 };
+//!
+//! let ground = assets::WORLD1.layer("ground").unwrap();
+//! let mut blitter = console.dma.blitter(&mut console.sc).unwrap();
+//! draw_layer(&mut blitter, ground, &TILES, camera_x, camera_y);
+//! ```
+//!
+//! Empty cells (tile 0) aren't drawn, so clear the screen first or draw a
+//! full layer underneath. Every tile is its own blit, and a screen of 8x8
+//! tiles is 256 of them, so prefer bigger tiles for busy layers.
+//!
+//! ## Scrolling Backgrounds
+//!
+//! For a background that scrolls every frame, [`ScrollingMap`] is much
+//! cheaper. It keeps the tile grid in RAM and a whole sprite page as a
+//! 256x256 ring of 16x16 tiles around the camera. When the camera moves, only
+//! the rows and columns of tiles that scrolled into view are copied into the
+//! ring, and the screen is then drawn out of it with 16x16 blits, one per
+//! tile, with `DMA_GCARRY` off:
+//!
+//! ```ignore
+//! static mut GRID: [u8; 64 * 32] = [0; 64 * 32];
+//!
+//! // tile n of the map is tile n - 1 of assets::TILES, a sheet of 16x16 tiles
+//! let mut map = ScrollingMap::new(unsafe { &mut GRID }, 64, 32, &assets::TILES, 7);
+//!
+//! loop {
+//!     unsafe { wait(); }
+//!     console.flip_framebuffers();
+//!
+//!     map.set_camera(player_x.saturating_sub(64), 0);
+//!     map.update(console);
+//!     map.draw(console);
+//!     // draw sprites on top...
+//! }
+//! ```
+//!
+//! Filling a fresh ring is 81 tiles of copying, a few frames of CPU time, so
+//! do the first [`update`](ScrollingMap::update) behind a fade or a title
+//! card. After that, scrolling one pixel a frame only copies a strip of nine
+//! tiles every 16 frames.
+//!
+//! A [`Camera`](crate::camera::Camera) can drive the scrolling, and stack a
+//! few maps as parallax bands.
+
+use crate::{
+    assets::{Image, TileLayer},
+    blitter::SpriteQuadrant,
+    console::Console,
+    scr::{BankFlags, VideoFlags},
+    screen::{self, clip},
+    via::far_call,
+    video_dma::blitter::BlitterGuard,
+};
+
+/// Where a tileset sits in sprite RAM: `columns` tiles to a row, starting at
+/// GX/GY (`x`, `y`) on the current sprite page
+#[derive(Debug, Copy, Clone)]
+pub struct Tileset {
+    pub x: u8,
+    pub y: u8,
+    pub columns: u8,
+    pub tile_width: u8,
+    pub tile_height: u8,
+}
+
+impl Tileset {
+    /// GX/GY of Tiled tile id `tile` (1 is the tileset's first tile)
+    #[inline(always)]
+    fn source(&self, tile: u8) -> (u8, u8) {
+        let index = tile - 1;
+        (
+            self.x.wrapping_add((index % self.columns).wrapping_mul(self.tile_width)),
+            self.y.wrapping_add((index / self.columns).wrapping_mul(self.tile_height)),
+        )
+    }
+}
+
+/// Draw the part of `layer` under a camera whose top left is at
+/// (`camera_x`, `camera_y`) in map pixels. Tiles hanging off the edges of the
+/// screen are clipped.
+///
+/// Switches ROM banks to read the layer, so call it from code in the fixed
+/// bank. Select the tileset's sprite page before calling.
+#[inline(never)]
+pub fn draw_layer(blitter: &mut BlitterGuard, layer: &TileLayer, tileset: &Tileset, camera_x: u16, camera_y: u16) {
+    let (tw, th) = (tileset.tile_width as u16, tileset.tile_height as u16);
+    let first_col = camera_x / tw;
+    let first_row = camera_y / th;
+    let last_col = (camera_x.saturating_add(screen::SIZE as u16 - 1) / tw).min(layer.width.saturating_sub(1));
+    let last_row = (camera_y.saturating_add(screen::SIZE as u16 - 1) / th).min(layer.height.saturating_sub(1));
+    if layer.width == 0 || layer.height == 0 || first_col > last_col || first_row > last_row {
+        return;
+    }
+
+    for row in first_row..=last_row {
+        let screen_y = (row * th).wrapping_sub(camera_y) as i16;
+        let Some((skip_y, fb_y, height)) = clip(screen_y, tileset.tile_height) else { continue };
+
+        far_call(layer.chunk(row).bank, || {
+            let tiles = layer.row(row);
+            for col in first_col..=last_col {
+                let tile = tiles[col as usize];
+                if tile == 0 {
+                    continue;
+                }
+                let screen_x = (col * tw).wrapping_sub(camera_x) as i16;
+                let Some((skip_x, fb_x, width)) = clip(screen_x, tileset.tile_width) else { continue };
+
+                let (sx, sy) = tileset.source(tile);
+                blitter.draw_sprite(sx.wrapping_add(skip_x), sy.wrapping_add(skip_y), fb_x, fb_y, width, height);
+                blitter.wait_blit();
+            }
+        });
+    }
+}
+
+/// [`ScrollingMap`] tiles are 16x16
+const TILE: u16 = 16;
+/// A 256x256 sprite page holds 16x16 of them
+const RING: u16 = 16;
+/// At most 9 tiles are on screen along each axis: 8, plus one scrolled partway in
+const VISIBLE: u16 = 9;
+/// Tiles changed with [`ScrollingMap::set_tile`] between updates before a full reload
+const MAX_PENDING: usize = 8;
+
+/// A tile grid in RAM that scrolls smoothly, keeping the tiles around the
+/// camera in a sprite page so a frame only copies what scrolled in. See the
+/// [module docs](self#scrolling-backgrounds).
+pub struct ScrollingMap<'a> {
+    tiles: &'a mut [u8],
+    width: u16,
+    height: u16,
+    tileset: &'static Image,
+    page: u8,
+    camera_x: u16,
+    camera_y: u16,
+    /// The top left tile of what's in the ring, once anything is
+    loaded: Option<(u16, u16)>,
+    pending: [(u16, u16); MAX_PENDING],
+    pending_len: u8,
+}
+
+impl<'a> ScrollingMap<'a> {
+    /// A `width` x `height` map over `tiles`, row by row. Tile n is the
+    /// `tileset`'s nth 16x16 tile counting from 1, left to right and then
+    /// down, and 0 is empty. The tiles are kept in sprite page `page` (0-7),
+    /// which the map uses all of.
+    pub fn new(tiles: &'a mut [u8], width: u16, height: u16, tileset: &'static Image, page: u8) -> Self {
+        Self {
+            tiles,
+            width,
+            height,
+            tileset,
+            page,
+            camera_x: 0,
+            camera_y: 0,
+            loaded: None,
+            pending: [(0, 0); MAX_PENDING],
+            pending_len: 0,
+        }
+    }
+
+    /// The tile at column `x`, row `y`; 0 outside the map
+    pub fn tile(&self, x: u16, y: u16) -> u8 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Change a tile. It shows from the next [`update`](Self::update).
+    pub fn set_tile(&mut self, x: u16, y: u16, tile: u8) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.tiles[y as usize * self.width as usize + x as usize] = tile;
+
+        if (self.pending_len as usize) < MAX_PENDING {
+            self.pending[self.pending_len as usize] = (x, y);
+            self.pending_len += 1;
+        } else {
+            // too many to track; reload the lot
+            self.loaded = None;
+        }
+    }
+
+    pub fn camera(&self) -> (u16, u16) {
+        (self.camera_x, self.camera_y)
+    }
+
+    /// How big the map is in pixels
+    pub fn pixel_size(&self) -> (u16, u16) {
+        (self.width.saturating_mul(TILE), self.height.saturating_mul(TILE))
+    }
+
+    /// Move the camera's top left to (`x`, `y`) in map pixels, kept inside the map
+    pub fn set_camera(&mut self, x: u16, y: u16) {
+        let (width, height) = self.pixel_size();
+        let max_x = width.saturating_sub(screen::SIZE as u16);
+        let max_y = height.saturating_sub(screen::SIZE as u16);
+        self.camera_x = x.min(max_x);
+        self.camera_y = y.min(max_y);
+    }
+
+    /// Copy the tiles that scrolled into view, or were changed, into the
+    /// ring. Call once a frame, after moving the camera and before
+    /// [`draw`](Self::draw); needs the blitter and sprite RAM free.
+    pub fn update(&mut self, console: &mut Console) {
+        let (left, top) = (self.camera_x / TILE, self.camera_y / TILE);
+        let previous_page = self.select_page(console);
+
+        match self.loaded {
+            Some((old_left, old_top)) if left.abs_diff(old_left) < VISIBLE && top.abs_diff(old_top) < VISIBLE => {
+                let columns = if left > old_left { old_left + VISIBLE..left + VISIBLE } else { left..old_left };
+                self.load_tiles(console, columns, top..top + VISIBLE);
+                let rows = if top > old_top { old_top + VISIBLE..top + VISIBLE } else { top..old_top };
+                self.load_tiles(console, left..left + VISIBLE, rows);
+            }
+            _ => self.load_tiles(console, left..left + VISIBLE, top..top + VISIBLE),
+        }
+
+        for i in 0..self.pending_len as usize {
+            let (x, y) = self.pending[i];
+            if (left..left + VISIBLE).contains(&x) && (top..top + VISIBLE).contains(&y) {
+                self.load_tiles(console, x..x + 1, y..y + 1);
+            }
+        }
+        self.pending_len = 0;
+        self.loaded = Some((left, top));
+
+        console.bank_flags = previous_page;
+        console.write_bank_flags();
+    }
+
+    /// Draw the screen's worth of map under the camera out of the ring, a
+    /// 16x16 blit per tile with the blitter's `DMA_GCARRY` off
+    pub fn draw(&self, console: &mut Console) {
+        self.draw_band(console, 0, screen::SIZE);
+    }
+
+    /// Draw just screen rows `top..top + height` of the map under the
+    /// camera, for stacking bands of different maps; see
+    /// [`camera`](crate::camera#parallax)
+    pub fn draw_band(&self, console: &mut Console, top: u8, height: u8) {
+        let height = height.min(screen::SIZE.saturating_sub(top));
+        if height == 0 {
+            return;
+        }
+        let previous_page = self.select_page(console);
+        let previous_flags = console.video_flags;
+        console.video_flags.remove(VideoFlags::DMA_GCARRY);
+
+        if let Some(mut blitter) = console.blitter() {
+            let (first_col, first_row) = (self.camera_x / TILE, (self.camera_y + top as u16) / TILE);
+            let last_row = (self.camera_y + (top + height - 1) as u16) / TILE;
+            for row in first_row..=last_row {
+                let band_y = (row * TILE).wrapping_sub(self.camera_y + top as u16) as i16;
+                let Some((skip_y, y, rows)) = clip(band_y, TILE as u8) else { continue };
+                if y >= height {
+                    continue;
+                }
+                let rows = rows.min(height - y);
+                for col in first_col..first_col + VISIBLE {
+                    let screen_x = (col * TILE).wrapping_sub(self.camera_x) as i16;
+                    let Some((skip_x, x, columns)) = clip(screen_x, TILE as u8) else { continue };
+                    let gx = ((col % RING) * TILE) as u8 + skip_x;
+                    let gy = ((row % RING) * TILE) as u8 + skip_y;
+                    blitter.draw_sprite(gx, gy, x, top + y, columns, rows);
+                    blitter.wait_blit();
+                }
+            }
+        }
+
+        console.video_flags = previous_flags;
+        console.write_video_flags();
+        console.bank_flags = previous_page;
+        console.write_bank_flags();
+    }
+
+    /// Point sprite RAM at the ring's page, returning the flags to restore
+    fn select_page(&self, console: &mut Console) -> BankFlags {
+        let previous = console.bank_flags;
+        console.bank_flags = previous.with_sprite_page(self.page);
+        console.write_bank_flags();
+        previous
+    }
+
+    /// Copy map tiles `columns` x `rows` into their places in the ring, a
+    /// quadrant at a time since that's all the CPU can see at once
+    fn load_tiles(&self, console: &mut Console, columns: core::ops::Range<u16>, rows: core::ops::Range<u16>) {
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+
+        for quadrant in 0..4u16 {
+            let in_quadrant = |x: u16, y: u16| (x % RING >= RING / 2) as u16 + 2 * (y % RING >= RING / 2) as u16 == quadrant;
+            if !columns.clone().any(|x| rows.clone().any(|y| in_quadrant(x, y))) {
+                continue;
+            }
+
+            if let Some(mut blitter) = console.blitter() {
+                blitter.set_vram_quad(match quadrant {
+                    0 => SpriteQuadrant::One,
+                    1 => SpriteQuadrant::Two,
+                    2 => SpriteQuadrant::Three,
+                    _ => SpriteQuadrant::Four,
+                });
+            }
+            let Some(mut sprite_mem) = console.dma.sprite_mem(&mut console.video_flags) else { return };
+            let ram = sprite_mem.bytes();
+
+            far_call(self.tileset.bank, || {
+                for y in rows.clone() {
+                    for x in columns.clone() {
+                        if in_quadrant(x, y) {
+                            self.copy_tile(ram, x, y);
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Copy map tile (`x`, `y`) into the current quadrant. The tileset's bank must be switched in.
+    fn copy_tile(&self, ram: &mut [u8; 0x4000], x: u16, y: u16) {
+        let (tile, size) = (self.tile(x, y), TILE as usize);
+        let dest = ((y % (RING / 2)) as usize * size) * 128 + (x % (RING / 2)) as usize * size;
+
+        if tile == 0 {
+            for row in 0..size {
+                ram[dest + row * 128..][..size].fill(0);
+            }
+            return;
+        }
+
+        let sheet_width = self.tileset.width as usize;
+        let columns = sheet_width / size;
+        let index = tile as usize - 1;
+        let source = (index / columns) * size * sheet_width + (index % columns) * size;
+        for row in 0..size {
+            ram[dest + row * 128..][..size].copy_from_slice(&self.tileset.pixels[source + row * sheet_width..][..size]);
+        }
+    }
+}
@@ -19,24 +19,64 @@
 //!
 //! ## Placing Data in Banks
 //!
-//! Use link sections to put data in specific banks:
+//! Use `#[bank(N)]` from `gametank-asset-macros` to put data or code in
+//! specific banks (it writes the `.rodata.bankN`/`.text.bankN` link section):
 //!
 //! ```ignore
 //! // This array will be in ROM bank 10
-//! #[unsafe(link_section = ".rodata.bank10")]
-//! static LEVEL_DATA: [u8; 8192] = include_bytes!("level1.bin");
+//! #[bank(10)]
+//! static LEVEL_DATA: [u8; 8192] = *include_bytes!("level1.bin");
 //!
 //! // Switch to bank 10 before accessing
 //! via.change_rom_bank(10);
 //! let first_byte = LEVEL_DATA[0];  // Now accessible!
 //! ```
 //!
+//! ## Calling Code in Other Banks
+//!
+//! Functions marked `#[bank(N)]` are reached through a trampoline in the fixed
+//! bank, which switches to bank N, makes the call, and switches back to
+//! whatever was mapped before (see [`far_call`]). Callers don't need to know
+//! where the function lives:
+//!
+//! ```ignore
+//! #[bank(126)]
+//! fn draw_background(blitter: &mut BlitterGuard) { ... }
+//!
+//! draw_background(&mut blitter); // from any bank
+//! ```
+//!
 //! **Tip for future carts:** Use banks 128-255 instead of 0-127 for compatibility
 //! with battery-backed RAM cartridges (they use bit 7 to select RAM vs ROM).
 
 use bit_field::BitField;
 use volatile_register::{RW, WO};
 
+/// Shadow copy of the bank the VIA last switched to, since it can't be read back.
+/// 0 until the first switch.
+#[unsafe(link_section = ".data.zp")]
+static mut ROM_BANK: u8 = 0;
+
+/// The bank currently mapped at `$8000-$BFFF`
+#[inline(always)]
+pub fn current_rom_bank() -> u8 {
+    unsafe { ROM_BANK }
+}
+
+/// Call `f` with `bank` mapped at `$8000-$BFFF`, then switch back to the bank
+/// that was mapped before. This has to run from the fixed bank: code in a
+/// switchable bank would be switched out from under itself. `#[bank]`
+/// trampolines are built on it.
+#[inline(always)]
+pub fn far_call<R>(bank: u8, f: impl FnOnce() -> R) -> R {
+    let via = unsafe { Via::new() };
+    let previous = current_rom_bank();
+    via.change_rom_bank(bank);
+    let result = f();
+    via.change_rom_bank(previous);
+    result
+}
+
 #[repr(C, packed)]
 pub struct Via {
     pub iorb: RW<u8>, // input/output register b
@@ -83,6 +123,7 @@ impl Via {
             self.iora.write(*self.iora.read().set_bit(0, true));
             self.iora.write(*self.iora.read().set_bit(2, true));
             self.iora.write(0);
+            ROM_BANK = banknum;
         }
     }
 
@@ -40,6 +40,20 @@
 //! (128×128 = 16K pixels) takes about 1/4 of a frame, giving you lots of time
 //! for game logic.
 //!
+//! ## Shapes
+//!
+//! Lines, circles and triangles are drawn as runs of color fills, so they
+//! cost a blit per run rather than a CPU write per pixel:
+//!
+//! ```ignore
+//! blitter.draw_line(10, 10, 100, 40, !WHITE);
+//! blitter.draw_circle_outline(64, 64, 20, !RED);
+//! blitter.fill_triangle((64, 20), (30, 90), (100, 90), !BLUE);
+//! ```
+//!
+//! Each waits for its last fill before returning, and anything hanging off
+//! the screen is clipped.
+//!
 //! ## Colors
 //!
 //! Colors are 8-bit HSL format: `0bHHH_SS_LLL`
@@ -54,11 +68,20 @@
 //! // ALWAYS invert when drawing!
 //! blitter.draw_square(10, 10, 32, 32, !RED);
 //! ```
+//!
+//! [`color`](crate::color) has these and more by name, and
+//! [`BlitterGuard::draw_rect`] inverts for you:
+//!
+//! ```ignore
+//! blitter.draw_rect(10, 10, 32, 32, color::RED);
+//! ```
 
 use crate::{
+    assets::SpriteTile,
     boot::wait,
     blitter::{Bcr, SpriteQuadrant},
     scr::VideoFlags,
+    screen::{self, SafeArea},
     video_dma::{framebuffers::Framebuffers, spritemem::SpriteMem, VideoDma},
 };
 
@@ -82,6 +105,9 @@ impl Blitter {
     }
 }
 
+/// Bit 7 of the blitter's WIDTH and HEIGHT registers mirrors the blit
+pub(crate) const FLIP: u8 = 0x80;
+
 /// Write video flags to the hardware register at $2007.
 #[inline(always)]
 fn write_video_flags(flags: VideoFlags) {
@@ -156,6 +182,21 @@ impl<'a> BlitterGuard<'a> {
         }
     }
 
+    /// Fill a rectangle with a solid color, like
+    /// [`draw_square`](Self::draw_square) but taking the color as it is,
+    /// not inverted; see [`color`](crate::color).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// blitter.draw_rect(10, 20, 16, 16, color::RED);
+    /// blitter.wait_blit();
+    /// ```
+    #[inline(always)]
+    pub fn draw_rect(&mut self, x: u8, y: u8, width: u8, height: u8, color: u8) {
+        self.draw_square(x, y, width, height, !color);
+    }
+
     /// Copy a rectangular region from sprite RAM to the framebuffer.
     ///
     /// # Arguments
@@ -198,6 +239,53 @@ impl<'a> BlitterGuard<'a> {
         }
     }
 
+    /// Copy a rectangle from sprite RAM like [`draw_sprite`](Self::draw_sprite),
+    /// mirrored left to right with `flip_x` and top to bottom with `flip_y`,
+    /// so a character can face both ways with one copy of the art.
+    ///
+    /// `sx`, `sy`, `width` and `height` are the same as for an unflipped
+    /// draw; the blitter's flip bits, and the far-edge source coordinates
+    /// they need, are handled here. `width` and `height` are at most 127.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // the player's sprite faces right; face left when moving left
+    /// blitter.draw_sprite_flipped(0, 0, x, y, 16, 24, facing_left, false);
+    /// blitter.wait_blit();
+    /// ```
+    #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_sprite_flipped(
+        &mut self,
+        sx: u8,
+        sy: u8,
+        fb_x: u8,
+        fb_y: u8,
+        width: u8,
+        height: u8,
+        flip_x: bool,
+        flip_y: bool,
+    ) {
+        let (width, height) = (width & !FLIP, height & !FLIP);
+        // a mirrored blit reads backwards from the complement of GX/GY, so
+        // point it at the far edge, inverted
+        let (sx, width) = if flip_x { (!sx.wrapping_add(width - 1), width | FLIP) } else { (sx, width) };
+        let (sy, height) = if flip_y { (!sy.wrapping_add(height - 1), height | FLIP) } else { (sy, height) };
+        self.draw_sprite(sx, sy, fb_x, fb_y, width, height);
+    }
+
+    /// Copy `tile` to (`fb_x`, `fb_y`) on the framebuffer. `tile.page` has to
+    /// be the selected sprite page; see
+    /// [`Console::set_sprite_page`](crate::console::Console::set_sprite_page).
+    ///
+    /// Handles from [`SpriteRam`](crate::vram::SpriteRam) and `gtrom slice`
+    /// are both [`SpriteTile`]s.
+    #[inline(always)]
+    pub fn draw_tile(&mut self, tile: &SpriteTile, fb_x: u8, fb_y: u8) {
+        self.draw_sprite(tile.x, tile.y, fb_x, fb_y, tile.width, tile.height);
+    }
+
     /// Set the sprite RAM quadrant for subsequent operations.
     ///
     /// Sprite RAM is organized as 256×512 pixels. This selects which
@@ -247,6 +335,8 @@ impl<'a> BlitterGuard<'a> {
     ///
     /// This is intended to be called just before vsync to hide content
     /// in the overscan region that may not be visible on all displays.
+    /// It's [`draw_safe_area`](Self::draw_safe_area) with
+    /// [`SafeArea::DEFAULT`].
     ///
     /// # Example
     ///
@@ -261,27 +351,190 @@ impl<'a> BlitterGuard<'a> {
     /// ```
     #[inline(always)]
     pub fn draw_letterbox(&mut self) {
-        const BLACK: u8 = !0u8; // Inverted color: !0 = 0xFF = black
-        const LETTERBOX_HEIGHT: u8 = 10;
+        self.draw_safe_area(&SafeArea::DEFAULT);
+    }
+
+    /// Cover the margins of `area` with its color. Like
+    /// [`draw_letterbox`](Self::draw_letterbox), the last bar is left
+    /// drawing; wait for it before the next blit.
+    pub fn draw_safe_area(&mut self, area: &SafeArea) {
+        let mut drawing = false;
+        for (x0, y0, x1, y1) in margins(area) {
+            self.fill_pieces(x0, y0, x1, y1, !area.color, &mut drawing);
+        }
+    }
+
+    /// Draw a line from (`x0`, `y0`) to (`x1`, `y1`), both ends included,
+    /// as one fill per run of pixels: shallow lines in horizontal runs and
+    /// steep ones in vertical runs. `color` is inverted, as for
+    /// [`draw_square`](Self::draw_square). Parts off the screen are clipped.
+    pub fn draw_line(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, color: u8) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let steep = -dy > dx;
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        let (mut run_x, mut run_y) = (x0, y0);
+
+        loop {
+            if x == x1 && y == y1 {
+                self.fill_clipped(run_x, run_y, x, y, color);
+                return;
+            }
+            let (mut next_x, mut next_y) = (x, y);
+            let twice = 2 * error;
+            if twice >= dy {
+                error += dy;
+                next_x += step_x;
+            }
+            if twice <= dx {
+                error += dx;
+                next_y += step_y;
+            }
+            // a run ends when the line steps across it
+            if (steep && next_x != x) || (!steep && next_y != y) {
+                self.fill_clipped(run_x, run_y, x, y, color);
+                (run_x, run_y) = (next_x, next_y);
+            }
+            (x, y) = (next_x, next_y);
+        }
+    }
+
+    /// Draw the outline of a circle of radius `radius` around (`cx`, `cy`),
+    /// a fill or two per row. `color` is inverted, as for
+    /// [`draw_square`](Self::draw_square).
+    pub fn draw_circle_outline(&mut self, cx: i16, cy: i16, radius: u8, color: u8) {
+        // the half-width of each row, shrinking row by row away from the
+        // middle; the outline on a row runs from just past the next row's
+        // half-width out to this row's
+        let r = radius as i32;
+        let limit = r * r + r;
+        let mut half = r;
+        let mut next = r;
+        for row in 0..=r {
+            while next >= 0 && next * next + (row + 1) * (row + 1) > limit {
+                next -= 1;
+            }
+            let inner = (next + 1).min(half);
+            for y in [cy + row as i16, cy - row as i16] {
+                if inner == 0 {
+                    self.fill_clipped(cx - half as i16, y, cx + half as i16, y, color);
+                } else {
+                    self.fill_clipped(cx + inner as i16, y, cx + half as i16, y, color);
+                    self.fill_clipped(cx - half as i16, y, cx - inner as i16, y, color);
+                }
+                if row == 0 {
+                    break;
+                }
+            }
+            half = next;
+        }
+    }
+
+    /// Fill the triangle with corners `a`, `b` and `c`, as (x, y), one fill
+    /// per row. `color` is inverted, as for [`draw_square`](Self::draw_square).
+    pub fn fill_triangle(&mut self, a: (i16, i16), b: (i16, i16), c: (i16, i16), color: u8) {
+        // sort the corners top to bottom
+        let (mut a, mut b, mut c) = (a, b, c);
+        if a.1 > b.1 {
+            core::mem::swap(&mut a, &mut b);
+        }
+        if b.1 > c.1 {
+            core::mem::swap(&mut b, &mut c);
+        }
+        if a.1 > b.1 {
+            core::mem::swap(&mut a, &mut b);
+        }
+
+        /// x where the edge from `p` to `q` crosses row `y`
+        fn edge(p: (i16, i16), q: (i16, i16), y: i16) -> i16 {
+            if q.1 == p.1 {
+                return p.0;
+            }
+            (p.0 as i32 + (q.0 - p.0) as i32 * (y - p.1) as i32 / (q.1 - p.1) as i32) as i16
+        }
+
+        for y in a.1.max(0)..=c.1.min(screen::SIZE as i16 - 1) {
+            let long = edge(a, c, y);
+            let short = if y < b.1 { edge(a, b, y) } else { edge(b, c, y) };
+            self.fill_clipped(long, y, short, y, color);
+        }
+    }
+
+    /// Fill the rectangle from (`x0`, `y0`) to (`x1`, `y1`), corners included,
+    /// clipped to the screen, and wait for it.
+    fn fill_clipped(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, color: u8) {
+        let mut drawing = false;
+        self.fill_pieces(x0, y0, x1, y1, color, &mut drawing);
+        if drawing {
+            self.wait_blit();
+        }
+    }
 
-        // Top bar: 127px wide, 10px tall, at (0, 0)
-        self.draw_square(0, 0, 127, LETTERBOX_HEIGHT, BLACK);
-        self.wait_blit();
+    /// Fill the rectangle from (`x0`, `y0`) to (`x1`, `y1`), clipped, in as
+    /// many fills as it takes: a fill is at most 127 pixels along each side
+    /// (bit 7 of WIDTH and HEIGHT flips instead), so a full row or column
+    /// takes two. `drawing` says whether a blit is still going, to wait for
+    /// before the next, and is left set if one is.
+    fn fill_pieces(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, color: u8, drawing: &mut bool) {
+        for (x, y, width, height) in pieces(x0, y0, x1, y1) {
+            if *drawing {
+                self.wait_blit();
+            }
+            self.draw_square(x, y, width, height, color);
+            *drawing = true;
+        }
+    }
+}
 
-        // Top bar: remaining 1px column at (127, 0)
-        self.draw_square(127, 0, 1, LETTERBOX_HEIGHT, BLACK);
-        self.wait_blit();
+/// The bars covering `area`'s margins, as corners (x0, y0, x1, y1). A
+/// margin of 0 has no bar.
+fn margins(area: &SafeArea) -> impl Iterator<Item = (i16, i16, i16, i16)> {
+    let (top, bottom) = (area.top as i16, screen::SIZE as i16 - area.bottom as i16);
+    let (left, right) = (area.left as i16, screen::SIZE as i16 - area.right as i16);
+    [
+        (0, 0, screen::SIZE as i16 - 1, top - 1),
+        (0, bottom, screen::SIZE as i16 - 1, screen::SIZE as i16 - 1),
+        (0, top, left - 1, bottom - 1),
+        (right, top, screen::SIZE as i16 - 1, bottom - 1),
+    ]
+    .into_iter()
+    .filter(|&(x0, y0, x1, y1)| x1 >= x0 && y1 >= y0)
+}
+
+/// The fills, as (x, y, width, height), covering the rectangle from (`x0`,
+/// `y0`) to (`x1`, `y1`) clipped to the screen; the corners can be given in
+/// either order.
+fn pieces(x0: i16, y0: i16, x1: i16, y1: i16) -> impl Iterator<Item = (u8, u8, u8, u8)> {
+    let (left, right) = (x0.min(x1).max(0), x0.max(x1).min(screen::SIZE as i16 - 1));
+    let (top, bottom) = (y0.min(y1).max(0), y0.max(y1).min(screen::SIZE as i16 - 1));
+    let starts = |from: i16, to: i16| (from..=to).step_by(screen::SIZE as usize - 1);
+    starts(top, bottom).flat_map(move |y| {
+        let height = (bottom - y + 1).min(screen::SIZE as i16 - 1);
+        starts(left, right).map(move |x| {
+            let width = (right - x + 1).min(screen::SIZE as i16 - 1);
+            (x as u8, y as u8, width as u8, height as u8)
+        })
+    })
+}
 
-        // Bottom bar: 127px wide, 10px tall, at (0, 118)
-        self.draw_square(0, 128 - LETTERBOX_HEIGHT, 127, LETTERBOX_HEIGHT, BLACK);
-        self.wait_blit();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Bottom bar: remaining 1px column at (127, 118)
-        self.draw_square(127, 128 - LETTERBOX_HEIGHT, 1, LETTERBOX_HEIGHT, BLACK);
-        self.wait_blit();
+    #[test]
+    fn default_safe_area_leaves_column_0_alone() {
+        let area = SafeArea::DEFAULT;
+        let rows = area.top..screen::SIZE - area.bottom;
+        let covered = margins(&area)
+            .flat_map(|(x0, y0, x1, y1)| pieces(x0, y0, x1, y1))
+            .any(|(x, y, _, height)| x == 0 && y < rows.end && y + height > rows.start);
+        assert!(!covered);
+    }
 
-        // Right column: 1px wide, middle section (between letterbox bars)
-        // From y=10 to y=117 (108 pixels)
-        self.draw_square(127, LETTERBOX_HEIGHT, 1, 128 - (LETTERBOX_HEIGHT * 2), BLACK);
+    #[test]
+    fn full_rows_take_two_fills() {
+        assert!(pieces(0, 0, 127, 0).eq([(0, 0, 127, 1), (127, 0, 1, 1)]));
+        assert_eq!(pieces(-5, 3, -1, 9).count(), 0);
     }
 }
@@ -33,13 +33,30 @@
 //! }
 //! ```
 //!
-//! The framebuffer is row-major, 128 bytes per row.
+//! The framebuffer is row-major, 128 bytes per row. For effects the blitter
+//! can't do, like plasma or a per-pixel dissolve, there are helpers that do
+//! the addressing:
+//!
+//! ```ignore
+//! if let Some(mut fb) = console.dma.framebuffers(&mut console.video_flags) {
+//!     fb.set_pixel(10, 20, WHITE);
+//!     fb.hline(0, 64, 128, RED);
+//!     for (x, pixel) in fb.row(30).iter_mut().enumerate() {
+//!         *pixel = plasma(x as u8, 30, t);
+//!     }
+//! }
+//! ```
+//!
+//! Unlike the blitter's fill color, colors written here aren't inverted.
 
 use crate::{
     scr::VideoFlags,
     video_dma::{Blitter, VideoDma, spritemem::SpriteMem},
 };
 
+/// Pixels in a row, and rows in a framebuffer
+const WIDTH: usize = 128;
+
 /// Write video flags to the hardware register at $2007.
 #[inline(always)]
 fn write_video_flags(flags: VideoFlags) {
@@ -70,7 +87,7 @@ impl Framebuffers {
 
 /// Exclusive access to framebuffer memory.
 ///
-/// Provides direct pixel access (`bytes`).
+/// Provides direct pixel access (`bytes`, `row`, `set_pixel` and the line helpers).
 /// For double buffering, use [`Console::flip_framebuffers`](crate::console::Console::flip_framebuffers).
 /// Released back to [`DmaManager`](super::DmaManager) when dropped.
 pub struct FramebuffersGuard<'a> {
@@ -97,4 +114,50 @@ impl<'a> FramebuffersGuard<'a> {
     pub fn bytes(&mut self) -> &mut [u8; 0x4000] {
         unsafe { &mut *(0x4000 as *mut [u8; 0x4000]) }
     }
+
+    /// Row `y` (0-127) of the framebuffer, left to right.
+    #[inline(always)]
+    pub fn row(&mut self, y: u8) -> &mut [u8; WIDTH] {
+        let start = (y & 0x7F) as usize * WIDTH;
+        unsafe { &mut *(0x4000 as *mut u8).add(start).cast::<[u8; WIDTH]>() }
+    }
+
+    /// Set the pixel at (`x`, `y`). Off-screen coordinates are ignored.
+    #[inline(always)]
+    pub fn set_pixel(&mut self, x: u8, y: u8, color: u8) {
+        if x < WIDTH as u8 && y < WIDTH as u8 {
+            self.row(y)[x as usize] = color;
+        }
+    }
+
+    /// The color of the pixel at (`x`, `y`), or 0 off-screen.
+    #[inline(always)]
+    pub fn pixel(&mut self, x: u8, y: u8) -> u8 {
+        if x < WIDTH as u8 && y < WIDTH as u8 { self.row(y)[x as usize] } else { 0 }
+    }
+
+    /// Fill `len` pixels of row `y` rightwards from `x`, clipped to the screen.
+    pub fn hline(&mut self, x: u8, y: u8, len: u8, color: u8) {
+        if x >= WIDTH as u8 || y >= WIDTH as u8 {
+            return;
+        }
+        let end = (x as usize + len as usize).min(WIDTH);
+        self.row(y)[x as usize..end].fill(color);
+    }
+
+    /// Fill `len` pixels of column `x` downwards from `y`, clipped to the screen.
+    pub fn vline(&mut self, x: u8, y: u8, len: u8, color: u8) {
+        if x >= WIDTH as u8 {
+            return;
+        }
+        let end = (y as usize + len as usize).min(WIDTH);
+        for row in y as usize..end {
+            self.bytes()[row * WIDTH + x as usize] = color;
+        }
+    }
+
+    /// Fill the whole framebuffer with `color`.
+    pub fn clear(&mut self, color: u8) {
+        self.bytes().fill(color);
+    }
 }
@@ -0,0 +1,176 @@
+//! # Sprite RAM Allocation
+//!
+//! Sprite RAM is 8 pages of 256×256 pixels, and keeping track of what went
+//! where by hand means a lot of page numbers and GX/GY offsets to keep in
+//! sync. A [`SpriteRam`] hands out the space instead, and gives back a
+//! [`SpriteTile`] saying where each thing went:
+//!
+//! ```ignore
+//! // every page but 0, which the scrolling map has
+//! let mut sprites = SpriteRam::new(0b1111_1110);
+//!
+//! let player = sprites.load_image(console, &assets::PLAYER).unwrap();
+//! let coin = sprites.load_image(console, &assets::COIN).unwrap();
+//!
+//! // each frame
+//! console.set_sprite_page(player.page);
+//! if let Some(mut blitter) = console.blitter() {
+//!     blitter.draw_tile(&player, x, y);
+//!     blitter.wait_blit();
+//! }
+//! ```
+//!
+//! Space is handed out in rows, left to right, and each piece stays inside
+//! one 128×128 quadrant so the CPU can load it in one go. Switching pages
+//! costs a register write per draw, so things drawn together should share a
+//! page: [`SpriteRam::alloc_on`] asks for a particular one.
+//!
+//! Nothing's freed a piece at a time. [`SpriteRam::clear_page`] frees a
+//! whole page, for loading the next level's sprites over the last one's.
+
+use crate::{
+    assets::{Image, SpriteTile},
+    blitter::SpriteQuadrant,
+    console::Console,
+    via::far_call,
+};
+
+/// Pixels along each side of a quadrant
+const QUADRANT: u16 = 128;
+
+/// The row being filled in one quadrant, and everything above it
+#[derive(Debug, Copy, Clone)]
+struct Shelf {
+    /// Where the current row starts
+    top: u8,
+    /// The tallest thing in the current row so far
+    height: u8,
+    /// Where the next thing in the row goes
+    x: u8,
+}
+
+impl Shelf {
+    const EMPTY: Self = Self { top: 0, height: 0, x: 0 };
+
+    /// Make room for `width` x `height`, returning where it goes in the quadrant
+    fn fit(&mut self, width: u8, height: u8) -> Option<(u8, u8)> {
+        let (top, width16, height16) = (self.top as u16, width as u16, height as u16);
+        if self.x as u16 + width16 <= QUADRANT && top + height16 <= QUADRANT {
+            let at = (self.x, self.top);
+            self.x += width;
+            self.height = self.height.max(height);
+            return Some(at);
+        }
+
+        let next = top + self.height as u16;
+        if next + height16 <= QUADRANT {
+            *self = Self { top: next as u8, height, x: width };
+            return Some((0, next as u8));
+        }
+        None
+    }
+}
+
+/// Keeps track of what's been put where in sprite RAM; see the
+/// [module docs](self)
+#[derive(Debug, Clone)]
+pub struct SpriteRam {
+    /// Four quadrants to a page, in [`SpriteQuadrant`] order
+    shelves: [Shelf; 32],
+    /// Bit n set if page n is ours to hand out
+    pages: u8,
+}
+
+impl SpriteRam {
+    /// An allocator over the pages set in `pages`, bit n for page n, with
+    /// all of them empty. Leave out pages that are managed some other way,
+    /// like a [`ScrollingMap`](crate::tilemap::ScrollingMap)'s.
+    pub const fn new(pages: u8) -> Self {
+        Self { shelves: [Shelf::EMPTY; 32], pages }
+    }
+
+    /// Room for a `width` x `height` sprite on whichever page has it, or
+    /// `None` if none do. Both have to be 1-128.
+    pub fn alloc(&mut self, width: u8, height: u8) -> Option<SpriteTile> {
+        (0..8).find_map(|page| self.alloc_on(page, width, height))
+    }
+
+    /// Room for a `width` x `height` sprite on page `page`, or `None` if it
+    /// doesn't fit there or the page isn't this allocator's
+    pub fn alloc_on(&mut self, page: u8, width: u8, height: u8) -> Option<SpriteTile> {
+        if page >= 8 || self.pages & (1 << page) == 0 {
+            return None;
+        }
+        if width == 0 || height == 0 || width as u16 > QUADRANT || height as u16 > QUADRANT {
+            return None;
+        }
+
+        for quadrant in [SpriteQuadrant::One, SpriteQuadrant::Two, SpriteQuadrant::Three, SpriteQuadrant::Four] {
+            let shelf = &mut self.shelves[page as usize * 4 + quadrant_index(&quadrant)];
+            if let Some((x, y)) = shelf.fit(width, height) {
+                return Some(SpriteTile {
+                    page,
+                    x: quadrant.value_gx() + x,
+                    y: quadrant.value_gy() + y,
+                    width,
+                    height,
+                });
+            }
+        }
+        None
+    }
+
+    /// Put `image` somewhere and copy it in, leaving its page selected.
+    /// `None` if there's no room, or it's bigger than a quadrant.
+    pub fn load_image(&mut self, console: &mut Console, image: &Image) -> Option<SpriteTile> {
+        let tile = self.alloc(image.width, image.height)?;
+        far_call(image.bank, || load(console, &tile, image.pixels));
+        Some(tile)
+    }
+
+    /// Free everything on page `page`. Tiles handed out from it before now
+    /// will be drawn over by whatever's loaded next.
+    pub fn clear_page(&mut self, page: u8) {
+        if page < 8 {
+            self.shelves[page as usize * 4..][..4].fill(Shelf::EMPTY);
+        }
+    }
+
+    /// Free every page
+    pub fn clear(&mut self) {
+        self.shelves = [Shelf::EMPTY; 32];
+    }
+
+    /// Whether nothing's been handed out from page `page` yet
+    pub fn is_page_empty(&self, page: u8) -> bool {
+        page >= 8 || self.shelves[page as usize * 4..][..4].iter().all(|shelf| shelf.x == 0)
+    }
+}
+
+fn quadrant_index(quadrant: &SpriteQuadrant) -> usize {
+    match quadrant {
+        SpriteQuadrant::One => 0,
+        SpriteQuadrant::Two => 1,
+        SpriteQuadrant::Three => 2,
+        SpriteQuadrant::Four => 3,
+    }
+}
+
+/// Copy `pixels`, row by row, into the space `tile` covers, leaving
+/// `tile.page` selected. `pixels` has to be readable, so switch in its bank
+/// first; a tile has to sit inside one quadrant, as ones from [`SpriteRam`]
+/// do. Needs the blitter and sprite RAM free.
+pub fn load(console: &mut Console, tile: &SpriteTile, pixels: &[u8]) {
+    console.set_sprite_page(tile.page);
+    if let Some(mut blitter) = console.blitter() {
+        blitter.set_vram_quad(SpriteQuadrant::containing(tile.x, tile.y));
+    }
+    let Some(mut sprite_mem) = console.dma.sprite_mem(&mut console.video_flags) else { return };
+    let ram = sprite_mem.bytes();
+
+    let (x, y) = ((tile.x % QUADRANT as u8) as usize, (tile.y % QUADRANT as u8) as usize);
+    let width = tile.width as usize;
+    for (row, line) in pixels.chunks(width).take(tile.height as usize).enumerate() {
+        ram[(y + row) * QUADRANT as usize + x..][..line.len()].copy_from_slice(line);
+    }
+}
@@ -0,0 +1,370 @@
+//! # GameTank Audio ABI
+//!
+//! Where things live in the audio coprocessor's 4KB of RAM, for each
+//! firmware, so the firmware, the ROM that drives it and the emulator that
+//! runs both agree without copying addresses around.
+//!
+//! Addresses here are ACP-side: what the firmware sees, from `$0000`. The
+//! main CPU sees audio RAM at [`ARAM_CPU_BASE`], so use [`cpu`] to get its
+//! view of one:
+//!
+//! ```ignore
+//! use gt_audio_abi::{cpu, wavetable_8ch};
+//!
+//! assert_eq!(cpu(wavetable_8ch::VOICE_BASE), 0x3041);
+//! ```
+//!
+//! The assembly firmware in `audiofw-src/` can't read these, so each of its
+//! `main.asm` files repeats its module's numbers with `.set`. Change both
+//! together; the tests check the `.set`s against the constants here.
+
+#![no_std]
+
+/// Where audio RAM starts on the main CPU's bus
+pub const ARAM_CPU_BASE: u16 = 0x3000;
+/// Size of audio RAM; it repeats through the ACP's lower 32KB
+pub const ARAM_SIZE: u16 = 0x1000;
+/// Mask from any ACP address below [`DAC_START`] to its byte of audio RAM
+pub const ARAM_MASK: u16 = ARAM_SIZE - 1;
+
+/// ACP writes from here up go to the DAC
+pub const DAC_START: u16 = 0x8000;
+/// Where the firmware writes each sample
+pub const DAC: u16 = 0x8040;
+
+/// Zero page below this belongs to the compiler's imaginary registers
+pub const ZP_RESERVED_END: u16 = 0x0041;
+/// The NMI, RESET and IRQ vectors
+pub const VECTORS: u16 = 0x0FFA;
+
+/// ACP-side address `acp` as the main CPU sees it
+pub const fn cpu(acp: u16) -> usize {
+    ARAM_CPU_BASE as usize + acp as usize
+}
+
+/// Log-scaled volume: decibels, mapped onto whatever steps a firmware's
+/// volume control has. Everything here is meant to run at compile time; the
+/// ROM only ever sees the tables.
+pub mod volume {
+    use core::f64::consts::{FRAC_PI_2, LN_10, PI};
+
+    /// `10^(db / 20)`: how loud `db` decibels (0 or less) is next to full
+    pub const fn gain(db: f64) -> f64 {
+        // e^x's series is quick for small x, so take a 16th and square it
+        // back up
+        let x = db * LN_10 / 20.0 / 16.0;
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        let mut n = 1;
+        while n < 10 {
+            term = term * x / n as f64;
+            sum += term;
+            n += 1;
+        }
+        let mut i = 0;
+        while i < 4 {
+            sum *= sum;
+            i += 1;
+        }
+        sum
+    }
+
+    /// Sine of `x` in 0..=PI
+    pub const fn sin(x: f64) -> f64 {
+        // symmetric about PI/2, where the series is accurate
+        let x = if x > FRAC_PI_2 { PI - x } else { x };
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 1;
+        while n < 8 {
+            term = -term * x * x / ((2 * n) * (2 * n + 1)) as f64;
+            sum += term;
+            n += 1;
+        }
+        sum
+    }
+
+    /// For each of `STEPS` levels, `db_per_step` decibels apart going down
+    /// from full, the setting in `gains` that sounds closest. `gains[0]` has
+    /// to be silence, and a level more than 6dB below the quietest other
+    /// setting gets it.
+    pub const fn levels<const N: usize, const STEPS: usize>(gains: &[f64; N], db_per_step: f64) -> [u8; STEPS] {
+        let mut quietest = 1.0;
+        let mut i = 1;
+        while i < N {
+            if gains[i] < quietest {
+                quietest = gains[i];
+            }
+            i += 1;
+        }
+
+        let mut table = [0; STEPS];
+        let mut step = 0;
+        while step < STEPS {
+            let target = gain(-(step as f64) * db_per_step);
+            if target >= quietest / 2.0 {
+                // closest by ratio, which is what the ear hears
+                let mut best = 1;
+                let mut best_ratio = f64::MAX;
+                let mut i = 1;
+                while i < N {
+                    let ratio = if gains[i] > target { gains[i] / target } else { target / gains[i] };
+                    if ratio < best_ratio {
+                        best = i;
+                        best_ratio = ratio;
+                    }
+                    i += 1;
+                }
+                table[step] = best as u8;
+            }
+            step += 1;
+        }
+        table
+    }
+}
+
+/// The 8-voice wavetable synth (`audio-wavetable-8ch`)
+pub mod wavetable_8ch {
+    pub const VOICE_BASE: u16 = 0x0041;
+    pub const VOICE_SIZE: u16 = 7;
+    pub const VOICE_COUNT: usize = 8;
+
+    /// Offsets into a voice
+    pub mod voice {
+        pub const PHASE: u16 = 0;
+        pub const FREQUENCY: u16 = 2;
+        pub const WAVETABLE: u16 = 4;
+        pub const VOLUME: u16 = 6;
+    }
+
+    /// Envelopes, one per voice, with the voices' stride
+    pub const ENVELOPE_BASE: u16 = 0x0080;
+
+    /// Offsets into an envelope
+    pub mod envelope {
+        pub const STAGE: u16 = 0;
+        pub const LEVEL: u16 = 1;
+        pub const ATTACK: u16 = 2;
+        pub const DECAY: u16 = 3;
+        pub const SUSTAIN: u16 = 4;
+        pub const RELEASE: u16 = 5;
+    }
+
+    /// Envelope stages
+    pub mod stage {
+        pub const OFF: u8 = 0;
+        pub const ATTACK: u8 = 1;
+        pub const DECAY: u8 = 2;
+        pub const SUSTAIN: u8 = 3;
+        pub const RELEASE: u8 = 4;
+        pub const DONE: u8 = 5;
+    }
+
+    /// Non-zero switches [`NOISE_VOICE`] to noise
+    pub const NOISE_MODE: u16 = 0x00B8;
+    pub const NOISE_VOICE: usize = 7;
+
+    /// Slides, one per voice, with the voices' stride
+    pub const SLIDE_BASE: u16 = 0x00B9;
+
+    /// Offsets into a slide
+    pub mod slide {
+        pub const STEP: u16 = 0;
+        pub const TARGET: u16 = 2;
+        pub const VOLUME_STEP: u16 = 4;
+        pub const VOLUME_TARGET: u16 = 5;
+    }
+
+    /// Samples between envelope and slide steps
+    pub const ENV_PERIOD: u8 = 64;
+
+    pub const VOLUME_TABLE: u16 = 0x0200;
+    /// Volume settings run 0-63
+    pub const VOLUME_MAX: u8 = 63;
+    /// How loud each volume setting is, 0.0-1.0. The volume table is a sine
+    /// half the size of a wavetable's, and a voice plays the difference
+    /// between two lookups the volume apart, so its loudness follows the
+    /// sine's first quarter.
+    pub const VOLUME_GAIN: [f64; VOLUME_MAX as usize + 1] = {
+        use core::f64::consts::PI;
+        let full = super::volume::sin(PI * VOLUME_MAX as f64 / 128.0);
+        let mut gains = [0.0; VOLUME_MAX as usize + 1];
+        let mut v = 0;
+        while v <= VOLUME_MAX as usize {
+            gains[v] = super::volume::sin(PI * v as f64 / 128.0) / full;
+            v += 1;
+        }
+        gains
+    };
+
+    pub const WAVETABLE_BASE: u16 = 0x0300;
+    pub const WAVETABLE_SIZE: u16 = 256;
+    pub const WAVETABLE_COUNT: usize = 10;
+}
+
+/// The 7-voice wavetable synth with linear volume (`audio-wavetable-7ch-linear`)
+pub mod wavetable_7ch_linear {
+    pub const VOICE_BASE: u16 = 0x0041;
+    pub const VOICE_SIZE: u16 = 9;
+    pub const VOICE_COUNT: usize = 7;
+
+    /// Offsets into a voice
+    pub mod voice {
+        pub const PHASE: u16 = 0;
+        pub const FREQUENCY: u16 = 2;
+        pub const WAVETABLE: u16 = 4;
+        pub const VOLUME_TABLE: u16 = 6;
+        pub const SHIFT: u16 = 8;
+    }
+
+    /// The four volume tables, loudest first
+    pub const VOLUME_TABLES: [u16; 4] = [0x0200, 0x0300, 0x0400, 0x0500];
+    /// How loud each volume table is
+    pub const VOLUME_TABLE_GAIN: [f64; 4] = [1.0, 0.875, 0.75, 0.625];
+    /// A shift this big or more silences a voice
+    pub const SILENT_SHIFT: u8 = 4;
+
+    pub const WAVETABLE_BASE: u16 = 0x0600;
+    pub const WAVETABLE_SIZE: u16 = 256;
+    pub const WAVETABLE_COUNT: usize = 6;
+}
+
+/// The 2-voice, 4-operator FM synth (`audio-fm-4op`)
+pub mod fm_4op {
+    pub const VOICE_BASE: u16 = 0x0041;
+    pub const VOICE_SIZE: u16 = 25;
+    pub const VOICE_COUNT: usize = 2;
+
+    /// Offsets into a voice
+    pub mod voice {
+        pub const ALGORITHM: u16 = 0;
+        /// The first operator; the rest follow every `OPERATOR_SIZE` bytes
+        pub const OPERATORS: u16 = 1;
+    }
+
+    pub const OPERATOR_SIZE: u16 = 6;
+    pub const OPERATOR_COUNT: usize = 4;
+
+    /// Offsets into an operator
+    pub mod operator {
+        pub const PHASE: u16 = 0;
+        pub const FREQUENCY: u16 = 2;
+        pub const ATTENUATION: u16 = 4;
+        pub const RATIO: u16 = 5;
+    }
+
+    /// The log-sine and exp tables
+    pub const TABLES: u16 = 0x0200;
+}
+
+/// The double-buffered sample streamer (`audio-pcm`)
+pub mod pcm {
+    /// Next sample to play
+    pub const PTR: u16 = 0x0041;
+    /// Buffer playing: 0 = A, 1 = B
+    pub const CURRENT: u16 = 0x0043;
+    /// Non-zero while buffer A holds samples to play
+    pub const FILLED_A: u16 = 0x0044;
+    /// Non-zero while buffer B holds samples to play
+    pub const FILLED_B: u16 = 0x0045;
+    /// Written by the main CPU before an NMI; cleared once taken
+    pub const COMMAND: u16 = 0x0046;
+    /// Non-zero until the firmware has rewound after a stop
+    pub const RESTART: u16 = 0x0047;
+
+    pub const BUFFER_A: u16 = 0x0400;
+    pub const BUFFER_B: u16 = 0x0800;
+    pub const BUFFER_SIZE: u16 = 0x0400;
+
+    /// Commands
+    pub mod cmd {
+        pub const FILLED_A: u8 = 0x01;
+        pub const FILLED_B: u8 = 0x02;
+        pub const STOP: u8 = 0x80;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    /// The value of `.set name, value` in firmware source, if it's a plain number
+    fn asm_set(asm: &str, name: &str) -> Option<u16> {
+        asm.lines().find_map(|line| {
+            let rest = line.trim().strip_prefix(".set ")?.strip_prefix(name)?.strip_prefix(',')?;
+            let value = rest.split(';').next()?.trim();
+            match value.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16).ok(),
+                None => value.parse().ok(),
+            }
+        })
+    }
+
+    fn check(asm: &str, expected: &[(&str, u16)]) {
+        for &(name, value) in expected {
+            assert_eq!(asm_set(asm, name), Some(value), "{} in main.asm", name);
+        }
+    }
+
+    #[test]
+    fn wavetable_8ch_matches_firmware() {
+        use wavetable_8ch::*;
+        check(include_str!("../../gametank/audiofw-src/wavetable-8ch/main.asm"), &[
+            ("VOICE_BASE", VOICE_BASE),
+            ("VOICE_SIZE", VOICE_SIZE),
+            ("VOICE_COUNT", VOICE_COUNT as u16),
+            ("ENV_BASE", ENVELOPE_BASE),
+            ("ENV_PERIOD", ENV_PERIOD as u16),
+            ("NOISE_MODE", NOISE_MODE),
+            ("NOISE_VOICE", NOISE_VOICE as u16),
+            ("SLIDE_BASE", SLIDE_BASE),
+            ("WAVETABLE_BASE", WAVETABLE_BASE),
+            ("WAVETABLE_SIZE", WAVETABLE_SIZE),
+            ("WAVETABLE_COUNT", WAVETABLE_COUNT as u16),
+        ]);
+    }
+
+    #[test]
+    fn wavetable_7ch_linear_matches_firmware() {
+        use wavetable_7ch_linear::*;
+        check(include_str!("../../gametank/audiofw-src/wavetable-7ch-linear/main.asm"), &[
+            ("VOICE_BASE", VOICE_BASE),
+            ("VOICE_SIZE", VOICE_SIZE),
+            ("VOICE_COUNT", VOICE_COUNT as u16),
+            ("WAVETABLE_BASE", WAVETABLE_BASE),
+            ("WAVETABLE_SIZE", WAVETABLE_SIZE),
+            ("WAVETABLE_COUNT", WAVETABLE_COUNT as u16),
+        ]);
+    }
+
+    #[test]
+    fn fm_4op_matches_firmware() {
+        use fm_4op::*;
+        check(include_str!("../../gametank/audiofw-src/fm-4op/main.asm"), &[
+            ("VOICE_BASE", VOICE_BASE),
+            ("VOICE_SIZE", VOICE_SIZE),
+            ("VOICE_COUNT", VOICE_COUNT as u16),
+            ("OP_SIZE", OPERATOR_SIZE),
+        ]);
+    }
+
+    #[test]
+    fn pcm_matches_firmware() {
+        use pcm::*;
+        check(include_str!("../../gametank/audiofw-src/pcm/main.asm"), &[
+            ("PTR_L", PTR),
+            ("CURRENT", CURRENT),
+            ("FILLED_A", FILLED_A),
+            ("FILLED_B", FILLED_B),
+            ("COMMAND", COMMAND),
+            ("RESTART", RESTART),
+            ("BUFFER_A", BUFFER_A),
+            ("BUFFER_B", BUFFER_B),
+            ("CMD_FILLED_A", cmd::FILLED_A as u16),
+            ("CMD_FILLED_B", cmd::FILLED_B as u16),
+            ("CMD_STOP", cmd::STOP as u16),
+        ]);
+    }
+}
@@ -1,5 +1,42 @@
 .section .text
 .global wait, return_from_interrupt, enable_irq_handler, disable_irq_handler, __set_v
+.global irq_save, irq_restore
+.global nmi_entry, irq_entry
+
+; Interrupt entry points, from the vector table in boot.rs. They save what a
+; Rust function is free to clobber (A, X, Y and __rc2-__rc19), call the
+; SDK's dispatcher for it, then put it all back.
+.macro dispatch handler
+    PHA
+    PHX
+    PHY
+    LDX #0
+1:
+    LDA __rc2,X
+    PHA
+    INX
+    CPX #18
+    BNE 1b
+
+    JSR \handler
+
+    LDX #17
+2:
+    PLA
+    STA __rc2,X
+    DEX
+    BPL 2b
+    PLY
+    PLX
+    PLA
+    RTI
+.endm
+
+nmi_entry:
+    dispatch vblank_nmi
+
+irq_entry:
+    dispatch irq_dispatch
 
 wait:
     WAI
@@ -16,6 +53,20 @@ disable_irq_handler:
     SEI
     RTS
 
+; Mask IRQs, returning the status register from before in A
+irq_save:
+    PHP
+    PLA
+    SEI
+    RTS
+
+; Put back the status register irq_save returned (A), unmasking IRQs
+; only if they were unmasked then
+irq_restore:
+    PHA
+    PLP
+    RTS
+
 ; Set the overflow flag (V)
 ; Used by llvm-mos for certain operations
 ; 65C02S has BIT #imm, so we can use immediate mode
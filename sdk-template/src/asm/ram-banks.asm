@@ -0,0 +1,60 @@
+; Reading and writing the RAM banks the program isn't running in
+;
+; Switching RAM banks swaps out all of $0000-$1FFF: zero page, the stack and
+; every static along with it. So these never touch RAM while the far bank is
+; switched in, except for the zero page bytes they copy their arguments into
+; first, one at a time, carried over in Y.
+;
+; Both take A = banking register value for the far bank,
+; X = banking register value to come back to, __rc2/__rc3 = address.
+; __rc2 and __rc3 in the far bank are clobbered; the SDK keeps clear of its
+; zero page and stack.
+;
+; Interrupts have to be off around these: the NMI and IRQ handlers are in
+; bank 0 too. gametank::ram masks IRQs and disables the vblank NMI first.
+
+.section .text
+.global ram_bank_peek, ram_bank_poke
+
+BANK_REG = 0x2005
+
+; Returns the byte at the address, in A
+ram_bank_peek:
+    sta __rc5
+    ldy __rc3
+    sta BANK_REG
+    sty __rc3
+    stx BANK_REG
+
+    ldy __rc2
+    lda __rc5
+    sta BANK_REG
+    sty __rc2
+
+    ldy #0
+    lda (__rc2),y
+    stx BANK_REG
+    rts
+
+; Writes __rc4 to the address
+ram_bank_poke:
+    sta __rc5
+    ldy __rc3
+    sta BANK_REG
+    sty __rc3
+    stx BANK_REG
+
+    ldy __rc2
+    lda __rc5
+    sta BANK_REG
+    sty __rc2
+    stx BANK_REG
+
+    ldy __rc4
+    lda __rc5
+    sta BANK_REG
+    tya
+    ldy #0
+    sta (__rc2),y
+    stx BANK_REG
+    rts
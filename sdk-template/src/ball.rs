@@ -20,10 +20,11 @@ impl Ball {
         if self.x <= 32 {
             self.vx = 1;
         }
-        if self.y >= (118 - self.size) as i8 {
+        let field = crate::SAFE_AREA.playfield();
+        if self.y as i16 >= field.bottom() - self.size as i16 {
             self.vy = -1;
         }
-        if self.y < 10 {
+        if (self.y as i16) < field.y {
             self.vy = 1;
         }
     }
@@ -4,46 +4,44 @@
 #![allow(static_mut_refs)]
 
 use gametank::{
-    audio::FIRMWARE, boot::wait, console::Console, via::Via, video_dma::blitter::BlitterGuard,
+    audio::FIRMWARE, console::Console, frame::FrameClock, screen::SafeArea, via::Via, video_dma::blitter::BlitterGuard,
 };
+use gametank_asset_macros::{bank, gt_main};
 
 use crate::ball::init_balls;
 
-use gametank_asset_macros::include_bmp;
-
 mod audio_demo;
 mod ball;
 
-#[unsafe(link_section = ".rodata.bank124")]
-pub static GRADIENT_BACKGROUND: [u8; 128 * 128] = include_bmp!("assets/gradient.bmp");
+// everything listed in assets.toml
+gametank::include_assets!();
+
+// the part of the screen a TV shows; the balls bounce inside it
+pub const SAFE_AREA: SafeArea = SafeArea::DEFAULT;
 
 fn load_background_sprite(console: &mut Console) {
-    console.via.change_rom_bank(124);
+    console.via.change_rom_bank(assets::GRADIENT_BACKGROUND.bank);
     if let Some(mut sm) = console.dma.sprite_mem(&mut console.video_flags) {
-        sm.bytes().copy_from_slice(&GRADIENT_BACKGROUND);
+        sm.bytes().copy_from_slice(assets::GRADIENT_BACKGROUND.pixels);
     }
 }
 
-#[unsafe(no_mangle)]
-#[unsafe(link_section = ".text.bank126")]
+// lives in bank 126; calling it switches there and back
+#[bank(126)]
 fn draw_background(blitter: &mut BlitterGuard) {
     blitter.draw_sprite(0, 0, 0, 0, 127, 127);
 }
 
-#[unsafe(no_mangle)]
+#[gt_main]
 fn main(console: &mut Console) {
     load_background_sprite(console);
 
-    // load_background_sprite sets bank to 124, and our draw_background function is in bank 126
-    console.set_rom_bank(126);
-
     let mut sequencer = audio_demo::init_demo();
     let mut balls = init_balls();
 
+    let mut clock = FrameClock::new();
     loop {
-        unsafe {
-            wait();
-        }
+        clock.wait();
 
         console.flip_framebuffers();
 
@@ -69,7 +67,7 @@ fn main(console: &mut Console) {
         }
 
         // Apply letterbox to mask overscan areas before vsync
-        blitter.draw_letterbox();
+        blitter.draw_safe_area(&SAFE_AREA);
         blitter.wait_blit();
     }
 }
@@ -4,6 +4,8 @@ use alloc::vec::Vec;
 use core::ops::IndexMut;
 use dasp_graph::{Buffer, Input, NodeData};
 use dasp_interpolate::linear::Linear;
+use dasp_interpolate::sinc::Sinc;
+use dasp_ring_buffer::Fixed;
 use dasp_signal::Signal;
 use log::{debug, error, trace, warn};
 use rtrb::{Consumer, Producer, RingBuffer};
@@ -42,6 +44,70 @@ impl Signal for GameTankSignal {
     }
 }
 
+/// Number of taps on either side of the sinc kernel
+const SINC_DEPTH: usize = 16;
+
+/// Corner frequency of the RC low-pass after the console's DAC
+pub const DEFAULT_DAC_CUTOFF_HZ: f32 = 4_800.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resampler {
+    /// Straight line between samples; cheap, but aliases badly
+    Linear,
+    /// Windowed sinc; band-limited, costs a few more multiplies per sample
+    Sinc,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AudioSettings {
+    pub resampler: Resampler,
+    /// Run the output through a model of the console's RC output stage
+    pub dac_filter: bool,
+    pub dac_cutoff_hz: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            resampler: Resampler::Sinc,
+            dac_filter: true,
+            dac_cutoff_hz: DEFAULT_DAC_CUTOFF_HZ,
+        }
+    }
+}
+
+/// Single-pole RC low-pass, run at the output sample rate
+pub struct RcLowPass {
+    alpha: f32,
+    state: f32,
+}
+
+impl RcLowPass {
+    pub fn new(cutoff_hz: f32, sample_rate: f64) -> Self {
+        Self {
+            alpha: Self::alpha(cutoff_hz, sample_rate),
+            state: 0.0,
+        }
+    }
+
+    /// Move the corner frequency, keeping the filter's state so there's no pop
+    pub fn set_cutoff(&mut self, cutoff_hz: f32, sample_rate: f64) {
+        self.alpha = Self::alpha(cutoff_hz, sample_rate);
+    }
+
+    fn alpha(cutoff_hz: f32, sample_rate: f64) -> f32 {
+        let dt = 1.0 / sample_rate as f32;
+        let rc = 1.0 / (2.0 * core::f32::consts::PI * cutoff_hz);
+        dt / (rc + dt)
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.state += self.alpha * (input - self.state);
+        self.state
+    }
+}
+
 pub struct GameTankAudio {
     pub producer: Producer<u8>,
 
@@ -51,18 +117,31 @@ pub struct GameTankAudio {
     pub output_buffer: Consumer<Buffer>,
 
     pub sample_rate: f64,
+    pub target_sample_rate: f64,
+    pub settings: AudioSettings,
     pub converter: Box<dyn Signal<Frame = f32> + Send>,
+    pub filter: Option<RcLowPass>,
 }
 
 impl GameTankAudio {
-    pub fn new(sample_rate: f64, target_sample_rate: f64) -> Self {
+    pub fn new(sample_rate: f64, target_sample_rate: f64, settings: AudioSettings) -> Self {
         // Ring buffer sized to hold a decent amount of samples to avoid underruns
         let (input_producer, input_buffer) = RingBuffer::<u8>::new(4096);
         let (output_producer, output_consumer) = RingBuffer::<Buffer>::new(4096);
-        let interp = Linear::new(0.0, 0.0);
 
         let signal = GameTankSignal::new(input_buffer);
-        let converter = signal.from_hz_to_hz(interp, sample_rate, target_sample_rate);
+        let converter: Box<dyn Signal<Frame = f32> + Send> = match settings.resampler {
+            Resampler::Linear => {
+                let interp = Linear::new(0.0, 0.0);
+                Box::new(signal.from_hz_to_hz(interp, sample_rate, target_sample_rate))
+            }
+            Resampler::Sinc => {
+                let interp = Sinc::new(Fixed::from([0.0f32; SINC_DEPTH * 2]));
+                Box::new(signal.from_hz_to_hz(interp, sample_rate, target_sample_rate))
+            }
+        };
+
+        let filter = settings.dac_filter.then(|| RcLowPass::new(settings.dac_cutoff_hz, target_sample_rate));
 
         Self {
             producer: input_producer,
@@ -70,13 +149,35 @@ impl GameTankAudio {
             output_queue: output_producer,
             output_buffer: output_consumer,
             sample_rate,
-            converter: Box::new(converter),
+            target_sample_rate,
+            settings,
+            converter,
+            filter,
         }
     }
 
+    /// Take on new filter settings without rebuilding the resampler.
+    /// A different resampler still needs a new `GameTankAudio`.
+    pub fn update_filter(&mut self, settings: AudioSettings) {
+        self.filter = match (self.filter.take(), settings.dac_filter) {
+            (Some(mut filter), true) => {
+                filter.set_cutoff(settings.dac_cutoff_hz, self.target_sample_rate);
+                Some(filter)
+            }
+            (None, true) => Some(RcLowPass::new(settings.dac_cutoff_hz, self.target_sample_rate)),
+            (_, false) => None,
+        };
+        self.settings = settings;
+    }
+
     pub fn convert_to_output_buffers(&mut self) {
         while !self.converter.is_exhausted() {
-            self.resampled.push_back(self.converter.next());
+            let sample = self.converter.next();
+            let sample = match &mut self.filter {
+                Some(filter) => filter.process(sample),
+                None => sample,
+            };
+            self.resampled.push_back(sample);
         }
 
         while self.resampled.len() >= 64 && self.output_queue.slots() >= 8 {
@@ -6,24 +6,39 @@ extern crate alloc;
 use alloc::boxed::Box;
 use log::{error};
 use gte_w65c02s::{System, W65C02S};
+use gt_audio_abi::{ARAM_MASK, ARAM_SIZE, DAC_START};
 
 pub mod audio_output;
 
-pub static mut ARAM: &'static mut [u8; 0x1000]  = &mut [0; 0x1000];
+/// The ACP's 4KB of RAM, which the main CPU sees at $3000
+pub type Aram = [u8; ARAM_SIZE as usize];
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct AcpBus {
     pub irq_counter: i32,
     
     pub sample: u8,
+
+    // boxed, like the cpu bus's ram, to keep it off the stack
+    pub aram: Box<Aram>,
+}
+
+impl Default for AcpBus {
+    fn default() -> Self {
+        Self {
+            irq_counter: 0,
+            sample: 0,
+            aram: Box::new([0; ARAM_SIZE as usize]),
+        }
+    }
 }
 
 impl AcpBus {
     #[inline(always)]
     pub(crate) fn write_byte(&mut self, address: u16, data: u8) {
-        unsafe { *ARAM.get_unchecked_mut((address & 0x0FFF) as usize) = data; }
+        unsafe { *self.aram.get_unchecked_mut((address & ARAM_MASK) as usize) = data; }
         match address {
-            0x8000..=0xFFFF => {
+            DAC_START..=0xFFFF => {
                 self.sample = data;
             }
             _ => {}
@@ -32,7 +47,7 @@ impl AcpBus {
 
     #[inline(always)]
     pub(crate) fn read_byte(&self, address: u16) -> u8 {
-        unsafe { *ARAM.get_unchecked((address as usize) & 0x0FFF) }
+        unsafe { *self.aram.get_unchecked((address & ARAM_MASK) as usize) }
     }
 }
 
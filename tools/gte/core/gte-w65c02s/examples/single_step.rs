@@ -0,0 +1,210 @@
+//! Runs the [65x02 single-step test vectors](https://github.com/SingleStepTests/65x02)
+//! against this core.
+//!
+//! ```sh
+//! git clone https://github.com/SingleStepTests/65x02
+//! cargo run --release --example single_step -- 65x02/wdc65c02/v1
+//! ```
+//!
+//! Pass a directory to run every `.json` in it, or individual files. Each case
+//! loads registers and RAM, steps once, and diffs registers and RAM against the
+//! expected final state. The core isn't bus-accurate, so cycle counts are only
+//! compared when `--cycles` is given. `--decimal` restricts the run to cases that
+//! start with the D flag set. `--verbose` prints every failing case rather than
+//! the first few per file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gte_w65c02s::{State, System, W65C02S, P_D};
+use serde::Deserialize;
+
+const FAILURES_SHOWN: usize = 3;
+
+#[derive(Deserialize)]
+struct TestCase {
+    name: String,
+    initial: CpuState,
+    #[serde(rename = "final")]
+    expected: CpuState,
+    cycles: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct CpuState {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+struct TestBus {
+    ram: Box<[u8; 0x10000]>,
+}
+
+impl System for TestBus {
+    fn read(&mut self, _cpu: &mut W65C02S, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn write(&mut self, _cpu: &mut W65C02S, addr: u16, data: u8) {
+        self.ram[addr as usize] = data;
+    }
+}
+
+struct Options {
+    paths: Vec<PathBuf>,
+    cycles: bool,
+    decimal_only: bool,
+    verbose: bool,
+}
+
+#[derive(Default)]
+struct Tally {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+fn run_case(case: &TestCase, bus: &mut TestBus, check_cycles: bool) -> Vec<String> {
+    for &(addr, value) in &case.initial.ram {
+        bus.ram[addr as usize] = value;
+    }
+
+    let mut cpu = W65C02S::new();
+    cpu.set_state(State::Running);
+    cpu.set_pc(case.initial.pc);
+    cpu.set_s(case.initial.s);
+    cpu.set_a(case.initial.a);
+    cpu.set_x(case.initial.x);
+    cpu.set_y(case.initial.y);
+    cpu.set_p(case.initial.p);
+
+    let cycles = cpu.step(bus);
+
+    let want = &case.expected;
+    let mut diffs = vec![];
+    let mut reg = |name: &str, got: u16, want: u16| {
+        if got != want {
+            diffs.push(format!("{}: got {:02X}, want {:02X}", name, got, want));
+        }
+    };
+    reg("pc", cpu.get_pc(), want.pc);
+    reg("s", cpu.get_s() as u16, want.s as u16);
+    reg("a", cpu.get_a() as u16, want.a as u16);
+    reg("x", cpu.get_x() as u16, want.x as u16);
+    reg("y", cpu.get_y() as u16, want.y as u16);
+    reg("p", cpu.get_p() as u16, want.p as u16);
+
+    for &(addr, value) in &want.ram {
+        let got = bus.ram[addr as usize];
+        if got != value {
+            diffs.push(format!("ram[{:04X}]: got {:02X}, want {:02X}", addr, got, value));
+        }
+    }
+
+    if check_cycles && cycles as usize != case.cycles.len() {
+        diffs.push(format!("cycles: got {}, want {}", cycles, case.cycles.len()));
+    }
+
+    // leave the bus clean for the next case
+    for &(addr, _) in case.initial.ram.iter().chain(&want.ram) {
+        bus.ram[addr as usize] = 0;
+    }
+
+    diffs
+}
+
+fn run_file(path: &Path, opts: &Options, bus: &mut TestBus, total: &mut Tally) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let cases: Vec<TestCase> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut tally = Tally::default();
+    for case in &cases {
+        if opts.decimal_only && case.initial.p & P_D == 0 {
+            tally.skipped += 1;
+            continue;
+        }
+
+        let diffs = run_case(case, bus, opts.cycles);
+        if diffs.is_empty() {
+            tally.passed += 1;
+            continue;
+        }
+
+        if opts.verbose || tally.failed < FAILURES_SHOWN {
+            println!("  FAIL {} ({})", case.name, diffs.join(", "));
+        }
+        tally.failed += 1;
+    }
+
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let status = if tally.failed == 0 { "ok" } else { "FAILED" };
+    println!("{:>10}: {:>6} passed, {:>6} failed, {:>6} skipped  {}", name, tally.passed, tally.failed, tally.skipped, status);
+
+    total.passed += tally.passed;
+    total.failed += tally.failed;
+    total.skipped += tally.skipped;
+    Ok(())
+}
+
+fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let mut jsons: Vec<_> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            jsons.sort();
+            files.extend(jsons);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn main() {
+    let mut opts = Options { paths: vec![], cycles: false, decimal_only: false, verbose: false };
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--cycles" => opts.cycles = true,
+            "--decimal" => opts.decimal_only = true,
+            "--verbose" => opts.verbose = true,
+            _ => opts.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if opts.paths.is_empty() {
+        eprintln!("usage: single_step [--cycles] [--decimal] [--verbose] <dir or .json>...");
+        std::process::exit(2);
+    }
+
+    let files = match collect_files(&opts.paths) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut bus = TestBus { ram: Box::new([0; 0x10000]) };
+    let mut total = Tally::default();
+
+    for file in &files {
+        if let Err(e) = run_file(file, &opts, &mut bus, &mut total) {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+
+    println!("total: {} passed, {} failed, {} skipped", total.passed, total.failed, total.skipped);
+    if total.failed > 0 {
+        std::process::exit(1);
+    }
+}
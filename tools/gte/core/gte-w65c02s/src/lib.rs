@@ -347,6 +347,42 @@ impl W65C02S {
     /// called during a `step`.
     #[inline(always)]
     pub fn get_state(&self) -> State { self.state }
+    /// Force the CPU into the given operating state. Mostly useful for test
+    /// harnesses that load register state directly instead of resetting.
+    #[inline(always)]
+    pub fn set_state(&mut self, state: State) { self.state = state }
+    /// Pack the entire processor state, including pending interrupts, into
+    /// bytes. Useful for save states.
+    pub fn to_bytes(&self) -> [u8; 13] {
+        let state = match self.state {
+            State::HasBeenReset => 0,
+            State::Running => 1,
+            State::AwaitingInterrupt => 2,
+            State::Stopped => 3,
+        };
+        let [pc_lo, pc_hi] = self.pc.to_le_bytes();
+        [state, pc_lo, pc_hi, self.a, self.x, self.y, self.s, self.p,
+         self.irq as u8, self.irq_pending as u8,
+         self.nmi as u8, self.nmi_edge as u8, self.nmi_pending as u8]
+    }
+    /// Rebuild a processor from the output of `to_bytes`. Returns `None` if
+    /// the bytes don't describe a valid state.
+    pub fn from_bytes(bytes: [u8; 13]) -> Option<W65C02S> {
+        let state = match bytes[0] {
+            0 => State::HasBeenReset,
+            1 => State::Running,
+            2 => State::AwaitingInterrupt,
+            3 => State::Stopped,
+            _ => return None,
+        };
+        Some(W65C02S {
+            state,
+            pc: u16::from_le_bytes([bytes[1], bytes[2]]),
+            a: bytes[3], x: bytes[4], y: bytes[5], s: bytes[6], p: bytes[7] | P_1,
+            irq: bytes[8] != 0, irq_pending: bytes[9] != 0,
+            nmi: bytes[10] != 0, nmi_edge: bytes[11] != 0, nmi_pending: bytes[12] != 0,
+        })
+    }
     /// Push a value onto the stack using the given `System`.
     #[inline(always)]
     pub fn push<S: System>(&mut self, system: &mut S, value: u8) {
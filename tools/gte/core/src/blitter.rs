@@ -1,197 +1,353 @@
-use log::{debug, info, warn};
-use crate::gametank_bus::{CpuBus};
-
-#[derive(Debug)]
-pub struct Blitter {
-    // start_time: Instant,
-
-    src_y: u8,
-    dst_y: u8,
-    height: u8,
-    flip_y: bool,
-
-    src_x: u8,
-    dst_x: u8,
-    width: u8,
-    flip_x: bool,
-
-    offset_x: u8,
-    offset_y: u8,
-
-    color_fill: bool,
-
-    color: u8,
-    blitting: bool,
-    cycles: i32,
-    pub irq_trigger: bool,
-}
-
-impl Blitter {
-    pub fn default() -> Self {
-        Self {
-            src_y: 0,
-            dst_y: 0,
-            height: 0,
-            flip_y: false,
-            src_x: 0,
-            dst_x: 0,
-            width: 0,
-            flip_x: false,
-            offset_x: 0,
-            offset_y: 0,
-            color_fill: false,
-            color: 0,
-            blitting: false,
-            cycles: 0,
-            irq_trigger: false,
-        }
-    }
-
-    pub fn clear_irq_trigger(&mut self) -> bool {
-        let result = self.irq_trigger;
-        self.irq_trigger = false;
-        result
-    }
-
-    pub fn cycle(&mut self, bus: &mut CpuBus) {
-        // debug!(target: "blitter", "{:?}", self);
-
-        let (bit_start, start_addressed) = bus.blitter.start.read_once();
-        if start_addressed {
-            self.irq_trigger = false;
-        }
-
-        // load y at blitter start
-        if !self.blitting && bit_start {
-            self.src_y = bus.blitter.gy;
-            self.dst_y = bus.blitter.vy;
-            self.height = bus.blitter.height & 0b01111111;
-            self.flip_y = bus.blitter.height & 0b10000000 != 0;
-            self.color = !bus.blitter.color;
-            self.color_fill = bus.system_control.dma_flags.dma_colorfill_enable();
-            self.blitting = true;
-            self.cycles = 0;
-
-            // latch for first line
-            self.src_x = bus.blitter.gx;
-            self.dst_x = bus.blitter.vx;
-            self.width = bus.blitter.width & 0b01111111;
-            self.flip_x = bus.blitter.width & 0b10000000 != 0;
-
-
-            debug!(target: "blitter", "starting blit from ({}, {}):({}, {}) page {} at ({}, {}); color mode {}, gcarry {}",
-                bus.blitter.gx, bus.blitter.gy,
-                bus.blitter.width, bus.blitter.height,
-                bus.system_control.banking_register.vram_page(),
-                bus.blitter.vx, bus.blitter.vy,
-                bus.system_control.dma_flags.dma_colorfill_enable(),
-                bus.system_control.dma_flags.dma_gcarry(),
-            );
-        }
-
-        if !self.blitting {
-            return
-        }
-
-        // don't update params during a blit line
-        if self.offset_x == 0 {
-            self.src_y = bus.blitter.gy;
-            self.dst_y = bus.blitter.vy;
-            self.height = bus.blitter.height & 0b01111111;
-            self.flip_y = bus.blitter.height & 0b10000000 != 0;
-        }
-
-        if self.offset_x >= self.width {
-            self.offset_x = 0;
-            self.offset_y += 1;
-        }
-
-        if self.offset_y >= self.height {
-            self.offset_y = 0;
-
-            self.blitting = false;
-            // bus.blitter.start = 0;
-            debug!("blit complete, copied {} pixels", self.cycles);
-            if bus.system_control.dma_flags.dma_irq() {
-                self.irq_trigger = true;
-            }
-            return
-        }
-
-
-        self.cycles += 1;
-
-        // if blitter is disabled, counters continue but no write occurs
-        if !bus.system_control.dma_flags.dma_enable() {
-            debug!(target: "blitter", "blit cycle skipped; dma access disabled. dma flags: {:08b}", bus.system_control.dma_flags.0);
-            self.offset_x += 1;
-            return
-        }
-
-        // get the next color to write
-        let color = if self.color_fill {
-            self.color
-        } else {
-            // select the page, this makes sense
-            let vram_page = bus.system_control.banking_register.vram_page() as usize;
-
-            // ok, src_x and src_y, that makes sense
-            let mut src_x_mod = self.src_x;
-            let mut src_y_mod = self.src_y;
-
-            let mut blit_src_x;
-            let mut blit_src_y;
-
-            if self.flip_x {
-                src_x_mod = !src_x_mod;
-                blit_src_x = src_x_mod.wrapping_sub(self.offset_x) as usize;
-            } else {
-                blit_src_x = (src_x_mod.wrapping_add(self.offset_x)) as usize;
-            }
-
-            if self.flip_y {
-                src_y_mod = !src_y_mod;
-                blit_src_y = (src_y_mod.wrapping_sub(self.offset_y)) as usize;
-            } else {
-                blit_src_y = (src_y_mod.wrapping_add(self.offset_y)) as usize;
-            }
-
-            // if gcarry is turned off, blits should tile 16x16
-            if !bus.system_control.dma_flags.dma_gcarry() {
-                blit_src_x = (src_x_mod.wrapping_add(self.offset_x % 16)) as usize;
-                blit_src_y = (src_y_mod.wrapping_add(self.offset_y % 16)) as usize;
-            }
-
-            let mut quad = 0;
-            if blit_src_x >= 128 {
-                quad += 128*128 - 128;
-            }
-            if blit_src_y >= 128 {
-                quad += 128*128;
-            }
-
-            bus.vram_banks[vram_page][blit_src_x + blit_src_y*128 + quad]
-        };
-
-        let out_x = self.dst_x.wrapping_add(self.offset_x) as usize;
-        let out_y = self.dst_y.wrapping_add(self.offset_y) as usize;
-        let out_fb = if bus.system_control.get_framebuffer_out() == 1 {
-            0
-        } else {
-            1
-        };
-
-        if out_x >= 128 || out_y >= 128 {
-            self.offset_x = self.offset_x.wrapping_add(1);
-            return
-        }
-
-        // write to active framebuffer, if not transparent
-        if bus.system_control.dma_flags.dma_opaque() || color != 0 {
-            bus.framebuffers[out_fb].borrow_mut()[out_x + out_y*128] = color;
-        }
-
-        // increment x offset
-        self.offset_x = self.offset_x.wrapping_add(1);
-    }
-}
+use log::{debug, info, warn};
+use crate::gametank_bus::{CpuBus};
+use crate::savestate::{StateError, StateReader, StateWriter};
+
+// These are estimates, not measurements: nobody has timed a blit on a real
+// console yet. If a capture disagrees, change them here along with the
+// cycle totals in the tests at the bottom of this file.
+
+/// cycles between writing the start register and the first pixel landing in the framebuffer
+pub const BLIT_START_LATENCY: u8 = 2;
+/// dead cycles spent reloading the x counter at the end of every row
+pub const BLIT_ROW_OVERHEAD: u8 = 1;
+/// cycles between the last pixel and the blit-complete irq reaching the cpu
+pub const BLIT_IRQ_LATENCY: u8 = 1;
+
+/// How closely the blitter follows the console's timing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlitterTiming {
+    /// Finish every blit before the cpu executes its next instruction.
+    Instant,
+    /// One pixel per cpu cycle, with no setup or row cost.
+    Fast,
+    /// One pixel per cpu cycle, plus start latency, row overhead and irq latency.
+    Accurate,
+}
+
+#[derive(Debug)]
+pub struct Blitter {
+    // start_time: Instant,
+
+    src_y: u8,
+    dst_y: u8,
+    height: u8,
+    flip_y: bool,
+
+    src_x: u8,
+    dst_x: u8,
+    width: u8,
+    flip_x: bool,
+
+    offset_x: u8,
+    offset_y: u8,
+
+    color_fill: bool,
+
+    color: u8,
+    blitting: bool,
+    cycles: i32,
+    stall_cycles: u8,
+    irq_delay: u8,
+    busy_cycles: u64,
+    pub irq_trigger: bool,
+    pub timing: BlitterTiming,
+}
+
+impl Blitter {
+    pub fn default() -> Self {
+        Self {
+            src_y: 0,
+            dst_y: 0,
+            height: 0,
+            flip_y: false,
+            src_x: 0,
+            dst_x: 0,
+            width: 0,
+            flip_x: false,
+            offset_x: 0,
+            offset_y: 0,
+            color_fill: false,
+            color: 0,
+            blitting: false,
+            cycles: 0,
+            stall_cycles: 0,
+            irq_delay: 0,
+            busy_cycles: 0,
+            irq_trigger: false,
+            timing: BlitterTiming::Fast,
+        }
+    }
+
+    pub fn with_timing(timing: BlitterTiming) -> Self {
+        Self {
+            timing,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_blitting(&self) -> bool {
+        self.blitting
+    }
+
+    /// Cycles spent blitting since the last call
+    pub fn take_busy_cycles(&mut self) -> u64 {
+        core::mem::take(&mut self.busy_cycles)
+    }
+
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        for b in [self.src_y, self.dst_y, self.height, self.src_x, self.dst_x, self.width,
+                  self.offset_x, self.offset_y, self.color, self.stall_cycles, self.irq_delay] {
+            w.u8(b);
+        }
+        for b in [self.flip_y, self.flip_x, self.color_fill, self.blitting, self.irq_trigger] {
+            w.bool(b);
+        }
+        w.i32(self.cycles);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        for b in [&mut self.src_y, &mut self.dst_y, &mut self.height, &mut self.src_x, &mut self.dst_x, &mut self.width,
+                  &mut self.offset_x, &mut self.offset_y, &mut self.color, &mut self.stall_cycles, &mut self.irq_delay] {
+            *b = r.u8()?;
+        }
+        for b in [&mut self.flip_y, &mut self.flip_x, &mut self.color_fill, &mut self.blitting, &mut self.irq_trigger] {
+            *b = r.bool()?;
+        }
+        self.cycles = r.i32()?;
+        Ok(())
+    }
+
+    /// Advance the blitter by however many cycles the cpu just spent.
+    pub fn run(&mut self, bus: &mut CpuBus, cpu_cycles: i32) {
+        if self.timing == BlitterTiming::Instant {
+            // latch a pending start, then drain the whole blit
+            self.cycle(bus);
+            while self.blitting {
+                self.cycle(bus);
+            }
+            return
+        }
+
+        for _ in 0..cpu_cycles {
+            self.cycle(bus);
+        }
+    }
+
+    pub fn clear_irq_trigger(&mut self) -> bool {
+        let result = self.irq_trigger;
+        self.irq_trigger = false;
+        result
+    }
+
+    pub fn cycle(&mut self, bus: &mut CpuBus) {
+        // debug!(target: "blitter", "{:?}", self);
+
+        let (bit_start, start_addressed) = bus.blitter.start.read_once();
+        if start_addressed {
+            self.irq_trigger = false;
+            self.irq_delay = 0;
+        }
+
+        if self.irq_delay > 0 {
+            self.irq_delay -= 1;
+            if self.irq_delay == 0 {
+                self.irq_trigger = true;
+            }
+        }
+
+        // load y at blitter start
+        if !self.blitting && bit_start {
+            self.src_y = bus.blitter.gy;
+            self.dst_y = bus.blitter.vy;
+            self.height = bus.blitter.height & 0b01111111;
+            self.flip_y = bus.blitter.height & 0b10000000 != 0;
+            self.color = !bus.blitter.color;
+            self.color_fill = bus.system_control.dma_flags.dma_colorfill_enable();
+            self.blitting = true;
+            self.cycles = 0;
+            self.stall_cycles = if self.timing == BlitterTiming::Accurate { BLIT_START_LATENCY } else { 0 };
+
+            // latch for first line
+            self.src_x = bus.blitter.gx;
+            self.dst_x = bus.blitter.vx;
+            self.width = bus.blitter.width & 0b01111111;
+            self.flip_x = bus.blitter.width & 0b10000000 != 0;
+
+
+            debug!(target: "blitter", "starting blit from ({}, {}):({}, {}) page {} at ({}, {}); color mode {}, gcarry {}",
+                bus.blitter.gx, bus.blitter.gy,
+                bus.blitter.width, bus.blitter.height,
+                bus.system_control.banking_register.vram_page(),
+                bus.blitter.vx, bus.blitter.vy,
+                bus.system_control.dma_flags.dma_colorfill_enable(),
+                bus.system_control.dma_flags.dma_gcarry(),
+            );
+        }
+
+        if !self.blitting {
+            return
+        }
+
+        self.busy_cycles += 1;
+
+        if self.stall_cycles > 0 {
+            self.stall_cycles -= 1;
+            return
+        }
+
+        // don't update params during a blit line
+        if self.offset_x == 0 {
+            self.src_y = bus.blitter.gy;
+            self.dst_y = bus.blitter.vy;
+            self.height = bus.blitter.height & 0b01111111;
+            self.flip_y = bus.blitter.height & 0b10000000 != 0;
+        }
+
+        if self.offset_x >= self.width {
+            self.offset_x = 0;
+            self.offset_y += 1;
+
+            // the counters reload between rows; nothing is written this cycle
+            if self.timing == BlitterTiming::Accurate && self.offset_y < self.height && BLIT_ROW_OVERHEAD > 0 {
+                self.stall_cycles = BLIT_ROW_OVERHEAD - 1;
+                return
+            }
+        }
+
+        if self.offset_y >= self.height {
+            self.offset_y = 0;
+
+            self.blitting = false;
+            // bus.blitter.start = 0;
+            debug!("blit complete, copied {} pixels", self.cycles);
+            if bus.system_control.dma_flags.dma_irq() {
+                if self.timing == BlitterTiming::Accurate {
+                    self.irq_delay = BLIT_IRQ_LATENCY;
+                } else {
+                    self.irq_trigger = true;
+                }
+            }
+            return
+        }
+
+
+        self.cycles += 1;
+
+        // if blitter is disabled, counters continue but no write occurs
+        if !bus.system_control.dma_flags.dma_enable() {
+            debug!(target: "blitter", "blit cycle skipped; dma access disabled. dma flags: {:08b}", bus.system_control.dma_flags.0);
+            self.offset_x += 1;
+            return
+        }
+
+        // get the next color to write
+        let color = if self.color_fill {
+            self.color
+        } else {
+            // select the page, this makes sense
+            let vram_page = bus.system_control.banking_register.vram_page() as usize;
+
+            // ok, src_x and src_y, that makes sense
+            let mut src_x_mod = self.src_x;
+            let mut src_y_mod = self.src_y;
+
+            let mut blit_src_x;
+            let mut blit_src_y;
+
+            if self.flip_x {
+                src_x_mod = !src_x_mod;
+                blit_src_x = src_x_mod.wrapping_sub(self.offset_x) as usize;
+            } else {
+                blit_src_x = (src_x_mod.wrapping_add(self.offset_x)) as usize;
+            }
+
+            if self.flip_y {
+                src_y_mod = !src_y_mod;
+                blit_src_y = (src_y_mod.wrapping_sub(self.offset_y)) as usize;
+            } else {
+                blit_src_y = (src_y_mod.wrapping_add(self.offset_y)) as usize;
+            }
+
+            // if gcarry is turned off, blits should tile 16x16
+            if !bus.system_control.dma_flags.dma_gcarry() {
+                blit_src_x = (src_x_mod.wrapping_add(self.offset_x % 16)) as usize;
+                blit_src_y = (src_y_mod.wrapping_add(self.offset_y % 16)) as usize;
+            }
+
+            let mut quad = 0;
+            if blit_src_x >= 128 {
+                quad += 128*128 - 128;
+            }
+            if blit_src_y >= 128 {
+                quad += 128*128;
+            }
+
+            bus.vram_banks[vram_page][blit_src_x + blit_src_y*128 + quad]
+        };
+
+        let out_x = self.dst_x.wrapping_add(self.offset_x) as usize;
+        let out_y = self.dst_y.wrapping_add(self.offset_y) as usize;
+        let out_fb = if bus.system_control.get_framebuffer_out() == 1 {
+            0
+        } else {
+            1
+        };
+
+        if out_x >= 128 || out_y >= 128 {
+            self.offset_x = self.offset_x.wrapping_add(1);
+            return
+        }
+
+        // write to active framebuffer, if not transparent
+        if bus.system_control.dma_flags.dma_opaque() || color != 0 {
+            bus.framebuffers[out_fb].borrow_mut()[out_x + out_y*128] = color;
+            if let Some(heatmap) = &mut bus.write_heatmap {
+                heatmap.record(out_x + out_y*128);
+            }
+        }
+
+        // increment x offset
+        self.offset_x = self.offset_x.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start a `width` x `height` fill and run it, returning the cycle the
+    /// blitter went idle on and the cycle the irq fired on, counting from 1
+    fn run_blit(timing: BlitterTiming, width: u8, height: u8) -> (u32, u32) {
+        let mut bus = CpuBus::default();
+        bus.blitter.write_byte(0x4004, width);
+        bus.blitter.write_byte(0x4005, height);
+        bus.blitter.write_byte(0x4006, 1);
+        let mut blitter = Blitter::with_timing(timing);
+
+        let (mut idle, mut irq) = (0, 0);
+        for cycle in 1..=1000 {
+            blitter.cycle(&mut bus);
+            if idle == 0 && !blitter.is_blitting() {
+                idle = cycle;
+            }
+            if blitter.clear_irq_trigger() {
+                irq = cycle;
+                break;
+            }
+        }
+        (idle, irq)
+    }
+
+    #[test]
+    fn accurate_blit_timing() {
+        // 2 start + 12 pixels + 2 row reloads, then the cycle that sees the
+        // last row is done, and the irq a cycle after
+        assert_eq!(run_blit(BlitterTiming::Accurate, 4, 3), (17, 18));
+    }
+
+    #[test]
+    fn fast_blit_timing() {
+        // a pixel a cycle, and the irq on the cycle it finishes
+        assert_eq!(run_blit(BlitterTiming::Fast, 4, 3), (13, 13));
+    }
+}
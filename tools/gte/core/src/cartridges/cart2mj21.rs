@@ -4,6 +4,7 @@ use log::warn;
 use crate::{
     cartridges::Cartridge,
     gametank_bus::{DDRA, IORA},
+    savestate::{StateError, StateReader, StateWriter},
 };
 
 /// Block lengths for the 35 blocks in the 2MB flash cartridge
@@ -69,6 +70,8 @@ pub struct Cartridge2M {
     pub bank_shifter: u8,
     pub bank_mask: u8,
     flash_state_machine: FlashStateMachine,
+    /// Set whenever a flash command changes the contents
+    flash_dirty: bool,
 }
 
 // VIA Port A bit masks
@@ -405,6 +408,42 @@ impl Cartridge2M {
         let range = Self::bank_range(bank);
         &self.data[range]
     }
+
+    /// Raw flash contents, in physical bank order
+    pub fn flash(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    /// Replace the flash contents with a copy taken from `flash`. Returns false if the size doesn't match.
+    pub fn restore_flash(&mut self, flash: &[u8]) -> bool {
+        if flash.len() != TOTAL_SIZE {
+            return false;
+        }
+        self.data.copy_from_slice(flash);
+        self.flash_dirty = false;
+        true
+    }
+
+    /// Whether the flash was written since the last call
+    pub fn take_flash_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.flash_dirty)
+    }
+
+    /// Flash contents and banking. An in-progress flash command sequence isn't kept,
+    /// so the state machine starts over from idle after a load.
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.bank_shifter);
+        w.u8(self.bank_mask);
+        w.bytes(&self.data[..]);
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.bank_shifter = r.u8()?;
+        self.bank_mask = r.u8()?;
+        r.bytes(&mut self.data[..])?;
+        self.flash_state_machine = FlashStateMachine::new();
+        Ok(())
+    }
 }
 
 impl Cartridge for Cartridge2M {
@@ -430,6 +469,7 @@ impl Cartridge for Cartridge2M {
             bank_shifter: 0,
             bank_mask: 0x7E,
             flash_state_machine: FlashStateMachine::new(),
+            flash_dirty: false,
         }
     }
 
@@ -453,6 +493,7 @@ impl Cartridge for Cartridge2M {
         if let Some(command) = should_execute {
             self.flash_state_machine
                 .execute_command(&mut self.data, self.bank_mask);
+            self.flash_dirty = true;
         }
     }
 
@@ -11,6 +11,7 @@ use crate::cartridges::cart2mj21::Cartridge2M;
 use crate::cartridges::cart8k::Cartridge8K;
 use crate::cartridges::cart16k::Cartridge16K;
 use crate::cartridges::cart32k::{Cartridge32K};
+use crate::savestate::{StateError, StateReader, StateWriter};
 
 pub trait Cartridge {
     fn from_slice(slice: &[u8]) -> Self;
@@ -32,6 +33,11 @@ pub enum CartridgeType {
 }
 
 impl CartridgeType {
+    /// Whether there's a cartridge for a ROM of `len` bytes; `from_slice` panics on any other size
+    pub fn supports_len(len: usize) -> bool {
+        matches!(len, 0x2000 | 0x4000 | 0x8000 | 0x200000)
+    }
+
     pub fn from_slice(slice: &[u8]) -> Self {
         match slice.len() {
             0x2000 => {
@@ -75,4 +81,63 @@ impl CartridgeType {
             _ => {}
         }
     }
+
+    /// ROM bank mapped at $8000-$BFFF, for carts that bank-switch
+    pub fn current_bank(&self) -> Option<u8> {
+        match self {
+            CartridgeType::Cart2m(c) => Some(c.bank_mask & 0x7F),
+            _ => None,
+        }
+    }
+
+    /// Writable (flash) contents, for carts that have any
+    pub fn flash_contents(&self) -> Option<&[u8]> {
+        match self {
+            CartridgeType::Cart2m(c) => Some(c.flash()),
+            _ => None,
+        }
+    }
+
+    /// Put back flash contents saved from `flash_contents`. Returns false if this cart can't take them.
+    pub fn restore_flash(&mut self, flash: &[u8]) -> bool {
+        match self {
+            CartridgeType::Cart2m(c) => c.restore_flash(flash),
+            _ => false,
+        }
+    }
+
+    /// Whether the flash changed since the last call; always false for ROM carts
+    pub fn take_flash_dirty(&mut self) -> bool {
+        match self {
+            CartridgeType::Cart2m(c) => c.take_flash_dirty(),
+            _ => false,
+        }
+    }
+
+    fn state_tag(&self) -> u8 {
+        match self {
+            CartridgeType::Cart8k(_) => 0,
+            CartridgeType::Cart16k(_) => 1,
+            CartridgeType::Cart32k(_) => 2,
+            CartridgeType::Cart2m(_) => 3,
+        }
+    }
+
+    /// ROM carts only record their type; flash carts also carry their contents
+    pub(crate) fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.state_tag());
+        if let CartridgeType::Cart2m(c) = self {
+            c.save_state(w);
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        if r.u8()? != self.state_tag() {
+            return Err(StateError::CartridgeMismatch);
+        }
+        if let CartridgeType::Cart2m(c) = self {
+            c.load_state(r)?;
+        }
+        Ok(())
+    }
 }
@@ -8,12 +8,11 @@ use core::fmt::{Debug, Formatter};
 use bytemuck::bytes_of;
 use heapless::{FnvIndexMap};
 use rtrb::PushError;
-use gte_acp::audio_output::GameTankAudio;
+use gte_acp::audio_output::{AudioSettings, GameTankAudio};
 use crate::blitter::Blitter;
 use crate::cartridges::CartridgeType;
 use crate::emulator::PlayState::{Paused, Playing, WasmInit};
 use crate::gametank_bus::{CpuBus};
-use gte_acp::AcpBus;
 use crate::inputs::{ControllerButton, InputCommand, KeyState};
 use crate::inputs::ControllerButton::{Down, Left, Right, Start, Up, A, B, C};
 use crate::inputs::InputCommand::{Controller1, Controller2, HardReset, PlayPause, SoftReset};
@@ -29,19 +28,54 @@ pub enum PlayState {
     Playing,
 }
 
+/// Wall time spent in each part of the machine, in milliseconds
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SubsystemTimes {
+    pub cpu_ms: f64,
+    pub blitter_ms: f64,
+    pub acp_ms: f64,
+}
+
+/// What the machine did over one frame, in cpu cycles
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameStats {
+    pub cpu_cycles: u64,
+    /// Cycles the cpu spent halted on WAI
+    pub wait_cycles: u64,
+    /// Cycles the blitter spent on a blit, including stalls
+    pub blitter_cycles: u64,
+}
+
+impl FrameStats {
+    pub fn wait_percent(&self) -> f32 {
+        self.percent_of_frame(self.wait_cycles)
+    }
+
+    pub fn blitter_percent(&self) -> f32 {
+        self.percent_of_frame(self.blitter_cycles)
+    }
+
+    fn percent_of_frame(&self, cycles: u64) -> f32 {
+        if self.cpu_cycles == 0 {
+            return 0.0
+        }
+        cycles as f32 * 100.0 / self.cpu_cycles as f32
+    }
+}
+
 pub trait TimeDaemon {
     fn get_now_ms(&self) -> f64;
 }
 
 pub struct Emulator<Clock: TimeDaemon> {
     pub cpu_bus: CpuBus,
-    pub acp_bus: AcpBus,
     pub cpu: W65C02S,
     pub acp: W65C02S,
 
     pub blitter: Blitter,
 
     pub clock_cycles_to_vblank: i32,
+    pub frame_count: u64,
 
     pub last_emu_tick: f64,
     pub cpu_ns_per_cycle: f64,
@@ -49,8 +83,12 @@ pub struct Emulator<Clock: TimeDaemon> {
     pub last_render_time: f64,
     pub audio_out: Option<GameTankAudio>,
     pub target_sample_rate: f64,
+    pub audio_settings: AudioSettings,
     pub play_state: PlayState,
     pub wait_counter: u64,
+    /// Stats for the last completed frame
+    pub frame_stats: FrameStats,
+    frame_stats_acc: FrameStats,
 
     pub input_state: FnvIndexMap<InputCommand, KeyState, 32>, // capacity of 32 entries
 
@@ -75,7 +113,6 @@ impl <Clock: TimeDaemon> Debug for Emulator<Clock> {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         f.debug_struct("Emulator")
             .field("cpu_bus", &self.cpu_bus)
-            .field("acp_bus", &self.acp_bus)
             .field("cpu", &self.cpu)
             .field("acp", &self.acp)
             .field("blitter", &self.blitter)
@@ -114,19 +151,22 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         Emulator {
             play_state,
             cpu_bus: bus,
-            acp_bus: AcpBus::default(),
             cpu,
             acp,
             blitter,
 
             clock_cycles_to_vblank: 59659,
+            frame_count: 0,
             last_emu_tick: last_cpu_tick_ms,
             cpu_frequency_hz,
             cpu_ns_per_cycle,
             last_render_time,
             audio_out: None,
             target_sample_rate,
+            audio_settings: AudioSettings::default(),
             wait_counter: 0,
+            frame_stats: FrameStats::default(),
+            frame_stats_acc: FrameStats::default(),
             input_state: Default::default(),
             clock,
         }
@@ -153,49 +193,83 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         let mut acp_cycle_accumulator = 0;
 
         while remaining_cycles > 0 {
-            if self.cpu.get_state() == AwaitingInterrupt {
-                self.wait_counter += 1;
-                // get cpu's current asm code
-            } else if self.wait_counter > 0 {
-                debug!("waited {} cycles", self.wait_counter);
-                self.wait_counter = 0;
-            }
+            remaining_cycles -= self.step(&mut acp_cycle_accumulator, None);
+        }
 
-            let cpu_cycles = self.cpu.step(&mut self.cpu_bus);
+        self.last_emu_tick = now_ms;
 
-            remaining_cycles -= cpu_cycles;
+        if !is_web && (now_ms - self.last_render_time) >= 16.67 {
+            debug!("time since last render: {}", now_ms - self.last_render_time);
+            self.last_render_time = now_ms;
+        }
+    }
 
-            acp_cycle_accumulator += cpu_cycles * 4;
+    /// Run one cpu instruction and everything that happens alongside it, returning the cycles spent.
+    /// If `times` is given, wall time per subsystem is accumulated into it.
+    pub fn step(&mut self, acp_cycle_accumulator: &mut i32, times: Option<&mut SubsystemTimes>) -> i32 {
+        let waiting = self.cpu.get_state() == AwaitingInterrupt;
+        if waiting {
+            self.wait_counter += 1;
+            // get cpu's current asm code
+        } else if self.wait_counter > 0 {
+            debug!("waited {} cycles", self.wait_counter);
+            self.wait_counter = 0;
+        }
 
-            // pass aram to acp
-            if self.cpu_bus.system_control.acp_enabled() {
-                self.run_acp(&mut acp_cycle_accumulator);
-            }
+        let profile = times.is_some();
+        let t_start = if profile { self.clock.get_now_ms() } else { 0.0 };
 
-            // blit
-            for _ in 0..cpu_cycles {
-                self.blitter.cycle(&mut self.cpu_bus);
-            }
-            // TODO: instant blit option
+        let cpu_cycles = self.cpu.step(&mut self.cpu_bus);
+        self.frame_stats_acc.cpu_cycles += cpu_cycles as u64;
+        if waiting {
+            self.frame_stats_acc.wait_cycles += cpu_cycles as u64;
+        }
+        let t_cpu = if profile { self.clock.get_now_ms() } else { 0.0 };
 
-            let blit_irq = self.blitter.irq_trigger;
-            if blit_irq {
-                debug!("blit irq");
-            }
-            self.cpu.set_irq(blit_irq);
+        *acp_cycle_accumulator += cpu_cycles * 4;
 
-            self.clock_cycles_to_vblank -= cpu_cycles;
-            if self.clock_cycles_to_vblank <= 0 {
-                self.vblank();
-            }
+        // pass aram to acp
+        if self.cpu_bus.system_control.acp_enabled() {
+            self.run_acp(acp_cycle_accumulator);
         }
+        let t_acp = if profile { self.clock.get_now_ms() } else { 0.0 };
 
-        self.last_emu_tick = now_ms;
+        // blit
+        self.blitter.run(&mut self.cpu_bus, cpu_cycles);
+        let t_blit = if profile { self.clock.get_now_ms() } else { 0.0 };
 
-        if !is_web && (now_ms - self.last_render_time) >= 16.67 {
-            debug!("time since last render: {}", now_ms - self.last_render_time);
-            self.last_render_time = now_ms;
+        if let Some(times) = times {
+            times.cpu_ms += t_cpu - t_start;
+            times.acp_ms += t_acp - t_cpu;
+            times.blitter_ms += t_blit - t_acp;
+        }
+
+        let blit_irq = self.blitter.irq_trigger;
+        if blit_irq {
+            debug!("blit irq");
         }
+        self.cpu.set_irq(blit_irq);
+
+        self.clock_cycles_to_vblank -= cpu_cycles;
+        if self.clock_cycles_to_vblank <= 0 {
+            self.vblank();
+        }
+
+        cpu_cycles
+    }
+
+    /// Run as fast as possible until the next vblank, ignoring wall time and play state.
+    /// Returns the number of cpu cycles emulated.
+    pub fn run_frame(&mut self, mut times: Option<&mut SubsystemTimes>) -> u64 {
+        let frame = self.frame_count;
+        let mut acp_cycle_accumulator = 0;
+        let mut cycles = 0;
+
+        while self.frame_count == frame {
+            cycles += self.step(&mut acp_cycle_accumulator, times.as_deref_mut()) as u64;
+        }
+
+        cycles
     }
 
     fn run_acp(&mut self, acp_cycle_accumulator: &mut i32) {
@@ -208,27 +282,32 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         }
 
         while *acp_cycle_accumulator > 0 {
-            let acp_cycles = self.acp.step(&mut self.acp_bus);
+            let acp_cycles = self.acp.step(&mut self.cpu_bus.acp_bus);
             *acp_cycle_accumulator -= acp_cycles;
-            self.acp_bus.irq_counter -= acp_cycles;
+            self.cpu_bus.acp_bus.irq_counter -= acp_cycles;
 
             // clear stuff ig
             self.acp.set_irq(false);
             self.acp.set_nmi(false);
 
-            if self.acp_bus.irq_counter <= 0 {
-                self.acp_bus.irq_counter = self.cpu_bus.system_control.sample_rate() as i32 * 4;
+            if self.cpu_bus.acp_bus.irq_counter <= 0 {
+                self.cpu_bus.acp_bus.irq_counter = self.cpu_bus.system_control.sample_rate() as i32 * 4;
                 self.acp.set_irq(true);
 
                 let sample_rate = self.cpu_frequency_hz / self.cpu_bus.system_control.sample_rate() as f64;
-                // if audio_out is none or mismatched sample rate
-                if self.audio_out.as_ref().map_or(true, |gta| gta.sample_rate != sample_rate) {
+                // if audio_out is none, or mismatched sample rate or resampler
+                if self.audio_out.as_ref().map_or(true, |gta| gta.sample_rate != sample_rate || gta.settings.resampler != self.audio_settings.resampler) {
                     warn!("recreated audio stream with new sample rate: {:.3}Hz ({})", sample_rate, self.cpu_bus.system_control.sample_rate());
-                    self.audio_out = Some(GameTankAudio::new(sample_rate, self.target_sample_rate));
+                    self.audio_out = Some(GameTankAudio::new(sample_rate, self.target_sample_rate, self.audio_settings));
                 }
 
                 if let Some(audio) = &mut self.audio_out {
-                    let next_sample_u8 = self.acp_bus.sample;
+                    // filter changes, like dragging the cutoff, apply in place
+                    if audio.settings != self.audio_settings {
+                        audio.update_filter(self.audio_settings);
+                    }
+
+                    let next_sample_u8 = self.cpu_bus.acp_bus.sample;
                     if let Err(e) = audio.producer.push(next_sample_u8) {
                         error!("not enough slots in audio producer: {e}");
                     }
@@ -244,6 +323,17 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
 
     fn vblank(&mut self) {
         self.clock_cycles_to_vblank += 59659;
+        self.frame_count += 1;
+
+        self.frame_stats = FrameStats {
+            blitter_cycles: self.blitter.take_busy_cycles(),
+            ..self.frame_stats_acc
+        };
+        self.frame_stats_acc = FrameStats::default();
+
+        if let Some(heatmap) = &mut self.cpu_bus.write_heatmap {
+            heatmap.end_frame();
+        }
 
         if self.cpu_bus.vblank_nmi_enabled() {
             self.cpu.set_nmi(true);
@@ -255,7 +345,7 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
         self.input_state.insert(input_command, state).expect("shit's full dog ://");
     }
 
-    fn process_inputs(&mut self) {
+    pub fn process_inputs(&mut self) {
         let keys: Vec<_> = self.input_state.keys().cloned().collect();  // Clone keys to avoid borrowing conflicts
 
         if keys.len() > 0 && self.play_state == WasmInit {
@@ -281,12 +371,14 @@ impl <Clock: TimeDaemon> Emulator<Clock> {
                 HardReset => {
                     // hard reset reinitializes memory/cpus
                     let cart = self.cpu_bus.cartridge.clone();
+                    let heatmap = self.cpu_bus.write_heatmap.take();
                     self.cpu_bus = CpuBus::default();
                     self.cpu_bus.cartridge = cart;
+                    self.cpu_bus.write_heatmap = heatmap;
                     self.cpu = W65C02S::new();
                     self.cpu.step(&mut self.cpu_bus); // take one initial step, to get through the reset vector
                     self.acp = W65C02S::new();
-                    self.blitter = Blitter::default();
+                    self.blitter = Blitter::with_timing(self.blitter.timing);
                 }
             }
             self.input_state.insert(*key, self.input_state[key].update()).expect("shit's full dog ://");
@@ -0,0 +1,88 @@
+//! Frontend traits
+//!
+//! Where frames go, where audio goes, and where controller state comes from.
+//! Native, headless, libretro and wasm frontends implement these and drive the
+//! emulator through [`Emulator::run_frame_with`], so they all share one code path.
+
+use alloc::vec::Vec;
+use crate::emulator::{Emulator, TimeDaemon};
+use crate::inputs::ControllerButton::{Down, Left, Right, Start, Up, A, B, C};
+use crate::inputs::InputCommand::{Controller1, Controller2};
+use crate::inputs::{InputCommand, KeyState};
+
+/// Every command an [`InputSource`] is asked about, once per frame
+pub const CONTROLLER_COMMANDS: [InputCommand; 16] = [
+    Controller1(Up), Controller1(Down), Controller1(Left), Controller1(Right),
+    Controller1(A), Controller1(B), Controller1(C), Controller1(Start),
+    Controller2(Up), Controller2(Down), Controller2(Left), Controller2(Right),
+    Controller2(A), Controller2(B), Controller2(C), Controller2(Start),
+];
+
+pub trait FrameSink {
+    /// Called once per frame with the displayed framebuffer, as palette indices
+    fn present(&mut self, framebuffer: &[u8; 128*128]);
+}
+
+pub trait AudioSink {
+    /// Mono samples at the emulator's target sample rate
+    fn push_samples(&mut self, samples: &[f32]);
+}
+
+pub trait InputSource {
+    fn is_pressed(&mut self, command: InputCommand) -> bool;
+}
+
+/// Discards video and audio, never presses anything
+pub struct NullFrontend;
+
+impl FrameSink for NullFrontend {
+    fn present(&mut self, _framebuffer: &[u8; 128*128]) {}
+}
+
+impl AudioSink for NullFrontend {
+    fn push_samples(&mut self, _samples: &[f32]) {}
+}
+
+impl InputSource for NullFrontend {
+    fn is_pressed(&mut self, _command: InputCommand) -> bool {
+        false
+    }
+}
+
+impl AudioSink for Vec<f32> {
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.extend_from_slice(samples);
+    }
+}
+
+impl<Clock: TimeDaemon> Emulator<Clock> {
+    /// Poll `input`, run one frame, then hand the audio it produced to `audio`
+    /// and the finished frame to `video`. Returns the number of cpu cycles emulated.
+    pub fn run_frame_with(&mut self, video: &mut dyn FrameSink, audio: &mut dyn AudioSink, input: &mut dyn InputSource) -> u64 {
+        for command in CONTROLLER_COMMANDS {
+            let pressed = input.is_pressed(command);
+            let state = match self.input_state.get(&command) {
+                Some(ks) => ks.update_state(pressed),
+                None => KeyState::new(pressed),
+            };
+            self.set_input_state(command, state);
+        }
+        self.process_inputs();
+
+        let cycles = self.run_frame(None);
+
+        self.drain_audio(audio);
+        video.present(&self.cpu_bus.read_full_framebuffer());
+
+        cycles
+    }
+
+    /// Move every finished audio buffer into `sink`
+    pub fn drain_audio(&mut self, sink: &mut dyn AudioSink) {
+        if let Some(audio_out) = &mut self.audio_out {
+            while let Ok(buf) = audio_out.output_buffer.pop() {
+                sink.push_samples(&buf[..]);
+            }
+        }
+    }
+}
@@ -5,12 +5,13 @@ use gte_w65c02s::{System, W65C02S};
 use crate::cartridges::cart2mj21::Cartridge2M;
 use crate::cartridges::CartridgeType;
 use crate::gametank_bus::reg_system_control::*;
-use gte_acp::ARAM;
+use gte_acp::AcpBus;
 use crate::gametank_bus::cpu_bus::ByteDecorator::{AudioRam, CpuStack, SystemRam, Unreadable, Vram, ZeroPage};
 use crate::gametank_bus::reg_blitter::{BlitStart, BlitterRegisters};
 use crate::gametank_bus::reg_etc::{new_framebuffer, BankingRegister, BlitterFlags, FrameBuffer, GraphicsMemoryMap, SharedFrameBuffer};
 use crate::gametank_bus::reg_system_control::*;
 use crate::inputs::GamePad;
+use crate::gametank_bus::{DebugLog, WriteHeatmap, DEBUG_PORT};
 
 const CURRENT_GAME: &[u8] = &[0; 0x2000];
 
@@ -29,6 +30,15 @@ pub enum ByteDecorator {
     Unreadable(u8),
 }
 
+impl ByteDecorator {
+    pub fn value(self) -> u8 {
+        match self {
+            Self::ZeroPage(b) | Self::CpuStack(b) | Self::SystemRam(b) | Self::AudioRam(b)
+            | Self::Vram(b) | Self::Framebuffer(b) | Self::Aram(b) | Self::Unreadable(b) => b,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CpuBus {
     pub system_control: SystemControl,
@@ -41,7 +51,14 @@ pub struct CpuBus {
 
     pub vram_quad_written: [bool; 32],
 
-    // pub aram: Option<ARAM>,
+    /// Framebuffer write counts, only tracked while a debugger asks for them
+    pub write_heatmap: Option<Box<WriteHeatmap>>,
+
+    /// Text the ROM printed through the debug port
+    pub debug_log: DebugLog,
+
+    /// The audio coprocessor's bus, whose RAM is mapped at $3000
+    pub acp_bus: AcpBus,
     pub cartridge: CartridgeType,
 }
 
@@ -74,8 +91,10 @@ impl Default for CpuBus {
             framebuffers: [new_framebuffer(0x00), new_framebuffer(0xFF)],
             vram_banks: Box::new([[0; 256*256]; 8]),
             cartridge: CartridgeType::from_slice(CURRENT_GAME),
-            // aram: Some(Box::new([0; 0x1000])),
+            acp_bus: AcpBus::default(),
             vram_quad_written: [false; 32],
+            write_heatmap: None,
+            debug_log: DebugLog::default(),
         };
 
         bus
@@ -135,6 +154,10 @@ impl CpuBus {
                 // println!("${:04X}={:08b}", address, data);
             }
 
+            DEBUG_PORT => {
+                self.debug_log.write(data);
+            }
+
             // versatile interface adapter (GPIO, timers)
             0x2800..=0x280F => {
                 // TODO: this is a bit hacky since the mutable via regs in "update_via" won't track changes after :/
@@ -147,8 +170,8 @@ impl CpuBus {
             }
 
             // audio RAM
-            0x3000..=0x3FFF => unsafe {
-                ARAM[(address - 0x3000) as usize] = data;
+            0x3000..=0x3FFF => {
+                self.acp_bus.aram[(address - 0x3000) as usize] = data;
             }
 
             // VRAM/Framebuffer/Blitter
@@ -157,6 +180,9 @@ impl CpuBus {
                     GraphicsMemoryMap::FrameBuffer => {
                         let fb = self.system_control.banking_register.framebuffer() as usize;
                         self.framebuffers[fb].borrow_mut()[address as usize - 0x4000] = data;
+                        if let Some(heatmap) = &mut self.write_heatmap {
+                            heatmap.record(address as usize - 0x4000);
+                        }
                     }
                     GraphicsMemoryMap::VRAM => {
                         let vram_page = self.system_control.banking_register.vram_page() as usize;
@@ -199,8 +225,8 @@ impl CpuBus {
             }
 
             // audio RAM
-            0x3000..=0x3FFF => unsafe {
-                return ARAM[(address - 0x3000) as usize];
+            0x3000..=0x3FFF => {
+                return self.acp_bus.aram[(address - 0x3000) as usize];
             }
 
             // VRAM/Framebuffer/Blitter
@@ -239,7 +265,7 @@ impl CpuBus {
             0x0200..=0x1FFF => { SystemRam(self.ram_banks[self.system_control.get_ram_bank()][address as usize]) },
             0x2000..=0x2009 => { Unreadable(self.system_control.peek_byte(address)) },
             // 0x2800..=0x280F => { Via(self.system_control.via_regs[(address & 0xF) as usize]) },
-            0x3000..=0x3FFF => unsafe { AudioRam(ARAM[(address - 0x3000) as usize]) },
+            0x3000..=0x3FFF => { AudioRam(self.acp_bus.aram[(address - 0x3000) as usize]) },
             0x4000..=0x7FFF => {
                 match self.system_control.get_graphics_memory_map() {
                     GraphicsMemoryMap::FrameBuffer => {
@@ -0,0 +1,67 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use log::info;
+
+/// Bytes written here are printed by the emulator, a line at a time.
+///
+/// Nothing on the console answers at $200A, so on hardware the writes go nowhere.
+/// The SDK's `debug!` macro writes here in debug builds.
+pub const DEBUG_PORT: u16 = 0x200A;
+
+/// Lines kept before the oldest are dropped
+const MAX_LINES: usize = 1000;
+/// A line with no newline is cut off here, so a stuck loop can't eat memory
+const MAX_LINE_LENGTH: usize = 256;
+
+/// What the ROM has written to [`DEBUG_PORT`]
+#[derive(Debug, Default)]
+pub struct DebugLog {
+    partial: Vec<u8>,
+    lines: VecDeque<String>,
+    /// Every line ever finished, including the ones dropped off the front
+    total: u64,
+}
+
+impl DebugLog {
+    /// Take a byte from the ROM. A newline or a zero ends the line.
+    pub fn write(&mut self, byte: u8) {
+        match byte {
+            b'\n' | 0 => self.finish_line(),
+            b'\r' => {}
+            _ => {
+                self.partial.push(byte);
+                if self.partial.len() >= MAX_LINE_LENGTH {
+                    self.finish_line();
+                }
+            }
+        }
+    }
+
+    fn finish_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.partial).into_owned();
+        self.partial.clear();
+        info!(target: "rom", "{}", line);
+
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.total += 1;
+    }
+
+    /// The most recent lines, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// How many lines have ever been written; goes up when anything new arrives
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn clear(&mut self) {
+        self.partial.clear();
+        self.lines.clear();
+    }
+}
@@ -3,6 +3,10 @@ mod cpu_bus;
 mod reg_system_control;
 mod reg_blitter;
 mod via_bus;
+mod write_heatmap;
+mod debug_log;
 
 pub use cpu_bus::*;
 pub use via_bus::*;
+pub use write_heatmap::*;
+pub use debug_log::*;
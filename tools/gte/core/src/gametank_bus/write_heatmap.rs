@@ -0,0 +1,37 @@
+use alloc::boxed::Box;
+
+/// Per-pixel framebuffer write counts, for spotting overdraw.
+///
+/// Counts accumulate over a frame from both cpu writes and blits, regardless of
+/// which framebuffer was targeted, and are latched into `last_frame` at vblank.
+#[derive(Debug)]
+pub struct WriteHeatmap {
+    counting: Box<[u8; 128*128]>,
+    last_frame: Box<[u8; 128*128]>,
+}
+
+impl Default for WriteHeatmap {
+    fn default() -> Self {
+        Self {
+            counting: Box::new([0; 128*128]),
+            last_frame: Box::new([0; 128*128]),
+        }
+    }
+}
+
+impl WriteHeatmap {
+    pub fn record(&mut self, index: usize) {
+        self.counting[index] = self.counting[index].saturating_add(1);
+    }
+
+    /// Latch this frame's counts and start counting the next one
+    pub fn end_frame(&mut self) {
+        core::mem::swap(&mut self.counting, &mut self.last_frame);
+        self.counting.fill(0);
+    }
+
+    /// Write counts for the most recently completed frame
+    pub fn last_frame(&self) -> &[u8; 128*128] {
+        &self.last_frame
+    }
+}
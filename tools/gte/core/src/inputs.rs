@@ -27,6 +27,31 @@ pub enum ControllerButton {
     C,
 }
 
+impl ControllerButton {
+    pub const ALL: [ControllerButton; 8] = [
+        ControllerButton::Up, ControllerButton::Down, ControllerButton::Left, ControllerButton::Right,
+        ControllerButton::B, ControllerButton::A, ControllerButton::Start, ControllerButton::C,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControllerButton::Up => "up",
+            ControllerButton::Down => "down",
+            ControllerButton::Left => "left",
+            ControllerButton::Right => "right",
+            ControllerButton::B => "b",
+            ControllerButton::A => "a",
+            ControllerButton::Start => "start",
+            ControllerButton::C => "c",
+        }
+    }
+
+    /// Parse a button name like "up", "a" or "start", ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name().eq_ignore_ascii_case(name))
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 #[derive(Eq, Hash, PartialEq)]
 pub enum InputCommand {
@@ -10,4 +10,6 @@ pub mod blitter;
 pub mod gametank_bus;
 pub mod cartridges;
 pub mod emulator;
+pub mod frontend;
 pub mod inputs;
+pub mod savestate;
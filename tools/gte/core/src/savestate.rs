@@ -0,0 +1,223 @@
+//! Save states
+//!
+//! A flat little-endian snapshot of everything a running program can observe:
+//! both cpus, every ram/vram bank, the framebuffers, system registers, blitter
+//! progress and (for flash carts) cartridge contents. Audio output and host input
+//! aren't saved; they're rebuilt as the emulator runs.
+
+use alloc::vec::Vec;
+use gte_w65c02s::W65C02S;
+use crate::emulator::{Emulator, TimeDaemon};
+
+pub const STATE_MAGIC: [u8; 4] = *b"GTES";
+pub const STATE_VERSION: u8 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// Not a save state at all
+    BadMagic,
+    /// Written by a different version of the emulator
+    UnsupportedVersion(u8),
+    /// Ran out of bytes partway through
+    Truncated,
+    /// The state was saved with a different kind of cartridge loaded
+    CartridgeMismatch,
+    /// A cpu's run state byte wasn't recognized
+    BadCpuState,
+}
+
+#[derive(Default)]
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.buf.push(v as u8);
+    }
+
+    pub fn i32(&mut self, v: i32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, v: &[u8]) {
+        self.buf.extend_from_slice(v);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < n {
+            return Err(StateError::Truncated);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, StateError> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(i32::from_le_bytes(b))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    pub fn bytes(&mut self, out: &mut [u8]) -> Result<(), StateError> {
+        out.copy_from_slice(self.take(out.len())?);
+        Ok(())
+    }
+
+    fn cpu(&mut self) -> Result<W65C02S, StateError> {
+        let mut b = [0; 13];
+        self.bytes(&mut b)?;
+        W65C02S::from_bytes(b).ok_or(StateError::BadCpuState)
+    }
+}
+
+impl<Clock: TimeDaemon> Emulator<Clock> {
+    /// Snapshot the whole machine. The size only depends on the cartridge type.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::default();
+        w.bytes(&STATE_MAGIC);
+        w.u8(STATE_VERSION);
+
+        w.bytes(&self.cpu.to_bytes());
+        w.bytes(&self.acp.to_bytes());
+        w.i32(self.clock_cycles_to_vblank);
+        w.u64(self.frame_count);
+
+        let sc = &self.cpu_bus.system_control;
+        w.u8(sc.reset_acp);
+        w.u8(sc.nmi_acp);
+        w.u8(sc.banking_register.0);
+        w.bytes(&sc.via_regs);
+        w.u8(sc.audio_enable_sample_rate);
+        w.u8(sc.dma_flags.0);
+        for pad in &sc.gamepads {
+            w.bool(pad.port_select);
+        }
+
+        let regs = &self.cpu_bus.blitter;
+        for b in [regs.vx, regs.vy, regs.gx, regs.gy, regs.width, regs.height, regs.start.write, regs.color] {
+            w.u8(b);
+        }
+        w.bool(regs.start.addressed);
+
+        for bank in self.cpu_bus.ram_banks.iter() {
+            w.bytes(bank);
+        }
+        for fb in &self.cpu_bus.framebuffers {
+            w.bytes(&fb.borrow()[..]);
+        }
+        for bank in self.cpu_bus.vram_banks.iter() {
+            w.bytes(bank);
+        }
+        for &quad in &self.cpu_bus.vram_quad_written {
+            w.bool(quad);
+        }
+        self.cpu_bus.cartridge.save_state(&mut w);
+
+        w.i32(self.cpu_bus.acp_bus.irq_counter);
+        w.u8(self.cpu_bus.acp_bus.sample);
+        w.bytes(&self.cpu_bus.acp_bus.aram[..]);
+
+        self.blitter.save_state(&mut w);
+
+        w.finish()
+    }
+
+    /// Restore a snapshot from `save_state`. The same kind of cartridge must be loaded.
+    /// On error the emulator may be partially overwritten and should be reset.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = StateReader::new(data);
+
+        let mut magic = [0; 4];
+        r.bytes(&mut magic)?;
+        if magic != STATE_MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = r.u8()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        self.cpu = r.cpu()?;
+        self.acp = r.cpu()?;
+        self.clock_cycles_to_vblank = r.i32()?;
+        self.frame_count = r.u64()?;
+
+        let sc = &mut self.cpu_bus.system_control;
+        sc.reset_acp = r.u8()?;
+        sc.nmi_acp = r.u8()?;
+        sc.banking_register.0 = r.u8()?;
+        r.bytes(&mut sc.via_regs)?;
+        sc.audio_enable_sample_rate = r.u8()?;
+        sc.dma_flags.0 = r.u8()?;
+        for pad in &mut sc.gamepads {
+            pad.port_select = r.bool()?;
+        }
+
+        let regs = &mut self.cpu_bus.blitter;
+        for b in [&mut regs.vx, &mut regs.vy, &mut regs.gx, &mut regs.gy, &mut regs.width, &mut regs.height, &mut regs.start.write, &mut regs.color] {
+            *b = r.u8()?;
+        }
+        regs.start.addressed = r.bool()?;
+
+        for bank in self.cpu_bus.ram_banks.iter_mut() {
+            r.bytes(bank)?;
+        }
+        for fb in &self.cpu_bus.framebuffers {
+            r.bytes(&mut fb.borrow_mut()[..])?;
+        }
+        for bank in self.cpu_bus.vram_banks.iter_mut() {
+            r.bytes(bank)?;
+        }
+        for quad in &mut self.cpu_bus.vram_quad_written {
+            *quad = r.bool()?;
+        }
+        self.cpu_bus.cartridge.load_state(&mut r)?;
+
+        self.cpu_bus.acp_bus.irq_counter = r.i32()?;
+        self.cpu_bus.acp_bus.sample = r.u8()?;
+        r.bytes(&mut self.cpu_bus.acp_bus.aram[..])?;
+
+        self.blitter.load_state(&mut r)?;
+
+        // stale samples from before the load would otherwise play out first
+        self.audio_out = None;
+        Ok(())
+    }
+}
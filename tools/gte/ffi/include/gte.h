@@ -0,0 +1,54 @@
+/* C API for the GameTank emulator core (gte-core-ffi) */
+
+#ifndef GTE_H
+#define GTE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define GTE_FRAMEBUFFER_SIZE (128 * 128)
+
+#define GTE_BUTTON_UP    (1 << 0)
+#define GTE_BUTTON_DOWN  (1 << 1)
+#define GTE_BUTTON_LEFT  (1 << 2)
+#define GTE_BUTTON_RIGHT (1 << 3)
+#define GTE_BUTTON_A     (1 << 4)
+#define GTE_BUTTON_B     (1 << 5)
+#define GTE_BUTTON_C     (1 << 6)
+#define GTE_BUTTON_START (1 << 7)
+
+typedef struct GteEmulator GteEmulator;
+
+GteEmulator *gte_create(double sample_rate);
+void gte_destroy(GteEmulator *gte);
+
+/* returns 0 on success, -1 on a null pointer or a size no cartridge has (8K, 16K, 32K or 2M) */
+int32_t gte_load_rom(GteEmulator *gte, const uint8_t *data, size_t len);
+
+/* runs until the next vblank, returns cpu cycles emulated */
+uint64_t gte_step_frame(GteEmulator *gte);
+uint64_t gte_frame_count(const GteEmulator *gte);
+
+/* out: GTE_FRAMEBUFFER_SIZE palette indices */
+void gte_get_framebuffer(const GteEmulator *gte, uint8_t *out);
+/* out: GTE_FRAMEBUFFER_SIZE * 4 bytes of RGBA */
+void gte_get_framebuffer_rgba(const GteEmulator *gte, uint8_t *out);
+
+/* controller: 0 or 1, buttons: mask of GTE_BUTTON_*; applied on the next step */
+void gte_set_input(GteEmulator *gte, uint8_t controller, uint8_t buttons);
+
+uint8_t gte_read_mem(const GteEmulator *gte, uint16_t address);
+
+/* drains up to max mono samples, returns how many were written; only the
+   most recent second is kept */
+size_t gte_read_audio(GteEmulator *gte, float *out, size_t max);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GTE_H */
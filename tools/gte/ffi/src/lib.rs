@@ -0,0 +1,196 @@
+//! C API for gte-core
+//!
+//! Lets other frontends (test rigs, launchers, scripting languages) embed the
+//! emulator through a handful of `extern "C"` functions. See `include/gte.h`
+//! for the matching header.
+//!
+//! The emulator is driven one frame at a time with [`gte_step_frame`]; wall
+//! time is never consulted, so runs are deterministic.
+
+use std::ptr;
+use std::slice;
+use gte_core::cartridges::CartridgeType;
+use gte_core::color_map::COLOR_MAP;
+use gte_core::emulator::{Emulator, TimeDaemon};
+use gte_core::frontend::{InputSource, NullFrontend};
+use gte_core::inputs::ControllerButton;
+use gte_core::inputs::InputCommand::{self, Controller1, Controller2};
+
+pub const GTE_FRAMEBUFFER_SIZE: usize = 128 * 128;
+/// Seconds of audio kept for `gte_read_audio`; older samples are dropped
+const MAX_QUEUED_AUDIO_SECONDS: f64 = 1.0;
+
+pub const GTE_BUTTON_UP: u8 = 1 << 0;
+pub const GTE_BUTTON_DOWN: u8 = 1 << 1;
+pub const GTE_BUTTON_LEFT: u8 = 1 << 2;
+pub const GTE_BUTTON_RIGHT: u8 = 1 << 3;
+pub const GTE_BUTTON_A: u8 = 1 << 4;
+pub const GTE_BUTTON_B: u8 = 1 << 5;
+pub const GTE_BUTTON_C: u8 = 1 << 6;
+pub const GTE_BUTTON_START: u8 = 1 << 7;
+
+/// Frames are stepped explicitly, so the clock is never read
+struct NullClock;
+
+impl TimeDaemon for NullClock {
+    fn get_now_ms(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Held buttons per controller, as masks of `GTE_BUTTON_*`
+struct ButtonMasks([u8; 2]);
+
+impl InputSource for ButtonMasks {
+    fn is_pressed(&mut self, command: InputCommand) -> bool {
+        let (mask, button) = match command {
+            Controller1(button) => (self.0[0], button),
+            Controller2(button) => (self.0[1], button),
+            _ => return false,
+        };
+        let bit = match button {
+            ControllerButton::Up => GTE_BUTTON_UP,
+            ControllerButton::Down => GTE_BUTTON_DOWN,
+            ControllerButton::Left => GTE_BUTTON_LEFT,
+            ControllerButton::Right => GTE_BUTTON_RIGHT,
+            ControllerButton::A => GTE_BUTTON_A,
+            ControllerButton::B => GTE_BUTTON_B,
+            ControllerButton::C => GTE_BUTTON_C,
+            ControllerButton::Start => GTE_BUTTON_START,
+        };
+        mask & bit != 0
+    }
+}
+
+/// Opaque handle handed out to C
+pub struct GteEmulator {
+    emu: Emulator<NullClock>,
+    audio: Vec<f32>,
+    buttons: ButtonMasks,
+}
+
+/// Create an emulator that resamples audio to `sample_rate`. Free it with `gte_destroy`.
+#[no_mangle]
+pub extern "C" fn gte_create(sample_rate: f64) -> *mut GteEmulator {
+    let emu = Emulator::init(NullClock, sample_rate);
+    Box::into_raw(Box::new(GteEmulator { emu, audio: Vec::new(), buttons: ButtonMasks([0; 2]) }))
+}
+
+/// # Safety
+/// `gte` must come from `gte_create` and not already be destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn gte_destroy(gte: *mut GteEmulator) {
+    if !gte.is_null() {
+        drop(Box::from_raw(gte));
+    }
+}
+
+/// Load a ROM image and reset. Returns 0 on success, -1 on bad arguments or a
+/// size no cartridge has.
+///
+/// # Safety
+/// `gte` must be a live handle and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gte_load_rom(gte: *mut GteEmulator, data: *const u8, len: usize) -> i32 {
+    let (Some(gte), false) = (gte.as_mut(), data.is_null()) else {
+        return -1;
+    };
+    // cartridge detection panics on sizes it doesn't know
+    if !CartridgeType::supports_len(len) {
+        return -1;
+    }
+    gte.emu.load_rom(slice::from_raw_parts(data, len));
+    0
+}
+
+/// Run until the next vblank. Returns the number of cpu cycles emulated.
+///
+/// # Safety
+/// `gte` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn gte_step_frame(gte: *mut GteEmulator) -> u64 {
+    let Some(gte) = gte.as_mut() else { return 0 };
+    let cycles = gte.emu.run_frame_with(&mut NullFrontend, &mut gte.audio, &mut gte.buttons);
+
+    // callers that never read the audio shouldn't grow it forever
+    let max = (gte.emu.target_sample_rate * MAX_QUEUED_AUDIO_SECONDS) as usize;
+    if gte.audio.len() > max {
+        gte.audio.drain(..gte.audio.len() - max);
+    }
+    cycles
+}
+
+/// Copy the displayed framebuffer as 16384 palette indices into `out`.
+///
+/// # Safety
+/// `gte` must be a live handle and `out` must have room for `GTE_FRAMEBUFFER_SIZE` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gte_get_framebuffer(gte: *const GteEmulator, out: *mut u8) {
+    let (Some(gte), false) = (gte.as_ref(), out.is_null()) else { return };
+    let fb = gte.emu.cpu_bus.read_full_framebuffer();
+    ptr::copy_nonoverlapping(fb.as_ptr(), out, GTE_FRAMEBUFFER_SIZE);
+}
+
+/// Copy the displayed framebuffer as RGBA8888 into `out`.
+///
+/// # Safety
+/// `gte` must be a live handle and `out` must have room for `GTE_FRAMEBUFFER_SIZE * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gte_get_framebuffer_rgba(gte: *const GteEmulator, out: *mut u8) {
+    let (Some(gte), false) = (gte.as_ref(), out.is_null()) else { return };
+    let fb = gte.emu.cpu_bus.read_full_framebuffer();
+    let out = slice::from_raw_parts_mut(out, GTE_FRAMEBUFFER_SIZE * 4);
+    for (px, &index) in out.chunks_exact_mut(4).zip(fb.iter()) {
+        let (r, g, b, a) = COLOR_MAP[index as usize];
+        px.copy_from_slice(&[r, g, b, a]);
+    }
+}
+
+/// Set the held buttons for controller 0 or 1, as a mask of `GTE_BUTTON_*`.
+/// Takes effect at the start of the next `gte_step_frame`.
+///
+/// # Safety
+/// `gte` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn gte_set_input(gte: *mut GteEmulator, controller: u8, buttons: u8) {
+    let Some(gte) = gte.as_mut() else { return };
+    if let Some(mask) = gte.buttons.0.get_mut(controller as usize) {
+        *mask = buttons;
+    }
+}
+
+/// Read a byte from the cpu's address space without side effects.
+/// Write-only registers read back as 0.
+///
+/// # Safety
+/// `gte` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn gte_read_mem(gte: *const GteEmulator, address: u16) -> u8 {
+    match gte.as_ref() {
+        Some(gte) => gte.emu.cpu_bus.peek_byte_decorated(address).value(),
+        None => 0,
+    }
+}
+
+/// Drain up to `max` mono f32 samples produced since the last call. Returns how many were written.
+/// Only the most recent second is kept, so read at least once a second to hear everything.
+///
+/// # Safety
+/// `gte` must be a live handle and `out` must have room for `max` floats.
+#[no_mangle]
+pub unsafe extern "C" fn gte_read_audio(gte: *mut GteEmulator, out: *mut f32, max: usize) -> usize {
+    let (Some(gte), false) = (gte.as_mut(), out.is_null()) else { return 0 };
+    let n = gte.audio.len().min(max);
+    ptr::copy_nonoverlapping(gte.audio.as_ptr(), out, n);
+    gte.audio.drain(..n);
+    n
+}
+
+/// Frames emulated since the emulator was created.
+///
+/// # Safety
+/// `gte` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn gte_frame_count(gte: *const GteEmulator) -> u64 {
+    gte.as_ref().map_or(0, |gte| gte.emu.frame_count)
+}
@@ -10,10 +10,14 @@ use std::ffi::c_uint;
 use std::time::Instant;
 use gte_core::color_map::COLOR_MAP;
 use gte_core::emulator::{Emulator, PlayState, TimeDaemon};
+use gte_core::frontend::{AudioSink, FrameSink, InputSource};
 use gte_core::inputs::{ControllerButton, InputCommand, KeyState};
 use gte_core::inputs::InputCommand::{Controller1, Controller2};
 use gte_core::inputs::KeyState::{JustPressed, JustReleased};
-use libretro_rs::prelude::env::{GetAvInfo, Init, Reset, Run, UnloadGame};
+use libretro_rs::prelude::env::{GetAvInfo, GetSerializeSize, Init, Reset, Run, Serialize, UnloadGame, Unserialize};
+
+const SAMPLE_RATE: f64 = 44100.0;
+const FRAMES_PER_SECOND: f64 = 3_579_545.0 / 59659.0;
 
 struct CoreEmulator {
     emu: Emulator<InstantClock>,
@@ -62,7 +66,7 @@ impl Default for CoreEmulator {
         input_bindings.insert((1, JoypadButton::Y), Controller2(ControllerButton::C));
 
         Self {
-            emu: Emulator::init(clock, 44100.0),
+            emu: Emulator::init(clock, SAMPLE_RATE),
             input_bindings,
             rendering_mode: None,
             pixel_format: None,
@@ -71,6 +75,39 @@ impl Default for CoreEmulator {
     }
 }
 
+/// Reads the emulator's bound commands from whatever the frontend polled this frame
+struct RetroInput<'a, C> {
+    callbacks: &'a mut C,
+    bindings: &'a HashMap<(c_uint, JoypadButton), InputCommand>,
+}
+
+impl<C: Callbacks> InputSource for RetroInput<'_, C> {
+    fn is_pressed(&mut self, command: InputCommand) -> bool {
+        self.bindings.iter()
+            .filter(|(_, bound)| **bound == command)
+            .any(|((port, button), _)| self.callbacks.is_joypad_button_pressed(DevicePort::new(*port), *button))
+    }
+}
+
+/// Interleaved stereo i16, as libretro's audio batch callback wants it
+struct StereoSamples(Vec<i16>);
+
+impl AudioSink for StereoSamples {
+    fn push_samples(&mut self, samples: &[f32]) {
+        for sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.0.push(sample); // left
+            self.0.push(sample); // right
+        }
+    }
+}
+
+impl FrameSink for FrameBufferThing {
+    fn present(&mut self, framebuffer: &[u8; 128*128]) {
+        self.video_frame = buffer_to_color_image(framebuffer);
+    }
+}
+
 pub fn buffer_to_color_image(framebuffer: &[u8; 128*128]) -> Vec<u8> {
     let mut pixels = Vec::with_capacity(128 * 128 * 2);
 
@@ -129,41 +166,21 @@ impl<'a> Core<'a> for CoreEmulator {
     }
 
     fn get_system_av_info(&self, env: &mut impl GetAvInfo) -> SystemAVInfo {
-        // default timing is 60FPS, 44.1KHz
-        SystemAVInfo::default_timings(GameGeometry::fixed(128, 128))
+        // one frame per vblank: 59659 cycles of the 3.579545MHz cpu clock
+        SystemAVInfo::new(GameGeometry::fixed(128, 128), SystemTiming::new(FRAMES_PER_SECOND, SAMPLE_RATE))
     }
 
     fn run(&mut self, env: &mut impl Run, callbacks: &mut impl Callbacks) -> InputsPolled {
         let inputs_polled = callbacks.poll_inputs();
-        // update emulator inputs
-        for ((port, button), command) in &self.input_bindings {
-            if let Some(ks) = self.emu.input_state.get(&command) {
-                self.emu.set_input_state(*command, ks.update_state(callbacks.is_joypad_button_pressed(DevicePort::new(*port), *button)))
-            } else {
-                self.emu.set_input_state(*command, KeyState::new(callbacks.is_joypad_button_pressed(DevicePort::new(*port), *button)))
-            }
-        }
-        
-        self.emu.process_cycles(false);
-        if let Some(ref mut audio_out) = &mut self.emu.audio_out {
-            let mut audio_samples = Vec::with_capacity(4096);
-            while !audio_out.output_buffer.is_empty() {
-                if let Ok(buffer) = audio_out.output_buffer.pop() {
-                    // is this going to kill perf???
-                    for sample in buffer.iter() {
-                        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
-                        audio_samples.push(sample); // left
-                        audio_samples.push(sample); // right
-                    }
-                }
-            }
-
-            callbacks.upload_audio_frame(audio_samples.as_slice());
-        }
 
+        // frontends pace us; step exactly one frame instead of following the wall clock
+        let mut audio = StereoSamples(Vec::with_capacity(4096));
+        let mut input = RetroInput { callbacks: &mut *callbacks, bindings: &self.input_bindings };
+        self.emu.run_frame_with(&mut self.framebuffer, &mut audio, &mut input);
 
-        let framebuffer = self.emu.cpu_bus.read_full_framebuffer();
-        self.framebuffer.video_frame = buffer_to_color_image(&framebuffer);
+        if self.emu.audio_out.is_some() {
+            callbacks.upload_audio_frame(audio.0.as_slice());
+        }
 
         let rendering_mode = self.rendering_mode.take().unwrap();
         let pixel_format = self.pixel_format.take().unwrap();
@@ -179,6 +196,24 @@ impl<'a> Core<'a> for CoreEmulator {
         self.emu.input_state.insert(InputCommand::HardReset, JustReleased);
     }
 
+    fn get_serialize_size(&self, env: &mut impl GetSerializeSize) -> usize {
+        // the state size only changes with the cartridge type, which is fixed once a game is loaded
+        self.emu.save_state().len()
+    }
+
+    fn serialize(&self, env: &mut impl Serialize, data: &mut [u8]) -> Result<(), CoreError> {
+        let state = self.emu.save_state();
+        let Some(out) = data.get_mut(..state.len()) else {
+            return Err(CoreError::new());
+        };
+        out.copy_from_slice(&state);
+        Ok(())
+    }
+
+    fn unserialize(&mut self, env: &mut impl Unserialize, data: &[u8]) -> Result<(), CoreError> {
+        self.emu.load_state(data).map_err(|_| CoreError::new())
+    }
+
     fn unload_game(self, env: &mut impl UnloadGame) -> Self::Init {
         // self will be dropped here, so we can just return the default state
         Self::default()
@@ -1,44 +1,67 @@
 use std::cell::{Cell, OnceCell};
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use egui::{epaint, vec2, Align, Button, Color32, Frame, Id, LayerId, Layout, Pos2, Rect, ResizeDirection, ScrollArea, TextureOptions, Ui, UiBuilder, Vec2, ViewportCommand};
 use egui_wgpu::ScreenDescriptor;
-use klingt::Klingt;
 use tracing::{error, info, warn};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
 use winit::event::{ElementState, KeyEvent, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
 use winit::window::{Window, WindowId};
+use crate::app_ui::audio_settings::AudioSettingsUI;
+use crate::app_ui::perf_hud::PerfHud;
+use crate::app_ui::debug_log::DebugLogWindow;
+use crate::app_ui::touch_gamepad::TouchGamepad;
 use crate::app_ui::gametankboy::GameTankBoyUI;
 use crate::app_ui::ram_inspector::MemoryInspector;
 use crate::app_ui::vram_viewer::{VRAMViewer, VRAMViewerLayout};
 use crate::app_uninit::App;
+use gte_core::blitter::BlitterTiming;
 use gte_core::color_map::{COLOR_MAP, COLOR_MAP_PERCEPTUALLY_AUTOMAPPED, COLOR_MAP_WRONG};
 use crate::egui_renderer::EguiRenderer;
 use gte_core::emulator::{Emulator, HEIGHT, WIDTH};
 use crate::graphics::GraphicsContext;
-use crate::audio::GameTankAudio; // <--- added
+use crate::emu_thread::EmuCommand;
+use crate::recent::RecentRoms;
+use crate::tabs::{check_rom_size, read_rom, tab_name, verify_rom, EmuTab};
+use crate::app_ui::elf_dialog::{ElfDialogResult, ElfLoadDialog};
+use gametank_sdk::rom_builder::{is_elf, RomBuilder};
 
 
 pub struct AppInitialized {
-    pub emulator: Emulator<InstantClock>,
+    pub tabs: Vec<EmuTab>,
+    active: usize,
+    recent: RecentRoms,
+    elf_dialog: Option<ElfLoadDialog>,
     pub gc: GraphicsContext,
     pub window: Arc<Window>,
     pub egui_renderer: EguiRenderer,
 
-    pub console_gui: GameTankBoyUI,
-    pub vram_viewer: VRAMViewer,
     pub mem_inspector: MemoryInspector,
+    pub audio_settings: AudioSettingsUI,
+    perf_hud: PerfHud,
+    debug_log: DebugLogWindow,
+    touch_gamepad: TouchGamepad,
+    #[cfg(target_arch = "wasm32")]
+    gamepads: crate::web_gamepad::WebGamepads,
+    #[cfg(target_arch = "wasm32")]
+    storage: crate::web_storage::WebStorage,
 
     pub input_bindings: HashMap<winit::keyboard::Key, InputCommand>,
 
     show_left_pane: bool,
     show_right_pane: bool,
     show_bottom_pane: bool,
-
-    audio: Option<GameTankAudio>,
+    side_by_side: bool,
+    show_heatmap: bool,
+    show_perf_hud: bool,
+    show_debug_log: bool,
+    /// Reload tabs when their ROM file changes, e.g. under `gtrom watch`
+    reload_on_change: bool,
+    last_reload_check: f64,
 }
 
 impl From<&mut App> for AppInitialized {
@@ -47,8 +70,6 @@ impl From<&mut App> for AppInitialized {
         let mut gc = app.gc.take().unwrap();
         let window = app.window.take().unwrap();
         let egui_renderer = app.egui_renderer.take().unwrap();
-        let console_gui = GameTankBoyUI::init(egui_renderer.context(), Self::buffer_to_color_image(&emulator.cpu_bus.read_full_framebuffer()));
-        let vram_viewer = VRAMViewer::new(VRAMViewerLayout::Pages, egui_renderer.context(), &mut emulator);
 
         gc.surface_config.width = window.inner_size().width;
         gc.surface_config.height = window.inner_size().height;
@@ -65,40 +86,231 @@ impl From<&mut App> for AppInitialized {
         input_bindings.insert(keyboard::Key::Character(SmolStr::new("x")), Controller1(ControllerButton::B));
         input_bindings.insert(keyboard::Key::Character(SmolStr::new("c")), Controller1(ControllerButton::C));
 
-        if let Some(filename) = std::env::args().nth(1) {
-            if let Ok(data) = std::fs::read(filename) {
-                emulator.load_rom(&data);
-                emulator.play_state = Playing;
-            } else {
-                error!("couldn't open provided file");
+        let audio_settings = AudioSettingsUI::new();
+
+        let mut recent = RecentRoms::load();
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let reload_on_change = args.iter().any(|a| a == "--watch");
+        // `--sym <file>` gives symbols for the first rom
+        let sym_path = args.iter().position(|a| a == "--sym").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+        let mut paths: Vec<PathBuf> = args.iter().enumerate()
+            .filter(|(i, a)| !a.starts_with("--") && (*i == 0 || args[i - 1] != "--sym"))
+            .map(|(_, a)| PathBuf::from(a))
+            .collect();
+        if paths.is_empty() && recent.reopen_last {
+            paths.extend(recent.last().map(Path::to_path_buf));
+        }
+
+        // every rom on the command line gets its own tab; the first one reuses the emulator we already have
+        let mut roms = paths.iter().filter_map(|path| match read_rom(path) {
+            Ok(data) => {
+                recent.push(path);
+                Some((path, data))
             }
+            Err(e) => {
+                error!("{}", e);
+                None
+            }
+        });
+
+        let first = roms.next();
+        let mut name = "untitled".to_string();
+        if let Some((path, data)) = &first {
+            emulator.load_rom(data);
+            emulator.play_state = Playing;
+            name = tab_name(path);
         }
 
-        // Create audio bridge if emulator already has audio_out (don't take or clone the ring endpoints)
-        let audio_bridge = if emulator.audio_out.is_some() {
-            Some(GameTankAudio::new())
-        } else {
-            None
-        };
+        let mut tabs = vec![EmuTab::new(name, emulator, egui_renderer.context(), &audio_settings.config)];
+        if let Some((path, _)) = first {
+            tabs[0].set_source(path);
+            if let Some(sym_path) = &sym_path {
+                tabs[0].set_symbols(sym_path);
+            }
+        }
+        for (path, data) in roms {
+            let mut tab = EmuTab::open(tab_name(path), &data, egui_renderer.context(), &audio_settings.config);
+            tab.set_source(path);
+            tab.set_muted(true);
+            tabs.push(tab);
+        }
 
         Self {
-            emulator,
+            tabs,
+            active: 0,
+            recent,
+            elf_dialog: None,
             gc,
             window,
             egui_renderer,
-            console_gui,
-            vram_viewer,
             mem_inspector: MemoryInspector {},
+            audio_settings,
+            perf_hud: PerfHud::new(),
+            debug_log: DebugLogWindow::new(),
+            touch_gamepad: TouchGamepad::new(),
+            #[cfg(target_arch = "wasm32")]
+            gamepads: crate::web_gamepad::WebGamepads::new(),
+            #[cfg(target_arch = "wasm32")]
+            storage: crate::web_storage::WebStorage::new(),
             input_bindings,
             show_left_pane: false,
             show_right_pane: false,
             show_bottom_pane: false,
-            audio: audio_bridge,
+            side_by_side: false,
+            show_heatmap: false,
+            show_perf_hud: false,
+            show_debug_log: false,
+            reload_on_change,
+            last_reload_check: 0.0,
         }
     }
 }
 
 impl AppInitialized {
+    fn tab(&mut self) -> &mut EmuTab {
+        &mut self.tabs[self.active]
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        self.active = index;
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            tab.set_muted(i != index);
+        }
+    }
+
+    fn open_tab(&mut self, name: String, rom: &[u8], source: Option<&Path>) {
+        let mut tab = EmuTab::open(name, rom, self.egui_renderer.context(), &self.audio_settings.config);
+        tab.set_heatmap(self.show_heatmap);
+        if let Some(path) = source {
+            tab.set_source(path);
+        }
+        self.tabs.push(tab);
+        self.select_tab(self.tabs.len() - 1);
+    }
+
+    /// Open a .gtr in a new tab. ELFs get converted and shown in the load dialog first.
+    fn open_rom_path(&mut self, path: &Path) {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("couldn't open {}: {}", path.display(), e);
+                return
+            }
+        };
+
+        if is_elf(&data) {
+            match RomBuilder::from_elf(&data) {
+                Ok(image) => self.elf_dialog = Some(ElfLoadDialog { path: path.to_path_buf(), image }),
+                Err(e) => error!("couldn't convert {}: {}", path.display(), e),
+            }
+            return
+        }
+
+        if path.extension().is_none_or(|ext| ext != "gtr") {
+            error!("{} is neither a .gtr nor an ELF", path.display());
+            return
+        }
+
+        if let Err(e) = verify_rom(path, &data) {
+            error!("{}", e);
+            return
+        }
+
+        self.recent.push(path);
+        self.open_tab(tab_name(path), &data, Some(path));
+        warn!("successfully loaded {}", path.display());
+    }
+
+    fn draw_elf_dialog(&mut self) {
+        let Some(dialog) = &self.elf_dialog else { return };
+
+        match dialog.draw(self.egui_renderer.context()) {
+            Some(ElfDialogResult::Load) => {
+                let dialog = self.elf_dialog.take().unwrap();
+                self.recent.push(&dialog.path);
+                self.open_tab(tab_name(&dialog.path), dialog.image.as_bytes(), Some(dialog.path.as_path()));
+                warn!("successfully loaded {}", dialog.path.display());
+            }
+            Some(ElfDialogResult::Cancel) => self.elf_dialog = None,
+            None => {}
+        }
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        // always keep one emulator around
+        if self.tabs.len() > 1 {
+            self.tabs.remove(index);
+            self.select_tab(self.active.min(self.tabs.len() - 1));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn draw_file_menu(&mut self, ui: &mut Ui) {
+        let mut open = None;
+
+        ui.menu_button("File", |ui| {
+            if ui.button("Open ROM…").clicked() {
+                ui.close_menu();
+                open = rfd::FileDialog::new()
+                    .add_filter("GameTank ROM", &["gtr"])
+                    .add_filter("llvm-mos ELF", &["elf"])
+                    .add_filter("All files", &["*"])
+                    .pick_file();
+            }
+
+            ui.menu_button("Open Recent", |ui| {
+                if self.recent.paths.is_empty() {
+                    ui.weak("nothing yet");
+                }
+                for path in &self.recent.paths {
+                    if ui.button(path.display().to_string()).clicked() {
+                        ui.close_menu();
+                        open = Some(path.clone());
+                    }
+                }
+                ui.separator();
+                if ui.add_enabled(!self.recent.paths.is_empty(), Button::new("Clear")).clicked() {
+                    self.recent.clear();
+                }
+            });
+
+            ui.separator();
+            if ui.checkbox(&mut self.recent.reopen_last, "Reopen last ROM on start").changed() {
+                self.recent.save();
+            }
+            ui.checkbox(&mut self.reload_on_change, "Reload ROMs when their file changes");
+        });
+
+        if let Some(path) = open {
+            self.open_rom_path(&path);
+        }
+    }
+
+    fn draw_tab_bar(&mut self, ui: &mut Ui) {
+        let mut select = None;
+        let mut close = None;
+
+        ui.horizontal(|ui| {
+            for (i, tab) in self.tabs.iter().enumerate() {
+                if ui.selectable_label(i == self.active, &tab.name).clicked() {
+                    select = Some(i);
+                }
+                if self.tabs.len() > 1 && ui.small_button("x").on_hover_text("close tab").clicked() {
+                    close = Some(i);
+                }
+                ui.separator();
+            }
+            ui.weak("drop a .gtr to open it in a new tab");
+        });
+
+        if let Some(i) = select {
+            self.select_tab(i);
+        }
+        if let Some(i) = close {
+            self.close_tab(i);
+        }
+    }
+
     fn handle_redraw(&mut self) {
         let screen_descriptor = ScreenDescriptor {
             size_in_pixels: [self.gc.surface_config.width, self.gc.surface_config.height],
@@ -128,11 +340,21 @@ impl AppInitialized {
 
         #[cfg(not(target_arch = "wasm32"))]
         {
+            let ctx = self.egui_renderer.context().clone();
+            egui::TopBottomPanel::top("tab_bar").resizable(false).show_separator_line(true).show(&ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    self.draw_file_menu(ui);
+                    ui.separator();
+                    self.draw_tab_bar(ui);
+                });
+            });
+
             egui::TopBottomPanel::bottom("bottom_pane_2").resizable(false).show_separator_line(true).show_animated(self.egui_renderer.context(), self.show_bottom_pane, |ui| {
                 ui.vertical(|ui| {
                     ui.vertical_centered(|ui| {
                         ui.allocate_space(vec2(ui.available_width(), 0.0));
-                        self.vram_viewer.draw(ui, &mut self.emulator);
+                        let tab = &mut self.tabs[self.active];
+                        tab.vram_viewer.draw(ui, &mut tab.runner.emulator.lock().unwrap());
                         ui.allocate_space(vec2(ui.available_width(), 0.0));
                     });
                 });
@@ -143,6 +365,26 @@ impl AppInitialized {
                     ui.toggle_value(&mut self.show_left_pane, "show left panel");
                     ui.toggle_value(&mut self.show_bottom_pane, "show bottom panel");
                     ui.toggle_value(&mut self.show_right_pane, "show right panel");
+                    ui.add_enabled_ui(self.tabs.len() > 1, |ui| ui.toggle_value(&mut self.side_by_side, "side by side"));
+                    if ui.toggle_value(&mut self.show_heatmap, "write heatmap")
+                        .on_hover_text("tint pixels by how many times they were written last frame")
+                        .changed() {
+                        for tab in &mut self.tabs {
+                            tab.set_heatmap(self.show_heatmap);
+                        }
+                    }
+                    ui.toggle_value(&mut self.show_perf_hud, "perf HUD");
+                    ui.toggle_value(&mut self.show_debug_log, "debug log")
+                        .on_hover_text("lines the ROM printed with gametank::debug!");
+                    ui.separator();
+                    let mut emulator = self.tabs[self.active].runner.emulator.lock().unwrap();
+                    egui::ComboBox::from_label("blitter timing")
+                        .selected_text(format!("{:?}", emulator.blitter.timing))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut emulator.blitter.timing, BlitterTiming::Instant, "Instant");
+                            ui.selectable_value(&mut emulator.blitter.timing, BlitterTiming::Fast, "Fast");
+                            ui.selectable_value(&mut emulator.blitter.timing, BlitterTiming::Accurate, "Accurate");
+                        });
                 });
             });
 
@@ -158,7 +400,8 @@ impl AppInitialized {
                 left_size = ui.available_width();
 
                 if self.show_left_pane {
-                    self.mem_inspector.draw(ui, &mut self.emulator);
+                    let tab = &mut self.tabs[self.active];
+                    self.mem_inspector.draw(ui, &mut tab.runner.emulator.lock().unwrap(), tab.symbols.as_ref());
                 }
             });
 
@@ -173,11 +416,20 @@ impl AppInitialized {
                 if self.show_right_pane {
                     let sa = ScrollArea::both().enable_scrolling(true).min_scrolled_width(0.0).show(ui, |ui| {
                         ui.with_layout(Layout::top_down_justified(Align::RIGHT), |ui| {
-                            Frame::default().show(ui, |ui| {
+                            Frame::default().inner_margin(8.0).show(ui, |ui| {
                                 ui.set_min_width(24.0);
                                 // ui.set_width(ui.available_width());
                                 ui.set_height(ui.available_height());
-                                ui.label("here's some gui shit");
+                                ui.with_layout(Layout::top_down(Align::LEFT), |ui| {
+                                    let runner = &mut self.tabs[self.active].runner;
+                                    let status = runner.audio_status.lock().unwrap().clone();
+                                    let reopen = self.audio_settings.draw(ui, &mut runner.emulator.lock().unwrap(), &status);
+                                    if reopen {
+                                        for tab in &mut self.tabs {
+                                            tab.runner.send(EmuCommand::ReopenAudio(self.audio_settings.config.clone()));
+                                        }
+                                    }
+                                });
                             })
                         });
 
@@ -191,12 +443,38 @@ impl AppInitialized {
             // Set the minimum size for the center pane
             let center_min_size = egui::vec2(128.0, 128.0);
             ui.set_min_size(center_min_size);
-            ui.horizontal_centered(|ui| {
-                ui.set_height(ui.available_height());
-                self.console_gui.draw(ui, &mut self.emulator);
-            });
+            if self.side_by_side && self.tabs.len() > 1 {
+                ui.columns(self.tabs.len(), |columns| {
+                    for (ui, tab) in columns.iter_mut().zip(&mut self.tabs) {
+                        ui.set_height(ui.available_height());
+                        ui.label(&tab.name);
+                        Self::draw_console(ui, tab);
+                    }
+                });
+            } else {
+                ui.horizontal_centered(|ui| {
+                    ui.set_height(ui.available_height());
+                    Self::draw_console(ui, &mut self.tabs[self.active]);
+                });
+            }
         });
 
+        self.perf_hud.tick();
+        if self.show_perf_hud {
+            let runner = &self.tabs[self.active].runner;
+            let perf = *runner.perf_status.lock().unwrap();
+            let audio = runner.audio_status.lock().unwrap().clone();
+            self.perf_hud.draw(self.egui_renderer.context(), &perf, &audio);
+        }
+
+        if self.show_debug_log {
+            let mut emulator = self.tabs[self.active].runner.emulator.lock().unwrap();
+            self.debug_log.draw(self.egui_renderer.context(), &mut self.show_debug_log, &mut emulator);
+        }
+
+        self.touch_gamepad.draw(self.egui_renderer.context());
+        self.draw_elf_dialog();
+
         self.egui_renderer.end_frame_and_draw(
             &self.gc.device,
             &self.gc.queue,
@@ -211,6 +489,13 @@ impl AppInitialized {
     }
 
 
+    fn draw_console(ui: &mut Ui, tab: &mut EmuTab) {
+        let play_state = tab.runner.emulator.lock().unwrap().play_state;
+        if tab.console_gui.draw(ui, &tab.screen, tab.heatmap.as_deref(), play_state) {
+            tab.runner.send(EmuCommand::TogglePlay);
+        }
+    }
+
     pub fn buffer_to_color_image(framebuffer: &[u8; 128*128]) -> egui::ColorImage {
         let mut pixels: Vec<u8> = Vec::with_capacity(128 * 128 * 4); // 4 channels per pixel (RGBA)
 
@@ -225,6 +510,34 @@ impl AppInitialized {
         egui::ColorImage::from_rgba_unmultiplied([128, 128], &pixels)
     }
 
+    pub fn buffer_to_png(framebuffer: &[u8; 128*128]) -> Result<Vec<u8>, String> {
+        let color_image = Self::buffer_to_color_image(framebuffer);
+        let image = image::RgbaImage::from_raw(128, 128, color_image.as_raw().to_vec())
+            .ok_or("framebuffer is the wrong size")?;
+
+        let mut png = std::io::Cursor::new(vec![]);
+        image.write_to(&mut png, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+        Ok(png.into_inner())
+    }
+
+
+    /// Keep what the embedding api reports in step with the active tab
+    fn publish_frame(tab: &EmuTab) {
+        let (play_state, frame_count) = {
+            let emulator = tab.runner.emulator.lock().unwrap();
+            (emulator.play_state, emulator.frame_count)
+        };
+        PLAY_STATE.with(|state| state.set(play_state));
+        LAST_FRAME.with(|frame| *frame.borrow_mut() = Some(tab.screen.clone()));
+
+        FRAME_CALLBACK.with(|cb| {
+            if let Some(callback) = cb.borrow().as_ref() {
+                if let Err(e) = callback.call1(&JsValue::NULL, &JsValue::from_f64(frame_count as f64)) {
+                    warn!("frame callback threw: {:?}", e);
+                }
+            }
+        });
+    }
 
     fn handle_resized(&mut self, width: u32, height: u32) {
         self.gc.surface_config.width = width;
@@ -235,10 +548,11 @@ impl AppInitialized {
 
 use std::cell::RefCell;
 use std::collections::HashMap;
-use gte_core::emulator::PlayState::{Paused, Playing};
+use gte_core::emulator::PlayState::{self, Paused, Playing, WasmInit};
 use gte_core::inputs::{ControllerButton, InputCommand, KeyState};
 use gte_core::inputs::InputCommand::Controller1;
 use wasm_bindgen::prelude::*;
+use web_sys::js_sys;
 use winit::event::ElementState::Pressed;
 use winit::keyboard;
 use winit::keyboard::NamedKey::{ArrowDown, ArrowLeft, ArrowRight, ArrowUp, Enter};
@@ -250,6 +564,15 @@ thread_local! {
     static ROM_DATA: RefCell<Option<Vec<u8>>> = RefCell::new(None);
     static SHOULD_SHUTDOWN: Cell<bool> = Cell::new(false);
     static EMULATOR_STOP: Cell<bool> = Cell::new(false);
+    static PENDING_COMMANDS: RefCell<Vec<EmuCommand>> = RefCell::new(vec![]);
+    static PLAY_STATE: Cell<PlayState> = Cell::new(WasmInit);
+    static LAST_FRAME: RefCell<Option<Box<[u8; 128*128]>>> = RefCell::new(None);
+    static FRAME_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+}
+
+/// Hand a command to the active tab on the next event loop pass
+pub(crate) fn queue_command(command: EmuCommand) {
+    PENDING_COMMANDS.with(|pending| pending.borrow_mut().push(command));
 }
 
 // Function to update the ROM data from JavaScript
@@ -273,29 +596,53 @@ pub fn emulator_stop() {
     EMULATOR_STOP.with(|flag| flag.set(true));
 }
 
-impl AppInitialized {
-    pub fn process_cycles(&mut self) {
-        self.emulator.process_cycles(false);
+#[wasm_bindgen]
+pub fn pause_emulator() {
+    queue_command(EmuCommand::SetPlayState(Paused));
+}
 
-        // If emulator created audio after initialization, create the bridge.
-        if self.audio.is_none() && self.emulator.audio_out.is_some() {
-            self.audio = Some(GameTankAudio::new());
-        }
+#[wasm_bindgen]
+pub fn resume_emulator() {
+    queue_command(EmuCommand::SetPlayState(Playing));
+}
 
-        // Drain whatever the emulator pushed into its own buffer and forward into our bridge.
-        if let (Some(ref mut audio_out), Some(ref mut audio)) = (&mut self.emulator.audio_out, &mut self.audio) {
-            while let Ok(buf) = audio_out.output_buffer.pop() {
-                audio.push_buffer(buf);
-            }
-        }
+/// Press or release a button ("up", "down", "left", "right", "a", "b", "c", "start") on controller 1 or 2
+#[wasm_bindgen]
+pub fn set_button(controller: u8, button: &str, pressed: bool) -> Result<(), JsValue> {
+    let button = ControllerButton::from_name(button)
+        .ok_or_else(|| JsValue::from_str(&format!("unknown button: {}", button)))?;
+    let command = match controller {
+        1 => InputCommand::Controller1(button),
+        2 => InputCommand::Controller2(button),
+        _ => return Err(JsValue::from_str(&format!("no controller {}; expected 1 or 2", controller))),
+    };
+    queue_command(EmuCommand::Input(command, pressed));
+    Ok(())
+}
 
-        // Drive the audio bridge if present. It will pull from the bridge's internal consumer.
-        if let Some(ref mut audio) = self.audio {
-            audio.process_audio();
-        }
+/// "init" until the first user interaction, then "paused" or "playing"
+#[wasm_bindgen]
+pub fn play_state() -> String {
+    match PLAY_STATE.with(Cell::get) {
+        WasmInit => "init",
+        Paused => "paused",
+        Playing => "playing",
+    }.to_string()
+}
 
-        // previous manual draining of audio_out is removed because the bridge now owns the consumer
-    }
+/// The most recent frame as a 128x128 PNG
+#[wasm_bindgen]
+pub fn screenshot() -> Result<Vec<u8>, JsValue> {
+    LAST_FRAME.with(|frame| match frame.borrow().as_deref() {
+        Some(frame) => AppInitialized::buffer_to_png(frame).map_err(|e| JsValue::from_str(&e)),
+        None => Err(JsValue::from_str("no frame has been drawn yet")),
+    })
+}
+
+/// Call `callback(frameNumber)` after every new frame. Pass null to stop.
+#[wasm_bindgen]
+pub fn on_frame(callback: Option<js_sys::Function>) {
+    FRAME_CALLBACK.with(|cb| *cb.borrow_mut() = callback);
 }
 
 impl ApplicationHandler for AppInitialized {
@@ -324,30 +671,20 @@ impl ApplicationHandler for AppInitialized {
             WindowEvent::KeyboardInput { event, .. } => {
                 let KeyEvent {  logical_key,   state,  .. } = event;
                 if let Some(cmd) = self.input_bindings.get(&logical_key).copied() {
-                    if let Some(ks) = self.emulator.input_state.get(&cmd) {
-                        self.emulator.set_input_state(cmd, ks.update_state(state==Pressed))
-                    } else {
-                        self.emulator.set_input_state(cmd, KeyState::new(state==Pressed))
-                    };
+                    self.tab().runner.send(EmuCommand::Input(cmd, state == Pressed));
                 }
             },
-            WindowEvent::MouseInput { .. } => { self.emulator.wasm_init(); }
-            WindowEvent::Touch(_) => { self.emulator.wasm_init(); }
+            WindowEvent::MouseInput { .. } => { self.tab().runner.send(EmuCommand::Wake); }
+            WindowEvent::Touch(touch) => {
+                self.tab().runner.send(EmuCommand::Wake);
+                let pixels_per_point = self.egui_renderer.context().pixels_per_point();
+                for (button, pressed) in self.touch_gamepad.handle_touch(&touch, pixels_per_point) {
+                    self.tab().runner.send(EmuCommand::Input(Controller1(button), pressed));
+                }
+            }
             WindowEvent::DroppedFile(path) => {
                 warn!("reading file from path...");
-                // check if filename ends in .gtr and load file into slice
-                let filename = path.file_name().unwrap().to_str().unwrap();
-                if !filename.ends_with(".gtr") {
-                    error!("not a valid gtr");
-                    return
-                }
-
-                let mut file = File::open(&path).unwrap();
-                let mut bytes = Vec::new();
-                file.read_to_end(&mut bytes).unwrap();
-
-                self.emulator.load_rom(bytes.as_slice());
-                warn!("successfully loaded {}", filename);
+                self.open_rom_path(&path);
             }
             _ => (),
         }
@@ -355,22 +692,57 @@ impl ApplicationHandler for AppInitialized {
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         // Check if a new ROM is waiting
-        if let Some(data) = &ROM_DATA.take() {
+        if let Some(data) = ROM_DATA.take() {
             warn!("got rom data!");
-            if !data.is_empty() {
-                self.emulator.load_rom(data);
+            if data.is_empty() {
+                self.tab().runner.send(EmuCommand::SetPlayState(Playing));
+            } else if let Err(e) = check_rom_size(&data) {
+                error!("{}", e);
+            } else {
+                // on the web, any flash saved for this rom is looked up before it starts
+                #[cfg(target_arch = "wasm32")]
+                self.storage.load_rom(data);
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.tab().runner.send(EmuCommand::LoadRom(data));
+                    self.tab().runner.send(EmuCommand::SetPlayState(Playing));
+                }
             }
-            self.emulator.play_state = Playing;
         }
 
-        if EMULATOR_STOP.with(|flag| flag.get()) {
-            self.emulator.play_state = Paused;
+        if EMULATOR_STOP.with(|flag| flag.replace(false)) {
+            self.tab().runner.send(EmuCommand::SetPlayState(Paused));
+        }
+
+        for command in PENDING_COMMANDS.with(|pending| pending.take()) {
+            self.tab().runner.send(command);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.storage.update(&mut self.tabs[self.active]);
+
+        #[cfg(target_arch = "wasm32")]
+        for (cmd, pressed) in self.gamepads.poll() {
+            self.tab().runner.send(EmuCommand::Input(cmd, pressed));
         }
 
         if SHOULD_SHUTDOWN.with(|flag| flag.get()) {
             event_loop.exit();
         }
 
-        self.process_cycles();
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.reload_on_change && crate::helpers::get_now_ms() - self.last_reload_check > 500.0 {
+            self.last_reload_check = crate::helpers::get_now_ms();
+            for tab in &mut self.tabs {
+                tab.reload_if_changed();
+            }
+        }
+
+        let active = self.active;
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if tab.update_screen() && i == active {
+                Self::publish_frame(tab);
+            }
+        }
     }
 }
\ No newline at end of file
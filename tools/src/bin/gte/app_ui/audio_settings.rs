@@ -0,0 +1,90 @@
+use egui::{ComboBox, Slider, Ui};
+use gte_core::emulator::Emulator;
+use gte_acp::audio_output::{Resampler, DEFAULT_DAC_CUTOFF_HZ};
+use crate::app_delegation::InstantClock;
+use crate::audio::{output_device_names, AudioConfig, BUFFER_SIZES};
+use crate::emu_thread::AudioStatus;
+
+pub struct AudioSettingsUI {
+    pub config: AudioConfig,
+    devices: Vec<String>,
+    latency_dirty: bool,
+}
+
+impl AudioSettingsUI {
+    pub fn new() -> Self {
+        Self {
+            config: AudioConfig::default(),
+            devices: output_device_names(),
+            latency_dirty: false,
+        }
+    }
+
+    /// Returns true when the output device config changed and the stream needs reopening
+    pub fn draw(&mut self, ui: &mut Ui, emulator: &mut Emulator<InstantClock>, status: &AudioStatus) -> bool {
+        let previous = self.config.clone();
+        let settings = &mut emulator.audio_settings;
+
+        ui.heading("Audio");
+
+        ui.horizontal(|ui| {
+            ComboBox::from_label("device")
+                .selected_text(self.config.device.as_deref().unwrap_or("default"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.config.device, None, "default");
+                    for name in &self.devices {
+                        ui.selectable_value(&mut self.config.device, Some(name.clone()), name);
+                    }
+                });
+            if ui.small_button("⟳").on_hover_text("rescan devices").clicked() {
+                self.devices = output_device_names();
+            }
+        });
+
+        ComboBox::from_label("buffer size")
+            .selected_text(self.config.buffer_size.map_or("default".to_string(), |n| format!("{} frames", n)))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.config.buffer_size, None, "default");
+                for size in BUFFER_SIZES {
+                    ui.selectable_value(&mut self.config.buffer_size, Some(size), format!("{} frames", size));
+                }
+            });
+
+        let slider = ui.add(Slider::new(&mut self.config.target_latency_ms, 10.0..=250.0).suffix(" ms").text("target latency"));
+        self.latency_dirty |= slider.changed();
+        // only reopen once the user lets go, not on every drag step
+        let latency_changed = self.latency_dirty && !slider.dragged();
+        if latency_changed {
+            self.latency_dirty = false;
+        }
+
+        match (&status.stream, &status.error) {
+            (Some((device_name, sample_rate)), _) => {
+                ui.label(format!("{} @ {}Hz", device_name, sample_rate));
+                ui.label(format!("queued: {:.1} ms", status.queued_ms));
+                ui.label(format!("underruns: {}  dropped: {}", status.underruns, status.dropped));
+            }
+            (None, Some(e)) => { ui.colored_label(egui::Color32::LIGHT_RED, e); }
+            (None, None) => { ui.label("no audio stream"); }
+        }
+
+        ui.separator();
+
+        ComboBox::from_label("resampler")
+            .selected_text(format!("{:?}", settings.resampler))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.resampler, Resampler::Linear, "Linear");
+                ui.selectable_value(&mut settings.resampler, Resampler::Sinc, "Sinc");
+            });
+
+        ui.checkbox(&mut settings.dac_filter, "DAC low-pass filter");
+        ui.add_enabled_ui(settings.dac_filter, |ui| {
+            ui.add(Slider::new(&mut settings.dac_cutoff_hz, 500.0..=20_000.0).logarithmic(true).suffix(" Hz").text("cutoff"));
+            if ui.button("reset cutoff").clicked() {
+                settings.dac_cutoff_hz = DEFAULT_DAC_CUTOFF_HZ;
+            }
+        });
+
+        latency_changed || self.config.device != previous.device || self.config.buffer_size != previous.buffer_size
+    }
+}
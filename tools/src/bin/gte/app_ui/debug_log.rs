@@ -0,0 +1,41 @@
+use egui::{Context, ScrollArea};
+use gte_core::emulator::Emulator;
+use crate::app_delegation::InstantClock;
+
+/// Window showing what the ROM printed with the SDK's `debug!`
+pub struct DebugLogWindow {
+    /// Scroll to the newest line whenever one arrives
+    follow: bool,
+}
+
+impl DebugLogWindow {
+    pub fn new() -> Self {
+        Self { follow: true }
+    }
+
+    pub fn draw(&mut self, ctx: &Context, open: &mut bool, emulator: &mut Emulator<InstantClock>) {
+        egui::Window::new("debug log")
+            .open(open)
+            .default_size([360.0, 240.0])
+            .show(ctx, |ui| {
+                let log = &mut emulator.cpu_bus.debug_log;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.follow, "follow");
+                    if ui.button("clear").clicked() {
+                        log.clear();
+                    }
+                    ui.label(format!("{} lines written", log.total()));
+                });
+                ui.separator();
+
+                ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(self.follow)
+                    .show(ui, |ui| {
+                        for line in log.lines() {
+                            ui.monospace(line);
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use egui::{Context, Grid, ScrollArea, Window};
+use gametank_sdk::rom_builder::RomImage;
+
+/// Shows what an ELF will turn into before it's loaded
+pub struct ElfLoadDialog {
+    pub path: PathBuf,
+    pub image: RomImage,
+}
+
+pub enum ElfDialogResult {
+    Load,
+    Cancel,
+}
+
+impl ElfLoadDialog {
+    pub fn draw(&self, ctx: &Context) -> Option<ElfDialogResult> {
+        let mut result = None;
+
+        Window::new("Load ELF").collapsible(false).resizable(true).show(ctx, |ui| {
+            ui.label(self.path.display().to_string());
+            ui.label(format!("entry point (reset vector): ${:04X}", self.image.reset_vector()));
+            ui.separator();
+
+            ui.strong("sections");
+            ScrollArea::vertical().id_salt("elf_sections").max_height(200.0).show(ui, |ui| {
+                Grid::new("elf_sections_grid").striped(true).show(ui, |ui| {
+                    ui.label("section");
+                    ui.label("bank");
+                    ui.label("rom");
+                    ui.label("addr");
+                    ui.end_row();
+
+                    for s in &self.image.sections {
+                        ui.monospace(&s.display_name);
+                        ui.monospace(s.bank.to_string());
+                        ui.monospace(format!("{:04X}..{:04X}", s.bank_loc, s.bank_loc + s.size));
+                        ui.monospace(format!("${:04X}", s.mem_loc));
+                        ui.end_row();
+                    }
+                });
+            });
+
+            ui.separator();
+            ui.strong("banks");
+            let bank_size = self.image.bank_size();
+            for (bank, used) in self.image.bank_usage() {
+                ui.monospace(format!("bank {:>3}: {:>5} / {} bytes ({:.0}%)", bank, used, bank_size, used as f32 * 100.0 / bank_size as f32));
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    result = Some(ElfDialogResult::Load);
+                }
+                if ui.button("Cancel").clicked() {
+                    result = Some(ElfDialogResult::Cancel);
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -2,8 +2,7 @@ use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Cursor, Read, Seek};
 use egui::{include_image, vec2, Button, Color32, ColorImage, Context, Frame, ImageOptions, ImageSource, Rect, Shadow, SizeHint, Style, TextureHandle, TextureOptions, Ui, Vec2, Widget};
 use egui::load::{SizedTexture, TextureLoadResult, TexturePoll};
-use gte_core::emulator::Emulator;
-use gte_core::emulator::PlayState::{Paused, Playing, WasmInit};
+use gte_core::emulator::PlayState::{self, Paused, Playing, WasmInit};
 use image::{GenericImageView, ImageFormat};
 use tracing::warn;
 use crate::app_delegation::InstantClock;
@@ -76,6 +75,22 @@ fn load_included_image(context: &Context, img: ImageSource) -> SizedTexture {
     }
 }
 
+/// Blend each pixel toward a color for its write count:
+/// untouched pixels are dimmed, then green, yellow, orange and red for 4+ writes
+fn tint_with_heatmap(image: &mut ColorImage, counts: &[u8; 128*128]) {
+    for (pixel, &count) in image.pixels.iter_mut().zip(counts.iter()) {
+        let tint = match count {
+            0 => Color32::BLACK,
+            1 => Color32::from_rgb(0, 200, 0),
+            2 => Color32::from_rgb(230, 230, 0),
+            3 => Color32::from_rgb(255, 140, 0),
+            _ => Color32::from_rgb(255, 0, 0),
+        };
+        let mix = |a: u8, b: u8| ((a as u16 + b as u16 * 3) / 4) as u8;
+        *pixel = Color32::from_rgb(mix(pixel.r(), tint.r()), mix(pixel.g(), tint.g()), mix(pixel.b(), tint.b()));
+    }
+}
+
 impl GameTankBoyUI {
     pub fn init(context: &Context, color_image: ColorImage) -> Self {
         let options = TextureOptions::NEAREST;
@@ -100,13 +115,16 @@ impl GameTankBoyUI {
         self.screen.set_partial([0, 0], color_image, TextureOptions::NEAREST);
     }
 
-    pub fn draw(&mut self, ui: &mut Ui, emulator: &mut Emulator<InstantClock>) {
+    /// Returns true when the power button was clicked.
+    /// With `heatmap` given, pixels are tinted by how often they were written last frame.
+    pub fn draw(&mut self, ui: &mut Ui, framebuffer: &[u8; 128*128], heatmap: Option<&[u8; 128*128]>, play_state: PlayState) -> bool {
         // Convert framebuffer to ColorImage
-        let color_image = {
-            let framebuffer = emulator.cpu_bus.read_full_framebuffer();
-            crate::app_initialized::AppInitialized::buffer_to_color_image(&framebuffer)
-        };
+        let mut color_image = crate::app_initialized::AppInitialized::buffer_to_color_image(framebuffer);
+        if let Some(counts) = heatmap {
+            tint_with_heatmap(&mut color_image, counts);
+        }
         self.update_screen(color_image);
+        let mut power_clicked = false;
 
         let available_width = ui.available_width();
         let available_height = ui.available_height();
@@ -173,7 +191,7 @@ impl GameTankBoyUI {
                     })
                 });
 
-                let power = match emulator.play_state {
+                let power = match play_state {
                     WasmInit => { self.textures.get("power_released").unwrap().clone() }
                     Paused => { self.textures.get("power_released").unwrap().clone() }
                     Playing => { self.textures.get("power_pressed").unwrap().clone() }
@@ -181,14 +199,10 @@ impl GameTankBoyUI {
                 let btn_sized_texture = egui::load::SizedTexture::new(power.id(), Vec2::new(48.0, 48.0));
                 let button = Button::image(egui::Image::new(btn_sized_texture)).frame(false);
 
-                if button.ui(ui).clicked() {
-                    match emulator.play_state {
-                        WasmInit => { emulator.play_state = Playing; }
-                        Paused => { emulator.play_state = Playing; }
-                        Playing => { emulator.play_state = Paused; }
-                    }
-                }
+                power_clicked = button.ui(ui).clicked();
             });
         });
+
+        power_clicked
     }
 }
\ No newline at end of file
@@ -1,3 +1,8 @@
 pub mod gametankboy;
 pub mod vram_viewer;
 pub mod ram_inspector;
+pub mod audio_settings;
+pub mod elf_dialog;
+pub mod perf_hud;
+pub mod debug_log;
+pub mod touch_gamepad;
@@ -0,0 +1,63 @@
+use egui::{Align2, Color32, Context, Frame, Grid, RichText, Ui};
+use crate::emu_thread::{AudioStatus, PerfStatus};
+use crate::helpers::get_now_ms;
+
+/// Host time available per emulated frame
+const FRAME_BUDGET_MS: f32 = 59659.0 / 3_579_545.0 * 1000.0;
+
+/// Small overlay with host and emulated timing, for judging how close a game is to dropping frames
+pub struct PerfHud {
+    last_redraw_ms: f64,
+    ui_frame_ms: f32,
+}
+
+impl PerfHud {
+    pub fn new() -> Self {
+        Self {
+            last_redraw_ms: get_now_ms(),
+            ui_frame_ms: 0.0,
+        }
+    }
+
+    /// Call once per redraw, whether or not the HUD is shown
+    pub fn tick(&mut self) {
+        let now = get_now_ms();
+        let elapsed = (now - self.last_redraw_ms) as f32;
+        self.last_redraw_ms = now;
+        // smooth it out so the number is readable
+        self.ui_frame_ms += (elapsed - self.ui_frame_ms) * 0.1;
+    }
+
+    pub fn draw(&self, ctx: &Context, perf: &PerfStatus, audio: &AudioStatus) {
+        egui::Area::new(egui::Id::new("perf_hud"))
+            .anchor(Align2::LEFT_TOP, [8.0, 8.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).fill(Color32::from_black_alpha(192)).show(ui, |ui| {
+                    Grid::new("perf_hud_grid").num_columns(2).show(ui, |ui| {
+                        let frame = &perf.frame;
+                        row(ui, "host frame", format!("{:.1} ms", self.ui_frame_ms), None);
+                        row(ui, "emulation", format!("{:.1} ms", perf.step_ms), warn_if(perf.step_ms > FRAME_BUDGET_MS * 0.8));
+                        row(ui, "cycles", format!("{}/frame", frame.cpu_cycles), None);
+                        row(ui, "blitter busy", format!("{:.1}%", frame.blitter_percent()), None);
+                        row(ui, "cpu idle (WAI)", format!("{:.1}%", frame.wait_percent()), warn_if(frame.cpu_cycles > 0 && frame.wait_percent() < 10.0));
+                        row(ui, "audio queued", format!("{:.0} ms", audio.queued_ms), warn_if(audio.stream.is_some() && audio.queued_ms < 5.0));
+                    });
+                });
+            });
+    }
+}
+
+fn warn_if(warn: bool) -> Option<Color32> {
+    warn.then_some(Color32::from_rgb(255, 96, 64))
+}
+
+fn row(ui: &mut Ui, label: &str, value: String, color: Option<Color32>) {
+    ui.monospace(label);
+    let text = RichText::new(value).monospace();
+    ui.label(match color {
+        Some(c) => text.color(c),
+        None => text,
+    });
+    ui.end_row();
+}
@@ -2,6 +2,7 @@ use egui::{Align, Color32, Label, Layout, RichText, Ui};
 use egui_extras::Column;
 use gte_core::emulator::Emulator;
 use gte_core::gametank_bus::ByteDecorator;
+use gametank_sdk::symbols::SymbolMap;
 use crate::app_delegation::InstantClock;
 
 pub struct MemoryInspector {
@@ -9,10 +10,14 @@ pub struct MemoryInspector {
 }
 
 impl MemoryInspector {
-    pub fn draw(&mut self, ui: &mut Ui, emulator: &mut Emulator<InstantClock>) {
+    pub fn draw(&mut self, ui: &mut Ui, emulator: &mut Emulator<InstantClock>, symbols: Option<&SymbolMap>) {
         let bytes_per_line = 16;
         let total_lines = 0x8000 / bytes_per_line;
 
+        let pc = emulator.cpu.get_pc();
+        let bank = emulator.cpu_bus.cartridge.current_bank().unwrap_or(0);
+        let location = symbols.and_then(|s| s.describe(pc, bank)).unwrap_or_default();
+        ui.monospace(format!("pc ${:04X}  bank {:>3}  {}", pc, bank, location));
 
         ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
         let tb = egui_extras::TableBuilder::new(ui)
@@ -51,7 +56,12 @@ impl MemoryInspector {
                             };
                             let t = RichText::new(format!("{:02X}", byte)).color(color);
 
-                            ui.label(t);
+                            let response = ui.label(t);
+                            if response.hovered() {
+                                if let Some(name) = symbols.and_then(|s| s.describe(address as u16, bank)) {
+                                    response.on_hover_text(name);
+                                }
+                            }
                         });
                     }
                 });
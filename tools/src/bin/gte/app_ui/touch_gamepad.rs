@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+use egui::{pos2, vec2, Align2, Color32, Context, FontId, Id, LayerId, Order, Pos2, Rect, Stroke};
+use gte_core::inputs::ControllerButton::{self, Down, Left, Right, Start, Up, A, B, C};
+use winit::event::{Touch, TouchPhase};
+
+const BUTTON_SIZE: f32 = 56.0;
+const MARGIN: f32 = 16.0;
+
+/// On-screen d-pad and A/B/C/Start buttons for phones.
+///
+/// egui only follows the first finger, so this hit-tests raw winit touches
+/// itself; that way the d-pad and a face button can be held at the same time.
+/// It stays hidden until the first touch arrives.
+pub struct TouchGamepad {
+    pub visible: bool,
+    buttons: Vec<(ControllerButton, Rect)>,
+    fingers: HashMap<u64, ControllerButton>,
+    held: HashSet<ControllerButton>,
+}
+
+impl TouchGamepad {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            buttons: vec![],
+            fingers: HashMap::new(),
+            held: HashSet::new(),
+        }
+    }
+
+    /// Lay out and paint the buttons over everything else
+    pub fn draw(&mut self, ctx: &Context) {
+        if !self.visible {
+            return
+        }
+
+        let screen = ctx.screen_rect();
+        let s = BUTTON_SIZE;
+        let square = |center: Pos2| Rect::from_center_size(center, vec2(s, s));
+
+        let dpad = pos2(screen.left() + MARGIN + s * 1.5, screen.bottom() - MARGIN - s * 1.5);
+        let face = pos2(screen.right() - MARGIN - s * 2.5, screen.bottom() - MARGIN - s);
+
+        self.buttons = vec![
+            (Up, square(dpad - vec2(0.0, s))),
+            (Down, square(dpad + vec2(0.0, s))),
+            (Left, square(dpad - vec2(s, 0.0))),
+            (Right, square(dpad + vec2(s, 0.0))),
+            (A, square(face + vec2(0.0, s * 0.25))),
+            (B, square(face + vec2(s * 1.1, -s * 0.25))),
+            (C, square(face + vec2(s * 2.2, -s * 0.75))),
+            (Start, Rect::from_center_size(pos2(screen.center().x, screen.bottom() - MARGIN - s * 0.25), vec2(s * 1.5, s * 0.5))),
+        ];
+
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("touch_gamepad")));
+        for (button, rect) in &self.buttons {
+            let alpha = if self.held.contains(button) { 160 } else { 64 };
+            painter.rect(*rect, 8.0, Color32::from_white_alpha(alpha), Stroke::new(1.0, Color32::from_white_alpha(96)), egui::StrokeKind::Inside);
+            painter.text(rect.center(), Align2::CENTER_CENTER, label(*button), FontId::proportional(18.0), Color32::from_black_alpha(192));
+        }
+    }
+
+    /// Track a finger and return the buttons that changed, and whether they're now pressed.
+    /// `pixels_per_point` converts winit's physical position into egui's coordinates.
+    pub fn handle_touch(&mut self, touch: &Touch, pixels_per_point: f32) -> Vec<(ControllerButton, bool)> {
+        self.visible = true;
+
+        let pos = pos2(touch.location.x as f32, touch.location.y as f32) / pixels_per_point;
+        match touch.phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                match self.buttons.iter().find(|(_, rect)| rect.contains(pos)) {
+                    Some((button, _)) => { self.fingers.insert(touch.id, *button); }
+                    None => { self.fingers.remove(&touch.id); }
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.fingers.remove(&touch.id);
+            }
+        }
+
+        let now: HashSet<ControllerButton> = self.fingers.values().copied().collect();
+        let mut changes: Vec<_> = now.difference(&self.held).map(|b| (*b, true)).collect();
+        changes.extend(self.held.difference(&now).map(|b| (*b, false)));
+        self.held = now;
+        changes
+    }
+}
+
+fn label(button: ControllerButton) -> &'static str {
+    match button {
+        Up => "▲",
+        Down => "▼",
+        Left => "◀",
+        Right => "▶",
+        A => "A",
+        B => "B",
+        C => "C",
+        Start => "START",
+    }
+}
@@ -2,7 +2,6 @@ use std::sync::{mpsc, Arc};
 use std::time::Instant;
 use egui::{epaint, Color32, TextureHandle, TextureOptions, Ui};
 use egui::UiKind::CentralPanel;
-use klingt::CpalDevice;
 use winit::application::ApplicationHandler;
 use winit::event_loop::ActiveEventLoop;
 #[cfg(target_arch = "wasm32")]
@@ -24,6 +23,7 @@ use gte_core::color_map::COLOR_MAP;
 use crate::egui_renderer::EguiRenderer;
 use gte_core::emulator::{Emulator, HEIGHT, WIDTH};
 use crate::app_delegation::{InstantClock};
+use crate::audio::{device_sample_rate, AudioConfig};
 use crate::graphics::GraphicsContext;
 
 
@@ -54,8 +54,8 @@ impl App {
         };
 
         // Query the audio device sample rate for proper resampling
-        let target_sample_rate = CpalDevice::default_output()
-            .map(|d| d.sample_rate() as f64)
+        let target_sample_rate = device_sample_rate(&AudioConfig::default())
+            .map(|rate| rate as f64)
             .unwrap_or(48000.0);
 
         Self {
@@ -0,0 +1,42 @@
+// Plays samples the emulator writes into a SharedArrayBuffer ring.
+// Header (Int32): [0] read index, [1] write index, [2] underrun count.
+// Samples (Float32) follow the 16 byte header; the emulator output is mono.
+class GteRingPlayer extends AudioWorkletProcessor {
+    constructor(options) {
+        super();
+        const buffer = options.processorOptions.buffer;
+        this.header = new Int32Array(buffer, 0, 4);
+        this.samples = new Float32Array(buffer, 16);
+        this.last = 0;
+    }
+
+    process(inputs, outputs) {
+        const out = outputs[0];
+        const frames = out[0].length;
+        const capacity = this.samples.length;
+        const write = Atomics.load(this.header, 1);
+        let read = Atomics.load(this.header, 0);
+        let starved = false;
+
+        for (let i = 0; i < frames; i++) {
+            if (read !== write) {
+                this.last = this.samples[read];
+                read = (read + 1) % capacity;
+            } else {
+                // hold the last sample so an underrun doesn't pop
+                starved = true;
+            }
+            for (const channel of out) {
+                channel[i] = this.last;
+            }
+        }
+
+        Atomics.store(this.header, 0, read);
+        if (starved) {
+            Atomics.add(this.header, 2, 1);
+        }
+        return true;
+    }
+}
+
+registerProcessor('gte-ring-player', GteRingPlayer);
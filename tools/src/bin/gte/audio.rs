@@ -1,116 +1,284 @@
-use dasp_graph::{Buffer, Input};
-use klingt::{AudioNode, CpalDevice, Handle, Klingt, ProcessContext};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, BuildStreamError, Device, FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig};
+use dasp_graph::Buffer;
+use gte_core::frontend::AudioSink;
 use rtrb::{Consumer, Producer, RingBuffer};
-use std::time::Instant;
-use tracing::warn;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{error, warn};
 
-/// A source node that reads audio buffers from an rtrb ring buffer
-pub struct RtrbSource {
+/// Buffer sizes offered in the settings panel, in frames
+pub const BUFFER_SIZES: [u32; 6] = [64, 128, 256, 512, 1024, 2048];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioConfig {
+    /// Output device name, `None` for the host's default
+    pub device: Option<String>,
+    /// Hardware buffer size in frames, `None` to let the backend decide
+    pub buffer_size: Option<u32>,
+    /// How much audio to keep queued ahead of the device before dropping buffers
+    pub target_latency_ms: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            buffer_size: None,
+            target_latency_ms: 40.0,
+        }
+    }
+}
+
+/// Names of every output device on the default host
+pub fn output_device_names() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+fn find_device(name: Option<&str>) -> Option<Device> {
+    let host = cpal::default_host();
+    match name {
+        Some(name) => host.output_devices().ok()?.find(|d| d.name().is_ok_and(|n| n == name)),
+        None => host.default_output_device(),
+    }
+}
+
+/// Sample rate the given device will be opened at
+pub fn device_sample_rate(config: &AudioConfig) -> Option<u32> {
+    let device = find_device(config.device.as_deref())?;
+    device.default_output_config().ok().map(|c| c.sample_rate().0)
+}
+
+/// Pulls 64-sample buffers from the ring and spreads them across the device's frames
+struct RtrbSource {
     output_buffer: Consumer<Buffer>,
+    current: Buffer,
+    position: usize,
     /// Last sample value, used to avoid pops when buffer underruns
     last_sample: f32,
+    underruns: Arc<AtomicU64>,
 }
 
-/// Message type for RtrbSource (no messages needed)
-#[derive(Clone, Copy, Debug)]
-pub enum RtrbSourceMessage {}
-
-impl AudioNode for RtrbSource {
-    type Message = RtrbSourceMessage;
-
-    fn process(
-        &mut self,
-        _ctx: &ProcessContext,
-        _messages: impl Iterator<Item = RtrbSourceMessage>,
-        _inputs: &[Input],
-        outputs: &mut [Buffer],
-    ) {
-        // Fill output buffer by popping from the emulator consumer.
-        // If no data available, hold last sample to avoid pops.
-        if let Some(output) = outputs.first_mut() {
-            match self.output_buffer.pop() {
-                Ok(buf) => {
-                    // Remember the last sample for continuity
-                    if let Some(&last) = buf.last() {
-                        self.last_sample = last;
-                    }
-                    *output = buf;
-                }
-                Err(_) => {
-                    // No data available - fill with last sample to avoid pops
-                    output.fill(self.last_sample);
-                }
-            }
+impl RtrbSource {
+    fn next_sample(&mut self) -> Option<f32> {
+        if self.position >= self.current.len() {
+            self.current = self.output_buffer.pop().ok()?;
+            self.position = 0;
         }
+        let sample = self.current[self.position];
+        self.position += 1;
+        Some(sample)
     }
 
-    fn num_outputs(&self) -> usize {
-        1
+    fn fill<T: SizedSample + FromSample<f32>>(&mut self, data: &mut [T], channels: usize) {
+        let mut starved = false;
+        for frame in data.chunks_mut(channels) {
+            match self.next_sample() {
+                Some(sample) => self.last_sample = sample,
+                None => starved = true,
+            }
+            frame.fill(T::from_sample(self.last_sample));
+        }
+        if starved {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
     }
 }
 
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &Device,
+    config: &StreamConfig,
+    mut source: RtrbSource,
+    channels: usize,
+) -> Result<Stream, BuildStreamError> {
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| source.fill(data, channels),
+        |e| error!("audio stream error: {}", e),
+        None,
+    )
+}
+
 pub struct GameTankAudio {
-    klingt: Klingt,
-    sample_rate: u32,
-    #[allow(dead_code)]
-    source_handle: Handle<RtrbSourceMessage>,
+    _stream: Stream,
+    pub sample_rate: u32,
+    pub device_name: String,
+    pub config: AudioConfig,
     producer: Producer<Buffer>,
-    start_time: Instant,
-    blocks_processed: u64,
+    capacity: usize,
+    underruns: Arc<AtomicU64>,
+    dropped: u64,
 }
 
 impl GameTankAudio {
-    /// Create the audio bridge. This creates an internal ring buffer (producer/consumer).
-    /// The emulator run loop should pop from its own buffer and push into this `producer`
-    /// via `push_buffer`.
-    pub fn new() -> Self {
-        let device = CpalDevice::default_output().expect("No audio device available");
-        let sample_rate = device.sample_rate();
-        let mut klingt = Klingt::new(sample_rate).with_output(device.create_sink());
-
-        // create an internal ring buffer where the source reads from the consumer
-        // and the app will push emulator buffers into the producer
-        let (producer, consumer) = RingBuffer::<Buffer>::new(4096);
-
-        // Create source node that will read from our internal consumer
+    /// Open the configured output device. The emulator run loop should pop from its own
+    /// buffer and push into this bridge via `push_buffer`.
+    pub fn new(config: AudioConfig) -> Result<Self, String> {
+        let device = find_device(config.device.as_deref())
+            .ok_or_else(|| format!("audio device not found: {:?}", config.device))?;
+        let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let default_config = device.default_output_config()
+            .map_err(|e| format!("no output config for {}: {}", device_name, e))?;
+
+        let sample_rate = default_config.sample_rate().0;
+        let channels = default_config.channels() as usize;
+        let stream_config = StreamConfig {
+            channels: default_config.channels(),
+            sample_rate: SampleRate(sample_rate),
+            buffer_size: config.buffer_size.map_or(BufferSize::Default, BufferSize::Fixed),
+        };
+
+        // enough 64-sample blocks to cover the target latency, plus one in flight
+        let latency_samples = (config.target_latency_ms / 1000.0 * sample_rate as f32) as usize;
+        let capacity = (latency_samples / 64).max(2) + 1;
+        let (producer, consumer) = RingBuffer::<Buffer>::new(capacity);
+
+        let underruns = Arc::new(AtomicU64::new(0));
         let source = RtrbSource {
             output_buffer: consumer,
+            current: Buffer::SILENT,
+            position: Buffer::LEN,
             last_sample: 0.0,
+            underruns: underruns.clone(),
         };
 
-        let source_handle = klingt.add(source);
-        klingt.output(&source_handle);
+        // open the stream in whatever format the device wants, converting from f32
+        let stream = match default_config.sample_format() {
+            SampleFormat::I8 => build_stream::<i8>(&device, &stream_config, source, channels),
+            SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, source, channels),
+            SampleFormat::I32 => build_stream::<i32>(&device, &stream_config, source, channels),
+            SampleFormat::I64 => build_stream::<i64>(&device, &stream_config, source, channels),
+            SampleFormat::U8 => build_stream::<u8>(&device, &stream_config, source, channels),
+            SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, source, channels),
+            SampleFormat::U32 => build_stream::<u32>(&device, &stream_config, source, channels),
+            SampleFormat::U64 => build_stream::<u64>(&device, &stream_config, source, channels),
+            SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, source, channels),
+            SampleFormat::F64 => build_stream::<f64>(&device, &stream_config, source, channels),
+            format => return Err(format!("{} wants {} samples, which aren't supported", device_name, format)),
+        }.map_err(|e| format!("failed to open {}: {}", device_name, e))?;
+        stream.play().map_err(|e| format!("failed to start {}: {}", device_name, e))?;
 
-        Self {
-            klingt,
+        Ok(Self {
+            _stream: stream,
             sample_rate,
-            source_handle,
+            device_name,
+            config,
             producer,
-            start_time: Instant::now(),
-            blocks_processed: 0,
-        }
+            capacity,
+            underruns,
+            dropped: 0,
+        })
     }
 
     /// Push a single emulator buffer into the internal ring buffer.
-    /// Drops the buffer if the ring is full.
+    /// Drops the buffer if we're already a full target latency ahead.
     pub fn push_buffer(&mut self, buf: Buffer) {
-        if let Err(_b) = self.producer.push(buf) {
-            warn!("audio bridge ring full; dropping audio buffer");
+        if self.producer.push(buf).is_err() {
+            self.dropped += 1;
+            if self.dropped % 64 == 1 {
+                warn!("audio bridge ring full; dropping audio buffer");
+            }
+        }
+    }
+
+    /// Times the device asked for audio and we didn't have any
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Buffers thrown away because the ring was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Audio currently queued ahead of the device, in milliseconds
+    pub fn queued_ms(&self) -> f32 {
+        let queued = self.capacity - self.producer.slots();
+        (queued * Buffer::LEN) as f32 * 1000.0 / self.sample_rate as f32
+    }
+}
+
+impl AudioSink for GameTankAudio {
+    fn push_samples(&mut self, samples: &[f32]) {
+        for chunk in samples.chunks(Buffer::LEN) {
+            let mut buf = Buffer::SILENT;
+            buf[..chunk.len()].copy_from_slice(chunk);
+            self.push_buffer(buf);
+        }
+    }
+}
+
+/// Where emulator audio goes. The web build prefers an AudioWorklet when the page
+/// allows it and otherwise uses cpal like the desktop does.
+pub enum AudioOutput {
+    Cpal(GameTankAudio),
+    #[cfg(target_arch = "wasm32")]
+    Worklet(crate::web_audio::WorkletAudio),
+}
+
+impl AudioOutput {
+    pub fn open(config: AudioConfig) -> Result<Self, String> {
+        #[cfg(target_arch = "wasm32")]
+        if crate::web_audio::WorkletAudio::available() {
+            match crate::web_audio::WorkletAudio::new(&config) {
+                Ok(worklet) => return Ok(Self::Worklet(worklet)),
+                Err(e) => warn!("audio worklet unavailable, falling back: {}", e),
+            }
         }
+
+        GameTankAudio::new(config).map(Self::Cpal)
     }
 
-    /// Process audio blocks to keep up with real-time.
-    /// Call this regularly from your main loop.
-    pub fn process_audio(&mut self) {
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        let rate = self.sample_rate as f64;
-        // Each block is 64 samples. Calculate how many blocks should be processed by now.
-        // Add a small buffer (4 blocks) to stay ahead.
-        let target_blocks = (elapsed * rate / 64.0) as u64 + 4;
-        
-        while self.blocks_processed < target_blocks {
-            self.klingt.process();
-            self.blocks_processed += 1;
+    pub fn device_name(&self) -> String {
+        match self {
+            Self::Cpal(a) => a.device_name.clone(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(_) => "AudioWorklet".to_string(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        match self {
+            Self::Cpal(a) => a.sample_rate,
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(a) => a.sample_rate,
+        }
+    }
+
+    pub fn queued_ms(&self) -> f32 {
+        match self {
+            Self::Cpal(a) => a.queued_ms(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(a) => a.queued_ms(),
+        }
+    }
+
+    pub fn underruns(&self) -> u64 {
+        match self {
+            Self::Cpal(a) => a.underruns(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(a) => a.underruns(),
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        match self {
+            Self::Cpal(a) => a.dropped(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(a) => a.dropped(),
+        }
+    }
+}
+
+impl AudioSink for AudioOutput {
+    fn push_samples(&mut self, samples: &[f32]) {
+        match self {
+            Self::Cpal(a) => a.push_samples(samples),
+            #[cfg(target_arch = "wasm32")]
+            Self::Worklet(a) => a.push_samples(samples),
         }
     }
 }
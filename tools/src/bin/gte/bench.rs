@@ -0,0 +1,83 @@
+use std::time::Instant;
+use gte_core::emulator::{Emulator, SubsystemTimes, TimeDaemon};
+use gte_core::frontend::NullFrontend;
+use crate::helpers::{arg_parse, arg_value};
+
+const DEFAULT_FRAMES: u64 = 3600;
+const FRAMES_PER_SECOND: f64 = 3_579_545.0 / 59659.0;
+
+/// High resolution clock for profiling; InstantClock only ticks in whole milliseconds
+pub struct BenchClock {
+    instant: Instant,
+}
+
+impl TimeDaemon for BenchClock {
+    fn get_now_ms(&self) -> f64 {
+        self.instant.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+pub struct BenchOptions {
+    pub rom: String,
+    pub frames: u64,
+    pub profile: bool,
+}
+
+impl BenchOptions {
+    /// Parses `gte --bench rom.gtr [--frames N] [--no-profile]`, or None if `--bench` wasn't given
+    pub fn from_args() -> Option<Result<Self, String>> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if !args.iter().any(|a| a == "--bench") {
+            return None;
+        }
+
+        let parse = || -> Result<Self, String> {
+            let rom = arg_value(&args, "--bench").cloned().ok_or("--bench needs a rom path")?;
+            let frames = arg_parse(&args, "--frames", DEFAULT_FRAMES)?;
+            let profile = !args.iter().any(|a| a == "--no-profile");
+            Ok(Self { rom, frames, profile })
+        };
+
+        Some(parse())
+    }
+}
+
+/// Run a ROM headless, as fast as possible, and print throughput numbers
+pub fn run(opts: BenchOptions) -> Result<(), String> {
+    let data = std::fs::read(&opts.rom).map_err(|e| format!("couldn't read {}: {}", opts.rom, e))?;
+
+    let clock = BenchClock { instant: Instant::now() };
+    let mut emulator = Emulator::init(clock, 48000.0);
+    emulator.load_rom(&data);
+
+    let mut times = SubsystemTimes::default();
+    let mut cycles = 0u64;
+
+    let start = Instant::now();
+    for _ in 0..opts.frames {
+        cycles += emulator.run_frame(opts.profile.then_some(&mut times));
+
+        // nobody's listening, keep the audio rings from filling up
+        emulator.drain_audio(&mut NullFrontend);
+    }
+    let wall = start.elapsed().as_secs_f64();
+
+    let emulated = opts.frames as f64 / FRAMES_PER_SECOND;
+    println!("rom:              {}", opts.rom);
+    println!("frames:           {}", opts.frames);
+    println!("wall time:        {:.3}s", wall);
+    println!("emulated time:    {:.3}s", emulated);
+    println!("speed:            {:.2}x realtime", emulated / wall);
+    println!("cpu cycles/sec:   {:.0}", cycles as f64 / wall);
+
+    if opts.profile {
+        let total = times.cpu_ms + times.blitter_ms + times.acp_ms;
+        let pct = |ms: f64| if total > 0.0 { ms / total * 100.0 } else { 0.0 };
+        println!("subsystems (profiling adds overhead, use --no-profile for raw speed):");
+        println!("  cpu:            {:>9.1}ms {:>5.1}%", times.cpu_ms, pct(times.cpu_ms));
+        println!("  blitter:        {:>9.1}ms {:>5.1}%", times.blitter_ms, pct(times.blitter_ms));
+        println!("  acp:            {:>9.1}ms {:>5.1}%", times.acp_ms, pct(times.acp_ms));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! Emulation runner
+//!
+//! On native targets the emulator runs on its own thread at a fixed timestep, so
+//! a stalled event loop (window drags, slow redraws) doesn't starve audio or make
+//! the emulator jump ahead to catch up. The UI talks to it over channels: inputs
+//! and commands go in, finished frames come out. Debug panels still lock the
+//! shared emulator directly while they draw.
+//!
+//! wasm has no threads, so there the runner is ticked from the event loop and
+//! follows the wall clock instead.
+
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{mpsc, Arc, Mutex};
+use gte_core::emulator::{Emulator, FrameStats};
+use gte_core::emulator::PlayState::{self, Paused, Playing};
+use gte_core::frontend::NullFrontend;
+use gte_core::inputs::{InputCommand, KeyState};
+use tracing::{error, warn};
+use crate::app_delegation::InstantClock;
+use crate::audio::{device_sample_rate, AudioConfig, AudioOutput};
+use crate::helpers::get_now_ms;
+use crate::tabs::check_rom_size;
+
+pub type SharedEmulator = Arc<Mutex<Emulator<InstantClock>>>;
+pub type Frame = Box<[u8; 128*128]>;
+
+/// Seconds per frame: one vblank every 59659 cycles of the 3.579545MHz cpu clock
+#[cfg(not(target_arch = "wasm32"))]
+const FRAME_SECS: f64 = 59659.0 / 3_579_545.0;
+/// How far behind the fixed timestep may fall before we stop trying to catch up
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_FRAMES_BEHIND: u32 = 4;
+
+pub enum EmuCommand {
+    Input(InputCommand, bool),
+    LoadRom(Vec<u8>),
+    /// A save state from `Emulator::save_state`
+    LoadState(Vec<u8>),
+    /// Flash cart contents saved from an earlier session
+    RestoreFlash(Vec<u8>),
+    SetPlayState(PlayState),
+    TogglePlay,
+    /// First user interaction; browsers won't start audio before one
+    Wake,
+    ReopenAudio(AudioConfig),
+    /// Close the output stream and throw samples away until unmuted
+    SetMuted(bool),
+    Shutdown,
+}
+
+/// Snapshot of the audio output, for the settings panel
+#[derive(Clone, Debug, Default)]
+pub struct AudioStatus {
+    /// Device name and sample rate, if a stream is open
+    pub stream: Option<(String, u32)>,
+    pub queued_ms: f32,
+    pub underruns: u64,
+    pub dropped: u64,
+    pub error: Option<String>,
+}
+
+/// How long emulation takes and what the machine did, for the perf HUD
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PerfStatus {
+    /// Host time spent emulating the last frame
+    pub step_ms: f32,
+    pub frame: FrameStats,
+}
+
+/// Everything that lives on the emulation side
+struct Driver {
+    emulator: SharedEmulator,
+    audio: Option<AudioOutput>,
+    audio_config: AudioConfig,
+    audio_error: Option<String>,
+    muted: bool,
+    audio_status: Arc<Mutex<AudioStatus>>,
+    perf_status: Arc<Mutex<PerfStatus>>,
+    frames: SyncSender<Frame>,
+}
+
+impl Driver {
+    /// Returns false once the runner should stop
+    fn apply(&mut self, command: EmuCommand) -> bool {
+        let mut emulator = self.emulator.lock().unwrap();
+        match command {
+            EmuCommand::Input(cmd, pressed) => {
+                let state = match emulator.input_state.get(&cmd) {
+                    Some(ks) => ks.update_state(pressed),
+                    None => KeyState::new(pressed),
+                };
+                emulator.set_input_state(cmd, state);
+            }
+            EmuCommand::LoadRom(data) => match check_rom_size(&data) {
+                Ok(()) => emulator.load_rom(&data),
+                Err(e) => error!("{}", e),
+            },
+            EmuCommand::LoadState(state) => {
+                if let Err(e) = emulator.load_state(&state) {
+                    error!("couldn't load save state: {:?}", e);
+                }
+            }
+            EmuCommand::RestoreFlash(flash) => {
+                if !emulator.cpu_bus.cartridge.restore_flash(&flash) {
+                    warn!("saved flash doesn't fit this cartridge; ignoring it");
+                }
+            }
+            EmuCommand::SetPlayState(state) => emulator.play_state = state,
+            EmuCommand::TogglePlay => {
+                emulator.play_state = if emulator.play_state == Playing { Paused } else { Playing };
+            }
+            EmuCommand::Wake => emulator.wasm_init(),
+            EmuCommand::ReopenAudio(config) => {
+                self.audio = None;
+                self.audio_error = None;
+                if let Some(rate) = device_sample_rate(&config) {
+                    emulator.target_sample_rate = rate as f64;
+                }
+                // the emulator resamples to the device rate, so its stream has to be rebuilt too
+                emulator.audio_out = None;
+                self.audio_config = config;
+            }
+            EmuCommand::SetMuted(muted) => {
+                self.muted = muted;
+                if muted {
+                    self.audio = None;
+                }
+            }
+            EmuCommand::Shutdown => return false,
+        }
+        true
+    }
+
+    /// Open audio once the emulator starts producing it, feed it, and publish the frame
+    fn after_frame(&mut self, emulator: &mut Emulator<InstantClock>, step_ms: f64) {
+        *self.perf_status.lock().unwrap() = PerfStatus {
+            step_ms: step_ms as f32,
+            frame: emulator.frame_stats,
+        };
+
+        if self.muted {
+            emulator.drain_audio(&mut NullFrontend);
+        } else if self.audio.is_none() && self.audio_error.is_none() && emulator.audio_out.is_some() {
+            match AudioOutput::open(self.audio_config.clone()) {
+                Ok(audio) => {
+                    // the worklet's context may not run at the rate cpal reported
+                    if audio.sample_rate() as f64 != emulator.target_sample_rate {
+                        emulator.target_sample_rate = audio.sample_rate() as f64;
+                        emulator.audio_out = None;
+                    }
+                    self.audio = Some(audio);
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    self.audio_error = Some(e);
+                }
+            }
+        }
+
+        if let Some(audio) = &mut self.audio {
+            emulator.drain_audio(audio);
+        }
+
+        *self.audio_status.lock().unwrap() = AudioStatus {
+            stream: self.audio.as_ref().map(|a| (a.device_name(), a.sample_rate())),
+            queued_ms: self.audio.as_ref().map_or(0.0, |a| a.queued_ms()),
+            underruns: self.audio.as_ref().map_or(0, |a| a.underruns()),
+            dropped: self.audio.as_ref().map_or(0, |a| a.dropped()),
+            error: self.audio_error.clone(),
+        };
+
+        // if the ui is behind it'll catch up on a later frame
+        let _ = self.frames.try_send(Box::new(**emulator.cpu_bus.read_full_framebuffer()));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn step_frame(&mut self) {
+        let shared = self.emulator.clone();
+        let mut emulator = shared.lock().unwrap();
+        let start = get_now_ms();
+        emulator.process_inputs();
+        if emulator.play_state == Playing {
+            emulator.run_frame(None);
+        }
+        self.after_frame(&mut emulator, get_now_ms() - start);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn step_wall_clock(&mut self) {
+        let shared = self.emulator.clone();
+        let mut emulator = shared.lock().unwrap();
+        let start = get_now_ms();
+        emulator.process_cycles(false);
+        self.after_frame(&mut emulator, get_now_ms() - start);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run(mut driver: Driver, commands: Receiver<EmuCommand>) {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::{Duration, Instant};
+
+    let frame = Duration::from_secs_f64(FRAME_SECS);
+    let mut deadline = Instant::now();
+
+    loop {
+        driver.step_frame();
+        deadline += frame;
+
+        // sleep out the rest of the frame, applying commands as they arrive
+        loop {
+            match commands.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(command) => if !driver.apply(command) { return },
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let behind = Instant::now().saturating_duration_since(deadline);
+        if behind > frame * MAX_FRAMES_BEHIND {
+            warn!("emulation fell {:.1}ms behind; skipping ahead", behind.as_secs_f64() * 1000.0);
+            deadline = Instant::now();
+        }
+    }
+}
+
+pub struct EmuRunner {
+    pub emulator: SharedEmulator,
+    pub audio_status: Arc<Mutex<AudioStatus>>,
+    pub perf_status: Arc<Mutex<PerfStatus>>,
+    frames: Receiver<Frame>,
+    #[cfg(not(target_arch = "wasm32"))]
+    commands: mpsc::Sender<EmuCommand>,
+    #[cfg(not(target_arch = "wasm32"))]
+    thread: Option<std::thread::JoinHandle<()>>,
+    #[cfg(target_arch = "wasm32")]
+    driver: Driver,
+}
+
+impl EmuRunner {
+    pub fn start(emulator: Emulator<InstantClock>, audio_config: AudioConfig) -> Self {
+        let emulator = Arc::new(Mutex::new(emulator));
+        let audio_status = Arc::new(Mutex::new(AudioStatus::default()));
+        let perf_status = Arc::new(Mutex::new(PerfStatus::default()));
+        let (frames_tx, frames) = mpsc::sync_channel(2);
+
+        // cpal streams can't move between threads, so the driver is built on the one it runs on
+        let make_driver = {
+            let emulator = emulator.clone();
+            let audio_status = audio_status.clone();
+            let perf_status = perf_status.clone();
+            move || Driver {
+                emulator,
+                audio: None,
+                audio_config,
+                audio_error: None,
+                muted: false,
+                audio_status,
+                perf_status,
+                frames: frames_tx,
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (commands, commands_rx) = mpsc::channel();
+            let thread = std::thread::Builder::new()
+                .name("emulator".into())
+                .spawn(move || run(make_driver(), commands_rx))
+                .expect("couldn't spawn emulator thread");
+
+            Self { emulator, audio_status, perf_status, frames, commands, thread: Some(thread) }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        Self { emulator, audio_status, perf_status, frames, driver: make_driver() }
+    }
+
+    pub fn send(&mut self, command: EmuCommand) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = self.commands.send(command);
+
+        #[cfg(target_arch = "wasm32")]
+        self.driver.apply(command);
+    }
+
+    /// Drive emulation from the event loop. Does nothing when it has its own thread.
+    pub fn tick(&mut self) {
+        #[cfg(target_arch = "wasm32")]
+        self.driver.step_wall_clock();
+    }
+
+    /// The newest finished frame since the last call, if any
+    pub fn latest_frame(&self) -> Option<Frame> {
+        self.frames.try_iter().last()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for EmuRunner {
+    fn drop(&mut self) {
+        let _ = self.commands.send(EmuCommand::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
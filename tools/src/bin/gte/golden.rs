@@ -0,0 +1,142 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use gte_core::color_map::COLOR_MAP;
+use gte_core::emulator::{Emulator, TimeDaemon};
+use gte_core::frontend::NullFrontend;
+use crate::helpers::{arg_parse, arg_value};
+
+const DEFAULT_FRAMES: u64 = 600;
+const DEFAULT_EVERY: u64 = 60;
+const HASH_FILE: &str = "hashes.txt";
+
+/// Golden runs never look at the clock, but the emulator needs one
+struct FrozenClock;
+
+impl TimeDaemon for FrozenClock {
+    fn get_now_ms(&self) -> f64 {
+        0.0
+    }
+}
+
+pub struct GoldenOptions {
+    pub rom: PathBuf,
+    pub dir: PathBuf,
+    pub frames: u64,
+    pub every: u64,
+    pub bless: bool,
+}
+
+impl GoldenOptions {
+    /// Parses `gte --golden rom.gtr [--frames N] [--every N] [--dir DIR] [--bless]`,
+    /// or None if `--golden` wasn't given
+    pub fn from_args() -> Option<Result<Self, String>> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if !args.iter().any(|a| a == "--golden") {
+            return None;
+        }
+
+        let parse = || -> Result<Self, String> {
+            let rom = PathBuf::from(arg_value(&args, "--golden").ok_or("--golden needs a rom path")?);
+            let stem = rom.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let dir = arg_value(&args, "--dir")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| rom.with_file_name("golden").join(stem));
+            let frames = arg_parse(&args, "--frames", DEFAULT_FRAMES)?;
+            let every = arg_parse(&args, "--every", DEFAULT_EVERY)?.max(1);
+            let bless = args.iter().any(|a| a == "--bless");
+            Ok(Self { rom, dir, frames, every, bless })
+        };
+
+        Some(parse())
+    }
+}
+
+/// FNV-1a, so hashes stay stable across platforms and releases
+fn hash_frame(fb: &[u8]) -> u64 {
+    fb.iter().fold(0xcbf29ce484222325, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn save_png(fb: &[u8], path: &Path) -> Result<(), String> {
+    let mut img = image::RgbaImage::new(128, 128);
+    for (pixel, &index) in img.pixels_mut().zip(fb) {
+        let (r, g, b, a) = COLOR_MAP[index as usize];
+        *pixel = image::Rgba([r, g, b, a]);
+    }
+    img.save(path).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+fn read_hashes(path: &Path) -> Result<Vec<(u64, u64)>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("couldn't read {} (run with --bless to create it): {}", path.display(), e))?;
+
+    text.lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let mut parts = l.split_whitespace();
+            let frame = parts.next().and_then(|f| f.parse().ok());
+            let hash = parts.next().and_then(|h| u64::from_str_radix(h, 16).ok());
+            frame.zip(hash).ok_or_else(|| format!("malformed line in {}: {}", path.display(), l))
+        })
+        .collect()
+}
+
+/// Run a ROM for a fixed number of frames and compare framebuffer hashes at each checkpoint
+/// against the stored goldens. With `--bless`, the goldens are rewritten instead.
+pub fn run(opts: GoldenOptions) -> Result<(), String> {
+    let data = std::fs::read(&opts.rom).map_err(|e| format!("couldn't read {}: {}", opts.rom.display(), e))?;
+
+    let mut emulator = Emulator::init(FrozenClock, 48000.0);
+    emulator.load_rom(&data);
+
+    let mut checkpoints = vec![];
+    for frame in 1..=opts.frames {
+        emulator.run_frame_with(&mut NullFrontend, &mut NullFrontend, &mut NullFrontend);
+
+        if frame % opts.every == 0 || frame == opts.frames {
+            let fb = **emulator.cpu_bus.read_full_framebuffer();
+            checkpoints.push((frame, fb));
+        }
+    }
+
+    std::fs::create_dir_all(&opts.dir).map_err(|e| format!("couldn't create {}: {}", opts.dir.display(), e))?;
+    let hash_path = opts.dir.join(HASH_FILE);
+
+    if opts.bless {
+        let mut out = format!("# golden framebuffer hashes for {}\n", opts.rom.display());
+        for (frame, fb) in &checkpoints {
+            let _ = writeln!(out, "{} {:016x}", frame, hash_frame(fb));
+            save_png(fb, &opts.dir.join(format!("frame_{:05}.png", frame)))?;
+        }
+        std::fs::write(&hash_path, out).map_err(|e| format!("couldn't write {}: {}", hash_path.display(), e))?;
+        println!("blessed {} checkpoints in {}", checkpoints.len(), opts.dir.display());
+        return Ok(());
+    }
+
+    let expected = read_hashes(&hash_path)?;
+    let mut failures = 0;
+
+    for (frame, want) in &expected {
+        let Some((_, fb)) = checkpoints.iter().find(|(f, _)| f == frame) else {
+            println!("frame {:>5}: MISSING (golden expects it, run was too short?)", frame);
+            failures += 1;
+            continue;
+        };
+
+        let got = hash_frame(fb);
+        if got == *want {
+            println!("frame {:>5}: ok", frame);
+        } else {
+            let actual_png = opts.dir.join(format!("frame_{:05}.actual.png", frame));
+            save_png(fb, &actual_png)?;
+            println!("frame {:>5}: FAIL expected {:016x}, got {:016x} (see {})", frame, want, got, actual_png.display());
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        Err(format!("{} of {} checkpoints differ from the golden images", failures, expected.len()))
+    } else {
+        println!("all {} checkpoints match", expected.len());
+        Ok(())
+    }
+}
@@ -21,3 +21,17 @@ pub fn get_now_ms() -> f64 {
         return START_INSTANT.unwrap().elapsed().as_secs_f64() * 1000.0;
     }
 }
+
+/// Value following `flag` on the command line, e.g. `--frames 600`
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1))
+}
+
+/// Parse the value following `flag`, falling back to `default` when the flag is absent
+pub fn arg_parse<T: std::str::FromStr>(args: &[String], flag: &str, default: T) -> Result<T, String> {
+    match arg_value(args, flag) {
+        Some(v) => v.parse().map_err(|_| format!("{} got an invalid value: {}", flag, v)),
+        None if args.iter().any(|a| a == flag) => Err(format!("{} needs a value", flag)),
+        None => Ok(default),
+    }
+}
@@ -9,6 +9,19 @@ mod app_ui;
 pub mod app_initialized;
 mod app_delegation;
 mod audio;
+mod emu_thread;
+mod recent;
+mod tabs;
+#[cfg(target_arch = "wasm32")]
+mod web_gamepad;
+#[cfg(target_arch = "wasm32")]
+mod web_audio;
+#[cfg(target_arch = "wasm32")]
+mod web_storage;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+mod golden;
 
 use app_delegation::DelegatedApp::Uninitialized;
 use std::cmp::PartialEq;
@@ -97,6 +110,22 @@ pub fn main() {
 
 
     #[cfg(not(target_arch = "wasm32"))] {
+        if let Some(opts) = bench::BenchOptions::from_args() {
+            if let Err(e) = opts.and_then(bench::run) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+
+        if let Some(opts) = golden::GoldenOptions::from_args() {
+            if let Err(e) = opts.and_then(golden::run) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+
         setup_logging();
         info!("stdout logger started");
 
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+const MAX_RECENT: usize = 10;
+const SETTINGS_FILE: &str = "gte.conf";
+
+/// Recently opened ROMs, newest first, persisted between runs.
+/// Nothing is saved on the web build.
+pub struct RecentRoms {
+    pub paths: Vec<PathBuf>,
+    pub reopen_last: bool,
+}
+
+impl RecentRoms {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn settings_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("gametank").join(SETTINGS_FILE))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn settings_path() -> Option<PathBuf> {
+        None
+    }
+
+    /// Reads `reopen_last = true|false` and `recent = <path>` lines
+    pub fn load() -> Self {
+        let mut recent = Self { paths: vec![], reopen_last: false };
+        let Some(text) = Self::settings_path().and_then(|p| std::fs::read_to_string(p).ok()) else {
+            return recent;
+        };
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key.trim() {
+                "reopen_last" => recent.reopen_last = value.trim() == "true",
+                "recent" => recent.paths.push(PathBuf::from(value.trim())),
+                _ => {}
+            }
+        }
+        recent.paths.truncate(MAX_RECENT);
+        recent
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::settings_path() else { return };
+
+        let mut text = format!("reopen_last = {}\n", self.reopen_last);
+        for rom in &self.paths {
+            text.push_str(&format!("recent = {}\n", rom.display()));
+        }
+
+        let result = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, text));
+        if let Err(e) = result {
+            warn!("couldn't save {}: {}", path.display(), e);
+        }
+    }
+
+    /// Move `path` to the front of the list and save
+    pub fn push(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT);
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.save();
+    }
+
+    pub fn last(&self) -> Option<&Path> {
+        self.paths.first().map(PathBuf::as_path)
+    }
+}
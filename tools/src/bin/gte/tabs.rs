@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use egui::Context;
+use tracing::{info, warn};
+use gte_core::cartridges::CartridgeType;
+use gte_core::emulator::Emulator;
+use gte_core::emulator::PlayState::Playing;
+use crate::app_delegation::InstantClock;
+use crate::app_initialized::AppInitialized;
+use crate::app_ui::gametankboy::GameTankBoyUI;
+use crate::app_ui::vram_viewer::{VRAMViewer, VRAMViewerLayout};
+use crate::audio::{device_sample_rate, AudioConfig};
+use crate::emu_thread::{EmuCommand, EmuRunner, Frame};
+use gametank_sdk::rom_builder::{is_elf, RomBuilder};
+use gametank_sdk::rom_header::RomHeader;
+use gametank_sdk::symbols::SymbolMap;
+
+/// One loaded ROM, its emulator, and the views onto it
+pub struct EmuTab {
+    pub name: String,
+    pub runner: EmuRunner,
+    pub screen: Frame,
+    /// Last frame's framebuffer write counts, while the heatmap overlay is on
+    pub heatmap: Option<Frame>,
+    pub console_gui: GameTankBoyUI,
+    pub vram_viewer: VRAMViewer,
+    /// Symbols for the loaded ROM, from its .sym file or ELF
+    pub symbols: Option<SymbolMap>,
+    /// File the ROM came from, and when it was last modified
+    source: Option<(PathBuf, Option<SystemTime>)>,
+    /// Where `symbols` came from, to reread alongside the ROM
+    symbols_path: Option<PathBuf>,
+}
+
+impl EmuTab {
+    pub fn new(name: String, mut emulator: Emulator<InstantClock>, context: &Context, audio_config: &AudioConfig) -> Self {
+        let screen = Box::new(**emulator.cpu_bus.read_full_framebuffer());
+        let console_gui = GameTankBoyUI::init(context, AppInitialized::buffer_to_color_image(&screen));
+        let vram_viewer = VRAMViewer::new(VRAMViewerLayout::Pages, context, &mut emulator);
+        let runner = EmuRunner::start(emulator, audio_config.clone());
+
+        Self { name, runner, screen, heatmap: None, console_gui, vram_viewer, symbols: None, source: None, symbols_path: None }
+    }
+
+    /// A fresh emulator with `rom` loaded and running
+    pub fn open(name: String, rom: &[u8], context: &Context, audio_config: &AudioConfig) -> Self {
+        let target_sample_rate = device_sample_rate(audio_config)
+            .map(|rate| rate as f64)
+            .unwrap_or(48000.0);
+        let mut emulator = Emulator::init(InstantClock { instant: Instant::now() }, target_sample_rate);
+        emulator.load_rom(rom);
+        emulator.play_state = Playing;
+
+        Self::new(name, emulator, context, audio_config)
+    }
+
+    /// Pick up the newest frame from the emulator thread. Returns true if there was one.
+    pub fn update_screen(&mut self) -> bool {
+        self.runner.tick();
+        let frame = self.runner.latest_frame();
+        let updated = frame.is_some();
+        if let Some(frame) = frame {
+            self.screen = frame;
+        }
+
+        if self.heatmap.is_some() {
+            let emulator = self.runner.emulator.lock().unwrap();
+            self.heatmap = emulator.cpu_bus.write_heatmap.as_ref().map(|h| Box::new(*h.last_frame()));
+        }
+
+        updated
+    }
+
+    /// Start or stop counting framebuffer writes for the heatmap overlay
+    pub fn set_heatmap(&mut self, enabled: bool) {
+        let mut emulator = self.runner.emulator.lock().unwrap();
+        if enabled {
+            emulator.cpu_bus.write_heatmap.get_or_insert_with(Default::default);
+            self.heatmap.get_or_insert_with(|| Box::new([0; 128*128]));
+        } else {
+            emulator.cpu_bus.write_heatmap = None;
+            self.heatmap = None;
+        }
+    }
+
+    /// Remember where the ROM came from so it can be reloaded when that file changes.
+    /// Picks up symbols from the ELF itself, or a .sym file next to the ROM.
+    pub fn set_source(&mut self, path: &Path) {
+        self.source = Some((path.to_path_buf(), modified(path)));
+
+        let sym_path = path.with_extension("sym");
+        if sym_path.exists() {
+            self.set_symbols(&sym_path);
+        } else if std::fs::read(path).is_ok_and(|data| is_elf(&data)) {
+            self.set_symbols(path);
+        }
+    }
+
+    /// Load symbols from a .sym file or an ELF
+    pub fn set_symbols(&mut self, path: &Path) {
+        self.symbols_path = Some(path.to_path_buf());
+        self.symbols = match read_symbols(path) {
+            Ok(symbols) => {
+                info!("loaded {} symbols from {}", symbols.symbols().len(), path.display());
+                Some(symbols)
+            }
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        };
+    }
+
+    /// Reload the ROM if its file was rebuilt since it was loaded. Returns true if it was.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let Some((path, loaded)) = &mut self.source else { return false };
+        let current = modified(path);
+        if current.is_none() || current == *loaded {
+            return false;
+        }
+        *loaded = current;
+
+        match read_rom(path) {
+            Ok(rom) => {
+                info!("{} changed; reloading", path.display());
+                self.runner.send(EmuCommand::LoadRom(rom));
+                self.runner.send(EmuCommand::SetPlayState(Playing));
+                if let Some(sym_path) = self.symbols_path.clone() {
+                    self.set_symbols(&sym_path);
+                }
+                true
+            }
+            // probably caught mid-write; the next change will retry
+            Err(e) => {
+                warn!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// Only the focused tab is heard
+    pub fn set_muted(&mut self, muted: bool) {
+        self.runner.send(EmuCommand::SetMuted(muted));
+    }
+}
+
+/// Tab label for a ROM path: the file name without its extension
+pub fn tab_name(path: &Path) -> String {
+    path.file_stem().map_or("untitled".to_string(), |s| s.to_string_lossy().to_string())
+}
+
+/// Read a ROM from disk, converting llvm-mos ELFs on the way in
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("couldn't open {}: {}", path.display(), e))?;
+    if !is_elf(&data) {
+        verify_rom(path, &data)?;
+        return Ok(data);
+    }
+
+    let image = RomBuilder::from_elf(&data).map_err(|e| format!("couldn't convert {}: {}", path.display(), e))?;
+    info!("converted {}, entry point ${:04X}", path.display(), image.reset_vector());
+    Ok(image.as_bytes().to_vec())
+}
+
+/// Only ROM sizes some cartridge has can be loaded; anything else would take the emulator thread down
+pub fn check_rom_size(data: &[u8]) -> Result<(), String> {
+    if CartridgeType::supports_len(data.len()) {
+        Ok(())
+    } else {
+        Err(format!("a {} byte ROM doesn't fit any cartridge (8K, 16K, 32K or 2M)", data.len()))
+    }
+}
+
+/// Check a ROM can be loaded, and if it has a footer, that it arrived in one piece
+pub fn verify_rom(path: &Path, data: &[u8]) -> Result<(), String> {
+    check_rom_size(data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if let Some(header) = RomHeader::read(data) {
+        header.verify(data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        info!("{}: {} (build {:016x}), {} bank checksums ok", path.display(), header.title, header.build_hash, header.bank_crcs.len());
+    }
+    Ok(())
+}
+
+/// Read symbols from a .sym file, or from an ELF's symbol table
+pub fn read_symbols(path: &Path) -> Result<SymbolMap, String> {
+    let data = std::fs::read(path).map_err(|e| format!("couldn't open {}: {}", path.display(), e))?;
+    if is_elf(&data) {
+        return SymbolMap::from_elf(&data).map_err(|e| format!("couldn't read symbols from {}: {}", path.display(), e));
+    }
+
+    let text = String::from_utf8_lossy(&data);
+    SymbolMap::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,140 @@
+//! AudioWorklet output for the web build
+//!
+//! Samples go into a SharedArrayBuffer ring that an AudioWorklet reads on the
+//! browser's audio thread, which avoids the latency and stutter of scheduling
+//! buffers from the main thread. SharedArrayBuffer needs the page to be
+//! cross-origin isolated; without that, `AudioOutput` falls back to cpal.
+
+use gte_core::frontend::AudioSink;
+use tracing::{error, warn};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::js_sys::{Array, Atomics, Float32Array, Int32Array, Object, Reflect, SharedArrayBuffer};
+use web_sys::{AudioContext, AudioWorkletNode, AudioWorkletNodeOptions, Blob, BlobPropertyBag, Url};
+use crate::audio::AudioConfig;
+
+const PROCESSOR_NAME: &str = "gte-ring-player";
+const RING_PLAYER_JS: &str = include_str!("assets/ring_player.js");
+
+const READ: u32 = 0;
+const WRITE: u32 = 1;
+const UNDERRUNS: u32 = 2;
+const HEADER_BYTES: u32 = 16;
+
+fn js_err(e: JsValue) -> String {
+    format!("{:?}", e)
+}
+
+pub struct WorkletAudio {
+    context: AudioContext,
+    header: Int32Array,
+    samples: Float32Array,
+    capacity: u32,
+    pub sample_rate: u32,
+    dropped: u64,
+}
+
+impl WorkletAudio {
+    /// SharedArrayBuffer is only there when the page is cross-origin isolated
+    pub fn available() -> bool {
+        web_sys::window().is_some_and(|w| w.cross_origin_isolated())
+    }
+
+    pub fn new(config: &AudioConfig) -> Result<Self, String> {
+        let context = AudioContext::new().map_err(js_err)?;
+        let sample_rate = context.sample_rate() as u32;
+
+        let capacity = ((config.target_latency_ms / 1000.0 * sample_rate as f32) as u32).max(256);
+        let buffer = SharedArrayBuffer::new(HEADER_BYTES + capacity * 4);
+        let header = Int32Array::new_with_byte_offset_and_length(&buffer, 0, 4);
+        let samples = Float32Array::new_with_byte_offset_and_length(&buffer, HEADER_BYTES, capacity);
+
+        // the worklet module loads asynchronously; samples queue up in the ring meanwhile
+        let loaded = context.audio_worklet().map_err(js_err)?
+            .add_module(&module_url()?).map_err(js_err)?;
+        let ctx = context.clone();
+        spawn_local(async move {
+            if let Err(e) = JsFuture::from(loaded).await {
+                error!("couldn't load the audio worklet: {:?}", e);
+                return;
+            }
+            if let Err(e) = connect_node(&ctx, &buffer) {
+                error!("couldn't start the audio worklet: {:?}", e);
+            }
+        });
+
+        Ok(Self { context, header, samples, capacity, sample_rate, dropped: 0 })
+    }
+
+    fn index(&self, slot: u32) -> u32 {
+        Atomics::load(&self.header, slot).unwrap_or(0) as u32
+    }
+
+    fn queued(&self) -> u32 {
+        (self.index(WRITE) + self.capacity - self.index(READ)) % self.capacity
+    }
+
+    pub fn queued_ms(&self) -> f32 {
+        self.queued() as f32 * 1000.0 / self.sample_rate as f32
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.index(UNDERRUNS) as u64
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl AudioSink for WorkletAudio {
+    /// Drops the whole batch if it doesn't fit, same as the cpal ring
+    fn push_samples(&mut self, samples: &[f32]) {
+        let free = self.capacity - 1 - self.queued();
+        if samples.len() as u32 > free {
+            self.dropped += 1;
+            if self.dropped % 64 == 1 {
+                warn!("audio worklet ring full; dropping audio");
+            }
+            return;
+        }
+
+        let write = self.index(WRITE);
+        let len = samples.len() as u32;
+        let first = len.min(self.capacity - write);
+        self.samples.subarray(write, write + first).copy_from(&samples[..first as usize]);
+        if first < len {
+            self.samples.subarray(0, len - first).copy_from(&samples[first as usize..]);
+        }
+        let _ = Atomics::store(&self.header, WRITE, ((write + len) % self.capacity) as i32);
+    }
+}
+
+impl Drop for WorkletAudio {
+    fn drop(&mut self) {
+        let _ = self.context.close();
+    }
+}
+
+/// The processor source, served from a blob so the page doesn't have to host it
+fn module_url() -> Result<String, String> {
+    let parts = Array::of1(&JsValue::from_str(RING_PLAYER_JS));
+    let options = BlobPropertyBag::new();
+    options.set_type("application/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_err)?;
+    Url::create_object_url_with_blob(&blob).map_err(js_err)
+}
+
+fn connect_node(context: &AudioContext, buffer: &SharedArrayBuffer) -> Result<(), JsValue> {
+    let processor_options = Object::new();
+    Reflect::set(&processor_options, &JsValue::from_str("buffer"), buffer)?;
+
+    let options = AudioWorkletNodeOptions::new();
+    options.set_processor_options(Some(&processor_options));
+    options.set_output_channel_count(&Array::of1(&JsValue::from(2)));
+
+    let node = AudioWorkletNode::new_with_options(context, PROCESSOR_NAME, &options)?;
+    node.connect_with_audio_node(&context.destination())?;
+    let _ = context.resume()?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Browser Gamepad API input
+//!
+//! Polls `navigator.getGamepads()` once per frame and turns button changes into
+//! controller inputs. The first two pads map to controllers 1 and 2. The default
+//! mapping follows the W3C "standard" layout; pages can rebind buttons through
+//! the exported functions below.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use gte_core::inputs::ControllerButton::{self, Down, Left, Right, Start, Up, A, B, C};
+use gte_core::inputs::InputCommand::{self, Controller1, Controller2};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Gamepad, GamepadButton};
+
+const DEFAULT_DEADZONE: f64 = 0.5;
+
+struct GamepadMapping {
+    /// Which Gamepad API button index drives each console button
+    buttons: Vec<(ControllerButton, u32)>,
+    /// How far the left stick has to move before it counts as a d-pad press
+    deadzone: f64,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        Self {
+            buttons: vec![(A, 0), (B, 1), (C, 2), (Start, 9), (Up, 12), (Down, 13), (Left, 14), (Right, 15)],
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+thread_local! {
+    static MAPPING: RefCell<GamepadMapping> = RefCell::new(GamepadMapping::default());
+}
+
+/// Bind a console button ("up", "a", "start", ...) to a Gamepad API button index
+#[wasm_bindgen]
+pub fn set_gamepad_button(button: &str, gamepad_button: u32) -> Result<(), JsValue> {
+    let button = ControllerButton::from_name(button).ok_or_else(|| JsValue::from_str(&format!("unknown button: {}", button)))?;
+    MAPPING.with(|m| {
+        let mut m = m.borrow_mut();
+        m.buttons.retain(|(b, _)| *b != button);
+        m.buttons.push((button, gamepad_button));
+    });
+    Ok(())
+}
+
+/// Left stick threshold, from 0.0 to 1.0; above 1.0 turns the stick off
+#[wasm_bindgen]
+pub fn set_gamepad_deadzone(deadzone: f64) {
+    MAPPING.with(|m| m.borrow_mut().deadzone = deadzone);
+}
+
+#[wasm_bindgen]
+pub fn reset_gamepad_mapping() {
+    MAPPING.with(|m| *m.borrow_mut() = GamepadMapping::default());
+}
+
+/// Buttons currently held on each pad, so only changes get forwarded
+pub struct WebGamepads {
+    held: [HashSet<ControllerButton>; 2],
+}
+
+impl WebGamepads {
+    pub fn new() -> Self {
+        Self { held: [HashSet::new(), HashSet::new()] }
+    }
+
+    /// Read every connected pad and return the inputs that changed
+    pub fn poll(&mut self) -> Vec<(InputCommand, bool)> {
+        let Some(pads) = web_sys::window().and_then(|w| w.navigator().get_gamepads().ok()) else {
+            return vec![];
+        };
+
+        let mut now: [HashSet<ControllerButton>; 2] = [HashSet::new(), HashSet::new()];
+        for i in 0..pads.length() {
+            let Ok(pad) = pads.get(i).dyn_into::<Gamepad>() else { continue };
+            let slot = pad.index() as usize;
+            if !pad.connected() || slot >= now.len() {
+                continue;
+            }
+            now[slot] = MAPPING.with(|m| held_buttons(&pad, &m.borrow()));
+        }
+
+        let mut changes = vec![];
+        for (slot, (held, now)) in self.held.iter_mut().zip(now).enumerate() {
+            let command = |b: ControllerButton| if slot == 0 { Controller1(b) } else { Controller2(b) };
+            changes.extend(now.difference(held).map(|b| (command(*b), true)));
+            changes.extend(held.difference(&now).map(|b| (command(*b), false)));
+            *held = now;
+        }
+        changes
+    }
+}
+
+fn held_buttons(pad: &Gamepad, mapping: &GamepadMapping) -> HashSet<ControllerButton> {
+    let buttons = pad.buttons();
+    let mut held: HashSet<ControllerButton> = mapping.buttons.iter()
+        .filter(|(_, index)| {
+            buttons.get(*index).dyn_into::<GamepadButton>().is_ok_and(|b| b.pressed())
+        })
+        .map(|(button, _)| *button)
+        .collect();
+
+    let axes = pad.axes();
+    let x = axes.get(0).as_f64().unwrap_or(0.0);
+    let y = axes.get(1).as_f64().unwrap_or(0.0);
+    if x < -mapping.deadzone { held.insert(Left); }
+    if x > mapping.deadzone { held.insert(Right); }
+    if y < -mapping.deadzone { held.insert(Up); }
+    if y > mapping.deadzone { held.insert(Down); }
+
+    held
+}
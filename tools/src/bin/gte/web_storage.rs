@@ -0,0 +1,198 @@
+//! IndexedDB persistence for the web build
+//!
+//! Save states and flash cart contents are stored per ROM, keyed by a hash of
+//! the ROM image, so progress survives a page reload. Flash is written back a
+//! second after the game stops writing to it.
+
+use std::cell::RefCell;
+use tracing::{error, info, warn};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::js_sys::{Function, Promise, Uint8Array};
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode, IdbVersionChangeEvent};
+use crate::app_initialized::queue_command;
+use crate::emu_thread::EmuCommand;
+use crate::helpers::get_now_ms;
+use crate::tabs::EmuTab;
+
+const DB_NAME: &str = "gte";
+const DB_VERSION: u32 = 1;
+const STATES: &str = "states";
+const FLASH: &str = "flash";
+/// How long flash has to sit unchanged before it's written back
+const FLASH_SETTLE_MS: f64 = 1000.0;
+
+enum StorageRequest {
+    SaveState(u32),
+    LoadState(u32),
+}
+
+thread_local! {
+    static REQUESTS: RefCell<Vec<StorageRequest>> = RefCell::new(vec![]);
+}
+
+/// Save the running game into `slot` for this ROM
+#[wasm_bindgen]
+pub fn save_state(slot: u32) {
+    REQUESTS.with(|r| r.borrow_mut().push(StorageRequest::SaveState(slot)));
+}
+
+/// Restore the state saved in `slot` for this ROM, if there is one
+#[wasm_bindgen]
+pub fn load_state(slot: u32) {
+    REQUESTS.with(|r| r.borrow_mut().push(StorageRequest::LoadState(slot)));
+}
+
+pub struct WebStorage {
+    rom_key: Option<String>,
+    flash_changed_ms: Option<f64>,
+}
+
+impl WebStorage {
+    pub fn new() -> Self {
+        Self { rom_key: None, flash_changed_ms: None }
+    }
+
+    /// Look up any saved flash for `rom`, then load both into the emulator and start it
+    pub fn load_rom(&mut self, rom: Vec<u8>) {
+        let key = rom_key(&rom);
+        self.rom_key = Some(key.clone());
+        self.flash_changed_ms = None;
+
+        spawn_local(async move {
+            let flash = match get(FLASH, &key).await {
+                Ok(flash) => flash,
+                Err(e) => {
+                    warn!("couldn't read saved flash: {:?}", e);
+                    None
+                }
+            };
+
+            queue_command(EmuCommand::LoadRom(rom));
+            if let Some(flash) = flash {
+                info!("restoring saved flash for {}", key);
+                queue_command(EmuCommand::RestoreFlash(flash));
+            }
+            queue_command(EmuCommand::SetPlayState(gte_core::emulator::PlayState::Playing));
+        });
+    }
+
+    /// Handle save/load requests from the page and write back flash once it settles
+    pub fn update(&mut self, tab: &mut EmuTab) {
+        let requests = REQUESTS.with(|r| r.take());
+        let Some(key) = self.rom_key.clone() else {
+            if !requests.is_empty() {
+                warn!("no rom loaded; ignoring save state request");
+            }
+            return
+        };
+
+        for request in requests {
+            match request {
+                StorageRequest::SaveState(slot) => {
+                    let state = tab.runner.emulator.lock().unwrap().save_state();
+                    persist(STATES, format!("{}/{}", key, slot), state);
+                }
+                StorageRequest::LoadState(slot) => {
+                    let state_key = format!("{}/{}", key, slot);
+                    spawn_local(async move {
+                        match get(STATES, &state_key).await {
+                            Ok(Some(state)) => queue_command(EmuCommand::LoadState(state)),
+                            Ok(None) => warn!("nothing saved in {}", state_key),
+                            Err(e) => error!("couldn't read {}: {:?}", state_key, e),
+                        }
+                    });
+                }
+            }
+        }
+
+        let now = get_now_ms();
+        let mut emulator = tab.runner.emulator.lock().unwrap();
+        if emulator.cpu_bus.cartridge.take_flash_dirty() {
+            self.flash_changed_ms = Some(now);
+        }
+        if self.flash_changed_ms.is_some_and(|changed| now - changed >= FLASH_SETTLE_MS) {
+            self.flash_changed_ms = None;
+            if let Some(flash) = emulator.cpu_bus.cartridge.flash_contents() {
+                persist(FLASH, key, flash.to_vec());
+            }
+        }
+    }
+}
+
+/// Identifies a ROM image across sessions
+fn rom_key(rom: &[u8]) -> String {
+    format!("{:08x}-{}", crc32fast::hash(rom), rom.len())
+}
+
+fn persist(store: &'static str, key: String, data: Vec<u8>) {
+    spawn_local(async move {
+        if let Err(e) = put(store, &key, &data).await {
+            error!("couldn't save {} to {}: {:?}", key, store, e);
+        }
+    });
+}
+
+/// Resolves with the request's result once IndexedDB reports success
+fn request_done(request: &IdbRequest) -> JsFuture {
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let req = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::NULL, &req.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let req = request.clone();
+        let on_error = Closure::once_into_js(move || {
+            let _ = reject.call1(&JsValue::NULL, &req.error().ok().flatten().map_or(JsValue::UNDEFINED, JsValue::from));
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise)
+}
+
+async fn open_db() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or("no window")?
+        .indexed_db()?
+        .ok_or("indexeddb isn't available")?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    let upgrade = Closure::<dyn FnMut(IdbVersionChangeEvent)>::new(|event: IdbVersionChangeEvent| {
+        let db = event.target()
+            .and_then(|t| t.dyn_into::<IdbOpenDbRequest>().ok())
+            .and_then(|r| r.result().ok())
+            .and_then(|r| r.dyn_into::<IdbDatabase>().ok());
+        let Some(db) = db else { return };
+        for store in [STATES, FLASH] {
+            if !db.object_store_names().contains(store) {
+                if let Err(e) = db.create_object_store(store) {
+                    error!("couldn't create {} store: {:?}", store, e);
+                }
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(upgrade.as_ref().unchecked_ref()));
+
+    let db = request_done(&request).await?;
+    db.dyn_into()
+}
+
+async fn put(store: &str, key: &str, data: &[u8]) -> Result<(), JsValue> {
+    let db = open_db().await?;
+    let tx = db.transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?;
+    let request = tx.object_store(store)?.put_with_key(&Uint8Array::from(data), &JsValue::from_str(key))?;
+    request_done(&request).await?;
+    Ok(())
+}
+
+async fn get(store: &str, key: &str) -> Result<Option<Vec<u8>>, JsValue> {
+    let db = open_db().await?;
+    let tx = db.transaction_with_str(store)?;
+    let request = tx.object_store(store)?.get(&JsValue::from_str(key))?;
+    let value = request_done(&request).await?;
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    Ok(Some(Uint8Array::new(&value).to_vec()))
+}
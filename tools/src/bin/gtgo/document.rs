@@ -0,0 +1,98 @@
+//! The file a sprite or tilemap editor has open
+//!
+//! A [`DocumentFile`] keeps where the document saves, whether it's changed
+//! since, and the copies of it to undo and redo back to. An editor keeps the
+//! document itself, and hands it to [`edit`](DocumentFile::edit) to change.
+
+use std::path::PathBuf;
+
+/// Edits kept for undoing
+const UNDO_LIMIT: usize = 64;
+
+/// The file behind a `T`, and the editor commands `C` that would drop it
+pub struct DocumentFile<T, C> {
+    pub path: Option<PathBuf>,
+    pub modified: bool,
+    /// The extension an untitled document gets
+    extension: &'static str,
+    /// A command that would drop unsaved changes, asked for once already
+    discarding: Option<C>,
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T: Clone + PartialEq, C: Copy + PartialEq> DocumentFile<T, C> {
+    pub fn new(extension: &'static str) -> Self {
+        Self { path: None, modified: false, extension, discarding: None, undo: vec![], redo: vec![] }
+    }
+
+    pub fn name(&self) -> String {
+        self.path.as_ref()
+            .and_then(|p| p.file_name())
+            .map_or(format!("untitled.{}", self.extension), |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Whether `cmd` has to wait: the first time it'd drop unsaved changes,
+    /// it only warns, in `status`
+    pub fn must_confirm(&mut self, cmd: C, status: &mut String) -> bool {
+        if !self.modified || self.discarding == Some(cmd) {
+            return false;
+        }
+        self.discarding = Some(cmd);
+        *status = format!("{} has unsaved changes; do that again to drop them", self.name());
+        true
+    }
+
+    /// Start over with a document from `path`, or a new one, and no history
+    pub fn replace(&mut self, path: Option<PathBuf>) {
+        self.path = path;
+        self.modified = false;
+        self.discarding = None;
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Change `document` with `change`, and keep what it was so it can be undone
+    pub fn edit(&mut self, document: &mut T, change: impl FnOnce(&mut T)) {
+        let before = document.clone();
+        change(document);
+        if *document != before {
+            self.remember(before);
+        }
+    }
+
+    /// Keep `before`, the document as it was before an edit, to undo back to
+    pub fn remember(&mut self, before: T) {
+        self.undo.push(before);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.modified = true;
+        self.discarding = None;
+    }
+
+    /// Put `document` back as it was before the last edit, or say in
+    /// `status` that there's nothing to undo
+    pub fn undo(&mut self, document: &mut T, status: &mut String) {
+        match self.undo.pop() {
+            Some(before) => {
+                self.redo.push(std::mem::replace(document, before));
+                self.modified = true;
+            }
+            None => *status = "Nothing to undo".to_string(),
+        }
+    }
+
+    /// Make the last undone edit to `document` again, or say in `status`
+    /// that there's nothing to redo
+    pub fn redo(&mut self, document: &mut T, status: &mut String) {
+        match self.redo.pop() {
+            Some(after) => {
+                self.undo.push(std::mem::replace(document, after));
+                self.modified = true;
+            }
+            None => *status = "Nothing to redo".to_string(),
+        }
+    }
+}
@@ -24,6 +24,36 @@ pub fn centered_rect(pct_x: u16, pct_y: u16, area: Rect) -> Rect {
 
 
 
+/// Where a view `size` long has to start, from `scroll`, to show `cursor`;
+/// it only moves when the cursor goes off an edge
+pub fn follow(scroll: usize, cursor: usize, size: usize) -> usize {
+    match size {
+        0 => scroll,
+        _ if cursor < scroll => cursor,
+        _ if cursor >= scroll + size => cursor + 1 - size,
+        _ => scroll,
+    }
+}
+
+/// Set the cells of a `width` wide grid that touch `(x, y)` and match it
+/// to `value`
+pub fn flood<T: Copy + PartialEq>(cells: &mut [T], width: usize, height: usize, x: usize, y: usize, value: T) {
+    let old = cells[y * width + x];
+    if old == value {
+        return;
+    }
+
+    let mut stack = vec![(x, y)];
+    while let Some((x, y)) = stack.pop() {
+        // wrapping takes 0 - 1 past the edge, which this stops
+        if x >= width || y >= height || cells[y * width + x] != old {
+            continue;
+        }
+        cells[y * width + x] = value;
+        stack.extend([(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)]);
+    }
+}
+
 pub fn poll_events() -> Vec<Event> {
     let mut events = vec![];
     while let Ok(true) = event::poll(Duration::from_millis(0)) {
@@ -0,0 +1,208 @@
+use std::{collections::VecDeque, io::{BufRead, BufReader, Read}, process::{Command, Stdio}, thread};
+
+use crossbeam_channel::{Receiver, Sender};
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind}, layout::{Constraint, Direction, Layout, Rect}, style::{Color, Stylize}, symbols::border, text::{Line, Span}, widgets::{Block, Paragraph}, Frame};
+
+use crate::helpers::SCHEME;
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const PANEL_HEIGHT: u16 = 12;
+const MAX_LINES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    /// Waiting for the job ahead of it to finish
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+pub struct Job {
+    pub name: String,
+    pub status: JobStatus,
+    pub output: Vec<String>,
+}
+
+enum JobEvent {
+    Output(usize, String),
+    Finished(usize, bool),
+}
+
+/// Build/flash jobs running on worker threads, with their output streamed back to the UI.
+/// They run one at a time, since they'd fight over the container and serial
+/// port otherwise; jobs spawned while one runs wait their turn.
+pub struct Jobs {
+    jobs: Vec<Job>,
+    /// Id, program and args of each queued job, oldest first
+    queue: VecDeque<(usize, String, Vec<String>)>,
+    tx: Sender<JobEvent>,
+    rx: Receiver<JobEvent>,
+    expanded: bool,
+    spinner: usize,
+}
+
+impl Jobs {
+    pub fn init() -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        Self { jobs: vec![], queue: VecDeque::new(), tx, rx, expanded: false, spinner: 0 }
+    }
+
+    pub fn spawn(&mut self, name: String, program: String, args: Vec<String>) {
+        let id = self.jobs.len();
+        self.jobs.push(Job { name, status: JobStatus::Queued, output: vec![] });
+        self.expanded = true;
+        self.queue.push_back((id, program, args));
+        self.start_next();
+    }
+
+    /// Start the oldest queued job, unless one's already running
+    fn start_next(&mut self) {
+        if self.is_busy() {
+            return;
+        }
+        let Some((id, program, args)) = self.queue.pop_front() else { return };
+        self.jobs[id].status = JobStatus::Running;
+
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let child = Command::new(&program)
+                .args(&args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn();
+
+            let mut child = match child {
+                Ok(c) => c,
+                Err(e) => {
+                    let _ = tx.send(JobEvent::Output(id, format!("failed to start {}: {}", program, e)));
+                    let _ = tx.send(JobEvent::Finished(id, false));
+                    return;
+                }
+            };
+
+            let readers: Vec<_> = [
+                child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+                child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+            ].into_iter().flatten().map(|stream| {
+                let tx = tx.clone();
+                thread::spawn(move || stream_lines(id, stream, tx))
+            }).collect();
+
+            for r in readers {
+                let _ = r.join();
+            }
+
+            let success = child.wait().map(|s| s.success()).unwrap_or(false);
+            let _ = tx.send(JobEvent::Finished(id, success));
+        });
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.jobs.iter().any(|j| j.status == JobStatus::Running)
+    }
+
+    /// Pull any output that's arrived from the worker threads
+    pub fn poll(&mut self) {
+        for event in self.rx.try_iter() {
+            match event {
+                JobEvent::Output(id, line) => {
+                    let out = &mut self.jobs[id].output;
+                    out.push(line);
+                    if out.len() > MAX_LINES {
+                        out.drain(..out.len() - MAX_LINES);
+                    }
+                }
+                JobEvent::Finished(id, success) => {
+                    self.jobs[id].status = if success { JobStatus::Succeeded } else { JobStatus::Failed };
+                }
+            }
+        }
+        self.start_next();
+        self.spinner = (self.spinner + 1) % SPINNER.len();
+    }
+
+    /// Returns true if the event was consumed by the job panel
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Key(KeyEvent { code: KeyCode::F(2), kind: KeyEventKind::Press, .. }) => {
+                self.expanded = !self.expanded;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The running job, or the last one when nothing's running
+    fn current(&self) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.status == JobStatus::Running).or(self.jobs.last())
+    }
+
+    /// Split the screen into (main area, job panel, status bar)
+    pub fn layout(&self, area: Rect) -> (Rect, Rect, Rect) {
+        let panel = if self.expanded && !self.jobs.is_empty() { PANEL_HEIGHT } else { 0 };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(panel),
+                Constraint::Length(1),
+            ])
+            .split(area);
+        (chunks[0], chunks[1], chunks[2])
+    }
+
+    pub fn render(&self, frame: &mut Frame, panel: Rect, status: Rect) {
+        let style = SCHEME.style(Color::Rgb(36, 36, 36));
+
+        if panel.height > 0 {
+            if let Some(job) = self.current() {
+                let block = Block::bordered()
+                    .border_set(border::ROUNDED)
+                    .title(format!(" {} ", job.name))
+                    .title_style(style.bold().fg(SCHEME.orange[1]))
+                    .style(style);
+                let visible = panel.height.saturating_sub(2) as usize;
+                let start = job.output.len().saturating_sub(visible);
+                let lines: Vec<Line> = job.output[start..].iter().map(|l| Line::from(l.as_str())).collect();
+                frame.render_widget(Paragraph::new(lines).block(block), panel);
+            }
+        }
+
+        let mut spans = vec![];
+        match self.current() {
+            Some(job) => {
+                let (icon, color) = match job.status {
+                    JobStatus::Queued => ("…", SCHEME.gray[2]),
+                    JobStatus::Running => (SPINNER[self.spinner], SCHEME.orange[1]),
+                    JobStatus::Succeeded => ("✓", SCHEME.green[1]),
+                    JobStatus::Failed => ("✗", SCHEME.red[1]),
+                };
+                spans.push(Span::from(format!(" {} ", icon)).fg(color).bold());
+                spans.push(Span::from(job.name.clone()));
+                if let Some(last) = job.output.last() {
+                    spans.push(Span::from(format!(" │ {}", last)).fg(SCHEME.gray[2]));
+                }
+                if !self.queue.is_empty() {
+                    spans.push(Span::from(format!(" (+{} queued)", self.queue.len())).fg(SCHEME.gray[2]));
+                }
+            }
+            None => spans.push(Span::from(" no jobs").fg(SCHEME.gray[2])),
+        }
+        spans.push(Span::from("  [F2] jobs").fg(SCHEME.gray[2]));
+
+        frame.render_widget(Paragraph::new(Line::from(spans)).style(style), status);
+    }
+}
+
+/// Forward lines from a child process, treating `\r` as a line break so progress bars update in place
+fn stream_lines(id: usize, stream: Box<dyn Read + Send>, tx: Sender<JobEvent>) {
+    let reader = BufReader::new(stream);
+    for chunk in reader.split(b'\n').map_while(Result::ok) {
+        let text = String::from_utf8_lossy(&chunk);
+        if let Some(line) = text.rsplit('\r').find(|s| !s.is_empty()) {
+            let _ = tx.send(JobEvent::Output(id, line.to_string()));
+        }
+    }
+}
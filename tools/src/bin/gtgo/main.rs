@@ -1,14 +1,19 @@
 pub mod main_menu;
 pub mod helpers;
+pub mod document;
 pub mod ui;
 pub mod tracker;
+pub mod jobs;
+pub mod palette;
+pub mod sprite;
+pub mod tilemap;
 
-use std::{thread::sleep, time::Duration};
+use std::{io::stdout, path::PathBuf, thread::sleep, time::Duration};
 
-use ratatui::{crossterm::event::Event, layout::Rect, DefaultTerminal, Frame};
+use ratatui::{crossterm::{event::{DisableMouseCapture, EnableMouseCapture, Event}, ExecutableCommand}, layout::Rect, DefaultTerminal, Frame};
 use anyhow::{bail, Ok, Result};
 
-use crate::{helpers::poll_events, main_menu::MainMenu};
+use crate::{helpers::poll_events, jobs::Jobs, main_menu::MainMenu, sprite::{is_sprite, SpriteEditor}, tilemap::{is_map, TilemapEditor}, tracker::Tracker};
 
 pub trait Component {
     fn update(&mut self, events: Vec<Event>);
@@ -17,26 +22,34 @@ pub trait Component {
 
 pub enum GlobalEvent {
     ChangeInterface(Box<dyn Component>),
+    SpawnJob { name: String, program: String, args: Vec<String> },
     Quit,
 }
 
 pub struct GtGo {
     terminal: DefaultTerminal,
     state: Box<dyn Component>,
+    jobs: Jobs,
     rx: crossbeam_channel::Receiver<GlobalEvent>
 }
 
 impl GtGo {
     fn run(&mut self) -> Result<()> {
         let _ = self.terminal.draw(|f| {
-            let events = poll_events();
+            let mut events = poll_events();
+            events.retain(|e| !self.jobs.handle_event(e));
+            self.jobs.poll();
+
+            let (main, panel, status) = self.jobs.layout(f.area());
             self.state.update(events);
-            self.state.render(f, f.area()); // unhandled error
+            self.state.render(f, main); // unhandled error
+            self.jobs.render(f, panel, status);
         });
 
         for event in self.rx.try_iter() {
             match event {
                 GlobalEvent::ChangeInterface(component) => self.state = component,
+                GlobalEvent::SpawnJob { name, program, args } => self.jobs.spawn(name, program, args),
                 GlobalEvent::Quit => bail!("Exit"),
             }
         }
@@ -47,7 +60,10 @@ impl GtGo {
 
 fn main() -> Result<()> {
     let terminal = ratatui::init();
+    // for drawing wavetables
+    let _ = stdout().execute(EnableMouseCapture);
     let result = run(terminal);
+    let _ = stdout().execute(DisableMouseCapture);
     ratatui::restore();
     result
 }
@@ -55,9 +71,20 @@ fn main() -> Result<()> {
 fn run(terminal: DefaultTerminal) -> Result<()> {
     let (tx, rx) = crossbeam_channel::unbounded();
 
+    // `gtgo song.gtm` goes straight to the tracker with that module, and
+    // `gtgo sprite.png` to the sprite editor, and `gtgo level.tmj` to the
+    // tilemap editor
+    let state: Box<dyn Component> = match std::env::args_os().nth(1).map(PathBuf::from) {
+        Some(path) if is_sprite(&path) => Box::new(SpriteEditor::open(tx, path)),
+        Some(path) if is_map(&path) => Box::new(TilemapEditor::open(tx, path)),
+        Some(path) => Box::new(Tracker::open(tx, path)),
+        None => Box::new(MainMenu::init(tx)),
+    };
+
     let mut app = GtGo { 
         terminal, 
-        state: Box::new(MainMenu::init(tx)),
+        state,
+        jobs: Jobs::init(),
         rx,
     };
 
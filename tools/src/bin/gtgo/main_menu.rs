@@ -1,11 +1,13 @@
+use std::process::{Command, Stdio};
+
 use crossbeam_channel::Sender;
 use ratatui::{crossterm::event::Event, layout::Rect, style::{Color, Stylize}, symbols::border, widgets::{Block, Widget}, Frame};
 
-use crate::{helpers::SCHEME, tracker::Tracker, ui::quickmenu::{qi, QuickMenu}, Component, GlobalEvent};
+use crate::{helpers::SCHEME, palette::browser::PaletteBrowser, sprite::SpriteEditor, tilemap::TilemapEditor, tracker::Tracker, ui::quickmenu::{qi, QuickMenu}, Component, GlobalEvent};
 
 #[allow(dead_code)]
 pub struct MainMenu {
-    has_podman: bool,
+    can_build: bool,
     quit: bool,
     qm: QuickMenu,
     tx: Sender<GlobalEvent>
@@ -13,10 +15,14 @@ pub struct MainMenu {
 
 impl MainMenu {
     pub fn init(tx_main: Sender<GlobalEvent>) -> Self {
-        // TODO: if has podman
-        let has_podman = false;
+        let can_build = build_env_available();
 
         let txx = tx_main.clone();
+        let tx_sprites = tx_main.clone();
+        let tx_maps = tx_main.clone();
+        let tx_colors = tx_main.clone();
+        let tx_build = tx_main.clone();
+        let tx_flash = tx_main.clone();
 
         let qm = QuickMenu::init(" Program Select ".to_string(), vec![
             qi("_Emulator", true, || { todo!() }),
@@ -24,12 +30,28 @@ impl MainMenu {
                 let tracker = Tracker::init(txx.clone());
                 let _ = txx.send(GlobalEvent::ChangeInterface(Box::new(tracker))); 
             }),
-            qi("_Build", has_podman, || { println!("ur mom") }),
-            qi("ROM _Flasher", true, || { todo!() }),
+            qi("_Sprites", true, move || {
+                let editor = SpriteEditor::init(tx_sprites.clone());
+                let _ = tx_sprites.send(GlobalEvent::ChangeInterface(Box::new(editor)));
+            }),
+            qi("_Maps", true, move || {
+                let editor = TilemapEditor::init(tx_maps.clone());
+                let _ = tx_maps.send(GlobalEvent::ChangeInterface(Box::new(editor)));
+            }),
+            qi("_Palette", true, move || {
+                let browser = PaletteBrowser::init(tx_colors.clone());
+                let _ = tx_colors.send(GlobalEvent::ChangeInterface(Box::new(browser)));
+            }),
+            qi("_Build", can_build, move || {
+                let _ = tx_build.send(gtrom_job("build", &["build"]));
+            }),
+            qi("ROM _Flasher", true, move || {
+                let _ = tx_flash.send(gtrom_job("flash", &["flash"]));
+            }),
         ]);
 
         Self {
-            has_podman,
+            can_build,
             quit: false,
             qm,
             tx: tx_main,
@@ -37,6 +59,30 @@ impl MainMenu {
     }
 }
 
+fn tool_runs(cmd: &str, args: &[&str]) -> bool {
+    Command::new(cmd)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Whether `gtrom build` has something to build with: a container runtime,
+/// or a local llvm-mos toolchain like `BuildEnv::select` falls back to
+fn build_env_available() -> bool {
+    ["podman", "docker"].iter().any(|rt| tool_runs(rt, &["--version"]))
+        || (tool_runs("cargo", &["+mos", "--version"]) && tool_runs("llvm-mc", &["--version"]))
+}
+
+fn gtrom_job(name: &str, args: &[&str]) -> GlobalEvent {
+    GlobalEvent::SpawnJob {
+        name: format!("gtrom {}", name),
+        program: "gtrom".to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+    }
+}
 
 impl Component for MainMenu {
     fn render(&mut self, frame: &mut Frame, area: Rect) {
@@ -44,7 +90,7 @@ impl Component for MainMenu {
             .border_set(border::ROUNDED)
             .title("─ GameTank GO! ")
             .title_style(SCHEME.style(Color::Rgb(36, 36, 36)).italic().bold());
-        block.render(frame.area(), frame.buffer_mut());
+        block.render(area, frame.buffer_mut());
         self.qm.render(frame, area);
     }
     
@@ -0,0 +1,318 @@
+//! Looking colors up
+//!
+//! Every color, as the emulator shows it, with the ways to write it in Rust
+//! and the SDK color it's closest to. Bytes that look like an earlier one
+//! are left out unless asked for, since gtrom would turn them into it anyway.
+
+use crossbeam_channel::Sender;
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}, layout::{Alignment, Constraint, Layout, Position, Rect}, style::{Style, Stylize}, text::{Line, Span}, widgets::{Block, Borders, Paragraph}, Frame};
+
+use crate::{helpers::SCHEME, main_menu::MainMenu, Component, GlobalEvent};
+
+use super::{first_alike, hsl, hue, literal, luminance, nearest_named, rgb, saturation, terminal, is_light, HUE_NAMES, TRANSPARENT};
+
+/// Rows the wheel scrolls
+const WHEEL_STEP: isize = 3;
+const SWATCH_WIDTH: usize = 6;
+
+pub struct PaletteBrowser {
+    tx_main: Sender<GlobalEvent>,
+
+    /// Everything a color can be found by, lowercase, for each byte
+    search_text: Vec<String>,
+    query: String,
+    /// Whether to list bytes that look like an earlier one
+    alike: bool,
+    /// The colors that match, in order
+    shown: Vec<u8>,
+    selected: usize,
+    scroll: usize,
+    status: String,
+    /// Kept while gtgo runs, since on some desktops copied text goes with it
+    clipboard: Option<arboard::Clipboard>,
+
+    /// Where the list was last drawn, for the mouse
+    list_area: Rect,
+}
+
+impl PaletteBrowser {
+    pub fn init(tx_main: Sender<GlobalEvent>) -> Self {
+        let mut browser = Self {
+            tx_main,
+            search_text: (0..=u8::MAX).map(search_text).collect(),
+            query: String::new(),
+            alike: false,
+            shown: vec![],
+            selected: 0,
+            scroll: 0,
+            status: "Type to search: a literal like 0b010, a hue or SDK color like red, or s3 for a saturation".to_string(),
+            clipboard: None,
+            list_area: Rect::default(),
+        };
+        browser.filter();
+        browser
+    }
+
+    fn color(&self) -> Option<u8> {
+        self.shown.get(self.selected).copied()
+    }
+
+    /// List the colors matching every word of the query, keeping the
+    /// selected one if it's still there
+    fn filter(&mut self) {
+        let selected = self.color();
+        let query = self.query.to_lowercase();
+        self.shown = (0..=u8::MAX)
+            .filter(|&color| self.alike || first_alike(color) == color)
+            .filter(|&color| query.split_whitespace().all(|word| self.search_text[color as usize].contains(word)))
+            .collect();
+        self.selected = selected
+            .and_then(|color| self.shown.iter().position(|&c| c == color))
+            .unwrap_or(0);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.shown.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    fn copy(&mut self, text: String) {
+        let copied = match self.clipboard.as_mut() {
+            Some(clipboard) => clipboard.set_text(&text),
+            None => arboard::Clipboard::new().and_then(|mut clipboard| {
+                clipboard.set_text(&text)?;
+                self.clipboard = Some(clipboard);
+                Ok(())
+            }),
+        };
+        self.status = match copied {
+            Ok(()) => format!("Copied {}", text),
+            Err(e) => format!("Couldn't copy {}: {}", text, e),
+        };
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        let page = self.list_area.height.max(1) as isize;
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) if !self.query.is_empty() => {
+                self.query.clear();
+                self.filter();
+            }
+            (KeyCode::Esc, _) => {
+                let menu = MainMenu::init(self.tx_main.clone());
+                let _ = self.tx_main.send(GlobalEvent::ChangeInterface(Box::new(menu)));
+            }
+            (KeyCode::Up, _) => self.move_selection(-1),
+            (KeyCode::Down, _) => self.move_selection(1),
+            (KeyCode::PageUp, _) => self.move_selection(-page),
+            (KeyCode::PageDown, _) => self.move_selection(page),
+            (KeyCode::Home, _) => self.selected = 0,
+            (KeyCode::End, _) => self.move_selection(isize::MAX),
+            (KeyCode::Enter, _) => {
+                if let Some(color) = self.color() {
+                    self.copy(literal(color));
+                }
+            }
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+                if let Some(color) = self.color() {
+                    self.copy(hsl(color));
+                }
+            }
+            (KeyCode::Tab, _) => {
+                self.alike = !self.alike;
+                self.filter();
+            }
+            (KeyCode::Backspace, _) => {
+                self.query.pop();
+                self.filter();
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+                self.query.clear();
+                self.filter();
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) if self.list_area.contains(Position::new(mouse.column, mouse.row)) => {
+                let row = self.scroll + (mouse.row - self.list_area.y) as usize;
+                if row < self.shown.len() {
+                    self.selected = row;
+                }
+            }
+            MouseEventKind::ScrollUp => self.move_selection(-WHEEL_STEP),
+            MouseEventKind::ScrollDown => self.move_selection(WHEEL_STEP),
+            _ => {}
+        }
+    }
+
+    fn render_list(&mut self, frame: &mut Frame, area: Rect) {
+        let title = format!(" {} of {} colors ", self.shown.len(), if self.alike { 256 } else { distinct() });
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.list_area = block.inner(area);
+        frame.render_widget(block, area);
+
+        // keep the selected color in view
+        let rows = self.list_area.height as usize;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if rows > 0 && self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        let lines: Vec<Line> = self.shown.iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(rows)
+            .map(|(i, &color)| {
+                let (name, named) = nearest_named(color);
+                let same = if first_alike(named) == first_alike(color) { "=" } else { "~" };
+                let (r, g, b) = rgb(color);
+                let mut spans = vec![
+                    Span::raw(" "),
+                    Span::raw(" ".repeat(SWATCH_WIDTH)).bg(terminal(color)),
+                    Span::raw(format!("  {}  0x{:02X}  {:<24} {} {:<10}  #{:02X}{:02X}{:02X}", literal(color), color, hsl(color), same, name, r, g, b)),
+                ];
+                let alike = first_alike(color);
+                if alike != color {
+                    spans.push(Span::raw(format!("  looks like {}", literal(alike))).fg(SCHEME.gray[0]));
+                }
+
+                let line = Line::from(spans).fg(SCHEME.white[1]);
+                match i == self.selected {
+                    true => line.bg(SCHEME.gray[0]).fg(SCHEME.black[0]),
+                    false => line,
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), self.list_area);
+    }
+
+    fn render_color(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::new().borders(Borders::ALL).title(" color ").fg(SCHEME.gray[0]);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let Some(color) = self.color() else { return };
+
+        let [swatch_area, info_area] = Layout::vertical([
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ]).areas(inner);
+        let ink = if is_light(color) { SCHEME.black[0] } else { SCHEME.white[1] };
+        let swatch = Paragraph::new(format!("\n\n{}", literal(color)))
+            .alignment(Alignment::Center)
+            .style(Style::new().bg(terminal(color)).fg(ink));
+        frame.render_widget(swatch, swatch_area);
+
+        let (r, g, b) = rgb(color);
+        let (name, named) = nearest_named(color);
+        let named = match first_alike(named) == first_alike(color) {
+            true => format!(" color::{}", name),
+            false => format!(" closest to color::{}", name),
+        };
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(format!(" {}", hsl(color))).fg(SCHEME.white[1]),
+            Line::from(named).fg(SCHEME.white[1]),
+            Line::from(""),
+            Line::from(format!(" hue {} ({})", hue(color), HUE_NAMES[hue(color) as usize].to_lowercase())),
+            Line::from(format!(" saturation {}", saturation(color))),
+            Line::from(format!(" luminance {}", luminance(color))),
+            Line::from(format!(" byte 0x{:02X}, {}", color, color)),
+            Line::from(format!(" shows as #{:02X}{:02X}{:02X}", r, g, b)),
+        ];
+
+        let alike: Vec<String> = (0..=u8::MAX)
+            .filter(|&other| other != color && first_alike(other) == first_alike(color))
+            .map(literal)
+            .collect();
+        if first_alike(color) == TRANSPARENT {
+            lines.push(Line::from(""));
+            lines.push(Line::from(" sprites can't use it: it's").fg(SCHEME.orange[2]));
+            lines.push(Line::from(" transparent in images").fg(SCHEME.orange[2]));
+        }
+        if !alike.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(" looks the same as").fg(SCHEME.gray[2]));
+            lines.extend(alike.into_iter().map(|l| Line::from(format!("   {}", l)).fg(SCHEME.gray[2])));
+        }
+        frame.render_widget(Paragraph::new(lines).fg(SCHEME.gray[2]), info_area);
+    }
+}
+
+/// How many different colors there are
+fn distinct() -> usize {
+    (0..=u8::MAX).filter(|&color| first_alike(color) == color).count()
+}
+
+/// The ways `color` can be searched for: its literal with and without the
+/// underscores, its byte, its hue and its parts, the SDK color it's closest
+/// to, and how it shows
+fn search_text(color: u8) -> String {
+    let (r, g, b) = rgb(color);
+    let literal = literal(color);
+    format!(
+        "{} {} 0x{:02x} {} h{} s{} l{} {} {} #{:02x}{:02x}{:02x}",
+        literal,
+        literal.replace('_', ""),
+        color,
+        color,
+        hue(color),
+        saturation(color),
+        luminance(color),
+        hsl(color),
+        nearest_named(color).0,
+        r,
+        g,
+        b,
+    ).to_lowercase()
+}
+
+impl Component for PaletteBrowser {
+    fn update(&mut self, events: Vec<Event>) {
+        for event in events {
+            match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => self.key(key),
+                Event::Mouse(mouse) => self.mouse(mouse),
+                _ => {}
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [header_area, body_area] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ]).areas(area);
+
+        let header = Block::new()
+            .bg(SCHEME.true_dark_color(SCHEME.black[3]))
+            .borders(Borders::TOP)
+            .title(" Gametank GO! | ☆•° . * . ﾟCOLORS  ﾟ. * . °•☆ ")
+            .title_alignment(Alignment::Center)
+            .italic()
+            .fg(SCHEME.orange[3]);
+        let looking = if self.alike { "every byte" } else { "one byte per color" };
+        let info = Paragraph::new(vec![
+            Line::from(format!(" search: {}▏  {}", self.query, looking)).fg(SCHEME.white[1]).not_italic(),
+            Line::from(format!(" {}", self.status)).fg(SCHEME.gray[2]),
+            Line::from(" type to search  backspace/^U erase  esc clear, then leave  arrows/pgup/pgdn pick").fg(SCHEME.gray[0]),
+            Line::from(" enter copy the literal  ^E copy hsl(..)  tab list look-alikes too").fg(SCHEME.gray[0]),
+        ]).block(header);
+        frame.render_widget(info, header_area);
+        frame.render_widget(Block::new().bg(SCHEME.true_dark_color(SCHEME.black[0])), body_area);
+
+        let [list_area, color_area] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Length(34),
+        ]).areas(body_area);
+        self.render_list(frame, list_area);
+        self.render_color(frame, color_area);
+    }
+}
@@ -0,0 +1,107 @@
+//! The GameTank's colors
+//!
+//! A color byte is `0bHHH_SS_LLL`: hue, saturation and luminance. Color 0 is
+//! black, which the blitter skips, so sprites use it for transparency.
+//! Some bytes look the same as others, so there are fewer colors than bytes.
+
+pub mod browser;
+
+use gametank_sdk::colors::nearest_color;
+use gte_core::color_map::COLOR_MAP;
+use image::Rgba;
+use ratatui::style::Color;
+
+pub const HUES: u8 = 8;
+pub const SATURATIONS: u8 = 4;
+pub const LUMINANCES: u8 = 8;
+
+/// The color the blitter skips
+pub const TRANSPARENT: u8 = 0;
+
+/// The hues, as the SDK's `gametank::color::hue` names them
+pub const HUE_NAMES: [&str; HUES as usize] = ["YELLOW", "ORANGE", "RED", "MAGENTA", "VIOLET", "BLUE", "CYAN", "GREEN"];
+
+/// The colors the SDK's `gametank::color` names
+pub const NAMED: [(&str, u8); 13] = [
+    ("BLACK", color(0, 0, 0)),
+    ("DARK_GRAY", color(0, 0, 2)),
+    ("GRAY", color(0, 0, 4)),
+    ("LIGHT_GRAY", color(0, 0, 6)),
+    ("WHITE", color(0, 0, 7)),
+    ("YELLOW", color(0, 3, 4)),
+    ("ORANGE", color(1, 3, 4)),
+    ("RED", color(2, 3, 4)),
+    ("MAGENTA", color(3, 3, 4)),
+    ("VIOLET", color(4, 3, 4)),
+    ("BLUE", color(5, 3, 4)),
+    ("CYAN", color(6, 3, 4)),
+    ("GREEN", color(7, 3, 4)),
+];
+
+pub fn hue(color: u8) -> u8 {
+    color >> 5
+}
+
+pub fn saturation(color: u8) -> u8 {
+    (color >> 3) & 0b11
+}
+
+pub fn luminance(color: u8) -> u8 {
+    color & 0b111
+}
+
+pub const fn color(hue: u8, saturation: u8, luminance: u8) -> u8 {
+    (hue % HUES) << 5 | (saturation % SATURATIONS) << 3 | (luminance % LUMINANCES)
+}
+
+/// How the emulator shows `color`
+pub fn rgb(color: u8) -> (u8, u8, u8) {
+    let (r, g, b, _) = COLOR_MAP[color as usize];
+    (r, g, b)
+}
+
+/// `color` for a truecolor terminal
+pub fn terminal(color: u8) -> Color {
+    let (r, g, b) = rgb(color);
+    Color::Rgb(r, g, b)
+}
+
+/// Whether text on `color` should be dark to be readable
+pub fn is_light(color: u8) -> bool {
+    let (r, g, b) = rgb(color);
+    r as u32 * 299 + g as u32 * 587 + b as u32 * 114 > 128_000
+}
+
+fn distance((r, g, b): (u8, u8, u8), (cr, cg, cb): (u8, u8, u8)) -> i32 {
+    let (dr, dg, db) = (r as i32 - cr as i32, g as i32 - cg as i32, b as i32 - cb as i32);
+    dr * dr + dg * dg + db * db
+}
+
+/// The color closest to an RGB one, the way gtrom maps images
+pub fn nearest(r: u8, g: u8, b: u8) -> u8 {
+    nearest_color(Rgba([r, g, b, 0xFF]))
+}
+
+/// The first color that looks the same as `color`, which is what gtrom
+/// turns `color` into when it's in an image
+pub fn first_alike(color: u8) -> u8 {
+    let (r, g, b) = rgb(color);
+    nearest(r, g, b)
+}
+
+/// The name of the SDK color that looks most like `color`
+pub fn nearest_named(color: u8) -> (&'static str, u8) {
+    NAMED.into_iter()
+        .min_by_key(|&(_, named)| distance(rgb(color), rgb(named)))
+        .unwrap()
+}
+
+/// `color` as it's written in Rust: `0b010_11_100`
+pub fn literal(color: u8) -> String {
+    format!("0b{:03b}_{:02b}_{:03b}", hue(color), saturation(color), luminance(color))
+}
+
+/// `color` as the SDK builds it: `hsl(hue::RED, 3, 4)`
+pub fn hsl(color: u8) -> String {
+    format!("hsl(hue::{}, {}, {})", HUE_NAMES[hue(color) as usize], saturation(color), luminance(color))
+}
@@ -0,0 +1,478 @@
+//! Drawing sprites in the GameTank's colors
+//!
+//! The editor works on a [`Page`]: the 16x16 tile under the cursor, big
+//! enough to draw in, next to the whole page at a pixel a half-cell, and the
+//! colors laid out by hue, saturation and luminance to pick from.
+
+mod page;
+
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{Receiver, Sender};
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}, layout::{Alignment, Constraint, Layout, Position, Rect}, style::{Color, Style, Stylize}, text::Line, widgets::{Block, Borders, Paragraph}, Frame};
+
+use crate::{document::DocumentFile, helpers::{follow, SCHEME}, main_menu::MainMenu, palette::{self, LUMINANCES, SATURATIONS, TRANSPARENT}, ui::quickmenu::{qi, QuickMenu}, Component, GlobalEvent};
+
+use page::{Page, SIZE, TILE};
+
+pub const EXTENSION: &str = "png";
+
+/// Colors across a row of the picker: two saturations' worth of luminances
+const PICKER_COLUMNS: u8 = 2 * LUMINANCES;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SpriteCmd {
+    New,
+    Browse,
+    Save,
+    SaveAs,
+    Quit,
+}
+
+pub struct SpriteEditor {
+    tx_main: Sender<GlobalEvent>,
+    tx: Sender<SpriteCmd>,
+    rx: Receiver<SpriteCmd>,
+
+    page: Page,
+    file: DocumentFile<Page, SpriteCmd>,
+
+    cursor: (usize, usize),
+    color: u8,
+    status: String,
+    menu: Option<QuickMenu>,
+
+    /// Where the tile, the page and the picker were last drawn, for the mouse
+    tile_area: Rect,
+    page_area: Rect,
+    picker_area: Rect,
+    /// The page's top left pixel on screen
+    scroll: (usize, usize),
+    /// The page as it was when the mouse went down in the tile
+    stroke: Option<Page>,
+}
+
+impl SpriteEditor {
+    pub fn init(tx_main: Sender<GlobalEvent>) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        Self {
+            tx_main,
+            tx,
+            rx,
+            page: Page::default(),
+            file: DocumentFile::new(EXTENSION),
+            cursor: (0, 0),
+            color: palette::color(0, 0, LUMINANCES - 1),
+            status: String::new(),
+            menu: None,
+            tile_area: Rect::default(),
+            page_area: Rect::default(),
+            picker_area: Rect::default(),
+            scroll: (0, 0),
+            stroke: None,
+        }
+    }
+
+    /// [`Tracker::open`](crate::tracker::Tracker::open) for sprites
+    pub fn open(tx_main: Sender<GlobalEvent>, path: PathBuf) -> Self {
+        let mut editor = Self::init(tx_main);
+        if !path.exists() {
+            editor.status = "New sprite".to_string();
+            editor.file.path = Some(path);
+            return editor;
+        }
+        editor.load(path);
+        editor
+    }
+
+    fn menu(&self) -> QuickMenu {
+        let tx = |cmd: SpriteCmd| {
+            let tx = self.tx.clone();
+            move || { let _ = tx.send(cmd); }
+        };
+
+        QuickMenu::init(" Sprite ".to_string(), vec![
+            qi("_New", true, tx(SpriteCmd::New)),
+            qi("_Open...", true, tx(SpriteCmd::Browse)),
+            qi("_Save", true, tx(SpriteCmd::Save)),
+            qi("Save _As...", true, tx(SpriteCmd::SaveAs)),
+            qi("_Quit", true, tx(SpriteCmd::Quit)),
+        ])
+    }
+
+    fn replace(&mut self, page: Page, path: Option<PathBuf>) {
+        self.page = page;
+        self.file.replace(path);
+        self.cursor = (0, 0);
+        self.scroll = (0, 0);
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        match Page::load(&path) {
+            Ok(page) => {
+                self.status = format!("Opened {} ({}x{})", path.display(), page.width, page.height);
+                self.replace(page, Some(path));
+            }
+            Err(e) => self.status = format!("{e:#}"),
+        }
+    }
+
+    fn save(&mut self, path: Option<PathBuf>) {
+        let picked = path.or_else(|| {
+            let mut dialog = rfd::FileDialog::new()
+                .add_filter("PNG image", &[EXTENSION])
+                .set_file_name(self.file.name());
+            if let Some(dir) = self.file.path.as_ref().and_then(|p| p.parent()) {
+                dialog = dialog.set_directory(dir);
+            }
+            dialog.save_file()
+        });
+        let Some(mut path) = picked else {
+            self.status = "Not saved. Pick a file, or start gtgo with one: gtgo sprite.png".to_string();
+            return;
+        };
+        if path.extension().is_none() {
+            path.set_extension(EXTENSION);
+        }
+
+        self.status = match self.page.save(&path) {
+            Ok(()) => {
+                self.file.modified = false;
+                format!("Saved {}", path.display())
+            }
+            Err(e) => format!("{e:#}"),
+        };
+        self.file.path = Some(path);
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.saturating_add_signed(dx).min(self.page.width - 1),
+            y.saturating_add_signed(dy).min(self.page.height - 1),
+        );
+    }
+
+    /// Step the color's hue, saturation or luminance, wrapping around
+    fn step_color(&mut self, hue: i8, saturation: i8, luminance: i8) {
+        let step = |value: u8, delta: i8| value.wrapping_add_signed(delta);
+        let color = self.color;
+        self.color = palette::color(
+            step(palette::hue(color), hue),
+            step(palette::saturation(color), saturation),
+            step(palette::luminance(color), luminance),
+        );
+    }
+
+    fn paint(&mut self, color: u8) {
+        let (x, y) = self.cursor;
+        self.file.edit(&mut self.page, |page| page.set(x, y, color));
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        let far = |step: isize| if key.modifiers.contains(KeyModifiers::SHIFT) { step * TILE as isize } else { step };
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('o'), KeyModifiers::CONTROL) => self.menu = Some(self.menu()),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => self.save(self.file.path.clone()),
+            (KeyCode::Char('z'), KeyModifiers::CONTROL) => self.file.undo(&mut self.page, &mut self.status),
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => self.file.redo(&mut self.page, &mut self.status),
+            (KeyCode::Left, _) => self.move_cursor(far(-1), 0),
+            (KeyCode::Right, _) => self.move_cursor(far(1), 0),
+            (KeyCode::Up, _) => self.move_cursor(0, far(-1)),
+            (KeyCode::Down, _) => self.move_cursor(0, far(1)),
+            (KeyCode::Char(' ') | KeyCode::Enter, KeyModifiers::NONE) => self.paint(self.color),
+            (KeyCode::Char('x') | KeyCode::Delete | KeyCode::Backspace, KeyModifiers::NONE) => self.paint(TRANSPARENT),
+            (KeyCode::Char('f'), KeyModifiers::NONE) => {
+                let ((x, y), color) = (self.cursor, self.color);
+                self.file.edit(&mut self.page, |page| page.fill(x, y, color));
+            }
+            (KeyCode::Char('i'), KeyModifiers::NONE) => self.color = self.page.get(self.cursor.0, self.cursor.1),
+            // shifted keys come with or without SHIFT, depending on the terminal
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => match c {
+                '[' => self.step_color(0, 0, -1),
+                ']' => self.step_color(0, 0, 1),
+                '{' => self.step_color(-1, 0, 0),
+                '}' => self.step_color(1, 0, 0),
+                '-' => self.step_color(0, -1, 0),
+                '=' | '+' => self.step_color(0, 1, 0),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// The pixel under a cell of the tile view, which shows a pixel as two
+    /// cells side by side
+    fn tile_pixel(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        if !self.tile_area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let (left, top) = tile_origin(self.cursor);
+        let x = left + (column - self.tile_area.x) as usize / 2;
+        let y = top + (row - self.tile_area.y) as usize;
+        self.page.contains(x, y).then_some((x, y))
+    }
+
+    /// The top pixel under a cell of the page view
+    fn page_pixel(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        if !self.page_area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let x = self.scroll.0 + (column - self.page_area.x) as usize;
+        let y = self.scroll.1 + (row - self.page_area.y) as usize * 2;
+        self.page.contains(x, y).then_some((x, y))
+    }
+
+    fn picker_color(&self, column: u16, row: u16) -> Option<u8> {
+        if !self.picker_area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let column = ((column - self.picker_area.x) / 2) as u8;
+        let row = (row - self.picker_area.y) as u8;
+        (column < PICKER_COLUMNS).then(|| picker_cell(row, column))
+    }
+
+    /// Draw in the tile with the left button, and pick up a color with the
+    /// right; a stroke is undone as one edit. A click on the page moves the
+    /// cursor there, and one on the picker picks a color.
+    fn mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some((x, y)) = self.tile_pixel(column, row) {
+                    self.stroke = Some(self.page.clone());
+                    self.page.set(x, y, self.color);
+                    self.cursor = (x, y);
+                } else if let Some(pixel) = self.page_pixel(column, row) {
+                    self.cursor = pixel;
+                } else if let Some(color) = self.picker_color(column, row) {
+                    self.color = color;
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.stroke.is_some() => {
+                if let Some((x, y)) = self.tile_pixel(column, row) {
+                    self.page.set(x, y, self.color);
+                    self.cursor = (x, y);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(before) = self.stroke.take().filter(|before| *before != self.page) {
+                    self.file.remember(before);
+                }
+            }
+            MouseEventKind::Down(MouseButton::Right) => {
+                if let Some((x, y)) = self.tile_pixel(column, row) {
+                    self.color = self.page.get(x, y);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep the cursor on screen in a page view `width` by `height` pixels
+    fn scroll_to_cursor(&mut self, width: usize, height: usize) {
+        self.scroll.0 = follow(self.scroll.0, self.cursor.0, width);
+        // whole cells, so each keeps the same two pixels
+        self.scroll.1 = follow(self.scroll.1, self.cursor.1, height) & !1;
+    }
+
+    fn render_tile(&mut self, frame: &mut Frame, area: Rect) {
+        let (left, top) = tile_origin(self.cursor);
+        let title = format!(" tile {},{}  pixel {},{} ", left / TILE, top / TILE, self.cursor.0, self.cursor.1);
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.tile_area = block.inner(area);
+        frame.render_widget(block, area);
+
+        let buf = frame.buffer_mut();
+        for row in 0..(TILE as u16).min(self.tile_area.height) {
+            for column in 0..(TILE as u16).min(self.tile_area.width / 2) {
+                let (x, y) = (left + column as usize, top + row as usize);
+                if !self.page.contains(x, y) {
+                    continue;
+                }
+                let color = self.page.get(x, y);
+                let bg = pixel_color(color, x, y);
+                let mark = if (x, y) == self.cursor { "[]" } else { "  " };
+                let fg = if palette::is_light(color) { SCHEME.black[0] } else { SCHEME.white[1] };
+                let at = (self.tile_area.x + column * 2, self.tile_area.y + row);
+                buf.set_string(at.0, at.1, mark, Style::new().fg(fg).bg(bg));
+            }
+        }
+    }
+
+    fn render_picker(&mut self, frame: &mut Frame, area: Rect) {
+        let (r, g, b) = palette::rgb(self.color);
+        let title = match self.color {
+            TRANSPARENT => " transparent ".to_string(),
+            // the same black as color 0, which is how gtrom reads it back
+            color if palette::nearest(r, g, b) == TRANSPARENT => format!(" {} saves transparent ", palette::literal(color)),
+            color => format!(" {} = {} ", palette::literal(color), color),
+        };
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.picker_area = block.inner(area);
+        frame.render_widget(block, area);
+
+        let rows = (palette::HUES * SATURATIONS / 2) as u16;
+        let buf = frame.buffer_mut();
+        for row in 0..rows.min(self.picker_area.height) {
+            for column in 0..(PICKER_COLUMNS as u16).min(self.picker_area.width / 2) {
+                let color = picker_cell(row as u8, column as u8);
+                let mark = if color == self.color { "[]" } else { "  " };
+                let fg = if palette::is_light(color) { SCHEME.black[0] } else { SCHEME.white[1] };
+                let at = (self.picker_area.x + column * 2, self.picker_area.y + row);
+                buf.set_string(at.0, at.1, mark, Style::new().fg(fg).bg(palette::terminal(color)));
+            }
+        }
+    }
+
+    fn render_page(&mut self, frame: &mut Frame, area: Rect) {
+        let title = format!(" page {}x{} ", self.page.width, self.page.height);
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.page_area = block.inner(area);
+        frame.render_widget(block, area);
+        self.scroll_to_cursor(self.page_area.width as usize, self.page_area.height as usize * 2);
+
+        let buf = frame.buffer_mut();
+        let cursor = SCHEME.orange[2];
+        for row in 0..self.page_area.height {
+            for column in 0..self.page_area.width {
+                let x = self.scroll.0 + column as usize;
+                let y = self.scroll.1 + row as usize * 2;
+                if !self.page.contains(x, y) {
+                    continue;
+                }
+                let half = |y: usize| match self.page.contains(x, y) {
+                    true if (x, y) == self.cursor => cursor,
+                    true => pixel_color(self.page.get(x, y), x, y),
+                    false => Color::Reset,
+                };
+                let cell = &mut buf[(self.page_area.x + column, self.page_area.y + row)];
+                cell.set_symbol("▀").set_fg(half(y)).set_bg(half(y + 1));
+            }
+        }
+    }
+}
+
+/// Top left pixel of the tile `pixel` is in
+fn tile_origin((x, y): (usize, usize)) -> (usize, usize) {
+    (x / TILE * TILE, y / TILE * TILE)
+}
+
+/// The picker's rows go through the hues, two saturations to a row
+fn picker_cell(row: u8, column: u8) -> u8 {
+    let saturations_per_row = PICKER_COLUMNS / LUMINANCES;
+    palette::color(
+        row / (SATURATIONS / saturations_per_row),
+        row % (SATURATIONS / saturations_per_row) * saturations_per_row + column / LUMINANCES,
+        column % LUMINANCES,
+    )
+}
+
+/// How a pixel shows, with transparency as a checkerboard
+fn pixel_color(color: u8, x: usize, y: usize) -> Color {
+    match color {
+        TRANSPARENT if (x ^ y) & 1 == 0 => SCHEME.black[2],
+        TRANSPARENT => SCHEME.black[3],
+        color => palette::terminal(color),
+    }
+}
+
+impl Component for SpriteEditor {
+    fn update(&mut self, events: Vec<Event>) {
+        if let Some(menu) = &mut self.menu {
+            menu.update(events);
+            if !menu.is_active() {
+                self.menu = None;
+            }
+        } else {
+            for event in events {
+                match event {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => self.key(key),
+                    Event::Mouse(mouse) => self.mouse(mouse),
+                    _ => {}
+                }
+            }
+        }
+
+        let cmds: Vec<_> = self.rx.try_iter().collect();
+        for cmd in cmds {
+            self.menu = None;
+            match cmd {
+                SpriteCmd::New => {
+                    if !self.file.must_confirm(cmd, &mut self.status) {
+                        self.replace(Page::default(), None);
+                        self.status = "New sprite".to_string();
+                    }
+                }
+                SpriteCmd::Browse => {
+                    if self.file.must_confirm(cmd, &mut self.status) {
+                        continue;
+                    }
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("PNG image", &[EXTENSION])
+                        .pick_file();
+                    if let Some(path) = picked {
+                        self.load(path);
+                    }
+                }
+                SpriteCmd::Save => self.save(self.file.path.clone()),
+                SpriteCmd::SaveAs => self.save(None),
+                SpriteCmd::Quit => {
+                    if !self.file.must_confirm(cmd, &mut self.status) {
+                        let menu = MainMenu::init(self.tx_main.clone());
+                        let _ = self.tx_main.send(GlobalEvent::ChangeInterface(Box::new(menu)));
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [header_area, body_area] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ]).areas(area);
+
+        let header = Block::new()
+            .bg(SCHEME.true_dark_color(SCHEME.black[3]))
+            .borders(Borders::TOP)
+            .title(" Gametank GO! | ☆•° . * . ﾟSPRITES  ﾟ. * . °•☆ ")
+            .title_alignment(Alignment::Center)
+            .italic()
+            .fg(SCHEME.orange[3]);
+        let modified = if self.file.modified { " *" } else { "" };
+        let info = Paragraph::new(vec![
+            Line::from(format!(" {}{}", self.file.name(), modified)).fg(SCHEME.white[1]).not_italic(),
+            Line::from(format!(" {}", self.status)).fg(SCHEME.gray[2]),
+            Line::from(" ^O sprite  ^S save  ^Z ^Y undo/redo  arrows move  shift: by a tile  space paint  x erase  f fill  i pick up").fg(SCHEME.gray[0]),
+            Line::from(" [ ] luminance  { } hue  - = saturation  drag in the tile to draw, right click to pick up").fg(SCHEME.gray[0]),
+        ]).block(header);
+        frame.render_widget(info, header_area);
+        frame.render_widget(Block::new().bg(SCHEME.true_dark_color(SCHEME.black[0])), body_area);
+
+        // a pixel is two cells wide in the tile and picker, and a tile's
+        // worth of rows is as many as the picker needs
+        let side = TILE as u16 * 2 + 2;
+        let [left, page_area] = Layout::horizontal([
+            Constraint::Length(side),
+            Constraint::Length(SIZE as u16 + 2),
+        ]).areas(body_area);
+        let [tile_area, picker_area] = Layout::vertical([
+            Constraint::Length(TILE as u16 + 2),
+            Constraint::Length(TILE as u16 + 2),
+        ]).areas(left);
+
+        self.render_tile(frame, tile_area);
+        self.render_picker(frame, picker_area);
+        self.render_page(frame, page_area);
+
+        if let Some(menu) = &mut self.menu {
+            menu.render(frame, area);
+        }
+    }
+}
+
+/// Whether `path` is something the sprite editor opens
+pub fn is_sprite(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(EXTENSION))
+}
@@ -0,0 +1,77 @@
+//! A page of sprite pixels, and the PNGs it's kept in
+//!
+//! A page is at most a 128x128 quadrant of sprite RAM, the unit `gtrom slice`
+//! packs tiles into. It saves as a PNG with color 0 transparent and the rest
+//! as the emulator shows them, so gtrom maps each pixel back to the same
+//! color, whether it's listed in `assets.toml` or sliced into tiles.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use gametank_sdk::colors::to_colors;
+use image::{Rgba, RgbaImage};
+
+use crate::{helpers::flood, palette::{self, TRANSPARENT}};
+
+/// Width and height of a full page
+pub const SIZE: usize = 128;
+/// Width and height of a tile, as `gtrom slice sheet.png 16x16` cuts them
+pub const TILE: usize = 16;
+
+#[derive(Clone, PartialEq)]
+pub struct Page {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self { width: SIZE, height: SIZE, pixels: vec![TRANSPARENT; SIZE * SIZE] }
+    }
+}
+
+impl Page {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: u8) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Paint the area of `(x, y)`'s color that touches it, edge to edge
+    pub fn fill(&mut self, x: usize, y: usize, color: u8) {
+        flood(&mut self.pixels, self.width, self.height, x, y, color);
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let img = image::open(path)
+            .with_context(|| format!("couldn't open {}", path.display()))?
+            .to_rgba8();
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        if width > SIZE || height > SIZE {
+            bail!("{} is {}x{}, bigger than a {}x{} page", path.display(), width, height, SIZE, SIZE);
+        }
+
+        let pixels = to_colors(&img);
+        Ok(Self { width, height, pixels })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let img = RgbaImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            match self.get(x as usize, y as usize) {
+                TRANSPARENT => Rgba([0, 0, 0, 0]),
+                color => {
+                    let (r, g, b) = palette::rgb(color);
+                    Rgba([r, g, b, 0xFF])
+                }
+            }
+        });
+        img.save(path).with_context(|| format!("couldn't save {}", path.display()))
+    }
+}
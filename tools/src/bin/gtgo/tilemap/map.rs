@@ -0,0 +1,241 @@
+//! Maps, kept as Tiled .tmj files
+//!
+//! A map is a layer of Tiled's tile ids, where 0 is an empty cell and n is
+//! the sheet's tile n - 1, and a collision layer, where any tile is solid.
+//! That's what gtrom's `[[tiled]]` assets read, so a saved map goes straight
+//! into `assets.toml`, and Tiled opens it too. Anything else in a map made in
+//! Tiled, like other layers and objects, is saved back as it was.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{helpers::flood, tilemap::sheet::Sheet};
+
+pub const EXTENSION: &str = "tmj";
+/// The layer gtrom makes collision bits from, unless `assets.toml` says otherwise
+pub const COLLISION_LAYER: &str = "collision";
+/// What a new map's tile layer is called
+const TILE_LAYER: &str = "tiles";
+
+/// Tiled keeps flip and rotation flags in the top four bits of a tile id
+const FLAG_BITS: u64 = 0xF000_0000;
+
+/// Biggest a map gets, in tiles, either way
+pub const MAX_SIZE: usize = 1024;
+
+#[derive(Clone, PartialEq)]
+pub struct Map {
+    pub width: usize,
+    pub height: usize,
+    tiles: Vec<u8>,
+    collision: Vec<bool>,
+}
+
+impl Map {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, tiles: vec![0; width * height], collision: vec![false; width * height] }
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> u8 {
+        self.tiles[y * self.width + x]
+    }
+
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: u8) {
+        self.tiles[y * self.width + x] = tile;
+    }
+
+    pub fn solid(&self, x: usize, y: usize) -> bool {
+        self.collision[y * self.width + x]
+    }
+
+    pub fn set_solid(&mut self, x: usize, y: usize, solid: bool) {
+        self.collision[y * self.width + x] = solid;
+    }
+
+    /// Put `tile` in the cells with `(x, y)`'s tile that touch it, edge to edge
+    pub fn fill_tiles(&mut self, x: usize, y: usize, tile: u8) {
+        flood(&mut self.tiles, self.width, self.height, x, y, tile);
+    }
+
+    /// Make the cells that touch `(x, y)` and are as solid as it `solid`
+    pub fn fill_solid(&mut self, x: usize, y: usize, solid: bool) {
+        flood(&mut self.collision, self.width, self.height, x, y, solid);
+    }
+
+    /// Grow or shrink from the bottom right, keeping what's left in place
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let mut resized = Self::new(width, height);
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                resized.set_tile(x, y, self.tile(x, y));
+                resized.set_solid(x, y, self.solid(x, y));
+            }
+        }
+        *self = resized;
+    }
+}
+
+/// The .tmj a map came from, or one for a new map
+pub struct Document {
+    json: Value,
+    /// The layer the map's tiles are in
+    tile_layer: String,
+    pub tile_width: usize,
+    pub tile_height: usize,
+}
+
+impl Document {
+    pub fn new(tile_width: usize, tile_height: usize) -> Self {
+        let json = json!({
+            "type": "map",
+            "version": "1.10",
+            "orientation": "orthogonal",
+            "renderorder": "right-down",
+            "infinite": false,
+            "compressionlevel": -1,
+            "nextlayerid": 1,
+            "nextobjectid": 1,
+            "layers": [],
+            "tilesets": [],
+        });
+        Self { json, tile_layer: TILE_LAYER.to_string(), tile_width, tile_height }
+    }
+
+    /// The map in a .tmj, and the sheet its tileset uses, if it's an image
+    pub fn load(path: &Path) -> Result<(Self, Map, Option<std::path::PathBuf>)> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        let json: Value = serde_json::from_str(&text).with_context(|| format!("couldn't parse {}", path.display()))?;
+        if json["infinite"].as_bool() == Some(true) {
+            bail!("{}: infinite maps aren't supported; turn off Map > Map Properties > Infinite in Tiled", path.display());
+        }
+        if json["orientation"].as_str().is_some_and(|o| o != "orthogonal") {
+            bail!("{}: only orthogonal maps are supported", path.display());
+        }
+
+        let size = |key: &str, max: usize| match json[key].as_u64() {
+            Some(n) if (1..=max as u64).contains(&n) => Ok(n as usize),
+            _ => Err(anyhow!("{}: {} should be 1-{}", path.display(), key, max)),
+        };
+        let (width, height) = (size("width", MAX_SIZE)?, size("height", MAX_SIZE)?);
+        let (tile_width, tile_height) = (size("tilewidth", u8::MAX as usize)?, size("tileheight", u8::MAX as usize)?);
+
+        let tile_layers: Vec<&Value> = json["layers"].as_array().into_iter().flatten()
+            .filter(|layer| layer["type"] == "tilelayer")
+            .collect();
+        let is_collision = |layer: &&&Value| layer["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(COLLISION_LAYER));
+        let tiles = tile_layers.iter().find(|layer| !is_collision(layer));
+        let collision = tile_layers.iter().find(is_collision);
+
+        let mut map = Map::new(width, height);
+        if let Some(layer) = tiles {
+            map.tiles = layer_data(path, layer, width * height)?;
+        }
+        if let Some(layer) = collision {
+            map.collision = layer_data(path, layer, width * height)?.into_iter().map(|tile| tile != 0).collect();
+        }
+
+        // an external tileset (`"source": "x.tsx"`) has its image in another file
+        let sheet = json["tilesets"][0]["image"].as_str()
+            .map(|image| path.parent().unwrap_or(Path::new(".")).join(image));
+        let tile_layer = tiles.and_then(|layer| layer["name"].as_str()).unwrap_or(TILE_LAYER).to_string();
+        Ok((Self { json, tile_layer, tile_width, tile_height }, map, sheet))
+    }
+
+    /// Write `map` into the document and save it to `path`, with `sheet` as
+    /// its tileset if there is one
+    pub fn save(&mut self, path: &Path, map: &Map, sheet: Option<&Sheet>) -> Result<()> {
+        self.json["width"] = json!(map.width);
+        self.json["height"] = json!(map.height);
+        self.json["tilewidth"] = json!(self.tile_width);
+        self.json["tileheight"] = json!(self.tile_height);
+
+        let collision: Vec<u8> = map.collision.iter().map(|&solid| solid as u8).collect();
+        let tile_layer = self.tile_layer.clone();
+        self.set_layer(&tile_layer, map, &map.tiles, 1.0);
+        // see-through, so the tiles show under it in Tiled
+        self.set_layer(COLLISION_LAYER, map, &collision, 0.5);
+
+        if let Some(sheet) = sheet {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            let image = sheet.path.strip_prefix(dir).unwrap_or(&sheet.path);
+            let name = sheet.path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+            self.json["tilesets"] = json!([{
+                "firstgid": 1,
+                "name": name,
+                "image": image.to_string_lossy().replace('\\', "/"),
+                "imagewidth": sheet.width,
+                "imageheight": sheet.height,
+                "tilewidth": sheet.tile_width,
+                "tileheight": sheet.tile_height,
+                "columns": sheet.columns,
+                "tilecount": sheet.tiles,
+                "margin": 0,
+                "spacing": 0,
+            }]);
+        }
+
+        // compact, as pretty-printing puts every tile on a line of its own
+        let text = serde_json::to_string(&self.json)?;
+        std::fs::write(path, text).with_context(|| format!("couldn't write {}", path.display()))
+    }
+
+    /// Put `data` in the tile layer called `name`, adding it if there isn't one
+    fn set_layer(&mut self, name: &str, map: &Map, data: &[u8], opacity: f32) {
+        if !self.json["layers"].is_array() {
+            self.json["layers"] = json!([]);
+        }
+        let layers = self.json["layers"].as_array_mut().unwrap();
+        let found = layers.iter().position(|layer| {
+            layer["type"] == "tilelayer" && layer["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name))
+        });
+        let index = match found {
+            Some(index) => index,
+            None => {
+                let id = self.json["nextlayerid"].as_u64().unwrap_or(1);
+                self.json["nextlayerid"] = json!(id + 1);
+                let layers = self.json["layers"].as_array_mut().unwrap();
+                layers.push(json!({
+                    "id": id,
+                    "name": name,
+                    "type": "tilelayer",
+                    "x": 0,
+                    "y": 0,
+                    "opacity": opacity,
+                    "visible": true,
+                }));
+                layers.len() - 1
+            }
+        };
+
+        let layer = &mut self.json["layers"][index];
+        layer["width"] = json!(map.width);
+        layer["height"] = json!(map.height);
+        layer["data"] = json!(data);
+        if let Some(layer) = layer.as_object_mut() {
+            // the data's written out plainly
+            layer.remove("encoding");
+            layer.remove("compression");
+        }
+    }
+}
+
+fn layer_data(path: &Path, layer: &Value, cells: usize) -> Result<Vec<u8>> {
+    let name = layer["name"].as_str().unwrap_or("");
+    if layer["encoding"].as_str().is_some_and(|e| e != "csv") {
+        bail!("{}: layer {:?} is compressed; set Map > Map Properties > Tile Layer Format to CSV in Tiled", path.display(), name);
+    }
+    let data = layer["data"].as_array()
+        .ok_or_else(|| anyhow!("{}: layer {:?} has no tile data", path.display(), name))?;
+    if data.len() != cells {
+        bail!("{}: layer {:?} has {} tiles, not one for each of the map's {}", path.display(), name, data.len(), cells);
+    }
+    data.iter()
+        .map(|gid| {
+            let id = gid.as_u64().map(|g| g & !FLAG_BITS);
+            id.and_then(|id| u8::try_from(id).ok())
+                .ok_or_else(|| anyhow!("{}: layer {:?} has tile {}, but tiles have to fit in a byte", path.display(), name, gid))
+        })
+        .collect()
+}
@@ -0,0 +1,636 @@
+//! Laying out tile maps and their collision
+//!
+//! Tiles come from a [`Sheet`], and go on a [`Map`] that's saved as a Tiled
+//! .tmj, which gtrom reads as a `[[tiled]]` asset with its collision layer.
+//! A cell shows its tile shrunk to 4x4 pixels, with half-blocks.
+
+mod map;
+mod sheet;
+
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{Receiver, Sender};
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}, layout::{Alignment, Constraint, Layout, Position, Rect}, style::{Color, Style, Stylize}, text::Line, widgets::{Block, Borders, Paragraph}, Frame};
+
+use crate::{document::DocumentFile, helpers::{follow, SCHEME}, main_menu::MainMenu, palette::{self, TRANSPARENT}, ui::quickmenu::{qi, QuickMenu}, Component, GlobalEvent};
+
+use map::{Document, Map, COLLISION_LAYER, EXTENSION, MAX_SIZE};
+use sheet::Sheet;
+
+/// Terminal cells a map cell takes, across and down; each shows two pixels
+/// of the tile, one over the other
+const CELL_COLUMNS: u16 = 4;
+const CELL_ROWS: u16 = 2;
+/// Sheet tiles across the sheet panel
+const SHEET_COLUMNS: u16 = 8;
+
+/// How far shift moves the cursor, in tiles
+const FAR: isize = 8;
+/// Tile sizes to pick from, square
+const TILE_SIZES: [usize; 3] = [8, 16, 32];
+const NEW_SIZE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MapCmd {
+    New,
+    Browse,
+    Save,
+    SaveAs,
+    LoadSheet,
+    TileSize,
+    Resize(isize, isize),
+    Quit,
+}
+
+/// What painting changes
+#[derive(Clone, Copy, PartialEq)]
+enum Layer {
+    Tiles,
+    Collision,
+}
+
+pub struct TilemapEditor {
+    tx_main: Sender<GlobalEvent>,
+    tx: Sender<MapCmd>,
+    rx: Receiver<MapCmd>,
+
+    map: Map,
+    document: Document,
+    sheet: Option<Sheet>,
+    file: DocumentFile<Map, MapCmd>,
+
+    cursor: (usize, usize),
+    /// The tile id to paint, 1 for the sheet's first tile
+    tile: u8,
+    layer: Layer,
+    status: String,
+    menu: Option<QuickMenu>,
+
+    /// Where the map and sheet were last drawn, for the mouse
+    map_area: Rect,
+    sheet_area: Rect,
+    /// The top left cell of the map on screen, and the sheet's top row
+    scroll: (usize, usize),
+    sheet_scroll: usize,
+    /// The map as it was when the mouse went down on it
+    stroke: Option<Map>,
+}
+
+impl TilemapEditor {
+    pub fn init(tx_main: Sender<GlobalEvent>) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        Self {
+            tx_main,
+            tx,
+            rx,
+            map: Map::new(NEW_SIZE, NEW_SIZE),
+            document: Document::new(TILE_SIZES[1], TILE_SIZES[1]),
+            sheet: None,
+            file: DocumentFile::new(EXTENSION),
+            cursor: (0, 0),
+            tile: 1,
+            layer: Layer::Tiles,
+            status: "Load a sheet to paint its tiles: ^O, then Load sheet".to_string(),
+            menu: None,
+            map_area: Rect::default(),
+            sheet_area: Rect::default(),
+            scroll: (0, 0),
+            sheet_scroll: 0,
+            stroke: None,
+        }
+    }
+
+    /// [`Tracker::open`](crate::tracker::Tracker::open) for maps
+    pub fn open(tx_main: Sender<GlobalEvent>, path: PathBuf) -> Self {
+        let mut editor = Self::init(tx_main);
+        if !path.exists() {
+            editor.file.path = Some(path);
+            return editor;
+        }
+        editor.load(path);
+        editor
+    }
+
+    fn menu(&self) -> QuickMenu {
+        let tx = |cmd: MapCmd| {
+            let tx = self.tx.clone();
+            move || { let _ = tx.send(cmd); }
+        };
+
+        let size = self.document.tile_width;
+        QuickMenu::init(" Map ".to_string(), vec![
+            qi("_New", true, tx(MapCmd::New)),
+            qi("_Open...", true, tx(MapCmd::Browse)),
+            qi("_Save", true, tx(MapCmd::Save)),
+            qi("Save _As...", true, tx(MapCmd::SaveAs)),
+            qi("_Load sheet...", true, tx(MapCmd::LoadSheet)),
+            qi(format!("Tile si_ze ({}x{})", size, size), true, tx(MapCmd::TileSize)),
+            qi(format!("_Wider ({})", self.map.width), true, tx(MapCmd::Resize(1, 0))),
+            qi("Nar_rower", true, tx(MapCmd::Resize(-1, 0))),
+            qi(format!("_Taller ({})", self.map.height), true, tx(MapCmd::Resize(0, 1))),
+            qi("S_horter", true, tx(MapCmd::Resize(0, -1))),
+            qi("_Quit", true, tx(MapCmd::Quit)),
+        ])
+    }
+
+    fn replace(&mut self, map: Map, document: Document, path: Option<PathBuf>) {
+        self.map = map;
+        self.document = document;
+        self.file.replace(path);
+        self.cursor = (0, 0);
+        self.scroll = (0, 0);
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        let (document, map, sheet) = match Document::load(&path) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.status = format!("{e:#}");
+                return;
+            }
+        };
+        let mut status = format!("Opened {} ({}x{} tiles)", path.display(), map.width, map.height);
+        self.replace(map, document, Some(path));
+
+        let loaded = match sheet {
+            Some(sheet) => self.load_sheet(sheet),
+            None => {
+                self.sheet = None;
+                Ok("its tileset isn't an image, so load a sheet to see the tiles".to_string())
+            }
+        };
+        match loaded {
+            Ok(loaded) => status = format!("{}; {}", status, loaded),
+            Err(e) => status = format!("{}; {e:#}", status),
+        }
+        self.status = status;
+    }
+
+    /// Cut the tiles out of the image at `path`, at the map's tile size,
+    /// saying how many there are
+    fn load_sheet(&mut self, path: PathBuf) -> anyhow::Result<String> {
+        let (width, height) = (self.document.tile_width, self.document.tile_height);
+        let sheet = Sheet::load(&path, width, height)?;
+        let name = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        let mut status = format!("{} has {} {}x{} tiles", name, sheet.tiles, width, height);
+        if sheet.unused() > 0 {
+            status.push_str(&format!(", and {} more than a map can use", sheet.unused()));
+        }
+        self.tile = self.tile.min(sheet.tiles as u8).max(1);
+        self.sheet = Some(sheet);
+        Ok(status)
+    }
+
+    fn save(&mut self, path: Option<PathBuf>) {
+        let picked = path.or_else(|| {
+            let mut dialog = rfd::FileDialog::new()
+                .add_filter("Tiled map", &[EXTENSION])
+                .set_file_name(self.file.name());
+            if let Some(dir) = self.file.path.as_ref().and_then(|p| p.parent()) {
+                dialog = dialog.set_directory(dir);
+            }
+            dialog.save_file()
+        });
+        let Some(mut path) = picked else {
+            self.status = format!("Not saved. Pick a file, or start gtgo with one: gtgo level.{}", EXTENSION);
+            return;
+        };
+        if path.extension().is_none() {
+            path.set_extension(EXTENSION);
+        }
+
+        self.status = match self.document.save(&path, &self.map, self.sheet.as_ref()) {
+            Ok(()) => {
+                self.file.modified = false;
+                format!("Saved {}; list it in assets.toml under [[tiled]]", path.display())
+            }
+            Err(e) => format!("{e:#}"),
+        };
+        self.file.path = Some(path);
+    }
+
+    /// Move the cursor, keeping it on the map
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.saturating_add_signed(dx).min(self.map.width - 1),
+            y.saturating_add_signed(dy).min(self.map.height - 1),
+        );
+    }
+
+    /// Step the tile to paint through the sheet
+    fn step_tile(&mut self, delta: isize) {
+        let tiles = self.sheet.as_ref().map_or(u8::MAX as usize, |s| s.tiles) as isize;
+        self.tile = ((self.tile as isize - 1 + delta).rem_euclid(tiles) + 1) as u8;
+    }
+
+    /// Paint the cursor's cell, or clear it
+    fn paint(&mut self, (x, y): (usize, usize), clear: bool) {
+        let tile = if clear { 0 } else { self.tile };
+        match self.layer {
+            Layer::Tiles => self.map.set_tile(x, y, tile),
+            Layer::Collision => self.map.set_solid(x, y, !clear),
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        let far = |step: isize| if key.modifiers.contains(KeyModifiers::SHIFT) { step * FAR } else { step };
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('o'), KeyModifiers::CONTROL) => self.menu = Some(self.menu()),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => self.save(self.file.path.clone()),
+            // the cursor has to stay on what could be a smaller map
+            (KeyCode::Char('z'), KeyModifiers::CONTROL) => {
+                self.file.undo(&mut self.map, &mut self.status);
+                self.move_cursor(0, 0);
+            }
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => {
+                self.file.redo(&mut self.map, &mut self.status);
+                self.move_cursor(0, 0);
+            }
+            (KeyCode::Left, _) => self.move_cursor(far(-1), 0),
+            (KeyCode::Right, _) => self.move_cursor(far(1), 0),
+            (KeyCode::Up, _) => self.move_cursor(0, far(-1)),
+            (KeyCode::Down, _) => self.move_cursor(0, far(1)),
+            (KeyCode::Tab, _) => {
+                self.layer = match self.layer {
+                    Layer::Tiles => Layer::Collision,
+                    Layer::Collision => Layer::Tiles,
+                };
+            }
+            (KeyCode::Char(' ') | KeyCode::Enter, KeyModifiers::NONE) => {
+                let cursor = self.cursor;
+                self.edit_with(|editor| editor.paint(cursor, false));
+            }
+            (KeyCode::Char('x') | KeyCode::Delete | KeyCode::Backspace, KeyModifiers::NONE) => {
+                let cursor = self.cursor;
+                self.edit_with(|editor| editor.paint(cursor, true));
+            }
+            (KeyCode::Char('f'), KeyModifiers::NONE) => {
+                let ((x, y), tile, layer) = (self.cursor, self.tile, self.layer);
+                self.file.edit(&mut self.map, |map| match layer {
+                    Layer::Tiles => map.fill_tiles(x, y, tile),
+                    Layer::Collision => map.fill_solid(x, y, !map.solid(x, y)),
+                });
+            }
+            (KeyCode::Char('i'), KeyModifiers::NONE) => self.pick(self.cursor),
+            // shifted keys come with or without SHIFT, depending on the terminal
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => match c {
+                '[' => self.step_tile(-1),
+                ']' => self.step_tile(1),
+                '{' => self.step_tile(-(SHEET_COLUMNS as isize)),
+                '}' => self.step_tile(SHEET_COLUMNS as isize),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// [`DocumentFile::edit`], for changes that need the editor's state
+    fn edit_with(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.map.clone();
+        change(self);
+        if self.map != before {
+            self.file.remember(before);
+        }
+    }
+
+    /// Pick up the tile in a cell to paint with; an empty one picks nothing
+    fn pick(&mut self, (x, y): (usize, usize)) {
+        match self.map.tile(x, y) {
+            0 => {}
+            tile => self.tile = tile,
+        }
+    }
+
+    fn map_cell(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        if !self.map_area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let x = self.scroll.0 + ((column - self.map_area.x) / CELL_COLUMNS) as usize;
+        let y = self.scroll.1 + ((row - self.map_area.y) / CELL_ROWS) as usize;
+        (x < self.map.width && y < self.map.height).then_some((x, y))
+    }
+
+    fn sheet_tile(&self, column: u16, row: u16) -> Option<u8> {
+        let sheet = self.sheet.as_ref()?;
+        if !self.sheet_area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let column = ((column - self.sheet_area.x) / CELL_COLUMNS) as usize;
+        let row = self.sheet_scroll + ((row - self.sheet_area.y) / CELL_ROWS) as usize;
+        let tile = row * SHEET_COLUMNS as usize + column;
+        (column < SHEET_COLUMNS as usize && tile < sheet.tiles).then_some(tile as u8 + 1)
+    }
+
+    /// Paint with the left button and clear with the right, a stroke at a
+    /// time that's undone as one edit; a click on the sheet picks a tile
+    fn mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(button @ (MouseButton::Left | MouseButton::Right)) => {
+                if let Some(cell) = self.map_cell(column, row) {
+                    self.stroke = Some(self.map.clone());
+                    self.paint(cell, button == MouseButton::Right);
+                    self.cursor = cell;
+                } else if let Some(tile) = self.sheet_tile(column, row).filter(|_| button == MouseButton::Left) {
+                    self.tile = tile;
+                }
+            }
+            MouseEventKind::Drag(button @ (MouseButton::Left | MouseButton::Right)) if self.stroke.is_some() => {
+                if let Some(cell) = self.map_cell(column, row) {
+                    self.paint(cell, button == MouseButton::Right);
+                    self.cursor = cell;
+                }
+            }
+            MouseEventKind::Up(_) => {
+                if let Some(before) = self.stroke.take().filter(|before| *before != self.map) {
+                    self.file.remember(before);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Step the tile size, cutting the sheet up again at it; a sheet too
+    /// small for the new size keeps the old one
+    fn step_tile_size(&mut self) {
+        let old = (self.document.tile_width, self.document.tile_height);
+        let index = TILE_SIZES.iter().position(|&s| s == old.0).map_or(0, |i| (i + 1) % TILE_SIZES.len());
+        (self.document.tile_width, self.document.tile_height) = (TILE_SIZES[index], TILE_SIZES[index]);
+
+        let Some(path) = self.sheet.as_ref().map(|s| s.path.clone()) else {
+            self.file.modified = true;
+            return;
+        };
+        match self.load_sheet(path) {
+            Ok(status) => {
+                self.file.modified = true;
+                self.status = status;
+            }
+            Err(e) => {
+                (self.document.tile_width, self.document.tile_height) = old;
+                self.status = format!("{e:#}");
+            }
+        }
+    }
+
+    fn resize(&mut self, dx: isize, dy: isize) {
+        let size = |size: usize, delta: isize| size.saturating_add_signed(delta).clamp(1, MAX_SIZE);
+        let (width, height) = (size(self.map.width, dx), size(self.map.height, dy));
+        self.file.edit(&mut self.map, |map| map.resize(width, height));
+        self.move_cursor(0, 0);
+    }
+
+    /// The colors of the two pixels in a terminal cell of a map cell:
+    /// `column` across and `half` down, out of 4 each
+    fn tile_pixels(&self, tile: u8, column: usize, half: usize) -> [Option<u8>; 2] {
+        let sheet = self.sheet.as_ref().filter(|s| tile != 0 && (tile as usize) <= s.tiles);
+        let Some(sheet) = sheet else { return [None; 2] };
+        let samples = (CELL_COLUMNS as usize, CELL_ROWS as usize * 2);
+        let x = (2 * column + 1) * sheet.tile_width / (2 * samples.0);
+        [half * 2, half * 2 + 1].map(|row| {
+            let y = (2 * row + 1) * sheet.tile_height / (2 * samples.1);
+            Some(sheet.pixel(tile as usize - 1, x, y))
+        })
+    }
+
+    /// Draw `tile` in the cell at `area`'s top left, tinted toward `tint`
+    fn render_cell(&self, frame: &mut Frame, at: (u16, u16), tile: u8, tint: Option<Color>, area: Rect) {
+        let buf = frame.buffer_mut();
+        // without the sheet, a tile's just its id
+        if self.sheet.is_none() && tile != 0 {
+            let bg = tint.unwrap_or(SCHEME.gray[0]);
+            let text = format!("{:^width$}", format!("{:02X}", tile), width = CELL_COLUMNS as usize);
+            for row in 0..CELL_ROWS {
+                let label = if row == 0 { text.as_str() } else { "    " };
+                if area.contains(Position::new(at.0, at.1 + row)) {
+                    buf.set_stringn(at.0, at.1 + row, label, (area.right() - at.0) as usize, Style::new().fg(SCHEME.white[1]).bg(bg));
+                }
+            }
+            return;
+        }
+
+        for row in 0..CELL_ROWS {
+            for column in 0..CELL_COLUMNS {
+                let position = Position::new(at.0 + column, at.1 + row);
+                if !area.contains(position) {
+                    continue;
+                }
+                let [top, bottom] = self.tile_pixels(tile, column as usize, row as usize);
+                let show = |color: Option<u8>, y: u16| {
+                    let color = match color {
+                        Some(color) if color != TRANSPARENT => palette::terminal(color),
+                        // a checkerboard where there's nothing
+                        _ if (column ^ y) & 1 == 0 => SCHEME.black[2],
+                        _ => SCHEME.black[3],
+                    };
+                    tint.map_or(color, |tint| blend(color, tint))
+                };
+                buf[position].set_symbol("▀").set_fg(show(top, row * 2)).set_bg(show(bottom, row * 2 + 1));
+            }
+        }
+    }
+
+    /// Keep the cursor on screen in a map view `columns` by `rows` cells
+    fn scroll_to_cursor(&mut self, columns: usize, rows: usize) {
+        self.scroll.0 = follow(self.scroll.0, self.cursor.0, columns);
+        self.scroll.1 = follow(self.scroll.1, self.cursor.1, rows);
+    }
+
+    fn render_map(&mut self, frame: &mut Frame, area: Rect) {
+        let (x, y) = self.cursor;
+        let solid = if self.map.solid(x, y) { "  solid" } else { "" };
+        let title = format!(" {}x{}  cell {},{}  tile {:02X}{} ", self.map.width, self.map.height, x, y, self.map.tile(x, y), solid);
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.map_area = block.inner(area);
+        frame.render_widget(block, area);
+
+        let (columns, rows) = ((self.map_area.width / CELL_COLUMNS) as usize, (self.map_area.height / CELL_ROWS) as usize);
+        self.scroll_to_cursor(columns, rows);
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (x, y) = (self.scroll.0 + column, self.scroll.1 + row);
+                if x >= self.map.width || y >= self.map.height {
+                    continue;
+                }
+                let tint = match () {
+                    _ if (x, y) == self.cursor => Some(SCHEME.orange[2]),
+                    _ if self.layer == Layer::Collision && self.map.solid(x, y) => Some(SCHEME.red[2]),
+                    _ => None,
+                };
+                let at = (self.map_area.x + column as u16 * CELL_COLUMNS, self.map_area.y + row as u16 * CELL_ROWS);
+                self.render_cell(frame, at, self.map.tile(x, y), tint, self.map_area);
+            }
+        }
+    }
+
+    fn render_sheet(&mut self, frame: &mut Frame, area: Rect) {
+        let title = match &self.sheet {
+            Some(sheet) => format!(" tile {:02X} of {:02X} ", self.tile, sheet.tiles),
+            None => " no sheet ".to_string(),
+        };
+        let block = Block::new().borders(Borders::ALL).title(title).fg(SCHEME.gray[0]);
+        self.sheet_area = block.inner(area);
+        frame.render_widget(block, area);
+        let Some(sheet) = &self.sheet else { return };
+
+        // keep the tile to paint in view
+        let rows = (self.sheet_area.height / CELL_ROWS) as usize;
+        let selected_row = (self.tile as usize - 1) / SHEET_COLUMNS as usize;
+        if selected_row < self.sheet_scroll {
+            self.sheet_scroll = selected_row;
+        } else if rows > 0 && selected_row >= self.sheet_scroll + rows {
+            self.sheet_scroll = selected_row + 1 - rows;
+        }
+
+        for row in 0..=rows {
+            for column in 0..SHEET_COLUMNS as usize {
+                let tile = (self.sheet_scroll + row) * SHEET_COLUMNS as usize + column + 1;
+                if tile > sheet.tiles {
+                    break;
+                }
+                let tint = (tile == self.tile as usize).then_some(SCHEME.orange[2]);
+                let at = (self.sheet_area.x + column as u16 * CELL_COLUMNS, self.sheet_area.y + row as u16 * CELL_ROWS);
+                self.render_cell(frame, at, tile as u8, tint, self.sheet_area);
+            }
+        }
+    }
+}
+
+/// Halfway from `color` to `tint`
+fn blend(color: Color, tint: Color) -> Color {
+    match (color, tint) {
+        (Color::Rgb(r, g, b), Color::Rgb(tr, tg, tb)) => {
+            let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+            Color::Rgb(mix(r, tr), mix(g, tg), mix(b, tb))
+        }
+        _ => tint,
+    }
+}
+
+impl Component for TilemapEditor {
+    fn update(&mut self, events: Vec<Event>) {
+        if let Some(menu) = &mut self.menu {
+            menu.update(events);
+            if !menu.is_active() {
+                self.menu = None;
+            }
+        } else {
+            for event in events {
+                match event {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => self.key(key),
+                    Event::Mouse(mouse) => self.mouse(mouse),
+                    _ => {}
+                }
+            }
+        }
+
+        let cmds: Vec<_> = self.rx.try_iter().collect();
+        for cmd in cmds {
+            self.menu = None;
+            match cmd {
+                MapCmd::New => {
+                    if !self.file.must_confirm(cmd, &mut self.status) {
+                        let (width, height) = (self.document.tile_width, self.document.tile_height);
+                        self.replace(Map::new(NEW_SIZE, NEW_SIZE), Document::new(width, height), None);
+                        self.status = "New map".to_string();
+                    }
+                }
+                MapCmd::Browse => {
+                    if self.file.must_confirm(cmd, &mut self.status) {
+                        continue;
+                    }
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Tiled map", &[EXTENSION])
+                        .pick_file();
+                    if let Some(path) = picked {
+                        self.load(path);
+                    }
+                }
+                MapCmd::Save => self.save(self.file.path.clone()),
+                MapCmd::SaveAs => self.save(None),
+                MapCmd::LoadSheet => {
+                    let mut dialog = rfd::FileDialog::new().add_filter("PNG image", &["png"]);
+                    if let Some(dir) = self.file.path.as_ref().and_then(|p| p.parent()) {
+                        dialog = dialog.set_directory(dir);
+                    }
+                    if let Some(path) = dialog.pick_file() {
+                        self.status = match self.load_sheet(path) {
+                            Ok(status) => {
+                                // the map's tileset changes when it's saved
+                                self.file.modified = true;
+                                status
+                            }
+                            Err(e) => format!("{e:#}"),
+                        };
+                    }
+                }
+                MapCmd::TileSize => {
+                    self.step_tile_size();
+                    // keep the selection on the item pressed, so it can be pressed again
+                    self.menu = Some(self.menu().with_selection(5));
+                }
+                MapCmd::Resize(dx, dy) => {
+                    self.resize(dx, dy);
+                    let selected = match (dx, dy) {
+                        (1, _) => 6,
+                        (-1, _) => 7,
+                        (_, 1) => 8,
+                        _ => 9,
+                    };
+                    self.menu = Some(self.menu().with_selection(selected));
+                }
+                MapCmd::Quit => {
+                    if !self.file.must_confirm(cmd, &mut self.status) {
+                        let menu = MainMenu::init(self.tx_main.clone());
+                        let _ = self.tx_main.send(GlobalEvent::ChangeInterface(Box::new(menu)));
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let [header_area, body_area] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ]).areas(area);
+
+        let header = Block::new()
+            .bg(SCHEME.true_dark_color(SCHEME.black[3]))
+            .borders(Borders::TOP)
+            .title(" Gametank GO! | ☆•° . * . ﾟTILEMAPS  ﾟ. * . °•☆ ")
+            .title_alignment(Alignment::Center)
+            .italic()
+            .fg(SCHEME.orange[3]);
+        let modified = if self.file.modified { " *" } else { "" };
+        let painting = match self.layer {
+            Layer::Tiles => "painting tiles",
+            Layer::Collision => "painting collision",
+        };
+        let info = Paragraph::new(vec![
+            Line::from(format!(" {}{}  {}", self.file.name(), modified, painting)).fg(SCHEME.white[1]).not_italic(),
+            Line::from(format!(" {}", self.status)).fg(SCHEME.gray[2]),
+            Line::from(" ^O map  ^S save  ^Z ^Y undo/redo  arrows move  shift: by 8  space paint  x clear  f fill  i pick up").fg(SCHEME.gray[0]),
+            Line::from(format!(" tab tiles/{}  [ ] tile  {{ }} a sheet row  drag to paint, right button to clear", COLLISION_LAYER)).fg(SCHEME.gray[0]),
+        ]).block(header);
+        frame.render_widget(info, header_area);
+        frame.render_widget(Block::new().bg(SCHEME.true_dark_color(SCHEME.black[0])), body_area);
+
+        let [sheet_area, map_area] = Layout::horizontal([
+            Constraint::Length(SHEET_COLUMNS * CELL_COLUMNS + 2),
+            Constraint::Fill(1),
+        ]).areas(body_area);
+        self.render_sheet(frame, sheet_area);
+        self.render_map(frame, map_area);
+
+        if let Some(menu) = &mut self.menu {
+            menu.render(frame, area);
+        }
+    }
+}
+
+/// Whether `path` is something the tilemap editor opens
+pub fn is_map(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(EXTENSION))
+}
@@ -0,0 +1,62 @@
+//! The sheet a map's tiles come from
+//!
+//! A PNG cut into equal tiles, left to right and then top to bottom, the way
+//! Tiled and `gtrom slice` number them. Its pixels are mapped onto the
+//! GameTank's colors as gtrom maps them, so the map looks as it will in game.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use gametank_sdk::colors::to_colors;
+
+/// Tile ids are a byte, and 0 is an empty cell
+pub const MAX_TILES: usize = u8::MAX as usize;
+
+pub struct Sheet {
+    pub path: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    pub columns: usize,
+    /// How many tiles there are, up to [`MAX_TILES`]
+    pub tiles: usize,
+    pixels: Vec<u8>,
+}
+
+impl Sheet {
+    pub fn load(path: &Path, tile_width: usize, tile_height: usize) -> Result<Self> {
+        let img = image::open(path)
+            .with_context(|| format!("couldn't open {}", path.display()))?
+            .to_rgba8();
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let (columns, rows) = (width / tile_width, height / tile_height);
+        if columns == 0 || rows == 0 {
+            bail!("{} is {}x{}, smaller than a {}x{} tile", path.display(), width, height, tile_width, tile_height);
+        }
+
+        let pixels = to_colors(&img);
+        Ok(Self {
+            path: path.to_path_buf(),
+            width,
+            height,
+            tile_width,
+            tile_height,
+            columns,
+            tiles: (columns * rows).min(MAX_TILES),
+            pixels,
+        })
+    }
+
+    /// How many tiles there are past the ones a map can use
+    pub fn unused(&self) -> usize {
+        (self.width / self.tile_width) * (self.height / self.tile_height) - self.tiles
+    }
+
+    /// The color of pixel `(x, y)` of tile `tile`, counting from 0
+    pub fn pixel(&self, tile: usize, x: usize, y: usize) -> u8 {
+        let (column, row) = (tile % self.columns, tile / self.columns);
+        let (x, y) = (column * self.tile_width + x, row * self.tile_height + y);
+        self.pixels[y * self.width + x]
+    }
+}
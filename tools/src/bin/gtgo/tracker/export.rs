@@ -0,0 +1,135 @@
+//! Exporting the module for a ROM
+//!
+//! A `.gtsong` is the whole module, as the sequence plays it, in the format
+//! the SDK's music player reads. Its wavetables go next to it in a `.gtwt`,
+//! laid out as they go in audio RAM. The Rust export writes both next to a
+//! `.rs` that includes them as statics, placed in a ROM bank the way gtrom
+//! places assets, so a game can `mod` it in and `music.play(&SONG)`.
+//!
+//! For sharing drafts, the preview renders the song to a WAV: see
+//! [`Render`](crate::tracker::preview::Render).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use gametank_sdk::rom_builder::{BANK_SIZE, FIXED_BANK};
+
+use gt_audio_abi::wavetable_8ch as abi;
+
+use crate::tracker::{song, wavetable::{self, TABLES}, TrackerData};
+
+pub const SONG_EXTENSION: &str = "gtsong";
+pub const WAVETABLES_EXTENSION: &str = "gtwt";
+pub const WAV_EXTENSION: &str = "wav";
+
+/// The module as song data
+pub fn song_bytes(data: &TrackerData) -> Result<Vec<u8>> {
+    let bytes = song::song(data, &data.sequences[..data.sequence_len], data.export_mutes)
+        .and_then(|song| song.to_bytes())
+        .map_err(|e| anyhow!(e))?;
+    // the player reads it straight out of ROM, so it can't span banks
+    if bytes.len() > BANK_SIZE {
+        bail!("the song is {} bytes, more than fits in a bank ({})", bytes.len(), BANK_SIZE);
+    }
+    Ok(bytes)
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes).with_context(|| format!("couldn't write {}", path.display()))
+}
+
+/// Write the module's song data to `path`, and its wavetables next to it;
+/// returns how big the song is
+pub fn write_song(data: &TrackerData, path: &Path) -> Result<usize> {
+    let bytes = song_bytes(data)?;
+    write(path, &bytes)?;
+    write(&path.with_extension(WAVETABLES_EXTENSION), &wavetable::aram_image(&data.tables))?;
+    Ok(bytes.len())
+}
+
+/// Write a Rust module to `path` with the song, as a static in `bank`, and
+/// the song data it includes next to it; returns the song data's path
+pub fn write_rust_module(data: &TrackerData, path: &Path, bank: u8) -> Result<PathBuf> {
+    if bank > FIXED_BANK {
+        bail!("bank {} is out of range (0-{})", bank, FIXED_BANK);
+    }
+    let song_path = path.with_extension(SONG_EXTENSION);
+    let len = write_song(data, &song_path)?;
+
+    let file = |path: PathBuf| path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+    let code = rust_module(
+        &static_name(path),
+        &file(song_path.clone()),
+        len,
+        &file(path.with_extension(WAVETABLES_EXTENSION)),
+        bank,
+    );
+    write(path, code.as_bytes())?;
+    Ok(song_path)
+}
+
+/// A file name as a static's name: `title-theme.rs` is `TITLE_THEME`
+fn static_name(path: &Path) -> String {
+    let stem = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned());
+    let name: String = stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    match name.chars().next() {
+        None => "SONG".to_string(),
+        Some(c) if c.is_ascii_digit() || name.chars().all(|c| c == '_') => format!("SONG_{}", name),
+        Some(_) => name,
+    }
+}
+
+fn rust_module(name: &str, song: &str, len: usize, wavetables: &str, bank: u8) -> String {
+    let section = match bank {
+        FIXED_BANK => String::new(),
+        bank => format!("#[unsafe(link_section = \".rodata.bank{bank}\")]\n"),
+    };
+    let (start, end) = (cpu_aram(wavetable::FIRST_SLOT), cpu_aram(wavetable::FIRST_SLOT + TABLES as u16));
+    let size = TABLES * wavetable::SIZE;
+
+    let mut code = String::from("//! Song data exported from gtgo. Export again rather than editing.\n\n");
+    if bank == FIXED_BANK {
+        code.push_str("/// In the fixed bank, so it can be played from anywhere\n");
+    } else {
+        code.push_str(&format!("/// In bank {bank}: switch to it around `music.update` while it plays\n"));
+    }
+    code.push_str(&format!("{section}pub static {name}: [u8; {len}] = *include_bytes!(\"{song}\");\n\n"));
+    code.push_str(&format!(
+        "/// The song's wavetables, to load before it plays:\n\
+         /// `console.audio.aram[0x{start:03X}..0x{end:03X}].copy_from_slice(&{name}_WAVETABLES)`\n\
+         {section}pub static {name}_WAVETABLES: [u8; {size}] = *include_bytes!(\"{wavetables}\");\n"
+    ));
+    code
+}
+
+/// Offset into `console.audio.aram` of wavetable slot `slot`
+fn cpu_aram(slot: u16) -> u16 {
+    abi::WAVETABLE_BASE + abi::WAVETABLE_SIZE * slot
+}
+
+/// `samples` as a mono, 16-bit WAV
+pub fn wav(rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // channels
+    wav.extend_from_slice(&rate.to_le_bytes());
+    wav.extend_from_slice(&(rate * 2).to_le_bytes()); // bytes a second
+    wav.extend_from_slice(&2u16.to_le_bytes()); // bytes a sample
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits a sample
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
@@ -0,0 +1,140 @@
+//! Effects as they're typed into and shown in the Fx and sequencer lanes
+//!
+//! Like classic trackers, an effect is a letter and two hex digits:
+//!
+//! | effect | does                                        | command              |
+//! |--------|---------------------------------------------|----------------------|
+//! | `Vxy`  | vibrato, depth x, speed y                   | `Vibrato(x, y)`      |
+//! | `Txy`  | tremolo, depth x, speed y                   | `Tremolo(x, y)`      |
+//! | `Wxx`  | switch to wavetable slot xx                 | `Wavetable(xx)`      |
+//! | `Pxx`  | set the phase to xx/256 of a cycle          | `Phase(xx << 8)`     |
+//! | `Uxy`  | slide pitch up y a frame, for x beats       | `SlidePitch(x, y)`   |
+//! | `Dxy`  | slide pitch down                            | `SlidePitch(x, -y)`  |
+//! | `Lxy`  | slide volume up (louder) y a frame, x beats | `SlideVol(x, y)`     |
+//! | `Qxy`  | slide volume down (quieter)                 | `SlideVol(x, -y)`    |
+//!
+//! A slide over 0 beats goes until it's stopped, and one by 0 stops it:
+//! `U00` is `StopPSlide`, `L00` `StopVSlide`.
+//!
+//! The sequencer lane takes `Txx`, a change of tempo to xx bpm, from 0F up.
+//! Its other commands show as `Jxx` (jump to pattern), `Bxx` (next pattern
+//! starts at row), `A00` (advance), `S00` (stop) and `Lxx` (load).
+
+use gametank_sdk::song::MIN_BPM;
+use gt_audio_abi::wavetable_8ch as abi;
+
+use crate::tracker::{ChannelCmd, SequencerCmd};
+
+/// Characters in an effect
+pub const LEN: usize = 3;
+
+/// Letters that start an effect
+pub const LETTERS: [char; 8] = ['v', 't', 'w', 'p', 'u', 'd', 'l', 'q'];
+/// And a sequencer command
+pub const SEQ_LETTERS: [char; 1] = ['t'];
+
+/// Whether `cmd` goes in the Fx lanes
+pub fn is_fx(cmd: &ChannelCmd) -> bool {
+    !matches!(cmd, ChannelCmd::Note(_) | ChannelCmd::Volume(_))
+}
+
+/// Whether `a` and `b` change the same thing, so one replaces the other
+pub fn same_effect(a: &ChannelCmd, b: &ChannelCmd) -> bool {
+    use ChannelCmd::*;
+    match (a, b) {
+        (SlidePitch(..) | StopPSlide, SlidePitch(..) | StopPSlide) => true,
+        (SlideVol(..) | StopVSlide, SlideVol(..) | StopVSlide) => true,
+        _ => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+/// A typed command's letter, in capitals, and its digits
+fn split(text: &str) -> Result<(char, u8, u8), String> {
+    let mut chars = text.chars();
+    let (Some(letter), Some(x), Some(y), None) = (chars.next(), chars.next(), chars.next(), chars.next()) else {
+        return Err(format!("an effect is a letter and two hex digits, not {}", text));
+    };
+    let letter = letter.to_ascii_uppercase();
+    let (Some(x), Some(y)) = (x.to_digit(16), y.to_digit(16)) else {
+        return Err(format!("{}{}{} needs two hex digits", letter, x, y));
+    };
+    Ok((letter, x as u8, y as u8))
+}
+
+/// The command for a typed effect, like `v38`
+pub fn parse(text: &str) -> Result<ChannelCmd, String> {
+    let (letter, x, y) = split(text)?;
+    let xx = x * 16 + y;
+
+    Ok(match letter {
+        'V' => ChannelCmd::Vibrato(x, y),
+        'T' => ChannelCmd::Tremolo(x, y),
+        'W' if (xx as usize) < abi::WAVETABLE_COUNT => ChannelCmd::Wavetable(xx as u16),
+        'W' => return Err(format!("there are only {} wavetable slots", abi::WAVETABLE_COUNT)),
+        'P' => ChannelCmd::Phase((xx as u16) << 8),
+        'U' | 'D' if y == 0 => ChannelCmd::StopPSlide,
+        'U' => ChannelCmd::SlidePitch(x, y as i16),
+        'D' => ChannelCmd::SlidePitch(x, -(y as i16)),
+        'L' | 'Q' if y == 0 => ChannelCmd::StopVSlide,
+        'L' => ChannelCmd::SlideVol(x, y as i16),
+        'Q' => ChannelCmd::SlideVol(x, -(y as i16)),
+        _ => return Err(format!("no effect {}", letter)),
+    })
+}
+
+/// The sequencer command for a typed one, like `t78` for 120 bpm
+pub fn parse_seq(text: &str) -> Result<SequencerCmd, String> {
+    let (letter, x, y) = split(text)?;
+    match (letter, x * 16 + y) {
+        ('T', bpm) if bpm >= MIN_BPM => Ok(SequencerCmd::Tempo(bpm)),
+        ('T', _) => Err(format!("tempos go from {:02X} ({} bpm) up", MIN_BPM, MIN_BPM)),
+        _ => Err(format!("no sequencer command {}", letter)),
+    }
+}
+
+/// Whether `a` and `b` change the same thing, so one replaces the other
+pub fn same_seq(a: &SequencerCmd, b: &SequencerCmd) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+/// A parameter digit, or `+` for one too big to show
+fn digit(n: impl Into<i32>) -> char {
+    u32::try_from(n.into()).ok().and_then(|n| char::from_digit(n, 16)).map_or('+', |c| c.to_ascii_uppercase())
+}
+
+/// Two digits for a byte
+fn byte(n: impl Into<i32>) -> String {
+    match u8::try_from(n.into()) {
+        Ok(n) => format!("{:02X}", n),
+        Err(_) => "++".to_string(),
+    }
+}
+
+/// How `cmd` shows in an Fx lane; the reverse of [`parse`]
+pub fn text(cmd: &ChannelCmd) -> String {
+    match *cmd {
+        ChannelCmd::Vibrato(x, y) => format!("V{}{}", digit(x), digit(y)),
+        ChannelCmd::Tremolo(x, y) => format!("T{}{}", digit(x), digit(y)),
+        ChannelCmd::Wavetable(slot) => format!("W{}", byte(slot)),
+        ChannelCmd::Phase(phase) => format!("P{:02X}", phase >> 8),
+        ChannelCmd::SlidePitch(x, y) if y < 0 => format!("D{}{}", digit(x), digit(-(y as i32))),
+        ChannelCmd::SlidePitch(x, y) => format!("U{}{}", digit(x), digit(y)),
+        ChannelCmd::StopPSlide => "U00".to_string(),
+        ChannelCmd::SlideVol(x, y) if y < 0 => format!("Q{}{}", digit(x), digit(-(y as i32))),
+        ChannelCmd::SlideVol(x, y) => format!("L{}{}", digit(x), digit(y)),
+        ChannelCmd::StopVSlide => "L00".to_string(),
+        ChannelCmd::Note(_) | ChannelCmd::Volume(_) => "???".to_string(),
+    }
+}
+
+/// How `cmd` shows in the sequencer lane; the reverse of [`parse_seq`]
+pub fn seq_text(cmd: &SequencerCmd) -> String {
+    match *cmd {
+        SequencerCmd::Tempo(bpm) => format!("T{:02X}", bpm),
+        SequencerCmd::Load(slot, _) => format!("L{:02X}", slot),
+        SequencerCmd::Pattern(pattern) => format!("J{:02X}", pattern),
+        SequencerCmd::Beat(row) => format!("B{:02X}", row),
+        SequencerCmd::Advance => "A00".to_string(),
+        SequencerCmd::Stop => "S00".to_string(),
+    }
+}
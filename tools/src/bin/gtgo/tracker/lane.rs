@@ -1,6 +1,6 @@
 
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LaneKind {
     Beat,
     Seq,
@@ -32,9 +32,9 @@ impl Lane {
 
     pub fn seq() -> Self {
         Self {
-            title: "SEQ".to_string(),
+            title: "SEQ ".to_string(),
             padding: (0, 0),
-            width: 3,
+            width: 4,
             kind: LaneKind::Seq,
             ch: None
         }
@@ -63,7 +63,7 @@ impl Lane {
     pub fn fx(ch: u8) -> Self {
         Self {
             title: ":↗↘ ".to_string(),
-            padding: (0, 1),
+            padding: (0, 0),
             width: 4,
             kind: LaneKind::Fx,
             ch: Some(ch as usize)
@@ -0,0 +1,48 @@
+//! Entering notes from a MIDI keyboard
+//!
+//! Note-ons from the connected port go straight to the pattern editor, as
+//! if played on its QWERTY piano, with their velocity if it's recorded.
+
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::Sender;
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::tracker::pattern_editor::PatternEvent;
+
+const CLIENT_NAME: &str = "gtgo";
+
+const NOTE_ON: u8 = 0x90;
+
+/// Names of the MIDI input ports there are now
+pub fn ports() -> Result<Vec<String>> {
+    let input = MidiInput::new(CLIENT_NAME).context("couldn't start MIDI input")?;
+    Ok(input.ports().iter().filter_map(|port| input.port_name(port).ok()).collect())
+}
+
+/// A connection to a MIDI input port, open until dropped
+pub struct MidiIn {
+    pub port: String,
+    _connection: MidiInputConnection<()>,
+}
+
+impl MidiIn {
+    /// Connect to the port named `port`, sending its notes to `tx`, with
+    /// their velocity if `velocity`
+    pub fn connect(port: &str, tx: Sender<PatternEvent>, velocity: bool) -> Result<Self> {
+        let input = MidiInput::new(CLIENT_NAME).context("couldn't start MIDI input")?;
+        let found = input.ports().into_iter()
+            .find(|p| input.port_name(p).is_ok_and(|name| name == port))
+            .ok_or_else(|| anyhow!("no MIDI input called {}", port))?;
+
+        let connection = input.connect(&found, "gtgo-in", move |_, message, _| {
+            // a note-on with no velocity is a note-off
+            if let [status, note, vel] = *message {
+                if status & 0xF0 == NOTE_ON && vel > 0 {
+                    let _ = tx.send(PatternEvent::MidiNote(note, velocity.then_some(vel)));
+                }
+            }
+        }, ()).map_err(|e| anyhow!("couldn't connect to {}: {}", port, e))?;
+
+        Ok(Self { port: port.to_string(), _connection: connection })
+    }
+}
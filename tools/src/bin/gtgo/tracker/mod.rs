@@ -1,11 +1,26 @@
 pub mod pattern_editor;
+pub mod export;
+mod fx;
 mod midi;
+mod midi_input;
 pub mod lane;
+pub mod module;
+mod player;
+pub mod preview;
+pub mod settings;
+pub mod song;
+pub mod undo;
+pub mod wavetable;
+pub mod wavetable_editor;
+
+use std::{cell::RefCell, path::{Path, PathBuf}, rc::Rc};
 
 use crossbeam_channel::{Receiver, Sender};
-use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers}, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::Stylize, widgets::{Block, Borders}};
+use gametank_sdk::{rom_builder::FIXED_BANK, song::{Song, MIN_BPM}};
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers}, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::Stylize, text::Line, widgets::{Block, Borders, Paragraph}};
+use serde::{Deserialize, Serialize};
 
-use crate::{helpers::SCHEME, main_menu::MainMenu, tracker::pattern_editor::PatternEditor, Component, GlobalEvent};
+use crate::{helpers::SCHEME, main_menu::MainMenu, tracker::{module::ModuleFile, pattern_editor::{PatternEditor, PatternEvent}, midi_input::MidiIn, preview::{Preview, Render}, settings::Settings, song::MAX_SWING_PERCENT, undo::History, wavetable::{Preset, Wavetable, TABLES}, wavetable_editor::{WaveEvent, WavetableEditor}, midi::MidiNote}, ui::quickmenu::{qi, QuickMenu}, Component, GlobalEvent};
 
 pub struct Handler {
     pub event: Event,
@@ -24,13 +39,43 @@ pub trait TSub: Component {
 //     EditSequence,
 // }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum TrackerCmd {
     Quit,
     FocusComponent(Option<usize>),
+    FileMenu,
+    New,
+    Open(PathBuf),
+    Browse,
+    Save,
+    SaveAs,
+    PlayPattern,
+    PlaySong,
+    ExportMenu,
+    ExportSong,
+    ExportRust,
+    ExportWav,
+    ExportBank(i16),
+    ExportMutes,
+    GrooveMenu,
+    Tempo(i16),
+    Swing(i16),
+    SettingsMenu,
+    MidiInput(Option<String>),
+    MidiVelocity,
+    Undo,
+    Redo,
+    NextView,
+    PresetMenu,
+    Audition(usize), // a wavetable
 }
 
-type Pattern = [[Beat; 64]; 9];
+pub const CHANNELS: usize = 8;
+/// Percent the groove menu steps swing by
+const SWING_STEP: i16 = 5;
+pub const PATTERN_ROWS: usize = 64;
+
+type Pattern = [[Beat; PATTERN_ROWS]; CHANNELS + 1];
 
 fn empty_pattern() -> Pattern {
     std::array::from_fn(|_| std::array::from_fn(|_| Beat::default()))
@@ -60,14 +105,22 @@ pub struct VoiceBeat {
     // idk: IndexMap<>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Beat {
+    #[serde(rename = "commands", default, skip_serializing_if = "Vec::is_empty")]
     cmd_list: Vec<ChannelCmd>,
+    #[serde(rename = "sequencer", default, skip_serializing_if = "Vec::is_empty")]
     sqc_list: Vec<SequencerCmd>
 }
 
+impl Beat {
+    pub fn is_empty(&self) -> bool {
+        self.cmd_list.is_empty() && self.sqc_list.is_empty()
+    }
+}
+
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SequencerCmd {
     Tempo(u8), // 0 - 256 in bpm. 60hz * 60s = 3600 / tempo = tick counter.
     Load(u8, u16), // load a wavetable from a pointer?
@@ -84,7 +137,7 @@ pub enum ChannelFx {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ChannelCmd {
     Tremolo(u8, u8), // volume
     Vibrato(u8, u8), // pitch
@@ -107,96 +160,660 @@ pub struct TrackerData {
     sequence: u8,
 
     sequences: [usize; 256], // a sequence is an array of pattern indices
+    sequence_len: usize, // how much of `sequences` is in use
     patterns: Vec<Pattern>,
+
+    tempo: u8, // starting bpm, until a Tempo command
+    swing: u8, // percent of a row every other row is late by
+    wavetables: [u8; CHANNELS], // wavetable slot each channel starts on
+    tables: [Wavetable; TABLES], // the module's wavetables, for slots from wavetable::FIRST_SLOT
+    bank: Option<u8>, // ROM bank the rust export puts the song in, None for the fixed bank
+    muted: [bool; CHANNELS],
+    soloed: [bool; CHANNELS],
+    export_mutes: bool, // leave what's muted out of exports too
+
+    history: History, // edits to undo and redo
+}
+
+impl Default for TrackerData {
+    fn default() -> Self {
+        Self {
+            beat: 0,
+            pattern: 0,
+            sequence: 0,
+            sequences: [0; 256],
+            sequence_len: 1,
+            patterns: vec![empty_pattern()],
+            tempo: 120,
+            swing: 0,
+            wavetables: [0; CHANNELS],
+            tables: wavetable::default_tables(),
+            bank: None,
+            muted: [false; CHANNELS],
+            soloed: [false; CHANNELS],
+            export_mutes: false,
+            history: History::default(),
+        }
+    }
+}
+
+impl TrackerData {
+    /// Whether channel `ch` plays: if any are soloed, only those, and
+    /// otherwise all that aren't muted
+    pub fn audible(&self, ch: usize) -> bool {
+        match self.soloed.contains(&true) {
+            true => self.soloed[ch],
+            false => !self.muted[ch],
+        }
+    }
 }
 
 pub struct Tracker {
     tx_main: Sender<GlobalEvent>,
-    #[allow(dead_code)]
     tr_tx: Sender<TrackerCmd>,
     tr_rx: Receiver<TrackerCmd>,
 
+    data: Rc<RefCell<TrackerData>>,
+    file: ModuleFile,
+    status: String,
+    file_menu: Option<QuickMenu>,
+    preview: Option<Preview>,
+    render: Option<Render>,
+    midi: Option<MidiIn>,
+    editor_tx: Sender<PatternEvent>,
+    wave_tx: Sender<WaveEvent>,
+
     selected_subcomponent: Option<usize>,
+    view: usize, // the subcomponent on screen
     subcomponents: Vec<Box<dyn TSub>>,
     handlers: Vec<Handler>,
+    global_handlers: Vec<Handler>,
 }
 
 pub fn tx_handler(tx: &Sender<TrackerCmd>, code: KeyCode, cmd: TrackerCmd) -> Handler {
+    key_handler(tx, KeyEvent::new(code, KeyModifiers::NONE), cmd)
+}
+
+pub fn ctrl_handler(tx: &Sender<TrackerCmd>, c: char, cmd: TrackerCmd) -> Handler {
+    key_handler(tx, KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL), cmd)
+}
+
+fn key_handler(tx: &Sender<TrackerCmd>, key: KeyEvent, cmd: TrackerCmd) -> Handler {
     let txx = tx.clone();
-    Handler { event: Event::Key(KeyEvent::new(code, KeyModifiers::NONE)), action: Box::new(move || {
-        let _ = txx.send(cmd);
+    Handler { event: Event::Key(key), action: Box::new(move || {
+        let _ = txx.send(cmd.clone());
     })}
 }
 
 impl Tracker {
     pub fn init(tx_main: Sender<GlobalEvent>) -> Self {
+        Self::with_data(tx_main, TrackerData::default(), None)
+    }
+
+    /// Open the module at `path`, or start a new one that saves there if
+    /// there's nothing there yet
+    pub fn open(tx_main: Sender<GlobalEvent>, path: PathBuf) -> Self {
+        if !path.exists() {
+            let mut tracker = Self::with_data(tx_main, TrackerData::default(), Some(path));
+            tracker.status = "New module".to_string();
+            return tracker;
+        }
+
+        match module::load(&path) {
+            Ok(data) => {
+                Settings::load().push_recent(&path);
+                Self::with_data(tx_main, data, Some(path))
+            }
+            Err(e) => {
+                let mut tracker = Self::init(tx_main);
+                tracker.status = format!("{e:#}");
+                tracker
+            }
+        }
+    }
+
+    fn with_data(tx_main: Sender<GlobalEvent>, data: TrackerData, path: Option<PathBuf>) -> Self {
         let (tr_tx, tr_rx) = crossbeam_channel::unbounded();
 
+        let file = ModuleFile::new(&data, path);
+        let data = Rc::new(RefCell::new(data));
+
+        let editor = PatternEditor::init(tr_tx.clone(), data.clone());
+        let editor_tx = editor.events();
+        let waves = WavetableEditor::init(tr_tx.clone(), data.clone());
+        let wave_tx = waves.events();
         let subcomponents: Vec<Box<dyn TSub>> = vec![
-            Box::new(PatternEditor::init(tr_tx.clone())),
+            Box::new(editor),
+            Box::new(waves),
         ];
 
         let handlers = vec![
             tx_handler(&tr_tx, KeyCode::Char('q'), TrackerCmd::Quit),
         ];
 
-        Tracker {
+        let global_handlers = vec![
+            ctrl_handler(&tr_tx, 'o', TrackerCmd::FileMenu),
+            ctrl_handler(&tr_tx, 's', TrackerCmd::Save),
+            ctrl_handler(&tr_tx, 'p', TrackerCmd::SettingsMenu),
+            ctrl_handler(&tr_tx, 'z', TrackerCmd::Undo),
+            ctrl_handler(&tr_tx, 'y', TrackerCmd::Redo),
+            tx_handler(&tr_tx, KeyCode::Tab, TrackerCmd::NextView),
+            tx_handler(&tr_tx, KeyCode::Char(' '), TrackerCmd::PlayPattern),
+            tx_handler(&tr_tx, KeyCode::F(5), TrackerCmd::PlaySong),
+        ];
+
+        let mut tracker = Tracker {
             tx_main,
             tr_tx,
             tr_rx,
+            data,
+            file,
+            status: String::new(),
+            file_menu: None,
+            preview: None,
+            render: None,
+            midi: None,
+            editor_tx,
+            wave_tx,
             selected_subcomponent: Some(0),
+            view: 0,
             subcomponents,
             handlers,
+            global_handlers,
+        };
+
+        // pick up the keyboard from last time, if it's plugged in
+        let settings = Settings::load();
+        if let Some(port) = settings.midi_input {
+            match MidiIn::connect(&port, tracker.editor_tx.clone(), settings.midi_velocity) {
+                Ok(midi) => tracker.midi = Some(midi),
+                Err(e) => tracker.status = format!("{e:#}"),
+            }
         }
+        tracker
+    }
+
+    fn file_menu(&self) -> QuickMenu {
+        let tx = |cmd: TrackerCmd| {
+            let tx = self.tr_tx.clone();
+            move || { let _ = tx.send(cmd.clone()); }
+        };
+
+        let mut items = vec![
+            qi("_New", true, tx(TrackerCmd::New)),
+            qi("_Open...", true, tx(TrackerCmd::Browse)),
+            qi("_Save", true, tx(TrackerCmd::Save)),
+            qi("Save _As...", true, tx(TrackerCmd::SaveAs)),
+            qi("_Export...", true, tx(TrackerCmd::ExportMenu)),
+            qi("_Tempo & swing...", true, tx(TrackerCmd::GrooveMenu)),
+        ];
+
+        for (i, path) in Settings::load().recent.into_iter().enumerate() {
+            let name = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+            let name: String = name.chars().take(16).collect();
+            items.push(qi(format!("_{} {}", i + 1, name), true, tx(TrackerCmd::Open(path))));
+        }
+
+        if let Some(autosave) = module::autosave_path().filter(|p| p.exists()) {
+            items.push(qi("Autosa_ved", true, tx(TrackerCmd::Open(autosave))));
+        }
+
+        QuickMenu::init(" Module ".to_string(), items)
+    }
+
+    fn export_menu(&self) -> QuickMenu {
+        let tx = |cmd: TrackerCmd| {
+            let tx = self.tr_tx.clone();
+            move || { let _ = tx.send(cmd.clone()); }
+        };
+
+        let mark = |on: bool| if on { "•" } else { " " };
+
+        let bank = match self.data.borrow().bank {
+            Some(bank) => bank.to_string(),
+            None => "fixed".to_string(),
+        };
+
+        QuickMenu::init(" Export ".to_string(), vec![
+            qi("_Song...", true, tx(TrackerCmd::ExportSong)),
+            qi("_Rust module...", true, tx(TrackerCmd::ExportRust)),
+            qi("_WAV...", true, tx(TrackerCmd::ExportWav)),
+            qi(format!("Bank _+ ({})", bank), true, tx(TrackerCmd::ExportBank(1))),
+            qi("Bank _-", true, tx(TrackerCmd::ExportBank(-1))),
+            qi(format!("{} _Mutes apply", mark(self.data.borrow().export_mutes)), true, tx(TrackerCmd::ExportMutes)),
+        ])
+    }
+
+    fn groove_menu(&self) -> QuickMenu {
+        let tx = |cmd: TrackerCmd| {
+            let tx = self.tr_tx.clone();
+            move || { let _ = tx.send(cmd.clone()); }
+        };
+
+        let data = self.data.borrow();
+        QuickMenu::init(" Tempo ".to_string(), vec![
+            qi(format!("_Faster ({} bpm)", data.tempo), true, tx(TrackerCmd::Tempo(1))),
+            qi("_Slower", true, tx(TrackerCmd::Tempo(-1))),
+            qi(format!("S_wing more ({}%)", data.swing), true, tx(TrackerCmd::Swing(SWING_STEP))),
+            qi("Swing _less", true, tx(TrackerCmd::Swing(-SWING_STEP))),
+        ])
+    }
+
+    fn settings_menu(&self) -> QuickMenu {
+        let tx = |cmd: TrackerCmd| {
+            let tx = self.tr_tx.clone();
+            move || { let _ = tx.send(cmd.clone()); }
+        };
+        let mark = |on: bool| if on { "•" } else { " " };
+
+        let settings = Settings::load();
+        let connected = self.midi.as_ref().map(|m| m.port.as_str());
+        let mut items = vec![
+            qi(format!("{} MIDI _off", mark(connected.is_none())), true, tx(TrackerCmd::MidiInput(None))),
+        ];
+
+        match midi_input::ports() {
+            Ok(ports) => for (i, port) in ports.into_iter().take(9).enumerate() {
+                let name: String = port.chars().take(14).collect();
+                let on = connected == Some(port.as_str());
+                items.push(qi(format!("{} _{} {}", mark(on), i + 1, name), true, tx(TrackerCmd::MidiInput(Some(port)))));
+            },
+            Err(_) => items.push(qi("  no MIDI _input", false, || {})),
+        }
+
+        items.push(qi(format!("{} Record _velocity", mark(settings.midi_velocity)), true, tx(TrackerCmd::MidiVelocity)));
+        QuickMenu::init(" Settings ".to_string(), items)
+    }
+
+    fn preset_menu(&self) -> QuickMenu {
+        let items = Preset::ALL.iter().enumerate().map(|(i, preset)| {
+            let (tx, preset) = (self.wave_tx.clone(), *preset);
+            qi(format!("_{} {}", i + 1, preset.name()), true, move || { let _ = tx.send(WaveEvent::Preset(preset)); })
+        }).collect();
+        QuickMenu::init(" Preset ".to_string(), items)
+    }
+
+    /// Take notes from the MIDI input `port`, or none, and remember it
+    fn connect_midi(&mut self, port: Option<String>) {
+        // one connection at a time; drop the old one before opening the new
+        self.midi = None;
+        let mut settings = Settings::load();
+
+        if let Some(port) = &port {
+            match MidiIn::connect(port, self.editor_tx.clone(), settings.midi_velocity) {
+                Ok(midi) => {
+                    self.status = format!("Playing notes in from {}", port);
+                    self.midi = Some(midi);
+                }
+                Err(e) => {
+                    self.status = format!("{e:#}");
+                    return;
+                }
+            }
+        }
+
+        settings.midi_input = port;
+        settings.save();
+    }
+
+    /// Step the export bank, wrapping through the fixed bank
+    fn step_bank(&mut self, delta: i16) {
+        let mut data = self.data.borrow_mut();
+        let banks = FIXED_BANK as i16 + 1;
+        let bank = (data.bank.unwrap_or(FIXED_BANK) as i16 + delta).rem_euclid(banks) as u8;
+        data.bank = (bank != FIXED_BANK).then_some(bank);
+    }
+
+    /// Ask where to export to, next to the module
+    fn pick_export_path(&self, extension: &str, filter: &str) -> Option<PathBuf> {
+        let name = Path::new(&self.file.name()).with_extension(extension);
+        let mut dialog = rfd::FileDialog::new()
+            .add_filter(filter, &[extension])
+            .set_file_name(name.to_string_lossy());
+        if let Some(dir) = self.file.path.as_ref().and_then(|p| p.parent()) {
+            dialog = dialog.set_directory(dir);
+        }
+        let mut path = dialog.save_file()?;
+        if path.extension().is_none() {
+            path.set_extension(extension);
+        }
+        Some(path)
+    }
+
+    fn export(&mut self, rust: bool) {
+        let (extension, filter) = match rust {
+            true => ("rs", "Rust module"),
+            false => (export::SONG_EXTENSION, "GameTank song"),
+        };
+        let Some(path) = self.pick_export_path(extension, filter) else { return };
+
+        let data = self.data.borrow();
+        let result = match rust {
+            true => export::write_rust_module(&data, &path, data.bank.unwrap_or(FIXED_BANK)).map(|song| {
+                let bank = data.bank.map_or("the fixed bank".to_string(), |b| format!("bank {}", b));
+                format!("Exported {} and {}, in {}", path.display(), song.display(), bank)
+            }),
+            false => export::write_song(&data, &path)
+                .map(|len| format!("Exported {} ({} bytes) and its wavetables", path.display(), len)),
+        };
+        drop(data);
+        self.status = result.unwrap_or_else(|e| format!("Can't export: {e:#}"));
+    }
+
+    /// Render the whole song, as it exports, to a WAV in the background
+    fn export_wav(&mut self) {
+        if let Some(render) = &self.render {
+            self.status = format!("Still rendering {}", render.path.display());
+            return;
+        }
+        let Some(path) = self.pick_export_path(export::WAV_EXTENSION, "WAV audio") else { return };
+
+        let data = self.data.borrow();
+        let song = song::song(&data, &data.sequences[..data.sequence_len], data.export_mutes)
+            .and_then(|song| song.to_bytes());
+        let wavetables = wavetable::aram_image(&data.tables);
+        drop(data);
+
+        match song.and_then(|song| Render::start(song, wavetables, path)) {
+            Ok(render) => {
+                self.status = format!("Rendering {}...", render.path.display());
+                self.render = Some(render);
+            }
+            Err(e) => self.status = format!("Can't render: {e}"),
+        }
+    }
+
+    /// Report on the render, and finish it once it's done
+    fn poll_render(&mut self) {
+        let Some(render) = self.render.take() else { return };
+        if !render.is_finished() {
+            let seconds = render.seconds();
+            self.status = format!("Rendering {}... {}:{:02}", render.path.display(), seconds / 60, seconds % 60);
+            self.render = Some(render);
+            return;
+        }
+
+        let path = render.path.clone();
+        self.status = match render.finish() {
+            Ok(seconds) => format!("Rendered {} ({}:{:02})", path.display(), seconds / 60, seconds % 60),
+            Err(e) => format!("Can't render: {e}"),
+        };
+    }
+
+    /// Swap in another module, autosaving this one first so nothing's lost
+    fn replace(&mut self, data: TrackerData, path: Option<PathBuf>) {
+        let autosaved = self.file.modified;
+        if let Err(e) = self.file.autosave(&self.data.borrow()) {
+            self.status = format!("{e:#}; keeping the open module");
+            return;
+        }
+
+        self.file = ModuleFile::new(&data, path);
+        *self.data.borrow_mut() = data;
+        self.status = if autosaved {
+            format!("Opened {} (the last module's changes are in the autosave)", self.file.name())
+        } else {
+            format!("Opened {}", self.file.name())
+        };
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        match module::load(&path) {
+            // the autosave opens as an untitled module, so saving won't write over it
+            Ok(data) if Some(&path) == module::autosave_path().as_ref() => self.replace(data, None),
+            Ok(data) => {
+                Settings::load().push_recent(&path);
+                self.replace(data, Some(path));
+            }
+            Err(e) => self.status = format!("{e:#}"),
+        }
+    }
+
+    fn save(&mut self, path: Option<PathBuf>) {
+        let Some(mut path) = path.or_else(|| self.pick_save_path()) else {
+            self.status = "Not saved. Pick a file, or start gtgo with one: gtgo song.gtm".to_string();
+            return;
+        };
+        if path.extension().is_none() {
+            path.set_extension(module::EXTENSION);
+        }
+
+        let result = self.file.save(&self.data.borrow(), path);
+        self.status = match result {
+            Ok(()) => format!("Saved {}", self.file.name()),
+            Err(e) => format!("{e:#}"),
+        };
+    }
+
+    /// The preview, opened the first time it's wanted
+    fn preview(&mut self) -> Option<&Preview> {
+        if self.preview.is_none() {
+            match Preview::new() {
+                Ok(preview) => self.preview = Some(preview),
+                Err(e) => {
+                    self.status = format!("Can't preview: {e}");
+                    return None;
+                }
+            }
+        }
+        self.preview.as_ref()
+    }
+
+    /// Play `song` with the module's wavetables loaded
+    fn play(&mut self, song: Result<Song, String>) {
+        let wavetables = wavetable::aram_image(&self.data.borrow().tables);
+        match song.and_then(|song| song.to_bytes()) {
+            Ok(bytes) => {
+                if let Some(preview) = self.preview() {
+                    preview.play(bytes, wavetables);
+                }
+            }
+            Err(e) => self.status = format!("Can't play this: {e}"),
+        }
+    }
+
+    /// Play the patterns in `order`, or stop if something's playing
+    fn toggle_preview(&mut self, order: Vec<usize>) {
+        if let Some(preview) = self.preview.as_ref().filter(|p| p.position().is_some()) {
+            preview.stop();
+            return;
+        }
+
+        let song = song::song(&self.data.borrow(), &order, true);
+        self.play(song);
+    }
+
+    fn pick_save_path(&self) -> Option<PathBuf> {
+        let name = match self.file.path {
+            Some(_) => self.file.name(),
+            None => format!("untitled.{}", module::EXTENSION),
+        };
+        rfd::FileDialog::new()
+            .add_filter("GameTank module", &[module::EXTENSION])
+            .set_file_name(name)
+            .save_file()
     }
 }
 
 impl Component for Tracker {
     fn update(&mut self, events: Vec<ratatui::crossterm::event::Event>) {
-        for e in &events {
-            let handlers = match self.selected_subcomponent {
-                Some(selected) => self.subcomponents[selected].active_handlers(),
-                None => &self.handlers,
-            };
+        if let Some(menu) = &mut self.file_menu {
+            menu.update(events);
+            if !menu.is_active() {
+                self.file_menu = None;
+            }
+        } else {
+            for e in &events {
+                let handlers = match self.selected_subcomponent {
+                    Some(selected) => self.subcomponents[selected].active_handlers(),
+                    None => &self.handlers,
+                };
+
+                for h in handlers {
+                    if h.event == *e {
+                        (h.action)()
+                    }
+                }
 
-            for h in handlers {
-                if h.event == *e {
-                    (h.action)()
+                for h in self.subcomponents.iter().flat_map(|c| c.global_handlers()).chain(&self.global_handlers) {
+                    if h.event == *e {
+                        (h.action)()
+                    }
                 }
             }
 
-            for h in self.subcomponents.iter().flat_map(|c| c.global_handlers()) {
-                if h.event == *e {
-                    (h.action)()
-                }
+            // only what's on screen sees the mouse and keys; the rest just
+            // catch up on their own events
+            for (i, component) in self.subcomponents.iter_mut().enumerate() {
+                component.update(if i == self.view { events.clone() } else { vec![] });
             }
         }
 
-        for component in &mut self.subcomponents {
-            component.update(events.clone());
-        }
-        
-        for cmd in self.tr_rx.try_iter() {
+        let cmds: Vec<_> = self.tr_rx.try_iter().collect();
+        for cmd in cmds {
             match cmd {
                 TrackerCmd::Quit => {
+                    if let Err(e) = self.file.autosave(&self.data.borrow()) {
+                        self.status = format!("{e:#}; not quitting");
+                        continue;
+                    }
                     let menu = MainMenu::init(self.tx_main.clone());
                     let _ = self.tx_main.send(GlobalEvent::ChangeInterface(Box::new(menu)));
                 },
                 TrackerCmd::FocusComponent(c) => {
                     self.selected_subcomponent = c;
                 }
+                TrackerCmd::FileMenu => self.file_menu = Some(self.file_menu()),
+                TrackerCmd::New => {
+                    self.file_menu = None;
+                    self.replace(TrackerData::default(), None);
+                }
+                TrackerCmd::Open(path) => {
+                    self.file_menu = None;
+                    self.load(path);
+                }
+                TrackerCmd::Browse => {
+                    self.file_menu = None;
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("GameTank module", &[module::EXTENSION])
+                        .pick_file();
+                    if let Some(path) = picked {
+                        self.load(path);
+                    }
+                }
+                TrackerCmd::Save => {
+                    self.file_menu = None;
+                    self.save(self.file.path.clone());
+                }
+                TrackerCmd::SaveAs => {
+                    self.file_menu = None;
+                    self.save(None);
+                }
+                TrackerCmd::ExportMenu => self.file_menu = Some(self.export_menu()),
+                TrackerCmd::ExportSong => {
+                    self.file_menu = None;
+                    self.export(false);
+                }
+                TrackerCmd::ExportRust => {
+                    self.file_menu = None;
+                    self.export(true);
+                }
+                TrackerCmd::ExportWav => {
+                    self.file_menu = None;
+                    self.export_wav();
+                }
+                TrackerCmd::ExportBank(delta) => {
+                    self.step_bank(delta);
+                    // keep the selection on the item pressed, so it can be pressed again
+                    let selected = if delta > 0 { 3 } else { 4 };
+                    self.file_menu = Some(self.export_menu().with_selection(selected));
+                }
+                TrackerCmd::ExportMutes => {
+                    let mut data = self.data.borrow_mut();
+                    data.export_mutes = !data.export_mutes;
+                    drop(data);
+                    self.file_menu = Some(self.export_menu().with_selection(usize::MAX));
+                }
+                TrackerCmd::GrooveMenu => self.file_menu = Some(self.groove_menu()),
+                TrackerCmd::Tempo(delta) | TrackerCmd::Swing(delta) => {
+                    let mut data = self.data.borrow_mut();
+                    let selected = match cmd {
+                        TrackerCmd::Tempo(_) => {
+                            data.tempo = (data.tempo as i16 + delta).clamp(MIN_BPM as i16, u8::MAX as i16) as u8;
+                            if delta > 0 { 0 } else { 1 }
+                        }
+                        _ => {
+                            data.swing = (data.swing as i16 + delta).clamp(0, MAX_SWING_PERCENT as i16) as u8;
+                            if delta > 0 { 2 } else { 3 }
+                        }
+                    };
+                    drop(data);
+                    // keep the selection on the item pressed, so it can be pressed again
+                    self.file_menu = Some(self.groove_menu().with_selection(selected));
+                }
+                TrackerCmd::SettingsMenu => self.file_menu = Some(self.settings_menu()),
+                TrackerCmd::MidiInput(port) => {
+                    self.file_menu = None;
+                    self.connect_midi(port);
+                }
+                TrackerCmd::MidiVelocity => {
+                    let mut settings = Settings::load();
+                    settings.midi_velocity = !settings.midi_velocity;
+                    settings.save();
+                    // reconnect, so the new setting takes
+                    let port = self.midi.as_ref().map(|m| m.port.clone());
+                    if port.is_some() {
+                        self.connect_midi(port);
+                    }
+                    // the toggle's the last item, keep it selected
+                    self.file_menu = Some(self.settings_menu().with_selection(usize::MAX));
+                }
+                TrackerCmd::Undo => {
+                    if !self.data.borrow_mut().undo() {
+                        self.status = "Nothing to undo".to_string();
+                    }
+                }
+                TrackerCmd::Redo => {
+                    if !self.data.borrow_mut().redo() {
+                        self.status = "Nothing to redo".to_string();
+                    }
+                }
+                TrackerCmd::NextView => {
+                    self.view = (self.view + 1) % self.subcomponents.len();
+                    self.selected_subcomponent = Some(self.view);
+                }
+                TrackerCmd::PresetMenu => self.file_menu = Some(self.preset_menu()),
+                TrackerCmd::Audition(table) => {
+                    self.play(Ok(song::audition(wavetable::FIRST_SLOT + table as u16, MidiNote::C4 as u8)));
+                }
+                TrackerCmd::PlayPattern => {
+                    let pattern = self.data.borrow().pattern as usize;
+                    self.toggle_preview(vec![pattern]);
+                }
+                TrackerCmd::PlaySong => {
+                    let order = {
+                        let data = self.data.borrow();
+                        data.sequences[..data.sequence_len].to_vec()
+                    };
+                    self.toggle_preview(order);
+                }
             }
         }
+
+        self.poll_render();
+        if let Err(e) = self.file.tick(&self.data.borrow()) {
+            self.status = format!("Autosave failed: {e:#}");
+        }
     }
 
-    fn render(&mut self, frame: &mut ratatui::Frame, _area: Rect) {
+    fn render(&mut self, frame: &mut ratatui::Frame, area: Rect) {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
                 Constraint::Length(8),
                 Constraint::Percentage(100),
             ])
-            .split(frame.area());
+            .split(area);
 
         let block1 = Block::new()
             .bg(SCHEME.true_dark_color(SCHEME.black[3]))
@@ -208,11 +825,26 @@ impl Component for Tracker {
 
         let blk = Block::new()
             .bg(SCHEME.true_dark_color(SCHEME.black[0]));
- 
-        frame.render_widget(block1.clone(), layout[0]);
+
+        let modified = if self.file.modified { " *" } else { "" };
+        let playing = match self.preview.as_ref().and_then(|p| p.position()) {
+            Some((order, row)) => format!("  ▶ {:02X}:{:02X}", order, row),
+            None => String::new(),
+        };
+        let info = Paragraph::new(vec![
+            Line::from(format!(" {}{}{}", self.file.name(), modified, playing)).fg(SCHEME.white[1]).not_italic(),
+            Line::from(format!(" {}", self.status)).fg(SCHEME.gray[2]),
+            Line::from(" ^O module  ^S save  ^P settings  ^Z ^Y undo/redo  tab patterns/waves  space play pattern  F5 play song").fg(SCHEME.gray[0]),
+        ]).block(block1);
+
+        frame.render_widget(info, layout[0]);
         frame.render_widget(blk.clone(), layout[1]);
 
-        let ed = &mut self.subcomponents[0];
+        let ed = &mut self.subcomponents[self.view];
         ed.render(frame, layout[1]);
+
+        if let Some(menu) = &mut self.file_menu {
+            menu.render(frame, area);
+        }
     }
 }
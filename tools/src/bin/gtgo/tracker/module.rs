@@ -0,0 +1,275 @@
+//! `.gtm` module files
+//!
+//! Everything the tracker edits, as JSON: tempo and swing, the sequence, which
+//! wavetable each channel starts on, and every pattern's non-empty beats.
+//! Empty beats are left out, so a sparse song stays small and diffs well.
+
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use anyhow::{bail, Context, Result};
+use gametank_sdk::rom_builder::FIXED_BANK;
+use serde::{Deserialize, Serialize};
+
+use crate::tracker::{empty_pattern, settings::Settings, song::MAX_SWING_PERCENT, wavetable::{self, Wavetable, TABLES}, Beat, TrackerData, CHANNELS, PATTERN_ROWS};
+
+pub const EXTENSION: &str = "gtm";
+/// Module format version this build writes
+const VERSION: u32 = 1;
+
+const AUTOSAVE_FILE: &str = "autosave.gtm";
+const AUTOSAVE_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize)]
+struct Module {
+    version: u32,
+    tempo: u8,
+    /// Percent of a row every other row is late by
+    #[serde(default, skip_serializing_if = "is_zero")]
+    swing: u8,
+    /// Pattern indices, in play order
+    sequence: Vec<usize>,
+    /// Wavetable slot each channel starts on
+    wavetables: [u8; CHANNELS],
+    /// The module's wavetables, as hex; the defaults if there are none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tables: Vec<String>,
+    patterns: Vec<Vec<Cell>>,
+    /// ROM bank the Rust export puts the song in, if not the fixed bank
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bank: Option<u8>,
+    /// Channels muted and soloed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    muted: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    soloed: Vec<u8>,
+    /// Whether exports leave out what's muted
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    export_mutes: bool,
+}
+
+/// A beat with something in it
+#[derive(Serialize, Deserialize)]
+struct Cell {
+    row: u8,
+    /// None for the sequencer lane
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    channel: Option<u8>,
+    #[serde(flatten)]
+    beat: Beat,
+}
+
+impl Module {
+    fn from_data(data: &TrackerData) -> Self {
+        let patterns = data.patterns.iter().map(|pattern| {
+            let mut cells = vec![];
+            for (lane, beats) in pattern.iter().enumerate() {
+                for (row, beat) in beats.iter().enumerate() {
+                    if !beat.is_empty() {
+                        let channel = lane.checked_sub(1).map(|ch| ch as u8);
+                        cells.push(Cell { row: row as u8, channel, beat: beat.clone() });
+                    }
+                }
+            }
+            cells
+        }).collect();
+
+        Self {
+            version: VERSION,
+            tempo: data.tempo,
+            swing: data.swing,
+            sequence: data.sequences[..data.sequence_len].to_vec(),
+            wavetables: data.wavetables,
+            tables: data.tables.iter().map(|table| table.iter().map(|b| format!("{:02x}", b)).collect()).collect(),
+            patterns,
+            bank: data.bank,
+            muted: channels(&data.muted),
+            soloed: channels(&data.soloed),
+            export_mutes: data.export_mutes,
+        }
+    }
+
+    fn into_data(self) -> Result<TrackerData> {
+        if self.version > VERSION {
+            bail!("made by a newer gtgo (module version {}, this reads up to {})", self.version, VERSION);
+        }
+        if self.patterns.is_empty() {
+            bail!("no patterns");
+        }
+        if self.sequence.is_empty() || self.sequence.len() > 256 {
+            bail!("the sequence needs 1-256 entries, not {}", self.sequence.len());
+        }
+        if let Some(missing) = self.sequence.iter().find(|p| **p >= self.patterns.len()) {
+            bail!("the sequence plays pattern {}, but there are only {}", missing, self.patterns.len());
+        }
+
+        if self.swing > MAX_SWING_PERCENT {
+            bail!("swing goes up to {}%, not {}%", MAX_SWING_PERCENT, self.swing);
+        }
+        if let Some(bank) = self.bank.filter(|b| *b > FIXED_BANK) {
+            bail!("bank {} is out of range (0-{})", bank, FIXED_BANK);
+        }
+
+        let muted = channel_flags(&self.muted).context("a muted channel doesn't exist")?;
+        let soloed = channel_flags(&self.soloed).context("a soloed channel doesn't exist")?;
+
+        let tables = match self.tables.len() {
+            0 => wavetable::default_tables(),
+            TABLES => {
+                let mut tables = [[0; wavetable::SIZE]; TABLES];
+                for (i, (table, hex)) in tables.iter_mut().zip(&self.tables).enumerate() {
+                    *table = parse_table(hex).with_context(|| format!("wavetable {} is broken", i + 1))?;
+                }
+                tables
+            }
+            n => bail!("there should be {} wavetables, not {}", TABLES, n),
+        };
+
+        let mut data = TrackerData {
+            tempo: self.tempo,
+            swing: self.swing,
+            wavetables: self.wavetables,
+            tables,
+            bank: self.bank.filter(|b| *b != FIXED_BANK),
+            muted,
+            soloed,
+            export_mutes: self.export_mutes,
+            sequence_len: self.sequence.len(),
+            patterns: Vec::with_capacity(self.patterns.len()),
+            ..TrackerData::default()
+        };
+        data.sequences[..self.sequence.len()].copy_from_slice(&self.sequence);
+
+        for (index, cells) in self.patterns.into_iter().enumerate() {
+            let mut pattern = empty_pattern();
+            for cell in cells {
+                let lane = cell.channel.map_or(0, |ch| ch as usize + 1);
+                if cell.row as usize >= PATTERN_ROWS || lane > CHANNELS {
+                    bail!("pattern {} has a beat at row {} of channel {:?}, off the pattern", index, cell.row, cell.channel);
+                }
+                pattern[lane][cell.row as usize] = cell.beat;
+            }
+            data.patterns.push(pattern);
+        }
+        Ok(data)
+    }
+}
+
+fn is_zero(n: &u8) -> bool {
+    *n == 0
+}
+
+/// The channels that are set in `flags`
+fn channels(flags: &[bool; CHANNELS]) -> Vec<u8> {
+    (0..CHANNELS).filter(|ch| flags[*ch]).map(|ch| ch as u8).collect()
+}
+
+fn channel_flags(channels: &[u8]) -> Result<[bool; CHANNELS]> {
+    let mut flags = [false; CHANNELS];
+    for ch in channels {
+        match flags.get_mut(*ch as usize) {
+            Some(flag) => *flag = true,
+            None => bail!("channel {} of {}", ch, CHANNELS),
+        }
+    }
+    Ok(flags)
+}
+
+fn parse_table(hex: &str) -> Result<Wavetable> {
+    if hex.len() != wavetable::SIZE * 2 || !hex.is_ascii() {
+        bail!("it should be {} hex digits", wavetable::SIZE * 2);
+    }
+    let mut table = [0; wavetable::SIZE];
+    for (sample, digits) in table.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits)?;
+        *sample = u8::from_str_radix(digits, 16).with_context(|| format!("{:?} isn't hex", digits))?;
+    }
+    Ok(table)
+}
+
+fn to_json(data: &TrackerData) -> String {
+    serde_json::to_string_pretty(&Module::from_data(data)).expect("modules always serialize")
+}
+
+pub fn load(path: &Path) -> Result<TrackerData> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    let module: Module = serde_json::from_str(&text).with_context(|| format!("{} isn't a module", path.display()))?;
+    module.into_data().with_context(|| format!("{} is broken", path.display()))
+}
+
+/// Write `text` next to `path` and move it over, so a crash mid-write
+/// can't leave half a song behind
+fn write(path: &Path, text: &str) -> Result<()> {
+    let tmp = path.with_extension(format!("{}~", EXTENSION));
+    std::fs::write(&tmp, text)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .with_context(|| format!("couldn't write {}", path.display()))
+}
+
+pub fn autosave_path() -> Option<PathBuf> {
+    Settings::gametank_dir(dirs::data_dir()).map(|dir| dir.join(AUTOSAVE_FILE))
+}
+
+/// The module file being edited, and what's been written to it
+pub struct ModuleFile {
+    pub path: Option<PathBuf>,
+    /// The module as last saved or loaded, to tell if it's changed
+    saved: String,
+    autosaved: String,
+    next_autosave: Instant,
+    pub modified: bool,
+}
+
+impl ModuleFile {
+    pub fn new(data: &TrackerData, path: Option<PathBuf>) -> Self {
+        let saved = to_json(data);
+        Self {
+            path,
+            autosaved: saved.clone(),
+            saved,
+            next_autosave: Instant::now() + AUTOSAVE_PERIOD,
+            modified: false,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.path.as_ref()
+            .and_then(|p| p.file_name())
+            .map_or("untitled".to_string(), |n| n.to_string_lossy().into_owned())
+    }
+
+    pub fn save(&mut self, data: &TrackerData, path: PathBuf) -> Result<()> {
+        let text = to_json(data);
+        write(&path, &text)?;
+        Settings::load().push_recent(&path);
+        self.path = Some(path);
+        self.saved = text;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Save to the autosave file if the module changed since it last was
+    pub fn autosave(&mut self, data: &TrackerData) -> Result<()> {
+        self.next_autosave = Instant::now() + AUTOSAVE_PERIOD;
+        let text = to_json(data);
+        self.modified = text != self.saved;
+        if !self.modified || text == self.autosaved {
+            return Ok(());
+        }
+
+        let Some(path) = autosave_path() else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("couldn't create {}", dir.display()))?;
+        }
+        write(&path, &text)?;
+        self.autosaved = text;
+        Ok(())
+    }
+
+    /// Autosave every so often
+    pub fn tick(&mut self, data: &TrackerData) -> Result<()> {
+        if Instant::now() < self.next_autosave {
+            return Ok(());
+        }
+        self.autosave(data)
+    }
+}
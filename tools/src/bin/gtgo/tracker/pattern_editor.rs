@@ -1,8 +1,10 @@
+use std::{cell::{Ref, RefCell, RefMut}, ops::RangeInclusive, rc::Rc};
+
 use crossbeam_channel::{Receiver, Sender};
 use rat_widget::table::{selection::RowSelection, textdata::{Cell, Row}, Table, TableData, TableState};
 use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers}, layout::{Constraint, Direction, Layout, Rect}, style::{Modifier, Style, Stylize}, text::{Line, Span}, widgets::Widget};
 
-use crate::{helpers::SCHEME, tracker::{empty_pattern, lane::{Lane, LaneKind}, midi::MidiNote, Beat, ChannelCmd, Handler, Pattern, TSub, TrackerCmd, TrackerData}, Component};
+use crate::{helpers::SCHEME, tracker::{fx, lane::{Lane, LaneKind}, midi::MidiNote, song::VOLUME_MAX, undo::Edit, Beat, ChannelCmd, Handler, Pattern, TSub, TrackerCmd, TrackerData, PATTERN_ROWS}, Component};
 
 #[derive(Clone, Copy)]
 pub enum PatternEvent {
@@ -11,9 +13,79 @@ pub enum PatternEvent {
     Left,
     Right,
     Quit,
-    Enter,
     SmallIncrement,
-    SmallDecrement
+    SmallDecrement,
+    Piano(u8), // semitones above the octave's C
+    MidiNote(u8, Option<u8>), // note, velocity
+    Clear,
+    OctaveDown,
+    OctaveUp,
+    StepDown,
+    StepUp,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    Copy,
+    Cut,
+    Paste,
+    TransposeUp, // an octave
+    TransposeDown,
+    Type(char), // a character of an effect, sequencer command or volume
+    Ramp, // interpolate volumes down the selection
+    Mute, // the cursor's channel
+    Solo,
+}
+
+/// Two keyboard rows as a piano, two octaves from Z and Q:
+/// the letters are the white keys, the row above them the black keys
+const PIANO_KEYS: [(char, u8); 29] = [
+    ('z', 0), ('s', 1), ('x', 2), ('d', 3), ('c', 4), ('v', 5), ('g', 6),
+    ('b', 7), ('h', 8), ('n', 9), ('j', 10), ('m', 11),
+    (',', 12), ('l', 13), ('.', 14), (';', 15), ('/', 16),
+    ('q', 12), ('2', 13), ('w', 14), ('3', 15), ('e', 16), ('r', 17), ('5', 18),
+    ('t', 19), ('6', 20), ('y', 21), ('7', 22), ('u', 23),
+];
+
+/// Volumes are typed as a hex digit; full volume is 0x10, so it gets a key of its own
+const FULL_VOLUME_KEY: char = '+';
+
+const MAX_OCTAVE: u8 = 8;
+const MAX_EDIT_STEP: u8 = 16;
+
+/// Cells copied out of a pattern: for each column, the lane's part of each
+/// beat, top to bottom
+#[derive(Clone)]
+struct Clip {
+    kinds: Vec<LaneKind>,
+    columns: Vec<Vec<Beat>>,
+}
+
+/// Whether `cmd` shows in lanes of `kind`
+fn in_lane(kind: LaneKind, cmd: &ChannelCmd) -> bool {
+    match kind {
+        LaneKind::Note => matches!(cmd, ChannelCmd::Note(_)),
+        LaneKind::Vol => matches!(cmd, ChannelCmd::Volume(_)),
+        LaneKind::Fx => fx::is_fx(cmd),
+        LaneKind::Beat | LaneKind::Seq => false,
+    }
+}
+
+/// The part of `beat` a lane of `kind` shows
+fn lane_part(kind: LaneKind, beat: &Beat) -> Beat {
+    Beat {
+        cmd_list: beat.cmd_list.iter().filter(|c| in_lane(kind, c)).cloned().collect(),
+        sqc_list: if kind == LaneKind::Seq { beat.sqc_list.clone() } else { vec![] },
+    }
+}
+
+/// Swap the part of `beat` a lane of `kind` shows for `part`'s
+fn set_lane_part(kind: LaneKind, beat: &mut Beat, part: &Beat) {
+    beat.cmd_list.retain(|c| !in_lane(kind, c));
+    beat.cmd_list.extend(part.cmd_list.iter().filter(|c| in_lane(kind, c)).cloned());
+    if kind == LaneKind::Seq {
+        beat.sqc_list = part.sqc_list.clone();
+    }
 }
 
 pub struct PatternEditor {
@@ -21,42 +93,105 @@ pub struct PatternEditor {
     pub sel_y: u8,
 
     pub scroll: i8,
+    pub octave: u8,
+    pub edit_step: u8, // rows to move down after entering a note
+    anchor: Option<(u8, u8)>, // the block selection's other corner, x/y
+    clip: Option<Clip>,
+    entry: String, // the effect or sequencer command being typed into the selected cell
+    error: Option<String>, // why the last one or volume typed was no good
     lanes: Vec<Lane>,
-    tracker_data: TrackerData,
+    tracker_data: Rc<RefCell<TrackerData>>,
     active_handlers: Vec<Handler>,
     global_handlers: Vec<Handler>,
     cx_rx: Receiver<PatternEvent>,
-    #[allow(dead_code)]
     cx_tx: Sender<PatternEvent>,
     par_tx: Sender<TrackerCmd>,
 }
 
 
 pub fn tx_handler(tx: &Sender<PatternEvent>, code: KeyCode, cmd: PatternEvent) -> Handler {
+    key_handler(tx, KeyEvent::new(code, KeyModifiers::NONE), cmd)
+}
+
+fn shift_handler(tx: &Sender<PatternEvent>, code: KeyCode, cmd: PatternEvent) -> Handler {
+    key_handler(tx, KeyEvent::new(code, KeyModifiers::SHIFT), cmd)
+}
+
+fn ctrl_handler(tx: &Sender<PatternEvent>, code: KeyCode, cmd: PatternEvent) -> Handler {
+    key_handler(tx, KeyEvent::new(code, KeyModifiers::CONTROL), cmd)
+}
+
+fn alt_handler(tx: &Sender<PatternEvent>, code: KeyCode, cmd: PatternEvent) -> Handler {
+    key_handler(tx, KeyEvent::new(code, KeyModifiers::ALT), cmd)
+}
+
+fn key_handler(tx: &Sender<PatternEvent>, key: KeyEvent, cmd: PatternEvent) -> Handler {
     let txx = tx.clone();
     let cmd = cmd.clone();
-    Handler { event: Event::Key(KeyEvent::new(code, KeyModifiers::NONE)), action: Box::new(move || {
+    Handler { event: Event::Key(key), action: Box::new(move || {
         let _ = txx.send(cmd);
     })}
 }
 
 impl PatternEditor {
-    pub fn init(parent_tx: Sender<TrackerCmd>) -> Self {
+    pub fn init(parent_tx: Sender<TrackerCmd>, tracker_data: Rc<RefCell<TrackerData>>) -> Self {
         let (cx_tx, cx_rx) = crossbeam_channel::unbounded();
 
-        let handlers = vec![
+        // q and j are piano keys, so the lane's left with esc and
+        // nudged with ctrl+up/down
+        let mut handlers = vec![
             tx_handler(&cx_tx, KeyCode::Esc, PatternEvent::Quit),
-            tx_handler(&cx_tx, KeyCode::Char('q'), PatternEvent::Quit),
             tx_handler(&cx_tx, KeyCode::Up, PatternEvent::Up),
             tx_handler(&cx_tx, KeyCode::Down, PatternEvent::Down),
             tx_handler(&cx_tx, KeyCode::Left, PatternEvent::Left),
             tx_handler(&cx_tx, KeyCode::Right, PatternEvent::Right),
-            tx_handler(&cx_tx, KeyCode::Char('j'), PatternEvent::SmallIncrement),
-            tx_handler(&cx_tx, KeyCode::Char('k'), PatternEvent::SmallDecrement),
+            shift_handler(&cx_tx, KeyCode::Up, PatternEvent::SelectUp),
+            shift_handler(&cx_tx, KeyCode::Down, PatternEvent::SelectDown),
+            shift_handler(&cx_tx, KeyCode::Left, PatternEvent::SelectLeft),
+            shift_handler(&cx_tx, KeyCode::Right, PatternEvent::SelectRight),
+            ctrl_handler(&cx_tx, KeyCode::Up, PatternEvent::SmallIncrement),
+            ctrl_handler(&cx_tx, KeyCode::Down, PatternEvent::SmallDecrement),
+            ctrl_handler(&cx_tx, KeyCode::PageUp, PatternEvent::TransposeUp),
+            ctrl_handler(&cx_tx, KeyCode::PageDown, PatternEvent::TransposeDown),
+            ctrl_handler(&cx_tx, KeyCode::Char('c'), PatternEvent::Copy),
+            ctrl_handler(&cx_tx, KeyCode::Char('x'), PatternEvent::Cut),
+            ctrl_handler(&cx_tx, KeyCode::Char('v'), PatternEvent::Paste),
+            ctrl_handler(&cx_tx, KeyCode::Char('r'), PatternEvent::Ramp),
+            alt_handler(&cx_tx, KeyCode::Char('m'), PatternEvent::Mute),
+            alt_handler(&cx_tx, KeyCode::Char('s'), PatternEvent::Solo),
+            tx_handler(&cx_tx, KeyCode::Delete, PatternEvent::Clear),
+            tx_handler(&cx_tx, KeyCode::Backspace, PatternEvent::Clear),
+            tx_handler(&cx_tx, KeyCode::Char('['), PatternEvent::OctaveDown),
+            tx_handler(&cx_tx, KeyCode::Char(']'), PatternEvent::OctaveUp),
+            tx_handler(&cx_tx, KeyCode::Char('-'), PatternEvent::StepDown),
+            tx_handler(&cx_tx, KeyCode::Char('='), PatternEvent::StepUp),
         ];
+        for (key, semitone) in PIANO_KEYS {
+            handlers.push(tx_handler(&cx_tx, KeyCode::Char(key), PatternEvent::Piano(semitone)));
+        }
+        // effects and volumes share keys with the piano; each only does
+        // anything in its own lanes
+        let mut typed: Vec<char> = fx::LETTERS.into_iter()
+            .chain(fx::SEQ_LETTERS)
+            .chain("0123456789abcdef".chars())
+            .collect();
+        typed.sort();
+        typed.dedup();
+        for key in typed {
+            handlers.push(tx_handler(&cx_tx, KeyCode::Char(key), PatternEvent::Type(key)));
+        }
+        // some terminals say + is shifted, some don't
+        handlers.push(tx_handler(&cx_tx, KeyCode::Char(FULL_VOLUME_KEY), PatternEvent::Type(FULL_VOLUME_KEY)));
+        handlers.push(shift_handler(&cx_tx, KeyCode::Char(FULL_VOLUME_KEY), PatternEvent::Type(FULL_VOLUME_KEY)));
 
         Self {
             scroll: -8,
+            octave: 4,
+            edit_step: 1,
+            anchor: None,
+            clip: None,
+            entry: String::new(),
+            error: None,
             lanes: vec![
                 Lane::beat(),
                 Lane::seq(),
@@ -69,13 +204,7 @@ impl PatternEditor {
                 Lane::note(6), Lane::vol(6), Lane::fx(6),
                 Lane::note(7), Lane::vol(7), Lane::fx(7),
             ],
-            tracker_data: TrackerData {
-                beat: 0,
-                pattern: 0,
-                sequence: 0,
-                sequences: [0; 256],
-                patterns: vec![empty_pattern()],
-            },
+            tracker_data,
             sel_x: 2,
             sel_y: 2,
             active_handlers: handlers,
@@ -86,12 +215,12 @@ impl PatternEditor {
         }
     }
 
-    pub fn current_pattern(&self) -> &Pattern {
-        &self.tracker_data.patterns[self.tracker_data.pattern as usize]
+    pub fn current_pattern(&self) -> Ref<'_, Pattern> {
+        Ref::map(self.tracker_data.borrow(), |data| &data.patterns[data.pattern as usize])
     }
 
-    pub fn current_pattern_mut(&mut self) -> &mut Pattern {
-        &mut self.tracker_data.patterns[self.tracker_data.pattern as usize]
+    pub fn current_pattern_mut(&mut self) -> RefMut<'_, Pattern> {
+        RefMut::map(self.tracker_data.borrow_mut(), |data| &mut data.patterns[data.pattern as usize])
     }
 
     fn get_channel_beat(ch: Option<usize>, beat: u8, pattern: &Pattern) -> &Beat {
@@ -101,7 +230,7 @@ impl PatternEditor {
         }
     }
 
-    pub fn get_selected_beat(&mut self) -> Option<&mut Beat> {
+    pub fn get_selected_beat(&mut self) -> Option<RefMut<'_, Beat>> {
         // TODO: this is gonna confuse the SHIT out of people
         let beat_idx = self.sel_y as usize;
         let lane = &self.lanes[self.sel_x as usize];
@@ -111,7 +240,308 @@ impl PatternEditor {
             _ => lane.ch,
         }?;
 
-        Some(&mut self.current_pattern_mut()[ch_idx][beat_idx])
+        Some(RefMut::map(self.current_pattern_mut(), |pattern| &mut pattern[ch_idx][beat_idx]))
+    }
+
+    /// The selected lane's kind and channel
+    fn selected_lane(&self) -> (LaneKind, Option<usize>) {
+        let lane = &self.lanes[self.sel_x as usize];
+        (lane.kind, lane.ch)
+    }
+
+    /// For sending the editor events from elsewhere, like a MIDI keyboard
+    pub fn events(&self) -> Sender<PatternEvent> {
+        self.cx_tx.clone()
+    }
+
+    /// Put `note` in the selected note cell, in place of any note there, with
+    /// `volume` if there is one, and move down by the edit step
+    fn enter_note(&mut self, note: u8, volume: Option<u8>) {
+        let (LaneKind::Note, Some(channel)) = self.selected_lane() else { return };
+        let row = self.sel_y as usize % PATTERN_ROWS;
+        {
+            let mut pattern = self.current_pattern_mut();
+            let beat = &mut pattern[channel+1][row];
+            match beat.cmd_list.iter_mut().rev().find(|c| matches!(c, ChannelCmd::Note(_))) {
+                Some(cmd) => *cmd = ChannelCmd::Note(note),
+                None => beat.cmd_list.push(ChannelCmd::Note(note)),
+            }
+            if let Some(volume) = volume {
+                beat.cmd_list.retain(|c| !matches!(c, ChannelCmd::Volume(_)));
+                beat.cmd_list.push(ChannelCmd::Volume(volume));
+            }
+        }
+        self.sel_y = ((row + self.edit_step as usize) % PATTERN_ROWS) as u8;
+    }
+
+    /// Take the next character of an effect in the selected Fx cell, or a
+    /// command in the sequencer lane. Once it's all there, it replaces any
+    /// there that does the same, and the cursor moves down by the edit step.
+    fn type_command(&mut self, c: char) {
+        let (kind, channel) = self.selected_lane();
+        let letters: &[char] = match kind {
+            LaneKind::Fx => &fx::LETTERS,
+            LaneKind::Seq => &fx::SEQ_LETTERS,
+            _ => return,
+        };
+        self.error = None;
+        if self.entry.is_empty() && !letters.contains(&c) {
+            self.error = Some(format!("no command {}", c.to_ascii_uppercase()));
+            return;
+        }
+        self.entry.push(c);
+        if self.entry.len() < fx::LEN {
+            return;
+        }
+
+        let entry = std::mem::take(&mut self.entry);
+        let row = self.sel_y as usize % PATTERN_ROWS;
+        let lane = channel.map_or(0, |ch| ch + 1);
+        let typed = match kind {
+            LaneKind::Seq => fx::parse_seq(&entry).map(|cmd| self.edit(|ed| {
+                let beat = &mut ed.current_pattern_mut()[lane][row];
+                beat.sqc_list.retain(|c| !fx::same_seq(c, &cmd));
+                beat.sqc_list.push(cmd);
+            })),
+            _ => fx::parse(&entry).map(|cmd| self.edit(|ed| {
+                let beat = &mut ed.current_pattern_mut()[lane][row];
+                beat.cmd_list.retain(|c| !fx::same_effect(c, &cmd));
+                beat.cmd_list.push(cmd);
+            })),
+        };
+        match typed {
+            Ok(()) => self.sel_y = ((row + self.edit_step as usize) % PATTERN_ROWS) as u8,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Set the selected volume cell to the typed hex digit, or full volume
+    /// for [`FULL_VOLUME_KEY`], and move down by the edit step
+    fn type_volume(&mut self, c: char) {
+        let (LaneKind::Vol, Some(channel)) = self.selected_lane() else { return };
+        let volume = match c {
+            FULL_VOLUME_KEY => VOLUME_MAX,
+            c => match c.to_digit(16) {
+                Some(v) => v as u8,
+                None => {
+                    self.error = Some(format!("volumes are 0-F, or {} for full", FULL_VOLUME_KEY));
+                    return;
+                }
+            },
+        };
+        self.error = None;
+
+        let row = self.sel_y as usize % PATTERN_ROWS;
+        self.edit(|ed| {
+            let mut pattern = ed.current_pattern_mut();
+            let beat = &mut pattern[channel+1][row];
+            beat.cmd_list.retain(|c| !matches!(c, ChannelCmd::Volume(_)));
+            beat.cmd_list.push(ChannelCmd::Volume(volume));
+        });
+        self.sel_y = ((row + self.edit_step as usize) % PATTERN_ROWS) as u8;
+    }
+
+    /// Make an edit to the current pattern with `change`, and record it so
+    /// it can be undone
+    fn edit(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.current_pattern().clone();
+        change(self);
+
+        let data = &mut *self.tracker_data.borrow_mut();
+        let pattern = data.pattern as usize;
+        if let Some(edit) = Edit::cells(pattern, &before, &data.patterns[pattern]) {
+            data.history.record(edit);
+        }
+    }
+
+    /// Move the cursor, dragging a block selection along if `select`
+    fn move_cursor(&mut self, dx: i8, dy: i8, select: bool) {
+        self.entry.clear();
+        self.error = None;
+        match (select, self.anchor) {
+            (true, None) => self.anchor = Some((self.sel_x, self.sel_y)),
+            (false, _) => self.anchor = None,
+            _ => {}
+        }
+        self.sel_x = self.sel_x.saturating_add_signed(dx).min(self.lanes.len() as u8 - 1);
+        self.sel_y = self.sel_y.saturating_add_signed(dy).min(PATTERN_ROWS as u8 - 1);
+    }
+
+    /// The columns and rows of the block selection, or the cursor's cell
+    fn block(&self) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+        let (x, y) = self.anchor.unwrap_or((self.sel_x, self.sel_y));
+        let (x0, x1) = (x.min(self.sel_x) as usize, x.max(self.sel_x) as usize);
+        let (y0, y1) = (y.min(self.sel_y) as usize, y.max(self.sel_y) as usize);
+        (x0..=x1.min(self.lanes.len() - 1), y0..=y1.min(PATTERN_ROWS - 1))
+    }
+
+    /// Which of the pattern's lanes a column edits, if any
+    fn pattern_lane(&self, column: usize) -> Option<usize> {
+        let lane = &self.lanes[column];
+        match lane.kind {
+            LaneKind::Beat => None,
+            LaneKind::Seq => Some(0),
+            _ => lane.ch.map(|ch| ch + 1),
+        }
+    }
+
+    fn copy(&mut self) {
+        let (columns, rows) = self.block();
+        let pattern = self.current_pattern();
+        let clip = Clip {
+            kinds: columns.clone().map(|x| self.lanes[x].kind).collect(),
+            columns: columns.map(|x| match self.pattern_lane(x) {
+                Some(lane) => pattern[lane][rows.clone()].iter().map(|b| lane_part(self.lanes[x].kind, b)).collect(),
+                None => vec![Beat::default(); rows.clone().count()],
+            }).collect(),
+        };
+        drop(pattern);
+        self.clip = Some(clip);
+    }
+
+    /// Empty the selected cells' lanes
+    fn clear(&mut self) {
+        let (columns, rows) = self.block();
+        let lanes: Vec<_> = columns.filter_map(|x| Some((self.lanes[x].kind, self.pattern_lane(x)?))).collect();
+        let mut pattern = self.current_pattern_mut();
+        for (kind, lane) in lanes {
+            for beat in &mut pattern[lane][rows.clone()] {
+                set_lane_part(kind, beat, &Beat::default());
+            }
+        }
+    }
+
+    /// Paste the clip with its top left at the cursor. Columns land on
+    /// lanes of the same kind only, and what's off the pattern is dropped.
+    fn paste(&mut self) {
+        let Some(clip) = self.clip.clone() else { return };
+        let (x, y) = (self.sel_x as usize, self.sel_y as usize);
+        let lanes: Vec<_> = clip.kinds.iter().enumerate()
+            .filter(|(i, kind)| self.lanes.get(x + i).is_some_and(|lane| lane.kind == **kind))
+            .filter_map(|(i, kind)| Some((i, *kind, self.pattern_lane(x + i)?)))
+            .collect();
+
+        let mut pattern = self.current_pattern_mut();
+        for (i, kind, lane) in lanes {
+            for (beat, part) in pattern[lane][y..].iter_mut().zip(&clip.columns[i]) {
+                set_lane_part(kind, beat, part);
+            }
+        }
+        drop(pattern);
+        self.anchor = None;
+    }
+
+    /// Move the selected notes by `semitones`, keeping them on the keyboard
+    fn transpose(&mut self, semitones: i8) {
+        let (columns, rows) = self.block();
+        let lanes: Vec<_> = columns
+            .filter(|x| self.lanes[*x].kind == LaneKind::Note)
+            .filter_map(|x| self.pattern_lane(x))
+            .collect();
+        let mut pattern = self.current_pattern_mut();
+        for lane in lanes {
+            for beat in &mut pattern[lane][rows.clone()] {
+                for cmd in &mut beat.cmd_list {
+                    if let ChannelCmd::Note(n) = cmd {
+                        *n = n.saturating_add_signed(semitones).min(127);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Raise or lower the volumes set in the selection by `delta`
+    fn step_volumes(&mut self, delta: i8) {
+        let (columns, rows) = self.block();
+        let lanes: Vec<_> = columns
+            .filter(|x| self.lanes[*x].kind == LaneKind::Vol)
+            .filter_map(|x| self.pattern_lane(x))
+            .collect();
+        let mut pattern = self.current_pattern_mut();
+        for lane in lanes {
+            for beat in &mut pattern[lane][rows.clone()] {
+                for cmd in &mut beat.cmd_list {
+                    if let ChannelCmd::Volume(v) = cmd {
+                        *v = v.saturating_add_signed(delta).min(VOLUME_MAX);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill each selected volume lane with a ramp from the volume on the
+    /// selection's first row to the one on its last, for fades
+    fn ramp(&mut self) {
+        let (columns, rows) = self.block();
+        let lanes: Vec<_> = columns
+            .filter(|x| self.lanes[*x].kind == LaneKind::Vol)
+            .filter_map(|x| self.pattern_lane(x))
+            .collect();
+        if lanes.is_empty() || rows.clone().count() < 3 {
+            self.error = Some("select three or more rows of a volume lane to ramp".to_string());
+            return;
+        }
+
+        let volume = |beat: &Beat| beat.cmd_list.iter().rev().find_map(|c| match c {
+            ChannelCmd::Volume(v) => Some(*v as i32),
+            _ => None,
+        });
+        let (first, last) = (*rows.start(), *rows.end());
+        let span = (last - first) as i32;
+        let mut pattern = self.current_pattern_mut();
+        let mut skipped = 0;
+        for lane in lanes {
+            let (Some(from), Some(to)) = (volume(&pattern[lane][first]), volume(&pattern[lane][last])) else {
+                skipped += 1;
+                continue;
+            };
+            for row in first + 1..last {
+                let i = (row - first) as i32;
+                // rounded to the nearest
+                let v = (from * (span - i) + to * i + span / 2) / span;
+                let beat = &mut pattern[lane][row];
+                beat.cmd_list.retain(|c| !matches!(c, ChannelCmd::Volume(_)));
+                beat.cmd_list.push(ChannelCmd::Volume(v as u8));
+            }
+        }
+        drop(pattern);
+        if skipped > 0 {
+            self.error = Some("a ramp needs a volume on the first and last rows".to_string());
+        }
+    }
+
+    /// Nudge the selected cell's note or volume by `delta`, starting one off
+    /// if there isn't one
+    fn nudge(&mut self, delta: i8) {
+        let (kind, Some(channel)) = self.selected_lane() else { return };
+        let row = self.sel_y as usize % PATTERN_ROWS;
+        let mut pattern = self.current_pattern_mut();
+        let beat = &mut pattern[channel+1][row];
+        match kind {
+            LaneKind::Note => {
+                let found = beat.cmd_list.iter_mut().rev().find_map(|c| match c {
+                    ChannelCmd::Note(n) => {
+                        *n = n.saturating_add_signed(delta).min(127); Some(())
+                    }
+                    _ => None,
+                });
+                if found.is_none() {
+                    beat.cmd_list.push(ChannelCmd::Note(MidiNote::C4 as u8));
+                }
+            }
+            LaneKind::Vol => {
+                let found = beat.cmd_list.iter_mut().rev().find_map(|c| match c {
+                    ChannelCmd::Volume(v) => {
+                        *v = v.saturating_add_signed(delta).min(VOLUME_MAX); Some(())
+                    }
+                    _ => None,
+                });
+                if found.is_none() {
+                    beat.cmd_list.push(ChannelCmd::Volume(VOLUME_MAX));
+                }
+            }
+            _ => {}
+        }
     }
 
     pub fn get_cell(&self, row: usize, column: usize) -> CellDisplay {
@@ -126,13 +556,16 @@ impl PatternEditor {
             LaneKind::Beat => {
                 CellDisplay::BeatNum(ym64)
             },
+            LaneKind::Seq | LaneKind::Fx if y == self.sel_y && column == self.sel_x as usize && !self.entry.is_empty() => {
+                CellDisplay::Entry(self.entry.to_ascii_uppercase())
+            }
             LaneKind::Seq => {
-                let beat = Self::get_channel_beat(lane.ch, ym64, pattern);
-                let ct = beat.sqc_list.len();
-                CellDisplay::SeqCmds(ct)
+                let beat = Self::get_channel_beat(lane.ch, ym64, &pattern);
+                let first = beat.sqc_list.first().map(fx::seq_text);
+                CellDisplay::Seq(first, beat.sqc_list.len().saturating_sub(1))
             },
             LaneKind::Note => {
-                let beat = Self::get_channel_beat(lane.ch, ym64, pattern);
+                let beat = Self::get_channel_beat(lane.ch, ym64, &pattern);
                 let note = beat.cmd_list.iter().find_map(|c| match c {
                     ChannelCmd::Note(num) => Some(MidiNote::from(*num)),
                     _ => None,
@@ -140,7 +573,7 @@ impl PatternEditor {
                 CellDisplay::Note(note)
             },
             LaneKind::Vol => {
-                let beat = Self::get_channel_beat(lane.ch, ym64, pattern);
+                let beat = Self::get_channel_beat(lane.ch, ym64, &pattern);
                 let vol = beat.cmd_list.iter().find_map(|c| match c {
                         ChannelCmd::Volume(v) => Some(*v),
                         _ => None,
@@ -148,12 +581,10 @@ impl PatternEditor {
                 CellDisplay::Vol(vol)
             }
             LaneKind::Fx => {
-                let beat = Self::get_channel_beat(lane.ch, ym64, pattern);
-                let n = beat.cmd_list.iter().filter(|c| 
-                    !matches!(c, ChannelCmd::Note(_) | ChannelCmd::Volume(_)))
-                    .count()
-                    .min(0xF) as u8;
-                CellDisplay::Fx(n)
+                let beat = Self::get_channel_beat(lane.ch, ym64, &pattern);
+                let mut effects = beat.cmd_list.iter().filter(|c| fx::is_fx(c));
+                let first = effects.next().map(fx::text);
+                CellDisplay::Fx(first, effects.count())
             }
         }
     }
@@ -182,13 +613,26 @@ impl <'a> TableData<'a> for &mut PatternEditor {
         ];
 
         let mut cells = vec![];
+        let data = self.tracker_data.borrow();
 
         for lane in &self.lanes {
             let cell = Cell::new(match lane.kind {
                 LaneKind::Beat => Span::from(lane.title.clone()),
                 LaneKind::Seq => Span::from(lane.title.clone()),
+                // channels that won't be heard are crossed out
+                LaneKind::Note if !data.audible(lane.ch.unwrap()) => {
+                    Span::from(lane.title.clone()).fg(SCHEME.gray[0]).italic().crossed_out()
+                }
                 LaneKind::Note => Span::from(lane.title.clone()).fg(c[lane.ch.unwrap()]).italic(),
-                LaneKind::Vol => Span::from(lane.title.clone()).fg(c[lane.ch.unwrap()]),
+                // and marked M or S in the volume lane's
+                LaneKind::Vol => {
+                    let ch = lane.ch.unwrap();
+                    match (data.soloed[ch], data.muted[ch]) {
+                        (true, _) => Span::from(" S ").fg(SCHEME.black[0]).bg(SCHEME.yellow[2]),
+                        (false, true) => Span::from(" M ").fg(SCHEME.black[0]).bg(SCHEME.red[2]),
+                        _ => Span::from(lane.title.clone()).fg(c[ch]),
+                    }
+                }
                 LaneKind::Fx => Span::from(lane.title.clone()).fg(c[lane.ch.unwrap()]),
             });
             cells.push(cell);
@@ -212,15 +656,22 @@ impl <'a> TableData<'a> for &mut PatternEditor {
         let is_active = (0..64).contains(&offset);
         let row_selected = row == (self.sel_y as i8 - self.scroll) as usize;
         let col_selected = column == self.sel_x as usize;
+        let (block_columns, block_rows) = self.block();
+        let in_block = self.anchor.is_some() && is_active
+            && block_columns.contains(&column) && block_rows.contains(&(offset as usize));
 
         let cell = self.get_cell(row, column);
         
         let style = if row_selected {
             if col_selected {
                 CellStyle::SelectedCell
+            } else if in_block {
+                CellStyle::Block
             } else {
                 CellStyle::SelectedRow
             }
+        } else if in_block {
+            CellStyle::Block
         } else if row_even {
             CellStyle::EvenRow
         } else {
@@ -240,34 +691,34 @@ pub enum CellStyle {
     OddRow,
     SelectedRow,
     SelectedCell,
+    Block,
     Bar,
 }
 
 pub enum CellDisplay {
     BeatNum(u8), // cell number & is_active
-    SeqCmds(usize), // 0 is ---, n is [n]
+    Seq(Option<String>, usize), // the first command, and how many more (shown as +)
     Note(MidiNote),
     Vol(Option<u8>), // 0..=16 (no change is -)
-    Fx(u8), // fx count, 0 is ---, n is [n]
+    Fx(Option<String>, usize), // the first effect, and how many more (shown as +)
+    Entry(String), // an effect or sequencer command being typed
 }
 
 impl CellDisplay {
     fn text(&self) -> String {
         match self {
             CellDisplay::BeatNum(beat) => format!("   {:02X}", beat),
-            CellDisplay::SeqCmds(n) => match n {
-                0 => "---".to_string(),
-                n => format!("[{:1x}]", n),
-            },
+            CellDisplay::Seq(first, more) | CellDisplay::Fx(first, more) => format!(
+                "{}{}",
+                first.as_deref().unwrap_or("---"),
+                if *more > 0 { "+" } else { " " },
+            ),
             CellDisplay::Note(midi_note) => midi_note.to_string(),
             CellDisplay::Vol(maybe_set) => match maybe_set {
                 Some(v) => format!("{:1x}", v),
                 None => "-".to_string(),
             },
-            CellDisplay::Fx(n) => match n {
-                0 => "---".to_string(),
-                n => format!("[{:1x}]", n),
-            }
+            CellDisplay::Entry(entry) => format!("{:·<width$} ", entry, width = fx::LEN),
         }
     }
 
@@ -277,9 +728,9 @@ impl CellDisplay {
 
         let (fg, modifiers)  = match self {
             CellDisplay::BeatNum(_) => (SCHEME.deepblue[2], Modifier::ITALIC),
-            CellDisplay::SeqCmds(v) => (match v {
-                0 => SCHEME.reduced_text_color(SCHEME.white[1]),
-                _ => SCHEME.reduced_text_color(SCHEME.white[1]),
+            CellDisplay::Seq(first, _) => (match first {
+                None => SCHEME.gray[0],
+                Some(_) => SCHEME.reduced_text_color(SCHEME.white[1]),
             }, Modifier::empty()),
             CellDisplay::Note(midi_note) => (match midi_note {
                 MidiNote::None => SCHEME.gray[1],
@@ -289,10 +740,11 @@ impl CellDisplay {
                 None => SCHEME.gray[0],
                 Some(_) => SCHEME.magenta[0],
             }, Modifier::empty()),
-            CellDisplay::Fx(n) => (match n {
-                0 => SCHEME.gray[0],
-                _ => SCHEME.yellow[1],
+            CellDisplay::Fx(first, _) => (match first {
+                None => SCHEME.gray[0],
+                Some(_) => SCHEME.yellow[1],
             }, Modifier::empty()),
+            CellDisplay::Entry(_) => (SCHEME.yellow[3], Modifier::empty()),
         };
 
         style = style.fg(fg).add_modifier(modifiers);
@@ -302,6 +754,7 @@ impl CellDisplay {
             CellStyle::EvenRow => (SCHEME.true_dark_color(SCHEME.black[3]), Modifier::empty()),
             CellStyle::OddRow => (SCHEME.true_dark_color(SCHEME.black[0]), Modifier::empty()),
             CellStyle::SelectedRow => (SCHEME.true_dark_color(SCHEME.blue[0]), Modifier::empty()),
+            CellStyle::Block => (SCHEME.true_dark_color(SCHEME.purple[1]), Modifier::empty()),
             CellStyle::SelectedCell => {
                 style = style.fg(SCHEME.deepblue[1]);
                 (SCHEME.true_dark_color(SCHEME.blue[3]), Modifier::SLOW_BLINK | Modifier::REVERSED)
@@ -341,48 +794,94 @@ impl CellDisplay {
 
 impl Component for PatternEditor {
     fn update(&mut self, _events: Vec<Event>) {
-        let (lane_kind, ch) = {
-            let lane = &self.lanes[self.sel_x as usize];
-            let kind = lane.kind;
-            let ch = lane.ch;
-            (kind, ch)
-        };
-        let sel_beat = self.sel_y as usize;
-
         while let Ok(event) = self.cx_rx.try_recv() {
             match event {
-                PatternEvent::Up => self.sel_y -= 1,
-                PatternEvent::Down => self.sel_y += 1,
-                PatternEvent::Left => self.sel_x -= 1,
-                PatternEvent::Right => self.sel_x += 1,
-                PatternEvent::Enter => todo!(),
+                PatternEvent::Up => self.move_cursor(0, -1, false),
+                PatternEvent::Down => self.move_cursor(0, 1, false),
+                PatternEvent::Left => self.move_cursor(-1, 0, false),
+                PatternEvent::Right => self.move_cursor(1, 0, false),
+                PatternEvent::SelectUp => self.move_cursor(0, -1, true),
+                PatternEvent::SelectDown => self.move_cursor(0, 1, true),
+                PatternEvent::SelectLeft => self.move_cursor(-1, 0, true),
+                PatternEvent::SelectRight => self.move_cursor(1, 0, true),
+                // esc drops a half-typed effect or the selection first
+                PatternEvent::Quit if !self.entry.is_empty() => self.entry.clear(),
+                PatternEvent::Quit if self.anchor.is_some() => self.anchor = None,
                 PatternEvent::Quit => { let _ = self.par_tx.send(TrackerCmd::FocusComponent(None)); },
-                PatternEvent::SmallIncrement => {
-                    if let Some(channel) = ch {
-                        let beat = &mut self.current_pattern_mut()[channel+1][sel_beat];
-                        match lane_kind {
-                            LaneKind::Note => {
-                                let found = beat.cmd_list.iter_mut().rev().find_map(|c| match c {
-                                    ChannelCmd::Note(n) => {
-                                        *n = n.saturating_add(1).min(127); Some(())
-                                    }
-                                    _ => None,
-                                });
-                                if found.is_none() {
-                                    beat.cmd_list.push(ChannelCmd::Note(MidiNote::C4 as u8));
-                                }
-                            }
-                            LaneKind::Vol => todo!(),
-                            _ => { println!("wrong col!") }
-                        }
+                // a block is transposed and its volumes stepped; a lone cell
+                // gets a note or volume if it has none
+                PatternEvent::SmallIncrement if self.anchor.is_some() => self.edit(|ed| {
+                    ed.transpose(1);
+                    ed.step_volumes(1);
+                }),
+                PatternEvent::SmallDecrement if self.anchor.is_some() => self.edit(|ed| {
+                    ed.transpose(-1);
+                    ed.step_volumes(-1);
+                }),
+                PatternEvent::SmallIncrement => self.edit(|ed| ed.nudge(1)),
+                PatternEvent::SmallDecrement => self.edit(|ed| ed.nudge(-1)),
+                PatternEvent::TransposeUp => self.edit(|ed| ed.transpose(12)),
+                PatternEvent::TransposeDown => self.edit(|ed| ed.transpose(-12)),
+                PatternEvent::Copy => self.copy(),
+                PatternEvent::Cut => {
+                    self.copy();
+                    self.edit(Self::clear);
+                }
+                PatternEvent::Paste => self.edit(Self::paste),
+                PatternEvent::Piano(semitone) => {
+                    let note = (self.octave + 1) * 12 + semitone;
+                    if note <= 127 {
+                        self.edit(|ed| ed.enter_note(note, None));
                     }
                 }
-                PatternEvent::SmallDecrement => todo!(),
+                PatternEvent::MidiNote(note, velocity) => {
+                    // velocity 1-127 over volumes 1-16
+                    let volume = velocity.map(|v| ((v.min(127) as u16 * VOLUME_MAX as u16).div_ceil(127)) as u8);
+                    self.edit(|ed| ed.enter_note(note.min(127), volume));
+                }
+                PatternEvent::Clear => self.edit(Self::clear),
+                PatternEvent::OctaveDown => self.octave = self.octave.saturating_sub(1),
+                PatternEvent::OctaveUp => self.octave = (self.octave + 1).min(MAX_OCTAVE),
+                PatternEvent::StepDown => self.edit_step = self.edit_step.saturating_sub(1),
+                PatternEvent::StepUp => self.edit_step = (self.edit_step + 1).min(MAX_EDIT_STEP),
+                PatternEvent::Type(c) => match self.selected_lane().0 {
+                    LaneKind::Fx | LaneKind::Seq => self.type_command(c),
+                    LaneKind::Vol => self.type_volume(c),
+                    _ => {}
+                },
+                PatternEvent::Ramp => self.edit(Self::ramp),
+                PatternEvent::Mute | PatternEvent::Solo => {
+                    let Some(ch) = self.selected_lane().1 else { continue };
+                    let mut data = self.tracker_data.borrow_mut();
+                    let flags = match event {
+                        PatternEvent::Mute => &mut data.muted,
+                        _ => &mut data.soloed,
+                    };
+                    flags[ch] = !flags[ch];
+                }
             }
         }
     }
 
     fn render(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let [info_area, area] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        let mut info = format!(" octave {}  step {}", self.octave, self.edit_step);
+        if self.anchor.is_some() {
+            let (columns, rows) = self.block();
+            info.push_str(&format!("  block {}x{}", columns.count(), rows.count()));
+        }
+        if let Some(clip) = &self.clip {
+            info.push_str(&format!("  clip {}x{}", clip.kinds.len(), clip.columns.first().map_or(0, Vec::len)));
+        }
+        match (&self.error, self.selected_lane().0) {
+            (Some(e), _) => info.push_str(&format!("  {}", e)),
+            (None, LaneKind::Fx) => info.push_str("   V vibrato  T tremolo  W wave  P phase  U D pitch slide  L Q volume slide"),
+            (None, LaneKind::Seq) => info.push_str("   Txx tempo, in hex bpm"),
+            (None, LaneKind::Vol) => info.push_str("   0-F + volume  ^↑ ^↓ step  ^R ramp the selection"),
+            (None, _) => info.push_str("   [ ] octave  - = step  del clear  ^C ^X ^V  alt+M alt+S mute/solo"),
+        }
+        frame.render_widget(Line::from(info).fg(SCHEME.gray[2]), info_area);
+
         let table_width = self.lanes.iter().map(|l| l.width).sum();
         let lower_layouts = Layout::default().constraints([
             Constraint::Fill(1),
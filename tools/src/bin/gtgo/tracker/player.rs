@@ -0,0 +1,306 @@
+//! The SDK's music player (`gametank::audio::music::Music`), for the host
+//!
+//! Steps through a song a frame at a time and writes the voices into audio
+//! RAM the same way the ROM would, so the preview sounds like the console.
+//! Keep it in step with the SDK's.
+
+use gametank_sdk::song::{cmd, MAGIC, MIN_BPM, VERSION};
+use gt_audio_abi::wavetable_8ch::{self as abi, voice};
+
+/// The SDK's pitch table: each MIDI note's frequency increment at the
+/// firmware's ~14kHz
+const MIDI_INCREMENTS: [u16; 128] = {
+    const FS: u64 = 13_983;
+    const SEMITONE_RATIO_Q16: u64 = 69_433;
+    let mut table = [0; 128];
+    let mut freq_q16: u64 = 535_400;
+    let mut i = 0;
+    while i < 128 {
+        table[i] = ((freq_q16 + FS / 2) / FS) as u16;
+        freq_q16 = (freq_q16 * SEMITONE_RATIO_Q16) >> 16;
+        i += 1;
+    }
+    table
+};
+
+/// A triangle between `-depth` and `depth`
+#[derive(Debug, Copy, Clone, Default)]
+struct Lfo {
+    depth: u8,
+    speed: u8,
+    phase: u8,
+}
+
+impl Lfo {
+    fn step(&mut self) -> i16 {
+        if self.depth == 0 {
+            return 0;
+        }
+        self.phase = self.phase.wrapping_add(self.speed);
+        let triangle = if self.phase < 128 { self.phase as i16 } else { 255 - self.phase as i16 } - 64;
+        triangle * self.depth as i16 / 64
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Channel {
+    frequency: u16,
+    volume: u8,
+    wavetable: u16,
+    vibrato: Lfo,
+    tremolo: Lfo,
+    pitch_slide: i16,
+    pitch_slide_frames: u8,
+    volume_slide: i8,
+    volume_slide_frames: u8,
+    phase: Option<u16>,
+}
+
+impl Channel {
+    fn step(&mut self) -> (u16, u8) {
+        if self.pitch_slide != 0 {
+            self.frequency = self.frequency.wrapping_add_signed(self.pitch_slide);
+            if self.pitch_slide_frames > 0 {
+                self.pitch_slide_frames -= 1;
+                if self.pitch_slide_frames == 0 {
+                    self.pitch_slide = 0;
+                }
+            }
+        }
+        if self.volume_slide != 0 {
+            self.volume = self.volume.saturating_add_signed(self.volume_slide);
+            if self.volume_slide_frames > 0 {
+                self.volume_slide_frames -= 1;
+                if self.volume_slide_frames == 0 {
+                    self.volume_slide = 0;
+                }
+            }
+        }
+
+        let vibrato = self.vibrato.step();
+        let frequency = self.frequency.wrapping_add_signed(vibrato * (self.frequency >> 8) as i16 / 8);
+        let volume = (self.volume as i16 + self.tremolo.step()).max(0) as u8;
+        (frequency, if self.frequency == 0 { 0 } else { volume })
+    }
+}
+
+pub struct Player {
+    song: Vec<u8>,
+    playing: bool,
+    channels: [Channel; abi::VOICE_COUNT],
+    order_at: u16,
+    order_len: u8,
+    order: u8,
+    at: u16,
+    wait_rows: u8,
+    frames_left: u8,
+    frames_per_row: u8,
+    swing: u8,
+    break_to: u8,
+    current: u8,
+    /// The row playing, and the one after it, for showing where the song is
+    row: u8,
+    next_row: u8,
+}
+
+impl Player {
+    /// Start `song` from the top, if it's one the player can read
+    pub fn new(song: Vec<u8>) -> Option<Self> {
+        if song.len() <= 7 || &song[..3] != MAGIC || !(1..=VERSION).contains(&song[3]) {
+            return None;
+        }
+        let mut player = Self {
+            order_len: song[6],
+            song,
+            playing: false,
+            channels: [Channel::default(); abi::VOICE_COUNT],
+            order_at: 7,
+            order: 0,
+            at: 0,
+            wait_rows: 0,
+            frames_left: 0,
+            frames_per_row: 1,
+            swing: 0,
+            break_to: 0,
+            current: 0,
+            row: 0,
+            next_row: 0,
+        };
+        player.set_tempo(player.song[4]);
+        player.playing = player.order_len > 0 && player.start_pattern(0, 0);
+        player.next_row = 0;
+        Some(player)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Which entry of the order and which row of it are playing
+    pub fn position(&self) -> (u8, u8) {
+        (self.order, self.row)
+    }
+
+    fn set_tempo(&mut self, bpm: u8) {
+        self.frames_per_row = (3600 / bpm.max(MIN_BPM) as u16) as u8;
+    }
+
+    /// Even rows go long and odd rows short, with swing
+    fn row_frames(&self) -> u8 {
+        let swing = ((self.frames_per_row as u16 * self.swing as u16) >> 8) as u8;
+        if self.row & 1 == 0 {
+            self.frames_per_row.saturating_add(swing)
+        } else {
+            self.frames_per_row.saturating_sub(swing).max(1)
+        }
+    }
+
+    fn byte(&self, at: u16) -> u8 {
+        self.song.get(at as usize).copied().unwrap_or(cmd::END)
+    }
+
+    fn word(&self, at: u16) -> u16 {
+        u16::from_le_bytes([self.byte(at), self.byte(at + 1)])
+    }
+
+    fn start_pattern(&mut self, order: u8, row: u8) -> bool {
+        let order = if order >= self.order_len { 0 } else { order };
+        let pattern = self.byte(self.order_at + order as u16);
+        let patterns_at = self.order_at + self.order_len as u16;
+        if pattern >= self.byte(patterns_at) {
+            return false;
+        }
+        self.order = order;
+        self.at = self.word(patterns_at + 1 + pattern as u16 * 2);
+        self.wait_rows = 0;
+        self.frames_left = 0;
+        self.row = row;
+        self.next_row = row.wrapping_add(1);
+
+        let mut rows = row;
+        while rows > 0 {
+            match self.byte(self.at) {
+                cmd::WAIT => {
+                    let wait = self.byte(self.at + 1);
+                    self.at += 2;
+                    if wait > rows {
+                        self.wait_rows = wait - rows;
+                        break;
+                    }
+                    rows -= wait;
+                }
+                cmd::END => break,
+                command => self.at += 1 + Self::params(command),
+            }
+        }
+        true
+    }
+
+    fn params(command: u8) -> u16 {
+        match command {
+            cmd::VOLUME | cmd::TEMPO | cmd::JUMP | cmd::BREAK_TO | cmd::SWING | cmd::WAIT => 1,
+            cmd::WAVETABLE | cmd::PHASE | cmd::VIBRATO | cmd::TREMOLO | cmd::SLIDE_VOLUME => 2,
+            cmd::SLIDE_PITCH => 3,
+            _ => 0,
+        }
+    }
+
+    fn read_row(&mut self) {
+        for _ in 0..255 {
+            let command = self.byte(self.at);
+            let [a, b, c] = [1, 2, 3].map(|i| self.byte(self.at + i));
+            self.at += 1 + Self::params(command);
+
+            let channel = &mut self.channels[self.current as usize];
+            match command {
+                0..=cmd::NOTE_MAX => {
+                    channel.frequency = MIDI_INCREMENTS[command as usize];
+                    channel.pitch_slide = 0;
+                }
+                0x80..=0x87 => self.current = (command - cmd::CHANNEL).min(abi::VOICE_COUNT as u8 - 1),
+                cmd::VOLUME => channel.volume = a,
+                cmd::WAVETABLE => channel.wavetable = u16::from_le_bytes([a, b]),
+                cmd::PHASE => channel.phase = Some(u16::from_le_bytes([a, b])),
+                cmd::VIBRATO => channel.vibrato = Lfo { depth: a, speed: b, phase: 0 },
+                cmd::TREMOLO => channel.tremolo = Lfo { depth: a, speed: b, phase: 0 },
+                cmd::SLIDE_PITCH => {
+                    channel.pitch_slide_frames = a;
+                    channel.pitch_slide = i16::from_le_bytes([b, c]);
+                }
+                cmd::SLIDE_VOLUME => {
+                    channel.volume_slide_frames = a;
+                    channel.volume_slide = b as i8;
+                }
+                cmd::STOP_PITCH_SLIDE => channel.pitch_slide = 0,
+                cmd::STOP_VOLUME_SLIDE => channel.volume_slide = 0,
+                cmd::NOTE_OFF => channel.frequency = 0,
+                cmd::TEMPO => self.set_tempo(a),
+                cmd::BREAK_TO => self.break_to = a,
+                cmd::SWING => self.swing = a,
+                cmd::WAIT => {
+                    self.wait_rows = a.max(1);
+                    return;
+                }
+                cmd::STOP => {
+                    self.playing = false;
+                    for channel in &mut self.channels {
+                        channel.frequency = 0;
+                    }
+                    return;
+                }
+                cmd::JUMP | cmd::ADVANCE | cmd::END => {
+                    let (order, row) = match command {
+                        cmd::JUMP => (a, 0),
+                        _ => (self.order + 1, std::mem::take(&mut self.break_to)),
+                    };
+                    self.playing = self.start_pattern(order, row);
+                    if !self.playing || self.wait_rows > 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Move the song along a frame and write the voices into `aram`
+    pub fn update(&mut self, aram: &mut [u8]) {
+        if self.playing && self.frames_left == 0 {
+            self.row = self.next_row;
+            self.next_row = self.next_row.wrapping_add(1);
+            if self.wait_rows == 0 {
+                self.read_row();
+            }
+            self.wait_rows = self.wait_rows.saturating_sub(1);
+            self.frames_left = self.row_frames();
+        }
+        self.frames_left = self.frames_left.saturating_sub(1);
+
+        if !self.playing {
+            for channel in &mut self.channels {
+                channel.frequency = 0;
+            }
+        }
+        self.write_voices(aram);
+    }
+
+    fn write_voices(&mut self, aram: &mut [u8]) {
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let (frequency, volume) = channel.step();
+            let base = (abi::VOICE_BASE + abi::VOICE_SIZE * index as u16) as usize;
+            let mut write = |offset: u16, bytes: &[u8]| {
+                let at = base + offset as usize;
+                aram[at..at + bytes.len()].copy_from_slice(bytes);
+            };
+
+            write(voice::FREQUENCY, &frequency.to_le_bytes());
+            write(voice::VOLUME, &[volume]);
+            if channel.wavetable != 0 {
+                write(voice::WAVETABLE, &channel.wavetable.to_le_bytes());
+            }
+            if let Some(phase) = channel.phase.take() {
+                write(voice::PHASE, &phase.to_le_bytes());
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Hearing the module as the console would play it
+//!
+//! The song runs through [`Player`], the SDK's music player, which writes
+//! the voices of the 8-voice wavetable firmware running on an emulated ACP.
+//! The samples it writes to the DAC go through gte's resampler and DAC
+//! filter and out through cpal.
+//!
+//! A [`Render`] runs the same way offline, as fast as it can, into a WAV.
+
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicU32, Ordering}, Arc}, thread::{self, JoinHandle}, time::Duration};
+
+use cpal::{traits::{DeviceTrait, HostTrait, StreamTrait}, BuildStreamError, Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use dasp_graph::Buffer;
+use gt_audio_abi::ARAM_SIZE;
+use gte_acp::{audio_output::{AudioSettings, GameTankAudio}, AcpBus};
+use gte_w65c02s::W65C02S;
+use rtrb::{Consumer, Producer, RingBuffer};
+
+use crate::tracker::{export, player::Player, song::wavetable_address, wavetable::FIRST_SLOT};
+
+/// The firmware the SDK plays music with, relative to a project
+const FIRMWARE: &str = "gametank/audiofw/wavetable-8ch.bin";
+
+/// Main CPU clock; the ACP runs at four times it
+const CPU_HZ: f64 = 3_579_545.0;
+/// CPU cycles a frame
+const FRAME_CYCLES: i32 = 59_659;
+/// What the SDK's `set_audio(0xFF)` writes: an IRQ, and a sample, every
+/// 255 * 4 ACP cycles
+const SAMPLE_RATE_REG: u8 = 0xFF;
+
+/// Device buffers to keep queued: enough to ride out a slow frame
+const QUEUED_BUFFERS: usize = 48;
+
+/// A render's sample rate
+const WAV_RATE: u32 = 44_100;
+/// Longest a render goes, for songs that neither stop nor loop
+const MAX_RENDER_SECONDS: u32 = 10 * 60;
+
+/// Look for the firmware from the working directory up, for when gtgo runs
+/// somewhere inside a project (or this repository)
+pub fn find_firmware() -> Result<Vec<u8>, String> {
+    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+    let found = cwd.ancestors()
+        .flat_map(|dir| [dir.join(FIRMWARE), dir.join("sdk-template").join(FIRMWARE)])
+        .find(|path| path.exists());
+    let Some(path) = found else {
+        return Err(format!("couldn't find {}; run gtgo from a GameTank project to hear it", FIRMWARE));
+    };
+    read_firmware(&path)
+}
+
+fn read_firmware(path: &Path) -> Result<Vec<u8>, String> {
+    let firmware = std::fs::read(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    if firmware.len() != ARAM_SIZE as usize {
+        return Err(format!("{} is {} bytes, not a {}-byte firmware image", path.display(), firmware.len(), ARAM_SIZE));
+    }
+    Ok(firmware)
+}
+
+/// The audio coprocessor, with its clock and IRQ timer
+pub struct Acp {
+    cpu: W65C02S,
+    bus: AcpBus,
+    cycles: i32,
+}
+
+impl Acp {
+    /// Load `firmware` into audio RAM and start it, as the SDK's audio setup does
+    pub fn boot(firmware: &[u8]) -> Self {
+        let mut bus = AcpBus::default();
+        bus.aram.copy_from_slice(firmware);
+        let mut cpu = W65C02S::new();
+        cpu.reset();
+        Self { cpu, bus, cycles: 0 }
+    }
+
+    pub fn sample_rate() -> f64 {
+        CPU_HZ / SAMPLE_RATE_REG as f64
+    }
+
+    /// Put `bytes` in audio RAM at `address`, as much as fits
+    pub fn write(&mut self, address: u16, bytes: &[u8]) {
+        let aram = &mut self.bus.aram[address as usize..];
+        let len = bytes.len().min(aram.len());
+        aram[..len].copy_from_slice(&bytes[..len]);
+    }
+
+    /// Run a frame of `player` and the firmware, passing each sample the
+    /// firmware plays to `out`
+    pub fn frame(&mut self, player: &mut Player, mut out: impl FnMut(u8)) {
+        player.update(&mut self.bus.aram[..]);
+
+        // as gte's emulator loop runs it
+        self.cycles += FRAME_CYCLES * 4;
+        while self.cycles > 0 {
+            let cycles = self.cpu.step(&mut self.bus);
+            self.cycles -= cycles;
+            self.bus.irq_counter -= cycles;
+            self.cpu.set_irq(false);
+            self.cpu.set_nmi(false);
+
+            if self.bus.irq_counter <= 0 {
+                self.bus.irq_counter = SAMPLE_RATE_REG as i32 * 4;
+                self.cpu.set_irq(true);
+                out(self.bus.sample);
+            }
+        }
+    }
+}
+
+/// Play `consumer`'s buffers on `device`, converting to the sample type it wants
+fn build_stream<T: SizedSample + FromSample<f32>>(device: &Device, config: &StreamConfig, mut consumer: Consumer<Buffer>) -> Result<Stream, BuildStreamError> {
+    let channels = config.channels as usize;
+    let mut current = Buffer::SILENT;
+    let mut position = Buffer::LEN;
+    let mut last = 0.0;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for frame in data.chunks_mut(channels) {
+                if position >= Buffer::LEN {
+                    if let Ok(next) = consumer.pop() {
+                        current = next;
+                        position = 0;
+                    }
+                }
+                // hold the last sample when there's nothing new, which
+                // is silence without a pop
+                if position < Buffer::LEN {
+                    last = current[position];
+                    position += 1;
+                }
+                frame.fill(T::from_sample(last));
+            }
+        },
+        |_| {},
+        None,
+    )
+}
+
+enum PreviewCmd {
+    Play(Vec<u8>, Vec<u8>),
+    Stop,
+    Quit,
+}
+
+/// Playing bit, order entry and row, packed for sharing with the UI
+const PLAYING: u32 = 1 << 16;
+
+pub struct Preview {
+    tx: Sender<PreviewCmd>,
+    position: Arc<AtomicU32>,
+    thread: Option<JoinHandle<()>>,
+    _stream: Stream,
+}
+
+impl Preview {
+    /// Open the default output device and start the preview thread
+    pub fn new() -> Result<Self, String> {
+        let firmware = find_firmware()?;
+
+        let device = cpal::default_host().default_output_device().ok_or("no audio output device")?;
+        let default_config = device.default_output_config().map_err(|e| format!("no audio output config: {}", e))?;
+        let sample_rate = default_config.sample_rate().0;
+        let config = StreamConfig {
+            channels: default_config.channels(),
+            sample_rate: default_config.sample_rate(),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        let (producer, consumer) = RingBuffer::<Buffer>::new(QUEUED_BUFFERS);
+        let stream = match default_config.sample_format() {
+            SampleFormat::I8 => build_stream::<i8>(&device, &config, consumer),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, consumer),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, consumer),
+            SampleFormat::I64 => build_stream::<i64>(&device, &config, consumer),
+            SampleFormat::U8 => build_stream::<u8>(&device, &config, consumer),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, consumer),
+            SampleFormat::U32 => build_stream::<u32>(&device, &config, consumer),
+            SampleFormat::U64 => build_stream::<u64>(&device, &config, consumer),
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, consumer),
+            SampleFormat::F64 => build_stream::<f64>(&device, &config, consumer),
+            format => return Err(format!("the audio output wants {} samples, which aren't supported", format)),
+        }.map_err(|e| format!("couldn't open audio output: {}", e))?;
+        stream.play().map_err(|e| format!("couldn't start audio output: {}", e))?;
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let shared = Arc::new(AtomicU32::new(0));
+        let position = shared.clone();
+        let thread = thread::spawn(move || run(rx, firmware, producer, sample_rate as f64, shared));
+
+        Ok(Self { tx, position, thread: Some(thread), _stream: stream })
+    }
+
+    /// Play a song from the top, in place of anything playing, with
+    /// `wavetables` loaded at the module's first slot
+    pub fn play(&self, song: Vec<u8>, wavetables: Vec<u8>) {
+        let _ = self.tx.send(PreviewCmd::Play(song, wavetables));
+    }
+
+    pub fn stop(&self) {
+        let _ = self.tx.send(PreviewCmd::Stop);
+    }
+
+    /// The order entry and row playing, if anything is
+    pub fn position(&self) -> Option<(u8, u8)> {
+        let position = self.position.load(Ordering::Relaxed);
+        (position & PLAYING != 0).then_some(((position >> 8) as u8, position as u8))
+    }
+}
+
+impl Drop for Preview {
+    /// Wait for the thread to let go of the ACP, so a render can have it
+    fn drop(&mut self) {
+        let _ = self.tx.send(PreviewCmd::Quit);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(rx: Receiver<PreviewCmd>, firmware: Vec<u8>, mut device: Producer<Buffer>, device_rate: f64, position: Arc<AtomicU32>) {
+    let mut audio = GameTankAudio::new(Acp::sample_rate(), device_rate, AudioSettings::default());
+    let mut playing: Option<(Acp, Player)> = None;
+
+    loop {
+        match rx.try_recv() {
+            Ok(PreviewCmd::Play(song, wavetables)) => {
+                // a fresh ACP each time, so nothing carries over
+                playing = Player::new(song).map(|player| {
+                    let mut acp = Acp::boot(&firmware);
+                    acp.write(wavetable_address(FIRST_SLOT), &wavetables);
+                    (acp, player)
+                });
+            }
+            Ok(PreviewCmd::Stop) => playing = None,
+            Ok(PreviewCmd::Quit) | Err(TryRecvError::Disconnected) => return,
+            Err(TryRecvError::Empty) => {}
+        }
+
+        match &mut playing {
+            Some((acp, player)) => {
+                // a frame's samples at a time, while there's room for them
+                while device.slots() >= QUEUED_BUFFERS / 2 {
+                    acp.frame(player, |sample| { let _ = audio.producer.push(sample); });
+                    audio.convert_to_output_buffers();
+                    while let Ok(buffer) = audio.output_buffer.pop() {
+                        let _ = device.push(buffer);
+                    }
+                }
+
+                let (order, row) = player.position();
+                position.store(PLAYING | (order as u32) << 8 | row as u32, Ordering::Relaxed);
+                if !player.is_playing() {
+                    playing = None;
+                }
+            }
+            None => position.store(0, Ordering::Relaxed),
+        }
+
+        thread::sleep(Duration::from_millis(4));
+    }
+}
+
+
+/// A song rendering to a WAV in the background
+pub struct Render {
+    pub path: PathBuf,
+    seconds: Arc<AtomicU32>,
+    thread: JoinHandle<Result<u32, String>>,
+}
+
+impl Render {
+    /// Start rendering `song` to `path`, with `wavetables` loaded at the
+    /// module's first slot. Drop the [`Preview`] first: they'd share an ACP
+    pub fn start(song: Vec<u8>, wavetables: Vec<u8>, path: PathBuf) -> Result<Self, String> {
+        let firmware = find_firmware()?;
+        let player = Player::new(song).ok_or("the song has nothing to play")?;
+
+        let seconds = Arc::new(AtomicU32::new(0));
+        let shared = seconds.clone();
+        let out = path.clone();
+        let thread = thread::spawn(move || render(&firmware, player, &wavetables, &out, &shared));
+
+        Ok(Self { path, seconds, thread })
+    }
+
+    /// How much of the song is rendered
+    pub fn seconds(&self) -> u32 {
+        self.seconds.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the render; returns how long the WAV is, in seconds
+    pub fn finish(self) -> Result<u32, String> {
+        self.thread.join().unwrap_or_else(|_| Err("the render crashed".to_string()))
+    }
+}
+
+/// Play the song once through, until it stops or goes back to somewhere it's
+/// been, and write it to `path`
+fn render(firmware: &[u8], mut player: Player, wavetables: &[u8], path: &Path, seconds: &AtomicU32) -> Result<u32, String> {
+    let mut acp = Acp::boot(firmware);
+    acp.write(wavetable_address(FIRST_SLOT), wavetables);
+    let mut audio = GameTankAudio::new(Acp::sample_rate(), WAV_RATE as f64, AudioSettings::default());
+
+    let frame_rate = (CPU_HZ / FRAME_CYCLES as f64).round() as u32;
+    let mut samples: Vec<i16> = vec![];
+    let mut last = player.position();
+    for frame in 1..=MAX_RENDER_SECONDS * frame_rate {
+        let len = samples.len();
+        acp.frame(&mut player, |sample| { let _ = audio.producer.push(sample); });
+        audio.convert_to_output_buffers();
+        while let Ok(buffer) = audio.output_buffer.pop() {
+            samples.extend(buffer.iter().map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16));
+        }
+
+        // a jump back, or the order wrapping around, is the song looping,
+        // and this frame is already the start of its second time through
+        let position = player.position();
+        if position < last {
+            samples.truncate(len);
+            break;
+        }
+        last = position;
+        seconds.store(frame / frame_rate, Ordering::Relaxed);
+        if !player.is_playing() {
+            break;
+        }
+    }
+
+    std::fs::write(path, export::wav(WAV_RATE, &samples))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+    Ok(samples.len() as u32 / WAV_RATE)
+}
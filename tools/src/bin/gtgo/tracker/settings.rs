@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+const MAX_RECENT: usize = 9;
+const SETTINGS_FILE: &str = "gtgo.conf";
+
+/// Tracker settings and recently saved and opened modules, newest first,
+/// persisted between runs
+pub struct Settings {
+    pub recent: Vec<PathBuf>,
+    /// Name of the MIDI input port to enter notes from
+    pub midi_input: Option<String>,
+    /// Put MIDI note velocities in the volume lane
+    pub midi_velocity: bool,
+}
+
+impl Settings {
+    pub fn gametank_dir(base: Option<PathBuf>) -> Option<PathBuf> {
+        base.map(|dir| dir.join("gametank"))
+    }
+
+    fn settings_path() -> Option<PathBuf> {
+        Self::gametank_dir(dirs::config_dir()).map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    /// Reads `midi_input = <port>`, `midi_velocity = true|false` and
+    /// `recent = <path>` lines
+    pub fn load() -> Self {
+        let mut settings = Self { recent: vec![], midi_input: None, midi_velocity: true };
+        let Some(text) = Self::settings_path().and_then(|p| std::fs::read_to_string(p).ok()) else {
+            return settings;
+        };
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key.trim() {
+                "midi_input" => settings.midi_input = Some(value.trim().to_string()),
+                "midi_velocity" => settings.midi_velocity = value.trim() == "true",
+                "recent" => settings.recent.push(PathBuf::from(value.trim())),
+                _ => {}
+            }
+        }
+        settings.recent.truncate(MAX_RECENT);
+        settings
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::settings_path() else { return };
+
+        let mut text = format!("midi_velocity = {}\n", self.midi_velocity);
+        if let Some(port) = &self.midi_input {
+            text.push_str(&format!("midi_input = {}\n", port));
+        }
+        for module in &self.recent {
+            text.push_str(&format!("recent = {}\n", module.display()));
+        }
+
+        // nowhere to report it from here, and losing the settings is harmless
+        let _ = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, text));
+    }
+
+    /// Move `path` to the front of the recent list and save
+    pub fn push_recent(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.recent.retain(|p| *p != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+        self.save();
+    }
+}
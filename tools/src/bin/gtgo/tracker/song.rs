@@ -0,0 +1,166 @@
+//! The module as a song for the SDK's music player (`gametank::audio::music`)
+//!
+//! Tracker commands mostly map one to one onto the song's. The exceptions:
+//! slides are timed in beats here and in frames there, so they're converted
+//! at the tempo they start at; `Pattern` jumps to the first place the order
+//! plays that pattern; and `Load` has no song equivalent, so it's left out.
+//! Muted channels, when mutes apply, are left out altogether and start
+//! silent. Swing is a percentage of a row here and 256ths of one there.
+
+use gametank_sdk::song::{ChannelCommand, PatternWriter, Song, SongCommand, MAX_CHANNELS, MAX_SWING, MIN_BPM};
+use gt_audio_abi::wavetable_8ch as abi;
+
+use crate::tracker::{ChannelCmd, Pattern, SequencerCmd, TrackerData, CHANNELS, PATTERN_ROWS};
+
+/// Tracker volumes run 0 to this; the firmware's to `abi::VOLUME_MAX`
+pub const VOLUME_MAX: u8 = 16;
+
+/// Swing runs 0% to this, the player's most
+pub const MAX_SWING_PERCENT: u8 = (MAX_SWING as u16 * 100 / 256) as u8;
+
+/// A tracker volume on the firmware's scale
+pub fn firmware_volume(volume: u8) -> u8 {
+    let (volume, max) = (volume.min(VOLUME_MAX) as u16, VOLUME_MAX as u16);
+    ((volume * abi::VOLUME_MAX as u16 + max / 2) / max) as u8
+}
+
+/// ACP-side address of wavetable slot `slot`
+pub fn wavetable_address(slot: u16) -> u16 {
+    abi::WAVETABLE_BASE + abi::WAVETABLE_SIZE * slot.min(abi::WAVETABLE_COUNT as u16 - 1)
+}
+
+/// An audition's two seconds: two rows a second
+const AUDITION_BPM: u8 = 120;
+const AUDITION_ROWS: u16 = 4;
+
+/// Frames `beats` rows take at `bpm`, as the player counts them
+fn frames(beats: u8, bpm: u8) -> u8 {
+    (beats as u32 * (3600 / bpm.max(MIN_BPM) as u32)).min(255) as u8
+}
+
+fn channel_command(cmd: &ChannelCmd, bpm: u8) -> ChannelCommand {
+    match *cmd {
+        ChannelCmd::Tremolo(depth, speed) => ChannelCommand::Tremolo { depth, speed },
+        ChannelCmd::Vibrato(depth, speed) => ChannelCommand::Vibrato { depth, speed },
+        ChannelCmd::Wavetable(slot) => ChannelCommand::Wavetable(wavetable_address(slot)),
+        ChannelCmd::Phase(phase) => ChannelCommand::Phase(phase),
+        ChannelCmd::Note(note) => ChannelCommand::Note(note),
+        ChannelCmd::Volume(volume) => ChannelCommand::Volume(firmware_volume(volume)),
+        ChannelCmd::SlideVol(beats, delta) => ChannelCommand::SlideVolume {
+            frames: frames(beats, bpm),
+            delta: delta.clamp(i8::MIN as i16, i8::MAX as i16) as i8,
+        },
+        ChannelCmd::StopVSlide => ChannelCommand::StopVolumeSlide,
+        ChannelCmd::SlidePitch(beats, delta) => ChannelCommand::SlidePitch { frames: frames(beats, bpm), delta },
+        ChannelCmd::StopPSlide => ChannelCommand::StopPitchSlide,
+    }
+}
+
+/// Write one pattern, starting at `bpm`, with only the `audible` channels;
+/// returns the tempo it ends on
+fn write_pattern(
+    data: &TrackerData,
+    pattern: &Pattern,
+    order: &[usize],
+    first: bool,
+    audible: &[bool; CHANNELS],
+    mut bpm: u8,
+) -> (Vec<u8>, u8) {
+    let mut writer = PatternWriter::new();
+
+    // channels start on their wavetables, at full volume
+    if first {
+        if data.swing > 0 {
+            let amount = (data.swing.min(MAX_SWING_PERCENT) as u16 * 256 / 100) as u8;
+            writer.song(SongCommand::Swing(amount));
+        }
+        for (ch, audible) in audible.iter().enumerate() {
+            let volume = if *audible { abi::VOLUME_MAX } else { 0 };
+            writer.channel(ch as u8, ChannelCommand::Wavetable(wavetable_address(data.wavetables[ch] as u16)));
+            writer.channel(ch as u8, ChannelCommand::Volume(volume));
+        }
+    }
+
+    for (row, beat) in pattern[0].iter().enumerate() {
+        writer.seek(row as u16);
+        let sequencer = &beat.sqc_list;
+
+        // tempo first, so the row's slides are timed at it
+        for cmd in sequencer {
+            if let SequencerCmd::Tempo(tempo) = *cmd {
+                bpm = tempo;
+                writer.song(SongCommand::Tempo(tempo));
+            }
+        }
+
+        for ch in (0..CHANNELS).filter(|ch| audible[*ch]) {
+            for cmd in &pattern[ch + 1][row].cmd_list {
+                writer.channel(ch as u8, channel_command(cmd, bpm));
+            }
+        }
+
+        // and anything that moves on to another pattern last
+        for cmd in sequencer {
+            let command = match *cmd {
+                SequencerCmd::Pattern(p) => order.iter().position(|o| *o == p as usize).map(|i| SongCommand::Jump(i as u8)),
+                SequencerCmd::Beat(row) => Some(SongCommand::BreakTo(row)),
+                SequencerCmd::Advance => Some(SongCommand::Advance),
+                SequencerCmd::Stop => Some(SongCommand::Stop),
+                SequencerCmd::Tempo(_) | SequencerCmd::Load(..) => None,
+            };
+            if let Some(command) = command {
+                writer.song(command);
+            }
+        }
+    }
+
+    (writer.finish(PATTERN_ROWS as u16), bpm)
+}
+
+/// A song that plays `note` on wavetable slot `slot` for a couple of
+/// seconds, to hear what the wavetable sounds like
+pub fn audition(slot: u16, note: u8) -> Song {
+    let mut writer = PatternWriter::new();
+    writer.channel(0, ChannelCommand::Wavetable(wavetable_address(slot)));
+    writer.channel(0, ChannelCommand::Volume(abi::VOLUME_MAX));
+    writer.channel(0, ChannelCommand::Note(note));
+    writer.seek(AUDITION_ROWS - 1);
+    writer.song(SongCommand::Stop);
+
+    Song {
+        bpm: AUDITION_BPM,
+        channels: MAX_CHANNELS,
+        order: vec![0],
+        patterns: vec![writer.finish(AUDITION_ROWS)],
+    }
+}
+
+/// The song that plays the patterns in `order`, with the channels muted
+/// and soloed if `mutes`. Only patterns the order plays are included.
+pub fn song(data: &TrackerData, order: &[usize], mutes: bool) -> Result<Song, String> {
+    if let Some(missing) = order.iter().find(|p| **p >= data.patterns.len()) {
+        return Err(format!("the order plays pattern {}, but there are only {}", missing, data.patterns.len()));
+    }
+
+    let audible = std::array::from_fn(|ch| !mutes || data.audible(ch));
+    let mut used: Vec<usize> = vec![];
+    let mut patterns = vec![];
+    let mut bpm = data.tempo;
+    for &p in order {
+        if used.contains(&p) {
+            continue;
+        }
+        let (bytes, end_bpm) = write_pattern(data, &data.patterns[p], order, used.is_empty(), &audible, bpm);
+        used.push(p);
+        patterns.push(bytes);
+        bpm = end_bpm;
+    }
+
+    Ok(Song {
+        bpm: data.tempo,
+        channels: MAX_CHANNELS,
+        order: order.iter().map(|p| used.iter().position(|u| u == p).unwrap() as u8).collect(),
+        patterns,
+    })
+}
+
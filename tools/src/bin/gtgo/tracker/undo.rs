@@ -0,0 +1,99 @@
+//! Undo and redo
+//!
+//! Every edit is kept as what it changed, before and after, so undoing it
+//! puts the old back and redoing it the new. Editors make their change and
+//! then record it; see `PatternEditor::edit`.
+
+use crate::tracker::{wavetable::Wavetable, Beat, Pattern, TrackerData, PATTERN_ROWS};
+
+/// Edits to keep; the oldest are forgotten past this
+const MAX_UNDO: usize = 256;
+
+pub struct CellEdit {
+    lane: usize,
+    row: usize,
+    before: Beat,
+    after: Beat,
+}
+
+/// A change to the module that can be undone and redone
+pub enum Edit {
+    /// Cells of a pattern
+    Cells { pattern: usize, cells: Vec<CellEdit> },
+    /// One of the module's wavetables
+    Wavetable { table: usize, before: Box<Wavetable>, after: Box<Wavetable> },
+}
+
+impl Edit {
+    /// The cells pattern `pattern` changed going from `before` to `after`,
+    /// if any did
+    pub fn cells(pattern: usize, before: &Pattern, after: &Pattern) -> Option<Self> {
+        let mut cells = vec![];
+        for (lane, (old, new)) in before.iter().zip(after).enumerate() {
+            for row in 0..PATTERN_ROWS {
+                if old[row] != new[row] {
+                    cells.push(CellEdit { lane, row, before: old[row].clone(), after: new[row].clone() });
+                }
+            }
+        }
+        (!cells.is_empty()).then_some(Edit::Cells { pattern, cells })
+    }
+
+    /// Table `table` going from `before` to `after`, if it changed
+    pub fn wavetable(table: usize, before: &Wavetable, after: &Wavetable) -> Option<Self> {
+        (before != after).then(|| Edit::Wavetable { table, before: Box::new(*before), after: Box::new(*after) })
+    }
+
+    fn apply(&self, data: &mut TrackerData, undo: bool) {
+        match self {
+            Edit::Cells { pattern, cells } => {
+                let Some(beats) = data.patterns.get_mut(*pattern) else { return };
+                for cell in cells {
+                    let beat = if undo { &cell.before } else { &cell.after };
+                    beats[cell.lane][cell.row] = beat.clone();
+                }
+                // show where it happened
+                data.pattern = *pattern as u8;
+            }
+            Edit::Wavetable { table, before, after } => {
+                data.tables[*table] = if undo { **before } else { **after };
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    /// Remember `edit`, which has just been made. Anything undone before it
+    /// can't be redone any more.
+    pub fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+        self.undo.push(edit);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+}
+
+impl TrackerData {
+    /// Undo the last edit; false if there's nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.history.undo.pop() else { return false };
+        edit.apply(self, true);
+        self.history.redo.push(edit);
+        true
+    }
+
+    /// Redo the last undone edit; false if there's nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.history.redo.pop() else { return false };
+        edit.apply(self, false);
+        self.history.undo.push(edit);
+        true
+    }
+}
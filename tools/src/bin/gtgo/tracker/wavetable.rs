@@ -0,0 +1,94 @@
+//! The module's own wavetables
+//!
+//! A module has [`TABLES`] single-cycle waveforms, loaded into the 8-voice
+//! firmware's slots from [`FIRST_SLOT`] on; slot 0 is the firmware's
+//! built-in sine. Samples are unsigned and centered on 0x80, like the
+//! `wavetable_*!` macros make them.
+
+use std::f32::consts::TAU;
+
+use gt_audio_abi::wavetable_8ch as abi;
+
+pub const TABLES: usize = 8;
+pub const SIZE: usize = abi::WAVETABLE_SIZE as usize;
+/// Firmware slot of the first table
+pub const FIRST_SLOT: u16 = 1;
+
+/// Peak distance from 0x80 that the firmware's volume table has room for
+pub const AMPLITUDE: u8 = 64;
+
+pub type Wavetable = [u8; SIZE];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Preset {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    Pulse25,
+    Pulse12,
+    Noise,
+    Flat,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 8] = [
+        Preset::Sine, Preset::Triangle, Preset::Saw, Preset::Square,
+        Preset::Pulse25, Preset::Pulse12, Preset::Noise, Preset::Flat,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Sine => "Sine",
+            Preset::Triangle => "Triangle",
+            Preset::Saw => "Saw",
+            Preset::Square => "Square",
+            Preset::Pulse25 => "Pulse 25%",
+            Preset::Pulse12 => "Pulse 12.5%",
+            Preset::Noise => "Noise",
+            Preset::Flat => "Flat",
+        }
+    }
+
+    /// The wave at full amplitude, given a phase of 0.0..1.0
+    fn sample(self, phase: f32, index: usize) -> f32 {
+        let pulse = |duty: f32| if phase < duty { 1.0 } else { -1.0 };
+        match self {
+            Preset::Sine => (phase * TAU).sin(),
+            Preset::Triangle => 1.0 - 4.0 * ((phase + 0.25) % 1.0 - 0.5).abs(),
+            // rising from the middle, like the sine
+            Preset::Saw => ((phase + 0.5) % 1.0) * 2.0 - 1.0,
+            Preset::Square => pulse(0.5),
+            Preset::Pulse25 => pulse(0.25),
+            Preset::Pulse12 => pulse(0.125),
+            // the same noise every time, so a module sounds the same when reopened
+            Preset::Noise => {
+                let x = (index as u32).wrapping_mul(0x9E37_79B9).rotate_left(7) ^ 0x5bd1_e995;
+                (x.wrapping_mul(0x2c1b_3c6d) >> 24) as f32 / 127.5 - 1.0
+            }
+            Preset::Flat => 0.0,
+        }
+    }
+
+    pub fn table(self) -> Wavetable {
+        std::array::from_fn(|i| {
+            let sample = self.sample(i as f32 / SIZE as f32, i);
+            (128.0 + sample.clamp(-1.0, 1.0) * AMPLITUDE as f32).round() as u8
+        })
+    }
+}
+
+/// What a new module starts with
+const DEFAULTS: [Preset; TABLES] = [
+    Preset::Square, Preset::Saw, Preset::Triangle, Preset::Pulse25,
+    Preset::Pulse12, Preset::Noise, Preset::Sine, Preset::Sine,
+];
+
+pub fn default_tables() -> [Wavetable; TABLES] {
+    DEFAULTS.map(Preset::table)
+}
+
+/// The tables as they sit in audio RAM, from [`FIRST_SLOT`]'s address up
+pub fn aram_image(tables: &[Wavetable; TABLES]) -> Vec<u8> {
+    tables.concat()
+}
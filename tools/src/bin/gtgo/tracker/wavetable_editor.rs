@@ -0,0 +1,245 @@
+//! Drawing the module's wavetables, with the keyboard or the mouse
+
+use std::{cell::RefCell, rc::Rc};
+
+use crossbeam_channel::{Receiver, Sender};
+use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}, layout::{Constraint, Layout, Rect}, style::Stylize, symbols::Marker, text::{Line, Span}, widgets::{canvas::{Canvas, Line as CanvasLine}, Block, Borders}};
+
+use crate::{helpers::SCHEME, tracker::{undo::Edit, wavetable::{Preset, Wavetable, FIRST_SLOT, SIZE, TABLES}, Handler, TSub, TrackerCmd, TrackerData}, Component};
+
+#[derive(Clone, Copy)]
+pub enum WaveEvent {
+    Left,
+    Right,
+    FarLeft,
+    FarRight,
+    Up,
+    Down,
+    FarUp,
+    FarDown,
+    Table(usize),
+    PresetMenu,
+    Preset(Preset),
+    Audition,
+    Quit,
+}
+
+/// How far the far moves go, in samples or levels
+const FAR: u8 = 16;
+
+pub struct WavetableEditor {
+    pub table: usize,
+    pub cursor: u8,
+    tracker_data: Rc<RefCell<TrackerData>>,
+    /// Where the wave was last drawn, for the mouse
+    graph: Rect,
+    /// The table as it was when the mouse went down, and the last sample drawn
+    stroke: Option<(Wavetable, u8)>,
+    active_handlers: Vec<Handler>,
+    global_handlers: Vec<Handler>,
+    cx_rx: Receiver<WaveEvent>,
+    cx_tx: Sender<WaveEvent>,
+    par_tx: Sender<TrackerCmd>,
+}
+
+fn key_handler(tx: &Sender<WaveEvent>, code: KeyCode, modifiers: KeyModifiers, cmd: WaveEvent) -> Handler {
+    let txx = tx.clone();
+    Handler { event: Event::Key(KeyEvent::new(code, modifiers)), action: Box::new(move || {
+        let _ = txx.send(cmd);
+    })}
+}
+
+impl WavetableEditor {
+    pub fn init(parent_tx: Sender<TrackerCmd>, tracker_data: Rc<RefCell<TrackerData>>) -> Self {
+        let (cx_tx, cx_rx) = crossbeam_channel::unbounded();
+
+        let key = |code, cmd| key_handler(&cx_tx, code, KeyModifiers::NONE, cmd);
+        let shift = |code, cmd| key_handler(&cx_tx, code, KeyModifiers::SHIFT, cmd);
+        let mut handlers = vec![
+            key(KeyCode::Esc, WaveEvent::Quit),
+            key(KeyCode::Left, WaveEvent::Left),
+            key(KeyCode::Right, WaveEvent::Right),
+            key(KeyCode::Up, WaveEvent::Up),
+            key(KeyCode::Down, WaveEvent::Down),
+            shift(KeyCode::Left, WaveEvent::FarLeft),
+            shift(KeyCode::Right, WaveEvent::FarRight),
+            shift(KeyCode::Up, WaveEvent::FarUp),
+            shift(KeyCode::Down, WaveEvent::FarDown),
+            key(KeyCode::Char('p'), WaveEvent::PresetMenu),
+            key(KeyCode::Enter, WaveEvent::Audition),
+        ];
+        for table in 0..TABLES {
+            let digit = char::from_digit(table as u32 + 1, 10).unwrap();
+            handlers.push(key(KeyCode::Char(digit), WaveEvent::Table(table)));
+        }
+
+        Self {
+            table: 0,
+            cursor: 0,
+            tracker_data,
+            graph: Rect::default(),
+            stroke: None,
+            active_handlers: handlers,
+            global_handlers: vec![],
+            cx_rx,
+            cx_tx,
+            par_tx: parent_tx,
+        }
+    }
+
+    /// For sending the editor events from elsewhere, like the preset menu
+    pub fn events(&self) -> Sender<WaveEvent> {
+        self.cx_tx.clone()
+    }
+
+    fn current_table(&self) -> Wavetable {
+        self.tracker_data.borrow().tables[self.table]
+    }
+
+    /// Change the current table with `change`, and record it so it can be undone
+    fn edit(&mut self, change: impl FnOnce(&mut Wavetable)) {
+        let data = &mut *self.tracker_data.borrow_mut();
+        let before = data.tables[self.table];
+        change(&mut data.tables[self.table]);
+        if let Some(edit) = Edit::wavetable(self.table, &before, &data.tables[self.table]) {
+            data.history.record(edit);
+        }
+    }
+
+    fn nudge(&mut self, delta: i16) {
+        let cursor = self.cursor as usize;
+        self.edit(|table| table[cursor] = (table[cursor] as i16 + delta).clamp(0, 255) as u8);
+    }
+
+    /// The sample and level under a terminal cell of the graph
+    fn graph_point(&self, column: u16, row: u16) -> Option<(u8, u8)> {
+        let graph = self.graph;
+        if graph.width == 0 || graph.height == 0 || !graph.contains((column, row).into()) {
+            return None;
+        }
+        let x = (column - graph.x) as f32 + 0.5;
+        let y = (row - graph.y) as f32 + 0.5;
+        let sample = (x * SIZE as f32 / graph.width as f32) as u8;
+        let level = (255.0 - y * 256.0 / graph.height as f32).round().clamp(0.0, 255.0) as u8;
+        Some((sample, level))
+    }
+
+    /// Draw with the mouse: a press starts a stroke, dragging draws a line
+    /// from the last point, and the release records it as one edit
+    fn mouse(&mut self, mouse: MouseEvent) {
+        let point = self.graph_point(mouse.column, mouse.row);
+        match (mouse.kind, point, self.stroke) {
+            (MouseEventKind::Down(MouseButton::Left), Some((sample, level)), _) => {
+                let before = self.current_table();
+                self.tracker_data.borrow_mut().tables[self.table][sample as usize] = level;
+                self.stroke = Some((before, sample));
+                self.cursor = sample;
+            }
+            (MouseEventKind::Drag(MouseButton::Left), Some((sample, level)), Some((before, last))) => {
+                let data = &mut *self.tracker_data.borrow_mut();
+                let table = &mut data.tables[self.table];
+                let (from, from_level) = (last as i32, table[last as usize] as i32);
+                let (to, to_level) = (sample as i32, level as i32);
+                // fill in the samples the mouse skipped over
+                for x in from.min(to)..=from.max(to) {
+                    table[x as usize] = match to - from {
+                        0 => to_level,
+                        span => from_level + (to_level - from_level) * (x - from) / span,
+                    } as u8;
+                }
+                self.stroke = Some((before, sample));
+                self.cursor = sample;
+            }
+            (MouseEventKind::Up(MouseButton::Left), _, Some((before, _))) => {
+                self.stroke = None;
+                let data = &mut *self.tracker_data.borrow_mut();
+                if let Some(edit) = Edit::wavetable(self.table, &before, &data.tables[self.table]) {
+                    data.history.record(edit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Component for WavetableEditor {
+    fn update(&mut self, events: Vec<Event>) {
+        for event in events {
+            if let Event::Mouse(mouse) = event {
+                self.mouse(mouse);
+            }
+        }
+
+        while let Ok(event) = self.cx_rx.try_recv() {
+            match event {
+                WaveEvent::Left => self.cursor = self.cursor.wrapping_sub(1),
+                WaveEvent::Right => self.cursor = self.cursor.wrapping_add(1),
+                WaveEvent::FarLeft => self.cursor = self.cursor.wrapping_sub(FAR),
+                WaveEvent::FarRight => self.cursor = self.cursor.wrapping_add(FAR),
+                WaveEvent::Up => self.nudge(1),
+                WaveEvent::Down => self.nudge(-1),
+                WaveEvent::FarUp => self.nudge(FAR as i16),
+                WaveEvent::FarDown => self.nudge(-(FAR as i16)),
+                WaveEvent::Table(table) => self.table = table,
+                WaveEvent::PresetMenu => { let _ = self.par_tx.send(TrackerCmd::PresetMenu); }
+                WaveEvent::Preset(preset) => self.edit(|table| *table = preset.table()),
+                WaveEvent::Audition => { let _ = self.par_tx.send(TrackerCmd::Audition(self.table)); }
+                WaveEvent::Quit => { let _ = self.par_tx.send(TrackerCmd::FocusComponent(None)); }
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let [tabs_area, graph_area, hints_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ]).areas(area);
+
+        let table = self.current_table();
+        let cursor = self.cursor as usize;
+
+        let mut tabs = vec![Span::from(" wavetable ")];
+        for t in 0..TABLES {
+            let tab = Span::from(format!(" {} ", t + 1));
+            tabs.push(if t == self.table { tab.fg(SCHEME.black[0]).bg(SCHEME.orange[2]) } else { tab.fg(SCHEME.gray[2]) });
+        }
+        tabs.push(Span::from(format!(
+            "   slot {}   sample {:02X} = {:02X}",
+            FIRST_SLOT as usize + self.table, cursor, table[cursor]
+        )).fg(SCHEME.white[1]));
+        frame.render_widget(Line::from(tabs), tabs_area);
+
+        let block = Block::new().borders(Borders::ALL).fg(SCHEME.gray[0]);
+        self.graph = block.inner(graph_area);
+
+        let canvas = Canvas::default()
+            .block(block)
+            .marker(Marker::Braille)
+            .x_bounds([0.0, SIZE as f64])
+            .y_bounds([0.0, 255.0])
+            .paint(|ctx| {
+                ctx.draw(&CanvasLine::new(0.0, 128.0, SIZE as f64, 128.0, SCHEME.gray[0]));
+                ctx.draw(&CanvasLine::new(cursor as f64 + 0.5, 0.0, cursor as f64 + 0.5, 255.0, SCHEME.blue[2]));
+                ctx.layer();
+                for (x, pair) in table.windows(2).enumerate() {
+                    let (x, a, b) = (x as f64 + 0.5, pair[0] as f64, pair[1] as f64);
+                    ctx.draw(&CanvasLine::new(x, a, x + 1.0, b, SCHEME.orange[1]));
+                }
+            });
+        frame.render_widget(canvas, graph_area);
+
+        let hints = " ←→ sample  ↑↓ level  shift: by 16  1-8 table  p presets  enter listen  drag to draw";
+        frame.render_widget(Line::from(hints).fg(SCHEME.gray[2]), hints_area);
+    }
+}
+
+impl TSub for WavetableEditor {
+    fn active_handlers(&self) -> &Vec<Handler> {
+        &self.active_handlers
+    }
+
+    fn global_handlers(&self) -> &Vec<Handler> {
+        &self.global_handlers
+    }
+}
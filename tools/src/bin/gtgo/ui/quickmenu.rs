@@ -5,14 +5,14 @@ use ratatui::{crossterm::event::{Event, KeyCode, KeyEvent}, layout::Rect, style:
 use crate::{helpers::SCHEME, Component};
 
 pub struct QmItem {
-    label: &'static str,
+    label: String,
     enabled: bool,
     active: Rc<Box<dyn Fn()>>,
 }
 
-pub fn qi<F>(label: &'static str, enabled: bool, active: F) -> QmItem
+pub fn qi<F>(label: impl Into<String>, enabled: bool, active: F) -> QmItem
 where F: Fn() + 'static, {
-    QmItem { label, enabled, active: Rc::new(Box::new(active)) }
+    QmItem { label: label.into(), enabled, active: Rc::new(Box::new(active)) }
 }
 
 
@@ -101,6 +101,12 @@ impl QuickMenu {
         }
     }
 
+    /// Start with item `selection` selected, or the last if there are fewer
+    pub fn with_selection(mut self, selection: usize) -> Self {
+        self.selection = selection.min(self.list_items.len().saturating_sub(1));
+        self
+    }
+
     pub fn is_active(&self) -> bool {
         self.is_active
     }
@@ -8,6 +8,8 @@ use std::time::Duration;
 use structopt::StructOpt;
 use tempfile::NamedTempFile;
 
+use gametank_sdk::rom_header::RomHeader;
+
 static FIRMWARE: &[u8] = include_bytes!("latest-fw.hex");
 
 #[derive(Debug, PartialEq, StructOpt)]
@@ -114,6 +116,18 @@ fn load_rom(port: &mut Box<dyn SerialPort>, file: Option<String>) -> anyhow::Res
     let path = file.ok_or_else(|| anyhow::anyhow!("No file provided"))?;
     let rom_buffer = fs::read(&path)?;
 
+    // ROMs with a footer can say whether they're intact before minutes go into flashing them
+    match RomHeader::read(&rom_buffer) {
+        Some(header) => {
+            header.verify(&rom_buffer).map_err(|e| anyhow::anyhow!(e))?;
+            println!(
+                "{}",
+                style(format!("{} (build {:016x}): {} bank checksums ok", header.title, header.build_hash, header.bank_crcs.len())).green()
+            );
+        }
+        None => println!("{}", style("No ROM footer, skipping verification").dim()),
+    }
+
     read_output(port);
 
     port.write_all(b"mode f\r").expect("write data failed");
@@ -6,10 +6,11 @@ use std::path::Path;
 use std::process::Command;
 
 use crate::container::podman_exec;
+use crate::messages::{child_stdout, status};
 
 /// Build assembly files into libasm.a (runs directly)
 pub fn build_asm(workdir: &str) -> Result<(), String> {
-    println!("Assembling .asm files...");
+    status!("Assembling .asm files...");
     
     let asm_dir = Path::new(workdir).join("src/asm");
     let target_dir = Path::new(workdir).join("target/asm");
@@ -24,7 +25,7 @@ pub fn build_asm(workdir: &str) -> Result<(), String> {
             let path = entry.path();
             if path.extension().map_or(false, |ext| ext == "asm") {
                 let filename = path.file_stem().unwrap().to_string_lossy();
-                println!("  Assembling {}...", filename);
+                status!("  Assembling {}...", filename);
                 
                 let status = Command::new("llvm-mc")
                     .args([
@@ -35,6 +36,7 @@ pub fn build_asm(workdir: &str) -> Result<(), String> {
                         "-o",
                         &format!("{}/target/asm/{}.o", workdir, filename),
                     ])
+                    .stdout(child_stdout())
                     .status()
                     .map_err(|e| format!("Failed to assemble {}: {}", filename, e))?;
 
@@ -46,7 +48,7 @@ pub fn build_asm(workdir: &str) -> Result<(), String> {
     }
 
     // Archive into libasm.a
-    println!("  Creating libasm.a...");
+    status!("  Creating libasm.a...");
     let o_files: Vec<_> = std::fs::read_dir(&target_dir)
         .map_err(|e| e.to_string())?
         .filter_map(|e| e.ok())
@@ -60,6 +62,7 @@ pub fn build_asm(workdir: &str) -> Result<(), String> {
         
         let status = Command::new("llvm-ar")
             .args(&args)
+            .stdout(child_stdout())
             .status()
             .map_err(|e| format!("Failed to archive: {}", e))?;
 
@@ -78,7 +81,7 @@ pub fn build_asm(workdir: &str) -> Result<(), String> {
 
 /// Build assembly files via container
 pub fn build_asm_in_container(workdir: &Path, working_dir: &Path) -> Result<(), String> {
-    println!("Assembling .asm files...");
+    status!("Assembling .asm files...");
     
     let asm_dir = workdir.join("src/asm");
     let target_dir = workdir.join("target/asm");
@@ -96,7 +99,7 @@ pub fn build_asm_in_container(workdir: &Path, working_dir: &Path) -> Result<(),
             let path = entry.path();
             if path.extension().map_or(false, |ext| ext == "asm") {
                 let filename = path.file_stem().unwrap().to_string_lossy();
-                println!("  Assembling {}...", filename);
+                status!("  Assembling {}...", filename);
                 
                 podman_exec("/workspace", &[
                     "llvm-mc",
@@ -120,7 +123,7 @@ pub fn build_asm_in_container(workdir: &Path, working_dir: &Path) -> Result<(),
         .collect();
 
     if !o_files.is_empty() {
-        println!("  Creating libasm.a...");
+        status!("  Creating libasm.a...");
         let mut args = vec![
             "llvm-ar".to_string(),
             "rcs".to_string(),
@@ -0,0 +1,72 @@
+//! Image conversion
+//!
+//! Maps pixels onto the GameTank palette, either straight to color bytes or to
+//! packed indices into a palette of just the colors the image uses.
+
+use std::path::Path;
+
+use ::image::RgbaImage;
+use gametank_sdk::colors::to_colors;
+
+pub struct Indexed {
+    pub pixels_per_byte: u8,
+    pub palette: Vec<u8>,
+    pub pixels: Vec<u8>,
+}
+
+pub fn load(path: &Path) -> Result<RgbaImage, String> {
+    ::image::open(path)
+        .map(|img| img.to_rgba8())
+        .map_err(|e| format!("Failed to load image {}: {}", path.display(), e))
+}
+
+/// Width and height, which the SDK's image types keep in a byte each
+pub fn dimensions(name: &str, img: &RgbaImage) -> Result<(u8, u8), String> {
+    let (w, h) = img.dimensions();
+    match (u8::try_from(w), u8::try_from(h)) {
+        (Ok(w), Ok(h)) => Ok((w, h)),
+        _ => Err(format!("{}: {}x{} is too big; images can be at most 255x255", name, w, h)),
+    }
+}
+
+pub fn to_raw(img: &RgbaImage) -> Vec<u8> {
+    to_colors(img)
+}
+
+/// Palette of the colors used, in order of first appearance, and the pixels
+/// as indices into it packed low bits first
+pub fn to_indexed(name: &str, img: &RgbaImage) -> Result<Indexed, String> {
+    let colors = to_colors(img);
+
+    let mut palette = vec![];
+    let indices: Vec<u8> = colors.iter()
+        .map(|color| match palette.iter().position(|c| c == color) {
+            Some(i) => i as u8,
+            None => {
+                palette.push(*color);
+                (palette.len() - 1) as u8
+            }
+        })
+        .collect();
+
+    if palette.len() > 256 {
+        return Err(format!("{}: uses {} colors, but an indexed image can have at most 256", name, palette.len()));
+    }
+
+    let bits_per_color: u8 = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+    let pixels_per_byte = 8 / bits_per_color;
+
+    let pixels = indices
+        .chunks(pixels_per_byte as usize)
+        .map(|chunk| {
+            chunk.iter().enumerate().fold(0u8, |acc, (i, &index)| acc | (index << (bits_per_color * i as u8)))
+        })
+        .collect();
+
+    Ok(Indexed { pixels_per_byte, palette, pixels })
+}
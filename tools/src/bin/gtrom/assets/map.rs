@@ -0,0 +1,53 @@
+//! Tile map conversion
+//!
+//! Reads a CSV grid of tile indices, one row per line, as exported by most
+//! map editors.
+
+use std::path::Path;
+
+pub struct Map {
+    pub width: u16,
+    pub height: u16,
+    pub tiles: Vec<u8>,
+}
+
+pub fn load(path: &Path) -> Result<Map, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read map {}: {}", path.display(), e))?;
+
+    let mut tiles = vec![];
+    let mut width = None;
+    let mut height = 0;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim().trim_end_matches(',');
+        if line.is_empty() {
+            continue;
+        }
+
+        let row = line.split(',')
+            .map(|cell| {
+                let cell = cell.trim();
+                cell.parse::<u8>().map_err(|_| format!("{}:{}: {:?} isn't a tile index (0-255)", path.display(), line_no + 1, cell))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        match width {
+            None => width = Some(row.len()),
+            Some(w) if w != row.len() => {
+                return Err(format!("{}:{}: row has {} tiles, but earlier rows have {}", path.display(), line_no + 1, row.len(), w));
+            }
+            _ => {}
+        }
+
+        tiles.extend(row);
+        height += 1;
+    }
+
+    let width = width.ok_or_else(|| format!("{} is empty", path.display()))?;
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(format!("{} is too big", path.display()));
+    };
+
+    Ok(Map { width, height, tiles })
+}
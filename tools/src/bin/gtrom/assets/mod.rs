@@ -0,0 +1,380 @@
+//! Asset pipeline
+//!
+//! Converts the images, tile maps and sounds listed in a ROM's `assets.toml`
+//! into `target/assets/`: a .bin for each blob of data, and `assets.rs` with a
+//! typed static for each asset that `gametank::include_assets!()` pulls in.
+//! Output goes under target/ so `gtrom watch` doesn't see its own writes.
+
+mod image;
+mod map;
+mod slice;
+mod sound;
+mod tiled;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::cargo::find_rom_dir;
+use crate::config;
+use crate::messages::status;
+use gametank_sdk::rom_builder::{BANK_SIZE, FIXED_BANK};
+
+pub use slice::{do_slice, parse_tile_size};
+
+pub const MANIFEST: &str = "assets.toml";
+const OUTPUT_DIR: &str = "target/assets";
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    image: Vec<ImageAsset>,
+    #[serde(default)]
+    map: Vec<MapAsset>,
+    #[serde(default)]
+    sound: Vec<SoundAsset>,
+    #[serde(default)]
+    tiled: Vec<TiledAsset>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ImageFormat {
+    /// One GameTank color per pixel, ready to copy into sprite RAM
+    #[default]
+    Raw,
+    /// Packed palette indices with the palette alongside
+    Indexed,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageAsset {
+    name: String,
+    path: PathBuf,
+    bank: Option<u8>,
+    #[serde(default)]
+    format: ImageFormat,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MapAsset {
+    name: String,
+    path: PathBuf,
+    bank: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SoundAsset {
+    name: String,
+    path: PathBuf,
+    bank: Option<u8>,
+    /// Resample to this rate; defaults to the file's own
+    sample_rate: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TiledAsset {
+    name: String,
+    path: PathBuf,
+    /// First bank; a map too big for one spills into the banks after it
+    bank: Option<u8>,
+    /// Tile layer to turn into a collision bitmap instead of tiles
+    collision: Option<String>,
+}
+
+/// Generated code and the blobs it includes
+#[derive(Default)]
+struct Output {
+    code: String,
+    blobs: BTreeMap<String, Vec<u8>>,
+    bank_usage: BTreeMap<u8, usize>,
+}
+
+impl Output {
+    /// Emit a banked byte array static for `data` and return its name
+    fn blob(&mut self, name: &str, suffix: &str, bank: u8, data: Vec<u8>) -> String {
+        let ident = format!("{}_{}", name, suffix);
+        let file = format!("{}.bin", ident.to_lowercase());
+
+        if bank != FIXED_BANK {
+            writeln!(self.code, "#[unsafe(link_section = \".rodata.bank{}\")]", bank).unwrap();
+        }
+        writeln!(
+            self.code,
+            "static {}: [u8; {}] = *include_bytes!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/{}/{}\"));",
+            ident, data.len(), OUTPUT_DIR, file
+        ).unwrap();
+
+        *self.bank_usage.entry(bank).or_default() += data.len();
+        self.blobs.insert(file, data);
+        ident
+    }
+}
+
+/// Bank an asset goes in; unbanked assets share the fixed bank with code
+fn resolve_bank(name: &str, bank: Option<u8>) -> Result<u8, String> {
+    match bank {
+        None => Ok(FIXED_BANK),
+        Some(bank) if bank <= FIXED_BANK => Ok(bank),
+        Some(bank) => Err(format!("{}: bank {} is out of range (0-{})", name, bank, FIXED_BANK)),
+    }
+}
+
+/// Asset names become statics, so they have to be unique identifiers
+fn check_name(name: &str, seen: &mut BTreeSet<String>) -> Result<(), String> {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("asset name {:?} isn't a valid Rust identifier", name));
+    }
+    if !seen.insert(name.to_string()) {
+        return Err(format!("asset name {} is used more than once", name));
+    }
+    Ok(())
+}
+
+fn convert(manifest: &Manifest, base_dir: &Path) -> Result<Output, String> {
+    let mut out = Output::default();
+    let mut seen = BTreeSet::new();
+
+    for asset in &manifest.image {
+        check_name(&asset.name, &mut seen)?;
+        let bank = resolve_bank(&asset.name, asset.bank)?;
+        let img = image::load(&base_dir.join(&asset.path))?;
+        let (width, height) = image::dimensions(&asset.name, &img)?;
+
+        match asset.format {
+            ImageFormat::Raw => {
+                let pixels = out.blob(&asset.name, "PIXELS", bank, image::to_raw(&img));
+                writeln!(
+                    out.code,
+                    "pub static {}: ::gametank::assets::Image = ::gametank::assets::Image {{ bank: {}, width: {}, height: {}, pixels: &{} }};\n",
+                    asset.name, bank, width, height, pixels
+                ).unwrap();
+            }
+            ImageFormat::Indexed => {
+                let indexed = image::to_indexed(&asset.name, &img)?;
+                let palette = out.blob(&asset.name, "PALETTE", bank, indexed.palette);
+                let pixels = out.blob(&asset.name, "PIXELS", bank, indexed.pixels);
+                writeln!(
+                    out.code,
+                    "pub static {}: ::gametank::assets::IndexedImage = ::gametank::assets::IndexedImage {{ bank: {}, width: {}, height: {}, pixels_per_byte: {}, palette: &{}, pixels: &{} }};\n",
+                    asset.name, bank, width, height, indexed.pixels_per_byte, palette, pixels
+                ).unwrap();
+            }
+        }
+        status!("{:<24}bank {:>3}  {}x{} image", asset.name, bank, width, height);
+    }
+
+    for asset in &manifest.map {
+        check_name(&asset.name, &mut seen)?;
+        let bank = resolve_bank(&asset.name, asset.bank)?;
+        let map = map::load(&base_dir.join(&asset.path))?;
+
+        let tiles = out.blob(&asset.name, "TILES", bank, map.tiles);
+        writeln!(
+            out.code,
+            "pub static {}: ::gametank::assets::TileMap = ::gametank::assets::TileMap {{ bank: {}, width: {}, height: {}, tiles: &{} }};\n",
+            asset.name, bank, map.width, map.height, tiles
+        ).unwrap();
+        status!("{:<24}bank {:>3}  {}x{} map", asset.name, bank, map.width, map.height);
+    }
+
+    for asset in &manifest.tiled {
+        check_name(&asset.name, &mut seen)?;
+        convert_tiled(&mut out, asset, base_dir)?;
+    }
+
+    for asset in &manifest.sound {
+        check_name(&asset.name, &mut seen)?;
+        let bank = resolve_bank(&asset.name, asset.bank)?;
+        let sound = sound::load(&base_dir.join(&asset.path), asset.sample_rate)?;
+        let length = sound.samples.len();
+
+        let samples = out.blob(&asset.name, "SAMPLES", bank, sound.samples);
+        writeln!(
+            out.code,
+            "pub static {}: ::gametank::assets::Sound = ::gametank::assets::Sound {{ bank: {}, sample_rate: {}, samples: &{} }};\n",
+            asset.name, bank, sound.sample_rate, samples
+        ).unwrap();
+        status!("{:<24}bank {:>3}  {} samples @ {}Hz", asset.name, bank, length, sound.sample_rate);
+    }
+
+    for (bank, used) in &out.bank_usage {
+        // the fixed bank is shared with code, so only the linker can tell if it's full
+        if *bank != FIXED_BANK && *used > BANK_SIZE {
+            return Err(format!("assets in bank {} need {} bytes, but a bank only holds {}", bank, used, BANK_SIZE));
+        }
+    }
+
+    Ok(out)
+}
+
+/// Hands out banks for a Tiled map's data in order, starting from the
+/// manifest's bank and moving on to the next one when the current one is full
+struct BankCursor {
+    bank: u8,
+    banked: bool,
+}
+
+impl BankCursor {
+    fn place(&mut self, out: &Output, name: &str, len: usize) -> Result<u8, String> {
+        if !self.banked {
+            return Ok(FIXED_BANK);
+        }
+        let used = out.bank_usage.get(&self.bank).copied().unwrap_or(0);
+        if used > 0 && used + len > BANK_SIZE {
+            self.bank += 1;
+        }
+        if self.bank >= FIXED_BANK {
+            return Err(format!("{}: ran out of banks before bank {}", name, FIXED_BANK));
+        }
+        Ok(self.bank)
+    }
+}
+
+/// A Tiled map: its tile layers in chunks of whole rows that each fit in a
+/// bank, the collision layer as a bitmap and the objects as a spawn table
+fn convert_tiled(out: &mut Output, asset: &TiledAsset, base_dir: &Path) -> Result<(), String> {
+    let first_bank = resolve_bank(&asset.name, asset.bank)?;
+    let map = tiled::load(&base_dir.join(&asset.path))?;
+    let (width, height) = (map.width as usize, map.height as usize);
+    let mut cursor = BankCursor { bank: first_bank, banked: first_bank != FIXED_BANK };
+
+    let collision_name = asset.collision.as_deref().unwrap_or("collision");
+    let collision_index = map.layers.iter().position(|l| l.name.eq_ignore_ascii_case(collision_name));
+    if collision_index.is_none() && asset.collision.is_some() {
+        return Err(format!("{}: no tile layer named {:?} to use for collision", asset.name, collision_name));
+    }
+
+    // unbanked maps share the fixed bank with code, so they're never split
+    let rows_per_chunk = if cursor.banked { (BANK_SIZE / width.max(1)).min(height).max(1) } else { height.max(1) };
+    if cursor.banked && width > BANK_SIZE {
+        return Err(format!("{}: a {} tile wide row doesn't fit in a bank", asset.name, width));
+    }
+
+    let mut layers = vec![];
+    for (index, layer) in map.layers.iter().enumerate() {
+        if Some(index) == collision_index {
+            continue;
+        }
+        let mut chunks = vec![];
+        for (chunk, rows) in layer.tiles.chunks(rows_per_chunk * width.max(1)).enumerate() {
+            let bank = cursor.place(out, &asset.name, rows.len())?;
+            let tiles = out.blob(&asset.name, &format!("LAYER{}_CHUNK{}", index, chunk), bank, rows.to_vec());
+            chunks.push(format!("::gametank::assets::TileChunk {{ bank: {}, tiles: &{} }}", bank, tiles));
+        }
+        layers.push(format!(
+            "::gametank::assets::TileLayer {{ name: {:?}, width: {}, height: {}, rows_per_chunk: {}, chunks: &[{}] }}",
+            layer.name, width, height, rows_per_chunk, chunks.join(", ")
+        ));
+    }
+
+    let collision = match collision_index {
+        Some(index) => {
+            // one bit per tile, most significant first, each row padded to whole bytes
+            let stride = width.div_ceil(8);
+            let mut bits = vec![0u8; stride * height];
+            for (i, &tile) in map.layers[index].tiles.iter().enumerate() {
+                let (x, y) = (i % width, i / width);
+                if tile != 0 {
+                    bits[y * stride + x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            let bank = cursor.place(out, &asset.name, bits.len())?;
+            let bits = out.blob(&asset.name, "COLLISION", bank, bits);
+            format!(
+                "Some(::gametank::assets::CollisionMap {{ bank: {}, width: {}, height: {}, bits: &{} }})",
+                bank, width, height, bits
+            )
+        }
+        None => "None".to_string(),
+    };
+
+    let objects: Vec<String> = map.objects.iter()
+        .map(|o| format!(
+            "::gametank::assets::MapObject {{ name: {:?}, class: {:?}, x: {}, y: {}, width: {}, height: {} }}",
+            o.name, o.class, o.x, o.y, o.width, o.height
+        ))
+        .collect();
+
+    writeln!(
+        out.code,
+        "pub static {}: ::gametank::assets::TiledMap = ::gametank::assets::TiledMap {{ width: {}, height: {}, tile_width: {}, tile_height: {}, layers: &[{}], collision: {}, objects: &[{}] }};\n",
+        asset.name, map.width, map.height, map.tile_width, map.tile_height, layers.join(", "), collision, objects.join(", ")
+    ).unwrap();
+
+    let banks = match cursor.bank {
+        last if last == first_bank => format!("bank {:>3}", first_bank),
+        last => format!("banks {}-{}", first_bank, last),
+    };
+    status!(
+        "{:<24}{}  {}x{} Tiled map, {} layers, {} objects",
+        asset.name, banks, map.width, map.height, layers.len(), map.objects.len()
+    );
+    Ok(())
+}
+
+/// Rewriting an unchanged file would still make cargo rebuild the crate
+fn write_if_changed(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if std::fs::read(path).is_ok_and(|old| old == contents) {
+        return Ok(());
+    }
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Convert the assets listed in `rom_dir/assets.toml` (or the manifest gtrom.toml
+/// names), if there is one. Asset paths are relative to the manifest.
+pub fn build_assets(rom_dir: &Path) -> Result<(), String> {
+    let manifest_path = config::get().asset_manifest(rom_dir);
+    if !manifest_path.exists() {
+        return Ok(());
+    }
+
+    status!("Converting assets...");
+    let text = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let manifest: Manifest = toml::from_str(&text)
+        .map_err(|e| format!("Failed to parse {}: {}", manifest_path.display(), e))?;
+
+    let out = convert(&manifest, manifest_path.parent().unwrap_or(rom_dir))?;
+
+    let output_dir = rom_dir.join(OUTPUT_DIR);
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create {}: {}", output_dir.display(), e))?;
+
+    // drop blobs for assets that were removed from the manifest
+    if let Ok(entries) = std::fs::read_dir(&output_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(".bin") && !out.blobs.contains_key(&name) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    for (file, data) in &out.blobs {
+        write_if_changed(&output_dir.join(file), data)?;
+    }
+
+    let source = manifest_path.file_name().map_or(MANIFEST.into(), |name| name.to_string_lossy());
+    let code = format!("// Generated by gtrom from {}. Don't edit.\n\n{}", source, out.code);
+    write_if_changed(&output_dir.join("assets.rs"), code.as_bytes())
+}
+
+/// `gtrom assets`: convert assets without building
+pub fn do_assets() -> Result<(), String> {
+    let (_working_dir, rom_dir) = find_rom_dir()?;
+    let manifest_path = config::get().asset_manifest(&rom_dir);
+    if !manifest_path.exists() {
+        return Err(format!("No asset manifest at {}", manifest_path.display()));
+    }
+    build_assets(&rom_dir)
+}
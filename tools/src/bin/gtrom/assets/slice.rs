@@ -0,0 +1,152 @@
+//! Sprite sheet slicing
+//!
+//! Cuts a sheet into equal tiles and packs them into 128x128 sprite RAM
+//! quadrants, the unit the CPU loads sprite RAM in, so no tile straddles a
+//! quadrant edge. Fully transparent tiles are dropped and identical tiles
+//! share one copy. Writes the quadrants back to back as one .bin, and a Rust
+//! module with a `SpriteTile` for every tile of the sheet.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use ::image::imageops;
+
+use super::image::{load, to_raw};
+use crate::messages::status;
+
+/// Width and height of a quadrant of sprite RAM
+const QUADRANT: u32 = 128;
+/// Eight pages of four quadrants
+const MAX_QUADRANTS: usize = 32;
+
+/// Parse a tile size like `16x16`
+pub fn parse_tile_size(s: &str) -> Result<(u8, u8), String> {
+    let (w, h) = s.split_once('x').ok_or_else(|| format!("tile size {:?} should look like 16x16", s))?;
+    let side = |v: &str| match v.trim().parse::<u8>() {
+        Ok(n) if (1..=QUADRANT as u8).contains(&n) => Ok(n),
+        _ => Err(format!("tile size {:?}: each side has to be 1-{}", s, QUADRANT)),
+    };
+    Ok((side(w)?, side(h)?))
+}
+
+/// Where a tile landed: its sprite RAM page and x, y within that page's 256x256
+struct Placement {
+    page: u8,
+    x: u8,
+    y: u8,
+}
+
+/// `gtrom slice`: pack `image_path` into sprite RAM quadrants, writing
+/// `<name>.bin` and `<name>.rs` to `output` (the image's directory by default)
+pub fn do_slice(image_path: &Path, tile: (u8, u8), output: Option<&Path>, name: Option<&str>) -> Result<(), String> {
+    let stem = image_path.file_stem().map_or("sheet".to_string(), |s| s.to_string_lossy().to_string());
+    let name = name.map_or_else(|| stem.to_ascii_uppercase().replace(['-', ' ', '.'], "_"), str::to_string);
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("{:?} isn't a valid Rust identifier; pick one with --name", name));
+    }
+
+    let img = load(image_path)?;
+    let (tw, th) = (tile.0 as u32, tile.1 as u32);
+    let (sw, sh) = img.dimensions();
+    if sw % tw != 0 || sh % th != 0 {
+        return Err(format!("{}: {}x{} doesn't divide into {}x{} tiles", image_path.display(), sw, sh, tw, th));
+    }
+
+    let (cols, rows) = (sw / tw, sh / th);
+    let per_row = QUADRANT / tw;
+    let per_quadrant = (per_row * (QUADRANT / th)) as usize;
+
+    let mut quadrants: Vec<Vec<u8>> = vec![];
+    let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut placements: Vec<Placement> = vec![];
+    let mut tiles: Vec<Option<usize>> = vec![];
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let pixels = to_raw(&imageops::crop_imm(&img, col * tw, row * th, tw, th).to_image());
+            if pixels.iter().all(|&p| p == 0) {
+                tiles.push(None);
+                continue;
+            }
+            if let Some(&slot) = seen.get(&pixels) {
+                tiles.push(Some(slot));
+                continue;
+            }
+
+            let slot = placements.len();
+            let (quadrant, within) = (slot / per_quadrant, (slot % per_quadrant) as u32);
+            let (qx, qy) = ((within % per_row) * tw, (within / per_row) * th);
+            if quadrant == quadrants.len() {
+                quadrants.push(vec![0; (QUADRANT * QUADRANT) as usize]);
+            }
+            for (y, line) in pixels.chunks(tw as usize).enumerate() {
+                let start = ((qy + y as u32) * QUADRANT + qx) as usize;
+                quadrants[quadrant][start..start + line.len()].copy_from_slice(line);
+            }
+
+            // quadrants go One, Two, Three, Four: left to right, then top to bottom
+            let offset = |bit: usize| ((quadrant >> bit) & 1) as u32 * QUADRANT;
+            placements.push(Placement {
+                page: (quadrant / 4) as u8,
+                x: (qx + offset(0)) as u8,
+                y: (qy + offset(1)) as u8,
+            });
+            seen.insert(pixels, slot);
+            tiles.push(Some(slot));
+        }
+    }
+
+    if quadrants.len() > MAX_QUADRANTS {
+        return Err(format!(
+            "{}: {} distinct tiles need {} quadrants, but sprite RAM only has {}",
+            image_path.display(), placements.len(), quadrants.len(), MAX_QUADRANTS
+        ));
+    }
+
+    let output = output.unwrap_or(image_path.parent().unwrap_or(Path::new(".")));
+    std::fs::create_dir_all(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let bin_name = format!("{}.bin", stem);
+    let bin_path = output.join(&bin_name);
+    let rs_path = output.join(format!("{}.rs", stem));
+
+    let mut code = String::new();
+    let source = image_path.file_name().map_or(stem.clone(), |s| s.to_string_lossy().to_string());
+    let _ = writeln!(code, "// Generated by gtrom slice from {}. Don't edit.", source);
+    let _ = writeln!(code, "//");
+    let _ = writeln!(code, "// {} is 128x128 quadrants of color bytes, 16KB each, in order: page 0", bin_name);
+    let _ = writeln!(code, "// quadrants One to Four, then page 1 and so on.");
+    let _ = writeln!(code);
+    let _ = writeln!(code, "use ::gametank::assets::SpriteTile;");
+    let _ = writeln!(code);
+    let _ = writeln!(code, "pub const {}_QUADRANTS: usize = {};", name, quadrants.len());
+    let _ = writeln!(code);
+    let mut all = vec![];
+    for (index, slot) in tiles.iter().enumerate() {
+        let Some(slot) = slot else { continue };
+        let p = &placements[*slot];
+        let _ = writeln!(
+            code,
+            "pub const {}_{}: SpriteTile = SpriteTile {{ page: {}, x: {}, y: {}, width: {}, height: {} }};",
+            name, index, p.page, p.x, p.y, tw, th
+        );
+        all.push(format!("{}_{}", name, index));
+    }
+    let _ = writeln!(code);
+    let _ = writeln!(code, "/// Every non-empty tile, in sheet order");
+    let _ = writeln!(code, "pub const {}: [SpriteTile; {}] = [{}];", name, all.len(), all.join(", "));
+
+    let data: Vec<u8> = quadrants.concat();
+    std::fs::write(&bin_path, &data).map_err(|e| format!("Failed to write {}: {}", bin_path.display(), e))?;
+    std::fs::write(&rs_path, code).map_err(|e| format!("Failed to write {}: {}", rs_path.display(), e))?;
+
+    status!(
+        "Sliced {} into {} tiles ({} distinct, {} empty) across {} quadrants",
+        source, tiles.len(), placements.len(), tiles.iter().filter(|t| t.is_none()).count(), quadrants.len()
+    );
+    status!("  {}", bin_path.display());
+    status!("  {}", rs_path.display());
+    Ok(())
+}
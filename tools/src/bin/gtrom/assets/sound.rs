@@ -0,0 +1,131 @@
+//! Sound conversion
+//!
+//! Reads PCM or float WAV files and turns them into unsigned 8-bit mono, the
+//! format the DAC takes.
+
+use std::path::Path;
+
+pub struct Sound {
+    pub sample_rate: u16,
+    pub samples: Vec<u8>,
+}
+
+struct Format {
+    tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+/// The RIFF chunks of a WAV file, by id
+fn chunks(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut chunks = vec![];
+    let mut rest = &data[12..];
+    while rest.len() >= 8 {
+        let id = &rest[0..4];
+        let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let body = rest.get(8..8 + len).unwrap_or(&rest[8..]);
+        chunks.push((id, body));
+        // chunks are padded to an even length
+        rest = rest.get(8 + len + (len & 1)..).unwrap_or(&[]);
+    }
+    Some(chunks)
+}
+
+fn parse_format(body: &[u8]) -> Option<Format> {
+    if body.len() < 16 {
+        return None;
+    }
+    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of the subformat GUID
+    if tag == 0xFFFE && body.len() >= 26 {
+        tag = u16::from_le_bytes([body[24], body[25]]);
+    }
+    Some(Format {
+        tag,
+        channels: u16::from_le_bytes([body[2], body[3]]),
+        sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+        bits: u16::from_le_bytes([body[14], body[15]]),
+    })
+}
+
+/// One sample as -1.0..1.0
+fn decode(format: &Format, bytes: &[u8]) -> Option<f32> {
+    Some(match (format.tag, format.bits) {
+        (1, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+        (1, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (1, 24) => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0,
+        (1, 32) => i32::from_le_bytes(bytes[0..4].try_into().unwrap()) as f32 / 2147483648.0,
+        (3, 32) => f32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+        _ => return None,
+    })
+}
+
+/// Linear interpolation is plenty for 8-bit output
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let step = from as f64 / to as f64;
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let a = samples[index];
+            let b = samples.get(index + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+pub fn load(path: &Path, sample_rate: Option<u32>) -> Result<Sound, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read sound {}: {}", path.display(), e))?;
+    let chunks = chunks(&data).ok_or_else(|| format!("{} isn't a WAV file", path.display()))?;
+
+    let format = chunks.iter()
+        .find(|(id, _)| *id == b"fmt ")
+        .and_then(|(_, body)| parse_format(body))
+        .ok_or_else(|| format!("{} has no usable format chunk", path.display()))?;
+    let body = chunks.iter()
+        .find(|(id, _)| *id == b"data")
+        .map(|(_, body)| *body)
+        .ok_or_else(|| format!("{} has no data chunk", path.display()))?;
+
+    let sample_bytes = format.bits as usize / 8;
+    let frame_bytes = sample_bytes * format.channels as usize;
+    if frame_bytes == 0 || decode(&format, &[0; 4]).is_none() {
+        return Err(format!("{}: unsupported WAV format (tag {}, {} bits)", path.display(), format.tag, format.bits));
+    }
+    if format.sample_rate == 0 {
+        return Err(format!("{}: the WAV header gives a sample rate of 0Hz", path.display()));
+    }
+
+    // mix down to mono
+    let mono: Vec<f32> = body.chunks_exact(frame_bytes)
+        .map(|frame| {
+            let sum: f32 = frame.chunks_exact(sample_bytes).filter_map(|s| decode(&format, s)).sum();
+            sum / format.channels as f32
+        })
+        .collect();
+
+    let rate = sample_rate.unwrap_or(format.sample_rate);
+    if rate == 0 {
+        return Err(format!("{}: sample_rate = 0 in assets.toml; give a rate in Hz", path.display()));
+    }
+    let rate_u16 = u16::try_from(rate)
+        .map_err(|_| format!("{}: {}Hz is too high; resample with sample_rate = <Hz> in assets.toml", path.display(), rate))?;
+
+    let samples = resample(&mono, format.sample_rate, rate)
+        .into_iter()
+        .map(|s| ((s.clamp(-1.0, 1.0) * 127.0) + 128.0).round() as u8)
+        .collect();
+
+    Ok(Sound { sample_rate: rate_u16, samples })
+}
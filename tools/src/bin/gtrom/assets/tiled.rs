@@ -0,0 +1,294 @@
+//! Tiled map conversion
+//!
+//! Reads maps saved by the Tiled editor, either .tmx (with layers in CSV
+//! format) or .tmj JSON. Layer groups are flattened. Tile layers keep Tiled's
+//! global tile ids, so 0 is an empty cell and tile n is the tileset's tile
+//! n - 1; flip flags are dropped. Every object layer's objects come out as one
+//! spawn list.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+pub struct TileLayer {
+    pub name: String,
+    pub tiles: Vec<u8>,
+}
+
+pub struct Object {
+    pub name: String,
+    /// Tiled's "class" (called "type" before Tiled 1.9)
+    pub class: String,
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+pub struct TiledMap {
+    pub width: u16,
+    pub height: u16,
+    pub tile_width: u8,
+    pub tile_height: u8,
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<Object>,
+}
+
+/// Tiled keeps flip and rotation flags in the top four bits of a tile id
+const FLAG_BITS: u32 = 0xF000_0000;
+
+fn tile_id(path: &Path, layer: &str, gid: u32) -> Result<u8, String> {
+    let id = gid & !FLAG_BITS;
+    u8::try_from(id).map_err(|_| format!("{}: layer {:?} uses tile {}, but tiles have to fit in a byte (0-255)", path.display(), layer, id))
+}
+
+fn dimension<T: TryFrom<i64>>(path: &Path, what: &str, value: i64) -> Result<T, String> {
+    T::try_from(value).map_err(|_| format!("{}: {} {} is out of range", path.display(), what, value))
+}
+
+pub fn load(path: &Path) -> Result<TiledMap, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read map {}: {}", path.display(), e))?;
+
+    let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    let map = match extension.as_deref() {
+        Some("tmx") => load_tmx(path, &text)?,
+        Some("tmj") | Some("json") => load_tmj(path, &text)?,
+        _ => return Err(format!("{}: Tiled maps are .tmx or .tmj files", path.display())),
+    };
+
+    let cells = map.width as usize * map.height as usize;
+    if let Some(layer) = map.layers.iter().find(|l| l.tiles.len() != cells) {
+        return Err(format!(
+            "{}: layer {:?} has {} tiles, but the map is {}x{}",
+            path.display(), layer.name, layer.tiles.len(), map.width, map.height
+        ));
+    }
+    Ok(map)
+}
+
+// -- TMX --------------------------------------------------------------------
+
+/// An XML tag, enough of one to read a TMX file. End tags are named `/name`.
+struct Tag<'a> {
+    name: &'a str,
+    /// Self-closing, like `<object ... />`
+    empty: bool,
+    attrs: Vec<(&'a str, String)>,
+    /// The text up to the next tag, for `<data>`
+    text: &'a str,
+}
+
+impl Tag<'_> {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Every tag in the document, in order. Comments and the XML declaration are
+/// skipped.
+fn tags(xml: &str) -> Vec<Tag<'_>> {
+    let mut tags = vec![];
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        let end = rest.find('>').unwrap_or(rest.len());
+        let body = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+        if body.starts_with(['?', '!']) {
+            continue;
+        }
+
+        let empty = body.ends_with('/');
+        let body = body.trim_end_matches('/');
+        let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let mut attrs = vec![];
+        let mut remaining = &body[name_end..];
+        while let Some((key, after)) = remaining.split_once('=') {
+            let after = after.trim_start();
+            let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else { break };
+            let Some((value, after)) = after[1..].split_once(quote) else { break };
+            attrs.push((key.trim(), unescape(value)));
+            remaining = after;
+        }
+
+        let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+        tags.push(Tag { name: &body[..name_end], empty, attrs, text });
+    }
+    tags
+}
+
+fn number(path: &Path, tag: &Tag, name: &str) -> Result<i64, String> {
+    let value = tag.attr(name).unwrap_or("0");
+    // object positions are often fractional
+    value.parse::<f64>()
+        .map(|v| v.round() as i64)
+        .map_err(|_| format!("{}: <{} {}={:?}> isn't a number", path.display(), tag.name, name, value))
+}
+
+fn load_tmx(path: &Path, xml: &str) -> Result<TiledMap, String> {
+    let tags = tags(xml);
+    let map = tags.iter().find(|t| t.name == "map").ok_or_else(|| format!("{} has no <map>", path.display()))?;
+    if map.attr("infinite") == Some("1") {
+        return Err(format!("{}: infinite maps aren't supported; turn off Map > Map Properties > Infinite", path.display()));
+    }
+    if map.attr("orientation").is_some_and(|o| o != "orthogonal") {
+        return Err(format!("{}: only orthogonal maps are supported", path.display()));
+    }
+
+    let mut layers = vec![];
+    let mut objects = vec![];
+    let mut layer_name = String::new();
+    // an embedded tileset's tiles can have collision shapes, which are <object>s too
+    let mut in_tileset = false;
+
+    for tag in &tags {
+        match tag.name {
+            // an external tileset is just `<tileset source=... />`
+            "tileset" => in_tileset = !tag.empty,
+            "/tileset" => in_tileset = false,
+            "layer" => layer_name = tag.attr("name").unwrap_or("").to_string(),
+            "data" => {
+                match tag.attr("encoding") {
+                    Some("csv") => {}
+                    _ => return Err(format!(
+                        "{}: layer {:?} isn't stored as CSV; set Map > Map Properties > Tile Layer Format to CSV",
+                        path.display(), layer_name
+                    )),
+                }
+                let tiles = tag.text.split(',')
+                    .map(str::trim)
+                    .filter(|cell| !cell.is_empty())
+                    .map(|cell| {
+                        let gid = cell.parse::<u32>().map_err(|_| format!("{}: {:?} isn't a tile id", path.display(), cell))?;
+                        tile_id(path, &layer_name, gid)
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                layers.push(TileLayer { name: layer_name.clone(), tiles });
+            }
+            "object" if !in_tileset => {
+                let class = tag.attr("class").or(tag.attr("type")).unwrap_or("");
+                // tile objects are anchored at their bottom left; everything else at the top left
+                let mut y = number(path, tag, "y")?;
+                if tag.attr("gid").is_some() {
+                    y -= number(path, tag, "height")?;
+                }
+                objects.push(Object {
+                    name: tag.attr("name").unwrap_or("").to_string(),
+                    class: class.to_string(),
+                    x: dimension(path, "object x", number(path, tag, "x")?)?,
+                    y: dimension(path, "object y", y)?,
+                    width: dimension(path, "object width", number(path, tag, "width")?)?,
+                    height: dimension(path, "object height", number(path, tag, "height")?)?,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TiledMap {
+        width: dimension(path, "width", number(path, map, "width")?)?,
+        height: dimension(path, "height", number(path, map, "height")?)?,
+        tile_width: dimension(path, "tile width", number(path, map, "tilewidth")?)?,
+        tile_height: dimension(path, "tile height", number(path, map, "tileheight")?)?,
+        layers,
+        objects,
+    })
+}
+
+// -- TMJ --------------------------------------------------------------------
+
+fn json_number(value: &Value, key: &str) -> i64 {
+    value.get(key).and_then(Value::as_f64).map_or(0, |v| v.round() as i64)
+}
+
+fn json_str<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+fn json_layers(path: &Path, layers: &[Value], map: &mut TiledMap) -> Result<(), String> {
+    for layer in layers {
+        let name = json_str(layer, "name");
+        match json_str(layer, "type") {
+            "tilelayer" => {
+                if layer.get("encoding").and_then(Value::as_str).is_some_and(|e| e != "csv") {
+                    return Err(format!(
+                        "{}: layer {:?} is base64 encoded; set Map > Map Properties > Tile Layer Format to CSV",
+                        path.display(), name
+                    ));
+                }
+                let data = layer.get("data").and_then(Value::as_array)
+                    .ok_or_else(|| format!("{}: layer {:?} has no tile data", path.display(), name))?;
+                let tiles = data.iter()
+                    .map(|gid| {
+                        let gid = gid.as_u64().and_then(|g| u32::try_from(g).ok())
+                            .ok_or_else(|| format!("{}: layer {:?} has a tile id that isn't a number", path.display(), name))?;
+                        tile_id(path, name, gid)
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                map.layers.push(TileLayer { name: name.to_string(), tiles });
+            }
+            "objectgroup" => {
+                for object in layer.get("objects").and_then(Value::as_array).into_iter().flatten() {
+                    let class = match json_str(object, "class") {
+                        "" => json_str(object, "type"),
+                        class => class,
+                    };
+                    let mut y = json_number(object, "y");
+                    if object.get("gid").is_some() {
+                        y -= json_number(object, "height");
+                    }
+                    map.objects.push(Object {
+                        name: json_str(object, "name").to_string(),
+                        class: class.to_string(),
+                        x: dimension(path, "object x", json_number(object, "x"))?,
+                        y: dimension(path, "object y", y)?,
+                        width: dimension(path, "object width", json_number(object, "width"))?,
+                        height: dimension(path, "object height", json_number(object, "height"))?,
+                    });
+                }
+            }
+            "group" => {
+                let children = layer.get("layers").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+                json_layers(path, children, map)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn load_tmj(path: &Path, text: &str) -> Result<TiledMap, String> {
+    let json: Value = serde_json::from_str(text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    if json.get("infinite").and_then(Value::as_bool) == Some(true) {
+        return Err(format!("{}: infinite maps aren't supported; turn off Map > Map Properties > Infinite", path.display()));
+    }
+    if json.get("orientation").and_then(Value::as_str).is_some_and(|o| o != "orthogonal") {
+        return Err(format!("{}: only orthogonal maps are supported", path.display()));
+    }
+
+    let mut map = TiledMap {
+        width: dimension(path, "width", json_number(&json, "width"))?,
+        height: dimension(path, "height", json_number(&json, "height"))?,
+        tile_width: dimension(path, "tile width", json_number(&json, "tilewidth"))?,
+        tile_height: dimension(path, "tile height", json_number(&json, "tileheight"))?,
+        layers: vec![],
+        objects: vec![],
+    };
+    let layers = json.get("layers").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+    json_layers(path, layers, &mut map)?;
+    Ok(map)
+}
@@ -1,11 +1,16 @@
 //! Audio firmware building
 //!
-//! Handles building audio coprocessor firmware from ASM or Rust sources.
+//! Handles building audio coprocessor firmware from ASM or Rust sources, and
+//! picking which firmware the ROM is built with.
 
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::container::{ensure_container, is_in_container, podman_exec};
+use crate::cargo::find_rom_dir;
+use crate::config::{self, CONFIG_FILE};
+use crate::container::{ensure_container, podman_exec, BuildEnv};
+use crate::messages::{child_stdout, status};
 
 /// Get firmware name from directory name
 fn get_firmware_name(path: &Path) -> Result<String, String> {
@@ -17,7 +22,7 @@ fn get_firmware_name(path: &Path) -> Result<String, String> {
 
 /// Build audio firmware (ASM project) - runs directly
 fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), String> {
-    println!("Building ASM audio firmware: {}", name);
+    status!("Building ASM audio firmware: {}", name);
     
     let build_dir = path.join("build");
     std::fs::create_dir_all(&build_dir)
@@ -29,7 +34,7 @@ fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), Str
         let file_path = entry.path();
         if file_path.extension().map_or(false, |ext| ext == "asm") {
             let filename = file_path.file_stem().unwrap().to_string_lossy();
-            println!("  Assembling {}...", filename);
+            status!("  Assembling {}...", filename);
             
             let status = Command::new("llvm-mc")
                 .args([
@@ -40,6 +45,7 @@ fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), Str
                     "-o",
                     build_dir.join(format!("{}.o", filename)).to_str().unwrap(),
                 ])
+                .stdout(child_stdout())
                 .status()
                 .map_err(|e| format!("Failed to assemble: {}", e))?;
             
@@ -70,6 +76,7 @@ fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), Str
     
     let status = Command::new("ld.lld")
         .args(&link_args)
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to link: {}", e))?;
     
@@ -81,6 +88,7 @@ fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), Str
     let bin_path = output_dir.join(format!("{}.bin", name));
     let status = Command::new("llvm-objcopy")
         .args(["-O", "binary", elf_path.to_str().unwrap(), bin_path.to_str().unwrap()])
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to objcopy: {}", e))?;
     
@@ -88,13 +96,13 @@ fn build_audio_asm(path: &Path, name: &str, output_dir: &Path) -> Result<(), Str
         return Err("objcopy failed".to_string());
     }
     
-    println!("Created: {}", bin_path.display());
+    status!("Created: {}", bin_path.display());
     Ok(())
 }
 
 /// Build audio firmware (Rust project) - runs directly
 fn build_audio_rust(path: &Path, name: &str, output_dir: &Path) -> Result<(), String> {
-    println!("Building Rust audio firmware: {}", name);
+    status!("Building Rust audio firmware: {}", name);
     
     // Build with cargo
     let status = Command::new("cargo")
@@ -105,6 +113,7 @@ fn build_audio_rust(path: &Path, name: &str, output_dir: &Path) -> Result<(), St
             "--target", "mos-unknown-none",
             "--release",
         ])
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to run cargo: {}", e))?;
     
@@ -128,6 +137,7 @@ fn build_audio_rust(path: &Path, name: &str, output_dir: &Path) -> Result<(), St
     let bin_path = output_dir.join(format!("{}.bin", name));
     let status = Command::new("llvm-objcopy")
         .args(["-O", "binary", elf_path.to_str().unwrap(), bin_path.to_str().unwrap()])
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to objcopy: {}", e))?;
     
@@ -135,13 +145,13 @@ fn build_audio_rust(path: &Path, name: &str, output_dir: &Path) -> Result<(), St
         return Err("objcopy failed".to_string());
     }
     
-    println!("Created: {}", bin_path.display());
+    status!("Created: {}", bin_path.display());
     Ok(())
 }
 
 /// Build audio firmware (ASM project) - runs inside container
 fn build_audio_asm_in_container(path: &Path, name: &str, output_dir: &Path, working_dir: &Path) -> Result<(), String> {
-    println!("Building ASM audio firmware: {}", name);
+    status!("Building ASM audio firmware: {}", name);
     
     let build_dir = path.join("build");
     std::fs::create_dir_all(&build_dir)
@@ -162,7 +172,7 @@ fn build_audio_asm_in_container(path: &Path, name: &str, output_dir: &Path, work
         let file_path = entry.path();
         if file_path.extension().map_or(false, |ext| ext == "asm") {
             let filename = file_path.file_stem().unwrap().to_string_lossy();
-            println!("  Assembling {}...", filename);
+            status!("  Assembling {}...", filename);
             
             podman_exec("/workspace", &[
                 "llvm-mc",
@@ -211,12 +221,12 @@ fn build_audio_asm_in_container(path: &Path, name: &str, output_dir: &Path, work
         &bin_path,
     ])?;
     
-    println!("Created: {}/{}.bin", output_dir.display(), name);
+    status!("Created: {}/{}.bin", output_dir.display(), name);
     Ok(())
 }
 
 /// Build audio firmware
-pub fn do_audio_build(path_str: &str) -> Result<(), String> {
+pub fn do_audio_build(path_str: &str, env: BuildEnv) -> Result<(), String> {
     let path = Path::new(path_str);
     
     if !path.exists() {
@@ -229,8 +239,10 @@ pub fn do_audio_build(path_str: &str) -> Result<(), String> {
     let working_dir = std::env::current_dir()
         .map_err(|e| format!("Failed to get current directory: {}", e))?;
     
-    // Find gametank/audiofw/ directory
-    let output_dir = if working_dir.join("gametank/audiofw").exists() || working_dir.join("gametank").exists() {
+    // gtrom.toml's audio.output_dir, or find the gametank/audiofw/ directory
+    let output_dir = if let Some(dir) = config::get().audio_output_dir() {
+        dir
+    } else if working_dir.join("gametank/audiofw").exists() || working_dir.join("gametank").exists() {
         working_dir.join("gametank/audiofw")
     } else if working_dir.join("audiofw").exists() || working_dir.file_name().map_or(false, |n| n == "gametank") {
         working_dir.join("audiofw")
@@ -239,8 +251,8 @@ pub fn do_audio_build(path_str: &str) -> Result<(), String> {
         path.join("bin")
     };
     
-    if is_in_container() {
-        // Direct build inside container
+    if env == BuildEnv::Native {
+        // Direct build, inside the container or with a local toolchain
         if path.join("Cargo.toml").exists() {
             build_audio_rust(path, &name, &output_dir)
         } else {
@@ -258,3 +270,144 @@ pub fn do_audio_build(path_str: &str) -> Result<(), String> {
         }
     }
 }
+
+/// Where firmware binaries live: gtrom.toml's `audio.output_dir`, or the SDK crate's audiofw/
+fn firmware_dir(rom_dir: &Path) -> PathBuf {
+    config::get().audio_output_dir().unwrap_or_else(|| rom_dir.join("gametank/audiofw"))
+}
+
+/// Firmware names with an `audio-<name>` feature in the ROM's Cargo.toml, and
+/// the one the default features pick
+fn firmware_features(rom_dir: &Path) -> Result<(Vec<String>, Option<String>), String> {
+    let path = rom_dir.join("Cargo.toml");
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: toml::Table = toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let Some(features) = manifest.get("features").and_then(|f| f.as_table()) else {
+        return Ok((vec![], None));
+    };
+    let names = features.keys()
+        .filter_map(|feature| feature.strip_prefix("audio-"))
+        .map(str::to_string)
+        .collect();
+    let default = features.get("default")
+        .and_then(|d| d.as_array())
+        .and_then(|d| d.iter().filter_map(|f| f.as_str()?.strip_prefix("audio-")).next())
+        .map(str::to_string);
+
+    Ok((names, default))
+}
+
+/// `gtrom audio list`: firmware the ROM can be built with, and which one it uses
+pub fn do_audio_list() -> Result<(), String> {
+    let (_working_dir, rom_dir) = find_rom_dir()?;
+    let dir = firmware_dir(&rom_dir);
+    let (features, default) = firmware_features(&rom_dir)?;
+    let current = config::get().audio_firmware().map(str::to_string).or(default);
+
+    let mut names: BTreeSet<String> = features.iter().cloned().collect();
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        names.extend(entries.filter_map(|e| e.ok()).filter_map(|e| {
+            let path = e.path();
+            if path.extension()? != "bin" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(str::to_string)
+        }));
+    }
+
+    if names.is_empty() {
+        status!("No audio firmware in {}", dir.display());
+        return Ok(());
+    }
+
+    status!("Audio firmware in {}:", dir.display());
+    for name in &names {
+        let marker = if current.as_deref() == Some(name.as_str()) { "*" } else { " " };
+        let binary = dir.join(format!("{}.bin", name));
+        let size = std::fs::metadata(&binary).map(|m| format!("{} bytes", m.len())).unwrap_or_else(|_| "no binary".to_string());
+        let feature = if features.contains(name) { "" } else { " (no audio-* feature in Cargo.toml)" };
+        status!(" {} {:<24} {}{}", marker, name, size, feature);
+    }
+    Ok(())
+}
+
+/// Point the `default` features at `audio-<name>`, leaving other defaults alone
+pub fn set_default_feature(cargo_toml: &str, name: &str) -> Option<String> {
+    let mut in_features = false;
+    let mut changed = false;
+    let lines: Vec<String> = cargo_toml.lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_features = trimmed == "[features]";
+            } else if in_features && trimmed.starts_with("default") && trimmed.contains('=') {
+                let (key, list) = line.split_once('=').unwrap();
+                let mut features: Vec<String> = list.trim().trim_start_matches('[').trim_end_matches(']')
+                    .split(',')
+                    .map(|f| f.trim().to_string())
+                    .filter(|f| !f.is_empty() && !f.starts_with("\"audio-"))
+                    .collect();
+                features.insert(0, format!("\"audio-{}\"", name));
+                changed = true;
+                return format!("{}= [{}]", key, features.join(", "));
+            }
+            line.to_string()
+        })
+        .collect();
+
+    changed.then(|| lines.join("\n") + if cargo_toml.ends_with('\n') { "\n" } else { "" })
+}
+
+/// Set `firmware` under [audio] in gtrom.toml, if it has one
+fn set_config_firmware(config_toml: &str, name: &str) -> Option<String> {
+    let mut in_audio = false;
+    let mut changed = false;
+    let lines: Vec<String> = config_toml.lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_audio = trimmed == "[audio]";
+            } else if in_audio && trimmed.starts_with("firmware") && trimmed.contains('=') {
+                changed = true;
+                return format!("firmware = \"{}\"", name);
+            }
+            line.to_string()
+        })
+        .collect();
+
+    changed.then(|| lines.join("\n") + if config_toml.ends_with('\n') { "\n" } else { "" })
+}
+
+/// `gtrom audio use <name>`: build the ROM with another firmware from now on
+pub fn do_audio_use(name: &str) -> Result<(), String> {
+    let (_working_dir, rom_dir) = find_rom_dir()?;
+    let (features, _default) = firmware_features(&rom_dir)?;
+    if !features.iter().any(|f| f == name) {
+        let known = if features.is_empty() { "none".to_string() } else { features.join(", ") };
+        return Err(format!("No audio-{} feature in {}/Cargo.toml (have: {})", name, rom_dir.display(), known));
+    }
+
+    let binary = firmware_dir(&rom_dir).join(format!("{}.bin", name));
+    if !binary.exists() {
+        status!("Warning: {} doesn't exist yet; build it with `gtrom audio build`", binary.display());
+    }
+
+    let cargo_path = rom_dir.join("Cargo.toml");
+    let cargo_toml = std::fs::read_to_string(&cargo_path).map_err(|e| format!("Failed to read Cargo.toml: {}", e))?;
+    let updated = set_default_feature(&cargo_toml, name)
+        .ok_or_else(|| format!("{} has no `default` features to put audio-{} in", cargo_path.display(), name))?;
+    std::fs::write(&cargo_path, updated).map_err(|e| format!("Failed to write Cargo.toml: {}", e))?;
+
+    // gtrom.toml's choice wins over Cargo.toml's, so it has to follow along
+    if let Some(root) = config::get().root() {
+        let config_path = root.join(CONFIG_FILE);
+        let config_toml = std::fs::read_to_string(&config_path).map_err(|e| format!("Failed to read {}: {}", CONFIG_FILE, e))?;
+        if let Some(updated) = set_config_firmware(&config_toml, name) {
+            std::fs::write(&config_path, updated).map_err(|e| format!("Failed to write {}: {}", CONFIG_FILE, e))?;
+        }
+    }
+
+    status!("Using {} audio firmware", name);
+    Ok(())
+}
@@ -1,11 +1,119 @@
 //! Cargo build orchestration
 //!
 //! Handles running cargo builds for the ROM, both directly and via container.
+//! The ROM crate can be its own workspace, like the template, or one member
+//! of a bigger one next to shared library crates.
 
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::container::podman_exec;
+use clap::Args;
+use gametank_sdk::rom_header::fnv1a;
+
+use crate::config::{self, CONFIG_FILE};
+use crate::container::{podman_exec, TARGET_CACHE};
+use crate::messages::{child_stdout, status};
+
+/// Which cargo profile to build or look at
+#[derive(Args, Clone, Debug, Default)]
+pub struct ProfileArgs {
+    /// Build in release mode (the default)
+    #[arg(short, long, conflicts_with_all = ["debug", "profile"])]
+    pub release: bool,
+
+    /// Build in debug mode
+    #[arg(long, conflicts_with = "profile")]
+    pub debug: bool,
+
+    /// Build with a custom cargo profile
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+}
+
+impl ProfileArgs {
+    /// Profile name to hand cargo
+    pub fn name(&self) -> &str {
+        match (&self.profile, self.debug) {
+            (Some(profile), _) => profile,
+            (None, true) => "dev",
+            (None, false) => "release",
+        }
+    }
+
+    /// Directory cargo puts the profile's output in; `dev` builds go in `debug`
+    pub fn dir(&self) -> &str {
+        match self.name() {
+            "dev" => "debug",
+            name => name,
+        }
+    }
+}
+
+/// Everything that goes into the cargo invocation for a ROM build
+#[derive(Args, Clone, Debug, Default)]
+pub struct BuildArgs {
+    #[command(flatten)]
+    pub profile: ProfileArgs,
+
+    /// Features to enable (space or comma separated)
+    #[arg(short = 'F', long)]
+    pub features: Vec<String>,
+
+    /// Enable all features
+    #[arg(long)]
+    pub all_features: bool,
+
+    /// Don't enable the default features
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// Extra unstable (-Z) flags for cargo, on top of build-std=core
+    #[arg(short = 'Z', value_name = "FLAG")]
+    pub unstable: Vec<String>,
+
+    /// Arguments passed straight through to cargo build
+    #[arg(last = true, value_name = "CARGO_ARGS")]
+    pub cargo_args: Vec<String>,
+}
+
+impl BuildArgs {
+    /// Everything after `cargo +mos`, building the ROM crate `package`
+    fn cargo_command(&self, package: &str) -> Vec<String> {
+        let mut args: Vec<String> = ["build", "-Z", "build-std=core", "--target", "mos-unknown-none", "--profile"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        args.push(self.profile.name().to_string());
+        // in a workspace, only the ROM is a binary for the mos target
+        args.push("-p".to_string());
+        args.push(package.to_string());
+
+        for flag in &self.unstable {
+            args.push("-Z".to_string());
+            args.push(flag.clone());
+        }
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        } else if !self.all_features && !self.no_default_features {
+            // the firmware features are exclusive, so the default one has to go
+            if let Some(firmware) = config::get().audio_firmware() {
+                args.push("--no-default-features".to_string());
+                args.push("--features".to_string());
+                args.push(format!("audio-{}", firmware));
+            }
+        }
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+
+        args.extend(self.cargo_args.iter().cloned());
+        args
+    }
+}
 
 /// Get crate name from Cargo.toml in the given directory
 pub fn get_crate_name(dir: &Path) -> Result<String, String> {
@@ -17,16 +125,96 @@ pub fn get_crate_name(dir: &Path) -> Result<String, String> {
 
 /// Parse crate name from Cargo.toml content
 pub fn parse_crate_name(content: &str) -> Result<String, String> {
-    content.lines()
-        .find(|l| l.trim().starts_with("name"))
-        .and_then(|l| l.split('=').nth(1))
-        .map(|s| s.trim().trim_matches('"').to_string())
-        .ok_or_else(|| "Could not find crate name in Cargo.toml".to_string())
+    let manifest: toml::Table = toml::from_str(content).map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
+    if let Some(name) = manifest.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+        return Ok(name.to_string());
+    }
+    if manifest.contains_key("workspace") {
+        return Err(format!("Cargo.toml is a workspace without a package; point `rom_dir` in {} at the ROM crate", CONFIG_FILE));
+    }
+    Err("Could not find crate name in Cargo.toml".to_string())
+}
+
+fn read_manifest(dir: &Path) -> Option<toml::Table> {
+    let content = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Root of the Cargo workspace `crate_dir` builds in: the nearest directory,
+/// itself included, whose Cargo.toml has a `[workspace]`. That's where cargo
+/// puts `target/`. Crates outside any workspace are their own root.
+pub fn workspace_root(crate_dir: &Path) -> PathBuf {
+    crate_dir.ancestors()
+        .find(|dir| read_manifest(dir).is_some_and(|manifest| manifest.contains_key("workspace")))
+        .unwrap_or(crate_dir)
+        .to_path_buf()
+}
+
+/// Member crates of the workspace rooted at `root`, expanding `dir/*` globs
+pub fn workspace_members(root: &Path) -> Vec<PathBuf> {
+    let Some(manifest) = read_manifest(root) else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace") else {
+        return Vec::new();
+    };
+    let patterns = |key: &str| -> Vec<String> {
+        workspace.get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = patterns("exclude").iter().map(|p| root.join(p)).collect();
+
+    let mut members = Vec::new();
+    for pattern in patterns("members") {
+        match pattern.strip_suffix("/*") {
+            Some(parent) => {
+                let Ok(entries) = std::fs::read_dir(root.join(parent)) else { continue };
+                let mut dirs: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+                dirs.sort();
+                members.extend(dirs);
+            }
+            None => members.push(root.join(pattern)),
+        }
+    }
+    members.retain(|dir| dir.join("Cargo.toml").exists() && !excluded.contains(dir));
+    members
+}
+
+/// Where cargo leaves the ROM's ELF for a profile: the workspace's target dir
+pub fn elf_path(rom_dir: &Path, profile: &ProfileArgs) -> Result<PathBuf, String> {
+    let crate_name = get_crate_name(rom_dir)?;
+    Ok(workspace_root(rom_dir).join(format!("target/mos-unknown-none/{}/{}", profile.dir(), crate_name)))
+}
+
+/// The ROM crate among a workspace's members, when there's exactly one
+fn find_workspace_rom(root: &Path) -> Result<Option<PathBuf>, String> {
+    let roms: Vec<PathBuf> = workspace_members(root).into_iter().filter(|dir| is_rom_crate(dir)).collect();
+    match roms.as_slice() {
+        [] => Ok(None),
+        [rom] => Ok(Some(rom.clone())),
+        _ => Err(format!(
+            "The workspace at {} has several ROM crates ({}); set `rom_dir` in {} to pick one",
+            root.display(),
+            roms.iter().map(|dir| dir.strip_prefix(root).unwrap_or(dir).display().to_string()).collect::<Vec<_>>().join(", "),
+            CONFIG_FILE,
+        )),
+    }
 }
 
 /// Find the ROM directory (either rom/ subdirectory or current dir with Cargo.toml)
-/// Walks up the directory tree to find the project root
+/// Uses gtrom.toml's `rom_dir` when there is one, otherwise walks up the
+/// directory tree to find the project root
 pub fn find_rom_dir() -> Result<(PathBuf, PathBuf), String> {
+    let config = config::get();
+    if let (Some(root), Some(rom_dir)) = (config.root(), config.rom_dir()) {
+        if !rom_dir.join("Cargo.toml").exists() {
+            return Err(format!("{} says the ROM is in {}, but there's no Cargo.toml there", CONFIG_FILE, rom_dir.display()));
+        }
+        return Ok((root.to_path_buf(), rom_dir));
+    }
+
     let mut current_dir = std::env::current_dir()
         .map_err(|e| format!("Failed to get current directory: {}", e))?;
     
@@ -35,15 +223,20 @@ pub fn find_rom_dir() -> Result<(PathBuf, PathBuf), String> {
         // Check if this directory has a rom/ subdirectory with a GameTank project
         if current_dir.join("rom").exists() {
             let rom_dir = current_dir.join("rom");
-            if is_gametank_project(&rom_dir) {
+            if is_rom_crate(&rom_dir) {
                 return Ok((current_dir, rom_dir));
             }
         }
         
         // Check if this directory itself is a GameTank ROM project
-        if is_gametank_project(&current_dir) {
+        if is_rom_crate(&current_dir) {
             return Ok((current_dir.clone(), current_dir));
         }
+
+        // Or a workspace with the ROM as one of its members
+        if let Some(rom_dir) = find_workspace_rom(&current_dir)? {
+            return Ok((current_dir, rom_dir));
+        }
         
         // Move up to parent directory
         if let Some(parent) = current_dir.parent() {
@@ -55,6 +248,15 @@ pub fn find_rom_dir() -> Result<(PathBuf, PathBuf), String> {
     }
 }
 
+/// A GameTank project that builds a ROM, rather than a library crate shared
+/// between members of a workspace
+fn is_rom_crate(dir: &Path) -> bool {
+    let is_library = dir.join("src/lib.rs").exists()
+        && !dir.join("src/main.rs").exists()
+        && !read_manifest(dir).is_some_and(|manifest| manifest.contains_key("bin"));
+    !is_library && is_gametank_project(dir)
+}
+
 /// Check if a directory is a GameTank ROM project
 /// A GameTank project has Cargo.toml and either:
 /// - src/asm/ directory (unique to GameTank projects)
@@ -93,22 +295,15 @@ fn is_gametank_project(dir: &Path) -> bool {
 }
 
 /// Run cargo build for the ROM (runs directly)
-pub fn cargo_build(workdir: &str, release: bool) -> Result<(), String> {
-    println!("Building ROM with cargo...");
-    
-    let mut args = vec![
-        "+mos", "build",
-        "-Z", "build-std=core",
-        "--target", "mos-unknown-none",
-    ];
-    
-    if release {
-        args.push("--release");
-    }
+pub fn cargo_build(workdir: &str, build: &BuildArgs) -> Result<(), String> {
+    status!("Building ROM with cargo...");
 
+    let crate_name = get_crate_name(Path::new(workdir))?;
     let status = Command::new("cargo")
         .current_dir(workdir)
-        .args(&args)
+        .arg("+mos")
+        .args(build.cargo_command(&crate_name))
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to run cargo: {}", e))?;
 
@@ -119,22 +314,43 @@ pub fn cargo_build(workdir: &str, release: bool) -> Result<(), String> {
     }
 }
 
+/// Target dir for a project in the container's cache volume.
+/// Keyed by host path too, since every template project starts out named "rom".
+/// FNV rather than std's hasher, whose output can change between Rust releases
+/// and would orphan the cached builds.
+fn container_target_dir(workdir: &Path, crate_name: &str) -> String {
+    let key = fnv1a(workdir.as_os_str().as_encoded_bytes());
+    format!("{}/{}-{:016x}", TARGET_CACHE, crate_name, key)
+}
+
 /// Run cargo build via container
-pub fn cargo_build_in_container(workdir: &Path, working_dir: &Path, release: bool) -> Result<(), String> {
-    println!("Building ROM with cargo...");
+///
+/// Builds into the persistent target volume, then copies the ELF back to
+/// `target/mos-unknown-none/<profile>/` in the workspace where conversion expects it.
+pub fn cargo_build_in_container(workdir: &Path, working_dir: &Path, build: &BuildArgs) -> Result<(), String> {
+    status!("Building ROM with cargo...");
     
     let rel_workdir = workdir.strip_prefix(working_dir).unwrap_or(workdir);
     let workspace_dir = format!("/workspace/{}", rel_workdir.to_string_lossy());
 
-    let mut args = vec![
-        "cargo", "+mos", "build",
-        "-Z", "build-std=core",
-        "--target", "mos-unknown-none",
-    ];
-    
-    if release {
-        args.push("--release");
-    }
+    let crate_name = get_crate_name(workdir)?;
+    let target_dir = container_target_dir(workdir, &crate_name);
+    let target_env = format!("CARGO_TARGET_DIR={}", target_dir);
+
+    let cargo_command = build.cargo_command(&crate_name);
+    let mut args = vec!["env", &target_env, "cargo", "+mos"];
+    args.extend(cargo_command.iter().map(String::as_str));
+
+    podman_exec(&workspace_dir, &args)?;
+
+    let cargo_workspace = workspace_root(workdir);
+    let rel_cargo_workspace = cargo_workspace.strip_prefix(working_dir).unwrap_or(&cargo_workspace);
+    let local_target = Path::new("/workspace").join(rel_cargo_workspace).join("target");
+    let local_target = local_target.display();
 
-    podman_exec(&workspace_dir, &args)
+    let profile = build.profile.dir();
+    let copy_back = format!(
+        "mkdir -p {local_target}/mos-unknown-none/{profile} && cp {target_dir}/mos-unknown-none/{profile}/{crate_name} {local_target}/mos-unknown-none/{profile}/"
+    );
+    podman_exec(&workspace_dir, &["sh", "-c", &copy_back])
 }
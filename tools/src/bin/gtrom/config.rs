@@ -0,0 +1,224 @@
+//! Project configuration
+//!
+//! `gtrom.toml` sits at the root of a project and pins down what would
+//! otherwise be guessed: where the ROM crate is, which container image and
+//! engine to build with, and so on. Everything is optional.
+//!
+//! ```toml
+//! rom_dir = "rom"
+//! emulator = "gte"
+//!
+//! [container]
+//! engine = "podman"
+//! image = "docker.io/dwbrite/rust-mos:gte"
+//!
+//! [audio]
+//! firmware = "wavetable-8ch"
+//! output_dir = "rom/gametank/audiofw"
+//!
+//! [flash]
+//! port = "/dev/ttyUSB0"
+//!
+//! [assets]
+//! manifest = "assets.toml"
+//!
+//! [package]
+//! title = "My Game"
+//! web_emulator = "gte-web"
+//!
+//! [convert]
+//! header = true
+//!
+//! [memory]
+//! cart = "32k"
+//! zp = { start = 0x40, size = 0xC0 }
+//! ```
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use gametank_sdk::memory_map::MemoryMap;
+
+use crate::container::{ContainerRuntime, IMAGE};
+
+pub const CONFIG_FILE: &str = "gtrom.toml";
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// ROM crate, relative to gtrom.toml
+    rom_dir: Option<PathBuf>,
+    /// Emulator to launch for `gtrom run` and `gtrom watch --run`
+    emulator: Option<PathBuf>,
+    #[serde(default)]
+    container: ContainerConfig,
+    #[serde(default)]
+    audio: AudioConfig,
+    #[serde(default)]
+    flash: FlashConfig,
+    #[serde(default)]
+    assets: AssetsConfig,
+    #[serde(default)]
+    package: PackageConfig,
+    #[serde(default)]
+    convert: ConvertConfig,
+    /// Cart layout and RAM regions the linker script is generated from
+    #[serde(default)]
+    memory: MemoryMap,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ContainerConfig {
+    engine: Option<Engine>,
+    image: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Engine {
+    Podman,
+    Docker,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct AudioConfig {
+    /// Firmware to build the ROM with, as the name of its `audio-*` feature
+    firmware: Option<String>,
+    /// Where `gtrom audio` puts built firmware, relative to gtrom.toml
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FlashConfig {
+    port: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct AssetsConfig {
+    /// Asset manifest, relative to the ROM crate
+    manifest: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct PackageConfig {
+    /// Game title; defaults to the crate name
+    title: Option<String>,
+    /// wasm-bindgen output of gte to bundle, relative to gtrom.toml
+    web_emulator: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConvertConfig {
+    /// Embed the ROM footer: title, build hash and a CRC32 of each bank
+    #[serde(default)]
+    header: bool,
+}
+
+/// Settings from the project's gtrom.toml, or the defaults when there isn't one
+#[derive(Default)]
+pub struct Config {
+    /// Directory gtrom.toml was found in
+    root: Option<PathBuf>,
+    file: ConfigFile,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+impl Config {
+    /// Look for gtrom.toml in the current directory and its parents
+    fn find() -> Result<Self, String> {
+        let cwd = std::env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+
+        let Some(root) = cwd.ancestors().find(|dir| dir.join(CONFIG_FILE).is_file()) else {
+            return Ok(Self::default());
+        };
+
+        let path = root.join(CONFIG_FILE);
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        Ok(Self { root: Some(root.to_path_buf()), file })
+    }
+
+    /// Project root: the directory gtrom.toml is in
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// The ROM crate, if gtrom.toml says where it is
+    pub fn rom_dir(&self) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        // skip `.` so rom_dir = "." doesn't show up in every path
+        let dir: PathBuf = self.file.rom_dir.iter().flat_map(|dir| dir.components()).filter(|c| *c != Component::CurDir).collect();
+        Some(if dir.as_os_str().is_empty() { root.clone() } else { root.join(dir) })
+    }
+
+    pub fn image(&self) -> &str {
+        self.file.container.image.as_deref().unwrap_or(IMAGE)
+    }
+
+    /// Container engine to use instead of detecting one
+    pub fn engine(&self) -> Option<ContainerRuntime> {
+        self.file.container.engine.map(|engine| match engine {
+            Engine::Podman => ContainerRuntime::Podman,
+            Engine::Docker => ContainerRuntime::Docker,
+        })
+    }
+
+    pub fn emulator(&self) -> &Path {
+        self.file.emulator.as_deref().unwrap_or(Path::new("gte"))
+    }
+
+    pub fn audio_firmware(&self) -> Option<&str> {
+        self.file.audio.firmware.as_deref()
+    }
+
+    pub fn audio_output_dir(&self) -> Option<PathBuf> {
+        Some(self.root.as_ref()?.join(self.file.audio.output_dir.as_ref()?))
+    }
+
+    pub fn flash_port(&self) -> Option<&str> {
+        self.file.flash.port.as_deref()
+    }
+
+    pub fn package_title(&self) -> Option<&str> {
+        self.file.package.title.as_deref()
+    }
+
+    pub fn web_emulator(&self) -> Option<PathBuf> {
+        Some(self.root.as_ref()?.join(self.file.package.web_emulator.as_ref()?))
+    }
+
+    pub fn rom_header(&self) -> bool {
+        self.file.convert.header
+    }
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        &self.file.memory
+    }
+
+    /// Asset manifest for a ROM crate
+    pub fn asset_manifest(&self, rom_dir: &Path) -> PathBuf {
+        rom_dir.join(self.file.assets.manifest.as_deref().unwrap_or(Path::new(crate::assets::MANIFEST)))
+    }
+}
+
+/// Read gtrom.toml, once, before anything asks for it
+pub fn load() -> Result<(), String> {
+    let config = Config::find()?;
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// The project's settings
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -5,6 +5,21 @@
 use std::path::Path;
 use std::process::{Command, Stdio};
 
+use crate::cargo::{find_rom_dir, workspace_root};
+use crate::config;
+use crate::messages::{child_stdout, status};
+
+/// Image with the rust-mos toolchain and llvm-mos, unless gtrom.toml picks another
+pub const IMAGE: &str = "docker.io/dwbrite/rust-mos:gte";
+
+/// Named volumes outlive the container, so downloaded crates and build
+/// artifacts survive it being recreated when the workspace changes
+const REGISTRY_VOLUME: &str = "gametank-cargo-registry";
+const GIT_VOLUME: &str = "gametank-cargo-git";
+const TARGET_VOLUME: &str = "gametank-target";
+/// Where the target volume is mounted inside the container
+pub const TARGET_CACHE: &str = "/cache/target";
+
 /// Container runtime to use
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ContainerRuntime {
@@ -13,8 +28,12 @@ pub enum ContainerRuntime {
 }
 
 impl ContainerRuntime {
-    /// Detect which container runtime is available
+    /// Detect which container runtime is available, or check the one gtrom.toml asks for
     pub fn detect() -> Option<Self> {
+        if let Some(engine) = config::get().engine() {
+            return Command::new(engine.as_str()).arg("--version").output().is_ok().then_some(engine);
+        }
+
         // Prefer podman over docker
         if Command::new("podman").arg("--version").output().is_ok() {
             return Some(Self::Podman);
@@ -25,7 +44,7 @@ impl ContainerRuntime {
         None
     }
     
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Self::Podman => "podman",
             Self::Docker => "docker",
@@ -33,6 +52,57 @@ impl ContainerRuntime {
     }
 }
 
+/// Where the llvm-mos toolchain runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildEnv {
+    /// Tools are called directly: inside the container, or from a local install
+    Native,
+    /// Tools are called through `podman exec`/`docker exec`
+    Container,
+}
+
+impl BuildEnv {
+    /// Build natively when already in a container or when asked to; otherwise use
+    /// a container, unless there's no runtime but a local toolchain is installed.
+    pub fn select(no_container: bool) -> Result<Self, String> {
+        if is_in_container() {
+            return Ok(Self::Native);
+        }
+
+        if no_container {
+            if !native_toolchain_available() {
+                return Err("--no-container needs a local llvm-mos toolchain, but `cargo +mos --version` or `llvm-mc --version` failed".to_string());
+            }
+            return Ok(Self::Native);
+        }
+
+        if ContainerRuntime::detect().is_none() {
+            if native_toolchain_available() {
+                status!("No container runtime found; using the local llvm-mos toolchain");
+                return Ok(Self::Native);
+            }
+            return Err("No container runtime or local llvm-mos toolchain found. Please install podman or docker.".to_string());
+        }
+
+        Ok(Self::Container)
+    }
+}
+
+fn tool_runs(cmd: &str, args: &[&str]) -> bool {
+    Command::new(cmd)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Check for a locally installed rust-mos toolchain and llvm-mos binaries
+pub fn native_toolchain_available() -> bool {
+    tool_runs("cargo", &["+mos", "--version"]) && tool_runs("llvm-mc", &["--version"])
+}
+
 /// Check if we're running inside a container
 pub fn is_in_container() -> bool {
     Path::new("/.dockerenv").exists()
@@ -41,10 +111,55 @@ pub fn is_in_container() -> bool {
 }
 
 /// Get the mount root for the container.
-/// This is the current working directory - the user's project root.
+/// This is the directory with gtrom.toml if there is one, otherwise the
+/// current working directory - the user's project root. When the ROM is
+/// part of a Cargo workspace above that, the whole workspace is mounted.
 pub fn get_mount_root() -> Result<std::path::PathBuf, String> {
-    std::env::current_dir()
-        .map_err(|e| format!("Failed to get current directory: {}", e))
+    let root = match config::get().root() {
+        Some(root) => root.to_path_buf(),
+        None => std::env::current_dir()
+            .map_err(|e| format!("Failed to get current directory: {}", e))?,
+    };
+
+    let cargo_workspace = find_rom_dir().ok().map(|(_, rom_dir)| workspace_root(&rom_dir));
+    match cargo_workspace {
+        Some(cargo_workspace) if root.starts_with(&cargo_workspace) => Ok(cargo_workspace),
+        _ => Ok(root),
+    }
+}
+
+/// Image names as the runtimes report them: podman adds the registry, and both leave out `:latest`
+fn normalize_image(image: &str) -> String {
+    let image = image.trim();
+    let image = image.strip_prefix("docker.io/").unwrap_or(image);
+    let image = image.strip_prefix("library/").unwrap_or(image);
+    let has_tag = image.rsplit('/').next().is_some_and(|name| name.contains(':') || name.contains('@'));
+    if has_tag {
+        image.to_string()
+    } else {
+        format!("{}:latest", image)
+    }
+}
+
+/// Whether the running container was started from `image`
+fn container_uses_image(cmd: &str, image: &str) -> bool {
+    Command::new(cmd)
+        .args(["inspect", "--format", "{{.Config.Image}}", "gametank"])
+        .output()
+        .map(|o| normalize_image(&String::from_utf8_lossy(&o.stdout)) == normalize_image(image))
+        .unwrap_or(false)
+}
+
+/// Cargo's home directory inside the image, where the registry and git caches live
+fn image_cargo_home(cmd: &str) -> String {
+    Command::new(cmd)
+        .args(["run", "--rm", config::get().image(), "sh", "-c", "echo ${CARGO_HOME:-$HOME/.cargo}"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|home| home.starts_with('/'))
+        .unwrap_or_else(|| "/root/.cargo".to_string())
 }
 
 /// Ensure the build container is running with the correct mount point
@@ -54,6 +169,7 @@ pub fn ensure_container() -> Result<(std::path::PathBuf, ContainerRuntime), Stri
     
     let mount_root = get_mount_root()?;
     let cmd = runtime.as_str();
+    let image = config::get().image();
     
     // Check if container is already running
     let output = Command::new(cmd)
@@ -84,19 +200,25 @@ pub fn ensure_container() -> Result<(std::path::PathBuf, ContainerRuntime), Stri
         // Clean up marker file
         let _ = std::fs::remove_file(&marker_path);
         
-        if test_output.map(|s| s.success()).unwrap_or(false) {
+        let sees_workspace = test_output.map(|s| s.success()).unwrap_or(false);
+        if sees_workspace && container_uses_image(cmd, image) {
             return Ok((mount_root, runtime));
         }
         
-        // Container can't see our workspace - recreate
-        println!("Workspace changed, recreating container...");
+        // Container can't see our workspace or runs another image - recreate
+        if sees_workspace {
+            status!("Build image changed, recreating container...");
+        } else {
+            status!("Workspace changed, recreating container...");
+        }
         let _ = Command::new(cmd)
             .args(["rm", "-f", "gametank"])
+            .stdout(child_stdout())
             .status();
     }
 
     // Start the container
-    println!("Starting build container with {}...", cmd);
+    status!("Starting build container with {}...", cmd);
 
     // Docker has no "--replace" equivalent so we need to stop and delete the old container
     // Piping stdout to null here since docker complains if the container doesn't exist
@@ -113,10 +235,19 @@ pub fn ensure_container() -> Result<(std::path::PathBuf, ContainerRuntime), Stri
         ContainerRuntime::Docker => format!("{}:/workspace", mount_root.display()),
     };
 
+    // Only the caches get volumes - the toolchain itself in cargo home has to come from the image
+    let cargo_home = image_cargo_home(cmd);
+    let registry_arg = format!("{}:{}/registry", REGISTRY_VOLUME, cargo_home);
+    let git_arg = format!("{}:{}/git", GIT_VOLUME, cargo_home);
+    let target_arg = format!("{}:{}", TARGET_VOLUME, TARGET_CACHE);
+
     let mut start_args = vec![
         "run", "-d", 
         "--name", "gametank", 
-        "-v", &volume_arg
+        "-v", &volume_arg,
+        "-v", &registry_arg,
+        "-v", &git_arg,
+        "-v", &target_arg,
     ];
     
     // Include --replace for the Podman runner
@@ -125,12 +256,13 @@ pub fn ensure_container() -> Result<(std::path::PathBuf, ContainerRuntime), Stri
     }
 
     start_args.extend([
-        "docker.io/dwbrite/rust-mos:gte", 
+        image, 
         "sleep", "infinity"
     ]);
     
     let status = Command::new(cmd)
         .args(start_args)
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to start container: {}", e))?;
 
@@ -147,6 +279,7 @@ pub fn container_exec(runtime: ContainerRuntime, workdir: &str, args: &[&str]) -
     let status = Command::new(cmd)
         .args(["exec", "-t", "-w", workdir, "gametank"])
         .args(args)
+        .stdout(child_stdout())
         .status()
         .map_err(|e| format!("Failed to exec in container: {}", e))?;
 
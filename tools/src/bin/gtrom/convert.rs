@@ -0,0 +1,109 @@
+//! ROM output formats
+//!
+//! `gtrom convert` lays out an ELF the same way a build does, then writes it
+//! as a .gtr for gte and gtld, Intel HEX for EPROM programmers, or one raw
+//! .bin per bank for loaders that flash a bank at a time.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use gametank_sdk::rom_builder::{RomBuilder, RomImage};
+use gametank_sdk::rom_header::fnv1a;
+
+use crate::messages::status;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RomFormat {
+    /// The whole image as one file
+    #[default]
+    Gtr,
+    /// Intel HEX, addressed by offset in the image
+    Ihex,
+    /// A directory with a .bin for each bank that has anything in it
+    Banks,
+}
+
+impl RomFormat {
+    fn default_output(self) -> &'static str {
+        match self {
+            Self::Gtr => "game.gtr",
+            Self::Ihex => "game.hex",
+            Self::Banks => "game-banks",
+        }
+    }
+}
+
+/// Bytes per data record; what most tools write and every programmer reads
+const IHEX_RECORD_LEN: usize = 16;
+
+fn ihex_record(out: &mut String, record_type: u8, address: u16, data: &[u8]) {
+    let [hi, lo] = address.to_be_bytes();
+    // two's complement of the sum of every byte in the record
+    let checksum = [data.len() as u8, hi, lo, record_type].iter().chain(data)
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg();
+
+    let _ = write!(out, ":{:02X}{:04X}{:02X}", data.len(), address, record_type);
+    for b in data {
+        let _ = write!(out, "{:02X}", b);
+    }
+    let _ = writeln!(out, "{:02X}", checksum);
+}
+
+/// `data` as Intel HEX, with an extended linear address record for every 64K past the first
+pub fn intel_hex(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, chunk) in data.chunks(IHEX_RECORD_LEN).enumerate() {
+        let offset = i * IHEX_RECORD_LEN;
+        if offset.is_multiple_of(0x10000) && offset > 0 {
+            ihex_record(&mut out, 0x04, 0, &((offset >> 16) as u16).to_be_bytes());
+        }
+        ihex_record(&mut out, 0x00, offset as u16, chunk);
+    }
+    ihex_record(&mut out, 0x01, 0, &[]);
+    out
+}
+
+/// Write each used bank to `dir` as `bank<N>.bin`, N zero-padded so they sort in bank order
+fn write_banks(image: &RomImage, dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let bank_size = image.bank_size();
+    for (bank, _) in image.bank_usage() {
+        let start = bank as usize * bank_size;
+        let path = dir.join(format!("bank{:03}.bin", bank));
+        std::fs::write(&path, &image.as_bytes()[start..start + bank_size])
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        status!("  {}", path.display());
+    }
+    Ok(())
+}
+
+/// Convert an ELF to a ROM in `format`, with a .map of where everything went next to it.
+/// With a `header_title`, the ROM gets a footer gte and gtld can check it against.
+pub fn do_convert(elf_path: &str, output: Option<&str>, format: RomFormat, header_title: Option<&str>) -> Result<(), String> {
+    let output = Path::new(output.unwrap_or(format.default_output()));
+    status!("Converting ELF: {} -> {}", elf_path, output.display());
+
+    let data = std::fs::read(elf_path).map_err(|e| format!("Could not read ELF file {}: {}", elf_path, e))?;
+    let mut image = RomBuilder::from_elf(&data)?;
+    if let Some(title) = header_title {
+        image.embed_header(title, fnv1a(&data))?;
+    }
+    status!("{}", image.usage_report().trim_end());
+
+    let write = |path: &Path, contents: &[u8]| {
+        std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+    match format {
+        RomFormat::Gtr => write(output, image.as_bytes())?,
+        RomFormat::Ihex => write(output, intel_hex(image.as_bytes()).as_bytes())?,
+        RomFormat::Banks => write_banks(&image, output)?,
+    }
+    write(&output.with_extension("map"), image.map_report().as_bytes())?;
+
+    status!("Created: {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Documentation
+//!
+//! Builds rustdoc for the ROM crate and everything it uses, including the SDK,
+//! and optionally opens it or serves it over HTTP. File URLs don't work from
+//! inside the build container, so `--serve` is the way to read docs there.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::cargo::{find_rom_dir, get_crate_name, workspace_root};
+use crate::container::is_in_container;
+use crate::messages::{child_stdout, status};
+
+/// Where cargo puts the ROM crate's output, which isn't always `target/`
+fn target_dir(rom_dir: &Path) -> PathBuf {
+    Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(rom_dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| serde_json::from_slice::<serde_json::Value>(&o.stdout).ok())
+        .and_then(|metadata| metadata.get("target_directory")?.as_str().map(PathBuf::from))
+        .unwrap_or_else(|| workspace_root(rom_dir).join("target"))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("woff2") => "font/woff2",
+        Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The file under `root` a request path points at, if it stays inside `root`
+fn resolve(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let path = request_path.split(['?', '#']).next()?.trim_start_matches('/');
+    let mut file = root.to_path_buf();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => file.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    file.is_file().then_some(file)
+}
+
+fn respond(mut stream: TcpStream, root: &Path) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(());
+    };
+
+    let file = (method == "GET").then(|| resolve(root, path)).flatten();
+    match file.and_then(|file| std::fs::read(&file).ok().map(|body| (file, body))) {
+        Some((file, body)) => {
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", content_type(&file), body.len())?;
+            stream.write_all(&body)
+        }
+        None => {
+            let body = "Not found";
+            write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+        }
+    }
+}
+
+/// Serve `root` until interrupted, opening `page` in a browser once it's up if asked to
+fn serve(root: &Path, port: u16, page: &str, open_browser: bool) -> Result<(), String> {
+    // inside a container, localhost is only the container's own
+    let host = if is_in_container() { "0.0.0.0" } else { "127.0.0.1" };
+    let listener = TcpListener::bind((host, port)).map_err(|e| format!("Failed to listen on {}:{}: {}", host, port, e))?;
+
+    let url = format!("http://localhost:{}/{}", port, page);
+    status!("Serving documentation at {} (Ctrl+C to stop)", url);
+    if open_browser {
+        open::that(&url).map_err(|e| format!("Failed to open browser: {}", e))?;
+    }
+
+    for stream in listener.incoming().filter_map(|s| s.ok()) {
+        let _ = respond(stream, root);
+    }
+    Ok(())
+}
+
+/// Build the ROM's documentation, then open and/or serve it
+pub fn do_docs(open: bool, serve_docs: bool, port: u16) -> Result<(), String> {
+    let (_working_dir, rom_dir) = find_rom_dir()?;
+
+    status!("Building documentation...");
+
+    let status = Command::new("cargo")
+        .args(["doc", "--document-private-items"])
+        .current_dir(&rom_dir)
+        .stdout(child_stdout())
+        .status()
+        .map_err(|e| format!("Failed to run cargo doc: {}", e))?;
+
+    if !status.success() {
+        return Err("Failed to build documentation".to_string());
+    }
+
+    // rustdoc names the directory after the crate, with - turned into _
+    let crate_dir = get_crate_name(&rom_dir)?.replace('-', "_");
+    let doc_root = target_dir(&rom_dir).join("doc");
+    let page = format!("{}/index.html", crate_dir);
+    let doc_path = doc_root.join(&page);
+    if !doc_path.exists() {
+        return Err(format!("Documentation not found at {}", doc_path.display()));
+    }
+
+    status!("Documentation: {}", doc_path.display());
+
+    if serve_docs {
+        return serve(&doc_root, port, &page, open);
+    }
+
+    if open {
+        status!("Opening documentation...");
+        open::that(&doc_path).map_err(|e| format!("Failed to open browser: {}", e))?;
+    }
+
+    Ok(())
+}
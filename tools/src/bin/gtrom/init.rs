@@ -4,10 +4,15 @@
 
 use std::io::Cursor;
 use std::path::Path;
+use std::process::{Command, Stdio};
 
 use flate2::read::GzDecoder;
 use tar::Archive;
 
+use crate::audio::set_default_feature;
+use crate::config::CONFIG_FILE;
+use crate::messages::{child_stdout, status};
+
 // Embed the SDK template tarball at compile time
 static SDK_TEMPLATE: &[u8] = include_bytes!("../sdk-template.tar.gz");
 
@@ -86,8 +91,107 @@ fn sanitize_crate_name(name: &str) -> String {
     }
 }
 
+const GITIGNORE: &str = "\
+target/
+*.gtr
+*.sym
+*.map
+*.zip
+";
+
+/// Builds the ROM on every push and keeps the .gtr as an artifact
+const CI_WORKFLOW: &str = r#"name: Build ROM
+
+on:
+  push:
+  pull_request:
+
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Install gtrom
+        run: curl --proto '=https' --tlsv1.2 -LsSf https://github.com/dwbrite/gametank-sdk/releases/latest/download/gametank-sdk-installer.sh | sh
+
+      - name: Build
+        run: gtrom build
+
+      - uses: actions/upload-artifact@v4
+        with:
+          name: rom
+          path: |
+            *.gtr
+            *.sym
+"#;
+
+/// Write a .gitignore and CI workflow, and make `dir` a git repo unless it's already in one
+fn init_git(dir: &Path) -> Result<(), String> {
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        std::fs::write(&gitignore, GITIGNORE).map_err(|e| format!("Failed to write .gitignore: {}", e))?;
+    }
+
+    let workflows = dir.join(".github/workflows");
+    std::fs::create_dir_all(&workflows).map_err(|e| format!("Failed to create {}: {}", workflows.display(), e))?;
+    std::fs::write(workflows.join("build.yml"), CI_WORKFLOW).map_err(|e| format!("Failed to write CI workflow: {}", e))?;
+
+    let in_repo = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if in_repo {
+        status!("  Already in a git repository; not running git init");
+        return Ok(());
+    }
+
+    let status = Command::new("git")
+        .arg("init")
+        .arg("--quiet")
+        .current_dir(dir)
+        .stdout(child_stdout())
+        .status()
+        .map_err(|e| format!("Failed to run git (is it installed?): {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err("git init failed".to_string())
+    }
+}
+
+/// Starting gtrom.toml for a new project
+fn default_config(rom_dir: &str, audio: &str) -> String {
+    format!(
+        "# gtrom settings for this project. Everything here is optional.\n\
+         \n\
+         # ROM crate, relative to this file\n\
+         rom_dir = \"{}\"\n\
+         \n\
+         # emulator = \"gte\"\n\
+         \n\
+         # [container]\n\
+         # engine = \"podman\" # or \"docker\"\n\
+         # image = \"docker.io/dwbrite/rust-mos:gte\"\n\
+         \n\
+         [audio]\n\
+         firmware = \"{}\"\n\
+         \n\
+         # [flash]\n\
+         # port = \"/dev/ttyUSB0\"\n\
+         \n\
+         # [assets]\n\
+         # manifest = \"assets.toml\"\n",
+        rom_dir, audio
+    )
+}
+
 /// Initialize a new GameTank project
-pub fn do_init(path: &str, name: Option<&str>, with_audiofw_src: bool, audio: &str) -> Result<(), String> {
+pub fn do_init(path: &str, name: Option<&str>, with_audiofw_src: bool, audio: &str, git: bool) -> Result<(), String> {
     let target_dir = Path::new(path);
     
     // Derive project name from path if not specified, then sanitize
@@ -119,10 +223,13 @@ pub fn do_init(path: &str, name: Option<&str>, with_audiofw_src: bool, audio: &s
         }
     }
     
-    println!("Creating new GameTank project: {}", project_name);
-    println!("  Audio firmware: {}", audio);
+    status!("Creating new GameTank project: {}", project_name);
+    status!("  Audio firmware: {}", audio);
     if with_audiofw_src {
-        println!("  Including audio firmware source");
+        status!("  Including audio firmware source");
+    }
+    if git {
+        status!("  Setting up git and GitHub Actions");
     }
     
     // Create target directory
@@ -144,27 +251,30 @@ pub fn do_init(path: &str, name: Option<&str>, with_audiofw_src: bool, audio: &s
             .map_err(|e| format!("Failed to write Cargo.toml: {}", e))?;
     }
     
-    // Update audio feature if not default
-    if audio != "wavetable-8v" {
-        let cargo_toml_path = target_dir.join("rom/Cargo.toml");
-        if cargo_toml_path.exists() {
-            let content = std::fs::read_to_string(&cargo_toml_path)
-                .map_err(|e| format!("Failed to read Cargo.toml: {}", e))?;
-            let updated = content.replace(
-                "audio = [\"audio-wavetable-8v\"]",
-                &format!("audio = [\"audio-{}\"]", audio)
-            );
+    let rom_dir = if target_dir.join("rom/Cargo.toml").exists() { "rom" } else { "." };
+
+    // same as `gtrom audio use`, so plain cargo builds agree with gtrom.toml
+    let cargo_toml_path = target_dir.join(rom_dir).join("Cargo.toml");
+    if let Ok(content) = std::fs::read_to_string(&cargo_toml_path) {
+        if let Some(updated) = set_default_feature(&content, audio) {
             std::fs::write(&cargo_toml_path, updated)
                 .map_err(|e| format!("Failed to write Cargo.toml: {}", e))?;
         }
     }
-    
-    println!("\nProject created successfully!");
-    println!("\nNext steps:");
+
+    std::fs::write(target_dir.join(CONFIG_FILE), default_config(rom_dir, audio))
+        .map_err(|e| format!("Failed to write {}: {}", CONFIG_FILE, e))?;
+
+    if git {
+        init_git(target_dir)?;
+    }
+
+    status!("\nProject created successfully!");
+    status!("\nNext steps:");
     if path != "." {
-        println!("  cd {}", path);
+        status!("  cd {}", path);
     }
-    println!("  gtrom build");
+    status!("  gtrom build");
     
     Ok(())
 }
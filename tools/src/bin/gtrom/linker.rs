@@ -0,0 +1,32 @@
+//! Linker script generation
+//!
+//! Writes the linker script for gtrom.toml's `[memory]` map into the ROM
+//! crate's `target/`, where the template's build.rs picks it up instead of
+//! its built-in 2MB layout.
+
+use std::path::Path;
+
+use crate::config;
+
+/// Relative to the ROM crate; build.rs looks for it at the same place
+pub const LINKER_SCRIPT: &str = "target/gtrom/linker.ld";
+
+/// Generate the ROM's linker script from the configured memory map
+pub fn write_linker_script(rom_dir: &Path) -> Result<(), String> {
+    let config = config::get();
+    let map = config.memory_map();
+    map.validate()?;
+
+    let path = rom_dir.join(LINKER_SCRIPT);
+    let script = map.linker_script(config.rom_header());
+
+    // leave it untouched when nothing changed, so cargo doesn't rerun build.rs and relink
+    if std::fs::read_to_string(&path).is_ok_and(|old| old == script) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, script).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
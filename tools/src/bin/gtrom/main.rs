@@ -3,11 +3,21 @@
 //! A unified CLI for building, running, and managing GameTank ROM projects.
 
 mod asm;
+mod assets;
 mod audio;
 mod cargo;
+mod config;
 mod container;
+mod convert;
+mod docs;
 mod init;
-mod rom_builder;
+mod linker;
+mod messages;
+mod midi;
+mod package;
+mod setup;
+mod size;
+mod watch;
 
 use std::path::PathBuf;
 use std::process::Command;
@@ -15,11 +25,23 @@ use std::process::Command;
 use clap::{Parser, Subcommand};
 
 use crate::asm::{build_asm, build_asm_in_container};
-use crate::audio::do_audio_build;
-use crate::cargo::{cargo_build, cargo_build_in_container, find_rom_dir, get_crate_name};
-use crate::container::{ensure_container, is_in_container};
+use crate::assets::{build_assets, do_assets, do_slice, parse_tile_size};
+use crate::audio::{do_audio_build, do_audio_list, do_audio_use};
+use crate::cargo::{cargo_build, cargo_build_in_container, elf_path, find_rom_dir, get_crate_name, BuildArgs, ProfileArgs};
+use crate::container::{ensure_container, BuildEnv};
+use crate::convert::{do_convert, RomFormat};
+use crate::docs::do_docs;
 use crate::init::do_init;
-use crate::rom_builder::RomBuilder;
+use crate::linker::write_linker_script;
+use crate::messages::{child_stdout, emit, status, step, Event, MessageFormat};
+use crate::midi::do_midi_convert;
+use crate::package::do_package;
+use crate::setup::do_setup;
+use crate::size::do_size;
+use crate::watch::do_watch;
+use gametank_sdk::rom_builder::RomBuilder;
+use gametank_sdk::rom_header::fnv1a;
+use gametank_sdk::symbols::SymbolMap;
 
 #[derive(Parser)]
 #[command(name = "gtrom")]
@@ -27,31 +49,101 @@ use crate::rom_builder::RomBuilder;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Use a locally installed rust-mos/llvm-mos toolchain instead of the build container
+    #[arg(long, global = true)]
+    no_container: bool,
+
+    /// `json` prints build events as JSON lines on stdout, and everything else on stderr
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Build the ROM (handles container orchestration automatically)
     Build {
-        /// Build in release mode
-        #[arg(short, long, default_value_t = true)]
-        release: bool,
+        #[command(flatten)]
+        build: BuildArgs,
     },
 
-    /// Build audio coprocessor firmware
+    /// Rebuild whenever the ROM's sources, assets or Cargo.toml change
+    Watch {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Launch gte and have it reload the ROM after every build
+        #[arg(long)]
+        run: bool,
+    },
+
+    /// Convert the assets listed in assets.toml (build does this too)
+    Assets {},
+
+    /// Cut a sprite sheet into tiles packed into 128x128 sprite RAM quadrants
+    Slice {
+        /// The sprite sheet
+        image: PathBuf,
+
+        /// Size of each tile, e.g. 16x16
+        #[arg(short, long, value_parser = parse_tile_size)]
+        tile: (u8, u8),
+
+        /// Directory for the .bin and .rs (defaults to the image's)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Prefix for the generated constants (defaults to the file name in caps)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Show the largest functions and statics in each bank of the last build
+    Size {
+        #[command(flatten)]
+        profile: ProfileArgs,
+
+        /// How many symbols to list per bank
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+
+        /// ELF to look at instead of the project's
+        #[arg(long)]
+        elf: Option<PathBuf>,
+    },
+
+    /// Build, list and pick audio coprocessor firmware
     Audio {
-        /// Path to the audio firmware project directory
-        path: String,
+        #[command(subcommand)]
+        command: AudioCommands,
+    },
+
+    /// Turn MIDI files into songs for the SDK's music player
+    Midi {
+        #[command(subcommand)]
+        command: MidiCommands,
     },
 
-    /// Convert an ELF binary to a .gtr ROM file
+    /// Convert an ELF binary to a .gtr ROM file, Intel HEX or per-bank .bins
     Convert {
         /// Path to the ELF binary
         elf_path: String,
 
-        /// Output .gtr file path
+        /// Output file path (a directory for `--format banks`)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// What to write
+        #[arg(short, long, value_enum, default_value_t = RomFormat::Gtr)]
+        format: RomFormat,
+
+        /// Embed a footer with the title, a build hash and each bank's CRC32, for gte and gtld to check
+        #[arg(long)]
+        header: bool,
+
+        /// Title for the footer (defaults to the ELF's file name)
+        #[arg(long, requires = "header")]
+        title: Option<String>,
     },
 
     /// Initialize a new GameTank project
@@ -69,118 +161,234 @@ enum Commands {
         with_audiofw_src: bool,
 
         /// Audio firmware to use
-        #[arg(long, default_value = "wavetable-8v")]
+        #[arg(long, default_value = "wavetable-8ch")]
         audio: String,
+
+        /// Make the project a git repo, with a .gitignore and a GitHub Actions build
+        #[arg(long)]
+        git: bool,
     },
 
     /// Build and run in the emulator (gte)
-    Run {},
+    Run {
+        #[command(flatten)]
+        build: BuildArgs,
+    },
 
     /// Build and flash to cartridge via gtld
     Flash {
-        /// Serial port (auto-detected if not specified)
+        /// Serial port (from gtrom.toml, or auto-detected if not specified)
         #[arg(short, long)]
         port: Option<String>,
+
+        #[command(flatten)]
+        build: BuildArgs,
+    },
+
+    /// Build and bundle the ROM, metadata, art and a web player into a zip
+    Package {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Output zip (defaults to <name>-<version>.zip)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// wasm-bindgen build of gte to include as a web player
+        #[arg(long, value_name = "DIR")]
+        web: Option<PathBuf>,
+    },
+
+    /// Build documentation for the ROM and the SDK
+    Docs {
+        /// Open the docs in a browser
+        #[arg(long)]
+        open: bool,
+
+        /// Serve the docs over HTTP, for when file URLs don't work (e.g. in a container)
+        #[arg(long)]
+        serve: bool,
+
+        /// Port for --serve
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
     },
 
-    /// Build and open SDK documentation in your browser
-    Docs {},
+    /// Check for (and fetch) everything needed to build ROMs
+    Setup {},
 }
 
-/// Convert ELF to GTR
-fn convert_elf_to_gtr(elf_path: &str, output: &str) -> Result<(), String> {
-    println!("Converting ELF to GTR: {} -> {}", elf_path, output);
-    RomBuilder::build(elf_path.to_string(), output.to_string());
-    Ok(())
+#[derive(Subcommand)]
+enum AudioCommands {
+    /// Build audio coprocessor firmware
+    Build {
+        /// Path to the audio firmware project directory
+        path: String,
+    },
+
+    /// List the firmware the ROM can use, marking the current one
+    List {},
+
+    /// Build the ROM with another firmware from now on
+    Use {
+        /// Firmware name, as in `gtrom audio list`
+        name: String,
+    },
 }
 
-/// Build and open SDK documentation
-fn do_docs() -> Result<(), String> {
-    let (_working_dir, rom_dir) = find_rom_dir()?;
-    
-    println!("Building documentation...");
-    
-    let status = Command::new("cargo")
-        .args(["doc", "--document-private-items"])
-        .current_dir(&rom_dir)
-        .status()
-        .map_err(|e| format!("Failed to run cargo doc: {}", e))?;
-    
-    if !status.success() {
-        return Err("Failed to build documentation".to_string());
-    }
-    
-    // Open the SDK docs directly
-    let doc_path = rom_dir.join("target/doc/gametank/index.html");
-    if !doc_path.exists() {
-        return Err(format!("Documentation not found at {:?}", doc_path));
-    }
-    
-    println!("Opening documentation...");
-    open::that(&doc_path).map_err(|e| format!("Failed to open browser: {}", e))?;
-    
-    Ok(())
+#[derive(Subcommand)]
+enum MidiCommands {
+    /// Convert a .mid file to a .gtsong, spreading its notes over the voices
+    Convert {
+        /// The MIDI file
+        input: PathBuf,
+
+        /// Where to write the song (defaults to the input with a .gtsong extension)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// How many voices the song can use
+        #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(1..=8))]
+        channels: u8,
+
+        /// Rows per quarter note to quantize to (defaults to as many as the tempo allows)
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+        rows_per_beat: Option<u8>,
+
+        /// Volume for the loudest notes (defaults to the firmware's maximum: 63, or 16 for wavetable-7ch-linear)
+        #[arg(long)]
+        max_volume: Option<u8>,
+
+        /// Loop back to the start at the end, on a bar line, instead of stopping
+        #[arg(long = "loop")]
+        looping: bool,
+    },
 }
 
 /// Full build process
-fn do_build(release: bool) -> Result<PathBuf, String> {
+pub(crate) fn do_build(build: &BuildArgs, env: BuildEnv) -> Result<PathBuf, String> {
     let (working_dir, rom_dir) = find_rom_dir()?;
 
-    if is_in_container() {
-        // Direct build inside container
+    // Asset conversion runs on the host, like the GTR conversion
+    step("assets", || build_assets(&rom_dir))?;
+    step("linker", || write_linker_script(&rom_dir))?;
+
+    if env == BuildEnv::Native {
+        // Direct build, inside the container or with a local toolchain
         let rom_dir_str = rom_dir.to_string_lossy().to_string();
-        build_asm(&rom_dir_str)?;
-        cargo_build(&rom_dir_str, release)?;
+        step("asm", || build_asm(&rom_dir_str))?;
+        step("cargo", || cargo_build(&rom_dir_str, build))?;
     } else {
         // Orchestrate from outside container
-        let (workspace_root, _runtime) = ensure_container()?;
-        build_asm_in_container(&rom_dir, &workspace_root)?;
-        cargo_build_in_container(&rom_dir, &workspace_root, release)?;
+        let (workspace_root, _runtime) = step("container", ensure_container)?;
+        step("asm", || build_asm_in_container(&rom_dir, &workspace_root))?;
+        step("cargo", || cargo_build_in_container(&rom_dir, &workspace_root, build))?;
     }
 
     let crate_name = get_crate_name(&rom_dir)?;
 
     // Convert to GTR (runs on host, doesn't need llvm)
-    let profile = if release { "release" } else { "debug" };
-    let elf_path = rom_dir.join(format!("target/mos-unknown-none/{}/{}", profile, crate_name));
+    let elf_path = elf_path(&rom_dir, &build.profile)?;
     let gtr_path = working_dir.join(format!("{}.gtr", crate_name));
-    
-    convert_elf_to_gtr(
-        elf_path.to_str().unwrap(),
-        gtr_path.to_str().unwrap(),
-    )?;
+    let map_path = gtr_path.with_extension("map");
+    let elf_data = std::fs::read(&elf_path).map_err(|e| format!("Could not read {}: {}", elf_path.display(), e))?;
+    emit(Event::Artifact { kind: "elf", path: &elf_path });
+
+    step("convert", || {
+        status!("Converting ELF to GTR: {} -> {}", elf_path.display(), gtr_path.display());
+        let mut image = RomBuilder::from_elf(&elf_data)?;
+        if config::get().rom_header() {
+            let title = config::get().package_title().map_or(crate_name.clone(), String::from);
+            image.embed_header(&title, fnv1a(&elf_data))?;
+        }
+        status!("{}", image.usage_report().trim_end());
+        emit(Event::Banks { banks: messages::bank_report(&image) });
+        std::fs::write(&gtr_path, image.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", gtr_path.display(), e))?;
+        std::fs::write(&map_path, image.map_report())
+            .map_err(|e| format!("Failed to write {}: {}", map_path.display(), e))
+    })?;
+    emit(Event::Artifact { kind: "gtr", path: &gtr_path });
+    emit(Event::Artifact { kind: "map", path: &map_path });
 
-    println!("Build complete: {}", gtr_path.display());
+    // symbols for gte, bank-aware so it can tell banked code apart
+    let sym_path = gtr_path.with_extension("sym");
+    step("symbols", || {
+        let symbols = SymbolMap::from_elf(&elf_data)?;
+        std::fs::write(&sym_path, symbols.to_sym_file())
+            .map_err(|e| format!("Failed to write {}: {}", sym_path.display(), e))
+    })?;
+    emit(Event::Artifact { kind: "sym", path: &sym_path });
+
+    status!("Build complete: {}", gtr_path.display());
     Ok(gtr_path)
 }
 
 fn main() {
     let cli = Cli::parse();
+    let no_container = cli.no_container;
+    messages::set_format(cli.message_format);
+
+    if let Err(e) = config::load() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 
     let result: Result<(), String> = match cli.command {
-        Commands::Build { release } => {
-            do_build(release).map(|_| ())
+        Commands::Build { build } => {
+            BuildEnv::select(no_container).and_then(|env| do_build(&build, env)).map(|_| ())
         }
         
-        Commands::Audio { path } => {
-            do_audio_build(&path)
+        Commands::Watch { build, run } => {
+            BuildEnv::select(no_container).and_then(|env| do_watch(&build, run, env))
         }
+
+        Commands::Assets {} => {
+            do_assets()
+        }
+
+        Commands::Slice { image, tile, output, name } => {
+            do_slice(&image, tile, output.as_deref(), name.as_deref())
+        }
+
+        Commands::Size { profile, count, elf } => {
+            do_size(&profile, count, elf)
+        }
+
+        Commands::Audio { command } => match command {
+            AudioCommands::Build { path } => {
+                BuildEnv::select(no_container).and_then(|env| do_audio_build(&path, env))
+            }
+            AudioCommands::List {} => do_audio_list(),
+            AudioCommands::Use { name } => do_audio_use(&name),
+        },
         
-        Commands::Convert { elf_path, output } => {
-            let out = output.unwrap_or_else(|| "game.gtr".to_string());
-            convert_elf_to_gtr(&elf_path, &out)
+        Commands::Midi { command } => match command {
+            MidiCommands::Convert { input, output, channels, rows_per_beat, max_volume, looping } => {
+                do_midi_convert(&input, output.as_deref(), channels, rows_per_beat, max_volume, looping)
+            }
+        },
+
+        Commands::Convert { elf_path, output, format, header, title } => {
+            let header = header.then(|| title.unwrap_or_else(|| {
+                PathBuf::from(&elf_path).file_stem().map_or("game".to_string(), |s| s.to_string_lossy().to_string())
+            }));
+            do_convert(&elf_path, output.as_deref(), format, header.as_deref())
         }
 
-        Commands::Init { path, name, with_audiofw_src, audio } => {
-            do_init(&path, name.as_deref(), with_audiofw_src, &audio)
+        Commands::Init { path, name, with_audiofw_src, audio, git } => {
+            do_init(&path, name.as_deref(), with_audiofw_src, &audio, git)
         }
         
-        Commands::Run {} => {
-            do_build(true).and_then(|gtr_path| {
+        Commands::Run { build } => {
+            BuildEnv::select(no_container).and_then(|env| do_build(&build, env)).and_then(|gtr_path| {
                 // Launch emulator
-                println!("Launching emulator...");
-                let status = Command::new("gte")
+                status!("Launching emulator...");
+                let status = Command::new(config::get().emulator())
                     .arg(&gtr_path)
+                    .arg("--sym")
+                    .arg(gtr_path.with_extension("sym"))
+                    .stdout(child_stdout())
                     .status()
                     .map_err(|e| format!("Failed to launch gte: {}", e))?;
                 
@@ -192,19 +400,20 @@ fn main() {
             })
         }
         
-        Commands::Flash { port } => {
-            do_build(true).and_then(|gtr_path| {
+        Commands::Flash { port, build } => {
+            BuildEnv::select(no_container).and_then(|env| do_build(&build, env)).and_then(|gtr_path| {
                 // Flash via gtld
-                println!("Flashing to cartridge...");
+                status!("Flashing to cartridge...");
                 let gtr_str = gtr_path.to_string_lossy().to_string();
                 let mut args = vec!["load".to_string(), gtr_str];
-                if let Some(ref p) = port {
+                if let Some(p) = port.as_deref().or(config::get().flash_port()) {
                     args.push("--port".to_string());
-                    args.push(p.clone());
+                    args.push(p.to_string());
                 }
                 
                 let status = Command::new("gtld")
                     .args(&args)
+                    .stdout(child_stdout())
                     .status()
                     .map_err(|e| format!("Failed to run gtld: {}", e))?;
                 
@@ -216,11 +425,25 @@ fn main() {
             })
         }
 
-        Commands::Docs {} => {
-            do_docs()
+        Commands::Package { build, output, web } => {
+            BuildEnv::select(no_container)
+                .and_then(|env| do_build(&build, env))
+                .and_then(|gtr_path| do_package(&gtr_path, output, web))
+        }
+
+        Commands::Docs { open, serve, port } => {
+            do_docs(open, serve, port)
+        }
+
+        Commands::Setup {} => {
+            do_setup()
         }
     };
 
+    if let Err(e) = &result {
+        emit(Event::Error { message: e });
+    }
+    emit(Event::Finished { success: result.is_ok() });
     if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
@@ -0,0 +1,131 @@
+//! Build output for people or programs
+//!
+//! With `--message-format json`, stdout carries one JSON event per line for
+//! editors and gtgo to follow a build, and everything meant for people
+//! (including the tools gtrom runs) moves to stderr.
+//!
+//! ```text
+//! {"event":"step-started","step":"cargo"}
+//! {"event":"step-finished","step":"cargo","success":true,"duration_ms":5120}
+//! {"event":"artifact","kind":"gtr","path":"/home/me/my-game/my-game.gtr"}
+//! ```
+
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use gametank_sdk::rom_builder::RomImage;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+static FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+pub fn set_format(format: MessageFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn is_json() -> bool {
+    FORMAT.get().copied().unwrap_or_default() == MessageFormat::Json
+}
+
+/// `println!` for progress meant for people; goes to stderr when stdout is JSON
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::messages::is_json() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use status;
+
+/// Where a child process's stdout should go, so it doesn't end up in the JSON
+pub fn child_stdout() -> Stdio {
+    if is_json() {
+        std::io::stderr().into()
+    } else {
+        Stdio::inherit()
+    }
+}
+
+#[derive(Serialize)]
+pub struct BankSection {
+    pub name: String,
+    pub size: usize,
+    pub offset: usize,
+}
+
+#[derive(Serialize)]
+pub struct BankReport {
+    pub bank: u8,
+    pub used: usize,
+    pub size: usize,
+    pub sections: Vec<BankSection>,
+    /// What `#[bank]` put here, e.g. `fn draw_background`
+    pub items: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    StepStarted { step: &'a str },
+    StepFinished { step: &'a str, success: bool, duration_ms: u64 },
+    /// `kind` is `elf`, `gtr`, `map`, `sym` or `zip`
+    Artifact { kind: &'a str, path: &'a Path },
+    Banks { banks: Vec<BankReport> },
+    Error { message: &'a str },
+    Finished { success: bool },
+}
+
+/// Print `event` as a line of JSON, if that's what was asked for
+pub fn emit(event: Event) {
+    if is_json() {
+        match serde_json::to_string(&event) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("Failed to serialize event: {}", e),
+        }
+    }
+}
+
+/// Run one step of a build between started and finished events
+pub fn step<T>(name: &str, f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    emit(Event::StepStarted { step: name });
+    let start = Instant::now();
+    let result = f();
+    emit(Event::StepFinished {
+        step: name,
+        success: result.is_ok(),
+        duration_ms: start.elapsed().as_millis() as u64,
+    });
+    result
+}
+
+/// How full each bank of `image` is, for the `banks` event
+pub fn bank_report(image: &RomImage) -> Vec<BankReport> {
+    image.bank_usage()
+        .into_iter()
+        .map(|(bank, used)| BankReport {
+            bank,
+            used,
+            size: image.bank_size(),
+            sections: image.sections.iter()
+                .filter(|s| s.bank == bank)
+                .map(|s| BankSection { name: s.display_name.clone(), size: s.size, offset: s.bank_loc })
+                .collect(),
+            items: image.inventory.iter()
+                .filter(|item| item.bank == bank)
+                .map(|item| item.to_string())
+                .collect(),
+        })
+        .collect()
+}
@@ -0,0 +1,316 @@
+//! MIDI import
+//!
+//! Turns a Standard MIDI File into a song for the SDK's music player: every
+//! track and MIDI channel's notes are quantized to rows and spread over the
+//! wavetable voices, a voice per note, preferring the voice that part
+//! played last so melodies stay on one voice.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use gametank_sdk::song::{ChannelCommand, PatternWriter, Song, SongCommand, MAX_CHANNELS, MIN_BPM};
+
+use crate::config;
+use crate::messages::status;
+
+/// Rows per pattern, as in the tracker
+const PATTERN_ROWS: u32 = 64;
+/// MIDI channel 10, which General MIDI keeps for drums
+const PERCUSSION: u8 = 9;
+/// Tempo until the file says otherwise: 120bpm
+const DEFAULT_TEMPO: u32 = 500_000;
+
+struct Note {
+    start: u64,
+    end: u64,
+    key: u8,
+    velocity: u8,
+    /// Which track and MIDI channel it's from
+    part: (usize, u8),
+}
+
+struct Midi {
+    /// Ticks per quarter note
+    division: u16,
+    notes: Vec<Note>,
+    /// Microseconds per quarter note from each tick on
+    tempos: BTreeMap<u64, u32>,
+    /// Notes left out for being on the drum channel
+    percussion: usize,
+}
+
+/// A variable-length quantity: 7 bits a byte, most significant first
+fn read_var(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn read_track(data: &[u8], track: usize, midi: &mut Midi) -> Option<()> {
+    let mut pos = 0;
+    let mut tick = 0u64;
+    let mut running = 0u8;
+    // notes sounding, by channel and key, oldest first
+    let mut open: BTreeMap<(u8, u8), Vec<(u64, u8)>> = BTreeMap::new();
+
+    while pos < data.len() {
+        tick += read_var(data, &mut pos)? as u64;
+        let mut status = *data.get(pos)?;
+        if status & 0x80 != 0 {
+            pos += 1;
+        } else {
+            // running status: the last channel message's, with new data
+            status = running;
+        }
+
+        match status {
+            0xFF => {
+                let kind = *data.get(pos)?;
+                pos += 1;
+                let len = read_var(data, &mut pos)? as usize;
+                let body = data.get(pos..pos + len)?;
+                pos += len;
+                match kind {
+                    0x51 if len == 3 => {
+                        midi.tempos.insert(tick, u32::from_be_bytes([0, body[0], body[1], body[2]]));
+                    }
+                    0x2F => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = read_var(data, &mut pos)? as usize;
+                pos += len;
+            }
+            0x80..=0xEF => {
+                running = status;
+                let channel = status & 0x0F;
+                let len = if matches!(status & 0xF0, 0xC0 | 0xD0) { 1 } else { 2 };
+                let body = data.get(pos..pos + len)?;
+                pos += len;
+
+                match (status & 0xF0, body) {
+                    (0x90, &[key, velocity]) if velocity > 0 => {
+                        open.entry((channel, key)).or_default().push((tick, velocity));
+                    }
+                    (0x80 | 0x90, &[key, _]) => {
+                        let started = open.get_mut(&(channel, key)).filter(|o| !o.is_empty()).map(|o| o.remove(0));
+                        if let Some((start, velocity)) = started {
+                            midi.push(Note { start, end: tick, key, velocity, part: (track, channel) });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    // anything still sounding stops with the track
+    for ((channel, key), notes) in open {
+        for (start, velocity) in notes {
+            midi.push(Note { start, end: tick, key, velocity, part: (track, channel) });
+        }
+    }
+    Some(())
+}
+
+impl Midi {
+    fn push(&mut self, note: Note) {
+        if note.part.1 == PERCUSSION {
+            self.percussion += 1;
+        } else {
+            self.notes.push(note);
+        }
+    }
+
+    fn parse(data: &[u8], path: &Path) -> Result<Self, String> {
+        let bad = || format!("{} isn't a MIDI file, or is cut short", path.display());
+        if data.len() < 14 || &data[0..4] != b"MThd" {
+            return Err(bad());
+        }
+        let division = u16::from_be_bytes([data[12], data[13]]);
+        if division & 0x8000 != 0 {
+            return Err(format!("{} is timed in SMPTE frames; only beat-timed MIDI files can be converted", path.display()));
+        }
+
+        let mut midi = Midi { division: division.max(1), notes: vec![], tempos: BTreeMap::new(), percussion: 0 };
+        let mut pos = 8 + u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+        let mut track = 0;
+        while pos + 8 <= data.len() {
+            let len = u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let body = data.get(pos + 8..pos + 8 + len).ok_or_else(bad)?;
+            if &data[pos..pos + 4] == b"MTrk" {
+                read_track(body, track, &mut midi).ok_or_else(bad)?;
+                track += 1;
+            }
+            pos += 8 + len;
+        }
+        Ok(midi)
+    }
+}
+
+/// Song rows a minute at `tempo` microseconds per quarter note
+fn rows_per_minute(tempo: u32, rows_per_beat: u8) -> u32 {
+    60_000_000 * rows_per_beat as u32 / tempo.max(1)
+}
+
+/// What happens on a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    Tempo(u8),
+    NoteOff(u8),
+    Note { voice: u8, key: u8, volume: u8 },
+}
+
+pub fn do_midi_convert(
+    input: &Path,
+    output: Option<&Path>,
+    channels: u8,
+    rows_per_beat: Option<u8>,
+    max_volume: Option<u8>,
+    looping: bool,
+) -> Result<(), String> {
+    let data = std::fs::read(input).map_err(|e| format!("Could not read {}: {}", input.display(), e))?;
+    let mut midi = Midi::parse(&data, input)?;
+    if midi.notes.is_empty() {
+        return Err(format!("{} has no notes to convert", input.display()));
+    }
+    let channels = channels.clamp(1, MAX_CHANNELS);
+    let max_volume = max_volume.unwrap_or(match config::get().audio_firmware() {
+        Some("wavetable-7ch-linear") => 16,
+        _ => 63,
+    });
+
+    // as fine a grid as the starting tempo leaves room for
+    let first_tempo = midi.tempos.get(&0).copied().unwrap_or(DEFAULT_TEMPO);
+    let rows_per_beat = rows_per_beat.unwrap_or_else(|| {
+        [8, 6, 4, 3, 2, 1].into_iter()
+            .find(|rows| rows_per_minute(first_tempo, *rows) <= 255)
+            .unwrap_or(1)
+    }).max(1);
+    let bpm = |tempo| rows_per_minute(tempo, rows_per_beat).clamp(MIN_BPM as u32, 255) as u8;
+    if rows_per_minute(first_tempo, rows_per_beat) > 255 {
+        status!("Warning: {} rows per beat is too fast for the player at this tempo; slowing down to 255 rows a minute", rows_per_beat);
+    }
+
+    let division = midi.division as u64;
+    let row = |tick: u64| ((tick * rows_per_beat as u64 + division / 2) / division) as u32;
+
+    let mut events: BTreeMap<u32, Vec<Event>> = BTreeMap::new();
+    for (&tick, &tempo) in midi.tempos.range(1..) {
+        events.entry(row(tick)).or_default().push(Event::Tempo(bpm(tempo)));
+    }
+
+    // a voice per note, preferring one the part played last
+    midi.notes.sort_by_key(|n| (n.start, n.part, n.key));
+    let mut busy_until = vec![0u32; channels as usize];
+    let mut last_part: Vec<Option<(usize, u8)>> = vec![None; channels as usize];
+    let mut dropped = 0;
+    let mut end = 0;
+    for note in &midi.notes {
+        let start = row(note.start);
+        let stop = row(note.end).max(start + 1);
+        let free = |v: &usize| busy_until[*v] <= start;
+        let voice = (0..channels as usize).filter(free).find(|v| last_part[*v] == Some(note.part))
+            .or_else(|| (0..channels as usize).filter(free).find(|v| last_part[*v].is_none()))
+            .or_else(|| (0..channels as usize).find(free));
+        let Some(voice) = voice else {
+            dropped += 1;
+            continue;
+        };
+        busy_until[voice] = stop;
+        last_part[voice] = Some(note.part);
+        end = end.max(stop);
+
+        let volume = ((note.velocity as u32 * max_volume as u32 + 63) / 127).max(1) as u8;
+        events.entry(start).or_default().push(Event::Note { voice: voice as u8, key: note.key, volume });
+        events.entry(stop).or_default().push(Event::NoteOff(voice as u8));
+    }
+
+    // loops come round on a bar line, taking bars as 4 beats
+    let bar = rows_per_beat as u32 * 4;
+    let rows = if looping { end.div_ceil(bar) * bar } else { end + 1 };
+    if looping {
+        // notes ending right on the loop point stop as it comes round
+        if let Some(wrapped) = events.remove(&rows) {
+            events.entry(0).or_default().extend(wrapped);
+        }
+    }
+
+    let mut patterns = vec![];
+    let mut volumes = vec![None; channels as usize];
+    for first_row in (0..rows).step_by(PATTERN_ROWS as usize) {
+        let len = PATTERN_ROWS.min(rows - first_row);
+        let mut pattern = PatternWriter::new();
+        for (&at, row_events) in events.range(first_row..first_row + len) {
+            pattern.seek((at - first_row) as u16);
+
+            let mut row_events = row_events.clone();
+            row_events.sort();
+            let starting: Vec<u8> = row_events.iter().filter_map(|e| match e {
+                Event::Note { voice, .. } => Some(*voice),
+                _ => None,
+            }).collect();
+
+            for event in row_events {
+                match event {
+                    Event::Tempo(bpm) => pattern.song(SongCommand::Tempo(bpm)),
+                    // a new note on the voice cuts the old one off anyway
+                    Event::NoteOff(voice) if starting.contains(&voice) => {}
+                    Event::NoteOff(voice) => pattern.channel(voice, ChannelCommand::NoteOff),
+                    Event::Note { voice, key, volume } => {
+                        if volumes[voice as usize] != Some(volume) {
+                            pattern.channel(voice, ChannelCommand::Volume(volume));
+                            volumes[voice as usize] = Some(volume);
+                        }
+                        pattern.channel(voice, ChannelCommand::Note(key));
+                    }
+                }
+            }
+        }
+        if !looping && first_row + len == rows {
+            pattern.seek(len as u16 - 1);
+            pattern.song(SongCommand::Stop);
+        }
+        patterns.push(pattern.finish(len as u16));
+    }
+
+    // the order plays each pattern once, and holds at most 255 of them
+    if patterns.len() > 255 {
+        return Err(format!(
+            "{} is {} rows long, which takes {} patterns; a song holds at most 255 ({} rows at {} rows per beat)",
+            input.display(), rows, patterns.len(), 255 * PATTERN_ROWS, rows_per_beat
+        ));
+    }
+
+    let song = Song {
+        bpm: bpm(first_tempo),
+        channels,
+        order: (0..patterns.len()).map(|i| i as u8).collect(),
+        patterns,
+    };
+    let bytes = song.to_bytes()?;
+
+    let output = output.map(PathBuf::from).unwrap_or_else(|| input.with_extension("gtsong"));
+    std::fs::write(&output, &bytes).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    status!(
+        "Converted {} -> {}: {} rows at {} rows per beat, {} patterns, {} bytes",
+        input.display(), output.display(), rows, rows_per_beat, song.order.len(), bytes.len()
+    );
+    if dropped > 0 {
+        status!("Warning: dropped {} notes that had more than {} playing at once", dropped, channels);
+    }
+    if midi.percussion > 0 {
+        status!("Warning: left out {} notes on the drum channel (10)", midi.percussion);
+    }
+    Ok(())
+}
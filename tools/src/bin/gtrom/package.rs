@@ -0,0 +1,185 @@
+//! Distributable packages
+//!
+//! Bundles a built ROM into a zip with a metadata file, box art and
+//! screenshot if the project has them, and a web folder that plays the ROM
+//! in the browser build of gte.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::cargo::find_rom_dir;
+use crate::config::{self, CONFIG_FILE};
+use crate::messages::{emit, status, Event};
+
+/// Written into the package as game.toml
+#[derive(Serialize)]
+struct Metadata {
+    title: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<String>,
+    rom: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    box_art: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screenshot: Option<String>,
+}
+
+/// Looked for in the project root, then the ROM crate
+const BOX_ART: &[&str] = &["boxart", "box-art", "box_art"];
+const SCREENSHOT: &[&str] = &["screenshot"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// The [package] fields of the ROM's Cargo.toml that end up in game.toml
+fn cargo_package(rom_dir: &Path) -> Result<toml::Table, String> {
+    let path = rom_dir.join("Cargo.toml");
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: toml::Table = toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    match manifest.get("package") {
+        Some(toml::Value::Table(package)) => Ok(package.clone()),
+        _ => Err(format!("{} has no [package]", path.display())),
+    }
+}
+
+fn find_image(dirs: &[&Path], stems: &[&str]) -> Option<PathBuf> {
+    dirs.iter()
+        .flat_map(|dir| stems.iter().flat_map(move |stem| IMAGE_EXTENSIONS.iter().map(move |ext| dir.join(format!("{}.{}", stem, ext)))))
+        .find(|path| path.is_file())
+}
+
+/// Page that loads gte's wasm module and hands it the ROM
+fn index_html(title: &str, module: &str, rom: &str) -> String {
+    let title = title.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+  html, body {{ margin: 0; height: 100%; background: #000; }}
+  #gt-canvas {{ display: block; width: 100%; height: 100%; }}
+</style>
+</head>
+<body>
+<canvas id="gt-canvas"></canvas>
+<script type="module">
+  import init, {{ update_rom_data }} from "./{module}.js";
+  await init();
+  const rom = await fetch("./{rom}").then((response) => response.arrayBuffer());
+  update_rom_data(new Uint8Array(rom));
+</script>
+</body>
+</html>
+"#)
+}
+
+/// Name of the wasm-bindgen module in `dir`: `gte` for `gte.js` and `gte_bg.wasm`
+fn web_module(dir: &Path) -> Result<String, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str()?.strip_suffix("_bg.wasm").map(str::to_string))
+        .find(|module| dir.join(format!("{}.js", module)).is_file())
+        .ok_or_else(|| format!("{} doesn't look like a wasm-bindgen build of gte (no <name>.js next to <name>_bg.wasm)", dir.display()))
+}
+
+struct Package {
+    zip: ZipWriter<File>,
+    prefix: String,
+    options: SimpleFileOptions,
+}
+
+impl Package {
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let path = format!("{}/{}", self.prefix, name);
+        self.zip.start_file(path.as_str(), self.options).map_err(|e| format!("Failed to add {}: {}", path, e))?;
+        self.zip.write_all(data).map_err(|e| format!("Failed to add {}: {}", path, e))
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<(), String> {
+        let data = std::fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        self.add(name, &data)
+    }
+}
+
+/// `gtrom package`: zip up the ROM built at `gtr_path`
+pub fn do_package(gtr_path: &Path, output: Option<PathBuf>, web: Option<PathBuf>) -> Result<(), String> {
+    let (working_dir, rom_dir) = find_rom_dir()?;
+    let package = cargo_package(&rom_dir)?;
+    let field = |key: &str| package.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+    let name = field("name").ok_or("Could not find crate name in Cargo.toml")?;
+    let version = field("version").unwrap_or_else(|| "0.0.0".to_string());
+    let rom = format!("{}.gtr", name);
+
+    let search = [working_dir.as_path(), rom_dir.as_path()];
+    let box_art = find_image(&search, BOX_ART);
+    let screenshot = find_image(&search, SCREENSHOT);
+    let packaged_name = |path: &Option<PathBuf>, stem: &str| {
+        path.as_ref().and_then(|p| p.extension()).map(|ext| format!("{}.{}", stem, ext.to_string_lossy()))
+    };
+
+    let metadata = Metadata {
+        title: config::get().package_title().map(str::to_string).unwrap_or_else(|| name.clone()),
+        version: version.clone(),
+        description: field("description"),
+        authors: package.get("authors")
+            .and_then(|v| v.as_array())
+            .map(|authors| authors.iter().filter_map(|a| a.as_str().map(str::to_string)).collect())
+            .unwrap_or_default(),
+        rom: rom.clone(),
+        box_art: packaged_name(&box_art, "boxart"),
+        screenshot: packaged_name(&screenshot, "screenshot"),
+    };
+
+    let prefix = format!("{}-{}", name, version);
+    let zip_path = output.unwrap_or_else(|| working_dir.join(format!("{}.zip", prefix)));
+    let file = File::create(&zip_path).map_err(|e| format!("Failed to create {}: {}", zip_path.display(), e))?;
+    let mut zip = Package {
+        zip: ZipWriter::new(file),
+        prefix,
+        options: SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    };
+
+    status!("Packaging {} {}...", metadata.title, version);
+    zip.add_file(&rom, gtr_path)?;
+
+    let metadata_toml = toml::to_string(&metadata).map_err(|e| format!("Failed to write game.toml: {}", e))?;
+    zip.add("game.toml", metadata_toml.as_bytes())?;
+
+    for (path, name) in [(&box_art, &metadata.box_art), (&screenshot, &metadata.screenshot)] {
+        if let (Some(path), Some(name)) = (path, name) {
+            status!("  {} <- {}", name, path.display());
+            zip.add_file(name, path)?;
+        }
+    }
+
+    match web.or_else(|| config::get().web_emulator()) {
+        Some(web_dir) => {
+            let module = web_module(&web_dir)?;
+            let entries = std::fs::read_dir(&web_dir).map_err(|e| format!("Failed to read {}: {}", web_dir.display(), e))?;
+            for entry in entries.filter_map(|e| e.ok()).filter(|e| e.path().is_file()) {
+                zip.add_file(&format!("web/{}", entry.file_name().to_string_lossy()), &entry.path())?;
+            }
+            zip.add_file(&format!("web/{}", rom), gtr_path)?;
+            zip.add("web/index.html", index_html(&metadata.title, &module, &rom).as_bytes())?;
+            status!("  web/ <- {}", web_dir.display());
+        }
+        None => {
+            status!("  no web emulator to bundle; pass --web <dir> or set package.web_emulator in {}", CONFIG_FILE);
+        }
+    }
+
+    zip.zip.finish().map_err(|e| format!("Failed to write {}: {}", zip_path.display(), e))?;
+    emit(Event::Artifact { kind: "zip", path: &zip_path });
+    status!("Package complete: {}", zip_path.display());
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Prerequisite checks
+//!
+//! Walks through everything a build needs and says what to do about anything
+//! that's missing, rather than leaving new users with "Failed to start container".
+
+use std::process::Command;
+
+use crate::config;
+use crate::container::{native_toolchain_available, ContainerRuntime};
+use crate::messages::{child_stdout, status};
+
+/// First line of a command's output, if it ran successfully
+fn first_line(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(cmd).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).lines().next().map(|l| l.trim().to_string())
+}
+
+fn pass(what: &str, detail: &str) {
+    status!("  [ok]   {}: {}", what, detail);
+}
+
+fn fail(what: &str, hint: &str) {
+    status!("  [fail] {}", what);
+    for line in hint.lines() {
+        status!("         {}", line);
+    }
+}
+
+fn install_hint() -> &'static str {
+    if cfg!(target_os = "windows") || cfg!(target_os = "macos") {
+        "Install Podman Desktop from https://podman-desktop.io/ (or Docker Desktop)."
+    } else {
+        "Install podman (or docker) with your package manager, e.g. `sudo apt install podman`."
+    }
+}
+
+/// Check the container runtime, pull the build image, and make sure the toolchain inside works
+pub fn do_setup() -> Result<(), String> {
+    status!("Checking prerequisites...");
+
+    let Some(runtime) = ContainerRuntime::detect() else {
+        let missing = match config::get().engine() {
+            Some(engine) => format!("{} (the engine in gtrom.toml) was not found.", engine.as_str()),
+            None => "Neither podman nor docker was found.".to_string(),
+        };
+        fail("container runtime", &format!("{}\n{}", missing, install_hint()));
+        if native_toolchain_available() {
+            pass("local toolchain", "cargo +mos and llvm-mc work; builds can use --no-container");
+            return Ok(());
+        }
+        return Err("No way to build ROMs yet; see above".to_string());
+    };
+    let cmd = runtime.as_str();
+    let image = config::get().image();
+
+    let version = first_line(cmd, &["--version"]).unwrap_or_else(|| cmd.to_string());
+    pass("container runtime", &version);
+
+    if first_line(cmd, &["info"]).is_none() {
+        let hint = match runtime {
+            ContainerRuntime::Podman if cfg!(any(target_os = "windows", target_os = "macos")) =>
+                "The podman machine isn't running. Start it with `podman machine init` (first time only)\nand `podman machine start`, or from Podman Desktop.",
+            ContainerRuntime::Podman =>
+                "`podman info` failed. Check that rootless podman is set up for your user\n(subuid/subgid entries), or run `podman system migrate`.",
+            ContainerRuntime::Docker =>
+                "The docker daemon isn't reachable. Start Docker Desktop or `sudo systemctl start docker`,\nand make sure your user is in the `docker` group.",
+        };
+        fail(&format!("{} is installed but not usable", cmd), hint);
+        return Err(format!("{} isn't working; see above", cmd));
+    }
+    pass(&format!("{} daemon", cmd), "reachable");
+
+    status!("Pulling {} (this can take a while the first time)...", image);
+    let pulled = Command::new(cmd)
+        .args(["pull", image])
+        .stdout(child_stdout())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !pulled {
+        fail("build image", &format!("`{} pull {}` failed. Check your network connection\nand that docker.io is reachable.", cmd, image));
+        return Err("Couldn't pull the build image".to_string());
+    }
+    pass("build image", image);
+
+    let in_image = |args: &[&str]| {
+        let mut full = vec!["run", "--rm", image];
+        full.extend(args);
+        first_line(cmd, &full)
+    };
+
+    let mut ok = true;
+    match in_image(&["cargo", "+mos", "--version"]) {
+        Some(v) => pass("rust-mos toolchain", &v),
+        None => {
+            fail("rust-mos toolchain", "`cargo +mos` doesn't run inside the image. Try pulling it again,\nor report this as a bug.");
+            ok = false;
+        }
+    }
+    match in_image(&["llvm-mc", "--version"]) {
+        Some(_) => pass("llvm-mos", "llvm-mc runs"),
+        None => {
+            fail("llvm-mos", "`llvm-mc` doesn't run inside the image.");
+            ok = false;
+        }
+    }
+
+    if native_toolchain_available() {
+        pass("local toolchain", "also installed; builds can use --no-container");
+    }
+
+    if ok {
+        status!("\nAll set! Try `gtrom init my-game` and `gtrom build`.");
+        Ok(())
+    } else {
+        Err("The build image is broken; see above".to_string())
+    }
+}
@@ -0,0 +1,78 @@
+//! Per-bank size report
+//!
+//! Reads the symbol table of the last build and lists what takes up the most
+//! room in each bank, to find what's eating a 16K bank.
+
+use std::path::PathBuf;
+
+use crate::cargo::{elf_path, find_rom_dir, ProfileArgs};
+use crate::messages::status;
+use gametank_sdk::rom_builder::{RomBuilder, BANK_SIZE};
+use gametank_sdk::symbols::{read_symbols, Symbol, SymbolKind};
+
+fn print_largest(title: &str, symbols: &[&Symbol], count: usize) {
+    if symbols.is_empty() {
+        return;
+    }
+
+    let mut largest = symbols.to_vec();
+    largest.sort_by_key(|s| std::cmp::Reverse(s.size));
+
+    status!("  {}", title);
+    for s in largest.iter().take(count) {
+        status!("    {:>6}  ${:04X}  {}", s.size, s.addr, s.name);
+    }
+    if largest.len() > count {
+        let rest: usize = largest[count..].iter().map(|s| s.size).sum();
+        status!("    {:>6}         ({} more)", rest, largest.len() - count);
+    }
+}
+
+/// `gtrom size`: the `count` largest functions and statics per bank
+pub fn do_size(profile: &ProfileArgs, count: usize, elf: Option<PathBuf>) -> Result<(), String> {
+    let elf = match elf {
+        Some(elf) => elf,
+        None => elf_path(&find_rom_dir()?.1, profile)?,
+    };
+    let data = std::fs::read(&elf)
+        .map_err(|e| format!("Could not read {}: {} (run `gtrom build` first)", elf.display(), e))?;
+
+    let symbols = read_symbols(&data)?;
+    if symbols.is_empty() {
+        status!("No sized functions or statics in {}", elf.display());
+        return Ok(());
+    }
+    // section totals include padding and anything without a symbol
+    let image = RomBuilder::from_elf(&data).ok();
+    let usage = image.as_ref().map(|image| image.bank_usage()).unwrap_or_default();
+    let bank_size = image.as_ref().map_or(BANK_SIZE, |image| image.bank_size());
+
+    let mut banks: Vec<Option<u8>> = symbols.iter().map(|s| s.bank).collect();
+    banks.dedup();
+
+    for bank in banks {
+        let in_bank: Vec<&Symbol> = symbols.iter().filter(|s| s.bank == bank).collect();
+        let functions: Vec<&Symbol> = in_bank.iter().copied().filter(|s| s.kind == SymbolKind::Function).collect();
+        let statics: Vec<&Symbol> = in_bank.iter().copied().filter(|s| s.kind == SymbolKind::Object).collect();
+
+        match bank {
+            Some(bank) => {
+                let used = usage.iter().find(|(b, _)| *b == bank).map_or_else(
+                    || in_bank.iter().map(|s| s.size).sum(),
+                    |(_, used)| *used,
+                );
+                status!("bank {:>3}  {} / {} bytes ({:.0}%)", bank, used, bank_size, used as f32 * 100.0 / bank_size as f32);
+            }
+            None => {
+                let used: usize = in_bank.iter().map(|s| s.size).sum();
+                status!("ram       {} bytes", used);
+            }
+        }
+
+        print_largest("functions", &functions, count);
+        print_largest("statics", &statics, count);
+        status!();
+    }
+
+    Ok(())
+}
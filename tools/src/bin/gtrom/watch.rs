@@ -0,0 +1,104 @@
+//! Rebuild on change
+//!
+//! Polls the ROM sources instead of using filesystem events, since those don't
+//! reliably cross the container's bind mount. With `--run`, gte is started once
+//! with `--watch` and reloads the ROM itself each time the .gtr is rewritten.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, SystemTime};
+
+use crate::cargo::{find_rom_dir, workspace_members, workspace_root, BuildArgs};
+use crate::config;
+use crate::container::BuildEnv;
+use crate::do_build;
+use crate::messages::{child_stdout, emit, status, Event};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Editors often write a file in several steps; wait for things to settle
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+/// Modification times of everything that feeds into the build
+fn snapshot(roots: &[PathBuf]) -> Snapshot {
+    let mut files = Snapshot::new();
+    for root in roots {
+        collect(root, &mut files);
+    }
+    files
+}
+
+fn collect(path: &Path, files: &mut Snapshot) {
+    let Ok(meta) = std::fs::metadata(path) else { return };
+    if meta.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else { return };
+        for entry in entries.filter_map(|e| e.ok()) {
+            collect(&entry.path(), files);
+        }
+    } else if let Ok(modified) = meta.modified() {
+        files.insert(path.to_path_buf(), modified);
+    }
+}
+
+/// Block until something under `roots` changes
+fn wait_for_change(roots: &[PathBuf], last: &Snapshot) -> Snapshot {
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if snapshot(roots) != *last {
+            std::thread::sleep(SETTLE_TIME);
+            return snapshot(roots);
+        }
+    }
+}
+
+fn launch_gte(gtr_path: &Path) -> Result<Child, String> {
+    status!("Launching emulator...");
+    Command::new(config::get().emulator())
+        .arg("--watch")
+        .arg(gtr_path)
+        .arg("--sym")
+        .arg(gtr_path.with_extension("sym"))
+        .stdout(child_stdout())
+        .spawn()
+        .map_err(|e| format!("Failed to launch gte: {}", e))
+}
+
+/// Build, then rebuild whenever `src/`, `assets/`, the asset manifest, `Cargo.toml` or another crate in the workspace change
+pub fn do_watch(build: &BuildArgs, run: bool, env: BuildEnv) -> Result<(), String> {
+    let (working_dir, rom_dir) = find_rom_dir()?;
+
+    let mut roots = vec![rom_dir.join("src"), rom_dir.join("assets"), config::get().asset_manifest(&rom_dir), rom_dir.join("Cargo.toml")];
+    if working_dir != rom_dir {
+        roots.push(working_dir.join("assets"));
+    }
+    // shared crates in the ROM's workspace
+    for member in workspace_members(&workspace_root(&rom_dir)).into_iter().filter(|dir| *dir != rom_dir) {
+        roots.push(member.join("src"));
+        roots.push(member.join("Cargo.toml"));
+    }
+
+    let mut gte: Option<Child> = None;
+    let mut files = snapshot(&roots);
+
+    loop {
+        match do_build(build, env) {
+            Ok(gtr_path) => {
+                // gte notices the new .gtr on its own once it's running
+                let exited = gte.as_mut().is_none_or(|child| child.try_wait().ok().flatten().is_some());
+                if run && exited {
+                    gte = Some(launch_gte(&gtr_path)?);
+                }
+            }
+            Err(e) => {
+                emit(Event::Error { message: &e });
+                eprintln!("Error: {}", e);
+            }
+        }
+
+        status!("Watching for changes (Ctrl+C to stop)...");
+        files = wait_for_change(&roots, &files);
+        status!();
+    }
+}
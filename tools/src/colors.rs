@@ -0,0 +1,39 @@
+//! Mapping images onto the GameTank's colors
+//!
+//! Every pixel becomes the color byte the emulator shows closest to it, and
+//! anything mostly transparent becomes color 0, which the blitter skips.
+//! `gtrom` converts images this way, and gtgo loads them the same way so what
+//! it shows is what ends up in the ROM.
+
+use std::collections::HashMap;
+
+use gte_core::color_map::COLOR_MAP;
+use image::{Rgba, RgbaImage};
+
+/// The GameTank color closest to `pixel`. Transparent pixels become color 0,
+/// which the blitter skips.
+pub fn nearest_color(pixel: Rgba<u8>) -> u8 {
+    if pixel[3] < 128 {
+        return 0;
+    }
+
+    let [r, g, b, _] = pixel.0;
+    let distance = |&(cr, cg, cb, _): &(u8, u8, u8, u8)| {
+        let (dr, dg, db) = (r as i32 - cr as i32, g as i32 - cg as i32, b as i32 - cb as i32);
+        dr * dr + dg * dg + db * db
+    };
+
+    COLOR_MAP.iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .map(|(i, _)| i as u8)
+        .unwrap()
+}
+
+/// Each pixel as its GameTank color, row by row
+pub fn to_colors(img: &RgbaImage) -> Vec<u8> {
+    let mut cache = HashMap::new();
+    img.pixels()
+        .map(|&pixel| *cache.entry(pixel).or_insert_with(|| nearest_color(pixel)))
+        .collect()
+}
@@ -5,3 +5,10 @@
 //! - gtrom: ROM build tool
 //! - gtgo: TUI toolkit
 //! - gtld: Cartridge loader
+
+pub mod colors;
+pub mod memory_map;
+pub mod rom_builder;
+pub mod rom_header;
+pub mod song;
+pub mod symbols;
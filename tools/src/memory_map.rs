@@ -0,0 +1,207 @@
+//! Cartridge memory maps
+//!
+//! Describes where a ROM's code and data live and generates the linker
+//! script for it. gtrom reads one from the `[memory]` table of gtrom.toml;
+//! without one, ROMs get the 2MB flash layout the template has always used.
+
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::rom_builder::VECTOR_TABLE;
+use crate::rom_header::RomHeader;
+
+/// The kinds of cartridge a ROM can be laid out for
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Cart {
+    /// 2MB flash: 127 switchable 16KB banks at $8000, and a fixed bank at $C000
+    #[default]
+    #[serde(rename = "2m")]
+    Flash2m,
+    /// 32KB ROM mapped flat at $8000-$FFFF, with no banking
+    #[serde(rename = "32k")]
+    Rom32k,
+}
+
+impl Cart {
+    /// Size of one bank in the ROM image
+    pub fn bank_size(self) -> usize {
+        match self {
+            Self::Flash2m => 0x4000,
+            Self::Rom32k => 0x8000,
+        }
+    }
+
+    pub fn bank_count(self) -> usize {
+        match self {
+            Self::Flash2m => 128,
+            Self::Rom32k => 1,
+        }
+    }
+
+    /// The bank that's always mapped, holding crt0 and the vectors
+    pub fn fixed_bank(self) -> u8 {
+        self.bank_count() as u8 - 1
+    }
+
+    /// Where the fixed bank starts in the cpu's address space
+    pub fn fixed_origin(self) -> u16 {
+        match self {
+            Self::Flash2m => 0xC000,
+            Self::Rom32k => 0x8000,
+        }
+    }
+
+    /// Where `bank` starts in the linker's address space: switchable banks sit
+    /// at $8000 in a 64K page of their own, the fixed bank at its cpu address
+    pub fn bank_origin(self, bank: u8) -> usize {
+        if bank == self.fixed_bank() {
+            self.fixed_origin() as usize
+        } else {
+            0x8000 + bank as usize * 0x10000
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flash2m => "2M",
+            Self::Rom32k => "32K",
+        }
+    }
+
+    /// Size of the whole ROM image
+    pub fn rom_size(self) -> usize {
+        self.bank_size() * self.bank_count()
+    }
+}
+
+/// An address range in the cpu's address space
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    pub start: u16,
+    pub size: u16,
+}
+
+impl Region {
+    fn end(self) -> u32 {
+        self.start as u32 + self.size as u32
+    }
+}
+
+/// Everything the linker needs to know about a cartridge and the console's RAM
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct MemoryMap {
+    pub cart: Cart,
+    /// Switchable banks that get a linker region, counting up from bank 0.
+    /// Only for 2MB carts; code asking for any other bank lands in the fixed bank.
+    pub banks: u8,
+    /// Zero page for statics; $00-$3F always holds the compiler's registers
+    pub zp: Region,
+    /// RAM for `.data` and `.bss`
+    pub ram: Region,
+}
+
+/// $00-$3F are llvm-mos's imaginary registers, __rc0-__rc63
+const RC_COUNT: u16 = 0x40;
+/// Console registers start here, so RAM can't run past it
+const RAM_LIMIT: u32 = 0x2000;
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self {
+            cart: Cart::Flash2m,
+            banks: 127,
+            zp: Region { start: 0x0040, size: 0x00C0 },
+            ram: Region { start: 0x0400, size: 0x1BFF },
+        }
+    }
+}
+
+impl MemoryMap {
+    /// Switchable banks that get their own `.text.bankN`/`.rodata.bankN` regions
+    pub fn switchable_banks(&self) -> u8 {
+        match self.cart {
+            Cart::Flash2m => self.banks,
+            Cart::Rom32k => 0,
+        }
+    }
+
+    /// Check the map describes something the hardware can actually do
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cart == Cart::Flash2m && self.banks > 127 {
+            return Err(format!("memory.banks is {}, but a 2MB cart only has 127 switchable banks", self.banks));
+        }
+        if self.zp.start < RC_COUNT || self.zp.end() > 0x100 {
+            return Err(format!(
+                "memory.zp (${:02X}, {} bytes) has to fit between $40 and $FF",
+                self.zp.start, self.zp.size
+            ));
+        }
+        if self.ram.start < 0x0200 || self.ram.end() > RAM_LIMIT {
+            return Err(format!(
+                "memory.ram (${:04X}, {} bytes) has to fit between $0200 and ${:04X}, above the stack",
+                self.ram.start, self.ram.size, RAM_LIMIT - 1
+            ));
+        }
+        Ok(())
+    }
+
+    /// The linker script for this map, in the form the template's build.rs uses.
+    /// With `footer`, the end of the fixed bank is left free for the ROM footer.
+    pub fn linker_script(&self, footer: bool) -> String {
+        let mut ld = String::new();
+        let fixed_origin = self.cart.fixed_origin();
+        let footer_size = if footer { RomHeader::size(self.cart.bank_count()) } else { 0 };
+
+        // writing to a String can't fail
+        let _ = writeln!(ld, "MEMORY {{");
+        for bank in 0..self.switchable_banks() as u32 {
+            let addr = 0x8000 + bank * 0x10000;
+            let _ = writeln!(ld, "  BANK{0} (rx) : ORIGIN = 0x{1:06X}, LENGTH = 0x4000", bank, addr);
+        }
+        let _ = writeln!(ld, "  RAM (rwx) : ORIGIN = 0x{:04X}, LENGTH = 0x{:04X}", self.ram.start, self.ram.size);
+        let _ = writeln!(ld, "  ZP (rw) : ORIGIN = 0x{:04X}, LENGTH = 0x{:04X}", self.zp.start, self.zp.size);
+        let _ = writeln!(ld, "  SCR (w) : ORIGIN = 0x2000, LENGTH = 0x0008");
+        // the fixed bank's code stops where the footer, or else the vectors, start
+        let fixed_size = VECTOR_TABLE - fixed_origin as usize - footer_size;
+        let _ = writeln!(ld, "  FIXED_FLASH (rx) : ORIGIN = 0x{:05X}, LENGTH = 0x{:04X}", fixed_origin, fixed_size);
+        let _ = writeln!(ld, "  VECTOR_TABLE (rw) : ORIGIN = 0x{:05X}, LENGTH = 6", VECTOR_TABLE);
+        let _ = writeln!(ld, "}}");
+
+        let _ = writeln!(ld, "SECTIONS {{");
+        for bank in 0..self.switchable_banks() {
+            let _ = writeln!(ld, "  .text.bank{0} : {{ KEEP(*(.text.bank{0})) KEEP(*(.text.bank{0}.*)) }} > BANK{0} = 0xFF", bank);
+            let _ = writeln!(ld, "  .rodata.bank{0} : {{ KEEP(*(.rodata.bank{0})) KEEP(*(.rodata.bank{0}.*)) }} > BANK{0}", bank);
+        }
+
+        // anything for a bank without a region gets picked up here
+        let _ = writeln!(ld, "  .text : {{ *(.text*) }} > FIXED_FLASH = 0xFF");
+        let _ = writeln!(ld, "  .rodata : {{ *(.rodata*) }} > FIXED_FLASH");
+        let _ = writeln!(ld, "  .vector_table : {{ KEEP(*(.vector_table)) }} > VECTOR_TABLE");
+        let _ = writeln!(ld, "  .bss : {{ __bss_start = .; *(.bss*) __bss_end = .; }} > RAM");
+        let _ = writeln!(ld, "  .zp : {{ __zp_start = .; KEEP(*(.data.zp)) __zp_end = .;}} > ZP AT > FIXED_FLASH");
+        let _ = writeln!(ld, "  .data : {{ __data_start = .; *(.data*) __data_end = .; }} > RAM AT > FIXED_FLASH");
+        // #[bank] inventory for gtrom's report; INFO keeps it out of the ROM
+        let _ = writeln!(ld, "  .gt_banks 0 (INFO) : {{ KEEP(*(.gt_banks)) }}");
+
+        let _ = writeln!(ld, "  PROVIDE(__zp_load = LOADADDR(.zp));");
+        let _ = writeln!(ld, "  PROVIDE(__zp_start = ADDR(.zp));");
+        let _ = writeln!(ld, "  PROVIDE(__zp_end = .);");
+
+        let _ = writeln!(ld, "  PROVIDE(__data_load = LOADADDR(.data));");
+        let _ = writeln!(ld, "  PROVIDE(__data_start = ADDR(.data));");
+        let _ = writeln!(ld, "  PROVIDE(__data_end = .);");
+
+        let _ = writeln!(ld, "  PROVIDE(__bss_start = ADDR(.bss));");
+        let _ = writeln!(ld, "  PROVIDE(__bss_end = .);");
+        let _ = writeln!(ld, "}}");
+
+        for rc in 0..RC_COUNT {
+            let _ = writeln!(ld, "__rc{} = 0x{:02X};", rc, rc);
+        }
+
+        ld
+    }
+}
@@ -0,0 +1,511 @@
+//! ELF to .gtr conversion
+//!
+//! Lays the sections of a llvm-mos ELF out into 128 16KB banks, or a flat
+//! 32KB image for ROMs linked for 32K carts. Used by `gtrom convert` and by
+//! gte when it's handed an ELF directly.
+//!
+//! Anything that would make a ROM that doesn't boot, or boots into garbage,
+//! is a [`RomError`] rather than a quietly broken image.
+
+use std::fmt;
+
+use elf::{ElfBytes, endian::AnyEndian};
+use rustc_demangle::demangle;
+
+use crate::memory_map::Cart;
+use crate::rom_header::RomHeader;
+
+/// Bank layout of a 2MB cart
+pub const BANK_SIZE: usize = 1 << 14;
+pub const BANK_COUNT: usize = 128;
+/// The bank mapped at $C000-$FFFF, holding crt0 and the vectors
+pub const FIXED_BANK: u8 = 127;
+
+/// Where the cpu reads its NMI, reset and IRQ vectors from
+pub const VECTOR_TABLE: usize = 0xFFFA;
+
+/// Returns true if `data` starts with the ELF magic number
+pub fn is_elf(data: &[u8]) -> bool {
+    data.starts_with(b"\x7fELF")
+}
+
+/// Why an ELF couldn't be turned into a ROM
+#[derive(Debug, Clone, PartialEq)]
+pub enum RomError {
+    /// Not an ELF, or not one the parser understands
+    Parse(String),
+    /// A section every ROM needs isn't there
+    MissingSection(&'static str),
+    /// A section was linked at an address its bank doesn't cover
+    Misplaced { section: String, addr: usize, bank: u8, origin: usize, bank_size: usize },
+    /// `.data` or `.zp` is there, but crt0 has no symbol saying where to copy it from
+    MissingLoadSymbol { section: String, symbol: &'static str },
+    /// `.vector_table` isn't where the cpu looks for it
+    VectorTable { addr: usize },
+    /// The reset vector doesn't point into the fixed bank's code
+    ResetVector { vector: u16, text_start: usize, text_end: usize },
+    /// A section runs past the end of its bank
+    Overflow { section: String, bank: u8, by: usize, size: usize, offset: usize, bank_size: usize },
+    /// A bank's sections add up to more than it holds
+    BankFull { bank: u8, used: usize, bank_size: usize },
+    /// Two sections landed on top of each other
+    Overlap { first: String, first_start: usize, first_end: usize, second: String, second_start: usize, bank: u8 },
+    /// The fixed bank has something where the ROM footer goes
+    NoRoomForHeader { section: String, needed: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "Failed to parse ELF: {}", e),
+            Self::MissingSection(name) => write!(f, "the ELF has no {} section; was it linked with the GameTank linker script?", name),
+            Self::Misplaced { section, addr, bank, origin, bank_size } => write!(
+                f,
+                "{} is linked at ${:06X}, outside bank {} (${:06X}..${:06X})",
+                section, addr, bank, origin, origin + bank_size
+            ),
+            Self::MissingLoadSymbol { section, symbol } => write!(
+                f,
+                "{} needs copying to RAM at boot, but there's no {} symbol saying where it is in ROM",
+                section, symbol
+            ),
+            Self::VectorTable { addr } => write!(
+                f,
+                ".vector_table is at ${:04X}, but the cpu reads its vectors from ${:04X}",
+                addr, VECTOR_TABLE
+            ),
+            Self::ResetVector { vector, text_start, text_end } => write!(
+                f,
+                "the reset vector points at ${:04X}, outside .text (${:04X}..${:04X}); the ROM would boot into garbage",
+                vector, text_start, text_end
+            ),
+            Self::Overflow { section, bank, by, size, offset, bank_size } => write!(
+                f,
+                "{} overflows bank {} by {} bytes ({} bytes starting at ${:04X}, bank holds {})",
+                section, bank, by, size, offset, bank_size
+            ),
+            Self::BankFull { bank, used, bank_size } => write!(
+                f,
+                "bank {} overflows: {} bytes of sections, but a bank holds {}",
+                bank, used, bank_size
+            ),
+            Self::Overlap { first, first_start, first_end, second, second_start, bank } => write!(
+                f,
+                "{} (${:04X}..${:04X}) overlaps {} (${:04X}..) in bank {}",
+                first, first_start, first_end, second, second_start, bank
+            ),
+            Self::NoRoomForHeader { section, needed } => write!(
+                f,
+                "the ROM footer needs the last {} bytes before the vectors, but {} is there",
+                needed, section
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<RomError> for String {
+    fn from(e: RomError) -> Self {
+        e.to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ElfSection {
+    _internal_name: String,
+    pub display_name: String,
+    bytes: Vec<u8>,
+    pub size: usize,
+    /// Where the section is at runtime; in RAM for loaded sections
+    pub mem_loc: usize,
+    pub bank_loc: usize,
+    pub bank: u8,
+    /// Copied from ROM to `mem_loc` by crt0 at boot
+    pub loaded: bool,
+}
+
+impl ElfSection {
+    fn from_static(section_name: String, elf: &ElfBytes<'_, AnyEndian>, bank: u8, cart: Cart) -> Result<Option<Self>, RomError> {
+        let Some(header) = elf.section_header_by_name(&section_name).map_err(|e| RomError::Parse(e.to_string()))? else {
+            return Ok(None);
+        };
+        let (d, _ch) = elf.section_data(&header).map_err(|e| RomError::Parse(e.to_string()))?;
+
+        let load_addr = header.sh_addr as usize;
+        let size = d.len();
+        let display_name = demangle(&section_name).to_string();
+
+        let origin = cart.bank_origin(bank);
+        let bank_end = origin + cart.bank_size();
+        if size > 0 && !(origin..bank_end).contains(&load_addr) {
+            return Err(RomError::Misplaced { section: display_name, addr: load_addr, bank, origin, bank_size: cart.bank_size() });
+        }
+
+        Ok(Some(Self {
+            display_name,
+            _internal_name: section_name,
+            bytes: Vec::from(d),
+            size,
+            mem_loc: load_addr,
+            bank,
+            bank_loc: if (origin..bank_end).contains(&load_addr) { load_addr - origin } else { 0 },
+            loaded: false,
+        }))
+    }
+
+    fn from_loaded(
+        section_name: String,
+        elf: &ElfBytes<'_, AnyEndian>,
+        load_symbol: &'static str,
+        cart: Cart,
+    ) -> Result<Option<Self>, RomError> {
+        let Some(header) = elf.section_header_by_name(&section_name).map_err(|e| RomError::Parse(e.to_string()))? else {
+            return Ok(None);
+        };
+        let (bytes, _) = elf.section_data(&header).map_err(|e| RomError::Parse(e.to_string()))?;
+        let display_name = demangle(&section_name).to_string();
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+
+        let load_sym = elf.symbol_table().ok().flatten().and_then(|(symtab, strtab)| {
+            symtab.iter().find(|sym| strtab.get(sym.st_name as usize).is_ok_and(|name| name == load_symbol))
+        });
+        let Some(load_sym) = load_sym else {
+            return Err(RomError::MissingLoadSymbol { section: display_name, symbol: load_symbol });
+        };
+
+        let load_rom_addr = load_sym.st_value as usize; // where the section is in ROM
+        let mem_target_addr = header.sh_addr as usize; // where the section ends up in RAM
+
+        // crt0 copies from the fixed bank, since it's the only one it knows is mapped
+        let bank = cart.fixed_bank();
+        let origin = cart.bank_origin(bank);
+        if !(origin..origin + cart.bank_size()).contains(&load_rom_addr) {
+            return Err(RomError::Misplaced { section: display_name, addr: load_rom_addr, bank, origin, bank_size: cart.bank_size() });
+        }
+
+        Ok(Some(Self {
+            display_name,
+            _internal_name: section_name,
+            bytes: bytes.to_vec(),
+            size: bytes.len(),
+            mem_loc: mem_target_addr,
+            bank,
+            bank_loc: load_rom_addr - origin,
+            loaded: true,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BankItemKind {
+    /// A function called straight, from code already in its bank
+    NearFn,
+    Static,
+    /// A function reached through a trampoline in the fixed bank
+    FarFn,
+}
+
+/// Something `#[bank(N)]` asked to put in bank N, from the ELF's `.gt_banks` inventory
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankItem {
+    pub bank: u8,
+    pub kind: BankItemKind,
+    pub name: String,
+}
+
+impl fmt::Display for BankItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            BankItemKind::NearFn => write!(f, "fn {} (near)", self.name),
+            BankItemKind::Static => write!(f, "static {}", self.name),
+            BankItemKind::FarFn => write!(f, "fn {}", self.name),
+        }
+    }
+}
+
+/// The `#[bank]` inventory: `[bank, kind, name..., 0]` entries, kind 0 for a near
+/// function, 1 for a static and 2 for a function with a trampoline. ELFs from
+/// before the attribute existed just don't have one.
+fn read_inventory(elf: &ElfBytes<'_, AnyEndian>) -> Vec<BankItem> {
+    let Some(header) = elf.section_header_by_name(".gt_banks").ok().flatten() else {
+        return vec![];
+    };
+    let Ok((mut data, _)) = elf.section_data(&header) else {
+        return vec![];
+    };
+
+    let mut items = vec![];
+    while let [bank, kind, rest @ ..] = data {
+        let Some(end) = rest.iter().position(|&b| b == 0) else { break };
+        let kind = match kind {
+            0 => BankItemKind::NearFn,
+            1 => BankItemKind::Static,
+            _ => BankItemKind::FarFn,
+        };
+        items.push(BankItem {
+            bank: *bank,
+            kind,
+            name: String::from_utf8_lossy(&rest[..end]).to_string(),
+        });
+        data = &rest[end + 1..];
+    }
+    items.sort_by(|a, b| (a.bank, &a.name).cmp(&(b.bank, &b.name)));
+    items
+}
+
+/// A fully laid out ROM and the sections that went into it
+pub struct RomImage {
+    // a Vec keeps 2MB off the stack - Windows has 1MB stack limit
+    data: Vec<u8>,
+    pub cart: Cart,
+    pub sections: Vec<ElfSection>,
+    /// Everything `#[bank]` placed, in bank order
+    pub inventory: Vec<BankItem>,
+}
+
+impl RomImage {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Size of each bank, for reporting how full they are
+    pub fn bank_size(&self) -> usize {
+        self.cart.bank_size()
+    }
+
+    /// Where the cpu starts executing, read from the reset vector at the end of the fixed bank
+    pub fn reset_vector(&self) -> u16 {
+        let end = self.data.len();
+        u16::from_le_bytes([self.data[end - 4], self.data[end - 3]])
+    }
+
+    /// Offset of a section's first byte in the ROM file
+    pub fn rom_offset(&self, section: &ElfSection) -> usize {
+        section.bank as usize * self.bank_size() + section.bank_loc
+    }
+
+    /// Bytes used in each bank that has anything in it, in bank order
+    pub fn bank_usage(&self) -> Vec<(u8, usize)> {
+        let mut usage = vec![0usize; self.cart.bank_count()];
+        for s in &self.sections {
+            usage[s.bank as usize] += s.size;
+        }
+        usage.iter().enumerate()
+            .filter(|(_, &used)| used > 0)
+            .map(|(bank, &used)| (bank as u8, used))
+            .collect()
+    }
+
+    /// A table of each used bank's fill level, and the sections and `#[bank]` items in it
+    pub fn usage_report(&self) -> String {
+        let mut report = String::new();
+        let usage = self.bank_usage();
+        for &(bank, used) in &usage {
+            report.push_str(&format!(
+                "bank {:>3}  {:>6} / {} ({:.0}%)\n",
+                bank, kilobytes(used), kilobytes(self.bank_size()), used as f32 * 100.0 / self.bank_size() as f32
+            ));
+            for s in self.sections.iter().filter(|s| s.bank == bank) {
+                report.push_str(&format!("  {:<24}{:>6}  @{:04X}\n", s.display_name, kilobytes(s.size), s.bank_loc));
+            }
+            for item in self.inventory.iter().filter(|item| item.bank == bank) {
+                report.push_str(&format!("    #[bank] {}\n", item));
+            }
+        }
+
+        // asked for a bank the memory map has no region for, so the linker put it with the fixed bank's code
+        for item in self.inventory.iter().filter(|item| !usage.iter().any(|&(bank, _)| bank == item.bank)) {
+            report.push_str(&format!(
+                "note: #[bank({})] {} ended up in bank {}; the memory map has no region for bank {}\n",
+                item.bank, item, self.cart.fixed_bank(), item.bank
+            ));
+        }
+        report
+    }
+
+    /// Fill in the footer at the end of the fixed bank, once everything else is in place
+    pub fn embed_header(&mut self, title: &str, build_hash: u64) -> Result<RomHeader, RomError> {
+        let needed = RomHeader::size(self.cart.bank_count());
+        let vectors = self.bank_size() - (0x10000 - VECTOR_TABLE);
+        let footer = vectors - needed..vectors;
+
+        let fixed_bank = self.cart.fixed_bank();
+        let in_the_way = self.sections.iter()
+            .filter(|s| s.bank == fixed_bank && s.size > 0)
+            .find(|s| s.bank_loc < footer.end && footer.start < s.bank_loc + s.size);
+        if let Some(s) = in_the_way {
+            return Err(RomError::NoRoomForHeader { section: s.display_name.clone(), needed });
+        }
+
+        let header = RomHeader::new(&self.data, self.cart.bank_count(), title, build_hash);
+        header.write(&mut self.data);
+        Ok(header)
+    }
+
+    /// Where every section landed, in ROM file order, for a `.map` file
+    pub fn map_report(&self) -> String {
+        let mut sections: Vec<&ElfSection> = self.sections.iter().filter(|s| s.size > 0).collect();
+        sections.sort_by_key(|s| self.rom_offset(s));
+
+        let mut report = format!(
+            "# {} ROM, {} bytes, reset vector ${:04X}\n# {:<10} {:>5} {:>6} {:>6}  section\n",
+            self.cart.name(), self.data.len(), self.reset_vector(), "offset", "bank", "cpu", "size"
+        );
+        for s in sections {
+            // what the cpu sees while the bank is mapped in
+            let cpu_addr = (self.cart.bank_origin(s.bank) & 0xFFFF) + s.bank_loc;
+            report.push_str(&format!(
+                "  0x{:06X}   {:>5}  ${:04X} {:>6}  {}",
+                self.rom_offset(s), s.bank, cpu_addr, s.size, s.display_name
+            ));
+            if s.loaded {
+                report.push_str(&format!(" -> ${:04X}", s.mem_loc));
+            }
+            report.push('\n');
+        }
+        report
+    }
+}
+
+/// `14.2K`, `16K` when it's a whole number, or plain bytes under 1K
+fn kilobytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{}B", bytes)
+    } else if bytes.is_multiple_of(1024) {
+        format!("{}K", bytes / 1024)
+    } else {
+        format!("{:.1}K", bytes as f32 / 1024.0)
+    }
+}
+
+/// Fail on anything that would silently corrupt the ROM: sections running past
+/// the end of their bank, or landing on top of each other
+fn check_layout(sections: &[ElfSection], cart: Cart) -> Result<(), RomError> {
+    let bank_size = cart.bank_size();
+    for s in sections {
+        if s.bank_loc + s.size > bank_size {
+            return Err(RomError::Overflow {
+                section: s.display_name.clone(),
+                bank: s.bank,
+                by: s.bank_loc + s.size - bank_size,
+                size: s.size,
+                offset: s.bank_loc,
+                bank_size,
+            });
+        }
+    }
+
+    for bank in 0..cart.bank_count() as u8 {
+        let mut in_bank: Vec<&ElfSection> = sections.iter().filter(|s| s.bank == bank && s.size > 0).collect();
+        in_bank.sort_by_key(|s| s.bank_loc);
+
+        let total: usize = in_bank.iter().map(|s| s.size).sum();
+        if total > bank_size {
+            return Err(RomError::BankFull { bank, used: total, bank_size });
+        }
+
+        for pair in in_bank.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if a.bank_loc + a.size > b.bank_loc {
+                return Err(RomError::Overlap {
+                    first: a.display_name.clone(),
+                    first_start: a.bank_loc,
+                    first_end: a.bank_loc + a.size,
+                    second: b.display_name.clone(),
+                    second_start: b.bank_loc,
+                    bank,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the cpu will find its vectors, and that reset lands in real code
+fn check_vectors(image: &RomImage) -> Result<(), RomError> {
+    let find = |name: &'static str| {
+        image.sections.iter().find(|s| s.display_name == name).ok_or(RomError::MissingSection(name))
+    };
+
+    let vectors = find(".vector_table")?;
+    if vectors.mem_loc != VECTOR_TABLE {
+        return Err(RomError::VectorTable { addr: vectors.mem_loc });
+    }
+
+    let text = find(".text")?;
+    let vector = image.reset_vector();
+    let text_end = text.mem_loc + text.size;
+    if !(text.mem_loc..text_end).contains(&(vector as usize)) {
+        return Err(RomError::ResetVector { vector, text_start: text.mem_loc, text_end });
+    }
+
+    Ok(())
+}
+
+/// Which cart an ELF was linked for: 2MB ROMs put `.text` in the fixed bank
+/// at $C000, 32K ones start it at $8000
+pub(crate) fn detect_cart(elf: &ElfBytes<'_, AnyEndian>) -> Cart {
+    let text = elf.section_header_by_name(".text").ok().flatten();
+    match text {
+        Some(header) if (0x8000..0xC000).contains(&header.sh_addr) => Cart::Rom32k,
+        _ => Cart::Flash2m,
+    }
+}
+
+pub struct RomBuilder {}
+
+impl RomBuilder {
+    /// Lay out an ELF image's sections into ROM banks
+    pub fn from_elf(data: &[u8]) -> Result<RomImage, RomError> {
+        let file = ElfBytes::<AnyEndian>::minimal_parse(data).map_err(|e| RomError::Parse(e.to_string()))?;
+        let elf = &file;
+        let cart = detect_cart(elf);
+
+        // 128 banks, or just the one
+        let static_sections: Vec<Vec<String>> = (0..cart.bank_count())
+            .map(|i| if i as u8 == cart.fixed_bank() {
+                vec![
+                    ".text".to_string(),
+                    ".rodata".to_string(),
+                    ".vector_table".to_string(),
+                ]
+            } else {
+                vec![format!(".text.bank{}", i), format!(".rodata.bank{}", i)]
+            })
+            .collect();
+
+        // loaded sections must be in the FIXED bank for crt0
+        let loaded_sections = [
+            (".data", "__data_load"),
+            (".zp", "__zp_load"),
+        ];
+
+        let mut sections = Vec::new();
+        for (bank, names) in static_sections.iter().enumerate() {
+            for name in names {
+                sections.extend(ElfSection::from_static(name.clone(), elf, bank as u8, cart)?);
+            }
+        }
+        for (section, load_symbol) in loaded_sections {
+            sections.extend(ElfSection::from_loaded(section.to_string(), elf, load_symbol, cart)?);
+        }
+
+        // ROM data - 128x 16k banks (2MB total), or 32k flat
+        let mut data = vec![0x00u8; cart.rom_size()];
+
+        check_layout(&sections, cart)?;
+
+        for s in &sections {
+            let start = s.bank as usize * cart.bank_size() + s.bank_loc;
+            data[start..start + s.size].copy_from_slice(&s.bytes);
+        }
+
+        let image = RomImage { data, cart, sections, inventory: read_inventory(elf) };
+        check_vectors(&image)?;
+        Ok(image)
+    }
+}
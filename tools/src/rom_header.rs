@@ -0,0 +1,140 @@
+//! ROM footer
+//!
+//! An optional block at the end of the fixed bank, just below the vectors,
+//! describing the ROM it's in: a title, a hash of the ELF it was built from
+//! and a CRC32 of every bank. `gtrom` writes it when asked to; gte and gtld
+//! check it, so a truncated download or banks from two different builds
+//! show up before anything runs.
+//!
+//! It's laid out backwards from the vectors, so it can be found without
+//! knowing its size:
+//!
+//! ```text
+//! [bank CRCs, u32 LE each] [title, 32 bytes] [build hash, u64 LE] [bank count] [version] "GTRH" [vectors]
+//! ```
+//!
+//! The fixed bank's CRC skips the footer itself.
+
+pub const MAGIC: &[u8; 4] = b"GTRH";
+const VERSION: u8 = 1;
+pub const TITLE_LEN: usize = 32;
+/// NMI, reset and IRQ at the very end of the image
+const VECTORS_LEN: usize = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomHeader {
+    pub title: String,
+    /// Hash of the ELF the ROM came from, to tell builds apart
+    pub build_hash: u64,
+    pub bank_crcs: Vec<u32>,
+}
+
+/// 64-bit FNV-1a, used for the build hash
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+impl RomHeader {
+    /// Bytes the footer takes up for a ROM with `bank_count` banks
+    pub fn size(bank_count: usize) -> usize {
+        bank_count * 4 + TITLE_LEN + 8 + 1 + 1 + MAGIC.len()
+    }
+
+    /// Where the footer sits in a ROM image of `len` bytes
+    fn range(len: usize, bank_count: usize) -> std::ops::Range<usize> {
+        let end = len - VECTORS_LEN;
+        end - Self::size(bank_count)..end
+    }
+
+    /// CRC32 of each bank, leaving the footer out of the fixed bank's
+    fn bank_crcs(rom: &[u8], bank_count: usize) -> Vec<u32> {
+        let bank_size = rom.len() / bank_count;
+        let footer = Self::range(rom.len(), bank_count);
+        rom.chunks(bank_size)
+            .enumerate()
+            .map(|(bank, data)| {
+                if bank + 1 < bank_count {
+                    return crc32fast::hash(data);
+                }
+                let start = bank * bank_size;
+                let mut hasher = crc32fast::Hasher::new();
+                hasher.update(&rom[start..footer.start]);
+                hasher.update(&rom[footer.end..]);
+                hasher.finalize()
+            })
+            .collect()
+    }
+
+    /// Describe `rom`, which is laid out in `bank_count` equal banks
+    pub fn new(rom: &[u8], bank_count: usize, title: &str, build_hash: u64) -> Self {
+        Self {
+            title: title.to_string(),
+            build_hash,
+            bank_crcs: Self::bank_crcs(rom, bank_count),
+        }
+    }
+
+    /// Write the footer into its place in `rom`; the area must be free
+    pub fn write(&self, rom: &mut [u8]) {
+        let mut footer = Vec::with_capacity(Self::size(self.bank_crcs.len()));
+        for crc in &self.bank_crcs {
+            footer.extend(crc.to_le_bytes());
+        }
+        let mut title = [0u8; TITLE_LEN];
+        let len = self.title.len().min(TITLE_LEN);
+        title[..len].copy_from_slice(&self.title.as_bytes()[..len]);
+        footer.extend(title);
+        footer.extend(self.build_hash.to_le_bytes());
+        footer.push(self.bank_crcs.len() as u8);
+        footer.push(VERSION);
+        footer.extend(MAGIC);
+
+        let range = Self::range(rom.len(), self.bank_crcs.len());
+        rom[range].copy_from_slice(&footer);
+    }
+
+    /// The footer in `rom`, if it has one
+    pub fn read(rom: &[u8]) -> Option<Self> {
+        let end = rom.len().checked_sub(VECTORS_LEN)?;
+        let fixed = rom.get(end.checked_sub(MAGIC.len() + 2)?..end)?;
+        let (&[bank_count, version], magic) = fixed.split_first_chunk::<2>()?;
+        if magic != MAGIC || version != VERSION || bank_count == 0 {
+            return None;
+        }
+
+        let bank_count = bank_count as usize;
+        if !rom.len().is_multiple_of(bank_count) || Self::size(bank_count) + VECTORS_LEN > rom.len() / bank_count {
+            return None;
+        }
+
+        let footer = &rom[Self::range(rom.len(), bank_count)];
+        let (crcs, rest) = footer.split_at(bank_count * 4);
+        let (title, rest) = rest.split_at(TITLE_LEN);
+        let build_hash = u64::from_le_bytes(rest[..8].try_into().ok()?);
+
+        Some(Self {
+            title: String::from_utf8_lossy(title).trim_end_matches('\0').to_string(),
+            build_hash,
+            bank_crcs: crcs.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect(),
+        })
+    }
+
+    /// Check every bank of `rom` against the footer, naming the ones that don't match
+    pub fn verify(&self, rom: &[u8]) -> Result<(), String> {
+        let actual = Self::bank_crcs(rom, self.bank_crcs.len());
+        let bad: Vec<String> = self.bank_crcs.iter().zip(&actual)
+            .enumerate()
+            .filter(|(_, (expected, actual))| expected != actual)
+            .map(|(bank, _)| bank.to_string())
+            .collect();
+
+        if bad.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} ({:016x}) is damaged or mixed with another build: CRC mismatch in bank {}",
+                self.title, self.build_hash, bad.join(", ")
+            ))
+        }
+    }
+}
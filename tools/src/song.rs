@@ -0,0 +1,225 @@
+//! Songs for the SDK's music player
+//!
+//! The compact binary `gametank::audio::music::Music` plays, as written by
+//! `gtrom midi convert` and the gtgo tracker. The format is documented with
+//! the player; this module only writes it:
+//!
+//! ```text
+//! "GTS" version:u8  bpm:u8  channels:u8
+//! order_len:u8  order:[u8; order_len]
+//! pattern_count:u8  offsets:[u16; pattern_count]
+//! patterns...
+//! ```
+//!
+//! A pattern is a stream of commands, each row's ending in a wait for how
+//! many rows until the next. [`PatternWriter`] takes care of the waits.
+
+pub const MAGIC: &[u8; 3] = b"GTS";
+/// Song format version this writes; players read it and the ones before.
+/// Version 2 added swing.
+pub const VERSION: u8 = 2;
+/// Voices the player can drive
+pub const MAX_CHANNELS: u8 = 8;
+/// The player turns its tempo into frames per row, and 15 is as slow as it goes
+pub const MIN_BPM: u8 = 15;
+/// Swing goes up to moving every other row by half a row
+pub const MAX_SWING: u8 = 128;
+
+/// Pattern stream commands, matching the player's
+pub mod cmd {
+    pub const NOTE_MAX: u8 = 0x7F;
+    pub const CHANNEL: u8 = 0x80;
+    pub const VOLUME: u8 = 0x90;
+    pub const WAVETABLE: u8 = 0x91;
+    pub const PHASE: u8 = 0x92;
+    pub const VIBRATO: u8 = 0x93;
+    pub const TREMOLO: u8 = 0x94;
+    pub const SLIDE_PITCH: u8 = 0x95;
+    pub const SLIDE_VOLUME: u8 = 0x96;
+    pub const STOP_PITCH_SLIDE: u8 = 0x97;
+    pub const STOP_VOLUME_SLIDE: u8 = 0x98;
+    pub const NOTE_OFF: u8 = 0x99;
+    pub const TEMPO: u8 = 0xA0;
+    pub const JUMP: u8 = 0xA1;
+    pub const BREAK_TO: u8 = 0xA2;
+    pub const ADVANCE: u8 = 0xA3;
+    pub const STOP: u8 = 0xA4;
+    pub const SWING: u8 = 0xA5;
+    pub const WAIT: u8 = 0xF0;
+    pub const END: u8 = 0xFF;
+}
+
+/// Something for one channel to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCommand {
+    /// A MIDI note, 0-127
+    Note(u8),
+    NoteOff,
+    Volume(u8),
+    /// ACP-side address of a wavetable
+    Wavetable(u16),
+    Phase(u16),
+    Vibrato { depth: u8, speed: u8 },
+    Tremolo { depth: u8, speed: u8 },
+    /// Add `delta` to the frequency each frame for `frames` frames, 0 for
+    /// until it's stopped
+    SlidePitch { frames: u8, delta: i16 },
+    SlideVolume { frames: u8, delta: i8 },
+    StopPitchSlide,
+    StopVolumeSlide,
+}
+
+impl ChannelCommand {
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            ChannelCommand::Note(note) => out.push(note.min(cmd::NOTE_MAX)),
+            ChannelCommand::NoteOff => out.push(cmd::NOTE_OFF),
+            ChannelCommand::Volume(volume) => out.extend([cmd::VOLUME, volume]),
+            ChannelCommand::Wavetable(address) => {
+                out.push(cmd::WAVETABLE);
+                out.extend(address.to_le_bytes());
+            }
+            ChannelCommand::Phase(phase) => {
+                out.push(cmd::PHASE);
+                out.extend(phase.to_le_bytes());
+            }
+            ChannelCommand::Vibrato { depth, speed } => out.extend([cmd::VIBRATO, depth, speed]),
+            ChannelCommand::Tremolo { depth, speed } => out.extend([cmd::TREMOLO, depth, speed]),
+            ChannelCommand::SlidePitch { frames, delta } => {
+                out.extend([cmd::SLIDE_PITCH, frames]);
+                out.extend(delta.to_le_bytes());
+            }
+            ChannelCommand::SlideVolume { frames, delta } => out.extend([cmd::SLIDE_VOLUME, frames, delta as u8]),
+            ChannelCommand::StopPitchSlide => out.push(cmd::STOP_PITCH_SLIDE),
+            ChannelCommand::StopVolumeSlide => out.push(cmd::STOP_VOLUME_SLIDE),
+        }
+    }
+}
+
+/// Something for the whole song to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SongCommand {
+    Tempo(u8),
+    /// Play this entry of the order next, from its first row
+    Jump(u8),
+    /// Start the next pattern at this row
+    BreakTo(u8),
+    /// Go on to the next pattern in the order now
+    Advance,
+    Stop,
+    /// Make even rows longer and odd rows shorter by this many 256ths of a
+    /// row, up to [`MAX_SWING`]; 0 plays them straight
+    Swing(u8),
+}
+
+impl SongCommand {
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            SongCommand::Tempo(bpm) => out.extend([cmd::TEMPO, bpm.max(MIN_BPM)]),
+            SongCommand::Jump(order) => out.extend([cmd::JUMP, order]),
+            SongCommand::BreakTo(row) => out.extend([cmd::BREAK_TO, row]),
+            SongCommand::Advance => out.push(cmd::ADVANCE),
+            SongCommand::Stop => out.push(cmd::STOP),
+            SongCommand::Swing(amount) => out.extend([cmd::SWING, amount.min(MAX_SWING)]),
+        }
+    }
+}
+
+/// Builds one pattern's command stream, a row at a time, from the top
+#[derive(Debug, Default)]
+pub struct PatternWriter {
+    bytes: Vec<u8>,
+    /// The row commands are going to
+    row: u16,
+    /// The row the last commands went to
+    written_row: u16,
+    channel: Option<u8>,
+}
+
+impl PatternWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move on to `row`. Rows only go forwards.
+    pub fn seek(&mut self, row: u16) {
+        self.row = self.row.max(row);
+    }
+
+    /// End the rows written so far with waits up to the current one
+    fn wait(&mut self) {
+        while self.written_row < self.row {
+            let rows = (self.row - self.written_row).min(255);
+            self.bytes.extend([cmd::WAIT, rows as u8]);
+            self.written_row += rows;
+        }
+    }
+
+    pub fn channel(&mut self, channel: u8, command: ChannelCommand) {
+        self.wait();
+        if self.channel != Some(channel) {
+            self.bytes.push(cmd::CHANNEL + channel.min(MAX_CHANNELS - 1));
+            self.channel = Some(channel);
+        }
+        command.write(&mut self.bytes);
+    }
+
+    pub fn song(&mut self, command: SongCommand) {
+        self.wait();
+        command.write(&mut self.bytes);
+    }
+
+    /// The finished pattern, `rows` long
+    pub fn finish(mut self, rows: u16) -> Vec<u8> {
+        self.seek(rows.max(1));
+        self.wait();
+        self.bytes.push(cmd::END);
+        self.bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Song {
+    /// Rows a minute
+    pub bpm: u8,
+    pub channels: u8,
+    /// Patterns to play, in order; the song loops back to the first after
+    /// the last
+    pub order: Vec<u8>,
+    /// Finished patterns, from [`PatternWriter::finish`]
+    pub patterns: Vec<Vec<u8>>,
+}
+
+impl Song {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        if self.order.is_empty() || self.order.len() > 255 {
+            return Err(format!("a song needs 1-255 entries in its order, not {}", self.order.len()));
+        }
+        if self.patterns.len() > 255 {
+            return Err(format!("a song can have at most 255 patterns, not {}", self.patterns.len()));
+        }
+        if let Some(missing) = self.order.iter().find(|p| **p as usize >= self.patterns.len()) {
+            return Err(format!("the order plays pattern {}, but there are only {}", missing, self.patterns.len()));
+        }
+
+        let mut out = MAGIC.to_vec();
+        out.extend([VERSION, self.bpm.max(MIN_BPM), self.channels.clamp(1, MAX_CHANNELS)]);
+        out.push(self.order.len() as u8);
+        out.extend(&self.order);
+        out.push(self.patterns.len() as u8);
+
+        let mut offset = out.len() + self.patterns.len() * 2;
+        for pattern in &self.patterns {
+            let at = u16::try_from(offset).map_err(|_| "the song is over 64KB".to_string())?;
+            out.extend(at.to_le_bytes());
+            offset += pattern.len();
+        }
+        if offset > u16::MAX as usize + 1 {
+            return Err("the song is over 64KB".to_string());
+        }
+        for pattern in &self.patterns {
+            out.extend(pattern);
+        }
+        Ok(out)
+    }
+}
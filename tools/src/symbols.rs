@@ -0,0 +1,201 @@
+//! Bank-aware symbols from llvm-mos ELFs
+//!
+//! The linker gives banked code and data 24-bit addresses (`$BB8000` and up),
+//! but the cpu only ever sees the low 16 bits. This pairs each symbol's cpu
+//! address with the bank it lives in, for `gtrom size` and .sym files.
+//!
+//! A .sym file has a line per symbol: `<bank>:<address> <size> <f|o> <name>`,
+//! all hex, with `--` as the bank for RAM. Lines starting with `;` are comments.
+//!
+//! ```text
+//! 7E:8000 0042 f rom::draw_background
+//! 7F:C123 01A0 f main
+//! --:0400 0010 o rom::ball::BALLS
+//! ```
+
+use elf::abi::{SHN_UNDEF, STT_FUNC, STT_OBJECT};
+use elf::{ElfBytes, endian::AnyEndian};
+use rustc_demangle::demangle;
+
+use crate::memory_map::Cart;
+use crate::rom_builder::detect_cart;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Object,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// Demangled, without the hash suffix
+    pub name: String,
+    pub kind: SymbolKind,
+    /// ROM bank the symbol lives in, or None for RAM
+    pub bank: Option<u8>,
+    /// Address as the cpu sees it
+    pub addr: u16,
+    pub size: usize,
+    /// Output section the symbol was linked into
+    pub section: String,
+}
+
+/// ROM bank for an output section on `cart`, following the layout `RomBuilder` uses.
+/// RAM sections (.data, .bss, .zp) have none.
+pub fn section_bank(section: &str, cart: Cart) -> Option<u8> {
+    let banked = section.strip_prefix(".text.bank").or_else(|| section.strip_prefix(".rodata.bank"));
+    if let Some(bank) = banked {
+        return bank.parse().ok();
+    }
+
+    match section {
+        ".text" | ".rodata" | ".vector_table" => Some(cart.fixed_bank()),
+        _ => None,
+    }
+}
+
+/// Every sized function and data symbol in the ELF, in address order
+pub fn read_symbols(data: &[u8]) -> Result<Vec<Symbol>, String> {
+    read_elf(data).map(|(symbols, _)| symbols)
+}
+
+/// The ELF's symbols, and the cart it was linked for
+fn read_elf(data: &[u8]) -> Result<(Vec<Symbol>, Cart), String> {
+    let elf = ElfBytes::<AnyEndian>::minimal_parse(data).map_err(|e| format!("Failed to parse ELF: {}", e))?;
+    let cart = detect_cart(&elf);
+
+    let (headers, section_names) = elf.section_headers_with_strtab()
+        .map_err(|e| format!("Failed to read section headers: {}", e))?;
+    let (headers, section_names) = headers.zip(section_names).ok_or("ELF has no section headers")?;
+
+    let (symtab, strtab) = elf.symbol_table()
+        .map_err(|e| format!("Failed to read symbol table: {}", e))?
+        .ok_or("ELF has no symbol table; was it stripped?")?;
+
+    let mut symbols: Vec<Symbol> = symtab.iter()
+        .filter(|sym| sym.st_shndx != SHN_UNDEF && sym.st_size > 0)
+        .filter_map(|sym| {
+            let kind = match sym.st_symtype() {
+                STT_FUNC => SymbolKind::Function,
+                STT_OBJECT => SymbolKind::Object,
+                _ => return None,
+            };
+            let name = strtab.get(sym.st_name as usize).ok()?;
+            let header = headers.get(sym.st_shndx as usize).ok()?;
+            let section = section_names.get(header.sh_name as usize).ok()?;
+
+            Some(Symbol {
+                name: format!("{:#}", demangle(name)),
+                kind,
+                bank: section_bank(section, cart),
+                addr: sym.st_value as u16,
+                size: sym.st_size as usize,
+                section: section.to_string(),
+            })
+        })
+        .collect();
+
+    symbols.sort_by_key(|s| (s.bank.map_or(-1, |b| b as i16), s.addr));
+    Ok((symbols, cart))
+}
+
+/// Symbols looked up by address, as the cpu sees them
+#[derive(Debug, Clone, Default)]
+pub struct SymbolMap {
+    symbols: Vec<Symbol>,
+    /// Decides which bank is mapped above the switchable window
+    cart: Cart,
+}
+
+impl SymbolMap {
+    pub fn new(symbols: Vec<Symbol>, cart: Cart) -> Self {
+        Self { symbols, cart }
+    }
+
+    /// Symbols straight from an ELF's symbol table
+    pub fn from_elf(data: &[u8]) -> Result<Self, String> {
+        read_elf(data).map(|(symbols, cart)| Self::new(symbols, cart))
+    }
+
+    /// Read a .sym file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = vec![];
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let bad_line = || format!("line {}: expected `<bank>:<address> <size> <f|o> <name>`", line_no + 1);
+
+            let mut fields = line.splitn(4, ' ');
+            let (Some(location), Some(size), Some(kind), Some(name)) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+                return Err(bad_line());
+            };
+            let (bank, addr) = location.split_once(':').ok_or_else(bad_line)?;
+
+            symbols.push(Symbol {
+                name: name.to_string(),
+                kind: match kind {
+                    "f" => SymbolKind::Function,
+                    "o" => SymbolKind::Object,
+                    _ => return Err(bad_line()),
+                },
+                bank: match bank {
+                    "--" => None,
+                    bank => Some(u8::from_str_radix(bank, 16).map_err(|_| bad_line())?),
+                },
+                addr: u16::from_str_radix(addr, 16).map_err(|_| bad_line())?,
+                size: usize::from_str_radix(size, 16).map_err(|_| bad_line())?,
+                section: String::new(),
+            });
+        }
+
+        // .sym files don't say which cart they're for, but only a 32K ROM has all its code in bank 0
+        let cart = match symbols.iter().any(|s| s.bank.is_some_and(|bank| bank != 0)) {
+            true => Cart::Flash2m,
+            false => Cart::Rom32k,
+        };
+        Ok(Self::new(symbols, cart))
+    }
+
+    /// Write a .sym file
+    pub fn to_sym_file(&self) -> String {
+        let mut text = String::from("; <bank>:<address> <size> <f|o> <name>, bank -- is RAM\n");
+        for s in &self.symbols {
+            let bank = s.bank.map_or("--".to_string(), |b| format!("{:02X}", b));
+            let kind = match s.kind {
+                SymbolKind::Function => 'f',
+                SymbolKind::Object => 'o',
+            };
+            text.push_str(&format!("{}:{:04X} {:04X} {} {}\n", bank, s.addr, s.size, kind, s.name));
+        }
+        text
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// The symbol covering `addr` with `bank` switched in at $8000, and how far into it `addr` is
+    pub fn lookup(&self, addr: u16, bank: u8) -> Option<(&Symbol, u16)> {
+        let bank = match addr {
+            0x0000..=0x7FFF => None,
+            addr if addr >= self.cart.fixed_origin() => Some(self.cart.fixed_bank()),
+            _ => Some(bank),
+        };
+
+        self.symbols.iter()
+            .filter(|s| s.bank == bank && s.addr <= addr && (addr as usize) < s.addr as usize + s.size)
+            .map(|s| (s, addr - s.addr))
+            .next()
+    }
+
+    /// `name` or `name+offset`, for showing an address
+    pub fn describe(&self, addr: u16, bank: u8) -> Option<String> {
+        self.lookup(addr, bank).map(|(s, offset)| match offset {
+            0 => s.name.clone(),
+            offset => format!("{}+{:#X}", s.name, offset),
+        })
+    }
+}